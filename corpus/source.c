@@ -0,0 +1,121 @@
+/*
+ * ringbuf.c - fixed-capacity byte ring buffer
+ *
+ * The buffer never allocates after creation. Writers that would overflow
+ * the buffer receive a short count instead of blocking.
+ */
+
+#include <stddef.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "ringbuf.h"
+
+struct ringbuf {
+	unsigned char *data;
+	size_t capacity;
+	size_t head;	/* next byte to read */
+	size_t len;	/* bytes currently stored */
+};
+
+struct ringbuf *ringbuf_new(size_t capacity)
+{
+	struct ringbuf *rb;
+
+	if (capacity == 0)
+		return NULL;
+	rb = malloc(sizeof(*rb));
+	if (rb == NULL)
+		return NULL;
+	rb->data = malloc(capacity);
+	if (rb->data == NULL) {
+		free(rb);
+		return NULL;
+	}
+	rb->capacity = capacity;
+	rb->head = 0;
+	rb->len = 0;
+	return rb;
+}
+
+void ringbuf_free(struct ringbuf *rb)
+{
+	if (rb == NULL)
+		return;
+	free(rb->data);
+	free(rb);
+}
+
+size_t ringbuf_len(const struct ringbuf *rb)
+{
+	return rb->len;
+}
+
+size_t ringbuf_space(const struct ringbuf *rb)
+{
+	return rb->capacity - rb->len;
+}
+
+size_t ringbuf_write(struct ringbuf *rb, const void *src, size_t count)
+{
+	const unsigned char *bytes = src;
+	size_t tail, first;
+
+	if (count > ringbuf_space(rb))
+		count = ringbuf_space(rb);
+	tail = (rb->head + rb->len) % rb->capacity;
+	first = rb->capacity - tail;
+	if (first > count)
+		first = count;
+	memcpy(rb->data + tail, bytes, first);
+	memcpy(rb->data, bytes + first, count - first);
+	rb->len += count;
+	return count;
+}
+
+size_t ringbuf_read(struct ringbuf *rb, void *dst, size_t count)
+{
+	unsigned char *bytes = dst;
+	size_t first;
+
+	if (count > rb->len)
+		count = rb->len;
+	first = rb->capacity - rb->head;
+	if (first > count)
+		first = count;
+	memcpy(bytes, rb->data + rb->head, first);
+	memcpy(bytes + first, rb->data, count - first);
+	rb->head = (rb->head + count) % rb->capacity;
+	rb->len -= count;
+	return count;
+}
+
+size_t ringbuf_peek(const struct ringbuf *rb, void *dst, size_t count)
+{
+	unsigned char *bytes = dst;
+	size_t first;
+
+	if (count > rb->len)
+		count = rb->len;
+	first = rb->capacity - rb->head;
+	if (first > count)
+		first = count;
+	memcpy(bytes, rb->data + rb->head, first);
+	memcpy(bytes + first, rb->data, count - first);
+	return count;
+}
+
+size_t ringbuf_discard(struct ringbuf *rb, size_t count)
+{
+	if (count > rb->len)
+		count = rb->len;
+	rb->head = (rb->head + count) % rb->capacity;
+	rb->len -= count;
+	return count;
+}
+
+void ringbuf_clear(struct ringbuf *rb)
+{
+	rb->head = 0;
+	rb->len = 0;
+}
@@ -0,0 +1,91 @@
+/* compression_lib C API. Generated by compression_lib::ffi::header();
+ * do not edit. */
+
+#ifndef COMPRESSION_LIB_H
+#define COMPRESSION_LIB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes. */
+#define CL_OK (0)
+#define CL_ERR_NULL_POINTER (-1)
+#define CL_ERR_UNKNOWN_ALGORITHM (-2)
+#define CL_ERR_BUFFER_TOO_SMALL (-3)
+#define CL_ERR_INVALID_INPUT (-4)
+#define CL_ERR_DECOMPRESSION (-5)
+#define CL_ERR_INVALID_HEADER (-6)
+#define CL_ERR_CORRUPTED_DATA (-7)
+#define CL_ERR_TRAILING_DATA (-8)
+#define CL_ERR_CHECKSUM_MISMATCH (-9)
+#define CL_ERR_LIMIT_EXCEEDED (-10)
+#define CL_ERR_IO (-11)
+#define CL_ERR_INTERNAL (-12)
+
+/* Algorithm tags. */
+#define CL_ALGORITHM_STORED 0
+#define CL_ALGORITHM_RLE 1
+#define CL_ALGORITHM_ZERO_RUN 2
+#define CL_ALGORITHM_RLE0 3
+#define CL_ALGORITHM_LZ77 4
+#define CL_ALGORITHM_LZ77_HUFFMAN 5
+#define CL_ALGORITHM_LZ4 6
+#define CL_ALGORITHM_SNAPPY 7
+#define CL_ALGORITHM_HUFFMAN 8
+#define CL_ALGORITHM_WORD_HUFFMAN 9
+#define CL_ALGORITHM_ARITHMETIC 10
+#define CL_ALGORITHM_SHANNON_FANO 11
+#define CL_ALGORITHM_TUNSTALL 12
+#define CL_ALGORITHM_PPM 13
+#define CL_ALGORITHM_LZMA 14
+#define CL_ALGORITHM_LZP 15
+#define CL_ALGORITHM_REPAIR 16
+#define CL_ALGORITHM_CM 17
+#define CL_ALGORITHM_AUTO 18
+#define CL_ALGORITHM_DELTA 19
+#define CL_ALGORITHM_SHUFFLE 20
+#define CL_ALGORITHM_BITSHUFFLE 21
+#define CL_ALGORITHM_BWT 22
+#define CL_ALGORITHM_COBS 23
+#define CL_ALGORITHM_BCJ 24
+#define CL_ALGORITHM_FAX 25
+#define CL_ALGORITHM_RLE2D 26
+
+/* Compresses input_len bytes at input with the algorithm tagged algorithm
+ * into output, storing the compressed length in *output_len. If it does not
+ * fit in output_capacity bytes, stores the length needed and returns
+ * CL_ERR_BUFFER_TOO_SMALL. input may be NULL if input_len is 0, and output
+ * if output_capacity is 0. */
+int32_t cl_compress(uint8_t algorithm, const uint8_t *input, size_t input_len,
+                    uint8_t *output, size_t output_capacity, size_t *output_len);
+
+/* cl_compress at a compression level from 1 (fastest) to 9 (best), clamped
+ * to that range; cl_compress uses 6. Decompression needs no level. */
+int32_t cl_compress_level(uint8_t algorithm, uint8_t level, const uint8_t *input,
+                          size_t input_len, uint8_t *output, size_t output_capacity,
+                          size_t *output_len);
+
+/* Reverses cl_compress with the same algorithm, on the same terms, without
+ * decoding more than output_capacity bytes. A frame that records a longer
+ * output is refused up front with CL_ERR_BUFFER_TOO_SMALL and its length
+ * stored; one that records none is stopped with CL_ERR_LIMIT_EXCEEDED once
+ * it passes output_capacity, and can be retried with a larger buffer. */
+int32_t cl_decompress(uint8_t algorithm, const uint8_t *input, size_t input_len,
+                      uint8_t *output, size_t output_capacity, size_t *output_len);
+
+/* Largest frame input_len bytes can compress to with algorithm: SIZE_MAX if
+ * the codec has no bound, 0 if no algorithm has that tag. */
+size_t cl_max_compressed_len(uint8_t algorithm, size_t input_len);
+
+/* A static, NUL-terminated description of a status code. */
+const char *cl_error_message(int32_t code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* COMPRESSION_LIB_H */
@@ -0,0 +1,280 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::CompressionError;
+use crate::image::{Fax, Rle2d};
+use crate::level::CompressionLevel;
+use crate::traits::{Codec, Compressor};
+use crate::{
+    Arithmetic, Auto, Bcj, BitShuffle, Bwt, Cm, Cobs, Delta, Huffman, Lz4, Lz77, Lz77Huffman, Lzma,
+    Lzp, Ppm, RePair, Rle, Rle0, ShannonFano, Shuffle, Snappy, Stored, Tunstall, WordHuffman,
+    ZeroRun,
+};
+
+/// A general-purpose codec chosen by name, as from a command line or a
+/// configuration file.
+///
+/// Each variant displays as, and parses from, the name of its module, such
+/// as `lz77` or `word_huffman`; parsing ignores ASCII case. These are the
+/// names [`registry::codec_by_name`](crate::registry::codec_by_name) knows
+/// from the start. [`Algorithm::new_codec`] creates the codec with its
+/// default settings; for the image codecs, [`Fax`] and [`Rle2d`], that is
+/// rows one byte wide, and decoding takes the image shape from the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Stored,
+    Rle,
+    ZeroRun,
+    Rle0,
+    Lz77,
+    Lz77Huffman,
+    Lz4,
+    Snappy,
+    Huffman,
+    WordHuffman,
+    Arithmetic,
+    ShannonFano,
+    Tunstall,
+    Ppm,
+    Lzma,
+    Lzp,
+    RePair,
+    Cm,
+    Auto,
+    Delta,
+    Shuffle,
+    BitShuffle,
+    Bwt,
+    Cobs,
+    Bcj,
+    Fax,
+    Rle2d,
+}
+
+impl Algorithm {
+    /// Every algorithm, in declaration order, which is also [`tag`] order.
+    ///
+    /// [`tag`]: Algorithm::tag
+    pub const ALL: [Self; 27] = [
+        Self::Stored,
+        Self::Rle,
+        Self::ZeroRun,
+        Self::Rle0,
+        Self::Lz77,
+        Self::Lz77Huffman,
+        Self::Lz4,
+        Self::Snappy,
+        Self::Huffman,
+        Self::WordHuffman,
+        Self::Arithmetic,
+        Self::ShannonFano,
+        Self::Tunstall,
+        Self::Ppm,
+        Self::Lzma,
+        Self::Lzp,
+        Self::RePair,
+        Self::Cm,
+        Self::Auto,
+        Self::Delta,
+        Self::Shuffle,
+        Self::BitShuffle,
+        Self::Bwt,
+        Self::Cobs,
+        Self::Bcj,
+        Self::Fax,
+        Self::Rle2d,
+    ];
+
+    /// The lowercase name this algorithm displays as and parses from.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Stored => "stored",
+            Self::Rle => "rle",
+            Self::ZeroRun => "zero_run",
+            Self::Rle0 => "rle0",
+            Self::Lz77 => "lz77",
+            Self::Lz77Huffman => "lz77_huffman",
+            Self::Lz4 => "lz4",
+            Self::Snappy => "snappy",
+            Self::Huffman => "huffman",
+            Self::WordHuffman => "word_huffman",
+            Self::Arithmetic => "arithmetic",
+            Self::ShannonFano => "shannon_fano",
+            Self::Tunstall => "tunstall",
+            Self::Ppm => "ppm",
+            Self::Lzma => "lzma",
+            Self::Lzp => "lzp",
+            Self::RePair => "repair",
+            Self::Cm => "cm",
+            Self::Auto => "auto",
+            Self::Delta => "delta",
+            Self::Shuffle => "shuffle",
+            Self::BitShuffle => "bitshuffle",
+            Self::Bwt => "bwt",
+            Self::Cobs => "cobs",
+            Self::Bcj => "bcj",
+            Self::Fax => "fax",
+            Self::Rle2d => "rle2d",
+        }
+    }
+
+    /// The byte identifying this algorithm in a
+    /// [`Pipeline`](crate::Pipeline) frame: its position in
+    /// [`Algorithm::ALL`]. New algorithms are only ever appended, so tags
+    /// never change.
+    #[must_use]
+    pub const fn tag(self) -> u8 {
+        self as u8
+    }
+
+    /// The algorithm with tag `tag`, if any.
+    #[must_use]
+    pub const fn from_tag(tag: u8) -> Option<Self> {
+        if (tag as usize) < Self::ALL.len() {
+            Some(Self::ALL[tag as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Creates the codec with its default settings.
+    #[must_use]
+    pub fn new_codec(self) -> Box<dyn Codec + Send + Sync> {
+        self.new_codec_with_level(CompressionLevel::DEFAULT)
+    }
+
+    /// Creates the codec with its default settings tuned to `level`, as by
+    /// [`Compressor::with_level`], which cannot reach a boxed codec.
+    #[must_use]
+    pub fn new_codec_with_level(self, level: CompressionLevel) -> Box<dyn Codec + Send + Sync> {
+        match self {
+            Self::Stored => Box::new(Stored::new().with_level(level)),
+            Self::Rle => Box::new(Rle::new().with_level(level)),
+            Self::ZeroRun => Box::new(ZeroRun::new().with_level(level)),
+            Self::Rle0 => Box::new(Rle0::new().with_level(level)),
+            Self::Lz77 => Box::new(Lz77::new().with_level(level)),
+            Self::Lz77Huffman => Box::new(Lz77Huffman::new().with_level(level)),
+            Self::Lz4 => Box::new(Lz4::new().with_level(level)),
+            Self::Snappy => Box::new(Snappy::new().with_level(level)),
+            Self::Huffman => Box::new(Huffman::new().with_level(level)),
+            Self::WordHuffman => Box::new(WordHuffman::new().with_level(level)),
+            Self::Arithmetic => Box::new(Arithmetic::new().with_level(level)),
+            Self::ShannonFano => Box::new(ShannonFano::new().with_level(level)),
+            Self::Tunstall => Box::new(Tunstall::new().with_level(level)),
+            Self::Ppm => Box::new(Ppm::new().with_level(level)),
+            Self::Lzma => Box::new(Lzma::new().with_level(level)),
+            Self::Lzp => Box::new(Lzp::new().with_level(level)),
+            Self::RePair => Box::new(RePair::new().with_level(level)),
+            Self::Cm => Box::new(Cm::new().with_level(level)),
+            Self::Auto => Box::new(Auto::new().with_level(level)),
+            Self::Delta => Box::new(Delta::new().with_level(level)),
+            Self::Shuffle => Box::new(Shuffle::new().with_level(level)),
+            Self::BitShuffle => Box::new(BitShuffle::new().with_level(level)),
+            Self::Bwt => Box::new(Bwt::new().with_level(level)),
+            Self::Cobs => Box::new(Cobs::new().with_level(level)),
+            Self::Bcj => Box::new(Bcj::new().with_level(level)),
+            Self::Fax => Box::new(Fax::new(8, 0).with_level(level)),
+            Self::Rle2d => Box::new(Rle2d::new(1).with_level(level)),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = CompressionError;
+
+    /// Parses an algorithm name, ignoring ASCII case.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` naming the input if no
+    /// algorithm has that name.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| CompressionError::InvalidInput(format!("unknown algorithm {name:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compressor, Decompressor};
+
+    #[test]
+    fn test_names_roundtrip() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.to_string().parse::<Algorithm>(), Ok(algorithm));
+            assert_eq!(
+                algorithm.name().to_ascii_uppercase().parse::<Algorithm>(),
+                Ok(algorithm)
+            );
+        }
+        assert_eq!("LZ77_Huffman".parse(), Ok(Algorithm::Lz77Huffman));
+        assert!(matches!(
+            "gzip".parse::<Algorithm>(),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!("".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_tags_follow_declaration_order() {
+        for (i, algorithm) in Algorithm::ALL.into_iter().enumerate() {
+            assert_eq!(usize::from(algorithm.tag()), i);
+            assert_eq!(Algorithm::from_tag(algorithm.tag()), Some(algorithm));
+        }
+        assert_eq!(Algorithm::Stored.tag(), 0);
+        assert_eq!(Algorithm::Bcj.tag(), 24);
+        assert_eq!(Algorithm::Rle2d.tag(), 26);
+        assert_eq!(Algorithm::from_tag(27), None);
+    }
+
+    #[test]
+    fn test_new_codec_roundtrips() {
+        let input = b"algorithms chosen from a config file; algorithms chosen".repeat(8);
+        for algorithm in Algorithm::ALL {
+            let codec = algorithm.new_codec();
+            let compressed = codec.compress(&input).unwrap();
+            assert_eq!(codec.decompress(&compressed).unwrap(), input, "{algorithm}");
+        }
+        assert_eq!(Compressor::name(&Algorithm::Huffman.new_codec()), "Huffman");
+    }
+
+    #[test]
+    fn test_new_codec_with_level() {
+        let input = b"levels reach codecs chosen by name; levels reach codecs".repeat(64);
+        for algorithm in Algorithm::ALL {
+            let fastest = algorithm.new_codec_with_level(CompressionLevel::FASTEST);
+            let compressed = fastest.compress(&input).unwrap();
+            let codec = algorithm.new_codec();
+            assert_eq!(codec.decompress(&compressed).unwrap(), input, "{algorithm}");
+        }
+
+        let lz77 = |level| Algorithm::Lz77.new_codec_with_level(level).compress(&input);
+        assert_eq!(
+            lz77(CompressionLevel::FASTEST).unwrap(),
+            Lz77::new()
+                .with_level(CompressionLevel::FASTEST)
+                .compress(&input)
+                .unwrap()
+        );
+        assert_eq!(
+            lz77(CompressionLevel::DEFAULT).unwrap(),
+            Lz77::new().compress(&input).unwrap()
+        );
+        let best = Algorithm::Lzma.new_codec_with_level(CompressionLevel::BEST);
+        let fastest = Algorithm::Lzma.new_codec_with_level(CompressionLevel::FASTEST);
+        assert_ne!(
+            best.compress(&input).unwrap(),
+            fastest.compress(&input).unwrap()
+        );
+    }
+}
@@ -0,0 +1,539 @@
+//! Single-file amalgamation of selected codecs for vendoring.
+//!
+//! [`generate`] emits one dependency-free Rust source file containing the
+//! requested modules and everything they depend on, each as an inline
+//! `pub mod`, with the unit tests stripped. Projects that cannot take
+//! crates.io dependencies, such as firmware builds, can check the file in and
+//! declare it with `mod compression;`.
+
+use std::fmt::Write;
+
+use crate::error::{CompressionError, Result};
+
+/// A module that can be amalgamated, with its direct dependencies.
+struct Source {
+    name: &'static str,
+    code: &'static str,
+    deps: &'static [&'static str],
+}
+
+/// Every module that can be amalgamated, dependencies before dependents.
+const SOURCES: &[Source] = &[
+    Source {
+        name: "error",
+        code: include_str!("error.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "level",
+        code: include_str!("level.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "limits",
+        code: include_str!("limits.rs"),
+        deps: &["error"],
+    },
+    Source {
+        name: "stats",
+        code: include_str!("stats.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "traits",
+        code: include_str!("traits.rs"),
+        deps: &["error", "level", "limits", "stats"],
+    },
+    Source {
+        name: "bitstream",
+        code: include_str!("bitstream.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "dispatch",
+        code: include_str!("dispatch.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "varint",
+        code: include_str!("varint.rs"),
+        deps: &["error"],
+    },
+    Source {
+        name: "sink",
+        code: include_str!("sink.rs"),
+        deps: &["error"],
+    },
+    Source {
+        name: "progress",
+        code: include_str!("progress.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "range_coder",
+        code: include_str!("range_coder.rs"),
+        deps: &["error"],
+    },
+    Source {
+        name: "prefix_code",
+        code: include_str!("prefix_code.rs"),
+        deps: &["bitstream", "error", "sink"],
+    },
+    Source {
+        name: "classify",
+        code: include_str!("classify.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "profile",
+        code: include_str!("profile.rs"),
+        deps: &["classify", "error", "varint"],
+    },
+    Source {
+        name: "rle",
+        code: include_str!("rle.rs"),
+        deps: &["bitstream", "error", "limits", "sink", "traits", "varint"],
+    },
+    Source {
+        name: "zero_run",
+        code: include_str!("zero_run.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+    Source {
+        name: "rle0",
+        code: include_str!("rle0.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+    Source {
+        name: "stored",
+        code: include_str!("stored.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+    Source {
+        name: "lz4",
+        code: include_str!("lz4.rs"),
+        deps: &["error", "stats", "traits", "varint"],
+    },
+    Source {
+        name: "snappy",
+        code: include_str!("snappy.rs"),
+        deps: &["error", "stats", "traits", "varint"],
+    },
+    Source {
+        name: "lz77",
+        code: include_str!("lz77.rs"),
+        deps: &[
+            "bitstream",
+            "dispatch",
+            "error",
+            "level",
+            "sink",
+            "stats",
+            "traits",
+            "varint",
+        ],
+    },
+    Source {
+        name: "huffman",
+        code: include_str!("huffman.rs"),
+        deps: &[
+            "bitstream",
+            "error",
+            "prefix_code",
+            "profile",
+            "sink",
+            "stats",
+            "traits",
+            "varint",
+        ],
+    },
+    Source {
+        name: "lz77_huffman",
+        code: include_str!("lz77_huffman.rs"),
+        deps: &["error", "huffman", "level", "lz77", "traits", "varint"],
+    },
+    Source {
+        name: "auto",
+        code: include_str!("auto.rs"),
+        deps: &[
+            "classify", "error", "huffman", "level", "lz77", "rle", "stored", "traits",
+        ],
+    },
+    Source {
+        name: "legacy",
+        code: include_str!("legacy.rs"),
+        deps: &["bitstream", "error", "lz77", "rle", "traits"],
+    },
+    Source {
+        name: "word_huffman",
+        code: include_str!("word_huffman.rs"),
+        deps: &["bitstream", "error", "huffman", "limits", "traits", "varint"],
+    },
+    Source {
+        name: "arithmetic",
+        code: include_str!("arithmetic.rs"),
+        deps: &["error", "limits", "range_coder", "traits"],
+    },
+    Source {
+        name: "ppm",
+        code: include_str!("ppm.rs"),
+        deps: &["error", "level", "range_coder", "traits", "varint"],
+    },
+    Source {
+        name: "lzma",
+        code: include_str!("lzma.rs"),
+        deps: &[
+            "error",
+            "level",
+            "profile",
+            "range_coder",
+            "stats",
+            "traits",
+            "varint",
+        ],
+    },
+    Source {
+        name: "lzp",
+        code: include_str!("lzp.rs"),
+        deps: &["error", "stats", "traits", "varint"],
+    },
+    Source {
+        name: "repair",
+        code: include_str!("repair.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+    Source {
+        name: "cm",
+        code: include_str!("cm.rs"),
+        deps: &["error", "range_coder", "traits", "varint"],
+    },
+    Source {
+        name: "shannon_fano",
+        code: include_str!("shannon_fano.rs"),
+        deps: &["error", "prefix_code", "traits"],
+    },
+    Source {
+        name: "tunstall",
+        code: include_str!("tunstall.rs"),
+        deps: &["bitstream", "error", "level", "traits"],
+    },
+    Source {
+        name: "rice",
+        code: include_str!("rice.rs"),
+        deps: &["bitstream", "error"],
+    },
+    Source {
+        name: "delta",
+        code: include_str!("delta.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "shuffle",
+        code: include_str!("shuffle.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "bitmap",
+        code: include_str!("bitmap.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+    Source {
+        name: "bwt",
+        code: include_str!("bwt.rs"),
+        deps: &["error", "level", "traits"],
+    },
+    Source {
+        name: "bitshuffle",
+        code: include_str!("bitshuffle.rs"),
+        deps: &["bitstream", "error", "traits"],
+    },
+    Source {
+        name: "mtf",
+        code: include_str!("mtf.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "image",
+        code: include_str!("image.rs"),
+        deps: &["bitstream", "error", "traits", "varint"],
+    },
+    Source {
+        name: "cobs",
+        code: include_str!("cobs.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "bcj",
+        code: include_str!("bcj.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "predictor",
+        code: include_str!("predictor.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "xor_float",
+        code: include_str!("xor_float.rs"),
+        deps: &["bitstream", "error"],
+    },
+    Source {
+        name: "timestamp",
+        code: include_str!("timestamp.rs"),
+        deps: &["bitstream", "error", "varint"],
+    },
+    Source {
+        name: "sequence",
+        code: include_str!("sequence.rs"),
+        deps: &["error", "varint"],
+    },
+    Source {
+        name: "id_column",
+        code: include_str!("id_column.rs"),
+        deps: &["error", "huffman", "sequence", "traits", "varint"],
+    },
+    Source {
+        name: "sketch",
+        code: include_str!("sketch.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "search",
+        code: include_str!("search.rs"),
+        deps: &["error", "rle", "varint"],
+    },
+    Source {
+        name: "chunked",
+        code: include_str!("chunked.rs"),
+        deps: &["error", "level", "limits", "progress", "traits"],
+    },
+    Source {
+        name: "hash",
+        code: include_str!("hash.rs"),
+        deps: &[],
+    },
+    Source {
+        name: "chunking",
+        code: include_str!("chunking.rs"),
+        deps: &["error", "hash", "limits", "traits", "varint"],
+    },
+    Source {
+        name: "mux",
+        code: include_str!("mux.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "pipe",
+        code: include_str!("pipe.rs"),
+        deps: &["error", "limits", "traits"],
+    },
+    Source {
+        name: "frame_delta",
+        code: include_str!("frame_delta.rs"),
+        deps: &["error", "traits"],
+    },
+    Source {
+        name: "channel",
+        code: include_str!("channel.rs"),
+        deps: &["error", "limits", "traits"],
+    },
+    Source {
+        name: "tiering",
+        code: include_str!("tiering.rs"),
+        deps: &["error", "traits", "varint"],
+    },
+];
+
+const TEST_MODULE: &str = "\n#[cfg(test)]\nmod tests {";
+
+/// Names of the modules [`generate`] accepts.
+pub fn modules() -> impl Iterator<Item = &'static str> {
+    SOURCES.iter().map(|source| source.name)
+}
+
+/// Module code without its trailing unit tests, with crate paths rewritten
+/// to the sibling modules of the amalgamation.
+fn strip(code: &str) -> String {
+    let body = code.find(TEST_MODULE).map_or(code, |end| &code[..end]);
+    body.trim_end().replace("crate::", "super::")
+}
+
+/// Generates a single-file module containing `modules` and their
+/// dependencies, in dependency order.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if a name is not one of
+/// [`modules`].
+pub fn generate(modules: &[&str]) -> Result<String> {
+    let mut selected = vec![false; SOURCES.len()];
+    let mut pending = modules.to_vec();
+    while let Some(name) = pending.pop() {
+        let index = SOURCES
+            .iter()
+            .position(|source| source.name == name)
+            .ok_or_else(|| CompressionError::InvalidInput(format!("unknown module: {name}")))?;
+        if !selected[index] {
+            selected[index] = true;
+            pending.extend_from_slice(SOURCES[index].deps);
+        }
+    }
+
+    let included: Vec<&Source> = SOURCES
+        .iter()
+        .zip(&selected)
+        .filter_map(|(source, &selected)| selected.then_some(source))
+        .collect();
+    let names: Vec<&str> = included.iter().map(|source| source.name).collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "// Generated by {} {}; do not edit.",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(
+        out,
+        "// SPDX-License-Identifier: {}",
+        env!("CARGO_PKG_LICENSE")
+    );
+    let _ = writeln!(out, "// Modules: {}", names.join(", "));
+    out.push_str("\n#![forbid(unsafe_code)]\n#![allow(dead_code)]\n");
+    for source in included {
+        let _ = write!(
+            out,
+            "\npub mod {} {{\n{}\n}}\n",
+            source.name,
+            strip(source.code)
+        );
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module_names(generated: &str) -> Vec<&str> {
+        generated
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub mod "))
+            .filter_map(|line| line.strip_suffix(" {"))
+            .collect()
+    }
+
+    #[test]
+    fn test_includes_dependencies_in_order() {
+        let generated = generate(&["huffman"]).unwrap();
+        assert_eq!(
+            module_names(&generated),
+            [
+                "error",
+                "level",
+                "limits",
+                "stats",
+                "traits",
+                "bitstream",
+                "varint",
+                "sink",
+                "prefix_code",
+                "classify",
+                "profile",
+                "huffman"
+            ]
+        );
+        assert!(generated.contains(
+            "// Modules: error, level, limits, stats, traits, bitstream, varint, sink, prefix_code, classify, profile, huffman"
+        ));
+    }
+
+    #[test]
+    fn test_duplicates_and_shared_dependencies() {
+        let generated = generate(&["rle", "delta", "rle"]).unwrap();
+        assert_eq!(
+            module_names(&generated),
+            [
+                "error",
+                "level",
+                "limits",
+                "stats",
+                "traits",
+                "bitstream",
+                "varint",
+                "sink",
+                "rle",
+                "delta"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strips_tests_and_crate_paths() {
+        let generated = generate(&["rle"]).unwrap();
+        assert!(!generated.contains("#[cfg(test)]"));
+        assert!(!generated.contains("crate::"));
+        assert!(generated.contains("use super::error::{CompressionError, Result};"));
+    }
+
+    #[test]
+    fn test_header() {
+        let generated = generate(&["error"]).unwrap();
+        assert!(generated.starts_with("// Generated by compression_lib"));
+        assert!(generated.contains(concat!(
+            "// SPDX-License-Identifier: ",
+            env!("CARGO_PKG_LICENSE")
+        )));
+    }
+
+    #[test]
+    fn test_unknown_module() {
+        let result = generate(&["rle", "zstd"]);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_dependency_table_matches_sources() {
+        for source in SOURCES {
+            let body = strip(source.code);
+            let mut used: Vec<&str> = modules()
+                .filter(|name| body.contains(&format!("super::{name}")))
+                .collect();
+            used.sort_unstable();
+            let mut deps = source.deps.to_vec();
+            deps.sort_unstable();
+            assert_eq!(used, deps, "dependencies of {}", source.name);
+        }
+    }
+
+    #[test]
+    fn test_generated_code_compiles() {
+        let dir = std::env::temp_dir().join(format!("amalgamate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let all: Vec<&str> = modules().collect();
+        std::fs::write(dir.join("compression.rs"), generate(&all).unwrap()).unwrap();
+        std::fs::write(dir.join("lib.rs"), "pub mod compression;\n").unwrap();
+
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let output = std::process::Command::new(rustc)
+            .args([
+                "--edition",
+                "2024",
+                "--crate-type",
+                "lib",
+                "--emit",
+                "metadata",
+            ])
+            .args(["-D", "warnings", "--out-dir"])
+            .arg(&dir)
+            .arg(dir.join("lib.rs"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::{CompressionError, Result};
+use crate::limits::DecompressionLimits;
+use crate::range_coder::{BitCoder, PROB_INIT, RangeDecoder, RangeEncoder, tree};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+/// The adaptive probabilities both sides evolve as they go.
+#[derive(Debug, Clone)]
+struct Model {
+    /// Chance that the stream ends instead of another byte following.
+    end: u32,
+    /// One probability per node of the binary tree over byte values.
+    bytes: [u32; 0x100],
+}
+
+impl Model {
+    const fn new() -> Self {
+        Self {
+            end: PROB_INIT,
+            bytes: [PROB_INIT; 0x100],
+        }
+    }
+
+    /// Codes `byte`, or the end of the stream for `None`. The decoder passes
+    /// anything and gets back what it read.
+    fn code<C: BitCoder>(&mut self, coder: &mut C, byte: Option<u8>) -> Result<Option<u8>> {
+        if coder.bit(&mut self.end, u32::from(byte.is_none()))? == 1 {
+            return Ok(None);
+        }
+        let value = tree(coder, &mut self.bytes, 8, u32::from(byte.unwrap_or(0)))?;
+        Ok(Some(value.to_le_bytes()[0]))
+    }
+}
+
+/// Adaptive order-0 binary arithmetic coder with no header.
+///
+/// Each byte is coded bit by bit through a binary tree of probabilities
+/// that start at even odds and adapt as bytes are seen, preceded by a flag
+/// that marks the end of the stream. Nothing about the data is stored up
+/// front, so unlike [`Huffman`](crate::Huffman), whose code table can
+/// outweigh a short message, the overhead is a few bytes of range-coder
+/// flush. Payloads of tens of bytes stay near their size or shrink; on
+/// longer inputs the ratio approaches that of a static order-0 code.
+/// Output layout: `[range-coded bytes]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Arithmetic;
+
+impl Arithmetic {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Decodes the frame at the start of `input`, stopping as soon as the
+    /// output would break `limits`: nothing up front says how long it is.
+    fn decode(input: &[u8], limits: &DecompressionLimits) -> Result<(Vec<u8>, usize)> {
+        let cap = limits.max_output_for(input.len() as u64);
+        let mut model = Model::new();
+        let mut decoder = RangeDecoder::new(input)?;
+        let mut output = Vec::new();
+        while let Some(byte) = model.code(&mut decoder, None)? {
+            if output.len() as u64 == cap {
+                limits.check(input.len() as u64, cap.saturating_add(1))?;
+            }
+            output.push(byte);
+        }
+        Ok((output, decoder.bytes_consumed()))
+    }
+}
+
+impl Compressor for Arithmetic {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut model = Model::new();
+        let mut encoder = RangeEncoder::new();
+        for &byte in input {
+            model.code(&mut encoder, Some(byte))?;
+        }
+        model.code(&mut encoder, None)?;
+        Ok(encoder.finish())
+    }
+
+    fn name(&self) -> &'static str {
+        "Arithmetic"
+    }
+}
+
+impl Decompressor for Arithmetic {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        let (output, consumed) = Self::decode(input, limits)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Arithmetic"
+    }
+}
+
+impl PartialDecompressor for Arithmetic {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        Self::decode(input, &DecompressionLimits::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Huffman;
+    use crate::range_coder::FLUSH_LEN;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = Arithmetic::new().compress(input).unwrap();
+        assert_eq!(Arithmetic::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_arithmetic_name() {
+        assert_eq!(Compressor::name(&Arithmetic::new()), "Arithmetic");
+        assert_eq!(Decompressor::name(&Arithmetic::new()), "Arithmetic");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]).len(), FLUSH_LEN);
+        assert!(Arithmetic::new().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_all_bytes() {
+        let input: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        roundtrip(&input);
+        roundtrip(&[0]);
+        roundtrip(&[0xFF; 3]);
+    }
+
+    #[test]
+    fn test_tiny_payloads_beat_huffman() {
+        for message in [
+            &b"{\"id\":17,\"ok\":true}"[..],
+            b"GET /status HTTP/1.1",
+            b"temp=21.5;hum=40;batt=3.71",
+        ] {
+            let arithmetic = roundtrip(message).len();
+            let huffman = Huffman::new().compress(message).unwrap().len();
+            assert!(arithmetic < huffman, "{arithmetic} vs {huffman}");
+            assert!(arithmetic <= message.len() + FLUSH_LEN, "{arithmetic}");
+        }
+    }
+
+    #[test]
+    fn test_skewed_input_beats_prefix_codes() {
+        // 90% one byte, the rest spread over three: about 0.63 bits per
+        // byte, where any prefix code such as Huffman needs at least one.
+        let input: Vec<u8> = (0..20_000u32)
+            .map(|i| match i % 30 {
+                0 => b'b',
+                10 => b'c',
+                20 => b'd',
+                _ => b'a',
+            })
+            .collect();
+        let compressed = roundtrip(&input);
+        assert!(compressed.len() * 8 < 20_000 * 9 / 10, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Arithmetic::new();
+        let first = codec.compress(b"abracadabra").unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [b"abracadabra".to_vec(), Vec::new(), b"abracadabra".to_vec()]
+        );
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Arithmetic::new();
+        let compressed = codec.compress(b"hello, hello, hello").unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        // The range coder's first byte is always zero.
+        let mut bad = compressed;
+        bad[0] = 1;
+        assert!(codec.decompress(&bad).is_err());
+    }
+
+    #[test]
+    fn test_limits_stop_decoding() {
+        let codec = Arithmetic::new();
+        let bomb = codec.compress(&vec![0; 1 << 20]).unwrap();
+        assert!(bomb.len() < 1 << 15);
+        assert_eq!(codec.decompressed_len(&bomb).unwrap(), None);
+        for limits in [
+            DecompressionLimits::new().with_max_output_size(1 << 16),
+            DecompressionLimits::new().with_max_ratio(10),
+        ] {
+            assert!(matches!(
+                codec.decompress_with_limits(&bomb, &limits),
+                Err(CompressionError::LimitExceeded(_))
+            ));
+        }
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        assert_eq!(
+            codec.decompress_with_limits(&bomb, &limits).unwrap().len(),
+            1 << 20
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::limits::DecompressionLimits;
+use crate::pipe::{CopyOptions, StreamingCompressor, StreamingDecompressor};
+use crate::traits::{Compressor, Decompressor};
+
+/// Bytes requested from the inner reader per read.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// An [`AsyncWrite`] that compresses everything written to it into
+/// `writer`, for tokio.
+///
+/// The async counterpart of [`CompressorWriter`](crate::CompressorWriter)
+/// and the same stream: bytes are gathered into blocks of the buffer size,
+/// and each full block is compressed on the calling task and passed on.
+/// Blocks are small enough that this needs no `spawn_blocking`.
+/// `shutdown` writes the last partial block and the end marker before
+/// shutting `writer` down; a writer dropped without it leaves the stream
+/// incomplete. Codec errors surface as `ErrorKind::InvalidData`.
+#[derive(Debug)]
+pub struct AsyncCompressorWriter<W, C> {
+    writer: W,
+    stream: Option<StreamingCompressor<C>>,
+    output: Vec<u8>,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin, C: Compressor> AsyncCompressorWriter<W, C> {
+    /// Creates a writer with 64 KiB blocks.
+    pub const fn new(writer: W, codec: C) -> Self {
+        Self::with_options(writer, codec, &CopyOptions::new())
+    }
+
+    /// Creates a writer with blocks of `options`' buffer size.
+    pub const fn with_options(writer: W, codec: C, options: &CopyOptions) -> Self {
+        Self {
+            writer,
+            stream: Some(StreamingCompressor::with_options(codec, options)),
+            output: Vec::new(),
+            written: 0,
+        }
+    }
+
+    pub const fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes out compressed bytes not yet taken by `writer`.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while self.written < self.output.len() {
+            let n =
+                ready!(Pin::new(&mut self.writer).poll_write(cx, &self.output[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.output.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, C: Compressor + Unpin> AsyncWrite for AsyncCompressorWriter<W, C> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        let Some(stream) = this.stream.as_mut() else {
+            return Poll::Ready(Err(std::io::Error::other("write after shutdown")));
+        };
+        stream.write(buf)?;
+        this.output = stream.take_output();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if let Some(stream) = this.stream.take() {
+            this.output = stream.finish()?;
+            ready!(this.poll_drain(cx))?;
+        }
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+/// An [`AsyncRead`] that decompresses a stream from `reader` as it is
+/// read, for tokio.
+///
+/// The async counterpart of
+/// [`DecompressorReader`](crate::DecompressorReader), built on
+/// [`StreamingDecompressor`]: compressed bytes are read in chunks of up to
+/// 8 KiB and each block is decompressed once it is whole, so at most one
+/// block is held. Reads return 0 after the end marker. As the inner reader
+/// is read in chunks, bytes following the end marker may be consumed; they
+/// are kept in [`remaining_input`](Self::remaining_input). Corrupted input,
+/// or a reader that ends before the marker, fails with
+/// `ErrorKind::InvalidData` or `ErrorKind::UnexpectedEof`.
+#[derive(Debug)]
+pub struct AsyncDecompressorReader<R, C> {
+    reader: R,
+    stream: StreamingDecompressor<C>,
+}
+
+impl<R: AsyncRead + Unpin, C: Decompressor> AsyncDecompressorReader<R, C> {
+    pub const fn new(reader: R, codec: C) -> Self {
+        Self {
+            reader,
+            stream: StreamingDecompressor::new(codec),
+        }
+    }
+
+    /// Bounds what each block may decompress to, as
+    /// [`StreamingDecompressor::with_limits`]. Unlimited by default.
+    #[must_use]
+    pub fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.stream = self.stream.with_limits(limits);
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        self.stream.limits()
+    }
+
+    /// Bytes read from `reader` but not decoded; once the stream is over,
+    /// whatever followed the end marker.
+    #[must_use]
+    pub fn remaining_input(&self) -> &[u8] {
+        self.stream.remaining_input()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin, C: Decompressor + Unpin> AsyncRead for AsyncDecompressorReader<R, C> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let mut chunk = [0; READ_CHUNK];
+        while buf.remaining() > 0 {
+            let n = this.stream.next_output(buf.initialize_unfilled())?;
+            if n > 0 {
+                buf.advance(n);
+                break;
+            }
+            if this.stream.is_finished() {
+                break;
+            }
+            let mut input = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut input))?;
+            if input.filled().is_empty() {
+                return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+            }
+            this.stream.feed(input.filled());
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz4, copy_compress, copy_decompress};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn text() -> Vec<u8> {
+        b"async services compress without blocking; ".repeat(500)
+    }
+
+    #[test]
+    fn test_writer_matches_copy_compress() {
+        let input = text();
+        let options = CopyOptions::new().with_buffer_size(4096);
+        let compressed = block_on(async {
+            let mut writer = AsyncCompressorWriter::with_options(Vec::new(), Lz4::new(), &options);
+            for chunk in input.chunks(777) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.flush().await.unwrap();
+            writer.shutdown().await.unwrap();
+            assert!(writer.write(b"late").await.is_err());
+            writer.into_inner()
+        });
+
+        let mut expected = Vec::new();
+        copy_compress(input.as_slice(), &mut expected, &Lz4::new(), &options).unwrap();
+        assert_eq!(compressed, expected);
+        let mut output = Vec::new();
+        copy_decompress(compressed.as_slice(), &mut output, &Lz4::new(), &options).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_reader_roundtrip() {
+        let input = text();
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Huffman::new(), &options).unwrap();
+        stream.extend_from_slice(b"trailer");
+
+        block_on(async {
+            let mut reader = AsyncDecompressorReader::new(stream.as_slice(), Huffman::new());
+            let mut head = [0; 10];
+            reader.read_exact(&mut head).await.unwrap();
+            assert_eq!(head, input[..10]);
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, input[10..]);
+            assert_eq!(reader.remaining_input(), b"trailer");
+        });
+    }
+
+    #[test]
+    fn test_reader_limits() {
+        let mut stream = Vec::new();
+        let options = CopyOptions::new().with_buffer_size(1000);
+        copy_compress(text().as_slice(), &mut stream, &Lz4::new(), &options).unwrap();
+        let limits = DecompressionLimits::new().with_max_output_size(500);
+        let err = block_on(async {
+            let mut reader =
+                AsyncDecompressorReader::new(stream.as_slice(), Lz4::new()).with_limits(limits);
+            assert_eq!(reader.limits(), &limits);
+            reader.read_to_end(&mut Vec::new()).await.unwrap_err()
+        });
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        let mut stream = Vec::new();
+        copy_compress(
+            text().as_slice(),
+            &mut stream,
+            &Lz4::new(),
+            &CopyOptions::new(),
+        )
+        .unwrap();
+        stream.pop();
+        let err = block_on(async {
+            let mut reader = AsyncDecompressorReader::new(stream.as_slice(), Lz4::new());
+            reader.read_to_end(&mut Vec::new()).await.unwrap_err()
+        });
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
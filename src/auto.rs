@@ -0,0 +1,404 @@
+use crate::classify::{ContentClass, classify};
+use crate::error::{CompressionError, Result};
+use crate::huffman::Huffman;
+use crate::level::CompressionLevel;
+use crate::lz77::Lz77;
+use crate::rle::Rle;
+use crate::stored::Stored;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+pub const ALGORITHM_STORED: u8 = 0;
+pub const ALGORITHM_RLE: u8 = 1;
+pub const ALGORITHM_LZ77: u8 = 2;
+pub const ALGORITHM_HUFFMAN: u8 = 3;
+
+/// The codec an [`Auto`] frame was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoAlgorithm {
+    /// Input framed verbatim by [`Stored`], when nothing else helped.
+    Stored,
+    Rle,
+    Lz77,
+    Huffman,
+}
+
+impl AutoAlgorithm {
+    /// The candidates [`Auto`] tries, cheapest to decode first so ties go
+    /// to the faster one.
+    const CANDIDATES: [Self; 3] = [Self::Rle, Self::Lz77, Self::Huffman];
+
+    /// The candidates worth trying on data of `class`: none for near-random
+    /// data, which would only be stored after paying for every attempt.
+    const fn candidates(class: ContentClass) -> &'static [Self] {
+        match class {
+            ContentClass::Compressed => &[],
+            ContentClass::Text | ContentClass::Numeric { .. } | ContentClass::Binary => {
+                &Self::CANDIDATES
+            }
+        }
+    }
+
+    const fn tag(self) -> u8 {
+        match self {
+            Self::Stored => ALGORITHM_STORED,
+            Self::Rle => ALGORITHM_RLE,
+            Self::Lz77 => ALGORITHM_LZ77,
+            Self::Huffman => ALGORITHM_HUFFMAN,
+        }
+    }
+
+    const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            ALGORITHM_STORED => Some(Self::Stored),
+            ALGORITHM_RLE => Some(Self::Rle),
+            ALGORITHM_LZ77 => Some(Self::Lz77),
+            ALGORITHM_HUFFMAN => Some(Self::Huffman),
+            _ => None,
+        }
+    }
+
+    fn compress(self, input: &[u8], level: CompressionLevel) -> Result<Vec<u8>> {
+        match self {
+            Self::Stored => Stored::new().compress(input),
+            Self::Rle => Rle::escaped().compress(input),
+            Self::Lz77 => Lz77::new().with_level(level).compress(input),
+            Self::Huffman => Huffman::new().compress(input),
+        }
+    }
+
+    fn decompress_partial(self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        match self {
+            Self::Stored => Stored::new().decompress_partial(input),
+            Self::Rle => Rle::escaped().decompress_partial(input),
+            Self::Lz77 => Lz77::new().decompress_partial(input),
+            Self::Huffman => Huffman::new().decompress_partial(input),
+        }
+    }
+
+    fn decompressed_len(self, input: &[u8]) -> Result<Option<u64>> {
+        match self {
+            Self::Stored => Stored::new().decompressed_len(input),
+            Self::Rle => Rle::escaped().decompressed_len(input),
+            Self::Lz77 => Lz77::new().decompressed_len(input),
+            Self::Huffman => Huffman::new().decompressed_len(input),
+        }
+    }
+}
+
+/// Codec that picks whichever of RLE, LZ77 and Huffman compresses the input
+/// best.
+///
+/// By default every candidate compresses the whole input and the smallest
+/// output wins, which costs the sum of their compression times. With
+/// [`Auto::sampled`] the candidates only race on a prefix of the input and
+/// the winner alone compresses the rest. Either way, if the result is not
+/// smaller than the input framed by [`Stored`], the stored frame is
+/// emitted instead, so output is never more than a few bytes larger than
+/// the input. RLE runs in its escaped format, the others with their
+/// default settings, and decompression reads the tag and hands the payload
+/// to the matching codec. Output layout: `[algorithm:
+/// u8][payload]`.
+///
+/// The raced data, sample or whole input, is first run through
+/// [`classify`], and data it finds [`ContentClass::Compressed`] is stored
+/// without trying any candidate. That saves three futile passes over
+/// encrypted or already-compressed data, at the cost of a few bytes when
+/// near-random data hides a short stretch that would have compressed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Auto {
+    sample_len: Option<usize>,
+    level: CompressionLevel,
+}
+
+impl Auto {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            sample_len: None,
+            level: CompressionLevel::DEFAULT,
+        }
+    }
+
+    /// Chooses the algorithm from the first `sample_len` bytes of each
+    /// input instead of the whole of it.
+    #[must_use]
+    pub const fn sampled(sample_len: usize) -> Self {
+        Self {
+            sample_len: Some(sample_len),
+            level: CompressionLevel::DEFAULT,
+        }
+    }
+
+    #[must_use]
+    pub const fn sample_len(&self) -> Option<usize> {
+        self.sample_len
+    }
+
+    /// The algorithm `frame` was compressed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidHeader` if `frame` is empty or its
+    /// tag is unknown.
+    pub fn algorithm(frame: &[u8]) -> Result<AutoAlgorithm> {
+        frame
+            .first()
+            .and_then(|&tag| AutoAlgorithm::from_tag(tag))
+            .ok_or(CompressionError::InvalidHeader)
+    }
+}
+
+impl Compressor for Auto {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut best = (AutoAlgorithm::Stored, Stored::new().compress(input)?);
+        match self.sample_len {
+            Some(sample_len) if sample_len < input.len() => {
+                let sample = &input[..sample_len];
+                let mut winner = (AutoAlgorithm::Stored, sample.len());
+                for &algorithm in AutoAlgorithm::candidates(classify(sample)) {
+                    let size = algorithm.compress(sample, self.level)?.len();
+                    if size < winner.1 {
+                        winner = (algorithm, size);
+                    }
+                }
+                if winner.0 != AutoAlgorithm::Stored {
+                    let payload = winner.0.compress(input, self.level)?;
+                    if payload.len() < best.1.len() {
+                        best = (winner.0, payload);
+                    }
+                }
+            }
+            _ => {
+                for &algorithm in AutoAlgorithm::candidates(classify(input)) {
+                    let payload = algorithm.compress(input, self.level)?;
+                    if payload.len() < best.1.len() {
+                        best = (algorithm, payload);
+                    }
+                }
+            }
+        }
+
+        let (algorithm, payload) = best;
+        let mut output = Vec::with_capacity(payload.len() + 1);
+        output.push(algorithm.tag());
+        output.extend_from_slice(&payload);
+        Ok(output)
+    }
+
+    /// Whatever wins can be no larger than the stored fallback.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        Stored::new()
+            .max_compressed_len(input_len)
+            .saturating_add(1)
+    }
+
+    /// Passes the level on to LZ77, the only candidate with settings to
+    /// tune; sampling is unaffected.
+    fn with_level(mut self, level: CompressionLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        "Auto"
+    }
+}
+
+impl Decompressor for Auto {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        Self::algorithm(input)?.decompressed_len(&input[1..])
+    }
+
+    fn name(&self) -> &'static str {
+        "Auto"
+    }
+}
+
+impl PartialDecompressor for Auto {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let algorithm = Self::algorithm(input)?;
+        let (output, consumed) = algorithm.decompress_partial(&input[1..])?;
+        Ok((output, consumed + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(codec: Auto, input: &[u8]) -> Vec<u8> {
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_auto_name() {
+        assert_eq!(Compressor::name(&Auto::new()), "Auto");
+        assert_eq!(Decompressor::name(&Auto::new()), "Auto");
+        assert_eq!(Auto::new().sample_len(), None);
+        assert_eq!(Auto::sampled(64).sample_len(), Some(64));
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressed = roundtrip(Auto::new(), &[]);
+        assert_eq!(Auto::algorithm(&compressed).unwrap(), AutoAlgorithm::Stored);
+        assert!(Auto::new().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_picks_per_input() {
+        let runs: Vec<u8> = [0u8, 1, 2, 3]
+            .iter()
+            .flat_map(|&byte| [byte; 200])
+            .collect();
+        let repeats = b"abcdefghijklmnopqrstuvwxyz0123456789".repeat(30);
+        let skewed: Vec<u8> = noise(1000)
+            .iter()
+            .map(|&b| b"aaaabbc"[usize::from(b % 7)])
+            .collect();
+        let random = noise(1000);
+        for (input, expected) in [
+            (&runs, AutoAlgorithm::Rle),
+            (&repeats, AutoAlgorithm::Lz77),
+            (&skewed, AutoAlgorithm::Huffman),
+            (&random, AutoAlgorithm::Stored),
+        ] {
+            let compressed = roundtrip(Auto::new(), input);
+            assert_eq!(Auto::algorithm(&compressed).unwrap(), expected);
+            assert!(compressed.len() <= input.len() + 3);
+        }
+    }
+
+    #[test]
+    fn test_candidates_follow_content_class() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(20);
+        assert_eq!(classify(&text), ContentClass::Text);
+        assert_eq!(
+            AutoAlgorithm::candidates(ContentClass::Text),
+            AutoAlgorithm::CANDIDATES
+        );
+        assert!(AutoAlgorithm::candidates(ContentClass::Compressed).is_empty());
+
+        // Near-random bytes around a run RLE would shrink: racing picks
+        // RLE, but the content class rules every candidate out.
+        let mut input = noise(4000);
+        input[2000..2100].fill(0);
+        assert_eq!(classify(&input), ContentClass::Compressed);
+        let rle = AutoAlgorithm::Rle
+            .compress(&input, CompressionLevel::DEFAULT)
+            .unwrap();
+        let stored = roundtrip(Auto::new(), &input);
+        assert!(rle.len() + 1 < stored.len());
+        assert_eq!(Auto::algorithm(&stored).unwrap(), AutoAlgorithm::Stored);
+        let sampled = roundtrip(Auto::sampled(3000), &input);
+        assert_eq!(Auto::algorithm(&sampled).unwrap(), AutoAlgorithm::Stored);
+
+        // The same run in a binary structure is left to the race.
+        let mut binary: Vec<u8> = (0..4000u32)
+            .map(|i| (i % 251).to_le_bytes()[0] | 0x80)
+            .collect();
+        binary[2000..2100].fill(0);
+        assert_ne!(classify(&binary), ContentClass::Compressed);
+        let raced = roundtrip(Auto::new(), &binary);
+        assert_ne!(Auto::algorithm(&raced).unwrap(), AutoAlgorithm::Stored);
+    }
+
+    #[test]
+    fn test_smallest_candidate_wins() {
+        let input = b"the quick brown fox jumps over the lazy dog. ".repeat(20);
+        let compressed = roundtrip(Auto::new(), &input);
+        let smallest = AutoAlgorithm::CANDIDATES
+            .iter()
+            .map(|algorithm| {
+                algorithm
+                    .compress(&input, CompressionLevel::DEFAULT)
+                    .unwrap()
+                    .len()
+            })
+            .min()
+            .unwrap();
+        assert_eq!(compressed.len(), smallest + 1);
+    }
+
+    #[test]
+    fn test_sampled() {
+        // Runs up front, then text: the sample only sees the runs.
+        let mut input = vec![b'x'; 300];
+        input.extend(b"the quick brown fox jumps over the lazy dog. ".repeat(10));
+        let sampled = roundtrip(Auto::sampled(256), &input);
+        assert_eq!(Auto::algorithm(&sampled).unwrap(), AutoAlgorithm::Rle);
+        // A sample covering the whole input races every candidate on it.
+        let full = roundtrip(Auto::sampled(input.len()), &input);
+        assert_eq!(full, Auto::new().compress(&input).unwrap());
+        // Incompressible samples fall back to stored without compressing
+        // the rest.
+        let random = noise(600);
+        let stored = roundtrip(Auto::sampled(100), &random);
+        assert_eq!(Auto::algorithm(&stored).unwrap(), AutoAlgorithm::Stored);
+    }
+
+    #[test]
+    fn test_with_level_tunes_lz77() {
+        // Repeats 80 bytes apart, beyond the fastest level's window.
+        let phrase =
+            b"level one searches a short window, so it cannot see back to the line before. ";
+        let input = phrase.repeat(20);
+        let fastest = roundtrip(Auto::new().with_level(CompressionLevel::FASTEST), &input);
+        let default = roundtrip(Auto::new(), &input);
+        assert!(
+            fastest.len() > default.len(),
+            "{} {}",
+            fastest.len(),
+            default.len()
+        );
+        let sampled = Auto::sampled(256).with_level(CompressionLevel::BEST);
+        assert_eq!(sampled.sample_len(), Some(256));
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Auto::new();
+        let first = codec.compress(&[7; 100]).unwrap();
+        let second = codec.compress(b"xyz").unwrap();
+        let stream = [first.as_slice(), &second].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [vec![7; 100], b"xyz".to_vec()]);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Auto::new();
+        assert_eq!(
+            Auto::algorithm(&[9, 0]),
+            Err(CompressionError::InvalidHeader)
+        );
+        assert!(codec.decompress(&[9, 0]).is_err());
+        let compressed = codec.compress(&[7; 100]).unwrap();
+        assert!(codec.decompress(&compressed[..1]).is_err());
+        let mut trailing = compressed;
+        trailing.push(0);
+        assert!(codec.decompress(&trailing).is_err());
+    }
+}
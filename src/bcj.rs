@@ -0,0 +1,369 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor};
+
+pub const ARCH_X86: u8 = 0;
+pub const ARCH_ARM: u8 = 1;
+pub const ARCH_ARM64: u8 = 2;
+
+/// x86 `call rel32` and `jmp rel32` opcodes.
+const X86_CALL: u8 = 0xE8;
+const X86_JMP: u8 = 0xE9;
+const X86_INSTRUCTION_LEN: usize = 5;
+/// x86 displacements are converted modulo 2^25, which maps the near range
+/// `[-2^24, 2^24)` onto itself so the decoder sees the same candidates.
+const X86_MASK: u32 = 0x01FF_FFFF;
+const X86_SIGN: u32 = 0x0100_0000;
+
+/// Top byte of an ARM `BL` instruction with the always condition.
+const ARM_BL: u8 = 0xEB;
+const ARM_MASK: u32 = 0x00FF_FFFF;
+/// ARM reads the program counter two instructions ahead.
+const ARM_PC_OFFSET: usize = 8;
+
+/// Opcode in the top six bits of an `AArch64` `BL` instruction.
+const ARM64_BL: u32 = 0b10_0101;
+const ARM64_MASK: u32 = 0x03FF_FFFF;
+
+/// Instruction set whose branches [`Bcj`] converts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BcjArch {
+    /// `E8`/`E9` near calls and jumps with a 32-bit displacement.
+    #[default]
+    X86,
+    /// 32-bit little-endian ARM `BL`, 4-byte aligned.
+    Arm,
+    /// `AArch64` `BL`, 4-byte aligned.
+    Arm64,
+}
+
+impl BcjArch {
+    const fn tag(self) -> u8 {
+        match self {
+            Self::X86 => ARCH_X86,
+            Self::Arm => ARCH_ARM,
+            Self::Arm64 => ARCH_ARM64,
+        }
+    }
+
+    const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            ARCH_X86 => Some(Self::X86),
+            ARCH_ARM => Some(Self::Arm),
+            ARCH_ARM64 => Some(Self::Arm64),
+            _ => None,
+        }
+    }
+}
+
+/// Reversible branch-conversion filter for machine code, in the style of
+/// the BCJ filters of xz and 7-Zip.
+///
+/// Calls encode their target relative to the instruction, so repeated calls
+/// to one function have different bytes at every call site. The filter
+/// rewrites those displacements as absolute targets, which repeat exactly
+/// and give LZ77 and Huffman much more to work with on program binaries.
+/// Data that is not code still round-trips, it just gains nothing. Output
+/// layout: `[arch: u8][converted code]`; run it before the main codec.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bcj {
+    arch: BcjArch,
+}
+
+impl Bcj {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_arch(BcjArch::X86)
+    }
+
+    /// Creates a filter for `arch` branches.
+    #[must_use]
+    pub const fn with_arch(arch: BcjArch) -> Self {
+        Self { arch }
+    }
+
+    #[must_use]
+    pub const fn arch(&self) -> BcjArch {
+        self.arch
+    }
+}
+
+/// Low 32 bits of a buffer position; displacements wrap modulo 2^32.
+fn position(pos: usize) -> u32 {
+    u32::try_from(pos & 0xFFFF_FFFF).unwrap_or(0)
+}
+
+fn read_u32(code: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([code[pos], code[pos + 1], code[pos + 2], code[pos + 3]])
+}
+
+fn convert_x86(code: &mut [u8], encode: bool) {
+    let mut i = 0;
+    while i + X86_INSTRUCTION_LEN <= code.len() {
+        if !matches!(code[i], X86_CALL | X86_JMP) {
+            i += 1;
+            continue;
+        }
+
+        let displacement = read_u32(code, i + 1);
+        if !matches!(displacement >> 24, 0x00 | 0xFF) {
+            i += 1;
+            continue;
+        }
+
+        let pc = position(i + X86_INSTRUCTION_LEN);
+        let converted = if encode {
+            displacement.wrapping_add(pc)
+        } else {
+            displacement.wrapping_sub(pc)
+        } & X86_MASK;
+        let converted = if converted & X86_SIGN == 0 {
+            converted
+        } else {
+            converted | !X86_MASK
+        };
+        code[i + 1..i + X86_INSTRUCTION_LEN].copy_from_slice(&converted.to_le_bytes());
+        i += X86_INSTRUCTION_LEN;
+    }
+}
+
+fn convert_arm(code: &mut [u8], encode: bool) {
+    for (index, instruction) in code.chunks_exact_mut(4).enumerate() {
+        if instruction[3] != ARM_BL {
+            continue;
+        }
+        let offset = read_u32(instruction, 0) & ARM_MASK;
+        let pc = position(index * 4 + ARM_PC_OFFSET) >> 2;
+        let converted = if encode {
+            offset.wrapping_add(pc)
+        } else {
+            offset.wrapping_sub(pc)
+        } & ARM_MASK;
+        instruction[..3].copy_from_slice(&converted.to_le_bytes()[..3]);
+    }
+}
+
+fn convert_arm64(code: &mut [u8], encode: bool) {
+    for (index, instruction) in code.chunks_exact_mut(4).enumerate() {
+        let word = read_u32(instruction, 0);
+        if word >> 26 != ARM64_BL {
+            continue;
+        }
+        let pc = position(index);
+        let converted = if encode {
+            word.wrapping_add(pc)
+        } else {
+            word.wrapping_sub(pc)
+        } & ARM64_MASK;
+        instruction.copy_from_slice(&((word & !ARM64_MASK) | converted).to_le_bytes());
+    }
+}
+
+fn convert(arch: BcjArch, code: &mut [u8], encode: bool) {
+    match arch {
+        BcjArch::X86 => convert_x86(code, encode),
+        BcjArch::Arm => convert_arm(code, encode),
+        BcjArch::Arm64 => convert_arm64(code, encode),
+    }
+}
+
+impl Compressor for Bcj {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() + 1);
+        output.push(self.arch.tag());
+        output.extend_from_slice(input);
+        convert(self.arch, &mut output[1..], true);
+
+        Ok(output)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        input_len.saturating_add(1)
+    }
+
+    fn name(&self) -> &'static str {
+        "BCJ"
+    }
+}
+
+impl Decompressor for Bcj {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (&tag, code) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        let arch = BcjArch::from_tag(tag).ok_or(CompressionError::InvalidHeader)?;
+
+        let mut output = code.to_vec();
+        convert(arch, &mut output, false);
+
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let (&tag, code) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        BcjArch::from_tag(tag).ok_or(CompressionError::InvalidHeader)?;
+        Ok(Some(code.len() as u64))
+    }
+
+    fn name(&self) -> &'static str {
+        "BCJ"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lz77;
+
+    fn roundtrip(bcj: Bcj, input: &[u8]) -> Vec<u8> {
+        let filtered = bcj.compress(input).unwrap();
+        assert_eq!(filtered.len(), input.len() + 1);
+        assert_eq!(bcj.decompress(&filtered).unwrap(), input);
+        filtered
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    /// x86 code calling one function from many sites, padded with `nop`s.
+    fn x86_calls(target: usize, sites: usize) -> Vec<u8> {
+        let mut code = Vec::new();
+        for site in 0..sites {
+            code.extend(std::iter::repeat_n(0x90, site % 7 + 1));
+            let pc = i64::try_from(code.len() + X86_INSTRUCTION_LEN).unwrap();
+            let rel = i32::try_from(i64::try_from(target).unwrap() - pc).unwrap();
+            code.push(X86_CALL);
+            code.extend_from_slice(&rel.to_le_bytes());
+        }
+        code
+    }
+
+    #[test]
+    fn test_bcj_new() {
+        let bcj = Bcj::new();
+        assert_eq!(bcj.arch(), BcjArch::X86);
+        assert_eq!(Bcj::default(), bcj);
+        assert_eq!(Compressor::name(&bcj), "BCJ");
+        assert_eq!(Decompressor::name(&bcj), "BCJ");
+    }
+
+    #[test]
+    fn test_empty() {
+        for arch in [BcjArch::X86, BcjArch::Arm, BcjArch::Arm64] {
+            let bcj = Bcj::with_arch(arch);
+            assert_eq!(roundtrip(bcj, &[]), [arch.tag()]);
+        }
+        assert!(matches!(
+            Bcj::new().decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_x86_known_output() {
+        // call +0x10 at offset 2: the target is 2 + 5 + 0x10 = 0x17.
+        let input = [0x90, 0x90, X86_CALL, 0x10, 0, 0, 0, 0xC3];
+        let filtered = roundtrip(Bcj::new(), &input);
+        assert_eq!(
+            filtered,
+            [ARCH_X86, 0x90, 0x90, X86_CALL, 0x17, 0, 0, 0, 0xC3]
+        );
+    }
+
+    #[test]
+    fn test_x86_negative_displacement() {
+        // jmp -16 at offset 0 targets -11, which stays sign-extended.
+        let mut input = vec![X86_JMP];
+        input.extend_from_slice(&(-16i32).to_le_bytes());
+        let filtered = roundtrip(Bcj::new(), &input);
+        assert_eq!(&filtered[2..], (-11i32).to_le_bytes());
+    }
+
+    #[test]
+    fn test_x86_far_displacement_untouched() {
+        let input = [X86_CALL, 0x00, 0x00, 0x00, 0x40, 0x90];
+        let filtered = roundtrip(Bcj::new(), &input);
+        assert_eq!(&filtered[1..], input);
+    }
+
+    #[test]
+    fn test_x86_calls_share_target() {
+        let code = x86_calls(0x1000, 200);
+        let filtered = roundtrip(Bcj::new(), &code);
+        let targets: Vec<&[u8]> = filtered[1..]
+            .windows(X86_INSTRUCTION_LEN)
+            .filter(|w| w[0] == X86_CALL)
+            .map(|w| &w[1..])
+            .collect();
+        assert_eq!(targets.len(), 200);
+        assert!(targets.iter().all(|&t| t == 0x1000u32.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_improves_lz77_on_x86() {
+        let code = x86_calls(0x4000, 500);
+        let plain = Lz77::new().compress(&code).unwrap();
+        let filtered = Lz77::new()
+            .compress(&Bcj::new().compress(&code).unwrap())
+            .unwrap();
+        assert!(filtered.len() * 2 < plain.len());
+    }
+
+    #[test]
+    fn test_arm_known_output() {
+        // bl with offset 1 at instruction 1: pc = (4 + 8) / 4 = 3.
+        let input = [0, 0, 0, 0, 1, 0, 0, ARM_BL];
+        let filtered = roundtrip(Bcj::with_arch(BcjArch::Arm), &input);
+        assert_eq!(filtered, [ARCH_ARM, 0, 0, 0, 0, 4, 0, 0, ARM_BL]);
+    }
+
+    #[test]
+    fn test_arm64_known_output() {
+        // bl with offset 2 at instruction 3 targets instruction 5.
+        let bl = (ARM64_BL << 26) | 2;
+        let mut input = [0u8; 16];
+        input[12..].copy_from_slice(&bl.to_le_bytes());
+        let filtered = roundtrip(Bcj::with_arch(BcjArch::Arm64), &input);
+        assert_eq!(&filtered[13..], ((ARM64_BL << 26) | 5).to_le_bytes());
+    }
+
+    #[test]
+    fn test_roundtrip_noise_all_arches() {
+        let input = noise(4099);
+        for arch in [BcjArch::X86, BcjArch::Arm, BcjArch::Arm64] {
+            roundtrip(Bcj::with_arch(arch), &input);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_dense_opcodes() {
+        let input: Vec<u8> = [X86_CALL, 0xFF, X86_JMP, 0x00, 0xFF, ARM_BL]
+            .iter()
+            .copied()
+            .cycle()
+            .take(1000)
+            .collect();
+        for arch in [BcjArch::X86, BcjArch::Arm, BcjArch::Arm64] {
+            roundtrip(Bcj::with_arch(arch), &input);
+        }
+    }
+
+    #[test]
+    fn test_decompress_uses_stored_arch() {
+        let input = noise(256);
+        let filtered = Bcj::with_arch(BcjArch::Arm).compress(&input).unwrap();
+        assert_eq!(Bcj::new().decompress(&filtered).unwrap(), input);
+    }
+
+    #[test]
+    fn test_unknown_arch() {
+        let result = Bcj::new().decompress(&[9, 1, 2, 3]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+}
@@ -0,0 +1,597 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor};
+use crate::varint;
+
+/// Chunk kinds, stored in the low bits of each chunk header.
+pub const KIND_ZERO_FILL: u64 = 0;
+pub const KIND_ONE_FILL: u64 = 1;
+pub const KIND_LITERALS: u64 = 2;
+const KIND_BITS: u32 = 2;
+const WORD_BITS: u64 = u64::BITS as u64;
+const WORD_BYTES: usize = 8;
+
+/// A run of identical words or a block of mixed ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Chunk {
+    Fill { ones: bool, words: u64 },
+    Literals(Vec<u64>),
+}
+
+/// Word-aligned compressed bitmap, in the style of WAH and EWAH.
+///
+/// The bitset is cut into 64-bit words. Runs of all-zero or all-one words
+/// collapse into a single fill, and the remaining words are stored as
+/// literals, so sparse and dense regions both stay small. [`Bitmap::and`]
+/// and [`Bitmap::or`] walk the fills and literals of both operands directly,
+/// skipping whole fills at once, and never expand the bitsets. Encoded
+/// layout: `[chunks: varint]` then `[words << 2 | kind: varint]` per chunk,
+/// followed by the words themselves as `u64` LE for literal chunks.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    chunks: Vec<Chunk>,
+}
+
+impl Bitmap {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { chunks: Vec::new() }
+    }
+
+    /// Creates a bitmap with the bits at `indices` set. Indices may repeat
+    /// and come in any order.
+    pub fn from_indices(indices: impl IntoIterator<Item = u64>) -> Self {
+        let mut indices: Vec<u64> = indices.into_iter().collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut bitmap = Self::new();
+        let mut next_word = 0;
+        let mut rest = indices.as_slice();
+        while let Some(&first) = rest.first() {
+            let word_index = first / WORD_BITS;
+            let in_word = rest.partition_point(|&i| i / WORD_BITS == word_index);
+            let word = rest[..in_word]
+                .iter()
+                .fold(0u64, |word, &i| word | 1 << (i % WORD_BITS));
+            bitmap.push_fill(false, word_index - next_word);
+            bitmap.push_word(word);
+            next_word = word_index + 1;
+            rest = &rest[in_word..];
+        }
+        bitmap
+    }
+
+    /// Creates a bitmap from a raw bitset, where bit `i` is bit `i % 8` of
+    /// byte `i / 8`.
+    #[must_use]
+    pub fn from_bitset(bits: &[u8]) -> Self {
+        let mut bitmap = Self::new();
+        for chunk in bits.chunks(WORD_BYTES) {
+            let mut word = [0u8; WORD_BYTES];
+            word[..chunk.len()].copy_from_slice(chunk);
+            bitmap.push_word(u64::from_le_bytes(word));
+        }
+        bitmap.trim();
+        bitmap
+    }
+
+    /// Expands the bitmap into a raw bitset, as read by
+    /// [`Bitmap::from_bitset`], just long enough to hold the last set bit
+    /// rounded up to a whole word.
+    #[must_use]
+    pub fn to_bitset(&self) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Fill { ones, words } => {
+                    let fill = if *ones { 0xFF } else { 0 };
+                    let len = usize::try_from(*words).unwrap_or(usize::MAX) * WORD_BYTES;
+                    bits.resize(bits.len() + len, fill);
+                }
+                Chunk::Literals(words) => {
+                    for word in words {
+                        bits.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+            }
+        }
+        bits
+    }
+
+    /// Returns whether bit `index` is set.
+    #[must_use]
+    pub fn contains(&self, index: u64) -> bool {
+        let target = index / WORD_BITS;
+        let mut word_index = 0;
+        for chunk in &self.chunks {
+            let words = chunk_words(chunk);
+            if target < word_index + words {
+                return match chunk {
+                    Chunk::Fill { ones, .. } => *ones,
+                    Chunk::Literals(literals) => {
+                        let offset = usize::try_from(target - word_index).unwrap_or(usize::MAX);
+                        literals[offset] >> (index % WORD_BITS) & 1 == 1
+                    }
+                };
+            }
+            word_index += words;
+        }
+        false
+    }
+
+    /// Number of set bits.
+    #[must_use]
+    pub fn count_ones(&self) -> u64 {
+        self.chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Fill { ones: true, words } => words * WORD_BITS,
+                Chunk::Fill { ones: false, .. } => 0,
+                Chunk::Literals(words) => words.iter().map(|w| u64::from(w.count_ones())).sum(),
+            })
+            .sum()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Indices of the set bits, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut word_index = 0;
+        self.chunks.iter().flat_map(move |chunk| {
+            let start = word_index;
+            word_index += chunk_words(chunk);
+            let bits: Box<dyn Iterator<Item = u64> + '_> = match chunk {
+                Chunk::Fill { ones: true, words } => {
+                    Box::new(start * WORD_BITS..(start + words) * WORD_BITS)
+                }
+                Chunk::Fill { ones: false, .. } => Box::new(std::iter::empty()),
+                Chunk::Literals(words) => {
+                    Box::new((start..).zip(words).flat_map(|(index, &word)| {
+                        (0..WORD_BITS)
+                            .filter(move |bit| word >> bit & 1 == 1)
+                            .map(move |bit| index * WORD_BITS + bit)
+                    }))
+                }
+            };
+            bits
+        })
+    }
+
+    /// Intersection of two bitmaps, computed on the compressed form.
+    #[must_use]
+    pub fn and(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Union of two bitmaps, computed on the compressed form.
+    #[must_use]
+    pub fn or(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a | b)
+    }
+
+    fn combine(&self, other: &Self, op: impl Fn(u64, u64) -> u64) -> Self {
+        let mut result = Self::new();
+        let mut left = Cursor::new(&self.chunks);
+        let mut right = Cursor::new(&other.chunks);
+        while !(left.is_done() && right.is_done()) {
+            // Two fills combine into one fill of their overlap; otherwise
+            // step a single word.
+            if let (Some((a, a_words)), Some((b, b_words))) = (left.fill(), right.fill()) {
+                let words = a_words.min(b_words);
+                let ones = op(fill_word(a), fill_word(b)) == u64::MAX;
+                result.push_fill(ones, words);
+                left.advance(words);
+                right.advance(words);
+            } else {
+                result.push_word(op(left.word(), right.word()));
+                left.advance(1);
+                right.advance(1);
+            }
+        }
+        result.trim();
+        result
+    }
+
+    /// Serializes the bitmap.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, self.chunks.len() as u64);
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Fill { ones, words } => {
+                    let kind = if *ones { KIND_ONE_FILL } else { KIND_ZERO_FILL };
+                    varint::write_u64(&mut output, words << KIND_BITS | kind);
+                }
+                Chunk::Literals(words) => {
+                    varint::write_u64(
+                        &mut output,
+                        (words.len() as u64) << KIND_BITS | KIND_LITERALS,
+                    );
+                    for word in words {
+                        output.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// Parses a bitmap written by [`Bitmap::encode`], returning it and the
+    /// number of bytes read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the input is truncated,
+    /// a chunk is empty, or a chunk kind is unknown.
+    pub fn decode(input: &[u8]) -> Result<(Self, usize)> {
+        let mut pos = 0;
+        let chunks = varint::read_u64(input, &mut pos)?;
+        let mut bitmap = Self::new();
+        for _ in 0..chunks {
+            let header = varint::read_u64(input, &mut pos)?;
+            let words = header >> KIND_BITS;
+            if words == 0 {
+                return Err(CompressionError::CorruptedData);
+            }
+            match header & ((1 << KIND_BITS) - 1) {
+                KIND_ZERO_FILL => bitmap.push_fill(false, words),
+                KIND_ONE_FILL => bitmap.push_fill(true, words),
+                KIND_LITERALS => {
+                    let len = usize::try_from(words)
+                        .ok()
+                        .and_then(|words| words.checked_mul(WORD_BYTES))
+                        .ok_or(CompressionError::CorruptedData)?;
+                    let literals = input
+                        .get(pos..pos + len)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    for word in literals.chunks_exact(WORD_BYTES) {
+                        let mut bytes = [0u8; WORD_BYTES];
+                        bytes.copy_from_slice(word);
+                        bitmap.push_word(u64::from_le_bytes(bytes));
+                    }
+                    pos += len;
+                }
+                _ => return Err(CompressionError::CorruptedData),
+            }
+        }
+        bitmap.trim();
+        Ok((bitmap, pos))
+    }
+
+    /// Number of words covered, including fills.
+    fn word_len(&self) -> u64 {
+        self.chunks.iter().map(chunk_words).sum()
+    }
+
+    fn push_fill(&mut self, ones: bool, words: u64) {
+        if words == 0 {
+            return;
+        }
+        if let Some(Chunk::Fill {
+            ones: last,
+            words: run,
+        }) = self.chunks.last_mut()
+            && *last == ones
+        {
+            *run += words;
+            return;
+        }
+        self.chunks.push(Chunk::Fill { ones, words });
+    }
+
+    fn push_word(&mut self, word: u64) {
+        match word {
+            0 => self.push_fill(false, 1),
+            u64::MAX => self.push_fill(true, 1),
+            _ => {
+                if let Some(Chunk::Literals(words)) = self.chunks.last_mut() {
+                    words.push(word);
+                } else {
+                    self.chunks.push(Chunk::Literals(vec![word]));
+                }
+            }
+        }
+    }
+
+    /// Drops a trailing zero fill, which carries no set bits.
+    fn trim(&mut self) {
+        if let Some(Chunk::Fill { ones: false, .. }) = self.chunks.last() {
+            self.chunks.pop();
+        }
+    }
+}
+
+const fn chunk_words(chunk: &Chunk) -> u64 {
+    match chunk {
+        Chunk::Fill { words, .. } => *words,
+        Chunk::Literals(words) => words.len() as u64,
+    }
+}
+
+const fn fill_word(ones: bool) -> u64 {
+    if ones { u64::MAX } else { 0 }
+}
+
+/// Position within a bitmap's chunks; past the end it reads as an endless
+/// zero fill.
+struct Cursor<'a> {
+    chunks: &'a [Chunk],
+    /// Words already consumed from `chunks[0]`.
+    offset: u64,
+}
+
+impl<'a> Cursor<'a> {
+    const fn new(chunks: &'a [Chunk]) -> Self {
+        Self { chunks, offset: 0 }
+    }
+
+    const fn is_done(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The current fill and the words left in it, if at a fill.
+    fn fill(&self) -> Option<(bool, u64)> {
+        match self.chunks.first() {
+            Some(Chunk::Fill { ones, words }) => Some((*ones, words - self.offset)),
+            Some(Chunk::Literals(_)) => None,
+            None => Some((false, u64::MAX)),
+        }
+    }
+
+    fn word(&self) -> u64 {
+        match self.chunks.first() {
+            Some(Chunk::Fill { ones, .. }) => fill_word(*ones),
+            Some(Chunk::Literals(words)) => {
+                words[usize::try_from(self.offset).unwrap_or(usize::MAX)]
+            }
+            None => 0,
+        }
+    }
+
+    fn advance(&mut self, words: u64) {
+        if let Some(chunk) = self.chunks.first() {
+            self.offset += words;
+            if self.offset == chunk_words(chunk) {
+                self.chunks = &self.chunks[1..];
+                self.offset = 0;
+            }
+        }
+    }
+}
+
+/// Compresses raw bitsets with [`Bitmap`].
+///
+/// Input bytes are read as a bitset, bit `i` being bit `i % 8` of byte
+/// `i / 8`. Output layout: `[original length: varint]` followed by the
+/// encoded bitmap.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BitmapCodec;
+
+impl BitmapCodec {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Compressor for BitmapCodec {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, input.len() as u64);
+        output.extend_from_slice(&Bitmap::from_bitset(input).encode());
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitmap"
+    }
+}
+
+impl Decompressor for BitmapCodec {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut pos = 0;
+        let len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+        let (bitmap, consumed) = Bitmap::decode(&input[pos..])?;
+        if pos + consumed != input.len() || bitmap.word_len() > len.div_ceil(WORD_BYTES) as u64 {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let mut output = bitmap.to_bitset();
+        if output[len.min(output.len())..]
+            .iter()
+            .any(|&byte| byte != 0)
+        {
+            return Err(CompressionError::CorruptedData);
+        }
+        output.resize(len, 0);
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitmap"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(indices: &[u64], other: &[u64], keep: impl Fn(bool, bool) -> bool) -> Vec<u64> {
+        let max = indices.iter().chain(other).copied().max().unwrap_or(0);
+        (0..=max)
+            .filter(|i| keep(indices.contains(i), other.contains(i)))
+            .collect()
+    }
+
+    fn multiples(step: u64, limit: u64) -> Vec<u64> {
+        (0..limit).step_by(usize::try_from(step).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        let bitmap = Bitmap::new();
+        assert!(bitmap.is_empty());
+        assert_eq!(bitmap.count_ones(), 0);
+        assert!(!bitmap.contains(0));
+        assert_eq!(bitmap.encode(), [0]);
+        assert_eq!(Bitmap::from_bitset(&[0; 100]), bitmap);
+        assert_eq!(Bitmap::default(), bitmap);
+    }
+
+    #[test]
+    fn test_from_indices() {
+        let bitmap = Bitmap::from_indices([5, 1000, 3, 5, 64]);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), [3, 5, 64, 1000]);
+        assert_eq!(bitmap.count_ones(), 4);
+        assert!(bitmap.contains(1000));
+        assert!(!bitmap.contains(999));
+        assert!(!bitmap.contains(1 << 40));
+    }
+
+    #[test]
+    fn test_fills_and_literals() {
+        let bitmap = Bitmap::from_indices((0..640).chain([700, 5000]));
+        assert_eq!(
+            bitmap.chunks,
+            [
+                Chunk::Fill {
+                    ones: true,
+                    words: 10
+                },
+                Chunk::Literals(vec![1 << (700 - 640)]),
+                Chunk::Fill {
+                    ones: false,
+                    words: 67
+                },
+                Chunk::Literals(vec![1 << (5000 % 64)]),
+            ]
+        );
+        assert_eq!(bitmap.count_ones(), 642);
+        assert!(bitmap.contains(639));
+        assert!(!bitmap.contains(640));
+    }
+
+    #[test]
+    fn test_sparse_bitmap_is_small() {
+        let indices = multiples(100_000, 100_000_000);
+        let bitmap = Bitmap::from_indices(indices.iter().copied());
+        assert!(bitmap.encode().len() < indices.len() * 12);
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), indices);
+    }
+
+    #[test]
+    fn test_and_or_match_naive() {
+        let a = multiples(3, 2000);
+        let b: Vec<u64> = (500..1500).chain(multiples(7, 3000)).collect();
+        let left = Bitmap::from_indices(a.iter().copied());
+        let right = Bitmap::from_indices(b.iter().copied());
+
+        let and: Vec<u64> = left.and(&right).iter().collect();
+        assert_eq!(and, naive(&a, &b, |x, y| x && y));
+        let or: Vec<u64> = left.or(&right).iter().collect();
+        assert_eq!(or, naive(&a, &b, |x, y| x || y));
+        assert_eq!(right.and(&left), left.and(&right));
+    }
+
+    #[test]
+    fn test_and_or_on_fills() {
+        let low = Bitmap::from_indices(0..6400);
+        let high = Bitmap::from_indices(3200..12800);
+        let and = low.and(&high);
+        assert_eq!(
+            and.chunks,
+            [
+                Chunk::Fill {
+                    ones: false,
+                    words: 50
+                },
+                Chunk::Fill {
+                    ones: true,
+                    words: 50
+                }
+            ]
+        );
+        let or = low.or(&high);
+        assert_eq!(
+            or.chunks,
+            [Chunk::Fill {
+                ones: true,
+                words: 200
+            }]
+        );
+        assert!(low.and(&Bitmap::new()).is_empty());
+        assert_eq!(low.or(&Bitmap::new()), low);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let bitmap = Bitmap::from_indices((0..640).chain(multiples(13, 10_000)));
+        let mut encoded = bitmap.encode();
+        encoded.push(0xAA);
+        let (decoded, consumed) = Bitmap::decode(&encoded).unwrap();
+        assert_eq!(decoded, bitmap);
+        assert_eq!(consumed, encoded.len() - 1);
+    }
+
+    #[test]
+    fn test_decode_corrupted() {
+        // Empty fill, unknown kind, truncated literal.
+        assert!(Bitmap::decode(&[1, 0]).is_err());
+        assert!(Bitmap::decode(&[1, 0b111]).is_err());
+        assert!(Bitmap::decode(&[1, 1 << KIND_BITS | 2, 1, 2]).is_err());
+        assert!(Bitmap::decode(&[2, 1 << KIND_BITS]).is_err());
+    }
+
+    #[test]
+    fn test_bitset_roundtrip() {
+        let bits: Vec<u8> = (0..100u32)
+            .map(|i| (i * 37).to_le_bytes()[0] & 0x81)
+            .collect();
+        let bitmap = Bitmap::from_bitset(&bits);
+        let mut restored = bitmap.to_bitset();
+        restored.resize(bits.len(), 0);
+        assert_eq!(restored, bits);
+    }
+
+    #[test]
+    fn test_codec_roundtrip() {
+        let codec = BitmapCodec::new();
+        assert_eq!(Compressor::name(&codec), "Bitmap");
+        assert_eq!(Decompressor::name(&codec), "Bitmap");
+        for input in [vec![], vec![0; 13], vec![0xFF; 4096], vec![1, 2, 3], {
+            let mut sparse = vec![0u8; 100_000];
+            sparse[12_345] = 0x10;
+            sparse[99_999] = 0x80;
+            sparse
+        }] {
+            let compressed = codec.compress(&input).unwrap();
+            assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        }
+        assert_eq!(codec.compress(&[]).unwrap(), [0, 0]);
+        assert!(codec.compress(&[0xFF; 4096]).unwrap().len() < 8);
+    }
+
+    #[test]
+    fn test_codec_rejects_bits_past_length() {
+        let codec = BitmapCodec::new();
+        let mut compressed = vec![1];
+        compressed.extend_from_slice(&Bitmap::from_indices([9]).encode());
+        assert!(matches!(
+            codec.decompress(&compressed),
+            Err(CompressionError::CorruptedData)
+        ));
+        let mut compressed = vec![1];
+        compressed.extend_from_slice(&Bitmap::from_indices([64]).encode());
+        assert!(codec.decompress(&compressed).is_err());
+        assert!(codec.decompress(&[]).is_err());
+    }
+}
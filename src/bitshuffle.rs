@@ -0,0 +1,260 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, Transform};
+
+const DEFAULT_ELEMENT_SIZE: u8 = 4;
+
+/// Reversible bit-shuffle filter, in the style of Blosc's bitshuffle.
+///
+/// Treats the input as an array of `element_size`-byte elements and
+/// transposes it at bit granularity: bit plane `p` holds bit `p % 8` of byte
+/// `p / 8` of every element, packed MSB-first. Arrays of small integers then
+/// become long runs of zero planes, which compress far better than the byte
+/// planes of [`Shuffle`](crate::Shuffle). Trailing bytes that do not fill an
+/// element are copied unchanged. Output layout: `[element size: u8][bit
+/// planes][trailing bytes]`; run it before the main codec. As a
+/// [`Transform`] it writes the planes and trailing bytes alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitShuffle {
+    element_size: u8,
+}
+
+impl Default for BitShuffle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitShuffle {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_element_size(DEFAULT_ELEMENT_SIZE)
+    }
+
+    /// Creates a filter for `element_size`-byte elements.
+    #[must_use]
+    pub const fn with_element_size(element_size: u8) -> Self {
+        Self { element_size }
+    }
+
+    #[must_use]
+    pub const fn element_size(&self) -> u8 {
+        self.element_size
+    }
+
+    fn checked_element_size(self) -> Result<usize> {
+        if self.element_size == 0 {
+            return Err(CompressionError::InvalidInput(
+                "element size must be non-zero".to_string(),
+            ));
+        }
+        Ok(usize::from(self.element_size))
+    }
+}
+
+impl Transform for BitShuffle {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let size = self.checked_element_size()?;
+        let body = input.len() / size * size;
+
+        // Every element contributes one bit per plane, so the planes fill
+        // exactly `body` bytes and need no padding.
+        let mut writer = BitWriter::new();
+        for byte in 0..size {
+            for bit in 0..u8::BITS {
+                for element in input[..body].chunks_exact(size) {
+                    writer.write_bit((element[byte] >> bit) & 1 == 1);
+                }
+            }
+        }
+
+        let mut output = writer.finish();
+        output.extend_from_slice(&input[body..]);
+
+        Ok(output)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let size = self.checked_element_size()?;
+        let elements = input.len() / size;
+        let body = elements * size;
+
+        let mut output = vec![0u8; input.len()];
+        let mut reader = BitReader::new(&input[..body]);
+        for byte in 0..size {
+            for bit in 0..u8::BITS {
+                for element in 0..elements {
+                    if reader.read_bit().ok_or(CompressionError::CorruptedData)? {
+                        output[element * size + byte] |= 1 << bit;
+                    }
+                }
+            }
+        }
+        output[body..].copy_from_slice(&input[body..]);
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "BitShuffle"
+    }
+}
+
+impl Compressor for BitShuffle {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let planes = self.forward(input)?;
+        let mut output = Vec::with_capacity(planes.len() + 1);
+        output.push(self.element_size);
+        output.extend_from_slice(&planes);
+        Ok(output)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        input_len.saturating_add(1)
+    }
+
+    fn name(&self) -> &'static str {
+        "BitShuffle"
+    }
+}
+
+impl Decompressor for BitShuffle {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (&size, shuffled) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        if size == 0 {
+            return Err(CompressionError::InvalidHeader);
+        }
+        Self::with_element_size(size).inverse(shuffled)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match input.split_first() {
+            None => Err(CompressionError::CorruptedData),
+            Some((0, _)) => Err(CompressionError::InvalidHeader),
+            Some((_, body)) => Ok(Some(body.len() as u64)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "BitShuffle"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rle, Shuffle};
+
+    fn roundtrip(shuffle: BitShuffle, input: &[u8]) -> Vec<u8> {
+        let filtered = shuffle.compress(input).unwrap();
+        assert_eq!(filtered.len(), input.len() + 1);
+        assert_eq!(shuffle.decompress(&filtered).unwrap(), input);
+        filtered
+    }
+
+    #[test]
+    fn test_bitshuffle_new() {
+        let shuffle = BitShuffle::new();
+        assert_eq!(shuffle.element_size(), DEFAULT_ELEMENT_SIZE);
+        assert_eq!(BitShuffle::default(), shuffle);
+        assert_eq!(Compressor::name(&shuffle), "BitShuffle");
+        assert_eq!(Decompressor::name(&shuffle), "BitShuffle");
+    }
+
+    #[test]
+    fn test_empty() {
+        let shuffle = BitShuffle::new();
+        assert_eq!(roundtrip(shuffle, &[]), [DEFAULT_ELEMENT_SIZE]);
+        assert!(matches!(
+            shuffle.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output() {
+        // Eight one-byte elements: bit plane p collects bit p of each byte.
+        let input = [1, 0, 1, 0, 1, 0, 1, 0];
+        let filtered = roundtrip(BitShuffle::with_element_size(1), &input);
+        assert_eq!(filtered, [1, 0b1010_1010, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_transform_omits_header() {
+        let shuffle = BitShuffle::with_element_size(1);
+        let input = [1, 0, 1, 0, 1, 0, 1, 0, 7];
+        let planes = shuffle.forward(&input[..8]).unwrap();
+        assert_eq!(planes, [0b1010_1010, 0, 0, 0, 0, 0, 0, 0]);
+        let with_trailing = BitShuffle::with_element_size(2).forward(&input).unwrap();
+        assert_eq!(with_trailing[8], 7);
+        assert_eq!(
+            BitShuffle::with_element_size(2)
+                .inverse(&with_trailing)
+                .unwrap(),
+            input
+        );
+        assert_eq!(Transform::name(&shuffle), "BitShuffle");
+        assert!(matches!(
+            BitShuffle::with_element_size(0).forward(&input),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_planes_span_bytes() {
+        // Two u16 elements 0x0100 and 0x0001: byte 0 bit 0 is set in the
+        // second element only, byte 1 bit 0 in the first only.
+        let filtered = roundtrip(BitShuffle::with_element_size(2), &[0x00, 0x01, 0x01, 0x00]);
+        assert_eq!(filtered, [2, 0b0100_0000, 0b0000_0000, 0b1000_0000, 0]);
+    }
+
+    #[test]
+    fn test_trailing_partial_element() {
+        let filtered = roundtrip(
+            BitShuffle::with_element_size(4),
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+        );
+        assert_eq!(&filtered[9..], &[9, 10]);
+    }
+
+    #[test]
+    fn test_shorter_than_element() {
+        assert_eq!(
+            roundtrip(BitShuffle::with_element_size(8), &[1, 2, 3]),
+            [8, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_various_sizes() {
+        let input: Vec<u8> = (0..1001u32).map(|i| (i * 37).to_le_bytes()[0]).collect();
+        for size in [1, 2, 3, 4, 7, 8, 16, 255] {
+            roundtrip(BitShuffle::with_element_size(size), &input);
+        }
+    }
+
+    #[test]
+    fn test_all_bits_set() {
+        roundtrip(BitShuffle::with_element_size(3), &[0xFF; 48]);
+    }
+
+    #[test]
+    fn test_beats_byte_shuffle_on_small_integers() {
+        let input: Vec<u8> = (0..1024u32).flat_map(|i| (i % 8).to_le_bytes()).collect();
+        let bytes = Rle::new()
+            .compress(&Shuffle::new().compress(&input).unwrap())
+            .unwrap();
+        let bits = Rle::new()
+            .compress(&BitShuffle::new().compress(&input).unwrap())
+            .unwrap();
+        assert!(bits.len() * 4 < bytes.len());
+    }
+
+    #[test]
+    fn test_zero_element_size() {
+        let result = BitShuffle::with_element_size(0).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = BitShuffle::new().decompress(&[0, 1]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+}
@@ -0,0 +1,224 @@
+/// Writes individual bits MSB-first into a byte buffer.
+#[derive(Debug, Default, Clone)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl BitWriter {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            bit_len: 0,
+        }
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        let shift = self.bit_len % 8;
+        if shift == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= 0x80 >> shift;
+        }
+        self.bit_len += 1;
+    }
+
+    /// Writes the low `count` bits of `value`, most significant bit first.
+    pub fn write_bits(&mut self, value: u64, count: u32) {
+        debug_assert!(count <= 64);
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    /// Writes `value` as an order-0 exponential-Golomb code: `value + 1` in
+    /// binary, preceded by one zero bit per bit after its leading one.
+    /// `value` must be below `u64::MAX`.
+    pub fn write_exp_golomb(&mut self, value: u64) {
+        debug_assert!(value < u64::MAX);
+        let coded = value + 1;
+        let width = u64::BITS - coded.leading_zeros();
+        self.write_bits(0, width - 1);
+        self.write_bits(coded, width);
+    }
+
+    #[must_use]
+    pub const fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Returns the written bytes, zero-padding the final partial byte.
+    #[must_use]
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads individual bits MSB-first from a byte slice.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    #[must_use]
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn read_bit(&mut self) -> Option<bool> {
+        let byte = *self.data.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1 == 1;
+        self.position += 1;
+        Some(bit)
+    }
+
+    /// Reads `count` bits as an unsigned integer, most significant bit first.
+    pub fn read_bits(&mut self, count: u32) -> Option<u64> {
+        debug_assert!(count <= 64);
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = (value << 1) | u64::from(self.read_bit()?);
+        }
+        Some(value)
+    }
+
+    /// Reads an order-0 exponential-Golomb code written by
+    /// [`BitWriter::write_exp_golomb`].
+    pub fn read_exp_golomb(&mut self) -> Option<u64> {
+        let mut zeros = 0;
+        while !self.read_bit()? {
+            zeros += 1;
+            if zeros >= u64::BITS {
+                return None;
+            }
+        }
+        let coded = (1 << zeros) | self.read_bits(zeros)?;
+        Some(coded - 1)
+    }
+
+    /// Reads up to eight bits as a byte.
+    pub fn read_u8(&mut self, count: u32) -> Option<u8> {
+        debug_assert!(count <= 8);
+        self.read_bits(count)
+            .and_then(|value| u8::try_from(value).ok())
+    }
+
+    /// Number of bits consumed so far.
+    #[must_use]
+    pub const fn bits_read(&self) -> usize {
+        self.position
+    }
+
+    /// Number of whole bytes touched so far, counting a partial byte as consumed.
+    #[must_use]
+    pub const fn bytes_consumed(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bits_full_byte() {
+        let mut writer = BitWriter::new();
+        for bit in [true, false, true, false, true, false, true, false] {
+            writer.write_bit(bit);
+        }
+        assert_eq!(writer.finish(), vec![0b1010_1010]);
+    }
+
+    #[test]
+    fn test_write_bits_partial_byte() {
+        let mut writer = BitWriter::new();
+        writer.write_bits(0b111, 3);
+        assert_eq!(writer.bit_len(), 3);
+        assert_eq!(writer.finish(), vec![0b1110_0000]);
+    }
+
+    #[test]
+    fn test_write_bits_spanning_bytes() {
+        let mut writer = BitWriter::new();
+        writer.write_bits(0x1FF, 9);
+        assert_eq!(writer.finish(), vec![0xFF, 0x80]);
+    }
+
+    #[test]
+    fn test_read_bits() {
+        let mut reader = BitReader::new(&[0b1010_1010]);
+        assert_eq!(reader.read_bits(3), Some(0b101));
+        assert_eq!(reader.read_bits(5), Some(0b01010));
+        assert_eq!(reader.read_bit(), None);
+    }
+
+    #[test]
+    fn test_read_u8() {
+        let mut reader = BitReader::new(&[0xAB, 0xC0]);
+        assert_eq!(reader.read_u8(8), Some(0xAB));
+        assert_eq!(reader.read_u8(2), Some(0b11));
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let mut reader = BitReader::new(&[0xFF]);
+        assert_eq!(reader.read_bits(9), None);
+    }
+
+    #[test]
+    fn test_roundtrip_mixed_widths() {
+        let mut writer = BitWriter::new();
+        writer.write_bits(5, 3);
+        writer.write_bits(0, 1);
+        writer.write_bits(u64::MAX, 64);
+        writer.write_bits(0x2A, 7);
+        let bytes = writer.finish();
+
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_bits(3), Some(5));
+        assert_eq!(reader.read_bits(1), Some(0));
+        assert_eq!(reader.read_bits(64), Some(u64::MAX));
+        assert_eq!(reader.read_bits(7), Some(0x2A));
+        assert_eq!(reader.bits_read(), 75);
+        assert_eq!(reader.bytes_consumed(), 10);
+    }
+
+    #[test]
+    fn test_exp_golomb_known_codes() {
+        let mut writer = BitWriter::new();
+        for value in [0, 1, 2, 3, 6] {
+            writer.write_exp_golomb(value);
+        }
+        // 1 | 010 | 011 | 00100 | 00111
+        assert_eq!(writer.bit_len(), 17);
+        assert_eq!(writer.finish(), vec![0b1010_0110, 0b0100_0011, 0b1000_0000]);
+    }
+
+    #[test]
+    fn test_exp_golomb_roundtrip() {
+        let values = [0, 1, 254, 255, 1 << 20, u64::MAX - 1];
+        let mut writer = BitWriter::new();
+        for value in values {
+            writer.write_exp_golomb(value);
+        }
+        let bytes = writer.finish();
+        let mut reader = BitReader::new(&bytes);
+        for value in values {
+            assert_eq!(reader.read_exp_golomb(), Some(value));
+        }
+        assert_eq!(BitReader::new(&[0; 9]).read_exp_golomb(), None);
+        assert_eq!(BitReader::new(&[0b0000_0100]).read_exp_golomb(), None);
+    }
+
+    #[test]
+    fn test_empty_writer() {
+        let writer = BitWriter::new();
+        assert_eq!(writer.bit_len(), 0);
+        assert!(writer.finish().is_empty());
+    }
+}
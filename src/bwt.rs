@@ -0,0 +1,576 @@
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor, Transform};
+
+pub const HEADER_LEN: usize = 8;
+pub const PRIMARY_INDEX_LEN: usize = 4;
+/// Largest accepted block size.
+pub const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+const DEFAULT_BLOCK_SIZE: usize = 900 * 1024;
+/// Block size for each [`CompressionLevel`], fastest first; level 6 is
+/// [`Bwt::new`].
+const LEVELS: [usize; 9] = [
+    64 * 1024,
+    128 * 1024,
+    256 * 1024,
+    512 * 1024,
+    768 * 1024,
+    DEFAULT_BLOCK_SIZE,
+    2 * 1024 * 1024,
+    4 * 1024 * 1024,
+    8 * 1024 * 1024,
+];
+const EMPTY: u32 = u32::MAX;
+
+/// Burrows–Wheeler transform.
+///
+/// Permutes each block so that bytes followed by similar contexts end up
+/// next to each other, turning text and other repetitive data into long
+/// runs of few distinct bytes for RLE, move-to-front or an entropy coder to
+/// finish. The suffix array behind the transform is built with SA-IS in time
+/// and memory linear in the block size, so blocks of tens of megabytes are
+/// practical; larger blocks capture longer-range context at the cost of
+/// about 13 bytes of working memory per input byte. Like [`Delta`], this is
+/// a filter: the output is as long as the input plus headers. Output
+/// layout: `[original length: u32][block size: u32]`, then per block
+/// `[primary index: u32][transformed bytes]`. As a [`Transform`] it writes
+/// the blocks alone, and inverting needs the same block size.
+///
+/// [`Delta`]: crate::Delta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bwt {
+    block_size: usize,
+}
+
+impl Default for Bwt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bwt {
+    /// Creates a transform with 900 KiB blocks.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_block_size(DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a transform over `block_size` byte blocks, clamped to
+    /// `1..=MAX_BLOCK_SIZE`.
+    #[must_use]
+    pub const fn with_block_size(block_size: usize) -> Self {
+        let block_size = if block_size == 0 {
+            1
+        } else if block_size > MAX_BLOCK_SIZE {
+            MAX_BLOCK_SIZE
+        } else {
+            block_size
+        };
+        Self { block_size }
+    }
+
+    #[must_use]
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+/// Suffix array of `text`, whose last symbol must be a unique sentinel
+/// smaller than every other symbol in `0..alphabet`, built by SA-IS
+/// (Nong, Zhang and Chan, 2009).
+fn suffix_array(text: &[u32], alphabet: usize) -> Vec<u32> {
+    let n = text.len();
+    if n == 1 {
+        return vec![0];
+    }
+
+    // A suffix is S-type if it sorts before its successor, L-type otherwise.
+    let mut is_s = vec![false; n];
+    is_s[n - 1] = true;
+    for i in (0..n - 1).rev() {
+        is_s[i] = text[i] < text[i + 1] || (text[i] == text[i + 1] && is_s[i + 1]);
+    }
+    let is_lms = |i: usize| i > 0 && is_s[i] && !is_s[i - 1];
+
+    let mut bucket_sizes = vec![0u32; alphabet];
+    for &c in text {
+        bucket_sizes[c as usize] += 1;
+    }
+
+    // Sort LMS substrings by inducing from the LMS positions in text order.
+    let lms: Vec<u32> = (1..n).filter(|&i| is_lms(i)).map(to_u32).collect();
+    let sa = induce(text, &is_s, &bucket_sizes, &lms);
+
+    // Name each LMS substring by its rank among the distinct ones.
+    let mut names = vec![EMPTY; n];
+    let mut name = 0;
+    let mut previous: Option<usize> = None;
+    for &p in sa.iter().filter(|&&p| is_lms(p as usize)) {
+        let p = p as usize;
+        if let Some(q) = previous
+            && !lms_substrings_equal(text, &is_s, p, q)
+        {
+            name += 1;
+        }
+        names[p] = name;
+        previous = Some(p);
+    }
+    let reduced: Vec<u32> = lms.iter().map(|&p| names[p as usize]).collect();
+
+    // Order the LMS suffixes, recursing while their substrings repeat.
+    let distinct = name as usize + 1;
+    let order = if distinct == reduced.len() {
+        let mut order = vec![0; reduced.len()];
+        for (i, &name) in reduced.iter().enumerate() {
+            order[name as usize] = to_u32(i);
+        }
+        order
+    } else {
+        suffix_array(&reduced, distinct)
+    };
+    let sorted_lms: Vec<u32> = order.iter().map(|&i| lms[i as usize]).collect();
+    induce(text, &is_s, &bucket_sizes, &sorted_lms)
+}
+
+/// Places `lms` at the ends of their buckets, keeping their order, and
+/// induces the L-type then the S-type suffixes from them.
+fn induce(text: &[u32], is_s: &[bool], bucket_sizes: &[u32], lms: &[u32]) -> Vec<u32> {
+    let mut sa = vec![EMPTY; text.len()];
+    let mut tails = bucket_ends(bucket_sizes);
+    for &p in lms.iter().rev() {
+        let c = text[p as usize] as usize;
+        tails[c] -= 1;
+        sa[tails[c] as usize] = p;
+    }
+
+    let mut heads = bucket_ends(bucket_sizes);
+    for (head, &size) in heads.iter_mut().zip(bucket_sizes) {
+        *head -= size;
+    }
+    for i in 0..sa.len() {
+        let p = sa[i];
+        if p != EMPTY && p > 0 && !is_s[p as usize - 1] {
+            let c = text[p as usize - 1] as usize;
+            sa[heads[c] as usize] = p - 1;
+            heads[c] += 1;
+        }
+    }
+
+    let mut tails = bucket_ends(bucket_sizes);
+    for i in (0..sa.len()).rev() {
+        let p = sa[i];
+        if p != EMPTY && p > 0 && is_s[p as usize - 1] {
+            let c = text[p as usize - 1] as usize;
+            tails[c] -= 1;
+            sa[tails[c] as usize] = p - 1;
+        }
+    }
+    sa
+}
+
+fn bucket_ends(bucket_sizes: &[u32]) -> Vec<u32> {
+    bucket_sizes
+        .iter()
+        .scan(0, |end, &size| {
+            *end += size;
+            Some(*end)
+        })
+        .collect()
+}
+
+/// Whether the LMS substrings starting at `p` and `q`, each running to the
+/// next LMS position inclusive, match in symbols and types.
+fn lms_substrings_equal(text: &[u32], is_s: &[bool], p: usize, q: usize) -> bool {
+    let is_lms = |i: usize| i > 0 && is_s[i] && !is_s[i - 1];
+    // The sentinel ends every LMS substring, so neither side overruns.
+    let mut k = 0;
+    loop {
+        if text[p + k] != text[q + k] || is_s[p + k] != is_s[q + k] {
+            return false;
+        }
+        if k > 0 && (is_lms(p + k) || is_lms(q + k)) {
+            return is_lms(p + k) && is_lms(q + k);
+        }
+        k += 1;
+    }
+}
+
+fn to_u32(i: usize) -> u32 {
+    u32::try_from(i).expect("block size is bounded by MAX_BLOCK_SIZE")
+}
+
+/// Transforms one block, returning the primary index: the row of the
+/// sorted rotations whose preceding symbol is the end-of-block sentinel,
+/// which the output leaves out.
+fn forward(block: &[u8], output: &mut Vec<u8>) -> u32 {
+    let mut text: Vec<u32> = block.iter().map(|&b| u32::from(b) + 1).collect();
+    text.push(0);
+    let sa = suffix_array(&text, 257);
+    drop(text);
+
+    let mut primary = 0;
+    for (row, &p) in sa.iter().enumerate() {
+        if p == 0 {
+            primary = to_u32(row);
+        } else {
+            output.push(block[p as usize - 1]);
+        }
+    }
+    primary
+}
+
+/// Inverts [`forward`] for a block of `last.len()` bytes.
+fn inverse(last: &[u8], primary: usize) -> Result<Vec<u8>> {
+    if primary == 0 || primary > last.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    // Row `primary` holds the sentinel, which sorts before every byte.
+    let mut starts = [0usize; 256];
+    for &b in last {
+        starts[usize::from(b)] += 1;
+    }
+    let mut total = 1;
+    for start in &mut starts {
+        let count = *start;
+        *start = total;
+        total += count;
+    }
+
+    // next[row] is the row of the rotation one symbol earlier.
+    let mut next = vec![0u32; last.len() + 1];
+    for (i, &b) in last.iter().enumerate() {
+        let row = if i < primary { i } else { i + 1 };
+        next[row] = to_u32(starts[usize::from(b)]);
+        starts[usize::from(b)] += 1;
+    }
+
+    let mut output = vec![0; last.len()];
+    let mut row = 0;
+    for byte in output.iter_mut().rev() {
+        let index = if row < primary { row } else { row - 1 };
+        *byte = last[index];
+        row = next[row] as usize;
+    }
+    Ok(output)
+}
+
+/// Transforms `input` block by block onto `output`.
+fn forward_blocks(input: &[u8], block_size: usize, output: &mut Vec<u8>) {
+    for block in input.chunks(block_size) {
+        let at = output.len();
+        output.extend_from_slice(&[0; PRIMARY_INDEX_LEN]);
+        let primary = forward(block, output);
+        output[at..at + PRIMARY_INDEX_LEN].copy_from_slice(&primary.to_le_bytes());
+    }
+}
+
+/// Inverts blocks from the start of `input` until `original_len` bytes are
+/// restored, returning them and the number of input bytes read.
+fn inverse_blocks(
+    input: &[u8],
+    block_size: usize,
+    original_len: usize,
+) -> Result<(Vec<u8>, usize)> {
+    let mut output = Vec::with_capacity(original_len.min(input.len()));
+    let mut pos = 0;
+    while output.len() < original_len {
+        let primary = read_u32(input, pos)?;
+        pos += PRIMARY_INDEX_LEN;
+        let len = block_size.min(original_len - output.len());
+        let block = input
+            .get(pos..pos + len)
+            .ok_or(CompressionError::CorruptedData)?;
+        output.extend_from_slice(&inverse(block, primary)?);
+        pos += len;
+    }
+    Ok((output, pos))
+}
+
+fn read_u32(input: &[u8], pos: usize) -> Result<usize> {
+    input
+        .get(pos..pos + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        .ok_or(CompressionError::CorruptedData)
+}
+
+impl Compressor for Bwt {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let original_len = u32::try_from(input.len())
+            .map_err(|_| CompressionError::InvalidInput("input too large".to_string()))?;
+        let blocks = input.len().div_ceil(self.block_size);
+        let mut output = Vec::with_capacity(HEADER_LEN + blocks * PRIMARY_INDEX_LEN + input.len());
+        output.extend_from_slice(&original_len.to_le_bytes());
+        output.extend_from_slice(&to_u32(self.block_size).to_le_bytes());
+        forward_blocks(input, self.block_size, &mut output);
+        Ok(output)
+    }
+
+    fn with_level(self, level: CompressionLevel) -> Self {
+        Self::with_block_size(level.pick(&LEVELS))
+    }
+
+    fn name(&self) -> &'static str {
+        "BWT"
+    }
+}
+
+impl Decompressor for Bwt {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        read_u32(input, 0).map(|len| Some(len as u64))
+    }
+
+    fn name(&self) -> &'static str {
+        "BWT"
+    }
+}
+
+impl PartialDecompressor for Bwt {
+    /// Decodes with the block size recorded in the frame, whatever this
+    /// instance's own setting.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let original_len = read_u32(input, 0)?;
+        let block_size = read_u32(input, 4)?;
+        if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+            return Err(CompressionError::InvalidHeader);
+        }
+
+        let (output, read) = inverse_blocks(&input[HEADER_LEN..], block_size, original_len)?;
+        Ok((output, HEADER_LEN + read))
+    }
+}
+
+impl Transform for Bwt {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let blocks = input.len().div_ceil(self.block_size);
+        let mut output = Vec::with_capacity(blocks * PRIMARY_INDEX_LEN + input.len());
+        forward_blocks(input, self.block_size, &mut output);
+        Ok(output)
+    }
+
+    /// Every block but the last is full, so the input length alone gives
+    /// the original length.
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let stride = PRIMARY_INDEX_LEN + self.block_size;
+        let tail = input.len() % stride;
+        if tail != 0 && tail <= PRIMARY_INDEX_LEN {
+            return Err(CompressionError::CorruptedData);
+        }
+        let original_len =
+            input.len() / stride * self.block_size + tail.saturating_sub(PRIMARY_INDEX_LEN);
+        inverse_blocks(input, self.block_size, original_len).map(|(output, _)| output)
+    }
+
+    fn name(&self) -> &'static str {
+        "BWT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecompressionLimits, Rle};
+
+    fn roundtrip(bwt: Bwt, input: &[u8]) -> Vec<u8> {
+        let transformed = bwt.compress(input).unwrap();
+        assert_eq!(bwt.decompress(&transformed).unwrap(), input);
+        transformed
+    }
+
+    fn naive_suffix_array(text: &[u32]) -> Vec<u32> {
+        let mut sa: Vec<u32> = (0..to_u32(text.len())).collect();
+        sa.sort_by(|&a, &b| text[a as usize..].cmp(&text[b as usize..]));
+        sa
+    }
+
+    fn pseudo_random(len: usize, alphabet: u32, mut state: u32) -> Vec<u32> {
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state % alphabet + 1
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bwt_name() {
+        assert_eq!(Compressor::name(&Bwt::new()), "BWT");
+        assert_eq!(Decompressor::name(&Bwt::new()), "BWT");
+        assert_eq!(Bwt::default().block_size(), 900 * 1024);
+        assert_eq!(Bwt::with_block_size(0).block_size(), 1);
+        assert_eq!(
+            Bwt::with_block_size(usize::MAX).block_size(),
+            MAX_BLOCK_SIZE
+        );
+    }
+
+    #[test]
+    fn test_suffix_array_matches_naive_sort() {
+        for (seed, alphabet) in [(1, 2), (7, 3), (42, 4), (99, 26), (1234, 256)] {
+            for len in [1, 2, 3, 10, 100, 1000] {
+                let mut text = pseudo_random(len, alphabet, seed);
+                text.push(0);
+                assert_eq!(
+                    suffix_array(&text, 257),
+                    naive_suffix_array(&text),
+                    "{seed} {alphabet} {len}"
+                );
+            }
+        }
+        let mut periodic: Vec<u32> = [1, 2, 1, 1, 2].repeat(200);
+        periodic.push(0);
+        assert_eq!(suffix_array(&periodic, 3), naive_suffix_array(&periodic));
+    }
+
+    #[test]
+    fn test_known_transform() {
+        // banana$ sorts as $banana, a$banan, ana$ban, anana$b, banana$,
+        // na$bana, nana$ba; the sentinel precedes row 4.
+        let transformed = Bwt::new().compress(b"banana").unwrap();
+        assert_eq!(transformed[..4], 6u32.to_le_bytes());
+        assert_eq!(transformed[8..12], 4u32.to_le_bytes());
+        assert_eq!(&transformed[12..], b"annbaa");
+    }
+
+    #[test]
+    fn test_transform_omits_header() {
+        let bwt = Bwt::with_block_size(4);
+        let transformed = bwt.forward(b"banana").unwrap();
+        assert_eq!(
+            transformed,
+            Bwt::with_block_size(4).compress(b"banana").unwrap()[8..]
+        );
+        assert_eq!(transformed.len(), 6 + 2 * PRIMARY_INDEX_LEN);
+        assert_eq!(bwt.inverse(&transformed).unwrap(), b"banana");
+        assert_eq!(bwt.inverse(&bwt.forward(b"").unwrap()).unwrap(), b"");
+        assert_eq!(Transform::name(&bwt), "BWT");
+
+        let text = b"full blocks and a short one".repeat(7);
+        assert_eq!(bwt.inverse(&bwt.forward(&text).unwrap()).unwrap(), text);
+        // A last block holding only its primary index.
+        assert_eq!(
+            bwt.inverse(&transformed[..8 + PRIMARY_INDEX_LEN]),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bwt = Bwt::new();
+        assert_eq!(roundtrip(bwt, b"").len(), HEADER_LEN);
+        roundtrip(bwt, b"a");
+        roundtrip(bwt, b"aaaaaaaaaa");
+        roundtrip(bwt, b"abracadabra");
+        roundtrip(bwt, &(0..=255).collect::<Vec<u8>>());
+        roundtrip(bwt, &b"the cat sat on the mat. ".repeat(100));
+    }
+
+    #[test]
+    fn test_small_blocks() {
+        let input = b"mississippi river banks ".repeat(50);
+        let bwt = Bwt::with_block_size(100);
+        let transformed = roundtrip(bwt, &input);
+        let blocks = input.len().div_ceil(100);
+        assert_eq!(
+            transformed.len(),
+            HEADER_LEN + blocks * PRIMARY_INDEX_LEN + input.len()
+        );
+        // The block size travels in the frame.
+        assert_eq!(Bwt::new().decompress(&transformed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_groups_contexts_into_runs() {
+        let input = b"she sells sea shells by the sea shore. ".repeat(50);
+        let plain = Rle::escaped().compress(&input).unwrap();
+        let transformed = roundtrip(Bwt::new(), &input);
+        let runs = Rle::escaped().compress(&transformed).unwrap();
+        assert!(
+            runs.len() * 4 < plain.len(),
+            "{} vs {}",
+            runs.len(),
+            plain.len()
+        );
+    }
+
+    #[test]
+    fn test_large_block_is_linear() {
+        let text: Vec<u8> = pseudo_random(1 << 20, 4, 5)
+            .into_iter()
+            .map(|c| b"ACGT"[c as usize - 1])
+            .collect();
+        roundtrip(Bwt::with_block_size(1 << 20), &text);
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let bwt = Bwt::with_block_size(16);
+        let stream = [
+            bwt.compress(b"first frame, two blocks").unwrap(),
+            bwt.compress(b"").unwrap(),
+            bwt.compress(b"tail").unwrap(),
+        ]
+        .concat();
+        let frames = bwt.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [
+                b"first frame, two blocks".to_vec(),
+                Vec::new(),
+                b"tail".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let bwt = Bwt::new();
+        assert!(bwt.decompress(&[]).is_err());
+        assert!(bwt.decompress(&[1, 0, 0, 0]).is_err());
+        assert_eq!(
+            bwt.decompress(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, b'a']),
+            Err(CompressionError::InvalidHeader)
+        );
+        let transformed = bwt.compress(b"abracadabra").unwrap();
+        assert!(
+            bwt.decompress(&transformed[..transformed.len() - 1])
+                .is_err()
+        );
+        let mut trailing = transformed.clone();
+        trailing.push(0);
+        assert!(bwt.decompress(&trailing).is_err());
+        for primary in [0, 12] {
+            let mut bad = transformed.clone();
+            bad[8..12].copy_from_slice(&u32::to_le_bytes(primary));
+            assert_eq!(bwt.decompress(&bad), Err(CompressionError::CorruptedData));
+        }
+    }
+
+    #[test]
+    fn test_limits_refuse_bombs() {
+        let bwt = Bwt::new();
+        let transformed = bwt.compress(b"abracadabra").unwrap();
+        assert_eq!(bwt.decompressed_len(&transformed).unwrap(), Some(11));
+        assert!(bwt.decompressed_len(&[1, 0]).is_err());
+
+        // A header claiming 4 GiB ahead of a few bytes.
+        let mut bomb = u32::MAX.to_le_bytes().to_vec();
+        bomb.extend_from_slice(&1024u32.to_le_bytes());
+        bomb.extend_from_slice(&[0; 16]);
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        assert!(matches!(
+            bwt.decompress_with_limits(&bomb, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+}
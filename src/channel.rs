@@ -0,0 +1,319 @@
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use crate::error::{CompressionError, Result};
+use crate::limits::DecompressionLimits;
+use crate::traits::Codec;
+
+/// Frame flag for a message sent as is.
+pub const FRAME_RAW: u8 = 0;
+/// Frame flag for a message compressed with the channel's codec.
+pub const FRAME_COMPRESSED: u8 = 1;
+
+/// A value that can be sent through a [`compressed_channel`] by converting
+/// it to and from bytes.
+///
+/// With the `serde` feature, `SerdeMessage` adapts any type that
+/// implements serde's `Serialize` and `DeserializeOwned`.
+pub trait Message: Sized {
+    /// Serializes the message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the message cannot be
+    /// serialized.
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+
+    /// Rebuilds a message from the bytes [`Message::to_bytes`] produced.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if `bytes` do not describe
+    /// a valid message.
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self>;
+}
+
+impl Message for Vec<u8> {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.clone())
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Ok(bytes)
+    }
+}
+
+impl Message for String {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.as_bytes().to_vec())
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_utf8(bytes).map_err(|_| CompressionError::CorruptedData)
+    }
+}
+
+/// Sending half of a [`compressed_channel`]. Clone it to send from several
+/// threads.
+#[derive(Debug)]
+pub struct CompressedSender<T, C> {
+    sender: Sender<Vec<u8>>,
+    codec: Arc<C>,
+    threshold: usize,
+    marker: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T, C> Clone for CompressedSender<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            codec: Arc::clone(&self.codec),
+            threshold: self.threshold,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: Message, C: Codec> CompressedSender<T, C> {
+    /// Serializes `message`, compresses it if it is at least the channel's
+    /// threshold and compression shrinks it, and sends it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the receiver has been dropped, or
+    /// any error from serialization or compression.
+    pub fn send(&self, message: &T) -> Result<()> {
+        let bytes = message.to_bytes()?;
+        let compressed = if bytes.len() >= self.threshold {
+            Some(self.codec.compress(&bytes)?).filter(|packed| packed.len() < bytes.len())
+        } else {
+            None
+        };
+        let (flag, payload) =
+            compressed.map_or((FRAME_RAW, bytes), |packed| (FRAME_COMPRESSED, packed));
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(flag);
+        frame.extend_from_slice(&payload);
+        self.sender
+            .send(frame)
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "receiver disconnected").into())
+    }
+
+    /// Smallest serialized size that is compressed before sending.
+    #[must_use]
+    pub const fn threshold(&self) -> usize {
+        self.threshold
+    }
+}
+
+/// Receiving half of a [`compressed_channel`].
+#[derive(Debug)]
+pub struct CompressedReceiver<T, C> {
+    receiver: Receiver<Vec<u8>>,
+    codec: Arc<C>,
+    limits: DecompressionLimits,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T, C> CompressedReceiver<T, C> {
+    /// Bounds what each compressed message may decompress to, for senders
+    /// that pass on data from an untrusted source. Unlimited by default.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        &self.limits
+    }
+}
+
+/// Error returned once every sender has been dropped.
+fn disconnected() -> CompressionError {
+    io::Error::new(ErrorKind::BrokenPipe, "all senders disconnected").into()
+}
+
+impl<T: Message, C: Codec> CompressedReceiver<T, C> {
+    /// Blocks until a message arrives and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` once every sender has been dropped and
+    /// the channel is empty, `CompressionError::LimitExceeded` if a message
+    /// would decompress past the limits, or any error from decompression or
+    /// [`Message::from_bytes`].
+    pub fn recv(&self) -> Result<T> {
+        let frame = self.receiver.recv().map_err(|_| disconnected())?;
+        self.decode(&frame)
+    }
+
+    /// Returns the next message if one is waiting, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// As [`recv`](Self::recv).
+    pub fn try_recv(&self) -> Result<Option<T>> {
+        match self.receiver.try_recv() {
+            Ok(frame) => self.decode(&frame).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(disconnected()),
+        }
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<T> {
+        let (&flag, payload) = frame.split_first().ok_or(CompressionError::CorruptedData)?;
+        let bytes = match flag {
+            FRAME_RAW => payload.to_vec(),
+            FRAME_COMPRESSED => self.codec.decompress_with_limits(payload, &self.limits)?,
+            _ => return Err(CompressionError::CorruptedData),
+        };
+        T::from_bytes(bytes)
+    }
+}
+
+/// Creates a channel whose messages are compressed with `codec` in transit
+/// once their serialized form reaches `threshold` bytes.
+///
+/// Built on [`std::sync::mpsc`], for pipelines that move large payloads
+/// between threads and want to bound the memory held by queued messages.
+/// Small messages, and those the codec cannot shrink, travel uncompressed,
+/// so latency-sensitive chatter pays nothing. The codec is shared by both
+/// halves. Each message is queued as a frame laid out as `[flag:
+/// u8][payload]`, where the flag is `FRAME_RAW` or `FRAME_COMPRESSED`.
+#[must_use]
+pub fn compressed_channel<T: Message, C: Codec>(
+    codec: C,
+    threshold: usize,
+) -> (CompressedSender<T, C>, CompressedReceiver<T, C>) {
+    let (sender, receiver) = mpsc::channel();
+    let codec = Arc::new(codec);
+    (
+        CompressedSender {
+            sender,
+            codec: Arc::clone(&codec),
+            threshold,
+            marker: std::marker::PhantomData,
+        },
+        CompressedReceiver {
+            receiver,
+            codec,
+            limits: DecompressionLimits::new(),
+            marker: std::marker::PhantomData,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lz4, Rle};
+
+    #[test]
+    fn test_send_recv_small_and_large() {
+        let (sender, receiver) = compressed_channel::<Vec<u8>, _>(Lz4::new(), 64);
+        assert_eq!(sender.threshold(), 64);
+        let small = b"ping".to_vec();
+        let large = b"payload ".repeat(500);
+        sender.send(&small).unwrap();
+        sender.send(&large).unwrap();
+        assert_eq!(receiver.recv().unwrap(), small);
+        assert_eq!(receiver.recv().unwrap(), large);
+        assert_eq!(receiver.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn test_frames_compress_only_above_threshold() {
+        let (sender, receiver) = compressed_channel::<Vec<u8>, _>(Lz4::new(), 64);
+        sender.send(&vec![7; 63]).unwrap();
+        sender.send(&vec![7; 4096]).unwrap();
+        // Noise that does not shrink goes raw even above the threshold.
+        let noise: Vec<u8> = (0..=255).collect();
+        sender.send(&noise).unwrap();
+        let frames: Vec<Vec<u8>> = receiver.receiver.try_iter().collect();
+        assert_eq!(frames[0][0], FRAME_RAW);
+        assert_eq!(frames[0].len(), 64);
+        assert_eq!(frames[1][0], FRAME_COMPRESSED);
+        assert!(frames[1].len() < 100, "{}", frames[1].len());
+        assert_eq!(frames[2][0], FRAME_RAW);
+    }
+
+    #[test]
+    fn test_pipeline_across_threads() {
+        let (sender, receiver) = compressed_channel::<String, _>(Rle::new(), 16);
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..10 {
+                        let line = format!("{worker}:{i}:{}", "=".repeat(i * 10));
+                        sender.send(&line).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let mut lines = Vec::new();
+        while let Ok(line) = receiver.recv() {
+            lines.push(line);
+        }
+        assert_eq!(lines.len(), 40);
+        assert!(lines.contains(&format!("3:9:{}", "=".repeat(90))));
+    }
+
+    #[test]
+    fn test_disconnected() {
+        let (sender, receiver) = compressed_channel::<Vec<u8>, _>(Lz4::new(), 0);
+        drop(receiver);
+        assert!(matches!(
+            sender.send(&Vec::new()),
+            Err(CompressionError::Io { .. })
+        ));
+
+        let (sender, receiver) = compressed_channel::<Vec<u8>, _>(Lz4::new(), 0);
+        drop(sender);
+        assert!(matches!(receiver.recv(), Err(CompressionError::Io { .. })));
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(CompressionError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let (sender, receiver) = compressed_channel::<Vec<u8>, _>(Lz4::new(), 64);
+        let limits = DecompressionLimits::new().with_max_output_size(4096);
+        let receiver = receiver.with_limits(limits);
+        assert_eq!(receiver.limits(), &limits);
+        sender.send(&vec![7; 4096]).unwrap();
+        sender.send(&vec![7; 4097]).unwrap();
+        // Raw messages are not decompressed, so the limits pass them.
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..8192)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+        sender.send(&noise).unwrap();
+        assert_eq!(receiver.recv().unwrap().len(), 4096);
+        assert!(matches!(
+            receiver.recv(),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert_eq!(receiver.recv().unwrap(), noise);
+    }
+
+    #[test]
+    fn test_invalid_string() {
+        assert!(String::from_bytes(vec![0xFF, 0xFE]).is_err());
+        assert_eq!(String::from_bytes(b"ok".to_vec()).unwrap(), "ok");
+    }
+}
@@ -0,0 +1,260 @@
+/// An integrity check over uncompressed data.
+///
+/// Implemented by [`ChecksumKind`] for the built-in algorithms, which are
+/// the ones a [container](crate::container) frame can record; other
+/// implementations suit checks a caller stores alongside its own data.
+pub trait Checksum {
+    /// Number of meaningful low-order bytes in a digest, at most 8.
+    fn digest_len(&self) -> usize;
+
+    /// The check value of `data`.
+    fn digest(&self, data: &[u8]) -> u64;
+
+    fn name(&self) -> &'static str;
+}
+
+/// A built-in checksum, chosen per frame.
+///
+/// CRC-32 is what zlib, gzip and PNG use; Adler-32 is cheaper but weak on
+/// short inputs; xxHash64 is the fastest on large inputs and has the widest
+/// digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumKind {
+    Crc32,
+    Adler32,
+    XxHash64,
+}
+
+impl ChecksumKind {
+    /// Every built-in checksum, in tag order.
+    pub const ALL: [Self; 3] = [Self::Crc32, Self::Adler32, Self::XxHash64];
+
+    /// The value identifying this checksum in a frame header; never 0, which
+    /// means no checksum.
+    #[must_use]
+    pub const fn tag(self) -> u8 {
+        match self {
+            Self::Crc32 => 1,
+            Self::Adler32 => 2,
+            Self::XxHash64 => 3,
+        }
+    }
+
+    #[must_use]
+    pub const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::Crc32),
+            2 => Some(Self::Adler32),
+            3 => Some(Self::XxHash64),
+            _ => None,
+        }
+    }
+}
+
+impl Checksum for ChecksumKind {
+    fn digest_len(&self) -> usize {
+        match self {
+            Self::Crc32 | Self::Adler32 => 4,
+            Self::XxHash64 => 8,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> u64 {
+        match self {
+            Self::Crc32 => u64::from(crc32(data)),
+            Self::Adler32 => u64::from(adler32(data)),
+            Self::XxHash64 => xxhash64(data, 0),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "CRC-32",
+            Self::Adler32 => "Adler-32",
+            Self::XxHash64 => "xxHash64",
+        }
+    }
+}
+
+/// Lookup table for the reflected IEEE polynomial, one entry per byte.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i: u32 = 0;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 of `data` as used by zlib, gzip and PNG (IEEE 802.3 polynomial,
+/// reflected, initial value and final XOR `0xFFFFFFFF`).
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+const ADLER_MOD: u32 = 65521;
+/// Most bytes summed before the sums must be reduced to stay in a `u32`.
+const ADLER_NMAX: usize = 5552;
+
+/// Adler-32 of `data`, as in the zlib format.
+#[must_use]
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for block in data.chunks(ADLER_NMAX) {
+        for &byte in block {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= ADLER_MOD;
+        b %= ADLER_MOD;
+    }
+    (b << 16) | a
+}
+
+const XXH_PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const XXH_PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const XXH_PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const XXH_PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const XXH_PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+const fn xxh_round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(XXH_PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(XXH_PRIME_1)
+}
+
+const fn xxh_merge(acc: u64, lane: u64) -> u64 {
+    (acc ^ xxh_round(0, lane))
+        .wrapping_mul(XXH_PRIME_1)
+        .wrapping_add(XXH_PRIME_4)
+}
+
+fn read_u64_le(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+/// 64-bit xxHash (XXH64) of `data` with `seed`.
+#[must_use]
+pub fn xxhash64(data: &[u8], seed: u64) -> u64 {
+    let stripes = data.chunks_exact(32);
+    let tail = stripes.remainder();
+    let mut hash = if data.len() >= 32 {
+        let mut acc = [
+            seed.wrapping_add(XXH_PRIME_1).wrapping_add(XXH_PRIME_2),
+            seed.wrapping_add(XXH_PRIME_2),
+            seed,
+            seed.wrapping_sub(XXH_PRIME_1),
+        ];
+        for stripe in stripes {
+            for (lane, acc) in stripe.chunks_exact(8).zip(&mut acc) {
+                *acc = xxh_round(*acc, read_u64_le(lane));
+            }
+        }
+        let hash = acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18));
+        acc.into_iter().fold(hash, xxh_merge)
+    } else {
+        seed.wrapping_add(XXH_PRIME_5)
+    };
+    hash = hash.wrapping_add(data.len() as u64);
+
+    let mut words = tail.chunks_exact(8);
+    for word in &mut words {
+        hash = (hash ^ xxh_round(0, read_u64_le(word)))
+            .rotate_left(27)
+            .wrapping_mul(XXH_PRIME_1)
+            .wrapping_add(XXH_PRIME_4);
+    }
+    let mut rest = words.remainder();
+    if let Some((word, after)) = rest.split_first_chunk::<4>() {
+        hash = (hash ^ u64::from(u32::from_le_bytes(*word)).wrapping_mul(XXH_PRIME_1))
+            .rotate_left(23)
+            .wrapping_mul(XXH_PRIME_2)
+            .wrapping_add(XXH_PRIME_3);
+        rest = after;
+    }
+    for &byte in rest {
+        hash = (hash ^ u64::from(byte).wrapping_mul(XXH_PRIME_5))
+            .rotate_left(11)
+            .wrapping_mul(XXH_PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(XXH_PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(XXH_PRIME_3);
+    hash ^ (hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOX: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn test_crc32_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(FOX), 0x414F_A339);
+    }
+
+    #[test]
+    fn test_adler32_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(b"123456789"), 0x091E_01DE);
+        // Long enough to need reducing mid-way.
+        let bytes = vec![0xFF; 100_000];
+        let (mut a, mut b) = (1u64, 0u64);
+        for &byte in &bytes {
+            a = (a + u64::from(byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(u64::from(adler32(&bytes)), (b << 16) | a);
+    }
+
+    #[test]
+    fn test_xxhash64_known_values() {
+        assert_eq!(xxhash64(b"", 0), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxhash64(b"a", 0), 0xD24E_C4F1_A98C_6E5B);
+        assert_eq!(xxhash64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+        assert_eq!(xxhash64(FOX, 0), 0x0B24_2D36_1FDA_71BC);
+    }
+
+    #[test]
+    fn test_kinds() {
+        for kind in ChecksumKind::ALL {
+            assert_eq!(ChecksumKind::from_tag(kind.tag()), Some(kind));
+            assert!(kind.digest_len() <= 8);
+            let digest = kind.digest(FOX);
+            assert!(digest <= u64::MAX >> (64 - 8 * kind.digest_len()));
+            assert_ne!(
+                digest,
+                kind.digest(b"The quick brown fox jumps over the lazy cat")
+            );
+        }
+        assert_eq!(ChecksumKind::from_tag(0), None);
+        assert_eq!(ChecksumKind::Crc32.digest(FOX), u64::from(crc32(FOX)));
+        assert_eq!(ChecksumKind::XxHash64.name(), "xxHash64");
+    }
+}
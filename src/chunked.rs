@@ -0,0 +1,665 @@
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::limits::DecompressionLimits;
+use crate::progress::{Progress, Tracker};
+use crate::traits::{Codec, Compressor, Decompressor, PartialDecompressor};
+
+/// Largest input [`Chunked::new`] compresses in one piece.
+pub const DEFAULT_THRESHOLD: usize = 4 * 1024 * 1024;
+/// Chunk size [`Chunked::new`] splits larger inputs into.
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+pub const MODE_SINGLE: u8 = 0;
+pub const MODE_CHUNKED: u8 = 1;
+pub const MODE_CHAINED: u8 = 2;
+
+/// Wraps a codec so that large inputs are processed in independent chunks.
+///
+/// Inputs up to `threshold` bytes are compressed in one piece. Larger
+/// inputs are split into `chunk_size` pieces, so the wrapped codec's
+/// working buffers (token lists, bit buffers) never grow beyond one chunk.
+/// In chained mode each chunk is compressed with the preceding input as
+/// history (see [`Compressor::compress_with_history`]), so LZ77 matches can
+/// reach across chunk boundaries at the cost of decoding chunks in order.
+/// Output layout: `[mode: u8]` followed by either the single payload or
+/// `[chunks: u32]` and `[length: u32][payload]` per chunk.
+#[derive(Debug, Clone)]
+pub struct Chunked<C: Codec> {
+    codec: C,
+    threshold: usize,
+    chunk_size: usize,
+    chained: bool,
+}
+
+impl<C: Codec> Chunked<C> {
+    pub const fn new(codec: C) -> Self {
+        Self::with_threshold(codec, DEFAULT_THRESHOLD, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates a wrapper that switches to `chunk_size` chunks for inputs
+    /// larger than `threshold` bytes.
+    pub const fn with_threshold(codec: C, threshold: usize, chunk_size: usize) -> Self {
+        Self {
+            codec,
+            threshold,
+            chunk_size,
+            chained: false,
+        }
+    }
+
+    /// Carries each chunk's history over into the next, trading independent
+    /// chunks for ratio. The wrapped codec must support
+    /// [`Compressor::compress_with_history`].
+    #[must_use]
+    pub const fn with_chained(mut self, chained: bool) -> Self {
+        self.chained = chained;
+        self
+    }
+
+    #[must_use]
+    pub const fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    #[must_use]
+    pub const fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    #[must_use]
+    pub const fn chained(&self) -> bool {
+        self.chained
+    }
+
+    pub const fn inner(&self) -> &C {
+        &self.codec
+    }
+
+    /// Compresses `input` like [`Compressor::compress`], calling `on_progress`
+    /// after each chunk with the entries and input bytes done so far and an
+    /// estimate of the time remaining.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the chunk size is zero or
+    /// chaining is enabled for a codec without history support, or any
+    /// error from the wrapped codec.
+    pub fn compress_with_progress(
+        &self,
+        input: &[u8],
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Result<Vec<u8>> {
+        if input.len() <= self.threshold {
+            let mut tracker = Tracker::new(1, input.len() as u64);
+            let mut output = vec![MODE_SINGLE];
+            output.extend_from_slice(&self.codec.compress(input)?);
+            on_progress(&tracker.advance(input.len() as u64));
+            return Ok(output);
+        }
+
+        if self.chunk_size == 0 {
+            return Err(CompressionError::InvalidInput(
+                "chunk size must be non-zero".to_string(),
+            ));
+        }
+
+        let chunks = input.len().div_ceil(self.chunk_size);
+        let count = u32::try_from(chunks)
+            .map_err(|_| CompressionError::InvalidInput("too many chunks".to_string()))?;
+        let mut tracker = Tracker::new(chunks, input.len() as u64);
+        let mut output = vec![if self.chained {
+            MODE_CHAINED
+        } else {
+            MODE_CHUNKED
+        }];
+        output.extend_from_slice(&count.to_le_bytes());
+
+        for (index, chunk) in input.chunks(self.chunk_size).enumerate() {
+            let compressed = if self.chained {
+                let history = &input[..index * self.chunk_size];
+                self.codec.compress_with_history(history, chunk)?
+            } else {
+                self.codec.compress(chunk)?
+            };
+            let len = u32::try_from(compressed.len())
+                .map_err(|_| CompressionError::InvalidInput("chunk too large".to_string()))?;
+            output.extend_from_slice(&len.to_le_bytes());
+            output.extend_from_slice(&compressed);
+            on_progress(&tracker.advance(chunk.len() as u64));
+        }
+
+        Ok(output)
+    }
+
+    /// Decompresses `input` like [`Decompressor::decompress`], calling
+    /// `on_progress` after each chunk. Byte counts refer to the compressed
+    /// input consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidHeader` for an unknown mode,
+    /// `CompressionError::CorruptedData` if the chunk framing is malformed,
+    /// or any error from the wrapped codec.
+    pub fn decompress_with_progress(
+        &self,
+        input: &[u8],
+        on_progress: impl FnMut(&Progress),
+    ) -> Result<Vec<u8>> {
+        self.decompress_limited(input, &DecompressionLimits::new(), on_progress)
+    }
+
+    /// Decodes `input` within what `limits` allow from all of it: each
+    /// chunk decodes under the output the chunks before it left over, and
+    /// a chained chunk's recorded length is checked before it is decoded.
+    fn decompress_limited(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Result<Vec<u8>> {
+        let Some(&mode) = input.first() else {
+            return Err(CompressionError::CorruptedData);
+        };
+        let budget = limits.max_output_for(input.len() as u64);
+
+        match mode {
+            MODE_SINGLE => {
+                let mut tracker = Tracker::new(1, input.len() as u64);
+                let output = self.codec.decompress_with_limits(
+                    &input[1..],
+                    &DecompressionLimits::new().with_max_output_size(budget),
+                )?;
+                on_progress(&tracker.advance(input.len() as u64));
+                Ok(output)
+            }
+            MODE_CHUNKED | MODE_CHAINED => {
+                let count = read_u32(input, 1)?;
+                let mut tracker = Tracker::new(count as usize, input.len() as u64);
+                let mut pos = 5;
+                let mut output = Vec::new();
+                for _ in 0..count {
+                    let start = pos;
+                    let len = read_u32(input, pos)? as usize;
+                    pos += 4;
+                    let chunk = input
+                        .get(pos..pos + len)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    let decompressed = if mode == MODE_CHAINED {
+                        if let Some(chunk_len) = self.codec.decompressed_len(chunk)? {
+                            let total = (output.len() as u64).saturating_add(chunk_len);
+                            limits.check(input.len() as u64, total)?;
+                        }
+                        self.codec.decompress_with_history(&output, chunk)?
+                    } else {
+                        let left = budget.saturating_sub(output.len() as u64);
+                        self.codec.decompress_with_limits(
+                            chunk,
+                            &DecompressionLimits::new().with_max_output_size(left),
+                        )?
+                    };
+                    output.extend_from_slice(&decompressed);
+                    limits.check(input.len() as u64, output.len() as u64)?;
+                    pos += len;
+                    // The mode byte and chunk count are folded into the first entry.
+                    let consumed = if start == 5 { pos } else { pos - start };
+                    on_progress(&tracker.advance(consumed as u64));
+                }
+                if pos != input.len() {
+                    return Err(CompressionError::CorruptedData);
+                }
+                Ok(output)
+            }
+            _ => Err(CompressionError::InvalidHeader),
+        }
+    }
+}
+
+/// Payloads of the chunks in a [`MODE_CHUNKED`] or [`MODE_CHAINED`]
+/// frame, read in order from its chunk table.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidHeader` for any other mode and
+/// `CompressionError::CorruptedData` if the chunk framing is malformed.
+pub fn chunk_payloads(input: &[u8]) -> Result<Vec<&[u8]>> {
+    match input.first() {
+        Some(&(MODE_CHUNKED | MODE_CHAINED)) => {}
+        Some(_) => return Err(CompressionError::InvalidHeader),
+        None => return Err(CompressionError::CorruptedData),
+    }
+    let count = read_u32(input, 1)?;
+    let mut pos = 5;
+    let mut chunks = Vec::new();
+    for _ in 0..count {
+        let len = read_u32(input, pos)? as usize;
+        pos += 4;
+        let chunk = input
+            .get(pos..pos + len)
+            .ok_or(CompressionError::CorruptedData)?;
+        chunks.push(chunk);
+        pos += len;
+    }
+    if pos != input.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(chunks)
+}
+
+fn read_u32(input: &[u8], pos: usize) -> Result<u32> {
+    input
+        .get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(CompressionError::CorruptedData)
+}
+
+impl<C: Codec> Compressor for Chunked<C> {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.compress_with_progress(input, |_| {})
+    }
+
+    /// Assumes the wrapped codec's bound also covers chunks compressed
+    /// with history.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        if input_len <= self.threshold || self.chunk_size == 0 {
+            return self.codec.max_compressed_len(input_len).saturating_add(1);
+        }
+        let full = input_len / self.chunk_size;
+        let rest = input_len % self.chunk_size;
+        let chunk = self.codec.max_compressed_len(self.chunk_size).saturating_add(4);
+        let last = if rest == 0 {
+            0
+        } else {
+            self.codec.max_compressed_len(rest).saturating_add(4)
+        };
+        chunk.saturating_mul(full).saturating_add(last).saturating_add(5)
+    }
+
+    /// Passes the level on to the wrapped codec; chunking is unaffected.
+    fn with_level(mut self, level: CompressionLevel) -> Self {
+        self.codec = self.codec.with_level(level);
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        Compressor::name(&self.codec)
+    }
+}
+
+impl<C: Codec> Decompressor for Chunked<C> {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.decompress_with_progress(input, |_| {})
+    }
+
+    /// Chunked frames add up the lengths in each chunk's header, so the
+    /// total is known only if the wrapped codec reports them all.
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let Some(&mode) = input.first() else {
+            return Err(CompressionError::CorruptedData);
+        };
+
+        match mode {
+            MODE_SINGLE => self.codec.decompressed_len(&input[1..]),
+            MODE_CHUNKED | MODE_CHAINED => {
+                let count = read_u32(input, 1)?;
+                let mut pos = 5;
+                let mut total = 0u64;
+                for _ in 0..count {
+                    let len = read_u32(input, pos)? as usize;
+                    pos += 4;
+                    let chunk = input
+                        .get(pos..pos + len)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    let Some(chunk_len) = self.codec.decompressed_len(chunk)? else {
+                        return Ok(None);
+                    };
+                    total = total
+                        .checked_add(chunk_len)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    pos += len;
+                }
+                Ok(Some(total))
+            }
+            _ => Err(CompressionError::InvalidHeader),
+        }
+    }
+
+    /// The chunk lengths are checked against `limits` up front where the
+    /// wrapped codec records them, and each chunk then decodes under what
+    /// the limits leave after the chunks before it.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        if let Some(len) = self.decompressed_len(input)? {
+            limits.check(input.len() as u64, len)?;
+        }
+        self.decompress_limited(input, limits, |_| {})
+    }
+
+    fn name(&self) -> &'static str {
+        Decompressor::name(&self.codec)
+    }
+}
+
+impl<C: Codec + PartialDecompressor> PartialDecompressor for Chunked<C> {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let Some(&mode) = input.first() else {
+            return Err(CompressionError::CorruptedData);
+        };
+
+        match mode {
+            MODE_SINGLE => {
+                let (output, consumed) = self.codec.decompress_partial(&input[1..])?;
+                Ok((output, consumed + 1))
+            }
+            MODE_CHUNKED | MODE_CHAINED => {
+                let count = read_u32(input, 1)?;
+                let mut end = 5;
+                for _ in 0..count {
+                    end += 4 + read_u32(input, end)? as usize;
+                }
+                if end > input.len() {
+                    return Err(CompressionError::CorruptedData);
+                }
+                Ok((self.decompress(&input[..end])?, end))
+            }
+            _ => Err(CompressionError::InvalidHeader),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz77, Rle};
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| b"abcabcabd"[i % 9]).collect()
+    }
+
+    #[test]
+    fn test_chunked_new() {
+        let chunked = Chunked::new(Rle::new());
+        assert_eq!(chunked.threshold(), DEFAULT_THRESHOLD);
+        assert_eq!(chunked.chunk_size(), DEFAULT_CHUNK_SIZE);
+        assert_eq!(Compressor::name(&chunked), "RLE");
+        assert_eq!(Decompressor::name(chunked.inner()), "RLE");
+    }
+
+    #[test]
+    fn test_empty() {
+        let chunked = Chunked::new(Lz77::new());
+        let compressed = chunked.compress(&[]).unwrap();
+        assert_eq!(compressed, [MODE_SINGLE, 0xFF, 0xFF, 0xFF, 0xFF, 3, 0, 12, 4]);
+        assert!(chunked.decompress(&compressed).unwrap().is_empty());
+        assert!(matches!(
+            chunked.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_below_threshold_is_single() {
+        let chunked = Chunked::with_threshold(Rle::new(), 100, 10);
+        let input = sample(100);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(compressed[0], MODE_SINGLE);
+        assert_eq!(&compressed[1..], Rle::new().compress(&input).unwrap());
+        assert_eq!(chunked.decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_above_threshold_is_chunked() {
+        let chunked = Chunked::with_threshold(Lz77::new(), 100, 64);
+        let input = sample(1000);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(compressed[0], MODE_CHUNKED);
+        assert_eq!(read_u32(&compressed, 1).unwrap(), 16);
+        assert_eq!(chunked.decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_roundtrip_huffman_chunks() {
+        let chunked = Chunked::with_threshold(Huffman::new(), 0, 333);
+        let input = sample(5000);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(chunked.decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_zero_chunk_size() {
+        let chunked = Chunked::with_threshold(Rle::new(), 0, 0);
+        let result = chunked.compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_decompress_invalid_mode() {
+        let chunked = Chunked::new(Rle::new());
+        let result = chunked.decompress(&[9, 1, b'a']);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_decompress_truncated_chunk() {
+        let chunked = Chunked::with_threshold(Rle::new(), 0, 4);
+        let compressed = chunked.compress(&sample(20)).unwrap();
+        let result = chunked.decompress(&compressed[..compressed.len() - 1]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_compress_progress_per_chunk() {
+        let chunked = Chunked::with_threshold(Rle::new(), 0, 100);
+        let input = sample(250);
+        let mut reports = Vec::new();
+        let compressed = chunked
+            .compress_with_progress(&input, |p| reports.push(p.clone()))
+            .unwrap();
+        assert_eq!(compressed, chunked.compress(&input).unwrap());
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(
+            reports.iter().map(|p| p.entry_bytes).collect::<Vec<_>>(),
+            [100, 100, 50]
+        );
+        assert!(reports.iter().all(|p| p.entries == 3 && p.bytes_total == 250));
+        let last = reports.last().unwrap();
+        assert!(last.is_complete());
+        assert_eq!(last.bytes_done, 250);
+        assert_eq!(last.remaining(), Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_compress_progress_single() {
+        let chunked = Chunked::new(Lz77::new());
+        let mut reports = 0;
+        chunked
+            .compress_with_progress(&sample(64), |p| {
+                assert!(p.is_complete());
+                assert_eq!(p.bytes_done, 64);
+                reports += 1;
+            })
+            .unwrap();
+        assert_eq!(reports, 1);
+    }
+
+    #[test]
+    fn test_decompress_progress_covers_input() {
+        let chunked = Chunked::with_threshold(Huffman::new(), 0, 64);
+        let input = sample(300);
+        let compressed = chunked.compress(&input).unwrap();
+        let mut last = None;
+        let restored = chunked
+            .decompress_with_progress(&compressed, |p| last = Some(p.clone()))
+            .unwrap();
+        assert_eq!(restored, input);
+        let last = last.unwrap();
+        assert_eq!(last.entries, 5);
+        assert_eq!(last.bytes_done, compressed.len() as u64);
+    }
+
+    #[test]
+    fn test_decompress_trailing_bytes() {
+        let chunked = Chunked::with_threshold(Rle::new(), 0, 4);
+        let mut compressed = chunked.compress(&sample(20)).unwrap();
+        compressed.push(0);
+        let result = chunked.decompress(&compressed);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let chunked = Chunked::with_threshold(Lz77::new(), 16, 8);
+        let single = chunked.compress(b"short").unwrap();
+        let multi = chunked
+            .compress(b"a longer input split into chunks")
+            .unwrap();
+        assert_eq!(single[0], MODE_SINGLE);
+        assert_eq!(multi[0], MODE_CHUNKED);
+        let stream = [multi.as_slice(), &single, &multi].concat();
+
+        let (data, consumed) = chunked.decompress_partial(&stream).unwrap();
+        assert_eq!(data, b"a longer input split into chunks");
+        assert_eq!(consumed, multi.len());
+        let frames = chunked.decompress_frames(&stream).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1], b"short");
+        assert!(
+            chunked
+                .decompress_partial(&multi[..multi.len() - 1])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_max_compressed_len_covers_chunks() {
+        let noise: Vec<u8> = (0..1000u32)
+            .map(|i| i.wrapping_mul(2_654_435_761).to_le_bytes()[3])
+            .collect();
+        for len in [0, 100, 128, 1000] {
+            let chunked = Chunked::with_threshold(Rle::new(), 100, 64);
+            let compressed = chunked.compress(&noise[..len]).unwrap();
+            assert!(compressed.len() <= chunked.max_compressed_len(len));
+            let chained = Chunked::with_threshold(Lz77::new(), 100, 64).with_chained(true);
+            let compressed = chained.compress(&noise[..len]).unwrap();
+            assert!(compressed.len() <= chained.max_compressed_len(len));
+        }
+        // 15 full chunks and one of 40 bytes, each with its length.
+        let chunked = Chunked::with_threshold(Rle::new(), 100, 64);
+        assert_eq!(chunked.max_compressed_len(1000), 5 + 15 * 132 + 84);
+    }
+
+    #[test]
+    fn test_decompressed_len_sums_chunks() {
+        let input = sample(1000);
+        let chunked = Chunked::with_threshold(Lz77::new(), 100, 64);
+        for len in [0, 100, 1000] {
+            let compressed = chunked.compress(&input[..len]).unwrap();
+            assert_eq!(
+                chunked.decompressed_len(&compressed).unwrap(),
+                Some(len as u64)
+            );
+        }
+        let chained = chunked.with_chained(true);
+        let compressed = chained.compress(&input).unwrap();
+        assert_eq!(chained.decompressed_len(&compressed).unwrap(), Some(1000));
+        // Pair-format RLE records no lengths, so neither does the container.
+        let pairs = Chunked::with_threshold(Rle::new(), 100, 64);
+        let compressed = pairs.compress(&input).unwrap();
+        assert_eq!(pairs.decompressed_len(&compressed).unwrap(), None);
+        assert!(pairs.decompressed_len(&compressed[..7]).is_err());
+        assert!(pairs.decompressed_len(&[9]).is_err());
+    }
+
+    #[test]
+    fn test_with_level_reaches_inner_codec() {
+        let chunked = Chunked::with_threshold(Lz77::new(), 100, 64)
+            .with_chained(true)
+            .with_level(CompressionLevel::FASTEST);
+        assert_eq!(
+            chunked.inner().window_size(),
+            Lz77::realtime().window_size()
+        );
+        assert_eq!(chunked.chunk_size(), 64);
+        assert!(chunked.chained());
+        let input = sample(1000);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(
+            Chunked::new(Lz77::new()).decompress(&compressed).unwrap(),
+            input
+        );
+    }
+
+    #[test]
+    fn test_decompress_with_limits_per_chunk() {
+        let limits = DecompressionLimits::new().with_max_output_size(500);
+        let roomy = DecompressionLimits::new().with_max_output_size(1000);
+        // Pair-format RLE records no lengths, so the chunks are held to the
+        // output left as they decode.
+        let input = vec![0; 1000];
+        let pairs = Chunked::with_threshold(Rle::new(), 100, 64);
+        let compressed = pairs.compress(&input).unwrap();
+        assert_eq!(pairs.decompressed_len(&compressed).unwrap(), None);
+        assert!(matches!(
+            pairs.decompress_with_limits(&compressed, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert_eq!(
+            pairs.decompress_with_limits(&compressed, &roomy).unwrap(),
+            input
+        );
+
+        let input = sample(1000);
+        for chained in [false, true] {
+            let codec = Chunked::with_threshold(Lz77::new(), 100, 64).with_chained(chained);
+            let compressed = codec.compress(&input).unwrap();
+            assert!(matches!(
+                codec.decompress_with_limits(&compressed, &limits),
+                Err(CompressionError::LimitExceeded(_))
+            ));
+            assert_eq!(
+                codec.decompress_with_limits(&compressed, &roomy).unwrap(),
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_chained_roundtrip() {
+        let independent = Chunked::with_threshold(Lz77::new(), 0, 64);
+        let chained = independent.clone().with_chained(true);
+        assert!(chained.chained());
+        // A 64-byte block with no internal repeats, repeated once per chunk.
+        let block: Vec<u8> = (0..64u32).map(|i| (i * 37).to_le_bytes()[0]).collect();
+        let input = block.repeat(16);
+        let compressed = chained.compress(&input).unwrap();
+        assert_eq!(compressed[0], MODE_CHAINED);
+        assert_eq!(read_u32(&compressed, 1).unwrap(), 16);
+        let independent_len = independent.compress(&input).unwrap().len();
+        assert!(compressed.len() * 2 < independent_len);
+        assert_eq!(chained.decompress(&compressed).unwrap(), input);
+        assert_eq!(chained.decompress_frames(&compressed).unwrap(), [input]);
+    }
+
+    #[test]
+    fn test_chained_single_chunk_is_plain() {
+        let chunked = Chunked::new(Lz77::new()).with_chained(true);
+        let compressed = chunked.compress(b"short").unwrap();
+        assert_eq!(compressed[0], MODE_SINGLE);
+        assert_eq!(chunked.decompress(&compressed).unwrap(), b"short");
+    }
+
+    #[test]
+    fn test_chained_requires_history_support() {
+        let chunked = Chunked::with_threshold(Rle::new(), 0, 4).with_chained(true);
+        let result = chunked.compress(&sample(20));
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+
+        let mut compressed = Chunked::with_threshold(Rle::new(), 0, 4)
+            .compress(&sample(20))
+            .unwrap();
+        compressed[0] = MODE_CHAINED;
+        assert!(chunked.decompress(&compressed).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use crate::error::{CompressionError, Result};
+use crate::hash::Gear;
+use crate::limits::DecompressionLimits;
+use crate::traits::{Codec, Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+const DEFAULT_MIN_SIZE: usize = 2 * 1024;
+const DEFAULT_AVG_SIZE: usize = 8 * 1024;
+const DEFAULT_MAX_SIZE: usize = 64 * 1024;
+/// Smallest average chunk size [`Chunker::with_sizes`] accepts.
+pub const MIN_AVG_SIZE: usize = 64;
+/// Largest average chunk size [`Chunker::with_sizes`] accepts.
+pub const MAX_AVG_SIZE: usize = 1 << 30;
+/// Extra mask bits below the average size and fewer above it, which pulls
+/// chunk sizes towards the average (the `FastCDC` normalization level).
+pub const NORMALIZATION: u32 = 2;
+/// Tag of a chunk stored in the payload; tag `n` above it refers back to
+/// the `n`th stored chunk, counting from one.
+pub const TAG_NEW: u64 = 0;
+
+/// Mask testing the top `bits` bits of the gear hash, the ones that depend
+/// on the whole window rather than the last few bytes.
+const fn mask(bits: u32) -> u64 {
+    !0 << (u64::BITS - bits)
+}
+
+/// `FastCDC` content-defined chunking.
+///
+/// A [`Gear`] hash rolls over the data, and a chunk ends where its top bits
+/// are all zero, so boundaries depend only on the bytes just before them.
+/// An insertion or deletion therefore moves only the boundaries next to
+/// it, and the chunks after it come out the same as before. No chunk is
+/// shorter than the minimum size, except at the end of the data, or longer
+/// than the maximum. Between the two, a stricter mask applies before the
+/// average size and a looser one after it, which keeps most chunks near
+/// the average.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunker {
+    min: usize,
+    avg: usize,
+    max: usize,
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chunker {
+    /// Creates a chunker with 2 KiB minimum, 8 KiB average and 64 KiB
+    /// maximum chunks.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_sizes(DEFAULT_MIN_SIZE, DEFAULT_AVG_SIZE, DEFAULT_MAX_SIZE)
+    }
+
+    /// Creates a chunker with the given chunk sizes. The average is rounded
+    /// up to a power of two between `MIN_AVG_SIZE` and `MAX_AVG_SIZE`,
+    /// the minimum is capped at the average, and the maximum raised to it.
+    #[must_use]
+    pub const fn with_sizes(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        let avg_size = if avg_size < MIN_AVG_SIZE {
+            MIN_AVG_SIZE
+        } else if avg_size > MAX_AVG_SIZE {
+            MAX_AVG_SIZE
+        } else {
+            avg_size.next_power_of_two()
+        };
+        Self {
+            min: if min_size < avg_size {
+                min_size
+            } else {
+                avg_size
+            },
+            avg: avg_size,
+            max: if max_size > avg_size {
+                max_size
+            } else {
+                avg_size
+            },
+        }
+    }
+
+    #[must_use]
+    pub const fn min_size(&self) -> usize {
+        self.min
+    }
+
+    #[must_use]
+    pub const fn avg_size(&self) -> usize {
+        self.avg
+    }
+
+    #[must_use]
+    pub const fn max_size(&self) -> usize {
+        self.max
+    }
+
+    /// Length of the first chunk of `data`, which is all of it if `data` is
+    /// no longer than the minimum size.
+    #[must_use]
+    pub fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min {
+            return data.len();
+        }
+        let end = data.len().min(self.max);
+        let normal = end.min(self.avg);
+        let bits = self.avg.trailing_zeros();
+        let strict = mask(bits + NORMALIZATION);
+        let loose = mask(bits - NORMALIZATION);
+        let mut gear = Gear::new();
+        for (i, &byte) in data.iter().enumerate().take(end).skip(self.min) {
+            let hash = gear.roll(byte);
+            let mask = if i < normal { strict } else { loose };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+
+    /// Splits `data` into chunks, in order.
+    #[must_use]
+    pub const fn chunks<'a>(&self, data: &'a [u8]) -> Chunks<'a> {
+        Chunks {
+            chunker: *self,
+            rest: data,
+        }
+    }
+}
+
+/// Iterator over the chunks of a buffer, from [`Chunker::chunks`].
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+    chunker: Chunker,
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let (chunk, rest) = self.rest.split_at(self.chunker.cut(self.rest));
+        self.rest = rest;
+        Some(chunk)
+    }
+}
+
+/// Wraps a codec so that repeated chunks are stored once.
+///
+/// The input is cut into content-defined chunks with a [`Chunker`], and
+/// every chunk equal to an earlier one becomes a reference to it. Only the
+/// first copy of each chunk reaches the wrapped codec, concatenated into
+/// one payload. Repeats are found at any distance, well beyond the window
+/// of [`Lz77`](crate::Lz77), and survive small edits between copies, since
+/// chunk boundaries resynchronize shortly after each edit. Data with no
+/// repeats of chunk size pays a byte or two per chunk. Output layout:
+/// `[chunk count: varint]`, then per chunk `[tag: varint]` with
+/// `[length: varint]` after each `TAG_NEW`, then `[payload]`.
+#[derive(Debug, Clone)]
+pub struct Dedup<C: Codec> {
+    codec: C,
+    chunker: Chunker,
+}
+
+impl<C: Codec> Dedup<C> {
+    pub const fn new(codec: C) -> Self {
+        Self::with_chunker(codec, Chunker::new())
+    }
+
+    pub const fn with_chunker(codec: C, chunker: Chunker) -> Self {
+        Self { codec, chunker }
+    }
+
+    #[must_use]
+    pub const fn chunker(&self) -> &Chunker {
+        &self.chunker
+    }
+
+    pub const fn inner(&self) -> &C {
+        &self.codec
+    }
+
+    /// Reads the chunk table at the start of `input`, returning the stored
+    /// chunk lengths, each chunk as an index into them, and the table size.
+    fn read_table(input: &[u8]) -> Result<(Vec<usize>, Vec<usize>, usize)> {
+        let mut pos = 0;
+        let count = varint::read_u64(input, &mut pos)?;
+        let count = usize::try_from(count).map_err(|_| CompressionError::CorruptedData)?;
+        let mut stored = Vec::new();
+        let mut order = Vec::with_capacity(count.min(input.len()));
+        for _ in 0..count {
+            let tag = varint::read_u64(input, &mut pos)?;
+            if tag == TAG_NEW {
+                let len = varint::read_u64(input, &mut pos)?;
+                stored.push(usize::try_from(len).map_err(|_| CompressionError::CorruptedData)?);
+                order.push(stored.len() - 1);
+            } else {
+                let index = usize::try_from(tag - 1)
+                    .ok()
+                    .filter(|&index| index < stored.len())
+                    .ok_or(CompressionError::CorruptedData)?;
+                order.push(index);
+            }
+        }
+        Ok((stored, order, pos))
+    }
+
+    /// Total length of the chunks at `indices` into `stored`.
+    fn total_len(stored: &[usize], mut indices: impl Iterator<Item = usize>) -> Result<u64> {
+        indices
+            .try_fold(0u64, |total, index| total.checked_add(stored[index] as u64))
+            .ok_or(CompressionError::CorruptedData)
+    }
+
+    /// Reassembles the input from the table and the decoded payload.
+    fn assemble(stored: &[usize], order: &[usize], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut starts = Vec::with_capacity(stored.len());
+        let mut start = 0usize;
+        for &len in stored {
+            starts.push(start);
+            start = start
+                .checked_add(len)
+                .ok_or(CompressionError::CorruptedData)?;
+        }
+        if start != payload.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        let mut output = Vec::with_capacity(payload.len());
+        for &index in order {
+            output.extend_from_slice(&payload[starts[index]..starts[index] + stored[index]]);
+        }
+        Ok(output)
+    }
+}
+
+impl<C: Codec> Compressor for Dedup<C> {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut seen: HashMap<&[u8], u64> = HashMap::new();
+        let mut table = Vec::new();
+        let mut payload = Vec::with_capacity(input.len());
+        let mut count = 0u64;
+        for chunk in self.chunker.chunks(input) {
+            count += 1;
+            if let Some(&index) = seen.get(chunk) {
+                varint::write_u64(&mut table, TAG_NEW + 1 + index);
+            } else {
+                seen.insert(chunk, seen.len() as u64);
+                varint::write_u64(&mut table, TAG_NEW);
+                varint::write_u64(&mut table, chunk.len() as u64);
+                payload.extend_from_slice(chunk);
+            }
+        }
+        let mut output = Vec::with_capacity(varint::MAX_LEN_U64 + table.len() + payload.len());
+        varint::write_u64(&mut output, count);
+        output.extend_from_slice(&table);
+        output.extend_from_slice(&self.codec.compress(&payload)?);
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        Compressor::name(&self.codec)
+    }
+}
+
+impl<C: Codec> Decompressor for Dedup<C> {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (stored, order, pos) = Self::read_table(input)?;
+        let payload = self.codec.decompress(&input[pos..])?;
+        Self::assemble(&stored, &order, &payload)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let (stored, order, _) = Self::read_table(input)?;
+        Self::total_len(&stored, order.into_iter()).map(Some)
+    }
+
+    /// The chunk table is checked against `limits` before the payload is
+    /// decoded, and the payload is held to the total of the stored chunks,
+    /// so neither can expand past what the table declares.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        let (stored, order, pos) = Self::read_table(input)?;
+        limits.check(
+            input.len() as u64,
+            Self::total_len(&stored, order.iter().copied())?,
+        )?;
+        let payload_len = Self::total_len(&stored, 0..stored.len())?;
+        let payload = self.codec.decompress_with_limits(
+            &input[pos..],
+            &DecompressionLimits::new().with_max_output_size(payload_len),
+        )?;
+        Self::assemble(&stored, &order, &payload)
+    }
+
+    fn name(&self) -> &'static str {
+        Decompressor::name(&self.codec)
+    }
+}
+
+impl<C: Codec + PartialDecompressor> PartialDecompressor for Dedup<C> {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let (stored, order, pos) = Self::read_table(input)?;
+        let (payload, consumed) = self.codec.decompress_partial(&input[pos..])?;
+        Ok((Self::assemble(&stored, &order, &payload)?, pos + consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lz4, Stored};
+
+    fn bomb_limits() -> DecompressionLimits {
+        DecompressionLimits::new().with_max_ratio(100)
+    }
+
+    fn noise(seed: u32, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state.to_le_bytes()[2]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunker_sizes() {
+        let chunker = Chunker::new();
+        assert_eq!(chunker.min_size(), 2048);
+        assert_eq!(chunker.avg_size(), 8192);
+        assert_eq!(chunker.max_size(), 65536);
+        let odd = Chunker::with_sizes(5000, 3000, 10);
+        assert_eq!(odd.avg_size(), 4096);
+        assert_eq!(odd.min_size(), 4096);
+        assert_eq!(odd.max_size(), 4096);
+        assert_eq!(Chunker::with_sizes(0, 1, 0).avg_size(), MIN_AVG_SIZE);
+    }
+
+    #[test]
+    fn test_chunks_cover_input_within_bounds() {
+        let data = noise(1, 500_000);
+        let chunker = Chunker::new();
+        let chunks: Vec<&[u8]> = chunker.chunks(&data).collect();
+        assert_eq!(chunks.concat(), data);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(last.len() <= chunker.max_size());
+        for chunk in rest {
+            assert!((chunker.min_size()..=chunker.max_size()).contains(&chunk.len()));
+        }
+        // Normalization keeps the mean near the average size.
+        let mean = data.len() / chunks.len();
+        assert!((4096..=16384).contains(&mean), "{mean}");
+        assert_eq!(chunker.chunks(&[]).count(), 0);
+        assert_eq!(chunker.chunks(b"short").collect::<Vec<_>>(), [b"short"]);
+    }
+
+    #[test]
+    fn test_boundaries_resynchronize_after_insertion() {
+        let data = noise(2, 200_000);
+        let edited = [&data[..1000], b"inserted bytes", &data[1000..]].concat();
+        let chunker = Chunker::new();
+        let before: Vec<&[u8]> = chunker.chunks(&data).collect();
+        let after: Vec<&[u8]> = chunker.chunks(&edited).collect();
+        let shared = after.iter().filter(|chunk| before.contains(chunk)).count();
+        assert!(shared + 2 >= before.len(), "{shared} of {}", before.len());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dedup = Dedup::new(Lz4::new());
+        let block = noise(3, 40_000);
+        let inputs = [
+            Vec::new(),
+            b"tiny".to_vec(),
+            noise(4, 100_000),
+            [&block[..], &block, b"edit", &block].concat(),
+        ];
+        for input in inputs {
+            let compressed = dedup.compress(&input).unwrap();
+            assert_eq!(dedup.decompress(&compressed).unwrap(), input);
+        }
+        assert_eq!(Compressor::name(&dedup), "LZ4");
+        assert_eq!(dedup.chunker(), &Chunker::new());
+    }
+
+    #[test]
+    fn test_repeats_beyond_window_stored_once() {
+        let block = noise(5, 64 * 1024);
+        let input = [&block[..], b"a small edit between copies", &block, &block].concat();
+        let compressed = Dedup::new(Stored::new()).compress(&input).unwrap();
+        // One copy of the block, plus the chunks around the edit and the
+        // joins between copies.
+        assert!(compressed.len() < 2 * block.len(), "{}", compressed.len());
+        // LZ4's 64 KiB offsets cannot reach back a whole block.
+        let lz4 = Lz4::new().compress(&input).unwrap();
+        assert!(lz4.len() > 3 * block.len());
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let dedup = Dedup::with_chunker(Lz4::new(), Chunker::with_sizes(64, 256, 1024));
+        let block = noise(6, 2000);
+        let first = [&block[..], &block].concat();
+        let stream = [
+            dedup.compress(&first).unwrap(),
+            dedup.compress(b"").unwrap(),
+            dedup.compress(b"last").unwrap(),
+        ]
+        .concat();
+        let frames = dedup.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [first, Vec::new(), b"last".to_vec()]);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let dedup = Dedup::new(Stored::new());
+        assert!(dedup.decompress(&[]).is_err());
+        // A reference to a chunk not yet stored.
+        let mut frame = vec![1, 1];
+        frame.extend_from_slice(&Stored::new().compress(b"").unwrap());
+        assert!(dedup.decompress(&frame).is_err());
+        // Stored lengths disagreeing with the payload.
+        let mut frame = vec![1, 0, 5];
+        frame.extend_from_slice(&Stored::new().compress(b"abc").unwrap());
+        assert_eq!(
+            dedup.decompress(&frame),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+
+    #[test]
+    fn test_limits_refuse_bombs() {
+        let dedup = Dedup::new(Stored::new());
+        let input = [&noise(7, 5000)[..], &noise(8, 5000)].concat();
+        let compressed = dedup.compress(&input).unwrap();
+        assert_eq!(
+            dedup.decompressed_len(&compressed).unwrap(),
+            Some(input.len() as u64)
+        );
+        let decoded = dedup.decompress_with_limits(&compressed, &bomb_limits());
+        assert_eq!(decoded.unwrap(), input);
+
+        // One stored chunk referenced a byte at a time.
+        let count = 100_000;
+        let mut bomb = Vec::new();
+        varint::write_u64(&mut bomb, count as u64);
+        bomb.extend_from_slice(&[0, 0xE8, 0x07]);
+        bomb.resize(bomb.len() + count - 1, 1);
+        bomb.extend_from_slice(&Stored::new().compress(&[0; 1000]).unwrap());
+        assert_eq!(
+            dedup.decompressed_len(&bomb).unwrap(),
+            Some(count as u64 * 1000)
+        );
+        assert!(matches!(
+            dedup.decompress_with_limits(&bomb, &bomb_limits()),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+
+        // A payload claiming more than the table's chunks add up to.
+        let dedup = Dedup::new(Lz4::new());
+        let mut bomb = vec![1, 0, 10];
+        varint::write_u64(&mut bomb, 1 << 40);
+        assert!(matches!(
+            dedup.decompress_with_limits(&bomb, &bomb_limits()),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+}
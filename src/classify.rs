@@ -0,0 +1,199 @@
+/// Broad content category inferred from byte statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentClass {
+    /// Printable text (ASCII or UTF-8).
+    Text,
+    /// Arrays of fixed-width numbers; `element_size` is the detected width
+    /// in bytes, suited to delta or byte-shuffle filtering.
+    Numeric { element_size: usize },
+    /// Near-random bytes such as already-compressed or encrypted data.
+    Compressed,
+    /// Anything else.
+    Binary,
+}
+
+const TEXT_RATIO: f64 = 0.95;
+const COMPRESSED_ENTROPY: f64 = 7.5;
+const MIN_COMPRESSED_LEN: usize = 64;
+const NUMERIC_GAIN: f64 = 0.75;
+const MIN_SAMPLES_PER_LANE: usize = 32;
+
+/// Shannon entropy in bits per byte.
+pub fn entropy<'a>(bytes: impl Iterator<Item = &'a u8>) -> f64 {
+    let mut counts = [0usize; 256];
+    let mut total = 0usize;
+    for &byte in bytes {
+        counts[usize::from(byte)] += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return 0.0;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let total = total as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            #[allow(clippy::cast_precision_loss)]
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn is_text(data: &[u8]) -> bool {
+    if std::str::from_utf8(data).is_err() {
+        return false;
+    }
+    let printable = data
+        .iter()
+        .filter(|&&b| matches!(b, b'\t' | b'\n' | b'\r' | 0x20..=0x7E) || b >= 0x80)
+        .count();
+    #[allow(clippy::cast_precision_loss)]
+    let ratio = printable as f64 / data.len() as f64;
+    ratio >= TEXT_RATIO
+}
+
+/// Mean per-lane entropy when `data` is viewed as `width`-byte elements.
+fn strided_entropy(data: &[u8], width: usize) -> f64 {
+    let total: f64 = (0..width)
+        .map(|lane| entropy(data.iter().skip(lane).step_by(width)))
+        .sum();
+    #[allow(clippy::cast_precision_loss)]
+    let width = width as f64;
+    total / width
+}
+
+/// Slack allowed when preferring a narrower element width: a multiple of
+/// the true width always scores at least as well, so ties go to the smaller.
+const WIDTH_TOLERANCE: f64 = 1.1;
+
+fn numeric_element_size(data: &[u8], overall: f64) -> Option<usize> {
+    let candidates: Vec<(usize, f64)> = [2, 4, 8]
+        .into_iter()
+        .filter(|width| data.len() >= width * MIN_SAMPLES_PER_LANE)
+        .map(|width| (width, strided_entropy(data, width)))
+        .collect();
+    let best = candidates
+        .iter()
+        .map(|&(_, lane_entropy)| lane_entropy)
+        .fold(f64::INFINITY, f64::min);
+    if best < overall * NUMERIC_GAIN {
+        return candidates
+            .iter()
+            .find(|&&(_, lane_entropy)| lane_entropy <= best * WIDTH_TOLERANCE)
+            .map(|&(width, _)| width);
+    }
+
+    if data.len() >= MIN_SAMPLES_PER_LANE {
+        let deltas: Vec<u8> = data.windows(2).map(|w| w[1].wrapping_sub(w[0])).collect();
+        if entropy(deltas.iter()) < overall * NUMERIC_GAIN {
+            return Some(1);
+        }
+    }
+    None
+}
+
+/// Classifies `data` from its byte statistics.
+///
+/// Text is recognized first, then numeric arrays (per-lane or delta
+/// entropy much lower than the raw byte entropy), then near-random data.
+/// Empty input is classified as [`ContentClass::Binary`].
+#[must_use]
+pub fn classify(data: &[u8]) -> ContentClass {
+    if data.is_empty() {
+        return ContentClass::Binary;
+    }
+    if is_text(data) {
+        return ContentClass::Text;
+    }
+
+    let overall = entropy(data.iter());
+    if let Some(element_size) = numeric_element_size(data, overall) {
+        return ContentClass::Numeric { element_size };
+    }
+    if data.len() >= MIN_COMPRESSED_LEN && overall >= COMPRESSED_ENTROPY {
+        return ContentClass::Compressed;
+    }
+    ContentClass::Binary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_empty() {
+        assert_eq!(classify(&[]), ContentClass::Binary);
+    }
+
+    #[test]
+    fn test_classify_text() {
+        let text = b"The quick brown fox jumps over the lazy dog.\nLine two\ttabbed.";
+        assert_eq!(classify(text), ContentClass::Text);
+    }
+
+    #[test]
+    fn test_classify_utf8_text() {
+        let text = "naïve café — über straße".repeat(5);
+        assert_eq!(classify(text.as_bytes()), ContentClass::Text);
+    }
+
+    #[test]
+    fn test_classify_compressed() {
+        assert_eq!(classify(&pseudo_random(4096)), ContentClass::Compressed);
+    }
+
+    #[test]
+    fn test_classify_u32_array() {
+        let data: Vec<u8> = (0..1000u32)
+            .flat_map(|i| (i * 2654).to_le_bytes())
+            .collect();
+        assert_eq!(classify(&data), ContentClass::Numeric { element_size: 4 });
+    }
+
+    #[test]
+    fn test_classify_u16_array() {
+        let data: Vec<u8> = pseudo_random(1000)
+            .into_iter()
+            .flat_map(|b| u16::from(b).to_le_bytes())
+            .collect();
+        assert_eq!(classify(&data), ContentClass::Numeric { element_size: 2 });
+    }
+
+    #[test]
+    fn test_classify_ramp_is_delta_friendly() {
+        let data: Vec<u8> = (0..2000u32)
+            .map(|i| u8::try_from((i * 3 + i / 7) % 256).unwrap())
+            .collect();
+        assert_eq!(classify(&data), ContentClass::Numeric { element_size: 1 });
+    }
+
+    #[test]
+    fn test_classify_binary() {
+        let mut data = vec![0u8, 1, 2, 0xFF, 0xFE];
+        data.extend_from_slice(b"\x00\x00header\x00");
+        assert_eq!(classify(&data), ContentClass::Binary);
+    }
+
+    #[test]
+    fn test_entropy_bounds() {
+        assert!(entropy([0u8; 100].iter()).abs() < f64::EPSILON);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((entropy(all.iter()) - 8.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+/// Byte that ends each frame and never appears inside one.
+pub const DELIMITER: u8 = 0x00;
+/// Most data bytes behind a single code byte.
+pub const MAX_BLOCK: usize = 254;
+
+/// Consistent overhead byte stuffing, for sending compressed frames over
+/// byte-oriented links that use a zero byte as the frame delimiter.
+///
+/// Runs after the main codec. Every zero byte is replaced by a code byte
+/// giving the distance to the next one, so the encoded frame contains no
+/// zeros and grows by one byte per 254, plus one. By default the frame is
+/// terminated with `DELIMITER`, so back-to-back frames can be split with
+/// [`PartialDecompressor::decompress_frames`]. Output layout: `[code: u8]
+/// [code - 1 data bytes]` blocks, then the delimiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cobs {
+    delimited: bool,
+}
+
+impl Default for Cobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cobs {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { delimited: true }
+    }
+
+    /// Sets whether frames end with `DELIMITER`. Without it the caller
+    /// supplies the framing, and decoding treats the whole input as one
+    /// frame.
+    #[must_use]
+    pub const fn with_delimiter(mut self, delimited: bool) -> Self {
+        self.delimited = delimited;
+        self
+    }
+
+    #[must_use]
+    pub const fn delimited(&self) -> bool {
+        self.delimited
+    }
+}
+
+fn decode_frame(frame: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(frame.len());
+    let mut rest = frame;
+    while let Some((&code, data)) = rest.split_first() {
+        let len = usize::from(code)
+            .checked_sub(1)
+            .ok_or(CompressionError::CorruptedData)?;
+        let block = data.get(..len).ok_or(CompressionError::CorruptedData)?;
+        if block.contains(&DELIMITER) {
+            return Err(CompressionError::CorruptedData);
+        }
+        output.extend_from_slice(block);
+        rest = &data[len..];
+        // A short block stands for a zero, except at the end of the frame.
+        if len < MAX_BLOCK && !rest.is_empty() {
+            output.push(0);
+        }
+    }
+    if frame.is_empty() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(output)
+}
+
+impl Compressor for Cobs {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() + input.len() / MAX_BLOCK + 2);
+        let mut code_pos = output.len();
+        output.push(0);
+        let mut block_len = 0;
+
+        for (i, &byte) in input.iter().enumerate() {
+            if byte == 0 {
+                output[code_pos] = u8::try_from(block_len + 1).unwrap_or(u8::MAX);
+                code_pos = output.len();
+                output.push(0);
+                block_len = 0;
+                continue;
+            }
+
+            output.push(byte);
+            block_len += 1;
+            // A full block needs no implied zero; only open another if more
+            // input follows.
+            if block_len == MAX_BLOCK && i + 1 < input.len() {
+                output[code_pos] = u8::MAX;
+                code_pos = output.len();
+                output.push(0);
+                block_len = 0;
+            }
+        }
+        output[code_pos] = u8::try_from(block_len + 1).unwrap_or(u8::MAX);
+
+        if self.delimited {
+            output.push(DELIMITER);
+        }
+        Ok(output)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let overhead = 1 + input_len / MAX_BLOCK + usize::from(self.delimited);
+        input_len.saturating_add(overhead)
+    }
+
+    fn name(&self) -> &'static str {
+        "COBS"
+    }
+}
+
+impl Decompressor for Cobs {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if !self.delimited {
+            return decode_frame(input);
+        }
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "COBS"
+    }
+}
+
+impl PartialDecompressor for Cobs {
+    /// Undelimited frames are not self-delimiting and return
+    /// `CompressionError::InvalidInput`.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        if !self.delimited {
+            return Err(CompressionError::InvalidInput(
+                "undelimited COBS frames are not self-delimiting".to_string(),
+            ));
+        }
+        let end = input
+            .iter()
+            .position(|&byte| byte == DELIMITER)
+            .ok_or(CompressionError::CorruptedData)?;
+        Ok((decode_frame(&input[..end])?, end + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lz77;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let cobs = Cobs::new();
+        let encoded = cobs.compress(input).unwrap();
+        assert_eq!(encoded.last(), Some(&DELIMITER));
+        assert!(!encoded[..encoded.len() - 1].contains(&DELIMITER));
+        assert_eq!(cobs.decompress(&encoded).unwrap(), input);
+        encoded
+    }
+
+    #[test]
+    fn test_cobs_new() {
+        let cobs = Cobs::new();
+        assert!(cobs.delimited());
+        assert_eq!(Cobs::default(), cobs);
+        assert!(!cobs.with_delimiter(false).delimited());
+        assert_eq!(Compressor::name(&cobs), "COBS");
+        assert_eq!(Decompressor::name(&cobs), "COBS");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [1, 0]);
+        assert!(Cobs::new().decompress(&[]).is_err());
+        assert!(Cobs::new().decompress(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_known_vectors() {
+        assert_eq!(roundtrip(&[0]), [1, 1, 0]);
+        assert_eq!(roundtrip(&[0, 0]), [1, 1, 1, 0]);
+        assert_eq!(
+            roundtrip(&[0x11, 0x22, 0, 0x33]),
+            [3, 0x11, 0x22, 2, 0x33, 0]
+        );
+        assert_eq!(
+            roundtrip(&[0x11, 0x22, 0x33, 0x44]),
+            [5, 0x11, 0x22, 0x33, 0x44, 0]
+        );
+        assert_eq!(roundtrip(&[0x11, 0, 0, 0]), [2, 0x11, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_full_blocks() {
+        let full: Vec<u8> = (1..=254).collect();
+        let encoded = roundtrip(&full);
+        assert_eq!(encoded.len(), 256);
+        assert_eq!(encoded[0], 0xFF);
+
+        let leading_zero: Vec<u8> = (0..=254).collect();
+        let encoded = roundtrip(&leading_zero);
+        assert_eq!(&encoded[..2], [1, 0xFF]);
+        assert_eq!(encoded.len(), 257);
+
+        let overflow: Vec<u8> = (1..=255).collect();
+        let encoded = roundtrip(&overflow);
+        assert_eq!(&encoded[254..], [0xFE, 2, 0xFF, 0]);
+    }
+
+    #[test]
+    fn test_overhead_bound() {
+        let input: Vec<u8> = (0..10_000u32)
+            .map(|i| (i % 255 + 1).to_le_bytes()[0])
+            .collect();
+        let encoded = roundtrip(&input);
+        assert!(encoded.len() <= input.len() + input.len().div_ceil(MAX_BLOCK) + 1);
+    }
+
+    #[test]
+    fn test_frames_over_serial_stream() {
+        let lz77 = Lz77::new();
+        let cobs = Cobs::new();
+        let messages: [&[u8]; 3] = [b"temperature=21.5", b"", b"\0\0\0status=ok\0"];
+        let stream: Vec<u8> = messages
+            .iter()
+            .flat_map(|m| cobs.compress(&lz77.compress(m).unwrap()).unwrap())
+            .collect();
+        let frames = cobs.decompress_frames(&stream).unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame, message) in frames.iter().zip(messages) {
+            assert_eq!(lz77.decompress(frame).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_undelimited() {
+        let cobs = Cobs::new().with_delimiter(false);
+        let encoded = cobs.compress(&[0x11, 0, 0x22]).unwrap();
+        assert_eq!(encoded, [2, 0x11, 2, 0x22]);
+        assert_eq!(cobs.decompress(&encoded).unwrap(), [0x11, 0, 0x22]);
+        assert!(matches!(
+            cobs.decompress_partial(&encoded),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let cobs = Cobs::new();
+        // Missing delimiter, block past the frame end, zero inside a block.
+        assert!(cobs.decompress(&[3, 1, 2]).is_err());
+        assert!(cobs.decompress(&[5, 1, 2, 0]).is_err());
+        assert!(
+            Cobs::new()
+                .with_delimiter(false)
+                .decompress(&[3, 1, 0])
+                .is_err()
+        );
+        // Bytes after the delimiter.
+        assert!(cobs.decompress(&[1, 0, 1, 0]).is_err());
+    }
+}
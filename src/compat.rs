@@ -0,0 +1,224 @@
+//! Interoperability checks against reference implementations.
+//!
+//! Enabled with the `compat-tests` feature. Outputs can be verified either
+//! by piping them through an external reference decoder (e.g. `gzip -dc`
+//! once a gzip-compatible codec exists) or against embedded test vectors
+//! such as those published alongside a format's RFC.
+
+use std::io::{self, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor};
+
+/// A known-good compressed payload and the data it must decode to.
+#[derive(Debug, Clone, Copy)]
+pub struct TestVector<'a> {
+    pub name: &'a str,
+    pub compressed: &'a [u8],
+    pub expected: &'a [u8],
+}
+
+/// Decodes every vector with `decoder` and checks the output.
+///
+/// # Errors
+///
+/// Returns `CompressionError::DecompressionError` naming the first vector
+/// that fails to decode or decodes to the wrong bytes.
+pub fn check_vectors<D: Decompressor>(decoder: &D, vectors: &[TestVector<'_>]) -> Result<()> {
+    for vector in vectors {
+        let output = decoder.decompress(vector.compressed).map_err(|e| {
+            CompressionError::DecompressionError(format!("vector {}: {e}", vector.name))
+        })?;
+        if output != vector.expected {
+            return Err(CompressionError::DecompressionError(format!(
+                "vector {}: output mismatch",
+                vector.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Runs `program` with `args`, feeding `input` on stdin and returning stdout.
+///
+/// # Errors
+///
+/// Returns `CompressionError::Io` if the process cannot be run and
+/// `CompressionError::DecompressionError` if it exits unsuccessfully.
+pub fn run_reference(program: &str, args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::new(ErrorKind::BrokenPipe, "reference stdin unavailable"))?;
+    let data = input.to_vec();
+    let feeder = std::thread::spawn(move || stdin.write_all(&data));
+
+    let output = child.wait_with_output()?;
+    feeder
+        .join()
+        .map_err(|_| io::Error::other("stdin writer panicked"))??;
+
+    if !output.status.success() {
+        return Err(CompressionError::DecompressionError(format!(
+            "reference {program} exited with {}",
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Compresses `input` with `codec` and checks that the external reference
+/// decoder reproduces it exactly.
+///
+/// # Errors
+///
+/// Returns any compression or process error, or
+/// `CompressionError::DecompressionError` if the reference output differs.
+pub fn check_reference_decoder<C: Compressor>(
+    codec: &C,
+    program: &str,
+    args: &[&str],
+    input: &[u8],
+) -> Result<()> {
+    let compressed = codec.compress(input)?;
+    let decoded = run_reference(program, args, &compressed)?;
+    if decoded != input {
+        return Err(CompressionError::DecompressionError(format!(
+            "{} output not reproduced by {program}",
+            codec.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Feeds data compressed by an external reference encoder to `codec` and
+/// checks that it decodes back to `input`.
+///
+/// # Errors
+///
+/// Returns any decompression or process error, or
+/// `CompressionError::DecompressionError` if the decoded output differs.
+pub fn check_reference_encoder<D: Decompressor>(
+    codec: &D,
+    program: &str,
+    args: &[&str],
+    input: &[u8],
+) -> Result<()> {
+    let compressed = run_reference(program, args, input)?;
+    let decoded = codec.decompress(&compressed)?;
+    if decoded != input {
+        return Err(CompressionError::DecompressionError(format!(
+            "{} failed to decode {program} output",
+            codec.name()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rle;
+
+    struct Identity;
+
+    impl Compressor for Identity {
+        fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Identity"
+        }
+    }
+
+    impl Decompressor for Identity {
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Identity"
+        }
+    }
+
+    #[test]
+    fn test_check_vectors_pass() {
+        let vectors = [
+            TestVector {
+                name: "run",
+                compressed: &[3, b'a'],
+                expected: b"aaa",
+            },
+            TestVector {
+                name: "empty",
+                compressed: &[0, 0],
+                expected: &[],
+            },
+        ];
+        check_vectors(&Rle::new(), &vectors).unwrap();
+    }
+
+    #[test]
+    fn test_check_vectors_mismatch() {
+        let vectors = [TestVector {
+            name: "wrong",
+            compressed: &[2, b'a'],
+            expected: b"aaa",
+        }];
+        let err = check_vectors(&Rle::new(), &vectors).unwrap_err();
+        assert!(err.to_string().contains("wrong"));
+    }
+
+    #[test]
+    fn test_check_vectors_decode_error() {
+        let vectors = [TestVector {
+            name: "corrupt",
+            compressed: &[0, b'a'],
+            expected: b"",
+        }];
+        let err = check_vectors(&Rle::new(), &vectors).unwrap_err();
+        assert!(err.to_string().contains("corrupt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reference_roundtrip_with_cat() {
+        check_reference_decoder(&Identity, "cat", &[], b"hello world").unwrap();
+        check_reference_encoder(&Identity, "cat", &[], b"hello world").unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reference_decoder_mismatch() {
+        let result = check_reference_decoder(&Rle::new(), "cat", &[], b"hello");
+        assert!(matches!(
+            result,
+            Err(CompressionError::DecompressionError(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reference_failure_exit() {
+        let result = run_reference("false", &[], b"");
+        assert!(matches!(
+            result,
+            Err(CompressionError::DecompressionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_reference_missing_program() {
+        let result = run_reference("definitely-not-a-real-decoder", &[], b"");
+        assert!(matches!(result, Err(CompressionError::Io { .. })));
+    }
+}
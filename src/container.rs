@@ -0,0 +1,854 @@
+//! The crate's self-describing frame format.
+//!
+//! A codec's own output says nothing about which codec wrote it, so a
+//! stored buffer can only be decoded by whoever remembers. A container
+//! frame puts the [`Algorithm`] in front of the payload, behind magic bytes
+//! and a format version, so [`decode_frame`] needs nothing but the bytes.
+//! The header also records the original and payload lengths, letting a
+//! reader size its output or skip the frame without decoding it, and
+//! optionally a checksum of the original data, which decoding verifies.
+//! Inputs above a threshold, 4 MiB by default, are compressed in chunks as
+//! by [`Chunked`], so one-shot encoding of a large buffer neither hits a
+//! codec's frame size limit nor grows its working buffers past a chunk.
+//! Chained chunks, each compressed with the previous ones as history, are
+//! selected per [`Container`] and recorded in the flags.
+//! Layout: `[magic: 4 bytes][version: u8][algorithm: u8][flags: u8]
+//! [original length: u64][payload length: u64][checksum: 4 or 8 bytes, if
+//! flagged][payload]`.
+
+use crate::algorithm::Algorithm;
+use crate::checksum::{Checksum, ChecksumKind};
+use crate::chunked::{
+    self, Chunked, DEFAULT_CHUNK_SIZE, DEFAULT_THRESHOLD, MODE_CHAINED, MODE_CHUNKED,
+};
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::limits::DecompressionLimits;
+use crate::rle::Rle;
+use crate::search::{search, search_chunks};
+use crate::traits::{Codec, Compressor, Decompressor, PartialDecompressor};
+
+/// The first bytes of every frame. The high bit of the first catches
+/// transfers that strip bit 7.
+pub const MAGIC: [u8; 4] = [0x89, b'C', b'L', b'F'];
+/// The version [`encode_frame`] writes and the only one read back.
+pub const VERSION: u8 = 1;
+/// Length of the header without a checksum.
+pub const HEADER_LEN: usize = 23;
+/// Flag bits holding the [`ChecksumKind::tag`] of the checksum that
+/// follows the lengths, or 0 for none.
+pub const CHECKSUM_MASK: u8 = 0x03;
+/// Flag set when the payload is a [`Chunked`] frame of the algorithm's
+/// output rather than the output itself.
+pub const FLAG_CHUNKED: u8 = 0x04;
+/// Flag set, along with [`FLAG_CHUNKED`], when each chunk was compressed
+/// with the preceding input as history, so chunks decode only in order.
+pub const FLAG_CHAINED: u8 = 0x08;
+/// Flag bits this version defines; frames with any other bit set are
+/// refused.
+pub const KNOWN_FLAGS: u8 = CHECKSUM_MASK | FLAG_CHUNKED | FLAG_CHAINED;
+
+/// The fields in front of a frame's payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub algorithm: Algorithm,
+    pub flags: u8,
+    /// Length of the data before compression.
+    pub original_len: u64,
+    /// Length of the payload following the header.
+    pub payload_len: u64,
+    /// Checksum of the data before compression and its digest, present
+    /// when the [`CHECKSUM_MASK`] bits are set.
+    pub checksum: Option<(ChecksumKind, u64)>,
+}
+
+impl FrameHeader {
+    /// Reads the header at the start of `frame`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if `frame` is shorter than
+    /// its header, or `CompressionError::InvalidHeader` if the magic bytes,
+    /// version, algorithm or flags are not ones this version writes, or
+    /// [`FLAG_CHAINED`] is set without [`FLAG_CHUNKED`].
+    pub fn read(frame: &[u8]) -> Result<Self> {
+        let header = frame
+            .first_chunk::<HEADER_LEN>()
+            .ok_or(CompressionError::CorruptedData)?;
+        if header[..4] != MAGIC || header[4] != VERSION || header[6] & !KNOWN_FLAGS != 0 {
+            return Err(CompressionError::InvalidHeader);
+        }
+        let algorithm = Algorithm::from_tag(header[5]).ok_or(CompressionError::InvalidHeader)?;
+        let read_u64 = |at: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&header[at..at + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let flags = header[6];
+        if flags & (FLAG_CHUNKED | FLAG_CHAINED) == FLAG_CHAINED {
+            return Err(CompressionError::InvalidHeader);
+        }
+        let checksum = match ChecksumKind::from_tag(flags & CHECKSUM_MASK) {
+            None => None,
+            Some(kind) => {
+                let bytes = frame
+                    .get(HEADER_LEN..HEADER_LEN + kind.digest_len())
+                    .ok_or(CompressionError::CorruptedData)?;
+                let mut digest = [0; 8];
+                digest[..bytes.len()].copy_from_slice(bytes);
+                Some((kind, u64::from_le_bytes(digest)))
+            }
+        };
+        Ok(Self {
+            algorithm,
+            flags,
+            original_len: read_u64(7),
+            payload_len: read_u64(15),
+            checksum,
+        })
+    }
+
+    /// Length of this header in bytes, checksum included.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        match self.checksum {
+            Some((kind, _)) => HEADER_LEN + kind.digest_len(),
+            None => HEADER_LEN,
+        }
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        let tag = self.checksum.map_or(0, |(kind, _)| kind.tag());
+        let flags = (self.flags & !CHECKSUM_MASK) | tag;
+        output.extend_from_slice(&MAGIC);
+        output.extend_from_slice(&[VERSION, self.algorithm.tag(), flags]);
+        output.extend_from_slice(&self.original_len.to_le_bytes());
+        output.extend_from_slice(&self.payload_len.to_le_bytes());
+        if let Some((kind, digest)) = self.checksum {
+            output.extend_from_slice(&digest.to_le_bytes()[..kind.digest_len()]);
+        }
+    }
+}
+
+/// Compresses `input` with `algorithm`'s default settings and wraps the
+/// result in a frame without a checksum, chunking inputs over 4 MiB.
+///
+/// # Errors
+///
+/// Returns any error from the codec.
+pub fn encode_frame(algorithm: Algorithm, input: &[u8]) -> Result<Vec<u8>> {
+    Container::new(algorithm).compress(input)
+}
+
+/// [`encode_frame`] with a CRC-32 of `input` in the header, so decoding
+/// catches payload damage that still decodes to the right length.
+///
+/// # Errors
+///
+/// Returns any error from the codec.
+pub fn encode_frame_checked(algorithm: Algorithm, input: &[u8]) -> Result<Vec<u8>> {
+    encode_frame_with_checksum(algorithm, Some(ChecksumKind::Crc32), input)
+}
+
+/// [`encode_frame`] with the `checksum` of `input` in the header, if any.
+///
+/// # Errors
+///
+/// Returns any error from the codec.
+pub fn encode_frame_with_checksum(
+    algorithm: Algorithm,
+    checksum: Option<ChecksumKind>,
+    input: &[u8],
+) -> Result<Vec<u8>> {
+    Container::new(algorithm)
+        .with_checksum(checksum)
+        .compress(input)
+}
+
+fn encode(container: &Container, input: &[u8]) -> Result<Vec<u8>> {
+    let codec = container.algorithm.new_codec_with_level(container.level);
+    let (payload, flags) = if input.len() > container.chunk_threshold {
+        let chunked =
+            Chunked::with_threshold(codec, container.chunk_threshold, container.chunk_size)
+                .with_chained(container.chained);
+        let chained = if container.chained { FLAG_CHAINED } else { 0 };
+        (chunked.compress(input)?, FLAG_CHUNKED | chained)
+    } else {
+        (codec.compress(input)?, 0)
+    };
+    let header = FrameHeader {
+        algorithm: container.algorithm,
+        flags,
+        original_len: input.len() as u64,
+        payload_len: payload.len() as u64,
+        checksum: container.checksum.map(|kind| (kind, kind.digest(input))),
+    };
+    let mut output = Vec::with_capacity(header.encoded_len() + payload.len());
+    header.write(&mut output);
+    output.extend_from_slice(&payload);
+    Ok(output)
+}
+
+/// Decodes a frame that must fill `frame` exactly, with whichever
+/// algorithm its header names.
+///
+/// # Errors
+///
+/// Returns `CompressionError::TrailingData` if bytes follow the frame,
+/// `CompressionError::CorruptedData` if it is truncated, or its payload
+/// records or decodes to a length other than the header's,
+/// `CompressionError::ChecksumMismatch` if it has a checksum and the
+/// decoded data does not match it, the errors of [`FrameHeader::read`], or
+/// any error from the codec.
+pub fn decode_frame(frame: &[u8]) -> Result<Vec<u8>> {
+    decode(frame, &DecompressionLimits::new())
+}
+
+fn decode(frame: &[u8], limits: &DecompressionLimits) -> Result<Vec<u8>> {
+    let (output, consumed) = decode_partial(frame, limits)?;
+    if consumed < frame.len() {
+        return Err(CompressionError::TrailingData(frame.len() - consumed));
+    }
+    Ok(output)
+}
+
+/// Finds every occurrence of `pattern` in the data of an [`Algorithm::Rle`]
+/// frame without decompressing it.
+///
+/// This is [`search`](crate::search()) on the frame's payload, or
+/// [`search_chunks`] over the chunk table of a chunked one. The recorded
+/// length and checksum are not checked, since that would take decoding it.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `pattern` is empty or the
+/// frame holds another algorithm's output,
+/// `CompressionError::TrailingData` if bytes follow the frame,
+/// `CompressionError::CorruptedData` if it is truncated or its payload is
+/// not valid RLE data, or the errors of [`FrameHeader::read`].
+pub fn search_frame(pattern: &[u8], frame: &[u8]) -> Result<Vec<usize>> {
+    let header = FrameHeader::read(frame)?;
+    if header.algorithm != Algorithm::Rle {
+        return Err(CompressionError::InvalidInput(format!(
+            "cannot search {} frames",
+            header.algorithm
+        )));
+    }
+    let payload = usize::try_from(header.payload_len)
+        .ok()
+        .and_then(|len| frame[header.encoded_len()..].get(..len))
+        .ok_or(CompressionError::CorruptedData)?;
+    let consumed = header.encoded_len() + payload.len();
+    if consumed < frame.len() {
+        return Err(CompressionError::TrailingData(frame.len() - consumed));
+    }
+    if header.flags & FLAG_CHUNKED == 0 {
+        return search(pattern, payload, Rle::new());
+    }
+    let chunks = chunked::chunk_payloads(payload).map_err(|_| CompressionError::CorruptedData)?;
+    search_chunks(pattern, chunks, Rle::new())
+}
+
+/// Decodes the frame at the start of `input` within `limits`, returning
+/// its data and its length in bytes.
+///
+/// The recorded length is checked against `limits` before anything is
+/// decoded, and the payload, each of its chunks included, decodes under
+/// what the limits allow from the whole frame.
+fn decode_partial(input: &[u8], limits: &DecompressionLimits) -> Result<(Vec<u8>, usize)> {
+    let header = FrameHeader::read(input)?;
+    let payload = usize::try_from(header.payload_len)
+        .ok()
+        .and_then(|len| input[header.encoded_len()..].get(..len))
+        .ok_or(CompressionError::CorruptedData)?;
+    let frame_len = (header.encoded_len() + payload.len()) as u64;
+    limits.check(frame_len, header.original_len)?;
+    let codec = header.algorithm.new_codec();
+    let codec: Box<dyn Codec> = if header.flags & FLAG_CHUNKED == 0 {
+        codec
+    } else {
+        let mode = if header.flags & FLAG_CHAINED == 0 {
+            MODE_CHUNKED
+        } else {
+            MODE_CHAINED
+        };
+        if payload.first() != Some(&mode) {
+            return Err(CompressionError::CorruptedData);
+        }
+        Box::new(Chunked::new(codec))
+    };
+    // A payload recording its own length must agree with the header before
+    // any output is allocated for it.
+    if codec
+        .decompressed_len(payload)?
+        .is_some_and(|len| len != header.original_len)
+    {
+        return Err(CompressionError::CorruptedData);
+    }
+    let output = codec.decompress_with_limits(
+        payload,
+        &DecompressionLimits::new().with_max_output_size(limits.max_output_for(frame_len)),
+    )?;
+    if output.len() as u64 != header.original_len {
+        return Err(CompressionError::CorruptedData);
+    }
+    if header
+        .checksum
+        .is_some_and(|(kind, digest)| kind.digest(&output) != digest)
+    {
+        return Err(CompressionError::ChecksumMismatch);
+    }
+    Ok((output, header.encoded_len() + payload.len()))
+}
+
+/// Codec writing container frames with one [`Algorithm`], for code that
+/// takes a [`Codec`].
+///
+/// Decompression follows each frame's header, whatever algorithm the
+/// instance writes, and verifies the checksum of frames that have one,
+/// whichever kind it is. Output layout: as [`encode_frame_with_checksum`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Container {
+    algorithm: Algorithm,
+    checksum: Option<ChecksumKind>,
+    level: CompressionLevel,
+    chunk_threshold: usize,
+    chunk_size: usize,
+    chained: bool,
+}
+
+impl Container {
+    #[must_use]
+    pub const fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            checksum: None,
+            level: CompressionLevel::DEFAULT,
+            chunk_threshold: DEFAULT_THRESHOLD,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chained: false,
+        }
+    }
+
+    /// Compresses inputs larger than `threshold` bytes in `chunk_size`
+    /// chunks, setting [`FLAG_CHUNKED`]. `usize::MAX` never chunks.
+    #[must_use]
+    pub const fn with_chunking(mut self, threshold: usize, chunk_size: usize) -> Self {
+        self.chunk_threshold = threshold;
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Compresses each chunk with the input before it as history, setting
+    /// [`FLAG_CHAINED`]: better ratio for chunks that decode only in order.
+    /// The algorithm must carry history, as [`Algorithm::Lz77`] does.
+    #[must_use]
+    pub const fn with_chained(mut self, chained: bool) -> Self {
+        self.chained = chained;
+        self
+    }
+
+    /// The checksum of the input to store in each frame, if any.
+    #[must_use]
+    pub const fn with_checksum(mut self, checksum: Option<ChecksumKind>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    #[must_use]
+    pub const fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    #[must_use]
+    pub const fn checksum(&self) -> Option<ChecksumKind> {
+        self.checksum
+    }
+
+    /// The level the algorithm compresses at, set through
+    /// [`Compressor::with_level`].
+    #[must_use]
+    pub const fn level(&self) -> CompressionLevel {
+        self.level
+    }
+
+    #[must_use]
+    pub const fn chunk_threshold(&self) -> usize {
+        self.chunk_threshold
+    }
+
+    #[must_use]
+    pub const fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    #[must_use]
+    pub const fn chained(&self) -> bool {
+        self.chained
+    }
+}
+
+impl Compressor for Container {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        encode(self, input)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let codec = self.algorithm.new_codec_with_level(self.level);
+        let payload = if input_len > self.chunk_threshold {
+            Chunked::with_threshold(codec, self.chunk_threshold, self.chunk_size)
+                .max_compressed_len(input_len)
+        } else {
+            codec.max_compressed_len(input_len)
+        };
+        payload.saturating_add(HEADER_LEN + 8)
+    }
+
+    /// Compresses with the algorithm tuned to `level`, as by
+    /// [`Algorithm::new_codec_with_level`]; decoding is unaffected.
+    fn with_level(mut self, level: CompressionLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        "Container"
+    }
+}
+
+impl Decompressor for Container {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        decode_frame(input)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        Ok(Some(FrameHeader::read(input)?.original_len))
+    }
+
+    /// The header's length is checked against `limits` first, and the
+    /// payload then decodes under them too, so a payload that expands past
+    /// what its header claims is stopped as well.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        decode(input, limits)
+    }
+
+    fn name(&self) -> &'static str {
+        "Container"
+    }
+}
+
+impl PartialDecompressor for Container {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        decode_partial(input, &DecompressionLimits::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Fax, Rle2d};
+    use crate::varint;
+
+    const TEXT: &[u8] = b"frames that say what wrote them, frames that say what wrote them";
+
+    #[test]
+    fn test_container_name() {
+        let container = Container::new(Algorithm::Lz4);
+        assert_eq!(Compressor::name(&container), "Container");
+        assert_eq!(Decompressor::name(&container), "Container");
+    }
+
+    #[test]
+    fn test_roundtrip_every_algorithm() {
+        for algorithm in Algorithm::ALL {
+            let frame = encode_frame(algorithm, TEXT).unwrap();
+            let header = FrameHeader::read(&frame).unwrap();
+            assert_eq!(header.algorithm, algorithm);
+            assert_eq!(header.original_len, TEXT.len() as u64);
+            assert_eq!(header.payload_len, (frame.len() - HEADER_LEN) as u64);
+            assert_eq!(decode_frame(&frame).unwrap(), TEXT, "{algorithm}");
+        }
+        let empty = encode_frame(Algorithm::Huffman, b"").unwrap();
+        assert!(decode_frame(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_frame() {
+        let text = b"ERROR ERROR  warn  ERRORR";
+        let frame = encode_frame_checked(Algorithm::Rle, text).unwrap();
+        assert_eq!(search_frame(b"RR", &frame).unwrap(), [1, 7, 20, 23]);
+        assert_eq!(search_frame(b"  w", &frame).unwrap(), [11]);
+
+        let mut trailing = frame.clone();
+        trailing.push(0);
+        assert!(matches!(
+            search_frame(b"RR", &trailing),
+            Err(CompressionError::TrailingData(1))
+        ));
+        assert!(matches!(
+            search_frame(b"RR", &frame[..frame.len() - 1]),
+            Err(CompressionError::CorruptedData)
+        ));
+        for algorithm in [Algorithm::Lz77, Algorithm::Stored] {
+            let frame = encode_frame(algorithm, text).unwrap();
+            assert!(matches!(
+                search_frame(b"RR", &frame),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_with_level() {
+        let input = TEXT.repeat(20);
+        let container = Container::new(Algorithm::Lzma);
+        assert_eq!(container.level(), CompressionLevel::DEFAULT);
+        let fastest = container.with_level(CompressionLevel::FASTEST);
+        assert_eq!(fastest.level(), CompressionLevel::FASTEST);
+        let frame = fastest.compress(&input).unwrap();
+        let payload = Algorithm::Lzma
+            .new_codec_with_level(CompressionLevel::FASTEST)
+            .compress(&input)
+            .unwrap();
+        assert_eq!(frame[HEADER_LEN..], payload);
+        assert_ne!(frame, container.compress(&input).unwrap());
+        assert_eq!(decode_frame(&frame).unwrap(), input);
+    }
+
+    #[test]
+    fn test_chunks_large_inputs() {
+        let container = Container::new(Algorithm::Lz77).with_chunking(1000, 256);
+        assert_eq!(
+            (container.chunk_threshold(), container.chunk_size()),
+            (1000, 256)
+        );
+        let input = TEXT.repeat(40);
+        let frame = container.compress(&input).unwrap();
+        let header = FrameHeader::read(&frame).unwrap();
+        assert_eq!(header.flags, FLAG_CHUNKED);
+        assert_eq!(header.original_len, input.len() as u64);
+        assert!(frame.len() <= container.max_compressed_len(input.len()));
+        assert_eq!(decode_frame(&frame).unwrap(), input);
+        assert_eq!(
+            Container::new(Algorithm::Rle).decompress(&frame).unwrap(),
+            input
+        );
+
+        let small = container.compress(TEXT).unwrap();
+        assert_eq!(FrameHeader::read(&small).unwrap().flags, 0);
+        let never = container.with_chunking(usize::MAX, 256);
+        assert_eq!(
+            FrameHeader::read(&never.compress(&input).unwrap())
+                .unwrap()
+                .flags,
+            0
+        );
+        let rle = Container::new(Algorithm::Rle).with_chunking(1000, 90);
+        let frame = rle.compress(&input).unwrap();
+        assert_eq!(FrameHeader::read(&frame).unwrap().flags, FLAG_CHUNKED);
+        let expected: Vec<usize> = input
+            .windows(6)
+            .enumerate()
+            .filter(|&(_, window)| window == b"frames")
+            .map(|(i, _)| i)
+            .collect();
+        assert!(expected.iter().any(|i| i / 90 != (i + 5) / 90));
+        assert_eq!(search_frame(b"frames", &frame).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_chained_chunks() {
+        let input = TEXT.repeat(40);
+        let chunked = Container::new(Algorithm::Lz77).with_chunking(1000, 256);
+        let chained = chunked.with_chained(true);
+        assert!(chained.chained() && !chunked.chained());
+        let frame = chained.compress(&input).unwrap();
+        assert_eq!(
+            FrameHeader::read(&frame).unwrap().flags,
+            FLAG_CHUNKED | FLAG_CHAINED
+        );
+        assert_eq!(decode_frame(&frame).unwrap(), input);
+        assert!(frame.len() < chunked.compress(&input).unwrap().len());
+
+        // Below the threshold there are no chunks to chain.
+        let small = chained.compress(TEXT).unwrap();
+        assert_eq!(FrameHeader::read(&small).unwrap().flags, 0);
+        assert!(
+            Container::new(Algorithm::Huffman)
+                .with_chunking(1000, 256)
+                .with_chained(true)
+                .compress(&input)
+                .is_err()
+        );
+
+        // The flag must agree with the payload's mode.
+        let mut unflagged = frame.clone();
+        unflagged[6] &= !FLAG_CHAINED;
+        assert!(matches!(
+            decode_frame(&unflagged),
+            Err(CompressionError::CorruptedData)
+        ));
+        let mut unchunked = frame;
+        unchunked[6] &= !FLAG_CHUNKED;
+        assert!(matches!(
+            decode_frame(&unchunked),
+            Err(CompressionError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_chunks_above_default_threshold() {
+        let input = vec![7; DEFAULT_THRESHOLD + 1];
+        let frame = encode_frame_checked(Algorithm::Stored, &input).unwrap();
+        assert_eq!(
+            FrameHeader::read(&frame).unwrap().flags & FLAG_CHUNKED,
+            FLAG_CHUNKED
+        );
+        assert_eq!(decode_frame(&frame).unwrap(), input);
+        let frame = encode_frame(Algorithm::Stored, &input[1..]).unwrap();
+        assert_eq!(FrameHeader::read(&frame).unwrap().flags, 0);
+    }
+
+    /// Wraps a payload from a configured image codec in a frame.
+    fn image_frame(algorithm: Algorithm, codec: &dyn Codec, image: &[u8]) -> Vec<u8> {
+        let payload = codec.compress(image).unwrap();
+        let header = FrameHeader {
+            algorithm,
+            flags: 0,
+            original_len: image.len() as u64,
+            payload_len: payload.len() as u64,
+            checksum: Some((ChecksumKind::Crc32, ChecksumKind::Crc32.digest(image))),
+        };
+        let mut frame = Vec::new();
+        header.write(&mut frame);
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    #[test]
+    fn test_roundtrip_image_algorithms() {
+        // A 13x4 1-bit image and a 4x3 RGBA one, two pixels repeated.
+        let bitmap = [0xA5, 0x50, 0x5A, 0xA8, 0xA5, 0x50, 0xFF, 0xF8];
+        let rgba: Vec<u8> = [[9, 8, 7, 255], [1, 2, 3, 255]]
+            .iter()
+            .flat_map(|pixel| pixel.repeat(6))
+            .collect();
+        for (algorithm, image) in [(Algorithm::Fax, &bitmap[..]), (Algorithm::Rle2d, &rgba)] {
+            let frame = encode_frame_checked(algorithm, image).unwrap();
+            assert_eq!(FrameHeader::read(&frame).unwrap().algorithm, algorithm);
+            assert_eq!(decode_frame(&frame).unwrap(), image, "{algorithm}");
+        }
+
+        // Frames keep the shape, so any configuration decodes.
+        let fax = image_frame(Algorithm::Fax, &Fax::new(13, 4), &bitmap);
+        assert_eq!(decode_frame(&fax).unwrap(), bitmap);
+        let rle2d = Rle2d::new(16).with_pixel_size(4);
+        let frame = image_frame(Algorithm::Rle2d, &rle2d, &rgba);
+        assert_eq!(decode_frame(&frame).unwrap(), rgba);
+        assert!(frame.len() < encode_frame_checked(Algorithm::Rle2d, &rgba).unwrap().len());
+    }
+
+    #[test]
+    fn test_layout() {
+        let frame = encode_frame(Algorithm::Stored, b"abc").unwrap();
+        assert_eq!(frame[..4], MAGIC);
+        assert_eq!(frame[4..7], [VERSION, Algorithm::Stored.tag(), 0]);
+        assert_eq!(frame[7..15], 3u64.to_le_bytes());
+        let payload = &frame[HEADER_LEN..];
+        assert_eq!(frame[15..23], (payload.len() as u64).to_le_bytes());
+        assert_eq!(
+            payload,
+            Algorithm::Stored.new_codec().compress(b"abc").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_codec() {
+        let container = Container::new(Algorithm::Lz77);
+        assert_eq!(container.algorithm(), Algorithm::Lz77);
+        let frame = container.compress(TEXT).unwrap();
+        assert!(frame.len() <= container.max_compressed_len(TEXT.len()));
+        // Any instance reads any algorithm.
+        let other = Container::new(Algorithm::Stored);
+        assert_eq!(other.decompress(&frame).unwrap(), TEXT);
+        assert_eq!(
+            other.decompressed_len(&frame).unwrap(),
+            Some(TEXT.len() as u64)
+        );
+
+        let mut stream = frame.clone();
+        stream.extend(
+            Container::new(Algorithm::Huffman)
+                .compress(b"next")
+                .unwrap(),
+        );
+        assert_eq!(
+            other.decompress_frames(&stream).unwrap(),
+            [TEXT.to_vec(), b"next".to_vec()]
+        );
+        assert_eq!(
+            decode_frame(&stream),
+            Err(CompressionError::TrailingData(stream.len() - frame.len()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_frames() {
+        let frame = encode_frame(Algorithm::Lz4, TEXT).unwrap();
+        assert_eq!(
+            decode_frame(&frame[..HEADER_LEN - 1]),
+            Err(CompressionError::CorruptedData)
+        );
+        assert_eq!(
+            decode_frame(&frame[..frame.len() - 1]),
+            Err(CompressionError::CorruptedData)
+        );
+        for (at, value) in [(0, b'x'), (4, VERSION + 1), (5, 200), (6, 0x80)] {
+            let mut bad = frame.clone();
+            bad[at] = value;
+            assert_eq!(
+                decode_frame(&bad),
+                Err(CompressionError::InvalidHeader),
+                "{at}"
+            );
+        }
+        // A recorded length that disagrees with the payload.
+        let mut bad = frame;
+        bad[7] ^= 1;
+        assert_eq!(decode_frame(&bad), Err(CompressionError::CorruptedData));
+    }
+
+    /// A frame around `payload` whose header records `original_len`.
+    fn raw_frame(algorithm: Algorithm, original_len: u64, payload: &[u8]) -> Vec<u8> {
+        let header = FrameHeader {
+            algorithm,
+            flags: 0,
+            original_len,
+            payload_len: payload.len() as u64,
+            checksum: None,
+        };
+        let mut frame = Vec::new();
+        header.write(&mut frame);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_payload_length_checked_before_decoding() {
+        // A ZeroRun payload of 8 GiB of zeros behind a header claiming 10
+        // bytes is refused without allocating its output.
+        let mut payload = Vec::new();
+        varint::write_u64(&mut payload, 8 << 30);
+        varint::write_u64(&mut payload, 8 << 30);
+        varint::write_u64(&mut payload, 0);
+        let frame = raw_frame(Algorithm::ZeroRun, 10, &payload);
+        assert_eq!(decode_frame(&frame), Err(CompressionError::CorruptedData));
+        assert_eq!(
+            Container::new(Algorithm::ZeroRun).decompress_partial(&frame),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+
+    #[test]
+    fn test_decompress_with_limits() {
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        let container = Container::new(Algorithm::ZeroRun);
+        // 8 GiB of zeros, recorded in the header too, is refused from the
+        // header alone.
+        let mut payload = Vec::new();
+        varint::write_u64(&mut payload, 8 << 30);
+        varint::write_u64(&mut payload, 8 << 30);
+        varint::write_u64(&mut payload, 0);
+        let frame = raw_frame(Algorithm::ZeroRun, 8 << 30, &payload);
+        assert!(matches!(
+            container.decompress_with_limits(&frame, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        // Behind a header claiming 10 bytes it is refused as corrupt.
+        let frame = raw_frame(Algorithm::ZeroRun, 10, &payload);
+        assert_eq!(
+            container.decompress_with_limits(&frame, &limits),
+            Err(CompressionError::CorruptedData)
+        );
+
+        // Pair-format RLE records no length, so a payload expanding past
+        // its header is held to the limits as it decodes, whole or in
+        // chunks.
+        let input = vec![0; 4096];
+        let tight = DecompressionLimits::new().with_max_output_size(1000);
+        for chunking in [usize::MAX, 1024] {
+            let rle = Container::new(Algorithm::Rle).with_chunking(chunking, 256);
+            let mut frame = rle.compress(&input).unwrap();
+            assert_eq!(rle.decompress_with_limits(&frame, &limits).unwrap(), input);
+            frame[7..15].copy_from_slice(&10u64.to_le_bytes());
+            assert!(matches!(
+                rle.decompress_with_limits(&frame, &tight),
+                Err(CompressionError::LimitExceeded(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        let frame = encode_frame_checked(Algorithm::Huffman, TEXT).unwrap();
+        let header = FrameHeader::read(&frame).unwrap();
+        assert_eq!(header.flags, ChecksumKind::Crc32.tag());
+        assert_eq!(
+            header.checksum,
+            Some((ChecksumKind::Crc32, u64::from(crate::crc32(TEXT))))
+        );
+        assert_eq!(header.encoded_len(), HEADER_LEN + 4);
+        assert_eq!(decode_frame(&frame).unwrap(), TEXT);
+        assert_eq!(
+            Container::new(Algorithm::Huffman)
+                .with_checksum(Some(ChecksumKind::Crc32))
+                .compress(TEXT)
+                .unwrap(),
+            frame
+        );
+
+        // A damaged checksum, or damaged data of the right length.
+        let mut bad = frame;
+        bad[HEADER_LEN] ^= 1;
+        assert_eq!(decode_frame(&bad), Err(CompressionError::ChecksumMismatch));
+        let stored = encode_frame_checked(Algorithm::Stored, TEXT).unwrap();
+        let mut bad = stored.clone();
+        let last = bad.len() - 1;
+        bad[last] ^= 0x20;
+        assert_eq!(decode_frame(&bad), Err(CompressionError::ChecksumMismatch));
+        assert_eq!(
+            decode_frame(&stored[..HEADER_LEN + 2]),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+
+    #[test]
+    fn test_checksum_kinds() {
+        for kind in ChecksumKind::ALL {
+            let container = Container::new(Algorithm::Lz4).with_checksum(Some(kind));
+            assert_eq!(container.checksum(), Some(kind));
+            let frame = container.compress(TEXT).unwrap();
+            assert!(frame.len() <= container.max_compressed_len(TEXT.len()));
+            let header = FrameHeader::read(&frame).unwrap();
+            assert_eq!(header.checksum, Some((kind, kind.digest(TEXT))));
+            assert_eq!(header.encoded_len(), HEADER_LEN + kind.digest_len());
+            assert_eq!(
+                encode_frame_with_checksum(Algorithm::Lz4, Some(kind), TEXT).unwrap(),
+                frame
+            );
+            assert_eq!(decode_frame(&frame).unwrap(), TEXT, "{}", kind.name());
+
+            let mut bad = frame;
+            bad[HEADER_LEN + kind.digest_len() - 1] ^= 0x40;
+            assert_eq!(decode_frame(&bad), Err(CompressionError::ChecksumMismatch));
+        }
+        assert_eq!(
+            encode_frame_with_checksum(Algorithm::Lz4, None, TEXT).unwrap(),
+            encode_frame(Algorithm::Lz4, TEXT).unwrap()
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Small standard test files embedded in the crate.
+//!
+//! Enabled with the `corpus` feature. The files follow the makeup of the
+//! Canterbury corpus — prose, source code, markup, a manual page, a table,
+//! sampled audio and a fax-like bilevel scan — trimmed to a few kilobytes
+//! each so benchmarks and ratio checks run without network access or
+//! external downloads. They are original stand-ins rather than copies of
+//! the Canterbury files, so results are comparable between runs of this
+//! crate but not with published Canterbury figures.
+
+/// One embedded test file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorpusFile {
+    /// File name, unique within the corpus.
+    pub name: &'static str,
+    /// The kind of data, following the Canterbury categories.
+    pub kind: &'static str,
+    pub data: &'static [u8],
+}
+
+const STANDARD: &[CorpusFile] = &[
+    CorpusFile {
+        name: "text.txt",
+        kind: "English text",
+        data: include_bytes!("../corpus/text.txt"),
+    },
+    CorpusFile {
+        name: "source.c",
+        kind: "C source",
+        data: include_bytes!("../corpus/source.c"),
+    },
+    CorpusFile {
+        name: "page.html",
+        kind: "HTML",
+        data: include_bytes!("../corpus/page.html"),
+    },
+    CorpusFile {
+        name: "manual.1",
+        kind: "GNU manual page",
+        data: include_bytes!("../corpus/manual.1"),
+    },
+    CorpusFile {
+        name: "table.csv",
+        kind: "Spreadsheet",
+        data: include_bytes!("../corpus/table.csv"),
+    },
+    CorpusFile {
+        name: "samples.bin",
+        kind: "16-bit audio",
+        data: include_bytes!("../corpus/samples.bin"),
+    },
+    CorpusFile {
+        name: "scan.pbm",
+        kind: "Fax image",
+        data: include_bytes!("../corpus/scan.pbm"),
+    },
+];
+
+/// The standard corpus, in a fixed order.
+#[must_use]
+pub const fn standard() -> &'static [CorpusFile] {
+    STANDARD
+}
+
+/// Looks up a standard corpus file by name.
+#[must_use]
+pub fn file(name: &str) -> Option<&'static CorpusFile> {
+    STANDARD.iter().find(|file| file.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_files() {
+        let files = standard();
+        assert_eq!(files.len(), 7);
+        for (i, file) in files.iter().enumerate() {
+            assert!((1024..=8192).contains(&file.data.len()), "{}", file.name);
+            assert!(files[..i].iter().all(|other| other.name != file.name));
+        }
+        assert!(
+            file("text.txt")
+                .unwrap()
+                .data
+                .starts_with(b"The lighthouse")
+        );
+        assert!(file("scan.pbm").unwrap().data.starts_with(b"P4\n"));
+        assert_eq!(file("alice29.txt"), None);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, Transform};
+
+const DEFAULT_STRIDE: u8 = 1;
+
+/// Reversible delta filter.
+///
+/// Replaces each byte with its wrapping difference from the byte `stride`
+/// positions earlier, so slowly varying samples become runs of small values
+/// that RLE and Huffman compress well. Set `stride` to the element width
+/// times the channel count for interleaved data, e.g. 4 for 16-bit stereo
+/// audio. Output layout: `[stride: u8][deltas]`; run it before the main codec.
+/// As a [`Transform`] it writes the deltas alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta {
+    stride: u8,
+}
+
+impl Default for Delta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Delta {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_stride(DEFAULT_STRIDE)
+    }
+
+    /// Creates a filter differencing bytes `stride` positions apart.
+    #[must_use]
+    pub const fn with_stride(stride: u8) -> Self {
+        Self { stride }
+    }
+
+    #[must_use]
+    pub const fn stride(&self) -> u8 {
+        self.stride
+    }
+
+    fn checked_stride(self) -> Result<usize> {
+        if self.stride == 0 {
+            return Err(CompressionError::InvalidInput(
+                "delta stride must be non-zero".to_string(),
+            ));
+        }
+        Ok(usize::from(self.stride))
+    }
+}
+
+impl Transform for Delta {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let stride = self.checked_stride()?;
+        let mut output = Vec::with_capacity(input.len());
+        output.extend_from_slice(&input[..stride.min(input.len())]);
+        output.extend(
+            input
+                .iter()
+                .zip(&input[stride.min(input.len())..])
+                .map(|(&previous, &current)| current.wrapping_sub(previous)),
+        );
+        Ok(output)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let stride = self.checked_stride()?;
+        let mut output = input.to_vec();
+        for i in stride..output.len() {
+            output[i] = output[i].wrapping_add(output[i - stride]);
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Delta"
+    }
+}
+
+impl Compressor for Delta {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let deltas = self.forward(input)?;
+        let mut output = Vec::with_capacity(deltas.len() + 1);
+        output.push(self.stride);
+        output.extend_from_slice(&deltas);
+        Ok(output)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        input_len.saturating_add(1)
+    }
+
+    fn name(&self) -> &'static str {
+        "Delta"
+    }
+}
+
+impl Decompressor for Delta {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        if input[0] == 0 {
+            return Err(CompressionError::InvalidHeader);
+        }
+        Self::with_stride(input[0]).inverse(&input[1..])
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match input.split_first() {
+            None => Err(CompressionError::CorruptedData),
+            Some((0, _)) => Err(CompressionError::InvalidHeader),
+            Some((_, body)) => Ok(Some(body.len() as u64)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Delta"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Rle};
+
+    fn roundtrip(delta: Delta, input: &[u8]) -> Vec<u8> {
+        let filtered = delta.compress(input).unwrap();
+        assert_eq!(delta.decompress(&filtered).unwrap(), input);
+        filtered
+    }
+
+    #[test]
+    fn test_delta_new() {
+        let delta = Delta::new();
+        assert_eq!(delta.stride(), 1);
+        assert_eq!(Delta::default(), delta);
+        assert_eq!(Compressor::name(&delta), "Delta");
+        assert_eq!(Decompressor::name(&delta), "Delta");
+    }
+
+    #[test]
+    fn test_empty() {
+        let delta = Delta::new();
+        assert_eq!(roundtrip(delta, &[]), [delta.stride()]);
+        assert!(matches!(
+            delta.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output() {
+        let filtered = Delta::new().compress(&[10, 12, 15, 15, 5]).unwrap();
+        assert_eq!(filtered, [1, 10, 2, 3, 0, 0xF6]);
+    }
+
+    #[test]
+    fn test_transform_omits_header() {
+        let delta = Delta::with_stride(2);
+        let input = [10, 12, 15, 15, 5];
+        let deltas = delta.forward(&input).unwrap();
+        assert_eq!(deltas, [10, 12, 5, 3, 0xF6]);
+        assert_eq!(delta.compress(&input).unwrap()[1..], deltas);
+        assert_eq!(delta.inverse(&deltas).unwrap(), input);
+        assert_eq!(Transform::name(&delta), "Delta");
+        for result in [
+            Delta::with_stride(0).forward(&input),
+            Delta::with_stride(0).inverse(&input),
+        ] {
+            assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_ramp() {
+        let input: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        roundtrip(Delta::new(), &input);
+    }
+
+    #[test]
+    fn test_roundtrip_shorter_than_stride() {
+        roundtrip(Delta::with_stride(8), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_interleaved_stride() {
+        // Two channels, each a ramp with its own step.
+        let input: Vec<u8> = (0..100u8).flat_map(|i| [i, i.wrapping_mul(3)]).collect();
+        let filtered = roundtrip(Delta::with_stride(2), &input);
+        assert!(filtered[3..].chunks(2).all(|pair| pair == [1, 3]));
+    }
+
+    #[test]
+    fn test_decompress_uses_stored_stride() {
+        let input: Vec<u8> = (0..50).collect();
+        let filtered = Delta::with_stride(4).compress(&input).unwrap();
+        assert_eq!(Delta::new().decompress(&filtered).unwrap(), input);
+    }
+
+    #[test]
+    fn test_improves_rle_on_ramp() {
+        let input: Vec<u8> = (0..1000u32).map(|i| i.to_le_bytes()[0]).collect();
+        let plain = Rle::new().compress(&input).unwrap();
+        let filtered = Rle::new()
+            .compress(&Delta::new().compress(&input).unwrap())
+            .unwrap();
+        assert!(filtered.len() * 10 < plain.len());
+    }
+
+    #[test]
+    fn test_pipeline_with_huffman() {
+        let input: Vec<u8> = (0..500u32).map(|i| (i * 7 / 3).to_le_bytes()[0]).collect();
+        let compressed = Huffman::new()
+            .compress(&Delta::new().compress(&input).unwrap())
+            .unwrap();
+        let restored = Delta::new()
+            .decompress(&Huffman::new().decompress(&compressed).unwrap())
+            .unwrap();
+        assert_eq!(restored, input);
+    }
+
+    #[test]
+    fn test_zero_stride() {
+        let result = Delta::with_stride(0).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Delta::new().decompress(&[0, 1, 2]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+}
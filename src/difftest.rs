@@ -0,0 +1,508 @@
+//! Differential round-trip testing across every codec.
+//!
+//! [`run`] compresses the same inputs with every codec in [`codecs`] and
+//! checks that each frame fits the codec's
+//! [`max_compressed_len`](Compressor::max_compressed_len), that its header
+//! agrees with [`decompressed_len`](Decompressor::decompressed_len) and that
+//! it decompresses back to identical bytes, then cross-checks
+//! the formats that identify their own contents: the algorithm tag of an
+//! [`Auto`] frame, the mode byte of a [`Chunked`] frame and the channel
+//! framing of a [`Mux`] stream must each lead a decoder configured
+//! differently from the encoder to the same bytes. Failures are collected
+//! into a [`Report`] rather than panicking, so one broken codec does not
+//! hide the others.
+
+use std::fmt;
+
+use crate::auto::{Auto, AutoAlgorithm};
+use crate::chunked::Chunked;
+use crate::error::Result;
+use crate::mux::{Demux, Mux};
+use crate::traits::{Codec, Compressor, Decompressor};
+use crate::{
+    Algorithm, Arithmetic, Bcj, BitShuffle, Bwt, Chunker, Cm, Cobs, CompressionLevel, Container,
+    Dedup, Delta, Huffman, Legacy, LegacyFormat, Lz4, Lz77, Lz77Huffman, Lzma, Lzp, Pipeline, Ppm,
+    Preset, RePair, Rle, Rle0, ShannonFano, Shuffle, Snappy, Stored, Tunstall, WordHuffman,
+    ZeroRun,
+};
+
+/// Input size above which [`Chunked`] splits inputs during [`run`], small
+/// enough that the sample inputs span several chunks.
+pub const CHUNK_SIZE: usize = 256;
+
+/// One check that did not reproduce its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The codec or container under test, e.g. `lz77` or `chunked/lz77`.
+    pub check: String,
+    /// Name of the input it failed on.
+    pub input: String,
+    pub detail: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}: {}", self.check, self.input, self.detail)
+    }
+}
+
+/// Outcome of [`run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Round trips performed.
+    pub checks: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Report {
+    /// Whether every check reproduced its input.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    fn check(&mut self, check: &str, input: &str, expected: &[u8], result: Result<Vec<u8>>) {
+        self.checks += 1;
+        let detail = match result {
+            Ok(output) if output == expected => return,
+            Ok(output) => describe(expected, &output),
+            Err(e) => e.to_string(),
+        };
+        self.mismatches.push(Mismatch {
+            check: check.to_string(),
+            input: input.to_string(),
+            detail,
+        });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} checks, {} mismatches",
+            self.checks,
+            self.mismatches.len()
+        )?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  {mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where `output` first departs from `expected`.
+fn describe(expected: &[u8], output: &[u8]) -> String {
+    let first = expected
+        .iter()
+        .zip(output)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| expected.len().min(output.len()));
+    format!(
+        "{} bytes back for {}, first difference at offset {first}",
+        output.len(),
+        expected.len()
+    )
+}
+
+/// Every codec [`run`] exercises, keyed by a name unique among them: the
+/// general-purpose codecs with their notable settings, plus the filters
+/// that accept arbitrary bytes.
+#[must_use]
+pub fn codecs() -> Vec<(&'static str, Box<dyn Codec>)> {
+    vec![
+        ("stored", Box::new(Stored::new())),
+        ("rle", Box::new(Rle::new())),
+        ("rle_escaped", Box::new(Rle::escaped())),
+        ("rle_varint", Box::new(Rle::varint())),
+        ("rle_golomb", Box::new(Rle::golomb())),
+        ("zero_run", Box::new(ZeroRun::new())),
+        ("rle0", Box::new(Rle0::new())),
+        ("lz77", Box::new(Lz77::new())),
+        ("lz77_realtime", Box::new(Lz77::realtime())),
+        (
+            "lz77_best",
+            Box::new(Lz77::new().with_level(CompressionLevel::BEST)),
+        ),
+        ("lz77_huffman", Box::new(Lz77Huffman::new())),
+        ("lz4", Box::new(Lz4::new())),
+        ("snappy", Box::new(Snappy::new())),
+        ("huffman", Box::new(Huffman::new())),
+        (
+            "huffman_text",
+            Box::new(Huffman::with_preset(Preset::EnglishText)),
+        ),
+        ("word_huffman", Box::new(WordHuffman::new())),
+        ("arithmetic", Box::new(Arithmetic::new())),
+        ("shannon_fano", Box::new(ShannonFano::new())),
+        ("tunstall", Box::new(Tunstall::new())),
+        ("ppm", Box::new(Ppm::new())),
+        ("lzma", Box::new(Lzma::new())),
+        (
+            "lzma_fastest",
+            Box::new(Lzma::new().with_level(CompressionLevel::FASTEST)),
+        ),
+        ("lzp", Box::new(Lzp::new())),
+        ("repair", Box::new(RePair::new())),
+        ("cm", Box::new(Cm::new())),
+        ("auto", Box::new(Auto::new())),
+        ("legacy", Box::new(Legacy::new(LegacyFormat::Lz77))),
+        (
+            "dedup",
+            Box::new(Dedup::with_chunker(
+                Lz4::new(),
+                Chunker::with_sizes(64, 256, 1024),
+            )),
+        ),
+        ("delta", Box::new(Delta::new())),
+        ("shuffle", Box::new(Shuffle::new())),
+        ("bitshuffle", Box::new(BitShuffle::new())),
+        ("bwt", Box::new(Bwt::with_block_size(100))),
+        ("cobs", Box::new(Cobs::new())),
+        ("bcj", Box::new(Bcj::new())),
+        (
+            "pipeline",
+            Box::new(
+                Pipeline::new()
+                    .with_stage(Algorithm::Delta)
+                    .with_stage(Algorithm::Lz4),
+            ),
+        ),
+        ("container", Box::new(Container::new(Algorithm::Lz77))),
+    ]
+}
+
+/// Deterministic inputs covering the usual edge cases: empty, a single
+/// byte, long runs, text, every byte value and incompressible noise.
+#[must_use]
+pub fn samples() -> Vec<(&'static str, Vec<u8>)> {
+    let mut state = 0x2545_f491_u32;
+    let noise = (0..1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        })
+        .collect();
+    let mut sparse = vec![0; 1024];
+    for i in (0..sparse.len()).step_by(97) {
+        sparse[i] = u8::try_from(i % 251).unwrap_or(0);
+    }
+    vec![
+        ("empty", Vec::new()),
+        ("single", b"x".to_vec()),
+        ("runs", [[b'a'; 300], [b'b'; 300], [0; 300]].concat()),
+        (
+            "text",
+            b"the quick brown fox jumps over the lazy dog; ".repeat(24),
+        ),
+        ("bytes", (0..=255).collect()),
+        ("noise", noise),
+        ("sparse", sparse),
+    ]
+}
+
+/// Runs [`run_with`] over [`codecs`] and [`samples`].
+#[must_use]
+pub fn run() -> Report {
+    let samples = samples();
+    let inputs: Vec<(&str, &[u8])> = samples
+        .iter()
+        .map(|(name, data)| (*name, data.as_slice()))
+        .collect();
+    run_with(&codecs(), &inputs)
+}
+
+/// Round-trips every input through every codec, through [`Chunked`]
+/// around every codec and through one [`Mux`] stream per codec, and
+/// decodes every [`Auto`] payload with the codec its tag names.
+#[must_use]
+pub fn run_with(codecs: &[(&str, Box<dyn Codec>)], inputs: &[(&str, &[u8])]) -> Report {
+    let mut report = Report::default();
+    for (name, codec) in codecs {
+        let codec = Borrowed(codec.as_ref());
+        for &(input_name, input) in inputs {
+            let compressed = codec.compress(input);
+            if let Ok(first) = &compressed
+                && codec.compress(input).ok().as_ref() != Some(first)
+            {
+                report.mismatches.push(Mismatch {
+                    check: (*name).to_string(),
+                    input: input_name.to_string(),
+                    detail: "output differs between runs".to_string(),
+                });
+            }
+            let bound = codec.max_compressed_len(input.len());
+            if let Ok(frame) = &compressed
+                && frame.len() > bound
+            {
+                report.mismatches.push(Mismatch {
+                    check: (*name).to_string(),
+                    input: input_name.to_string(),
+                    detail: format!("{} bytes exceed max_compressed_len {bound}", frame.len()),
+                });
+            }
+            let header = compressed
+                .as_ref()
+                .map(|frame| codec.decompressed_len(frame));
+            if let Ok(Ok(Some(len))) = header
+                && len != input.len() as u64
+            {
+                report.mismatches.push(Mismatch {
+                    check: (*name).to_string(),
+                    input: input_name.to_string(),
+                    detail: format!("decompressed_len reports {len} for {} bytes", input.len()),
+                });
+            } else if let Ok(Err(e)) = header {
+                report.mismatches.push(Mismatch {
+                    check: (*name).to_string(),
+                    input: input_name.to_string(),
+                    detail: format!("decompressed_len failed: {e}"),
+                });
+            }
+            report.check(
+                name,
+                input_name,
+                input,
+                compressed.and_then(|compressed| codec.decompress(&compressed)),
+            );
+
+            // The decoder reads the mode byte, whatever its own threshold.
+            let chunked = Chunked::with_threshold(codec, CHUNK_SIZE, CHUNK_SIZE)
+                .compress(input)
+                .and_then(|frame| Chunked::new(codec).decompress(&frame));
+            report.check(&format!("chunked/{name}"), input_name, input, chunked);
+        }
+        check_mux(&mut report, name, codec, inputs);
+    }
+    check_auto(&mut report, inputs);
+    report
+}
+
+/// Writes each input on its own channel of one stream and demultiplexes it.
+fn check_mux(report: &mut Report, name: &str, codec: Borrowed<'_>, inputs: &[(&str, &[u8])]) {
+    let mut mux = Mux::new(Vec::new(), codec);
+    let written = inputs
+        .iter()
+        .zip(0u16..)
+        .try_for_each(|(&(_, input), channel)| mux.write(channel, input));
+    let split = written
+        .and_then(|()| mux.flush())
+        .and_then(|()| Demux::new(mux.into_inner().as_slice(), codec).split());
+    let check = format!("mux/{name}");
+    for (&(input_name, input), channel) in inputs.iter().zip(0u16..) {
+        let output = match &split {
+            Ok(channels) => Ok(channels.get(&channel).cloned().unwrap_or_default()),
+            Err(e) => Err(e.clone()),
+        };
+        report.check(&check, input_name, input, output);
+    }
+}
+
+/// Decodes each [`Auto`] payload with the standalone codec its tag names.
+fn check_auto(report: &mut Report, inputs: &[(&str, &[u8])]) {
+    for &(input_name, input) in inputs {
+        let output = Auto::new().compress(input).and_then(|frame| {
+            let payload = &frame[1..];
+            match Auto::algorithm(&frame)? {
+                AutoAlgorithm::Stored => Stored::new().decompress(payload),
+                AutoAlgorithm::Rle => Rle::escaped().decompress(payload),
+                AutoAlgorithm::Lz77 => Lz77::new().decompress(payload),
+                AutoAlgorithm::Huffman => Huffman::new().decompress(payload),
+            }
+        });
+        report.check("auto/tag", input_name, input, output);
+    }
+}
+
+/// A borrowed trait object usable where the containers want a sized codec.
+#[derive(Clone, Copy)]
+struct Borrowed<'a>(&'a dyn Codec);
+
+impl Compressor for Borrowed<'_> {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.0.compress(input)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        self.0.max_compressed_len(input_len)
+    }
+
+    fn name(&self) -> &'static str {
+        Compressor::name(self.0)
+    }
+}
+
+impl Decompressor for Borrowed<'_> {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.0.decompress(input)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        self.0.decompressed_len(input)
+    }
+
+    fn name(&self) -> &'static str {
+        Decompressor::name(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CompressionError;
+
+    /// Codec that drops the last byte of everything it decodes.
+    struct Lossy;
+
+    impl Compressor for Lossy {
+        fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Lossy"
+        }
+    }
+
+    impl Decompressor for Lossy {
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input[..input.len().saturating_sub(1)].to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Lossy"
+        }
+    }
+
+    /// Identity codec that claims frames half the size of its input.
+    struct Overpromising;
+
+    impl Compressor for Overpromising {
+        fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn max_compressed_len(&self, input_len: usize) -> usize {
+            input_len / 2
+        }
+
+        fn name(&self) -> &'static str {
+            "Overpromising"
+        }
+    }
+
+    impl Decompressor for Overpromising {
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Overpromising"
+        }
+    }
+
+    /// Identity codec whose header peek is always one byte short.
+    struct Miscounting;
+
+    impl Compressor for Miscounting {
+        fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn name(&self) -> &'static str {
+            "Miscounting"
+        }
+    }
+
+    impl Decompressor for Miscounting {
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.to_vec())
+        }
+
+        fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+            Ok(Some(input.len().saturating_sub(1) as u64))
+        }
+
+        fn name(&self) -> &'static str {
+            "Miscounting"
+        }
+    }
+
+    #[test]
+    fn test_all_codecs_agree() {
+        let report = run();
+        assert!(report.is_clean(), "{report}");
+        let codecs = codecs().len();
+        let inputs = samples().len();
+        assert_eq!(report.checks, codecs * inputs * 3 + inputs);
+    }
+
+    #[test]
+    fn test_codec_names_unique() {
+        let mut names: Vec<&str> = codecs().into_iter().map(|(name, _)| name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), codecs().len());
+    }
+
+    #[test]
+    fn test_reports_mismatches() {
+        let codecs: Vec<(&str, Box<dyn Codec>)> =
+            vec![("lossy", Box::new(Lossy)), ("lz4", Box::new(Lz4::new()))];
+        let report = run_with(&codecs, &[("empty", b""), ("abc", b"abc")]);
+        assert!(!report.is_clean());
+        let failed: Vec<(&str, &str)> = report
+            .mismatches
+            .iter()
+            .map(|m| (m.check.as_str(), m.input.as_str()))
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                ("lossy", "abc"),
+                ("chunked/lossy", "abc"),
+                ("mux/lossy", "abc")
+            ]
+        );
+        assert_eq!(
+            report.mismatches[0].detail,
+            "2 bytes back for 3, first difference at offset 2"
+        );
+        assert!(report.to_string().starts_with("14 checks, 3 mismatches\n"));
+    }
+
+    #[test]
+    fn test_reports_broken_bounds() {
+        let codecs: Vec<(&str, Box<dyn Codec>)> = vec![("over", Box::new(Overpromising))];
+        let report = run_with(&codecs, &[("empty", b""), ("abc", b"abc")]);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(
+            report.mismatches[0].to_string(),
+            "over on abc: 3 bytes exceed max_compressed_len 1"
+        );
+    }
+
+    #[test]
+    fn test_reports_wrong_lengths() {
+        let codecs: Vec<(&str, Box<dyn Codec>)> = vec![("miscount", Box::new(Miscounting))];
+        let report = run_with(&codecs, &[("empty", b""), ("abc", b"abc")]);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(
+            report.mismatches[0].to_string(),
+            "miscount on abc: decompressed_len reports 2 for 3 bytes"
+        );
+    }
+
+    #[test]
+    fn test_reports_errors() {
+        let mut report = Report::default();
+        report.check("x", "y", b"", Err(CompressionError::CorruptedData));
+        assert_eq!(report.checks, 1);
+        assert_eq!(
+            report.mismatches[0].detail,
+            CompressionError::CorruptedData.to_string()
+        );
+    }
+}
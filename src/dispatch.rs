@@ -0,0 +1,199 @@
+//! Runtime selection of hot-loop implementations by CPU feature.
+//!
+//! The backend is detected once, on first use, and cached, and
+//! [`force_scalar`] overrides it process-wide to test the fallback path on
+//! any host. All backends are
+//! portable safe Rust with no intrinsics: the wider ones compare whole
+//! 16-byte words at a time, in runs of one or two words sized to the
+//! host's vector registers, and leave the choice of instructions to the
+//! compiler.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DETECTED: OnceLock<Backend> = OnceLock::new();
+static FORCE_SCALAR: AtomicBool = AtomicBool::new(false);
+
+/// An implementation family for the crate's hot loops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// One byte at a time.
+    Scalar,
+    /// One 16-byte word at a time, for hosts with 128-bit vectors (SSE2,
+    /// NEON).
+    Word128,
+    /// Two 16-byte words at a time, for hosts with 256-bit vectors (AVX2).
+    Word256,
+}
+
+impl Backend {
+    /// Every backend, each of which runs on any host.
+    pub const ALL: [Self; 3] = [Self::Scalar, Self::Word128, Self::Word256];
+
+    /// Bytes compared per step.
+    #[must_use]
+    pub const fn lane_width(self) -> usize {
+        match self {
+            Self::Scalar => 1,
+            Self::Word128 => 16,
+            Self::Word256 => 32,
+        }
+    }
+}
+
+fn detect() -> Backend {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            return Backend::Word256;
+        }
+        if std::arch::is_x86_feature_detected!("sse2") {
+            return Backend::Word128;
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return Backend::Word128;
+        }
+    }
+    Backend::Scalar
+}
+
+/// The backend suited to this host, detected once and cached.
+pub fn detected() -> Backend {
+    *DETECTED.get_or_init(detect)
+}
+
+/// The backend currently in use: the detected one unless scalar is forced.
+pub fn backend() -> Backend {
+    if FORCE_SCALAR.load(Ordering::Relaxed) {
+        Backend::Scalar
+    } else {
+        detected()
+    }
+}
+
+/// Forces (or stops forcing) the scalar backend for the whole process, e.g.
+/// to test the fallback path on a host with wide vectors.
+pub fn force_scalar(force: bool) {
+    FORCE_SCALAR.store(force, Ordering::Relaxed);
+}
+
+/// Length of the common prefix of `a` and `b`, using the active backend.
+#[must_use]
+pub fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    common_prefix_len_with(backend(), a, b)
+}
+
+/// Length of the common prefix of `a` and `b`, using `backend`. Every
+/// backend gives the same answer, so this suits benchmarking one against
+/// another or testing the scalar path on any host.
+#[must_use]
+pub fn common_prefix_len_with(backend: Backend, a: &[u8], b: &[u8]) -> usize {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let lanes = match backend {
+        Backend::Scalar => 0,
+        Backend::Word128 => prefix_u128(a, b),
+        Backend::Word256 => prefix_u128x2(a, b),
+    };
+    lanes
+        + a[lanes..]
+            .iter()
+            .zip(&b[lanes..])
+            .take_while(|(x, y)| x == y)
+            .count()
+}
+
+/// Compares 16-byte lanes, returning the matched length in bytes. Stops at
+/// the first differing lane, resolving the exact byte within it.
+fn prefix_u128(a: &[u8], b: &[u8]) -> usize {
+    let mut matched = 0;
+    for (x, y) in a.chunks_exact(16).zip(b.chunks_exact(16)) {
+        let x = u128::from_le_bytes(x.try_into().unwrap_or_default());
+        let y = u128::from_le_bytes(y.try_into().unwrap_or_default());
+        let diff = x ^ y;
+        if diff != 0 {
+            return matched + (diff.trailing_zeros() / 8) as usize;
+        }
+        matched += 16;
+    }
+    matched
+}
+
+/// Compares 32-byte lanes as two 16-byte halves.
+fn prefix_u128x2(a: &[u8], b: &[u8]) -> usize {
+    let mut matched = 0;
+    for (x, y) in a.chunks_exact(32).zip(b.chunks_exact(32)) {
+        if x != y {
+            return matched + prefix_u128(x, y);
+        }
+        matched += 32;
+    }
+    matched + prefix_u128(&a[matched..], &b[matched..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(a: &[u8], b: &[u8]) -> usize {
+        a.iter().zip(b).take_while(|(x, y)| x == y).count()
+    }
+
+    #[test]
+    fn test_detected_is_stable() {
+        assert_eq!(detected(), detected());
+    }
+
+    #[test]
+    fn test_force_scalar() {
+        force_scalar(true);
+        assert_eq!(backend(), Backend::Scalar);
+        assert_eq!(common_prefix_len(b"abcdef", b"abcxyz"), 3);
+        force_scalar(false);
+        assert_eq!(backend(), detected());
+    }
+
+    #[test]
+    fn test_lane_width() {
+        assert_eq!(Backend::Scalar.lane_width(), 1);
+        assert_eq!(Backend::Word128.lane_width(), 16);
+        assert_eq!(Backend::Word256.lane_width(), 32);
+    }
+
+    #[test]
+    fn test_backends_agree_on_mismatch_positions() {
+        let a: Vec<u8> = (0..200u8).collect();
+        for mismatch in 0..a.len() {
+            let mut b = a.clone();
+            b[mismatch] ^= 0xFF;
+            for backend in Backend::ALL {
+                assert_eq!(common_prefix_len_with(backend, &a, &b), mismatch);
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_full_match_and_lengths() {
+        let a = vec![7u8; 100];
+        for len in 0..=100 {
+            for backend in Backend::ALL {
+                assert_eq!(common_prefix_len_with(backend, &a, &a[..len]), len);
+                assert_eq!(common_prefix_len_with(backend, &a[..len], &a), len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_common_prefix_len_matches_naive() {
+        let a = b"the quick brown fox jumps over the lazy dog, the quick brown cat";
+        let b = b"the quick brown fox jumps over the lazy dog, the quick brown fox";
+        assert_eq!(common_prefix_len(a, b), naive(a, b));
+        for backend in Backend::ALL {
+            assert_eq!(common_prefix_len_with(backend, a, b), naive(a, b));
+        }
+        assert_eq!(common_prefix_len(b"", b"abc"), 0);
+    }
+}
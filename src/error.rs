@@ -7,6 +7,20 @@ pub enum CompressionError {
     BufferTooSmall,
     InvalidHeader,
     CorruptedData,
+    /// A complete frame was followed by this many unread bytes.
+    TrailingData(usize),
+    /// Data decoded without error but does not match the checksum stored
+    /// with it.
+    ChecksumMismatch,
+    /// Decoding would break a
+    /// [`DecompressionLimits`](crate::DecompressionLimits) bound.
+    LimitExceeded(String),
+    /// An underlying reader, writer or file failed; `kind` is kept so the
+    /// error converts back to a `std::io::Error` of the same kind.
+    Io {
+        kind: std::io::ErrorKind,
+        message: String,
+    },
 }
 
 impl fmt::Display for CompressionError {
@@ -17,12 +31,36 @@ impl fmt::Display for CompressionError {
             Self::BufferTooSmall => write!(f, "Buffer too small for output"),
             Self::InvalidHeader => write!(f, "Invalid compression header"),
             Self::CorruptedData => write!(f, "Corrupted compressed data"),
+            Self::TrailingData(len) => write!(f, "{len} trailing bytes after compressed frame"),
+            Self::ChecksumMismatch => write!(f, "Checksum mismatch in decompressed data"),
+            Self::LimitExceeded(msg) => write!(f, "Decompression limit exceeded: {msg}"),
+            Self::Io { message, .. } => write!(f, "I/O error: {message}"),
         }
     }
 }
 
 impl std::error::Error for CompressionError {}
 
+impl From<std::io::Error> for CompressionError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// For codecs behind `std::io` traits: I/O failures keep their kind and
+/// message, anything else is reported as `InvalidData`.
+impl From<CompressionError> for std::io::Error {
+    fn from(err: CompressionError) -> Self {
+        match err {
+            CompressionError::Io { kind, message } => Self::new(kind, message),
+            other => Self::new(std::io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CompressionError>;
 
 #[cfg(test)]
@@ -59,6 +97,63 @@ mod tests {
         assert_eq!(err.to_string(), "Corrupted compressed data");
     }
 
+    #[test]
+    fn test_error_display_trailing_data() {
+        let err = CompressionError::TrailingData(3);
+        assert_eq!(err.to_string(), "3 trailing bytes after compressed frame");
+    }
+
+    #[test]
+    fn test_error_display_checksum_mismatch() {
+        let err = CompressionError::ChecksumMismatch;
+        assert_eq!(err.to_string(), "Checksum mismatch in decompressed data");
+    }
+
+    #[test]
+    fn test_error_display_limit_exceeded() {
+        let err = CompressionError::LimitExceeded("too big".to_string());
+        assert_eq!(err.to_string(), "Decompression limit exceeded: too big");
+    }
+
+    #[test]
+    fn test_error_display_io() {
+        let err = CompressionError::Io {
+            kind: std::io::ErrorKind::BrokenPipe,
+            message: "broken pipe".to_string(),
+        };
+        assert_eq!(err.to_string(), "I/O error: broken pipe");
+    }
+
+    #[test]
+    fn test_error_from_io() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "eof");
+        let err = CompressionError::from(io_err);
+        assert_eq!(
+            err,
+            CompressionError::Io {
+                kind: std::io::ErrorKind::UnexpectedEof,
+                message: "eof".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_into_io() {
+        let io_err = std::io::Error::from(CompressionError::CorruptedData);
+        assert_eq!(io_err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(io_err.to_string(), "Corrupted compressed data");
+        let io_err = std::io::Error::from(CompressionError::Io {
+            kind: std::io::ErrorKind::BrokenPipe,
+            message: "broken pipe".to_string(),
+        });
+        assert_eq!(io_err.kind(), std::io::ErrorKind::BrokenPipe);
+        assert_eq!(io_err.to_string(), "broken pipe");
+
+        let original = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let io_err = std::io::Error::from(CompressionError::from(original));
+        assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
+    }
+
     #[test]
     fn test_error_clone() {
         let err = CompressionError::InvalidInput("test".to_string());
@@ -74,6 +169,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn test_result_type_ok() {
         let result: Result<i32> = Ok(42);
         assert!(result.is_ok());
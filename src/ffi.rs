@@ -0,0 +1,624 @@
+//! C ABI for calling the codecs from C and C++.
+//!
+//! Codecs are chosen by their [`Algorithm::tag`] byte, which never changes,
+//! and every function reports failure through one of the fixed `CL_ERR_*`
+//! codes rather than unwinding. Buffers belong to the caller: output is
+//! written into the space given, and when it does not fit, the size needed
+//! is stored and [`ERR_BUFFER_TOO_SMALL`] returned so the call can be
+//! repeated with a larger buffer. [`cl_max_compressed_len`] sizes a buffer
+//! that always fits a compressed frame, and [`cl_compress_level`] takes a
+//! compression level from 1 to 9. Decompression never produces more
+//! than the buffer holds: a frame whose header records a longer output is
+//! refused before decoding, and one without such a header is stopped with
+//! [`ERR_LIMIT_EXCEEDED`] once its output passes the buffer.
+//!
+//! `include/compression_lib.h` declares all of it; it is the output of
+//! [`header`], and a test keeps the two identical. Build the shared library
+//! with `cargo build --release --features ffi`. The crate is always built as
+//! a `cdylib` as well as a `lib`, but without this feature the shared
+//! library exports nothing.
+
+use std::ffi::{CStr, c_char};
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::algorithm::Algorithm;
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::limits::DecompressionLimits;
+use crate::traits::{Codec, Compressor};
+
+pub const OK: i32 = 0;
+/// A pointer that must not be null was null.
+pub const ERR_NULL_POINTER: i32 = -1;
+/// No [`Algorithm`] has the tag given.
+pub const ERR_UNKNOWN_ALGORITHM: i32 = -2;
+/// The output buffer was too small; the size needed was stored.
+pub const ERR_BUFFER_TOO_SMALL: i32 = -3;
+pub const ERR_INVALID_INPUT: i32 = -4;
+pub const ERR_DECOMPRESSION: i32 = -5;
+pub const ERR_INVALID_HEADER: i32 = -6;
+pub const ERR_CORRUPTED_DATA: i32 = -7;
+pub const ERR_TRAILING_DATA: i32 = -8;
+pub const ERR_CHECKSUM_MISMATCH: i32 = -9;
+pub const ERR_LIMIT_EXCEEDED: i32 = -10;
+pub const ERR_IO: i32 = -11;
+/// The codec panicked; this is a bug in the library.
+pub const ERR_INTERNAL: i32 = -12;
+
+/// Every status code with its header name and the message
+/// [`cl_error_message`] returns for it.
+pub const STATUS_CODES: [(&str, i32, &CStr); 13] = [
+    ("CL_OK", OK, c"success"),
+    ("CL_ERR_NULL_POINTER", ERR_NULL_POINTER, c"null pointer"),
+    (
+        "CL_ERR_UNKNOWN_ALGORITHM",
+        ERR_UNKNOWN_ALGORITHM,
+        c"unknown algorithm",
+    ),
+    (
+        "CL_ERR_BUFFER_TOO_SMALL",
+        ERR_BUFFER_TOO_SMALL,
+        c"output buffer too small",
+    ),
+    ("CL_ERR_INVALID_INPUT", ERR_INVALID_INPUT, c"invalid input"),
+    (
+        "CL_ERR_DECOMPRESSION",
+        ERR_DECOMPRESSION,
+        c"decompression error",
+    ),
+    (
+        "CL_ERR_INVALID_HEADER",
+        ERR_INVALID_HEADER,
+        c"invalid compression header",
+    ),
+    (
+        "CL_ERR_CORRUPTED_DATA",
+        ERR_CORRUPTED_DATA,
+        c"corrupted compressed data",
+    ),
+    (
+        "CL_ERR_TRAILING_DATA",
+        ERR_TRAILING_DATA,
+        c"trailing bytes after compressed frame",
+    ),
+    (
+        "CL_ERR_CHECKSUM_MISMATCH",
+        ERR_CHECKSUM_MISMATCH,
+        c"checksum mismatch in decompressed data",
+    ),
+    (
+        "CL_ERR_LIMIT_EXCEEDED",
+        ERR_LIMIT_EXCEEDED,
+        c"decompression limit exceeded",
+    ),
+    ("CL_ERR_IO", ERR_IO, c"I/O error"),
+    ("CL_ERR_INTERNAL", ERR_INTERNAL, c"internal error"),
+];
+
+/// The status code reporting `err`.
+#[must_use]
+pub const fn error_code(err: &CompressionError) -> i32 {
+    match err {
+        CompressionError::InvalidInput(_) => ERR_INVALID_INPUT,
+        CompressionError::DecompressionError(_) => ERR_DECOMPRESSION,
+        CompressionError::BufferTooSmall => ERR_BUFFER_TOO_SMALL,
+        CompressionError::InvalidHeader => ERR_INVALID_HEADER,
+        CompressionError::CorruptedData => ERR_CORRUPTED_DATA,
+        CompressionError::TrailingData(_) => ERR_TRAILING_DATA,
+        CompressionError::ChecksumMismatch => ERR_CHECKSUM_MISMATCH,
+        CompressionError::LimitExceeded(_) => ERR_LIMIT_EXCEEDED,
+        CompressionError::Io { .. } => ERR_IO,
+    }
+}
+
+const PROTOTYPES: &str = "\
+/* Compresses input_len bytes at input with the algorithm tagged algorithm
+ * into output, storing the compressed length in *output_len. If it does not
+ * fit in output_capacity bytes, stores the length needed and returns
+ * CL_ERR_BUFFER_TOO_SMALL. input may be NULL if input_len is 0, and output
+ * if output_capacity is 0. */
+int32_t cl_compress(uint8_t algorithm, const uint8_t *input, size_t input_len,
+                    uint8_t *output, size_t output_capacity, size_t *output_len);
+
+/* cl_compress at a compression level from 1 (fastest) to 9 (best), clamped
+ * to that range; cl_compress uses 6. Decompression needs no level. */
+int32_t cl_compress_level(uint8_t algorithm, uint8_t level, const uint8_t *input,
+                          size_t input_len, uint8_t *output, size_t output_capacity,
+                          size_t *output_len);
+
+/* Reverses cl_compress with the same algorithm, on the same terms, without
+ * decoding more than output_capacity bytes. A frame that records a longer
+ * output is refused up front with CL_ERR_BUFFER_TOO_SMALL and its length
+ * stored; one that records none is stopped with CL_ERR_LIMIT_EXCEEDED once
+ * it passes output_capacity, and can be retried with a larger buffer. */
+int32_t cl_decompress(uint8_t algorithm, const uint8_t *input, size_t input_len,
+                      uint8_t *output, size_t output_capacity, size_t *output_len);
+
+/* Largest frame input_len bytes can compress to with algorithm: SIZE_MAX if
+ * the codec has no bound, 0 if no algorithm has that tag. */
+size_t cl_max_compressed_len(uint8_t algorithm, size_t input_len);
+
+/* A static, NUL-terminated description of a status code. */
+const char *cl_error_message(int32_t code);
+";
+
+/// Renders the C header declaring this module: the status codes, one
+/// `CL_ALGORITHM_*` tag per [`Algorithm`], and the function prototypes.
+#[must_use]
+pub fn header() -> String {
+    let mut out = String::from(
+        "/* compression_lib C API. Generated by compression_lib::ffi::header();\n \
+         * do not edit. */\n\n\
+         #ifndef COMPRESSION_LIB_H\n#define COMPRESSION_LIB_H\n\n\
+         #include <stddef.h>\n#include <stdint.h>\n\n\
+         #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n/* Status codes. */\n",
+    );
+    for (name, code, _) in STATUS_CODES {
+        let _ = writeln!(out, "#define {name} ({code})");
+    }
+    out.push_str("\n/* Algorithm tags. */\n");
+    for algorithm in Algorithm::ALL {
+        let name = algorithm.name().to_ascii_uppercase();
+        let _ = writeln!(out, "#define CL_ALGORITHM_{name} {}", algorithm.tag());
+    }
+    out.push('\n');
+    out.push_str(PROTOTYPES);
+    out.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif /* COMPRESSION_LIB_H */\n");
+    out
+}
+
+fn codec(
+    algorithm: u8,
+    level: CompressionLevel,
+) -> std::result::Result<Box<dyn Codec + Send + Sync>, i32> {
+    Algorithm::from_tag(algorithm)
+        .map(|algorithm| algorithm.new_codec_with_level(level))
+        .ok_or(ERR_UNKNOWN_ALGORITHM)
+}
+
+/// Runs `f`, turning its errors and panics into status codes.
+fn guarded<T>(f: impl FnOnce() -> Result<T>) -> std::result::Result<T, i32> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(error_code(&err)),
+        Err(_) => Err(ERR_INTERNAL),
+    }
+}
+
+/// What [`run`] does to the caller's input.
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    Compress(CompressionLevel),
+    Decompress,
+}
+
+impl Operation {
+    /// The level to create the codec with.
+    const fn level(self) -> CompressionLevel {
+        match self {
+            Self::Compress(level) => level,
+            Self::Decompress => CompressionLevel::DEFAULT,
+        }
+    }
+
+    /// The output length, if known without producing the output.
+    fn needed(self, codec: &dyn Codec, input: &[u8]) -> Result<Option<u64>> {
+        match self {
+            Self::Compress(_) => Ok(None),
+            Self::Decompress => codec.decompressed_len(input),
+        }
+    }
+
+    /// Produces the output; decompression stops once it passes `capacity`.
+    fn apply(self, codec: &dyn Codec, input: &[u8], capacity: usize) -> Result<Vec<u8>> {
+        match self {
+            Self::Compress(_) => codec.compress(input),
+            Self::Decompress => {
+                let limits = DecompressionLimits::new().with_max_output_size(capacity as u64);
+                codec.decompress_with_limits(input, &limits)
+            }
+        }
+    }
+}
+
+/// Runs `operation` on the caller's input and copies its result into the
+/// caller's output, guarding every pointer and catching panics. An output
+/// known up front not to fit is reported without producing it.
+///
+/// # Safety
+///
+/// As for [`cl_compress`].
+unsafe fn run(
+    algorithm: u8,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_capacity: usize,
+    output_len: *mut usize,
+    operation: Operation,
+) -> i32 {
+    if output_len.is_null()
+        || (input.is_null() && input_len > 0)
+        || (output.is_null() && output_capacity > 0)
+    {
+        return ERR_NULL_POINTER;
+    }
+    let codec = match codec(algorithm, operation.level()) {
+        Ok(codec) => codec,
+        Err(code) => return code,
+    };
+    let input = if input_len == 0 {
+        &[][..]
+    } else {
+        // SAFETY: the caller guarantees `input` points to `input_len`
+        // readable bytes, and it was checked to be non-null.
+        unsafe { std::slice::from_raw_parts(input, input_len) }
+    };
+    match guarded(|| operation.needed(&*codec, input)) {
+        Ok(Some(len)) if len > output_capacity as u64 => {
+            // SAFETY: `output_len` was checked to be non-null, and the
+            // caller guarantees it is writable.
+            unsafe { output_len.write(usize::try_from(len).unwrap_or(usize::MAX)) };
+            return ERR_BUFFER_TOO_SMALL;
+        }
+        Ok(_) => {}
+        Err(code) => return code,
+    }
+    let result = match guarded(|| operation.apply(&*codec, input, output_capacity)) {
+        Ok(data) => data,
+        Err(code) => return code,
+    };
+    // SAFETY: `output_len` was checked to be non-null, and the caller
+    // guarantees it is writable.
+    unsafe { output_len.write(result.len()) };
+    if result.len() > output_capacity {
+        return ERR_BUFFER_TOO_SMALL;
+    }
+    if !result.is_empty() {
+        // SAFETY: the caller guarantees `output` points to
+        // `output_capacity` writable bytes not overlapping the input, and
+        // the result fits.
+        unsafe { std::ptr::copy_nonoverlapping(result.as_ptr(), output, result.len()) };
+    }
+    OK
+}
+
+/// Compresses `input_len` bytes at `input` with the algorithm tagged
+/// `algorithm`, writing the frame to `output` and its length to
+/// `*output_len`.
+///
+/// Returns [`OK`], [`ERR_BUFFER_TOO_SMALL`] with the length needed stored
+/// if the frame does not fit in `output_capacity` bytes, or another status
+/// code on failure.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, or may be null if
+/// `input_len` is 0; `output` must point to `output_capacity` writable
+/// bytes not overlapping `input`, or may be null if `output_capacity` is 0;
+/// `output_len` must point to a writable `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cl_compress(
+    algorithm: u8,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_capacity: usize,
+    output_len: *mut usize,
+) -> i32 {
+    // SAFETY: the caller upholds this function's contract, which is run's.
+    unsafe {
+        run(
+            algorithm,
+            input,
+            input_len,
+            output,
+            output_capacity,
+            output_len,
+            Operation::Compress(CompressionLevel::DEFAULT),
+        )
+    }
+}
+
+/// [`cl_compress`] with the codec tuned to `level`, clamped to `1..=9`, as
+/// by [`Algorithm::new_codec_with_level`]. Frames decode with
+/// [`cl_decompress`] whatever the level.
+///
+/// # Safety
+///
+/// As for [`cl_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cl_compress_level(
+    algorithm: u8,
+    level: u8,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_capacity: usize,
+    output_len: *mut usize,
+) -> i32 {
+    // SAFETY: the caller upholds this function's contract, which is run's.
+    unsafe {
+        run(
+            algorithm,
+            input,
+            input_len,
+            output,
+            output_capacity,
+            output_len,
+            Operation::Compress(CompressionLevel::new(level)),
+        )
+    }
+}
+
+/// Reverses [`cl_compress`] with the same `algorithm`, decoding at most
+/// `output_capacity` bytes.
+///
+/// A frame whose header records a longer output is refused with
+/// [`ERR_BUFFER_TOO_SMALL`] and that length stored, before anything is
+/// decoded; a frame without one stops with [`ERR_LIMIT_EXCEEDED`] as soon
+/// as its output passes `output_capacity`, or once decoded for codecs that
+/// cannot stop early.
+///
+/// # Safety
+///
+/// As for [`cl_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cl_decompress(
+    algorithm: u8,
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_capacity: usize,
+    output_len: *mut usize,
+) -> i32 {
+    // SAFETY: the caller upholds this function's contract, which is run's.
+    unsafe {
+        run(
+            algorithm,
+            input,
+            input_len,
+            output,
+            output_capacity,
+            output_len,
+            Operation::Decompress,
+        )
+    }
+}
+
+/// Largest frame `input_len` bytes can compress to with `algorithm`:
+/// `usize::MAX` if the codec has no bound, 0 if no algorithm has that tag.
+#[unsafe(no_mangle)]
+pub extern "C" fn cl_max_compressed_len(algorithm: u8, input_len: usize) -> usize {
+    codec(algorithm, CompressionLevel::DEFAULT)
+        .map_or(0, |codec| codec.max_compressed_len(input_len))
+}
+
+/// A static description of status code `code`, or of an unknown code.
+#[unsafe(no_mangle)]
+pub extern "C" fn cl_error_message(code: i32) -> *const c_char {
+    STATUS_CODES
+        .iter()
+        .find(|&&(_, known, _)| known == code)
+        .map_or(c"unknown status code", |&(_, _, message)| message)
+        .as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(
+        function: unsafe extern "C" fn(u8, *const u8, usize, *mut u8, usize, *mut usize) -> i32,
+        algorithm: Algorithm,
+        input: &[u8],
+        capacity: usize,
+    ) -> (i32, Vec<u8>) {
+        let mut output = vec![0; capacity];
+        let mut len = 0;
+        // SAFETY: both buffers are live and sized as passed.
+        let status = unsafe {
+            function(
+                algorithm.tag(),
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                output.len(),
+                &raw mut len,
+            )
+        };
+        output.truncate(len);
+        (status, output)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let input = b"called from C++; called from C++; called from C++".repeat(20);
+        for algorithm in [Algorithm::Lz77, Algorithm::Lz4, Algorithm::Huffman] {
+            let bound = cl_max_compressed_len(algorithm.tag(), input.len());
+            let (status, compressed) = call(cl_compress, algorithm, &input, bound);
+            assert_eq!(status, OK, "{algorithm}");
+            assert_eq!(compressed, algorithm.new_codec().compress(&input).unwrap());
+            let (status, output) = call(cl_decompress, algorithm, &compressed, input.len());
+            assert_eq!((status, output), (OK, input.clone()), "{algorithm}");
+        }
+        // Empty input with null buffers.
+        let mut len = usize::MAX;
+        // SAFETY: null buffers are allowed for zero lengths.
+        let status = unsafe {
+            cl_compress(
+                Algorithm::Stored.tag(),
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &raw mut len,
+            )
+        };
+        let expected = Algorithm::Stored.new_codec().compress(b"").unwrap().len();
+        assert_eq!(len, expected);
+        assert_eq!(
+            status,
+            if expected == 0 {
+                OK
+            } else {
+                ERR_BUFFER_TOO_SMALL
+            }
+        );
+    }
+
+    #[test]
+    fn test_compress_level() {
+        let input = b"levels from C: levels from C: levels from C".repeat(40);
+        for level in [0, 1, 6, 9, 200] {
+            let mut output = vec![0; input.len()];
+            let mut len = 0;
+            // SAFETY: both buffers are live and sized as passed.
+            let status = unsafe {
+                cl_compress_level(
+                    Algorithm::Lzma.tag(),
+                    level,
+                    input.as_ptr(),
+                    input.len(),
+                    output.as_mut_ptr(),
+                    output.len(),
+                    &raw mut len,
+                )
+            };
+            assert_eq!(status, OK);
+            output.truncate(len);
+            let codec = Algorithm::Lzma.new_codec_with_level(CompressionLevel::new(level));
+            assert_eq!(output, codec.compress(&input).unwrap(), "level {level}");
+            let (status, decoded) = call(cl_decompress, Algorithm::Lzma, &output, input.len());
+            assert_eq!((status, decoded), (OK, input.clone()));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = b"too big for the buffer".repeat(10);
+        let (status, compressed) = call(cl_compress, Algorithm::Stored, &input, 4);
+        assert_eq!(status, ERR_BUFFER_TOO_SMALL);
+        assert_eq!(compressed.len(), 4);
+        let mut len = 0;
+        let mut output = vec![0; 4];
+        // SAFETY: both buffers are live and sized as passed.
+        let status = unsafe {
+            cl_compress(
+                Algorithm::Stored.tag(),
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                output.len(),
+                &raw mut len,
+            )
+        };
+        assert_eq!(status, ERR_BUFFER_TOO_SMALL);
+        assert_eq!(
+            len,
+            Algorithm::Stored
+                .new_codec()
+                .compress(&input)
+                .unwrap()
+                .len()
+        );
+
+        assert_eq!(
+            call(cl_decompress, Algorithm::Huffman, &[0xFF; 3], 64).0,
+            ERR_CORRUPTED_DATA
+        );
+        // SAFETY: a null `output_len` is refused before anything is read.
+        let status = unsafe {
+            cl_decompress(
+                Algorithm::Lz77.tag(),
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                output.len(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, ERR_NULL_POINTER);
+        // SAFETY: a null `input` with a length is refused before use.
+        let status =
+            unsafe { cl_compress(0, std::ptr::null(), 1, output.as_mut_ptr(), 4, &raw mut len) };
+        assert_eq!(status, ERR_NULL_POINTER);
+        // SAFETY: an unknown tag is refused before the buffers are used.
+        let status = unsafe {
+            cl_compress(
+                0xFF,
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                4,
+                &raw mut len,
+            )
+        };
+        assert_eq!(status, ERR_UNKNOWN_ALGORITHM);
+        assert_eq!(cl_max_compressed_len(0xFF, 100), 0);
+    }
+
+    #[test]
+    fn test_decompress_within_capacity() {
+        let input = vec![0; 1 << 20];
+        let compressed = Algorithm::Lz4.new_codec().compress(&input).unwrap();
+        // The length in the header is stored without decoding anything.
+        let mut len = 0;
+        // SAFETY: a null `output` is allowed for a zero capacity.
+        let status = unsafe {
+            cl_decompress(
+                Algorithm::Lz4.tag(),
+                compressed.as_ptr(),
+                compressed.len(),
+                std::ptr::null_mut(),
+                0,
+                &raw mut len,
+            )
+        };
+        assert_eq!((status, len), (ERR_BUFFER_TOO_SMALL, input.len()));
+        let (status, output) = call(cl_decompress, Algorithm::Lz4, &compressed, 64);
+        assert_eq!((status, output.len()), (ERR_BUFFER_TOO_SMALL, 64));
+
+        // Arithmetic frames record no length, so decoding stops at the
+        // capacity instead.
+        let compressed = Algorithm::Arithmetic.new_codec().compress(&input).unwrap();
+        let (status, output) = call(cl_decompress, Algorithm::Arithmetic, &compressed, 64);
+        assert_eq!((status, output.len()), (ERR_LIMIT_EXCEEDED, 0));
+        let (status, output) = call(
+            cl_decompress,
+            Algorithm::Arithmetic,
+            &compressed,
+            input.len(),
+        );
+        assert_eq!((status, output), (OK, input));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        for (_, code, message) in STATUS_CODES {
+            // SAFETY: cl_error_message returns a static NUL-terminated string.
+            let returned = unsafe { CStr::from_ptr(cl_error_message(code)) };
+            assert_eq!(returned, message);
+        }
+        // SAFETY: as above.
+        let unknown = unsafe { CStr::from_ptr(cl_error_message(1)) };
+        assert_eq!(unknown, c"unknown status code");
+        assert_eq!(
+            error_code(&CompressionError::from(std::io::Error::other(""))),
+            ERR_IO
+        );
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let header = header();
+        assert_eq!(
+            include_str!("../include/compression_lib.h"),
+            header,
+            "regenerate include/compression_lib.h from ffi::header()"
+        );
+        assert!(header.contains("#define CL_ALGORITHM_LZ77 4\n"));
+        assert!(header.contains("#define CL_ERR_INTERNAL (-12)\n"));
+    }
+}
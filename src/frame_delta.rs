@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::traits::Codec;
+
+/// How a frame is differenced against the previous frame on its channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaMode {
+    /// Bitwise XOR with the previous frame.
+    #[default]
+    Xor,
+    /// Wrapping byte subtraction of the previous frame.
+    Subtract,
+}
+
+impl DeltaMode {
+    const fn apply(self, current: u8, previous: u8) -> u8 {
+        match self {
+            Self::Xor => current ^ previous,
+            Self::Subtract => current.wrapping_sub(previous),
+        }
+    }
+
+    const fn revert(self, delta: u8, previous: u8) -> u8 {
+        match self {
+            Self::Xor => delta ^ previous,
+            Self::Subtract => delta.wrapping_add(previous),
+        }
+    }
+}
+
+/// Compresses successive state buffers as differences from the previous
+/// buffer on the same channel.
+///
+/// Unchanged regions become zero bytes, which the wrapped codec compresses
+/// well. Frames may change size; bytes past the end of the previous frame are
+/// differenced against zero. The encoding and decoding sides each keep their
+/// own `FrameDelta`, and must see the same sequence of frames per channel.
+#[derive(Debug)]
+pub struct FrameDelta<C: Codec> {
+    codec: C,
+    mode: DeltaMode,
+    previous: HashMap<u16, Vec<u8>>,
+}
+
+impl<C: Codec> FrameDelta<C> {
+    pub fn new(codec: C, mode: DeltaMode) -> Self {
+        Self {
+            codec,
+            mode,
+            previous: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn mode(&self) -> DeltaMode {
+        self.mode
+    }
+
+    /// Differences `frame` against the previous frame on `channel` and
+    /// compresses the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the wrapped codec. The channel state is only
+    /// updated on success.
+    pub fn encode(&mut self, channel: u16, frame: &[u8]) -> Result<Vec<u8>> {
+        let previous = self.previous.get(&channel).map_or(&[][..], Vec::as_slice);
+        let delta: Vec<u8> = frame
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| self.mode.apply(byte, previous.get(i).copied().unwrap_or(0)))
+            .collect();
+
+        let compressed = self.codec.compress(&delta)?;
+        self.previous.insert(channel, frame.to_vec());
+        Ok(compressed)
+    }
+
+    /// Decompresses a frame produced by [`FrameDelta::encode`] and restores
+    /// it against the previous frame on `channel`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the wrapped codec. The channel state is only
+    /// updated on success.
+    pub fn decode(&mut self, channel: u16, compressed: &[u8]) -> Result<Vec<u8>> {
+        let delta = self.codec.decompress(compressed)?;
+        let previous = self.previous.get(&channel).map_or(&[][..], Vec::as_slice);
+        let frame: Vec<u8> = delta
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| {
+                self.mode
+                    .revert(byte, previous.get(i).copied().unwrap_or(0))
+            })
+            .collect();
+
+        self.previous.insert(channel, frame.clone());
+        Ok(frame)
+    }
+
+    /// Forgets the previous frame on `channel`, so the next frame is sent
+    /// in full (e.g. after a resync).
+    pub fn reset(&mut self, channel: u16) {
+        self.previous.remove(&channel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CompressionError;
+    use crate::{Huffman, Rle};
+
+    fn frames() -> Vec<Vec<u8>> {
+        let mut state = vec![0x10u8; 64];
+        let mut frames = vec![state.clone()];
+        for step in 0..5u8 {
+            state[usize::from(step) * 3] = step.wrapping_mul(37);
+            frames.push(state.clone());
+        }
+        frames
+    }
+
+    fn roundtrip(mode: DeltaMode) {
+        let mut encoder = FrameDelta::new(Rle::new(), mode);
+        let mut decoder = FrameDelta::new(Rle::new(), mode);
+        for frame in frames() {
+            let compressed = encoder.encode(0, &frame).unwrap();
+            assert_eq!(decoder.decode(0, &compressed).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_xor() {
+        roundtrip(DeltaMode::Xor);
+    }
+
+    #[test]
+    fn test_roundtrip_subtract() {
+        roundtrip(DeltaMode::Subtract);
+    }
+
+    #[test]
+    fn test_default_mode() {
+        assert_eq!(DeltaMode::default(), DeltaMode::Xor);
+        assert_eq!(
+            FrameDelta::new(Rle::new(), DeltaMode::Subtract).mode(),
+            DeltaMode::Subtract
+        );
+    }
+
+    #[test]
+    fn test_unchanged_frame_compresses_small() {
+        let mut encoder = FrameDelta::new(Rle::new(), DeltaMode::Xor);
+        let frame: Vec<u8> = (0..200).collect();
+        let first = encoder.encode(0, &frame).unwrap();
+        let second = encoder.encode(0, &frame).unwrap();
+        assert!(second.len() < first.len());
+        assert_eq!(second, vec![200, 0]);
+    }
+
+    #[test]
+    fn test_channels_are_independent() {
+        let mut encoder = FrameDelta::new(Huffman::new(), DeltaMode::Xor);
+        let mut decoder = FrameDelta::new(Huffman::new(), DeltaMode::Xor);
+        let a1 = encoder.encode(1, b"aaaa").unwrap();
+        let b1 = encoder.encode(2, b"bbbb").unwrap();
+        let a2 = encoder.encode(1, b"aaab").unwrap();
+
+        assert_eq!(decoder.decode(1, &a1).unwrap(), b"aaaa");
+        assert_eq!(decoder.decode(2, &b1).unwrap(), b"bbbb");
+        assert_eq!(decoder.decode(1, &a2).unwrap(), b"aaab");
+    }
+
+    #[test]
+    fn test_frame_size_changes() {
+        let mut encoder = FrameDelta::new(Rle::new(), DeltaMode::Subtract);
+        let mut decoder = FrameDelta::new(Rle::new(), DeltaMode::Subtract);
+        for frame in [&b"abcdef"[..], b"abc", b"abcdefgh", b""] {
+            let compressed = encoder.encode(0, frame).unwrap();
+            assert_eq!(decoder.decode(0, &compressed).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut encoder = FrameDelta::new(Rle::new(), DeltaMode::Xor);
+        let full = encoder.encode(0, b"zzzz").unwrap();
+        encoder.reset(0);
+        assert_eq!(encoder.encode(0, b"zzzz").unwrap(), full);
+    }
+
+    #[test]
+    fn test_decode_error_keeps_state() {
+        let mut encoder = FrameDelta::new(Rle::new(), DeltaMode::Xor);
+        let mut decoder = FrameDelta::new(Rle::new(), DeltaMode::Xor);
+        let first = encoder.encode(0, b"state").unwrap();
+        decoder.decode(0, &first).unwrap();
+
+        let result = decoder.decode(0, &[0, 1]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+
+        let second = encoder.encode(0, b"stats").unwrap();
+        assert_eq!(decoder.decode(0, &second).unwrap(), b"stats");
+    }
+}
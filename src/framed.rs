@@ -0,0 +1,210 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::error::{CompressionError, Result};
+use crate::limits::DecompressionLimits;
+use crate::traits::{Compressor, Decompressor};
+
+pub const FRAME_HEADER_LEN: usize = 4;
+const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+/// A tokio-util [`Encoder`] and [`Decoder`] that compresses each message
+/// on its own, for `Framed` transports such as a `TcpStream`.
+///
+/// Every message becomes one frame, `[payload length: u32][payload]`
+/// little-endian, so messages can be decoded as soon as they arrive. The
+/// decoder waits for a whole frame before decompressing it and rejects
+/// frames longer than [`max_frame_len`](Self::max_frame_len) as soon as
+/// their header arrives, so a bad peer cannot make it buffer without bound.
+/// Payloads are decompressed under [`limits`](Self::limits), so a small
+/// frame cannot expand without bound either. Both ends must use the same
+/// codec. Output layout per message:
+/// `[payload length: u32][payload]`.
+#[derive(Debug, Clone)]
+pub struct MessageCodec<C> {
+    codec: C,
+    max_frame_len: usize,
+    limits: DecompressionLimits,
+}
+
+impl<C> MessageCodec<C> {
+    /// Creates a codec accepting frames of up to 8 MiB, with no limit on
+    /// what they decompress to.
+    pub const fn new(codec: C) -> Self {
+        Self {
+            codec,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            limits: DecompressionLimits::new(),
+        }
+    }
+
+    /// Limits the compressed payload of a frame to `max_frame_len` bytes,
+    /// in both directions.
+    #[must_use]
+    pub const fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Bounds what each received payload may decompress to.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        &self.limits
+    }
+}
+
+impl<C: Compressor, T: AsRef<[u8]>> Encoder<T> for MessageCodec<C> {
+    type Error = CompressionError;
+
+    /// Compresses `message` and appends it to `dst` as one frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the payload exceeds the
+    /// frame limit, or any error from the codec.
+    fn encode(&mut self, message: T, dst: &mut BytesMut) -> Result<()> {
+        let payload = self.codec.compress(message.as_ref())?;
+        let len = u32::try_from(payload.len())
+            .ok()
+            .filter(|_| payload.len() <= self.max_frame_len)
+            .ok_or_else(|| {
+                CompressionError::InvalidInput(format!(
+                    "frame of {} bytes exceeds the limit of {}",
+                    payload.len(),
+                    self.max_frame_len
+                ))
+            })?;
+        dst.reserve(FRAME_HEADER_LEN + payload.len());
+        dst.put_u32_le(len);
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+impl<C: Decompressor> Decoder for MessageCodec<C> {
+    type Item = Vec<u8>;
+    type Error = CompressionError;
+
+    /// Takes the next whole frame from `src` and decompresses it, or
+    /// returns `None` until one has arrived.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionError` if a frame header
+    /// announces more than the frame limit,
+    /// `CompressionError::LimitExceeded` if its payload would decompress
+    /// past the limits, or any error from the codec.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Vec<u8>>> {
+        let Some(header) = src.first_chunk::<FRAME_HEADER_LEN>() else {
+            return Ok(None);
+        };
+        let len = u32::from_le_bytes(*header) as usize;
+        if len > self.max_frame_len {
+            return Err(CompressionError::DecompressionError(format!(
+                "frame of {len} bytes exceeds the limit of {}",
+                self.max_frame_len
+            )));
+        }
+        if src.len() < FRAME_HEADER_LEN + len {
+            src.reserve(FRAME_HEADER_LEN + len - src.len());
+            return Ok(None);
+        }
+        src.advance(FRAME_HEADER_LEN);
+        let payload = src.split_to(len);
+        self.codec
+            .decompress_with_limits(&payload, &self.limits)
+            .map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz4, Rle};
+
+    #[test]
+    fn test_roundtrip_messages() {
+        let mut codec = MessageCodec::new(Lz4::new());
+        let messages = [
+            b"first message".to_vec(),
+            Vec::new(),
+            b"a longer message, a longer message, a longer message".to_vec(),
+        ];
+        let mut wire = BytesMut::new();
+        for message in &messages {
+            codec.encode(message, &mut wire).unwrap();
+        }
+        for message in &messages {
+            assert_eq!(codec.decode(&mut wire).unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(codec.decode(&mut wire).unwrap(), None);
+        assert!(wire.is_empty());
+    }
+
+    #[test]
+    fn test_waits_for_whole_frame() {
+        let mut codec = MessageCodec::new(Huffman::new());
+        let mut frame = BytesMut::new();
+        codec
+            .encode(b"arrives one byte at a time", &mut frame)
+            .unwrap();
+        let mut wire = BytesMut::new();
+        for (i, &byte) in frame.iter().enumerate() {
+            assert_eq!(codec.decode(&mut wire).unwrap(), None, "{i}");
+            wire.put_u8(byte);
+        }
+        assert_eq!(
+            codec.decode(&mut wire).unwrap().unwrap(),
+            b"arrives one byte at a time"
+        );
+    }
+
+    #[test]
+    fn test_frame_limit() {
+        let mut codec = MessageCodec::new(Lz4::new()).with_max_frame_len(16);
+        assert_eq!(codec.max_frame_len(), 16);
+        let mut wire = BytesMut::new();
+        let noise: Vec<u8> = (0..=u8::MAX).collect();
+        assert!(matches!(
+            codec.encode(&noise, &mut wire),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(wire.is_empty());
+
+        wire.put_u32_le(17);
+        assert!(matches!(
+            codec.decode(&mut wire),
+            Err(CompressionError::DecompressionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 16);
+        let mut codec = MessageCodec::new(Rle::varint()).with_limits(limits);
+        assert_eq!(codec.limits(), &limits);
+        let mut wire = BytesMut::new();
+        codec.encode(vec![7; 1 << 16], &mut wire).unwrap();
+        assert_eq!(codec.decode(&mut wire).unwrap().unwrap().len(), 1 << 16);
+
+        // Six bytes of varint RLE that describe 4 GiB.
+        let bomb = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x00];
+        wire.put_u32_le(6);
+        wire.put_slice(&bomb);
+        assert!(matches!(
+            codec.decode(&mut wire),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+}
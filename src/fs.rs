@@ -0,0 +1,348 @@
+//! Compressing one file into another.
+//!
+//! [`compress_file`] and [`decompress_file`] wrap
+//! [`copy_compress`](crate::copy_compress) and
+//! [`copy_decompress`](crate::copy_decompress) with buffered file handles,
+//! so memory stays at a couple of blocks whatever the file size. Files use
+//! 1 MiB blocks, larger than the streaming default, as a file is read
+//! whole anyway and bigger blocks compress better. The output is the same
+//! block stream the pipe functions write.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::error::{CompressionError, Result};
+use crate::pipe::{self, CopyOptions, CopyStats};
+use crate::traits::Codec;
+
+/// Block size [`compress_file`] uses.
+pub const FILE_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// The options [`compress_file`] and [`decompress_file`] use: 1 MiB blocks
+/// and no limits.
+#[must_use]
+pub const fn file_options() -> CopyOptions {
+    CopyOptions::new().with_buffer_size(FILE_BLOCK_SIZE)
+}
+
+/// Compresses the file at `src` into a new file at `dst`, replacing any
+/// file already there.
+///
+/// # Errors
+///
+/// Returns the same errors as [`compress_file_with_options`].
+pub fn compress_file<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+) -> Result<CopyStats> {
+    compress_file_with_options(src, dst, codec, &file_options())
+}
+
+/// [`compress_file`] with the block size and other settings of `options`.
+///
+/// If compression fails part way, the partly written `dst` is removed.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `src` and `dst` are the same
+/// file, `CompressionError::Io` if either cannot be opened, read or written,
+/// or any error from [`copy_compress`](crate::copy_compress).
+pub fn compress_file_with_options<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let reader = open_source(src, dst)?;
+    let capacity = options.buffer_size().min(FILE_BLOCK_SIZE);
+    write_or_remove(dst, |file| {
+        pipe::copy_compress(
+            reader,
+            BufWriter::with_capacity(capacity, file),
+            codec,
+            options,
+        )
+    })
+}
+
+/// Reverses [`compress_file`]: decompresses the file at `src`, written with
+/// the same codec, into a new file at `dst`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decompress_file_with_options`].
+pub fn decompress_file<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+) -> Result<CopyStats> {
+    decompress_file_with_options(src, dst, codec, &file_options())
+}
+
+/// [`decompress_file`] with the limits and other settings of `options`;
+/// pass [`DecompressionLimits`](crate::DecompressionLimits) through
+/// [`CopyOptions::with_limits`] for files from untrusted sources.
+///
+/// Blocks are decompressed straight from a read buffer of twice the block
+/// size, and if decompression fails part way, the partly written `dst` is
+/// removed.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `src` and `dst` are the same
+/// file, `CompressionError::Io` if either cannot be opened, read or written,
+/// or any error from [`copy_decompress`](crate::copy_decompress).
+pub fn decompress_file_with_options<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let reader = open_source(src, dst)?;
+    let capacity = options.buffer_size().min(FILE_BLOCK_SIZE);
+    write_or_remove(dst, |file| {
+        pipe::copy_decompress_buf(
+            BufReader::with_capacity(2 * capacity, reader),
+            BufWriter::with_capacity(capacity, file),
+            codec,
+            options,
+        )
+    })
+}
+
+/// [`compress_file_with_options`] reading `src` through a memory map.
+///
+/// Blocks are compressed in place from the page cache rather than read
+/// into a buffer, so a multi-gigabyte input is never copied into process
+/// memory. The output is the same block stream, written to `dst` block by
+/// block.
+///
+/// The file must not be truncated or modified while it is mapped: another
+/// process shrinking it makes reads fault, and changing it changes the
+/// input under the codec.
+///
+/// # Errors
+///
+/// Returns the same errors as [`compress_file_with_options`], or
+/// `CompressionError::Io` if `src` cannot be mapped.
+#[cfg(feature = "mmap")]
+pub fn compress_file_mapped<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let map = map(&open_source(src, dst)?)?;
+    let capacity = options.buffer_size().min(FILE_BLOCK_SIZE);
+    write_or_remove(dst, |file| {
+        pipe::copy_compress_slice(
+            &map,
+            BufWriter::with_capacity(capacity, file),
+            codec,
+            options,
+        )
+    })
+}
+
+/// [`decompress_file_with_options`] reading `src` through a memory map.
+///
+/// Every block is decompressed straight from the mapping, however large,
+/// and the output is written to `dst` block by block.
+///
+/// The same caveat as [`compress_file_mapped`] applies to `src`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decompress_file_with_options`], or
+/// `CompressionError::Io` if `src` cannot be mapped.
+#[cfg(feature = "mmap")]
+pub fn decompress_file_mapped<C: Codec + ?Sized>(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let map = map(&open_source(src, dst)?)?;
+    let capacity = options.buffer_size().min(FILE_BLOCK_SIZE);
+    write_or_remove(dst, |file| {
+        pipe::copy_decompress_buf(
+            &map[..],
+            BufWriter::with_capacity(capacity, file),
+            codec,
+            options,
+        )
+    })
+}
+
+/// Maps `file` read-only.
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+fn map(file: &File) -> Result<memmap2::Mmap> {
+    // SAFETY: the mapping is only read, and only while the caller holds
+    // it; the public functions document that the file must not change
+    // meanwhile, which is the one thing the map cannot guard against.
+    let map = unsafe { memmap2::Mmap::map(file)? };
+    Ok(map)
+}
+
+/// Opens `src` for reading, refusing a `dst` that names the same file,
+/// which creating `dst` would truncate before it was read.
+fn open_source(src: &Path, dst: &Path) -> Result<File> {
+    let file = File::open(src)?;
+    let same = match fs::canonicalize(dst) {
+        Ok(dst) => fs::canonicalize(src)? == dst,
+        Err(_) => false,
+    };
+    if same {
+        return Err(CompressionError::InvalidInput(format!(
+            "{} is both source and destination",
+            src.display()
+        )));
+    }
+    Ok(file)
+}
+
+/// Creates `dst` and runs `copy` into it, removing the file if `copy`
+/// fails so no truncated output is left behind.
+fn write_or_remove(dst: &Path, copy: impl FnOnce(File) -> Result<CopyStats>) -> Result<CopyStats> {
+    let result = copy(File::create(dst)?);
+    if result.is_err() {
+        let _ = fs::remove_file(dst);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{DecompressionLimits, Lz4, Lz77};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("compression_lib_fs_{}_{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = TempDir::new("roundtrip");
+        let (src, packed, unpacked) = (
+            dir.0.join("data"),
+            dir.0.join("data.lz4"),
+            dir.0.join("data.out"),
+        );
+        let data = b"a path to a path, a path to a path; ".repeat(40_000);
+        fs::write(&src, &data).unwrap();
+
+        let stats = compress_file(&src, &packed, &Lz4::new()).unwrap();
+        assert_eq!(stats.bytes_in, data.len() as u64);
+        assert_eq!(stats.blocks, 2);
+        assert_eq!(fs::metadata(&packed).unwrap().len(), stats.bytes_out);
+        assert!(stats.bytes_out < stats.bytes_in);
+
+        let stats = decompress_file(&packed, &unpacked, &Lz4::new()).unwrap();
+        assert_eq!(stats.bytes_out, data.len() as u64);
+        assert_eq!(fs::read(&unpacked).unwrap(), data);
+
+        // Empty files survive too.
+        fs::write(&src, b"").unwrap();
+        compress_file(&src, &packed, &Lz77::new()).unwrap();
+        decompress_file(&packed, &unpacked, &Lz77::new()).unwrap();
+        assert_eq!(fs::read(&unpacked).unwrap(), b"");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped() {
+        let dir = TempDir::new("mapped");
+        let (src, packed, unpacked) = (
+            dir.0.join("data"),
+            dir.0.join("data.lz4"),
+            dir.0.join("data.out"),
+        );
+        let data = b"mapped, not read; mapped, not read; ".repeat(40_000);
+        fs::write(&src, &data).unwrap();
+        let options = file_options();
+
+        let stats = compress_file_mapped(&src, &packed, &Lz4::new(), &options).unwrap();
+        assert_eq!((stats.bytes_in, stats.blocks), (data.len() as u64, 2));
+        let stream = fs::read(&packed).unwrap();
+        compress_file(&src, &packed, &Lz4::new()).unwrap();
+        assert_eq!(fs::read(&packed).unwrap(), stream);
+
+        decompress_file_mapped(&packed, &unpacked, &Lz4::new(), &options).unwrap();
+        assert_eq!(fs::read(&unpacked).unwrap(), data);
+
+        fs::write(&src, b"").unwrap();
+        compress_file_mapped(&src, &packed, &Lz4::new(), &options).unwrap();
+        decompress_file_mapped(&packed, &unpacked, &Lz4::new(), &options).unwrap();
+        assert_eq!(fs::read(&unpacked).unwrap(), b"");
+
+        fs::write(&packed, &stream[..stream.len() - 1]).unwrap();
+        assert_eq!(
+            decompress_file_mapped(&packed, &unpacked, &Lz4::new(), &options),
+            Err(CompressionError::CorruptedData)
+        );
+        assert!(!unpacked.exists());
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = TempDir::new("errors");
+        let (src, packed, unpacked) = (
+            dir.0.join("data"),
+            dir.0.join("data.lz77"),
+            dir.0.join("data.out"),
+        );
+        assert!(matches!(
+            compress_file(&src, &packed, &Lz77::new()),
+            Err(CompressionError::Io { .. })
+        ));
+        assert!(!packed.exists());
+
+        fs::write(&src, b"same file").unwrap();
+        assert!(matches!(
+            compress_file(&src, &src, &Lz77::new()),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert_eq!(fs::read(&src).unwrap(), b"same file");
+
+        // A truncated stream or a broken limit leaves no output behind.
+        fs::write(&src, vec![b'z'; 10_000]).unwrap();
+        compress_file(&src, &packed, &Lz77::new()).unwrap();
+        let options =
+            file_options().with_limits(DecompressionLimits::new().with_max_output_size(100));
+        assert!(matches!(
+            decompress_file_with_options(&packed, &unpacked, &Lz77::new(), &options),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert!(!unpacked.exists());
+        let stream = fs::read(&packed).unwrap();
+        fs::write(&packed, &stream[..stream.len() - 1]).unwrap();
+        assert_eq!(
+            decompress_file(&packed, &unpacked, &Lz77::new()),
+            Err(CompressionError::CorruptedData)
+        );
+        assert!(!unpacked.exists());
+    }
+}
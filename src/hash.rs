@@ -0,0 +1,203 @@
+//! Rolling hashes for chunking and match finding.
+//!
+//! A rolling hash updates in constant time as a window slides one byte, so
+//! every window of a buffer can be hashed in one pass. [`Gear`] needs only
+//! the incoming byte and forgets old bytes by shifting them out; it drives
+//! [`Chunker`](crate::Chunker). [`RabinKarp`] hashes windows of any chosen
+//! length exactly, for finding repeats of a fixed size. Neither is
+//! collision-resistant: equal hashes mark candidates, which callers confirm
+//! by comparing bytes.
+
+/// Random 64-bit value per byte for [`Gear`], from `SplitMix64`.
+pub const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Bytes that still influence a [`Gear`] hash; each roll shifts the oldest
+/// one out of the top bit.
+pub const GEAR_WINDOW: usize = 64;
+
+/// Multiplier of the [`RabinKarp`] polynomial, the 64-bit FNV prime.
+pub const RABIN_KARP_BASE: u64 = 0x0000_0100_0000_01B3;
+
+/// Gear hash: shift left one bit, add the incoming byte's [`GEAR`] value.
+///
+/// A byte's contribution moves up one bit per roll and falls off the top
+/// after [`GEAR_WINDOW`] of them, so the hash covers the last
+/// [`GEAR_WINDOW`] bytes with no need to remove anything. The most recent
+/// byte reaches every bit, while a byte `k` rolls old only reaches bits `k`
+/// and up: the low bits depend on the last few bytes alone and the top bits
+/// on the whole window. Test the top bits, not the low ones, when looking
+/// for boundaries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Gear {
+    hash: u64,
+}
+
+impl Gear {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { hash: 0 }
+    }
+
+    /// Adds `byte` and returns the new hash.
+    pub const fn roll(&mut self, byte: u8) -> u64 {
+        self.hash = (self.hash << 1).wrapping_add(GEAR[byte as usize]);
+        self.hash
+    }
+
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Forgets every byte rolled in so far.
+    pub const fn reset(&mut self) {
+        self.hash = 0;
+    }
+}
+
+/// Rabin–Karp polynomial hash of a fixed-length window, modulo 2^64.
+///
+/// A window `b[0..n]` hashes to the sum of `b[i] * B^(n-1-i)`, with `B`
+/// [`RABIN_KARP_BASE`]. Sliding the window subtracts the outgoing byte's
+/// term and appends the incoming one, so equal windows always hash equal,
+/// whatever came before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RabinKarp {
+    window: usize,
+    /// `B^(window - 1)`, the weight of the outgoing byte.
+    outgoing_weight: u64,
+}
+
+impl RabinKarp {
+    /// Creates a hash over windows of `window` bytes, at least one.
+    #[must_use]
+    pub const fn new(window: usize) -> Self {
+        let window = if window == 0 { 1 } else { window };
+        let mut outgoing_weight = 1u64;
+        let mut i = 1;
+        while i < window {
+            outgoing_weight = outgoing_weight.wrapping_mul(RABIN_KARP_BASE);
+            i += 1;
+        }
+        Self {
+            window,
+            outgoing_weight,
+        }
+    }
+
+    #[must_use]
+    pub const fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Hashes `bytes` from scratch; for a full window this is the value
+    /// [`RabinKarp::roll`] arrives at.
+    #[must_use]
+    pub fn hash(&self, bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0, |hash, &byte| {
+            hash.wrapping_mul(RABIN_KARP_BASE)
+                .wrapping_add(u64::from(byte))
+        })
+    }
+
+    /// Slides a window hashing to `hash` one byte on: `outgoing` leaves at
+    /// the front and `incoming` joins at the back.
+    #[must_use]
+    pub const fn roll(&self, hash: u64, outgoing: u8, incoming: u8) -> u64 {
+        hash.wrapping_sub((outgoing as u64).wrapping_mul(self.outgoing_weight))
+            .wrapping_mul(RABIN_KARP_BASE)
+            .wrapping_add(incoming as u64)
+    }
+
+    /// Hashes every window of `data`, in order; empty if `data` is shorter
+    /// than one window.
+    #[must_use]
+    pub fn windows(&self, data: &[u8]) -> Vec<u64> {
+        let Some(first) = data.get(..self.window) else {
+            return Vec::new();
+        };
+        let mut hash = self.hash(first);
+        let mut hashes = Vec::with_capacity(data.len() - self.window + 1);
+        hashes.push(hash);
+        for (&outgoing, &incoming) in data.iter().zip(&data[self.window..]) {
+            hash = self.roll(hash, outgoing, incoming);
+            hashes.push(hash);
+        }
+        hashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"rolling hashes slide along the data one byte at a time, \
+        rolling hashes slide along";
+
+    #[test]
+    fn test_gear_forgets_old_bytes() {
+        let mut a = Gear::new();
+        let mut b = Gear::new();
+        for &byte in b"different prefix" {
+            a.roll(byte);
+        }
+        for &byte in b"another" {
+            b.roll(byte);
+        }
+        for &byte in &TEXT[..GEAR_WINDOW] {
+            a.roll(byte);
+            b.roll(byte);
+        }
+        assert_eq!(a.value(), b.value());
+        a.reset();
+        assert_eq!(a, Gear::new());
+        assert_eq!(a.roll(0), GEAR[0]);
+    }
+
+    #[test]
+    fn test_gear_table_is_spread() {
+        let mut sorted = GEAR.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 256);
+        let ones: u32 = GEAR.iter().map(|value| value.count_ones()).sum();
+        assert!((256 * 28..256 * 36).contains(&ones), "{ones}");
+    }
+
+    #[test]
+    fn test_rabin_karp_roll_matches_hash() {
+        for window in [1, 2, 7, 32] {
+            let rk = RabinKarp::new(window);
+            let hashes = rk.windows(TEXT);
+            assert_eq!(hashes.len(), TEXT.len() - window + 1);
+            for (hash, bytes) in hashes.iter().zip(TEXT.windows(window)) {
+                assert_eq!(*hash, rk.hash(bytes));
+            }
+        }
+        assert!(RabinKarp::new(8).windows(b"short").is_empty());
+        assert_eq!(RabinKarp::new(0).window(), 1);
+    }
+
+    #[test]
+    fn test_rabin_karp_finds_repeats() {
+        let rk = RabinKarp::new(16);
+        let hashes = rk.windows(TEXT);
+        // The text ends with a copy of its first 26 bytes.
+        let copy = TEXT.len() - b"rolling hashes slide along".len();
+        assert_eq!(hashes[..=10], hashes[copy..=copy + 10]);
+        assert!(!hashes[1..copy].contains(&hashes[0]));
+    }
+}
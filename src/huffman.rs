@@ -1,8 +1,24 @@
+use std::cmp::{Ordering, Reverse};
 use std::collections::{BinaryHeap, HashMap};
-use std::cmp::Ordering;
+use std::time::Instant;
 
+use crate::bitstream::{BitReader, BitWriter};
 use crate::error::{CompressionError, Result};
-use crate::traits::{Compressor, Decompressor};
+use crate::prefix_code::{self, CanonicalDecoder, MAX_CODE_LENGTH};
+use crate::profile::Profile;
+use crate::sink::{Sink, SliceSink};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Preset frame tag for input stored as raw bytes.
+pub const PRESET_STORED: u8 = 0;
+pub const PRESET_ENGLISH_TEXT: u8 = 1;
+pub const PRESET_JSON: u8 = 2;
+pub const PRESET_BASE64: u8 = 3;
+pub const PRESET_HEX: u8 = 4;
+/// Preset frame tag for input coded with a [`Profile`]'s table.
+pub const PRESET_PROFILE: u8 = 0xFF;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct HuffmanNode {
@@ -50,23 +66,12 @@ impl HuffmanNode {
         }
     }
 
-    fn build_codes(&self, prefix: Vec<bool>, codes: &mut HashMap<u8, Vec<bool>>) {
+    fn code_lengths(&self, depth: u8, lengths: &mut [u8; 256]) {
         match &self.data {
-            NodeData::Leaf(byte) => {
-                if prefix.is_empty() {
-                    codes.insert(*byte, vec![false]);
-                } else {
-                    codes.insert(*byte, prefix);
-                }
-            }
+            NodeData::Leaf(byte) => lengths[usize::from(*byte)] = depth.max(1),
             NodeData::Internal { left, right } => {
-                let mut left_prefix = prefix.clone();
-                left_prefix.push(false);
-                left.build_codes(left_prefix, codes);
-
-                let mut right_prefix = prefix;
-                right_prefix.push(true);
-                right.build_codes(right_prefix, codes);
+                left.code_lengths(depth + 1, lengths);
+                right.code_lengths(depth + 1, lengths);
             }
         }
     }
@@ -85,9 +90,12 @@ fn build_huffman_tree(freq_table: &HashMap<u8, usize>) -> Option<HuffmanNode> {
         return None;
     }
 
-    let mut heap: BinaryHeap<HuffmanNode> = freq_table
-        .iter()
-        .map(|(&byte, &freq)| HuffmanNode::new_leaf(byte, freq))
+    let mut entries: Vec<(u8, usize)> = freq_table.iter().map(|(&b, &f)| (b, f)).collect();
+    entries.sort_unstable();
+
+    let mut heap: BinaryHeap<HuffmanNode> = entries
+        .into_iter()
+        .map(|(byte, freq)| HuffmanNode::new_leaf(byte, freq))
         .collect();
 
     while heap.len() > 1 {
@@ -99,179 +107,562 @@ fn build_huffman_tree(freq_table: &HashMap<u8, usize>) -> Option<HuffmanNode> {
     heap.pop()
 }
 
-fn serialize_tree(node: &HuffmanNode, output: &mut Vec<u8>) {
-    match &node.data {
-        NodeData::Leaf(byte) => {
-            output.push(1);
-            output.push(*byte);
+/// Computes optimal code lengths no longer than `limit` bits using the
+/// package-merge algorithm.
+///
+/// Returns `None` if `limit` is too small to give every symbol a code.
+fn package_merge_lengths(freq_table: &HashMap<u8, usize>, limit: u8) -> Option<[u8; 256]> {
+    let mut leaves: Vec<(usize, u8)> = freq_table.iter().map(|(&b, &f)| (f, b)).collect();
+    leaves.sort_unstable();
+
+    let mut lengths = [0u8; 256];
+    if leaves.len() <= 1 {
+        for &(_, byte) in &leaves {
+            lengths[usize::from(byte)] = 1;
         }
-        NodeData::Internal { left, right } => {
-            output.push(0);
-            serialize_tree(left, output);
-            serialize_tree(right, output);
+        return (limit >= 1).then_some(lengths);
+    }
+    if u32::from(limit) < usize::BITS && (1usize << limit) < leaves.len() {
+        return None;
+    }
+
+    let singletons: Vec<(usize, Vec<u8>)> = leaves.iter().map(|&(f, b)| (f, vec![b])).collect();
+    let mut current = singletons.clone();
+
+    for _ in 1..limit {
+        let packages = current.chunks_exact(2).map(|pair| {
+            let mut symbols = pair[0].1.clone();
+            symbols.extend_from_slice(&pair[1].1);
+            (pair[0].0 + pair[1].0, symbols)
+        });
+
+        let mut merged = Vec::with_capacity(singletons.len() * 2);
+        let mut leaves_iter = singletons.iter().cloned().peekable();
+        let mut packages_iter = packages.peekable();
+        loop {
+            let take_leaf = match (leaves_iter.peek(), packages_iter.peek()) {
+                (Some(leaf), Some(package)) => leaf.0 <= package.0,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let item = if take_leaf {
+                leaves_iter.next()
+            } else {
+                packages_iter.next()
+            };
+            merged.extend(item);
         }
+        current = merged;
     }
+
+    for (_, symbols) in current.iter().take(2 * leaves.len() - 2) {
+        for &symbol in symbols {
+            lengths[usize::from(symbol)] += 1;
+        }
+    }
+    Some(lengths)
+}
+
+/// Built-in code table for [`Huffman::with_preset`], tuned to the byte
+/// frequencies typical of one kind of content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Prose: lowercase letters and spaces, then punctuation and capitals.
+    EnglishText,
+    /// Quotes, separators, digits and lowercase keys, compact or indented.
+    Json,
+    /// The standard and URL-safe base64 alphabets with padding.
+    Base64,
+    /// Hexadecimal digits, lowercase ahead of uppercase.
+    Hex,
 }
 
-fn deserialize_tree(data: &[u8], pos: &mut usize) -> Result<HuffmanNode> {
-    if *pos >= data.len() {
-        return Err(CompressionError::CorruptedData);
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+
+/// Weights per 100,000 bytes of typical content, applied in order so later
+/// entries override earlier ones. Bytes not listed weigh 1, so every byte
+/// keeps a code.
+const ENGLISH_TEXT_WEIGHTS: &[(&[u8], u64)] = &[
+    (UPPERCASE, 60),
+    (DIGITS, 50),
+    (b";:()", 40),
+    (b"!?", 60),
+    (b"ACEHMNOSW", 120),
+    (b"\'\"-", 200),
+    (b"AIT", 300),
+    (b"\n", 400),
+    (b"jqxz", 100),
+    (b"k", 550),
+    (b"v", 750),
+    (b".", 900),
+    (b",", 1000),
+    (b"b", 1150),
+    (b"p", 1400),
+    (b"gy", 1500),
+    (b"f", 1700),
+    (b"w", 1800),
+    (b"m", 1900),
+    (b"cu", 2100),
+    (b"l", 3100),
+    (b"d", 3300),
+    (b"r", 4600),
+    (b"h", 4700),
+    (b"s", 5000),
+    (b"in", 5400),
+    (b"o", 5900),
+    (b"a", 6200),
+    (b"t", 6900),
+    (b"e", 9500),
+    (b" ", 17_000),
+];
+
+const JSON_WEIGHTS: &[(&[u8], u64)] = &[
+    (UPPERCASE, 80),
+    (b"\\/", 50),
+    (b"[]-", 200),
+    (b".", 500),
+    (b"_", 500),
+    (b"{}", 700),
+    (b"\n", 700),
+    (b"bfgkpvwy", 600),
+    (b"cdhmu", 1000),
+    (b"ilnorst", 1700),
+    (b"ae", 2200),
+    (DIGITS, 1200),
+    (b":,", 2300),
+    (b" ", 3000),
+    (b"\"", 9000),
+];
+
+const BASE64_WEIGHTS: &[(&[u8], u64)] = &[
+    (UPPERCASE, 1500),
+    (b"abcdefghijklmnopqrstuvwxyz", 1500),
+    (DIGITS, 1500),
+    (b"+/", 1500),
+    (b"-_", 20),
+    (b"\n", 50),
+    (b"=", 100),
+];
+
+const HEX_WEIGHTS: &[(&[u8], u64)] = &[
+    (DIGITS, 5000),
+    (b"abcdef", 5000),
+    (b"ABCDEF", 500),
+    (b"\n x", 100),
+];
+
+impl Preset {
+    const fn tag(self) -> u8 {
+        match self {
+            Self::EnglishText => PRESET_ENGLISH_TEXT,
+            Self::Json => PRESET_JSON,
+            Self::Base64 => PRESET_BASE64,
+            Self::Hex => PRESET_HEX,
+        }
     }
 
-    let node_type = data[*pos];
-    *pos += 1;
+    const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            PRESET_ENGLISH_TEXT => Some(Self::EnglishText),
+            PRESET_JSON => Some(Self::Json),
+            PRESET_BASE64 => Some(Self::Base64),
+            PRESET_HEX => Some(Self::Hex),
+            _ => None,
+        }
+    }
 
-    if node_type == 1 {
-        if *pos >= data.len() {
-            return Err(CompressionError::CorruptedData);
+    const fn weights(self) -> &'static [(&'static [u8], u64)] {
+        match self {
+            Self::EnglishText => ENGLISH_TEXT_WEIGHTS,
+            Self::Json => JSON_WEIGHTS,
+            Self::Base64 => BASE64_WEIGHTS,
+            Self::Hex => HEX_WEIGHTS,
         }
-        let byte = data[*pos];
-        *pos += 1;
-        Ok(HuffmanNode::new_leaf(byte, 0))
-    } else {
-        let left = deserialize_tree(data, pos)?;
-        let right = deserialize_tree(data, pos)?;
-        Ok(HuffmanNode::new_internal(left, right))
     }
-}
 
-fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(bits.len().div_ceil(8));
-    for chunk in bits.chunks(8) {
-        let mut byte = 0u8;
-        for (i, &bit) in chunk.iter().enumerate() {
-            if bit {
-                byte |= 1 << (7 - i);
+    /// The preset's code lengths, covering all 256 bytes.
+    fn code_lengths(self) -> [u8; 256] {
+        let mut freqs = [1u64; 256];
+        for &(bytes, weight) in self.weights() {
+            for &byte in bytes {
+                freqs[usize::from(byte)] = weight;
             }
         }
-        bytes.push(byte);
+        complete_code_lengths(&freqs)
     }
-    bytes
 }
 
-fn bytes_to_bits(bytes: &[u8], num_bits: usize) -> Vec<bool> {
-    let mut bits = Vec::with_capacity(num_bits);
-    for &byte in bytes {
-        for i in 0..8 {
-            if bits.len() >= num_bits {
-                break;
-            }
-            bits.push((byte >> (7 - i)) & 1 == 1);
-        }
+/// Code lengths for `freqs`, which must all be non-zero so every byte gets
+/// a code. Frequencies up to `2^40` keep codes within `MAX_CODE_LENGTH`.
+fn complete_code_lengths(freqs: &[u64; 256]) -> [u8; 256] {
+    let mut lengths = [0u8; 256];
+    for (length, built) in lengths.iter_mut().zip(huffman_lengths(freqs)) {
+        *length = u8::try_from(built).unwrap_or(MAX_CODE_LENGTH);
     }
-    bits
+    lengths
 }
 
+/// Byte-oriented Huffman coder.
+///
+/// By default each frame carries its own code-length table, computed from
+/// the input. With a [`Preset`], both sides use a built-in table instead and
+/// frames carry only a preset tag and the length, which pays off for short
+/// messages whose table would cost more than it saves. A [`Profile`]
+/// captured from sample data works the same way with a table of its own.
+/// Either way, input the table codes no smaller than it is, such as random
+/// bytes, is stored raw, so near-uniform data costs a few header bytes
+/// rather than a 256-entry table on top of 8-bit codes. Output layout:
+/// `[code-length table][original length: u32][bit count: u32][bits]` or
+/// `[stored marker][original length: u32][bytes]`, with 64-bit lengths
+/// from 4 GiB up, or with a preset `[preset: u8][original length:
+/// varint][bits or stored bytes]`.
 #[derive(Debug, Default, Clone, Copy)]
-pub struct Huffman;
+pub struct Huffman {
+    max_code_length: Option<u8>,
+    preset: Option<Preset>,
+    profile: Option<[u8; 256]>,
+}
 
 impl Huffman {
     #[must_use]
     pub const fn new() -> Self {
-        Self
+        Self {
+            max_code_length: None,
+            preset: None,
+            profile: None,
+        }
     }
-}
 
-impl Compressor for Huffman {
-    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if input.is_empty() {
-            return Ok(Vec::new());
+    /// Creates a codec whose codes never exceed `limit` bits.
+    ///
+    /// Compression fails with `CompressionError::InvalidInput` if the input
+    /// has more distinct symbols than `limit` bits can address.
+    #[must_use]
+    pub const fn with_max_code_length(limit: u8) -> Self {
+        Self {
+            max_code_length: Some(limit),
+            preset: None,
+            profile: None,
         }
+    }
 
-        let freq_table = build_frequency_table(input);
-        let tree = build_huffman_tree(&freq_table)
+    /// Creates a codec that codes with `preset`'s built-in table rather
+    /// than storing one per frame. Decoding follows the preset named in
+    /// each frame, so any preset codec reads any preset frame.
+    #[must_use]
+    pub const fn with_preset(preset: Preset) -> Self {
+        Self {
+            max_code_length: None,
+            preset: Some(preset),
+            profile: None,
+        }
+    }
+
+    /// Creates a codec that codes with a table built from `profile`'s byte
+    /// frequencies, skipping the per-input count and stored table. The
+    /// decoder must be built from the same profile. Bytes the sample never
+    /// contained still get (long) codes.
+    #[must_use]
+    pub fn with_profile(profile: &Profile) -> Self {
+        Self {
+            max_code_length: None,
+            preset: None,
+            profile: Some(profile_code_lengths(profile)),
+        }
+    }
+
+    /// Starts a [`HuffmanBuilder`], for settings checked before any data
+    /// is compressed.
+    #[must_use]
+    pub const fn builder() -> HuffmanBuilder {
+        HuffmanBuilder::new()
+    }
+
+    #[must_use]
+    pub const fn max_code_length(&self) -> Option<u8> {
+        self.max_code_length
+    }
+
+    #[must_use]
+    pub const fn preset(&self) -> Option<Preset> {
+        self.preset
+    }
+
+    /// The frame tag and code lengths of a built-in or profile table.
+    fn fixed_table(&self) -> Option<(u8, [u8; 256])> {
+        match (self.preset, self.profile) {
+            (Some(preset), _) => Some((preset.tag(), preset.code_lengths())),
+            (None, Some(lengths)) => Some((PRESET_PROFILE, lengths)),
+            (None, None) => None,
+        }
+    }
+
+    /// Decodes the frame at the start of `input` into `output`, returning
+    /// the length of the frame.
+    fn decode_to(&self, input: &[u8], output: &mut impl Sink) -> Result<usize> {
+        if self.preset.is_none() && self.profile.is_none() {
+            return prefix_code::decode_partial_into(input, output);
+        }
+        decompress_preset(input, self.profile.as_ref(), output)
+    }
+
+    fn code_lengths(self, freq_table: &HashMap<u8, usize>) -> Result<[u8; 256]> {
+        if let Some(limit) = self.max_code_length {
+            if limit > MAX_CODE_LENGTH {
+                return Err(CompressionError::InvalidInput(format!(
+                    "max code length {limit} exceeds {MAX_CODE_LENGTH}"
+                )));
+            }
+            return package_merge_lengths(freq_table, limit).ok_or_else(|| {
+                CompressionError::InvalidInput(format!(
+                    "{} symbols do not fit in {limit}-bit codes",
+                    freq_table.len()
+                ))
+            });
+        }
+
+        let tree = build_huffman_tree(freq_table)
             .ok_or_else(|| CompressionError::InvalidInput("cannot build tree".to_string()))?;
+        let mut lengths = [0u8; 256];
+        tree.code_lengths(0, &mut lengths);
+        Ok(lengths)
+    }
+}
 
-        let mut codes = HashMap::new();
-        tree.build_codes(Vec::new(), &mut codes);
+/// Code lengths of the table [`Huffman::with_profile`] builds.
+fn profile_code_lengths(profile: &Profile) -> [u8; 256] {
+    let mut freqs = [0u64; 256];
+    for (freq, &count) in freqs.iter_mut().zip(profile.frequencies()) {
+        *freq = u64::from(count) + 1;
+    }
+    complete_code_lengths(&freqs)
+}
 
-        let mut bits = Vec::new();
-        for &byte in input {
-            let code = codes.get(&byte).ok_or(CompressionError::CorruptedData)?;
-            bits.extend(code);
+/// Builder for [`Huffman`] that rejects settings the codec would otherwise
+/// ignore or only report at compress time.
+///
+/// A code length limit outside `1..=MAX_CODE_LENGTH` fails every input, and
+/// a preset, a profile and a length limit each pick the code table, so
+/// [`HuffmanBuilder::build`] accepts at most one of them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HuffmanBuilder {
+    max_code_length: Option<u8>,
+    preset: Option<Preset>,
+    profile: Option<[u8; 256]>,
+}
+
+impl HuffmanBuilder {
+    /// Starts from the configuration of [`Huffman::new`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_code_length: None,
+            preset: None,
+            profile: None,
         }
+    }
 
-        let mut output = Vec::new();
+    /// See [`Huffman::with_max_code_length`].
+    #[must_use]
+    pub const fn with_max_code_length(mut self, limit: u8) -> Self {
+        self.max_code_length = Some(limit);
+        self
+    }
+
+    /// See [`Huffman::with_preset`].
+    #[must_use]
+    pub const fn with_preset(mut self, preset: Preset) -> Self {
+        self.preset = Some(preset);
+        self
+    }
 
-        serialize_tree(&tree, &mut output);
+    /// See [`Huffman::with_profile`].
+    #[must_use]
+    pub fn with_profile(mut self, profile: &Profile) -> Self {
+        self.profile = Some(profile_code_lengths(profile));
+        self
+    }
+
+    /// Checks the configuration and creates the codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the code length limit is
+    /// zero or above `MAX_CODE_LENGTH`, or more than one of a limit, a
+    /// preset and a profile was set.
+    pub fn build(self) -> Result<Huffman> {
+        if let Some(limit) = self
+            .max_code_length
+            .filter(|limit| !(1..=MAX_CODE_LENGTH).contains(limit))
+        {
+            return Err(CompressionError::InvalidInput(format!(
+                "max code length {limit} is outside 1..={MAX_CODE_LENGTH}"
+            )));
+        }
+        let tables = usize::from(self.max_code_length.is_some())
+            + usize::from(self.preset.is_some())
+            + usize::from(self.profile.is_some());
+        if tables > 1 {
+            return Err(CompressionError::InvalidInput(
+                "only one of a code length limit, a preset and a profile can be set".to_string(),
+            ));
+        }
+        Ok(Huffman {
+            max_code_length: self.max_code_length,
+            preset: self.preset,
+            profile: self.profile,
+        })
+    }
+}
 
-        let original_len = u32::try_from(input.len()).unwrap_or(u32::MAX);
-        output.extend_from_slice(&original_len.to_le_bytes());
+/// Size of the frame [`prefix_code::encode`] would write for input with
+/// these symbol counts, worked out from the counts so that inputs no code
+/// can shrink, such as near-uniform bytes, are stored without being coded.
+fn coded_len(freq_table: &HashMap<u8, usize>, lengths: &[u8; 256]) -> usize {
+    let bits: usize = freq_table
+        .iter()
+        .map(|(&byte, &count)| count * usize::from(lengths[usize::from(byte)]))
+        .sum();
+    prefix_code::code_lengths_len(lengths) + 8 + bits.div_ceil(8)
+}
 
-        let num_bits = u32::try_from(bits.len()).unwrap_or(u32::MAX);
-        output.extend_from_slice(&num_bits.to_le_bytes());
+fn compress_preset(input: &[u8], tag: u8, lengths: &[u8; 256]) -> Vec<u8> {
+    let codes = prefix_code::canonical_codes(lengths);
+    let mut bits = BitWriter::new();
+    for &byte in input {
+        let index = usize::from(byte);
+        bits.write_bits(codes[index], u32::from(lengths[index]));
+    }
+    let bits = bits.finish();
 
-        let encoded_bytes = bits_to_bytes(&bits);
-        output.extend_from_slice(&encoded_bytes);
+    let mut output = Vec::with_capacity(input.len().min(bits.len()) + 11);
+    if bits.len() < input.len() {
+        output.push(tag);
+        varint::write_u64(&mut output, input.len() as u64);
+        output.extend_from_slice(&bits);
+    } else {
+        output.push(PRESET_STORED);
+        varint::write_u64(&mut output, input.len() as u64);
+        output.extend_from_slice(input);
+    }
+    output
+}
 
-        Ok(output)
+fn decompress_preset(
+    input: &[u8],
+    profile: Option<&[u8; 256]>,
+    output: &mut impl Sink,
+) -> Result<usize> {
+    let &tag = input.first().ok_or(CompressionError::CorruptedData)?;
+    let mut pos = 1;
+    let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+        .map_err(|_| CompressionError::CorruptedData)?;
+
+    if tag == PRESET_STORED {
+        let stored = input
+            .get(pos..pos.saturating_add(original_len))
+            .ok_or(CompressionError::CorruptedData)?;
+        output.extend_from_slice(stored)?;
+        return Ok(pos + original_len);
     }
 
-    fn name(&self) -> &'static str {
-        "Huffman"
+    let lengths = match (tag, profile) {
+        (PRESET_PROFILE, Some(lengths)) => *lengths,
+        _ => Preset::from_tag(tag)
+            .ok_or(CompressionError::InvalidHeader)?
+            .code_lengths(),
+    };
+    let decoder = CanonicalDecoder::new(&lengths);
+    let mut bits = BitReader::new(&input[pos..]);
+    output.reserve(original_len.min((input.len() - pos) * 8));
+    for _ in 0..original_len {
+        output.push(decoder.decode_symbol(&mut bits)?)?;
     }
+    Ok(pos + bits.bytes_consumed())
 }
 
-impl Decompressor for Huffman {
-    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+impl Compressor for Huffman {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if let Some((tag, lengths)) = self.fixed_table() {
+            return Ok(compress_preset(input, tag, &lengths));
+        }
         if input.is_empty() {
-            return Ok(Vec::new());
+            return Ok(prefix_code::encode_empty());
+        }
+
+        let freq_table = build_frequency_table(input);
+        let lengths = self.code_lengths(&freq_table)?;
+        if coded_len(&freq_table, &lengths)
+            >= prefix_code::stored_overhead(input.len()) + input.len()
+        {
+            return Ok(prefix_code::encode_stored(input));
         }
+        Ok(prefix_code::encode(input, &lengths))
+    }
 
-        let mut pos = 0;
-        let tree = deserialize_tree(input, &mut pos)?;
+    /// Also counts the distinct bytes in `input`.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let output = self.compress(input)?;
+        let mut stats =
+            CompressionStats::new(input.len() as u64, output.len() as u64, start.elapsed());
+        let mut seen = [false; 256];
+        for &byte in input {
+            seen[usize::from(byte)] = true;
+        }
+        stats.symbols = Some(seen.iter().filter(|&&seen| seen).count() as u64);
+        Ok((output, stats))
+    }
 
-        if pos + 8 > input.len() {
-            return Err(CompressionError::CorruptedData);
+    /// Input a table would not shrink is stored, so only the framing is
+    /// ever added.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        if self.fixed_table().is_some() {
+            return (1 + varint::len_u64(input_len as u64)).saturating_add(input_len);
         }
+        prefix_code::stored_overhead(input_len)
+            .saturating_add(input_len)
+            .max(prefix_code::EMPTY_LEN)
+    }
 
-        let original_len = u32::from_le_bytes([
-            input[pos],
-            input[pos + 1],
-            input[pos + 2],
-            input[pos + 3],
-        ]) as usize;
-        pos += 4;
-
-        let num_bits = u32::from_le_bytes([
-            input[pos],
-            input[pos + 1],
-            input[pos + 2],
-            input[pos + 3],
-        ]) as usize;
-        pos += 4;
-
-        let encoded_bytes = &input[pos..];
-        let bits = bytes_to_bits(encoded_bytes, num_bits);
-
-        let mut output = Vec::with_capacity(original_len);
-        let mut current_node = &tree;
-        let mut bit_idx = 0;
-
-        while output.len() < original_len && bit_idx < bits.len() {
-            match &current_node.data {
-                NodeData::Leaf(byte) => {
-                    output.push(*byte);
-                    current_node = &tree;
-                }
-                NodeData::Internal { left, right } => {
-                    current_node = if bits[bit_idx] { right } else { left };
-                    bit_idx += 1;
-                }
-            }
+    fn name(&self) -> &'static str {
+        "Huffman"
+    }
+}
+
+impl Decompressor for Huffman {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
         }
+        Ok(output)
+    }
 
-        if let NodeData::Leaf(byte) = &current_node.data
-            && output.len() < original_len
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        if self
+            .decompressed_len(input)?
+            .is_some_and(|len| len > output.len() as u64)
         {
-            output.push(*byte);
+            return Err(CompressionError::BufferTooSmall);
         }
-
-        if output.len() != original_len {
+        let mut sink = SliceSink::new(output);
+        if self.decode_to(input, &mut sink)? != input.len() {
             return Err(CompressionError::CorruptedData);
         }
+        Ok(sink.len())
+    }
 
-        Ok(output)
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        if self.preset.is_none() && self.profile.is_none() {
+            return prefix_code::decoded_len(input).map(Some);
+        }
+        if input.is_empty() {
+            return Err(CompressionError::CorruptedData);
+        }
+        varint::read_u64(input, &mut 1).map(Some)
     }
 
     fn name(&self) -> &'static str {
@@ -279,6 +670,231 @@ impl Decompressor for Huffman {
     }
 }
 
+impl PartialDecompressor for Huffman {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut output = Vec::new();
+        let consumed = self.decode_to(input, &mut output)?;
+        Ok((output, consumed))
+    }
+}
+
+/// A symbol's codeword in a canonical prefix code, from [`build_codes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeLen {
+    /// Codeword length in bits; zero for symbols that never occur.
+    pub len: u8,
+    /// The codeword in the low `len` bits, to be written most significant
+    /// bit first.
+    pub code: u64,
+}
+
+/// Longest codeword [`build_codes`] produces, so every code fits a `u64`.
+pub const MAX_BUILT_CODE_LENGTH: u8 = 64;
+
+/// Huffman code lengths for `freqs`, zero for unused symbols. Weights are
+/// summed in `u128` so no combination of `u64` frequencies overflows.
+fn huffman_lengths(freqs: &[u64]) -> Vec<u32> {
+    let used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+    let mut lengths = vec![0; freqs.len()];
+    if let [only] = used[..] {
+        lengths[only] = 1;
+    }
+    if used.len() < 2 {
+        return lengths;
+    }
+
+    // Leaves are nodes 0..n and each merge appends a node, so a parent
+    // always has a higher index than its children.
+    let mut heap: BinaryHeap<Reverse<(u128, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((u128::from(freqs[symbol]), node)))
+        .collect();
+    let mut parents = vec![0; 2 * used.len() - 1];
+    let mut next = used.len();
+    while let (Some(Reverse((a, left))), Some(Reverse((b, right)))) = (heap.pop(), heap.pop()) {
+        parents[left] = next;
+        parents[right] = next;
+        heap.push(Reverse((a + b, next)));
+        next += 1;
+    }
+
+    let mut depths = vec![0; parents.len()];
+    for node in (0..parents.len() - 1).rev() {
+        depths[node] = depths[parents[node]] + 1;
+    }
+    for (node, &symbol) in used.iter().enumerate() {
+        lengths[symbol] = depths[node];
+    }
+    lengths
+}
+
+/// Builds an optimal prefix code for an alphabet of any size, given each
+/// symbol's frequency.
+///
+/// This is the construction behind [`Huffman`], freed from byte alphabets
+/// for symbol sets of your own such as opcodes. Entry `i` of the result is the codeword for
+/// symbol `i`; codewords are canonical, so equal lengths count up in symbol
+/// order and the lengths alone are enough for a decoder to rebuild them. A
+/// lone used symbol gets a 1-bit code. Should an optimal code need words
+/// longer than `MAX_BUILT_CODE_LENGTH` bits, which takes frequencies
+/// growing like Fibonacci numbers across dozens of symbols, frequencies are
+/// halved until it fits and the result is no longer strictly optimal.
+#[must_use]
+pub fn build_codes(freqs: &[u64]) -> Vec<CodeLen> {
+    let mut lengths = huffman_lengths(freqs);
+    let mut shift = 0;
+    while lengths
+        .iter()
+        .any(|&len| len > u32::from(MAX_BUILT_CODE_LENGTH))
+    {
+        shift += 1;
+        let scaled: Vec<u64> = freqs
+            .iter()
+            .map(|&freq| if freq == 0 { 0 } else { (freq >> shift).max(1) })
+            .collect();
+        lengths = huffman_lengths(&scaled);
+    }
+
+    let mut order: Vec<usize> = (0..freqs.len()).filter(|&i| lengths[i] > 0).collect();
+    order.sort_by_key(|&i| (lengths[i], i));
+    let mut codes = vec![CodeLen { len: 0, code: 0 }; freqs.len()];
+    let mut code = 0u64;
+    let mut previous_len = order.first().map_or(0, |&i| lengths[i]);
+    for &symbol in &order {
+        let len = lengths[symbol];
+        code <<= len - previous_len;
+        previous_len = len;
+        codes[symbol] = CodeLen {
+            len: u8::try_from(len).unwrap_or(MAX_BUILT_CODE_LENGTH),
+            code,
+        };
+        code = code.wrapping_add(1);
+    }
+    codes
+}
+
+/// How one symbol's Huffman code compares with its ideal length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolEfficiency {
+    pub symbol: u8,
+    pub count: usize,
+    /// Length of the symbol's Huffman code in bits.
+    pub code_length: u8,
+    /// Information content `-log2(p)`, the length an ideal entropy coder
+    /// would spend.
+    pub ideal_bits: f64,
+    /// Bits lost over the whole input: `count * (code_length - ideal_bits)`.
+    /// Negative when the code is shorter than ideal.
+    pub loss_bits: f64,
+}
+
+/// Comparison of Huffman code lengths with the entropy bound, from
+/// [`analyze_code_efficiency`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EfficiencyReport {
+    pub total_symbols: usize,
+    /// Shannon entropy of the input in bits per symbol.
+    pub entropy_bits: f64,
+    /// Mean Huffman code length in bits per symbol.
+    pub average_code_length: f64,
+    /// Every symbol present, largest loss first.
+    pub symbols: Vec<SymbolEfficiency>,
+}
+
+impl EfficiencyReport {
+    /// Bits per symbol spent above the entropy bound. This is the most an
+    /// arithmetic or rANS coder could save on the payload.
+    #[must_use]
+    pub fn redundancy(&self) -> f64 {
+        self.average_code_length - self.entropy_bits
+    }
+
+    /// Ratio of entropy to mean code length; `1.0` for empty input.
+    #[must_use]
+    pub fn efficiency(&self) -> f64 {
+        if self.average_code_length == 0.0 {
+            1.0
+        } else {
+            self.entropy_bits / self.average_code_length
+        }
+    }
+
+    /// Total payload bits lost to whole-bit code lengths.
+    #[must_use]
+    pub fn total_loss_bits(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let total = self.total_symbols as f64;
+        self.redundancy() * total
+    }
+
+    /// The `n` symbols with the largest losses.
+    #[must_use]
+    pub fn worst(&self, n: usize) -> &[SymbolEfficiency] {
+        &self.symbols[..n.min(self.symbols.len())]
+    }
+}
+
+/// Compares the Huffman code lengths for `data` with the entropy bound,
+/// symbol by symbol.
+///
+/// Useful when deciding whether an arithmetic or rANS coder is worth its
+/// cost for a dataset: a small [`EfficiencyReport::redundancy`] means
+/// Huffman is already close to optimal. The code-length table is not
+/// counted.
+#[must_use]
+pub fn analyze_code_efficiency(data: &[u8]) -> EfficiencyReport {
+    let freq_table = build_frequency_table(data);
+    let mut lengths = [0u8; 256];
+    if let Some(tree) = build_huffman_tree(&freq_table) {
+        tree.code_lengths(0, &mut lengths);
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let total = data.len() as f64;
+    let mut symbols: Vec<SymbolEfficiency> = freq_table
+        .iter()
+        .map(|(&symbol, &count)| {
+            #[allow(clippy::cast_precision_loss)]
+            let count_f = count as f64;
+            let code_length = lengths[usize::from(symbol)];
+            let ideal_bits = -(count_f / total).log2();
+            SymbolEfficiency {
+                symbol,
+                count,
+                code_length,
+                ideal_bits,
+                loss_bits: count_f * (f64::from(code_length) - ideal_bits),
+            }
+        })
+        .collect();
+    symbols.sort_unstable_by(|a, b| {
+        b.loss_bits
+            .total_cmp(&a.loss_bits)
+            .then(a.symbol.cmp(&b.symbol))
+    });
+
+    let (entropy_bits, average_code_length) = if data.is_empty() {
+        (0.0, 0.0)
+    } else {
+        symbols.iter().fold((0.0, 0.0), |(entropy, average), s| {
+            #[allow(clippy::cast_precision_loss)]
+            let p = s.count as f64 / total;
+            (
+                p.mul_add(s.ideal_bits, entropy),
+                p.mul_add(f64::from(s.code_length), average),
+            )
+        })
+    };
+
+    EfficiencyReport {
+        total_symbols: data.len(),
+        entropy_bits,
+        average_code_length,
+        symbols,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,14 +915,17 @@ mod tests {
     fn test_compress_empty() {
         let huffman = Huffman::new();
         let result = huffman.compress(&[]).unwrap();
-        assert!(result.is_empty());
+        assert!(!result.is_empty());
+        assert!(huffman.decompress(&result).unwrap().is_empty());
     }
 
     #[test]
     fn test_decompress_empty() {
         let huffman = Huffman::new();
-        let result = huffman.decompress(&[]).unwrap();
-        assert!(result.is_empty());
+        assert!(matches!(
+            huffman.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
     }
 
     #[test]
@@ -424,66 +1043,40 @@ mod tests {
     }
 
     #[test]
-    fn test_bits_to_bytes() {
-        let bits = vec![true, false, true, false, true, false, true, false];
-        let bytes = bits_to_bytes(&bits);
-        assert_eq!(bytes, vec![0b10101010]);
-    }
-
-    #[test]
-    fn test_bits_to_bytes_partial() {
-        let bits = vec![true, true, true];
-        let bytes = bits_to_bytes(&bits);
-        assert_eq!(bytes, vec![0b11100000]);
-    }
-
-    #[test]
-    fn test_bytes_to_bits() {
-        let bytes = vec![0b10101010];
-        let bits = bytes_to_bits(&bytes, 8);
-        assert_eq!(bits, vec![true, false, true, false, true, false, true, false]);
-    }
-
-    #[test]
-    fn test_bytes_to_bits_partial() {
-        let bytes = vec![0b11100000];
-        let bits = bytes_to_bits(&bytes, 3);
-        assert_eq!(bits, vec![true, true, true]);
-    }
-
-    #[test]
-    fn test_serialize_deserialize_tree() {
-        let left = HuffmanNode::new_leaf(b'a', 5);
-        let right = HuffmanNode::new_leaf(b'b', 3);
-        let tree = HuffmanNode::new_internal(left, right);
-
-        let mut serialized = Vec::new();
-        serialize_tree(&tree, &mut serialized);
-
-        let mut pos = 0;
-        let deserialized = deserialize_tree(&serialized, &mut pos).unwrap();
-
-        assert_eq!(tree.frequency, 8);
-        match deserialized.data {
-            NodeData::Internal { left, right } => {
-                assert!(matches!(left.data, NodeData::Leaf(b'a')));
-                assert!(matches!(right.data, NodeData::Leaf(b'b')));
-            }
-            _ => panic!("Expected internal node"),
-        }
+    fn test_decompress_bit_count_mismatch() {
+        let huffman = Huffman::new();
+        // 32 one-bit codes fill four bytes, big enough not to be stored.
+        let mut input = vec![b'a'; 31];
+        input.push(b'b');
+        let mut compressed = huffman.compress(&input).unwrap();
+        let len = compressed.len();
+        compressed[len - 5] ^= 0x01; // bump the stored bit count
+        assert!(huffman.decompress(&compressed).is_err());
     }
 
     #[test]
-    fn test_deserialize_tree_corrupted() {
-        let result = deserialize_tree(&[], &mut 0);
-        assert!(result.is_err());
+    fn test_compress_with_stats() {
+        let input = b"the quick brown fox jumps over the lazy dog";
+        let (output, stats) = Huffman::new().compress_with_stats(input).unwrap();
+        assert_eq!(output, Huffman::new().compress(input).unwrap());
+        assert_eq!(stats.symbols, Some(27));
+        assert_eq!((stats.tokens, stats.matches), (None, None));
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (input.len() as u64, output.len() as u64)
+        );
+        let (_, stats) = Huffman::new().compress_with_stats(b"").unwrap();
+        assert_eq!(stats.symbols, Some(0));
     }
 
     #[test]
-    fn test_deserialize_tree_truncated_leaf() {
-        let data = vec![1];
-        let result = deserialize_tree(&data, &mut 0);
-        assert!(result.is_err());
+    fn test_compressed_output_is_deterministic() {
+        let huffman = Huffman::new();
+        let input = b"the quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            huffman.compress(input).unwrap(),
+            huffman.compress(input).unwrap()
+        );
     }
 
     #[test]
@@ -547,12 +1140,95 @@ mod tests {
     }
 
     #[test]
-    fn test_build_codes_single_symbol() {
+    fn test_code_lengths_single_symbol() {
         let node = HuffmanNode::new_leaf(b'x', 10);
-        let mut codes = HashMap::new();
-        node.build_codes(Vec::new(), &mut codes);
-        assert!(codes.contains_key(&b'x'));
-        assert!(!codes.get(&b'x').unwrap().is_empty());
+        let mut lengths = [0u8; 256];
+        node.code_lengths(0, &mut lengths);
+        assert_eq!(lengths[usize::from(b'x')], 1);
+    }
+
+    fn fibonacci_input() -> Vec<u8> {
+        let mut input = Vec::new();
+        let (mut a, mut b) = (1usize, 1usize);
+        for symbol in 0..20u8 {
+            input.extend(std::iter::repeat_n(symbol, a));
+            (a, b) = (b, a + b);
+        }
+        input
+    }
+
+    #[test]
+    fn test_huffman_with_max_code_length() {
+        let huffman = Huffman::with_max_code_length(15);
+        assert_eq!(huffman.max_code_length(), Some(15));
+        assert_eq!(Huffman::new().max_code_length(), None);
+    }
+
+    #[test]
+    fn test_package_merge_respects_limit() {
+        let freq = build_frequency_table(&fibonacci_input());
+        let unlimited = Huffman::new().code_lengths(&freq).unwrap();
+        assert!(unlimited.iter().copied().max().unwrap() > 8);
+
+        let limited = package_merge_lengths(&freq, 8).unwrap();
+        assert_eq!(limited.iter().copied().max().unwrap(), 8);
+        let kraft: f64 = limited
+            .iter()
+            .filter(|&&len| len > 0)
+            .map(|&len| 0.5f64.powi(i32::from(len)))
+            .sum();
+        assert!(kraft <= 1.0);
+    }
+
+    #[test]
+    fn test_package_merge_matches_huffman_when_unconstrained() {
+        let freq = build_frequency_table(b"the quick brown fox jumps over the lazy dog");
+        let limited = package_merge_lengths(&freq, 32).unwrap();
+        let unlimited = Huffman::new().code_lengths(&freq).unwrap();
+        let cost = |lengths: &[u8; 256]| -> usize {
+            freq.iter()
+                .map(|(&b, &f)| f * usize::from(lengths[usize::from(b)]))
+                .sum()
+        };
+        assert_eq!(cost(&limited), cost(&unlimited));
+    }
+
+    #[test]
+    fn test_package_merge_single_symbol() {
+        let freq = build_frequency_table(b"aaaa");
+        let lengths = package_merge_lengths(&freq, 4).unwrap();
+        assert_eq!(lengths[usize::from(b'a')], 1);
+    }
+
+    #[test]
+    fn test_package_merge_limit_too_small() {
+        let freq = build_frequency_table(b"abcde");
+        assert!(package_merge_lengths(&freq, 2).is_none());
+        assert!(package_merge_lengths(&freq, 3).is_some());
+    }
+
+    #[test]
+    fn test_roundtrip_length_limited() {
+        let huffman = Huffman::with_max_code_length(7);
+        let input = fibonacci_input();
+        let compressed = huffman.compress(&input).unwrap();
+        let decompressed = Huffman::new().decompress(&compressed).unwrap();
+        assert_eq!(decompressed, input);
+    }
+
+    #[test]
+    fn test_compress_limit_too_small_for_alphabet() {
+        let huffman = Huffman::with_max_code_length(4);
+        let input: Vec<u8> = (0..=255).collect();
+        let result = huffman.compress(&input);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_compress_limit_above_format_maximum() {
+        let huffman = Huffman::with_max_code_length(64);
+        let result = huffman.compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
     }
 
     #[test]
@@ -561,4 +1237,468 @@ mod tests {
         let node2 = HuffmanNode::new_leaf(b'b', 5);
         assert!(node1.partial_cmp(&node2).is_some());
     }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let huffman = Huffman::new();
+        let first = huffman.compress(b"mississippi").unwrap();
+        let second = huffman.compress(b"").unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+
+        let (data, consumed) = huffman.decompress_partial(&stream).unwrap();
+        assert_eq!(data, b"mississippi");
+        assert_eq!(consumed, first.len());
+        assert_eq!(huffman.decompress_frames(&stream).unwrap().len(), 3);
+        assert!(huffman.decompress(&stream).is_err());
+        assert!(
+            huffman
+                .decompress_partial(&first[..first.len() - 1])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_efficiency_dyadic_is_optimal() {
+        // Probabilities 1/2, 1/4, 1/8, 1/8 give integer ideal lengths.
+        let report = analyze_code_efficiency(b"aaaabbcd");
+        assert_eq!(report.total_symbols, 8);
+        assert!((report.entropy_bits - 1.75).abs() < 1e-9);
+        assert!((report.average_code_length - 1.75).abs() < 1e-9);
+        assert!(report.redundancy().abs() < 1e-9);
+        assert!((report.efficiency() - 1.0).abs() < 1e-9);
+        assert!(report.symbols.iter().all(|s| s.loss_bits.abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_efficiency_skewed_two_symbols() {
+        // A 1-bit floor wastes most of the code on a 99:1 split.
+        let mut data = vec![b'a'; 99];
+        data.push(b'b');
+        let report = analyze_code_efficiency(&data);
+        assert!(report.entropy_bits < 0.1);
+        assert!((report.average_code_length - 1.0).abs() < 1e-9);
+        assert!(report.efficiency() < 0.1);
+        let losses: f64 = report.symbols.iter().map(|s| s.loss_bits).sum();
+        assert!((report.total_loss_bits() - losses).abs() < 1e-9);
+
+        let worst = report.worst(1);
+        assert_eq!(worst[0].symbol, b'a');
+        assert_eq!(worst[0].count, 99);
+        assert_eq!(worst[0].code_length, 1);
+        assert!(report.symbols[1].loss_bits < 0.0);
+        assert_eq!(report.worst(10).len(), 2);
+    }
+
+    #[test]
+    fn test_efficiency_matches_compressed_payload() {
+        let data = b"the quick brown fox jumps over the lazy dog";
+        let report = analyze_code_efficiency(data);
+        let lengths = Huffman::new()
+            .code_lengths(&build_frequency_table(data))
+            .unwrap();
+        let bits: usize = data
+            .iter()
+            .map(|&b| usize::from(lengths[usize::from(b)]))
+            .sum();
+        #[allow(clippy::cast_precision_loss)]
+        let expected = bits as f64 / data.len() as f64;
+        assert!((report.average_code_length - expected).abs() < 1e-9);
+        assert!(report.redundancy() >= 0.0 && report.redundancy() < 1.0);
+        assert!(
+            report
+                .symbols
+                .windows(2)
+                .all(|pair| pair[0].loss_bits >= pair[1].loss_bits)
+        );
+    }
+
+    #[test]
+    fn test_efficiency_empty_and_single_symbol() {
+        let empty = analyze_code_efficiency(&[]);
+        assert_eq!(empty.total_symbols, 0);
+        assert!(empty.symbols.is_empty());
+        assert!((empty.efficiency() - 1.0).abs() < f64::EPSILON);
+
+        let single = analyze_code_efficiency(b"zzzz");
+        assert!(single.entropy_bits.abs() < f64::EPSILON);
+        assert!((single.total_loss_bits() - 4.0).abs() < 1e-9);
+        assert!(single.efficiency().abs() < f64::EPSILON);
+    }
+
+    /// Checks the code is complete and prefix-free: canonical codes of a
+    /// complete code end on all ones at the longest length.
+    fn assert_complete(codes: &[CodeLen]) {
+        let kraft: u128 = codes
+            .iter()
+            .filter(|c| c.len > 0)
+            .map(|c| 1u128 << (MAX_BUILT_CODE_LENGTH - c.len))
+            .sum();
+        assert_eq!(kraft, 1u128 << MAX_BUILT_CODE_LENGTH);
+        for (i, a) in codes.iter().enumerate().filter(|(_, c)| c.len > 0) {
+            for b in codes[i + 1..].iter().filter(|c| c.len > 0) {
+                let (short, long) = if a.len <= b.len { (a, b) } else { (b, a) };
+                assert_ne!(long.code >> (long.len - short.len), short.code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_codes_textbook() {
+        let codes = build_codes(&[45, 13, 12, 16, 9, 5]);
+        let expected = [
+            (1, 0b0),
+            (3, 0b100),
+            (3, 0b101),
+            (3, 0b110),
+            (4, 0b1110),
+            (4, 0b1111),
+        ];
+        let actual: Vec<(u8, u64)> = codes.iter().map(|c| (c.len, c.code)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_build_codes_degenerate() {
+        assert!(build_codes(&[]).is_empty());
+        assert_eq!(build_codes(&[0, 0]), [CodeLen { len: 0, code: 0 }; 2]);
+        let codes = build_codes(&[0, 7, 0]);
+        assert_eq!(codes[1], CodeLen { len: 1, code: 0 });
+        assert_eq!(codes[0].len + codes[2].len, 0);
+    }
+
+    #[test]
+    fn test_build_codes_large_alphabet() {
+        let freqs: Vec<u64> = (0..1500u64)
+            .map(|i| (i * 7919) % 1000 + u64::from(i % 3 == 0))
+            .collect();
+        let codes = build_codes(&freqs);
+        assert_eq!(codes.len(), 1500);
+        assert_complete(&codes);
+        let unused = freqs.iter().filter(|&&f| f == 0).count();
+        assert_eq!(codes.iter().filter(|c| c.len == 0).count(), unused);
+    }
+
+    #[test]
+    fn test_build_codes_matches_huffman_cost() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog";
+        let freq_table = build_frequency_table(data);
+        let mut freqs = vec![0u64; 256];
+        for (&byte, &count) in &freq_table {
+            freqs[usize::from(byte)] = count as u64;
+        }
+        let lengths = Huffman::new().code_lengths(&freq_table).unwrap();
+        let cost = |len: &dyn Fn(usize) -> u64| (0..256).map(|i| freqs[i] * len(i)).sum::<u64>();
+        let codes = build_codes(&freqs);
+        assert_eq!(
+            cost(&|i| u64::from(codes[i].len)),
+            cost(&|i| u64::from(lengths[i]))
+        );
+    }
+
+    #[test]
+    fn test_build_codes_caps_length() {
+        // Fibonacci frequencies make an optimal code 92 bits deep.
+        let mut freqs = vec![1u64, 1];
+        while let Some(next) = freqs[freqs.len() - 2].checked_add(freqs[freqs.len() - 1]) {
+            freqs.push(next);
+        }
+        assert_eq!(huffman_lengths(&freqs).into_iter().max(), Some(92));
+        let codes = build_codes(&freqs);
+        assert!(codes.iter().all(|c| c.len <= MAX_BUILT_CODE_LENGTH));
+        assert_complete(&codes);
+    }
+
+    const PRESETS: [Preset; 4] = [
+        Preset::EnglishText,
+        Preset::Json,
+        Preset::Base64,
+        Preset::Hex,
+    ];
+
+    const PROSE: &[u8] = b"It was the best of times, it was the worst of times, it was \
+        the age of wisdom, it was the age of foolishness, it was the epoch of \
+        belief, it was the epoch of incredulity, it was the season of Light, \
+        it was the season of Darkness.\n";
+
+    fn preset_roundtrip(preset: Preset, input: &[u8]) -> Vec<u8> {
+        let codec = Huffman::with_preset(preset);
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_preset_tables_are_complete() {
+        for preset in PRESETS {
+            let lengths = preset.code_lengths();
+            assert!(lengths.iter().all(|&len| (1..=MAX_CODE_LENGTH).contains(&len)));
+            let kraft: u128 = lengths
+                .iter()
+                .map(|&len| 1u128 << (MAX_CODE_LENGTH - len))
+                .sum();
+            assert_eq!(kraft, 1u128 << MAX_CODE_LENGTH, "{preset:?}");
+            assert_eq!(Preset::from_tag(preset.tag()), Some(preset));
+        }
+        assert_eq!(Preset::from_tag(PRESET_STORED), None);
+    }
+
+    #[test]
+    fn test_preset_accessors() {
+        let codec = Huffman::with_preset(Preset::Json);
+        assert_eq!(codec.preset(), Some(Preset::Json));
+        assert_eq!(codec.max_code_length(), None);
+        assert_eq!(Huffman::new().preset(), None);
+        assert_eq!(Compressor::name(&codec), "Huffman");
+    }
+
+    #[test]
+    fn test_preset_empty() {
+        for preset in PRESETS {
+            assert_eq!(preset_roundtrip(preset, &[]), [PRESET_STORED, 0]);
+            assert!(Huffman::with_preset(preset).decompress(&[]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_preset_ratios() {
+        let prose = preset_roundtrip(Preset::EnglishText, PROSE);
+        assert_eq!(prose[0], PRESET_ENGLISH_TEXT);
+        assert!(prose.len() * 100 < PROSE.len() * 60, "{}", prose.len());
+
+        let json = br#"{"id":1042,"name":"sensor-7","active":true,"unit":"celsius"}"#;
+        let compressed = preset_roundtrip(Preset::Json, json);
+        assert!(compressed.len() * 100 < json.len() * 75, "{}", compressed.len());
+
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let base64: Vec<u8> = random_bytes(600)
+            .iter()
+            .map(|&byte| alphabet[usize::from(byte & 63)])
+            .chain(*b"==")
+            .collect();
+        let compressed = preset_roundtrip(Preset::Base64, &base64);
+        assert!(compressed.len() * 100 < base64.len() * 78, "{}", compressed.len());
+
+        let hex: Vec<u8> = random_bytes(300)
+            .iter()
+            .flat_map(|byte| format!("{byte:02x}").into_bytes())
+            .collect();
+        let compressed = preset_roundtrip(Preset::Hex, &hex);
+        assert!(compressed.len() * 100 < hex.len() * 55, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_preset_beats_stored_table_on_short_messages() {
+        for (preset, message) in [
+            (Preset::Json, &br#"{"ok":true,"id":17}"#[..]),
+            (Preset::EnglishText, b"see you at the station at noon"),
+            (Preset::Hex, b"deadbeef0badf00d"),
+        ] {
+            let with_preset = preset_roundtrip(preset, message).len();
+            let dynamic = Huffman::new().compress(message).unwrap().len();
+            assert!(with_preset < message.len(), "{preset:?}");
+            assert!(with_preset < dynamic, "{with_preset} vs {dynamic}");
+        }
+    }
+
+    #[test]
+    fn test_preset_falls_back_to_stored() {
+        let noise = random_bytes(500);
+        let compressed = preset_roundtrip(Preset::Hex, &noise);
+        assert_eq!(compressed[..3], [PRESET_STORED, 0xF4, 0x03]);
+        assert_eq!(compressed.len(), 3 + noise.len());
+        // Bytes outside the alphabet cost more than a byte each.
+        let upper = preset_roundtrip(Preset::Base64, b"\x00\x01\xFF");
+        assert_eq!(upper[0], PRESET_STORED);
+    }
+
+    #[test]
+    fn test_incompressible_input_is_stored() {
+        let huffman = Huffman::new();
+        for input in [random_bytes(4096), random_bytes(3), b"abcd".to_vec()] {
+            let compressed = huffman.compress(&input).unwrap();
+            assert_eq!(
+                compressed,
+                prefix_code::encode_stored(&input),
+                "{}",
+                input.len()
+            );
+            assert_eq!(huffman.decompress(&compressed).unwrap(), input);
+        }
+        // Skewed input still gets its own table.
+        let skewed: Vec<u8> = random_bytes(4096).iter().map(|&b| b % 16).collect();
+        let compressed = huffman.compress(&skewed).unwrap();
+        assert!(compressed.len() < skewed.len() * 5 / 8);
+        assert_eq!(huffman.decompress(&compressed).unwrap(), skewed);
+    }
+
+    #[test]
+    fn test_preset_frames_name_their_table() {
+        let json = Huffman::with_preset(Preset::Json)
+            .compress(br#"{"a":1}"#)
+            .unwrap();
+        let hex = Huffman::with_preset(Preset::Hex);
+        assert_eq!(hex.decompress(&json).unwrap(), br#"{"a":1}"#);
+        assert!(Huffman::new().decompress(&json).is_err());
+    }
+
+    #[test]
+    fn test_preset_decompress_partial_back_to_back() {
+        let codec = Huffman::with_preset(Preset::EnglishText);
+        let first = codec.compress(PROSE).unwrap();
+        let second = codec.compress(&random_bytes(20)).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [PROSE.to_vec(), random_bytes(20), PROSE.to_vec()]);
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_preset_decompress_corrupted() {
+        let codec = Huffman::with_preset(Preset::EnglishText);
+        let compressed = codec.compress(PROSE).unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        let mut unknown = compressed;
+        unknown[0] = 9;
+        assert!(matches!(
+            codec.decompress(&unknown),
+            Err(CompressionError::InvalidHeader)
+        ));
+        assert!(codec.decompress(&[PRESET_STORED, 5, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_decompressed_len() {
+        let noise = random_bytes(50);
+        for codec in [Huffman::new(), Huffman::with_preset(Preset::EnglishText)] {
+            for input in [PROSE, &noise, b""] {
+                let compressed = codec.compress(input).unwrap();
+                let len = codec.decompressed_len(&compressed).unwrap();
+                assert_eq!(len, Some(input.len() as u64));
+            }
+            assert!(codec.decompressed_len(&[]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_profile_table() {
+        let sample = b"GET /api/v1/items?page=3 200 12ms\nGET /api/v1/users 404 3ms\n".repeat(30);
+        let profile = Profile::capture(&sample);
+        let codec = Huffman::with_profile(&profile);
+        assert_eq!(codec.preset(), None);
+
+        let message = b"GET /api/v1/items?page=7 200 9ms\n";
+        let compressed = codec.compress(message).unwrap();
+        assert_eq!(compressed[0], PRESET_PROFILE);
+        assert_eq!(codec.decompress(&compressed).unwrap(), message);
+        // A table of its own would cost more than the message saves, so
+        // without the profile it is stored.
+        let dynamic = Huffman::new().compress(message).unwrap();
+        assert_eq!(dynamic, prefix_code::encode_stored(message));
+        assert!(compressed.len() * 3 < dynamic.len() * 2, "{compressed:?}");
+
+        // Bytes the sample never held still round-trip, stored if need be.
+        for input in [&b"\x00\xFF{}"[..], b"GET /api/v1/\xE2\x82\xAC"] {
+            let compressed = codec.compress(input).unwrap();
+            assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_profile_frames_need_the_profile() {
+        let profile = Profile::capture(&b"abracadabra ".repeat(50));
+        let compressed = Huffman::with_profile(&profile)
+            .compress(b"a bad cab, a bar")
+            .unwrap();
+        assert_eq!(compressed[0], PRESET_PROFILE);
+        assert!(matches!(
+            Huffman::with_preset(Preset::Json).decompress(&compressed),
+            Err(CompressionError::InvalidHeader)
+        ));
+        assert!(Huffman::new().decompress(&compressed).is_err());
+        // A profile codec still reads preset frames.
+        let json = Huffman::with_preset(Preset::Json).compress(b"{}").unwrap();
+        assert_eq!(
+            Huffman::with_profile(&profile).decompress(&json).unwrap(),
+            b"{}"
+        );
+    }
+
+    #[test]
+    fn test_builder_validates() {
+        let text = b"a builder catches bad limits before the first frame".repeat(4);
+        let limited = Huffman::builder().with_max_code_length(8).build().unwrap();
+        assert_eq!(limited.max_code_length(), Some(8));
+        let compressed = limited.compress(&text).unwrap();
+        assert_eq!(Huffman::new().decompress(&compressed).unwrap(), text);
+
+        let profile = Profile::capture(&text);
+        let built = Huffman::builder().with_profile(&profile).build().unwrap();
+        assert_eq!(
+            built.compress(&text).unwrap(),
+            Huffman::with_profile(&profile).compress(&text).unwrap()
+        );
+        let json = Huffman::builder()
+            .with_preset(Preset::Json)
+            .build()
+            .unwrap();
+        assert_eq!(json.preset(), Some(Preset::Json));
+
+        for builder in [
+            Huffman::builder().with_max_code_length(0),
+            Huffman::builder().with_max_code_length(MAX_CODE_LENGTH + 1),
+            Huffman::builder()
+                .with_preset(Preset::Hex)
+                .with_profile(&profile),
+            Huffman::builder()
+                .with_max_code_length(12)
+                .with_preset(Preset::Json),
+        ] {
+            assert!(matches!(
+                builder.build(),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        let text = b"the quick brown fox jumps over the lazy dog".repeat(8);
+        let stored: Vec<u8> = (0..=255).collect();
+        for codec in [Huffman::new(), Huffman::with_preset(Preset::EnglishText)] {
+            for input in [&text[..], &stored, b""] {
+                let compressed = codec.compress(input).unwrap();
+                let mut output = vec![0; input.len() + 10];
+                let len = codec.decompress_into(&compressed, &mut output).unwrap();
+                assert_eq!(output[..len], *input);
+                if let Some(short) = input.len().checked_sub(1) {
+                    assert_eq!(
+                        codec.decompress_into(&compressed, &mut output[..short]),
+                        Err(CompressionError::BufferTooSmall)
+                    );
+                }
+                let mut trailing = compressed;
+                trailing.push(0);
+                assert_eq!(
+                    codec.decompress_into(&trailing, &mut output),
+                    Err(CompressionError::CorruptedData)
+                );
+            }
+        }
+    }
 }
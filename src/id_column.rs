@@ -0,0 +1,311 @@
+use crate::error::{CompressionError, Result};
+use crate::huffman::Huffman;
+use crate::sequence::{decode_i64_sequence, encode_i64_sequence};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Bytes per identifier.
+pub const ID_LEN: usize = 16;
+/// Bytes of the big-endian millisecond timestamp leading each identifier.
+pub const TIMESTAMP_LEN: usize = 6;
+/// Column stored as raw bytes.
+pub const COLUMN_STORED: u8 = 0;
+/// Column stored as a Huffman frame.
+pub const COLUMN_HUFFMAN: u8 = 1;
+
+/// Column codec for 16-byte time-ordered identifiers such as ULIDs and
+/// version 7 UUIDs.
+///
+/// Both put a 48-bit big-endian millisecond timestamp in the first six
+/// bytes. Those are split off and delta-coded as a sequence, so IDs minted
+/// in order cost a byte or two of timestamp instead of six. The remaining
+/// ten bytes are transposed into one column per byte position and each
+/// column is Huffman-coded or stored, whichever is smaller: random bytes
+/// stay stored, while fixed version and variant bits shrink. Identifiers
+/// without a timestamp prefix still round-trip. Output layout:
+/// `[timestamps length: varint][timestamps: i64 sequence]` then, for each of
+/// the ten columns, `[mode: u8][column bytes or Huffman frame]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdColumn;
+
+impl IdColumn {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+fn timestamp(id: &[u8]) -> i64 {
+    let mut bytes = [0u8; 8];
+    bytes[8 - TIMESTAMP_LEN..].copy_from_slice(&id[..TIMESTAMP_LEN]);
+    i64::from_be_bytes(bytes)
+}
+
+impl Compressor for IdColumn {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if !input.len().is_multiple_of(ID_LEN) {
+            return Err(CompressionError::InvalidInput(format!(
+                "input length {} is not a multiple of {ID_LEN}-byte identifiers",
+                input.len()
+            )));
+        }
+
+        let timestamps: Vec<i64> = input.chunks_exact(ID_LEN).map(timestamp).collect();
+        let timestamps = encode_i64_sequence(&timestamps);
+        let mut output = Vec::with_capacity(input.len());
+        varint::write_u64(&mut output, timestamps.len() as u64);
+        output.extend_from_slice(&timestamps);
+
+        for position in TIMESTAMP_LEN..ID_LEN {
+            let column: Vec<u8> = input.chunks_exact(ID_LEN).map(|id| id[position]).collect();
+            let huffman = Huffman::new().compress(&column)?;
+            if huffman.len() < column.len() {
+                output.push(COLUMN_HUFFMAN);
+                output.extend_from_slice(&huffman);
+            } else {
+                output.push(COLUMN_STORED);
+                output.extend_from_slice(&column);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "IdColumn"
+    }
+}
+
+impl Decompressor for IdColumn {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    /// Reads the identifier count from the header of the timestamp
+    /// sequence.
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let mut pos = 0;
+        varint::read_u64(input, &mut pos)?;
+        // Skip the sequence's flags byte.
+        pos += 1;
+        varint::read_u64(input, &mut pos)?
+            .checked_mul(ID_LEN as u64)
+            .map(Some)
+            .ok_or(CompressionError::CorruptedData)
+    }
+
+    fn name(&self) -> &'static str {
+        "IdColumn"
+    }
+}
+
+impl PartialDecompressor for IdColumn {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let timestamps_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+        let timestamps = input
+            .get(pos..pos.saturating_add(timestamps_len))
+            .ok_or(CompressionError::CorruptedData)?;
+        let timestamps = decode_i64_sequence(timestamps)?;
+        pos += timestamps_len;
+
+        let count = timestamps.len();
+        let mut output = vec![0u8; count * ID_LEN];
+        for (id, &timestamp) in output.chunks_exact_mut(ID_LEN).zip(&timestamps) {
+            let bytes = timestamp.to_be_bytes();
+            if bytes[..8 - TIMESTAMP_LEN].iter().any(|&byte| byte != 0) {
+                return Err(CompressionError::CorruptedData);
+            }
+            id[..TIMESTAMP_LEN].copy_from_slice(&bytes[8 - TIMESTAMP_LEN..]);
+        }
+
+        for position in TIMESTAMP_LEN..ID_LEN {
+            let &mode = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+            pos += 1;
+            let column = match mode {
+                COLUMN_STORED => {
+                    let column = input
+                        .get(pos..pos + count)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    pos += count;
+                    column.to_vec()
+                }
+                COLUMN_HUFFMAN => {
+                    let (column, consumed) = Huffman::new().decompress_partial(&input[pos..])?;
+                    pos += consumed;
+                    column
+                }
+                _ => return Err(CompressionError::InvalidHeader),
+            };
+            if column.len() != count {
+                return Err(CompressionError::CorruptedData);
+            }
+            for (id, byte) in output.chunks_exact_mut(ID_LEN).zip(column) {
+                id[position] = byte;
+            }
+        }
+
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecompressionLimits;
+    use crate::sequence::FLAG_RUNS;
+
+    const RANDOM_LEN: usize = ID_LEN - TIMESTAMP_LEN;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = IdColumn::new().compress(input).unwrap();
+        assert_eq!(IdColumn::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    /// `count` identifiers minted a few per millisecond, with random tails.
+    /// Version 7 UUID tails carry the version nibble and variant bits.
+    fn ids(count: usize, v7: bool) -> Vec<u8> {
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        let mut output = Vec::with_capacity(count * ID_LEN);
+        let mut millis = 1_700_000_000_000u64;
+        for _ in 0..count {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            millis += state % 3;
+            output.extend_from_slice(&millis.to_be_bytes()[2..]);
+            let mut tail = [0u8; RANDOM_LEN];
+            for (i, byte) in tail.iter_mut().enumerate() {
+                *byte = state
+                    .rotate_left(u32::try_from(i).unwrap() * 7)
+                    .to_le_bytes()[0];
+            }
+            if v7 {
+                tail[0] = 0x70 | (tail[0] & 0x0F);
+                tail[2] = 0x80 | (tail[2] & 0x3F);
+            }
+            output.extend_from_slice(&tail);
+        }
+        output
+    }
+
+    #[test]
+    fn test_id_column_name() {
+        assert_eq!(Compressor::name(&IdColumn::new()), "IdColumn");
+        assert_eq!(Decompressor::name(&IdColumn::new()), "IdColumn");
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressed = roundtrip(&[]);
+        assert!(!compressed.is_empty());
+        assert!(IdColumn::new().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_ulids_shrink_timestamps() {
+        let input = ids(10_000, false);
+        let compressed = roundtrip(&input);
+        // Ten random bytes per ID are incompressible; the six timestamp bytes
+        // should collapse to about one.
+        assert!(
+            compressed.len() < 10_000 * (RANDOM_LEN + 2),
+            "{}",
+            compressed.len()
+        );
+    }
+
+    #[test]
+    fn test_uuid_v7_shrinks_fixed_bits() {
+        let ulid_len = roundtrip(&ids(10_000, false)).len();
+        let v7_len = roundtrip(&ids(10_000, true)).len();
+        assert!(v7_len + 6_000 < ulid_len, "{v7_len} vs {ulid_len}");
+    }
+
+    #[test]
+    fn test_unordered_ids_roundtrip() {
+        let mut input = ids(500, true);
+        input.reverse();
+        let compressed = roundtrip(&input);
+        assert!(compressed.len() < input.len() + input.len() / 8);
+        roundtrip(&[0xFF; ID_LEN * 3]);
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert!(matches!(
+            IdColumn::new().compress(&[0; 17]),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = IdColumn::new();
+        let first = codec.compress(&ids(20, true)).unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [ids(20, true), Vec::new(), ids(20, true)]);
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = IdColumn::new();
+        let compressed = codec.compress(&ids(4, false)).unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        // Unknown column mode.
+        let mut bad_mode = compressed.clone();
+        let first_column = 1 + usize::from(compressed[0]);
+        bad_mode[first_column] = 9;
+        assert!(matches!(
+            codec.decompress(&bad_mode),
+            Err(CompressionError::InvalidHeader)
+        ));
+        // Timestamp wider than 48 bits.
+        let wide = encode_i64_sequence(&[1 << 50]);
+        let mut frame = vec![u8::try_from(wide.len()).unwrap()];
+        frame.extend_from_slice(&wide);
+        frame.extend(std::iter::repeat_n([COLUMN_STORED, 0], RANDOM_LEN).flatten());
+        assert!(codec.decompress(&frame).is_err());
+    }
+
+    #[test]
+    fn test_limits_refuse_bombs() {
+        let codec = IdColumn::new();
+        let compressed = codec.compress(&ids(50, true)).unwrap();
+        assert_eq!(
+            codec.decompressed_len(&compressed).unwrap(),
+            Some(50 * ID_LEN as u64)
+        );
+
+        // A billion equal timestamps as a single run of zero deltas.
+        let count = 1u64 << 30;
+        let mut timestamps = vec![FLAG_RUNS];
+        varint::write_u64(&mut timestamps, count);
+        timestamps.push(0);
+        varint::write_u64(&mut timestamps, count);
+        let mut bomb = vec![u8::try_from(timestamps.len()).unwrap()];
+        bomb.extend_from_slice(&timestamps);
+        assert_eq!(
+            codec.decompressed_len(&bomb).unwrap(),
+            Some(count * ID_LEN as u64)
+        );
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        assert!(matches!(
+            codec.decompress_with_limits(&bomb, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+}
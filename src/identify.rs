@@ -0,0 +1,111 @@
+use crate::algorithm::Algorithm;
+use crate::container::{MAGIC, VERSION};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+/// Skippable zstd frames start with `0x184D2A5?` little-endian.
+const ZSTD_SKIPPABLE: [u8; 3] = [0x2A, 0x4D, 0x18];
+/// The compression method gzip and zlib both number 8.
+const DEFLATE: u8 = 8;
+
+/// A compressed format recognised from its leading bytes by [`identify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A [`container`](crate::container) frame, holding the algorithm that
+    /// wrote its payload.
+    Container(Algorithm),
+    /// A gzip member (RFC 1952).
+    Gzip,
+    /// A zlib stream (RFC 1950).
+    Zlib,
+    /// A zstd frame, or a skippable frame in front of one.
+    Zstd,
+}
+
+impl Format {
+    /// The algorithm a container frame names; `None` for external formats,
+    /// which this crate does not decode.
+    #[must_use]
+    pub const fn algorithm(self) -> Option<Algorithm> {
+        match self {
+            Self::Container(algorithm) => Some(algorithm),
+            Self::Gzip | Self::Zlib | Self::Zstd => None,
+        }
+    }
+}
+
+/// Recognises the format of an unlabeled buffer from its first few bytes,
+/// so a blob can be routed to the right decoder.
+///
+/// Only magic numbers are checked, so a prefix of the data is enough and a
+/// match does not promise the rest decodes. A zlib header is just two bytes
+/// with a 1-in-31 check, so it is tried last and may match arbitrary data;
+/// the raw output of a codec, which carries no magic, gives `None`.
+#[must_use]
+pub fn identify(data: &[u8]) -> Option<Format> {
+    if data.len() >= 6 && data[..4] == MAGIC && data[4] == VERSION {
+        return Algorithm::from_tag(data[5]).map(Format::Container);
+    }
+    if data.len() >= 3 && data[..2] == GZIP_MAGIC && data[2] == DEFLATE {
+        return Some(Format::Gzip);
+    }
+    if data.starts_with(&ZSTD_MAGIC)
+        || (data.len() >= 4 && data[1..4] == ZSTD_SKIPPABLE && data[0] & 0xF0 == 0x50)
+    {
+        return Some(Format::Zstd);
+    }
+    match data {
+        [cmf, flg, ..] if cmf & 0x0F == DEFLATE && cmf >> 4 <= 7 => {
+            (u16::from_be_bytes([*cmf, *flg]) % 31 == 0).then_some(Format::Zlib)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode_frame;
+    use crate::{Compressor, Lz77};
+
+    #[test]
+    fn test_identify() {
+        let data = b"blobs arrive unlabeled; blobs arrive unlabeled".repeat(4);
+        for algorithm in [Algorithm::Stored, Algorithm::Lz77, Algorithm::Bcj] {
+            let frame = encode_frame(algorithm, &data).unwrap();
+            let format = identify(&frame);
+            assert_eq!(format, Some(Format::Container(algorithm)));
+            assert_eq!(format.and_then(Format::algorithm), Some(algorithm));
+        }
+
+        // `gzip -c`, `zlib.compress` and `zstd -c` output of "hello".
+        assert_eq!(
+            identify(&[0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03]),
+            Some(Format::Gzip)
+        );
+        assert_eq!(identify(&[0x78, 0x9C, 0xCB, 0x48]), Some(Format::Zlib));
+        assert_eq!(identify(&[0x78, 0x01]), Some(Format::Zlib));
+        assert_eq!(identify(&[0x78, 0xDA]), Some(Format::Zlib));
+        assert_eq!(
+            identify(&[0x28, 0xB5, 0x2F, 0xFD, 0x04, 0x58]),
+            Some(Format::Zstd)
+        );
+        assert_eq!(
+            identify(&[0x5E, 0x2A, 0x4D, 0x18, 0, 0, 0, 0]),
+            Some(Format::Zstd)
+        );
+        assert_eq!(Format::Gzip.algorithm(), None);
+
+        assert_eq!(identify(&Lz77::new().compress(&data).unwrap()), None);
+        assert_eq!(identify(b""), None);
+        assert_eq!(identify(&[0x1F, 0x8B, 0x07]), None);
+        assert_eq!(identify(&[0x78, 0x9D]), None);
+        // Right magic, but a version or algorithm this crate never wrote.
+        let mut frame = encode_frame(Algorithm::Rle, &data).unwrap();
+        frame[5] = 0xFF;
+        assert_eq!(identify(&frame), None);
+        frame[4] = 2;
+        frame[5] = Algorithm::Rle.tag();
+        assert_eq!(identify(&frame), None);
+    }
+}
@@ -0,0 +1,887 @@
+//! Codecs for image data.
+//!
+//! [`Fax`] codes 1-bit scanned documents in the style of CCITT Group 4
+//! (T.6): each row is described by where its colour changes relative to the
+//! row above, so the text and rules of a typical page cost a few bits per
+//! edge rather than per pixel.
+//!
+//! [`Rle2d`] run-length codes images of any pixel size row against row, for
+//! screenshots and framebuffers where most of a row either repeats one
+//! colour or matches the row above.
+
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Widest row, in pixels, either side accepts.
+pub const MAX_WIDTH: u32 = 1 << 16;
+
+/// [`Rle2d`] op for pixels stored as is.
+pub const ROW_OP_LITERAL: u8 = 0;
+/// [`Rle2d`] op for one pixel repeated.
+pub const ROW_OP_REPEAT: u8 = 1;
+/// [`Rle2d`] op for pixels copied from the row above.
+pub const ROW_OP_ABOVE: u8 = 2;
+/// Shortest run, in pixels, [`Rle2d`] codes as a repeat or a copy.
+pub const MIN_ROW_RUN: usize = 3;
+
+/// A two-dimensional coding mode, with its T.6 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The reference row's next two changes lie before the coding row's
+    /// next change: skip to the second.
+    Pass,
+    /// Two runs coded explicitly.
+    Horizontal,
+    /// The next change is within three pixels of the reference row's.
+    Vertical(i8),
+}
+
+impl Mode {
+    /// `(code, bit count)`.
+    const fn code(self) -> (u64, u32) {
+        match self {
+            Self::Vertical(0) => (0b1, 1),
+            Self::Vertical(1) => (0b011, 3),
+            Self::Vertical(-1) => (0b010, 3),
+            Self::Horizontal => (0b001, 3),
+            Self::Pass => (0b0001, 4),
+            Self::Vertical(2) => (0b00_0011, 6),
+            Self::Vertical(-2) => (0b00_0010, 6),
+            Self::Vertical(3) => (0b000_0011, 7),
+            Self::Vertical(_) => (0b000_0010, 7),
+        }
+    }
+
+    fn read(reader: &mut BitReader<'_>) -> Option<Self> {
+        let mut code = 0;
+        for len in 1..=7 {
+            code = (code << 1) | u64::from(reader.read_bit()?);
+            let mode = match (code, len) {
+                (0b1, 1) => Self::Vertical(0),
+                (0b011, 3) => Self::Vertical(1),
+                (0b010, 3) => Self::Vertical(-1),
+                (0b001, 3) => Self::Horizontal,
+                (0b0001, 4) => Self::Pass,
+                (0b00_0011, 6) => Self::Vertical(2),
+                (0b00_0010, 6) => Self::Vertical(-2),
+                (0b000_0011, 7) => Self::Vertical(3),
+                (0b000_0010, 7) => Self::Vertical(-3),
+                _ => continue,
+            };
+            return Some(mode);
+        }
+        None
+    }
+}
+
+/// A row of pixels behind an imaginary white pixel at index 0, so pixel `x`
+/// sits at index `x + 1` and changing elements are indices whose pixel
+/// differs from the one before. `width + 1` marks the end of the row.
+#[derive(Debug, Clone)]
+struct Row {
+    pixels: Vec<bool>,
+    changes: Vec<usize>,
+}
+
+impl Row {
+    fn white(width: usize) -> Self {
+        Self {
+            pixels: vec![false; width + 1],
+            changes: Vec::new(),
+        }
+    }
+
+    fn from_pixels(pixels: Vec<bool>) -> Self {
+        let changes = (1..pixels.len())
+            .filter(|&i| pixels[i] != pixels[i - 1])
+            .collect();
+        Self { pixels, changes }
+    }
+
+    const fn end(&self) -> usize {
+        self.pixels.len()
+    }
+
+    /// First changing element after `a0`.
+    fn next_change(&self, a0: usize) -> usize {
+        let i = self.changes.partition_point(|&c| c <= a0);
+        self.changes.get(i).copied().unwrap_or_else(|| self.end())
+    }
+
+    /// `b1`: the first changing element after `a0` to the colour opposite
+    /// `color`, and `b2`: the change after it.
+    fn b1_b2(&self, a0: usize, color: bool) -> (usize, usize) {
+        let i = self.changes.partition_point(|&c| c <= a0);
+        let end = self.end();
+        self.changes[i..]
+            .iter()
+            .position(|&c| self.pixels[c] != color)
+            .map_or((end, end), |j| {
+                let b1 = self.changes[i + j];
+                (b1, self.changes.get(i + j + 1).copied().unwrap_or(end))
+            })
+    }
+}
+
+fn write_row(row: &Row, reference: &Row, writer: &mut BitWriter) {
+    let end = row.end();
+    let mut a0 = 0;
+    let mut color = false;
+    while a0 < end {
+        let a1 = row.next_change(a0);
+        let (b1, b2) = reference.b1_b2(a0, color);
+        let mode = if b2 < a1 {
+            a0 = b2;
+            Mode::Pass
+        } else if a1.abs_diff(b1) <= 3 {
+            let shift = i8::try_from(a1.abs_diff(b1)).unwrap_or(0);
+            a0 = a1;
+            color = !color;
+            Mode::Vertical(if a1 >= b1 { shift } else { -shift })
+        } else {
+            Mode::Horizontal
+        };
+        let (code, len) = mode.code();
+        writer.write_bits(code, len);
+
+        if mode == Mode::Horizontal {
+            let a2 = row.next_change(a1);
+            writer.write_exp_golomb((a1 - a0.max(1)) as u64);
+            writer.write_exp_golomb((a2 - a1) as u64);
+            a0 = a2;
+        }
+    }
+}
+
+fn read_row(reader: &mut BitReader<'_>, reference: &Row) -> Result<Row> {
+    let end = reference.end();
+    let mut pixels = vec![false; end];
+    let mut a0 = 0;
+    let mut color = false;
+    while a0 < end {
+        let mode = Mode::read(reader).ok_or(CompressionError::CorruptedData)?;
+        let (b1, b2) = reference.b1_b2(a0, color);
+        let start = a0.max(1);
+        match mode {
+            Mode::Pass => {
+                pixels[start..b2].fill(color);
+                a0 = b2;
+            }
+            Mode::Vertical(shift) => {
+                let a1 = b1
+                    .checked_add_signed(isize::from(shift))
+                    .filter(|&a1| a1 > a0 && a1 <= end)
+                    .ok_or(CompressionError::CorruptedData)?;
+                pixels[start..a1].fill(color);
+                a0 = a1;
+                color = !color;
+            }
+            Mode::Horizontal => {
+                let mut run = || {
+                    reader
+                        .read_exp_golomb()
+                        .and_then(|run| usize::try_from(run).ok())
+                        .ok_or(CompressionError::CorruptedData)
+                };
+                let a1 = start.saturating_add(run()?);
+                let a2 = a1.saturating_add(run()?);
+                if a2 > end || a2 <= a0 {
+                    return Err(CompressionError::CorruptedData);
+                }
+                pixels[start..a1].fill(color);
+                pixels[a1..a2].fill(!color);
+                a0 = a2;
+            }
+        }
+    }
+    Ok(Row::from_pixels(pixels))
+}
+
+/// CCITT Group 4-style coder for 1-bit images such as scanned documents.
+///
+/// Input is `height` rows of `width` pixels, packed MSB-first with 1 for
+/// black and each row padded with zero bits to a whole byte. Every row is
+/// coded against the one above (the first against a white row) with the
+/// T.6 pass, horizontal and vertical modes; horizontal run lengths use
+/// exponential-Golomb codes rather than the T.4 tables, so streams are not
+/// interchangeable with fax hardware. Output layout: `[width: varint]
+/// [height: varint][mode codes and runs: bits]`. A height of 0 takes as many
+/// rows as the input holds; the frame stores the actual height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fax {
+    width: u32,
+    height: u32,
+}
+
+impl Fax {
+    #[must_use]
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes per packed input row.
+    #[must_use]
+    pub const fn stride(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+}
+
+fn unpack(row: &[u8], width: usize) -> Vec<bool> {
+    let mut pixels = Vec::with_capacity(width + 1);
+    pixels.push(false);
+    pixels.extend((0..width).map(|x| row[x / 8] & (0x80 >> (x % 8)) != 0));
+    pixels
+}
+
+fn pack(row: &Row, output: &mut Vec<u8>) {
+    for byte in row.pixels[1..].chunks(8) {
+        let packed = byte
+            .iter()
+            .enumerate()
+            .fold(0u8, |acc, (i, &black)| acc | (u8::from(black) << (7 - i)));
+        output.push(packed);
+    }
+}
+
+impl Compressor for Fax {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if self.width > MAX_WIDTH {
+            return Err(CompressionError::InvalidInput(format!(
+                "image width must be at most {MAX_WIDTH} pixels"
+            )));
+        }
+        let width = self.width as usize;
+        let stride = self.stride();
+        let height = if self.height == 0 {
+            input.len().checked_div(stride).unwrap_or(0)
+        } else {
+            self.height as usize
+        };
+        if Some(input.len()) != stride.checked_mul(height) {
+            return Err(CompressionError::InvalidInput(format!(
+                "expected {height} rows of {stride} bytes, got {} bytes",
+                input.len()
+            )));
+        }
+        let padding = 0xFFu8 >> (width % 8);
+        if !width.is_multiple_of(8)
+            && input
+                .chunks(stride)
+                .any(|row| row[stride - 1] & padding != 0)
+        {
+            return Err(CompressionError::InvalidInput(
+                "row padding bits must be zero".to_string(),
+            ));
+        }
+
+        let mut output = Vec::with_capacity(input.len() / 8 + 2 * varint::MAX_LEN_U64);
+        varint::write_u64(&mut output, u64::from(self.width));
+        varint::write_u64(&mut output, height as u64);
+
+        let mut writer = BitWriter::new();
+        let mut reference = Row::white(width);
+        for y in 0..height {
+            let row = &input[y * stride..(y + 1) * stride];
+            let row = Row::from_pixels(unpack(row, width));
+            write_row(&row, &reference, &mut writer);
+            reference = row;
+        }
+        output.extend_from_slice(&writer.finish());
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Fax"
+    }
+}
+
+impl Decompressor for Fax {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    /// The stored width and height give the packed size.
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let mut pos = 0;
+        let width = varint::read_u64(input, &mut pos)?;
+        let height = varint::read_u64(input, &mut pos)?;
+        if width > u64::from(MAX_WIDTH) {
+            return Err(CompressionError::CorruptedData);
+        }
+        width
+            .div_ceil(8)
+            .checked_mul(height)
+            .map(Some)
+            .ok_or(CompressionError::CorruptedData)
+    }
+
+    fn name(&self) -> &'static str {
+        "Fax"
+    }
+}
+
+impl PartialDecompressor for Fax {
+    /// Decodes using the dimensions stored in the frame, which may differ
+    /// from this coder's.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let width = varint::read_u64(input, &mut pos)?;
+        let height = varint::read_u64(input, &mut pos)?;
+        if width > u64::from(MAX_WIDTH) {
+            return Err(CompressionError::CorruptedData);
+        }
+        let width = usize::try_from(width).map_err(|_| CompressionError::CorruptedData)?;
+        let height = usize::try_from(height).map_err(|_| CompressionError::CorruptedData)?;
+
+        let mut output = Vec::with_capacity(
+            width
+                .div_ceil(8)
+                .saturating_mul(height)
+                .min(input.len() * 64),
+        );
+        let mut reader = BitReader::new(&input[pos..]);
+        let mut reference = Row::white(width);
+        for _ in 0..height {
+            let row = read_row(&mut reader, &reference)?;
+            pack(&row, &mut output);
+            reference = row;
+        }
+        Ok((output, pos + reader.bytes_consumed()))
+    }
+}
+
+/// Run-length coder for raster images that also codes runs of pixels
+/// matching the row above.
+///
+/// Input is rows of `stride` bytes, the last possibly short, made of pixels
+/// of `pixel_size` bytes each. The coder walks the pixels in order and
+/// emits the longer of a run copied from the row above and a run of one
+/// repeated pixel once either reaches [`MIN_ROW_RUN`] pixels, keeping
+/// everything else as literals, so flat areas, vertical edges and repeated
+/// rows of UI chrome all collapse where plain RLE only sees the horizontal
+/// runs. Output layout: `[stride: varint][pixel size: u8][original length:
+/// varint]`, then ops `[op | (count - 1) << 2: varint]` followed by `count`
+/// pixels for [`ROW_OP_LITERAL`], one pixel for [`ROW_OP_REPEAT`] and
+/// nothing for [`ROW_OP_ABOVE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rle2d {
+    stride: usize,
+    pixel_size: u8,
+}
+
+impl Rle2d {
+    /// Creates a coder for rows of `stride` one-byte pixels.
+    #[must_use]
+    pub const fn new(stride: usize) -> Self {
+        Self {
+            stride,
+            pixel_size: 1,
+        }
+    }
+
+    /// Compares and repeats whole pixels of `pixel_size` bytes, such as 4
+    /// for RGBA, which must divide the stride.
+    #[must_use]
+    pub const fn with_pixel_size(mut self, pixel_size: u8) -> Self {
+        self.pixel_size = pixel_size;
+        self
+    }
+
+    #[must_use]
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    #[must_use]
+    pub const fn pixel_size(&self) -> u8 {
+        self.pixel_size
+    }
+}
+
+/// Writes `pixels` as a literal op unless it is empty.
+fn write_literal(pixels: &[u8], pixel_size: usize, output: &mut Vec<u8>) {
+    if !pixels.is_empty() {
+        let count = (pixels.len() / pixel_size) as u64;
+        varint::write_u64(output, (count - 1) << 2 | u64::from(ROW_OP_LITERAL));
+        output.extend_from_slice(pixels);
+    }
+}
+
+impl Compressor for Rle2d {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let stride = self.stride;
+        let pixel_size = usize::from(self.pixel_size);
+        if pixel_size == 0 || stride == 0 || !stride.is_multiple_of(pixel_size) {
+            return Err(CompressionError::InvalidInput(format!(
+                "stride {stride} is not a non-zero multiple of pixel size {pixel_size}"
+            )));
+        }
+        if !input.len().is_multiple_of(pixel_size) {
+            return Err(CompressionError::InvalidInput(format!(
+                "{} bytes is not a whole number of {pixel_size}-byte pixels",
+                input.len()
+            )));
+        }
+
+        let mut output = Vec::with_capacity(input.len() / 4 + 2 * varint::MAX_LEN_U64 + 1);
+        varint::write_u64(&mut output, stride as u64);
+        output.push(self.pixel_size);
+        varint::write_u64(&mut output, input.len() as u64);
+
+        let mut literal_start = 0;
+        let mut pos = 0;
+        while pos < input.len() {
+            let above = if pos >= stride {
+                input[pos..]
+                    .iter()
+                    .zip(&input[pos - stride..])
+                    .take_while(|(a, b)| a == b)
+                    .count()
+                    / pixel_size
+            } else {
+                0
+            };
+            let pixel = &input[pos..pos + pixel_size];
+            let repeat = input[pos..]
+                .chunks_exact(pixel_size)
+                .take_while(|&next| next == pixel)
+                .count();
+
+            let (op, count) = if above >= MIN_ROW_RUN && above >= repeat {
+                (ROW_OP_ABOVE, above)
+            } else if repeat >= MIN_ROW_RUN {
+                (ROW_OP_REPEAT, repeat)
+            } else {
+                pos += pixel_size;
+                continue;
+            };
+            write_literal(&input[literal_start..pos], pixel_size, &mut output);
+            varint::write_u64(&mut output, (count as u64 - 1) << 2 | u64::from(op));
+            if op == ROW_OP_REPEAT {
+                output.extend_from_slice(pixel);
+            }
+            pos += count * pixel_size;
+            literal_start = pos;
+        }
+        write_literal(&input[literal_start..], pixel_size, &mut output);
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "RLE-2D"
+    }
+}
+
+impl Decompressor for Rle2d {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let mut pos = 0;
+        varint::read_u64(input, &mut pos)?;
+        pos += 1;
+        varint::read_u64(input, &mut pos).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "RLE-2D"
+    }
+}
+
+impl PartialDecompressor for Rle2d {
+    /// Decodes using the stride and pixel size stored in the frame, which
+    /// may differ from this coder's.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let stride = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+        let pixel_size = usize::from(*input.get(pos).ok_or(CompressionError::CorruptedData)?);
+        pos += 1;
+        let len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+        if pixel_size == 0
+            || stride == 0
+            || !stride.is_multiple_of(pixel_size)
+            || !len.is_multiple_of(pixel_size)
+        {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let mut output = Vec::with_capacity(len.min(input.len().saturating_mul(64)));
+        while output.len() < len {
+            let header = varint::read_u64(input, &mut pos)?;
+            let op = u8::try_from(header & 0b11).map_err(|_| CompressionError::CorruptedData)?;
+            let bytes = usize::try_from((header >> 2) + 1)
+                .ok()
+                .and_then(|count| count.checked_mul(pixel_size))
+                .filter(|&bytes| bytes <= len - output.len())
+                .ok_or(CompressionError::CorruptedData)?;
+            match op {
+                ROW_OP_LITERAL => {
+                    let pixels = input
+                        .get(pos..pos + bytes)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    output.extend_from_slice(pixels);
+                    pos += bytes;
+                }
+                ROW_OP_REPEAT => {
+                    let pixel = input
+                        .get(pos..pos + pixel_size)
+                        .ok_or(CompressionError::CorruptedData)?;
+                    for _ in 0..bytes / pixel_size {
+                        output.extend_from_slice(pixel);
+                    }
+                    pos += pixel_size;
+                }
+                ROW_OP_ABOVE => {
+                    if output.len() < stride {
+                        return Err(CompressionError::CorruptedData);
+                    }
+                    for _ in 0..bytes {
+                        output.push(output[output.len() - stride]);
+                    }
+                }
+                _ => return Err(CompressionError::CorruptedData),
+            }
+        }
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecompressionLimits, Rle};
+
+    fn roundtrip(fax: Fax, input: &[u8]) -> Vec<u8> {
+        let compressed = fax.compress(input).unwrap();
+        assert_eq!(fax.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    /// A 1728-pixel-wide page of ruled lines and blocks of "text".
+    fn page(height: u32) -> (Fax, Vec<u8>) {
+        let fax = Fax::new(1728, height);
+        let stride = fax.stride();
+        let mut image = vec![0u8; stride * height as usize];
+        for y in 0..height as usize {
+            let row = &mut image[y * stride..(y + 1) * stride];
+            if y % 97 == 0 {
+                row[10..stride - 10].fill(0xFF);
+            } else if y % 24 < 12 {
+                // Glyphs every other byte, with word gaps, changing shape
+                // every few rows.
+                for (i, byte) in row[20..stride - 20].iter_mut().enumerate() {
+                    let glyph = (i / 2 + y / 24) % 7;
+                    if i % 2 == 0 && glyph != 0 {
+                        *byte = [0x3C, 0x18, 0x7E, 0x66][(glyph + y / 4) % 4];
+                    }
+                }
+            }
+        }
+        (fax, image)
+    }
+
+    #[test]
+    fn test_fax_new() {
+        let fax = Fax::new(13, 4);
+        assert_eq!((fax.width(), fax.height(), fax.stride()), (13, 4, 2));
+        assert_eq!(Compressor::name(&fax), "Fax");
+        assert_eq!(Decompressor::name(&fax), "Fax");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(Fax::new(0, 0), &[]), [0, 0]);
+        assert_eq!(roundtrip(Fax::new(100, 0), &[]), [100, 0]);
+        assert_eq!(roundtrip(Fax::new(0, 9), &[]), [0, 9, 0xFF, 0x80]);
+        assert!(Fax::new(0, 0).decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_height_from_input() {
+        let fax = Fax::new(13, 0);
+        let input = [0xA5, 0x50, 0x5A, 0xA8, 0xFF, 0xF8];
+        let compressed = roundtrip(fax, &input);
+        assert_eq!(compressed, Fax::new(13, 3).compress(&input).unwrap());
+        assert!(matches!(
+            fax.compress(&input[..5]),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert_eq!(roundtrip(Fax::new(0, 0), &[]), [0, 0]);
+    }
+
+    #[test]
+    fn test_known_output() {
+        // White row: V0.
+        assert_eq!(roundtrip(Fax::new(8, 1), &[0x00]), [8, 1, 0x80]);
+        // Black row: H, runs 0 and 8.
+        assert_eq!(roundtrip(Fax::new(8, 1), &[0xFF]), [8, 1, 0x31, 0x20]);
+        // Same black row again: V0 for both edges.
+        assert_eq!(roundtrip(Fax::new(8, 2), &[0xFF, 0xFF]), [8, 2, 0x31, 0x38]);
+    }
+
+    #[test]
+    fn test_roundtrip_shapes() {
+        roundtrip(Fax::new(1, 3), &[0x80, 0x00, 0x80]);
+        roundtrip(Fax::new(13, 3), &[0xA5, 0x50, 0x5A, 0xA8, 0xFF, 0xF8]);
+        let noise: Vec<u8> = (0..40 * 5u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 11).to_le_bytes()[0])
+            .collect();
+        roundtrip(Fax::new(320, 5), &noise);
+    }
+
+    #[test]
+    fn test_page_compresses_well() {
+        let (fax, image) = page(600);
+        let compressed = roundtrip(fax, &image);
+        let rle = Rle::escaped().compress(&image).unwrap();
+        assert!(compressed.len() * 8 < image.len(), "{}", compressed.len());
+        assert!(compressed.len() * 3 < rle.len());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let fax = Fax::new(12, 2);
+        assert!(matches!(
+            fax.compress(&[0; 3]),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        // Padding bits set in the second byte of a row.
+        assert!(matches!(
+            fax.compress(&[0, 0x01, 0, 0]),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(Fax::new(MAX_WIDTH + 1, 0).compress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_decoder_uses_stored_dimensions() {
+        let compressed = Fax::new(8, 2).compress(&[0x0F, 0xF0]).unwrap();
+        assert_eq!(
+            Fax::new(1, 1).decompress(&compressed).unwrap(),
+            [0x0F, 0xF0]
+        );
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let fax = Fax::new(8, 2);
+        let first = fax.compress(&[0x0F, 0xF0]).unwrap();
+        let second = Fax::new(8, 0).compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = fax.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [vec![0x0F, 0xF0], Vec::new(), vec![0x0F, 0xF0]]);
+        assert!(fax.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let fax = Fax::new(8, 1);
+        // Missing rows.
+        assert!(fax.decompress(&[8, 2, 0x80]).is_err());
+        // Horizontal runs past the end of the row.
+        assert!(fax.decompress(&[8, 1, 0x31, 0x40]).is_err());
+        // Invalid mode code.
+        assert!(fax.decompress(&[8, 1, 0x00]).is_err());
+        // Width beyond the limit.
+        assert!(fax.decompress(&[0x81, 0x80, 0x08, 0]).is_err());
+        assert!(fax.decompressed_len(&[0x81, 0x80, 0x08, 0]).is_err());
+    }
+
+    #[test]
+    fn test_limits_refuse_bombs() {
+        let (fax, input) = page(40);
+        let compressed = fax.compress(&input).unwrap();
+        assert_eq!(
+            fax.decompressed_len(&compressed).unwrap(),
+            Some(input.len() as u64)
+        );
+
+        // Rows of white cost a bit each.
+        let mut bomb = Vec::new();
+        varint::write_u64(&mut bomb, u64::from(MAX_WIDTH));
+        varint::write_u64(&mut bomb, 1 << 30);
+        bomb.extend_from_slice(&[0xFF; 64]);
+        assert_eq!(fax.decompressed_len(&bomb).unwrap(), Some(8192 << 30));
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        assert!(matches!(
+            fax.decompress_with_limits(&bomb, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+
+    /// A 64x48 RGBA "screenshot": a title bar, a window body with a border,
+    /// and rows of text-like spans repeated down the body.
+    fn screenshot() -> (Rle2d, Vec<u8>) {
+        let (width, height) = (64, 48);
+        let mut image = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel: [u8; 4] = if y < 6 {
+                    [0x2B, 0x57, 0x9A, 0xFF]
+                } else if x == 0 || x == width - 1 {
+                    [0x80, 0x80, 0x80, 0xFF]
+                } else if y % 8 < 3 && x % 9 < 6 && (x / 9 + y / 8) % 3 != 0 {
+                    [0x10, 0x10, 0x10, 0xFF]
+                } else {
+                    [0xF4, 0xF4, 0xF4, 0xFF]
+                };
+                image.extend_from_slice(&pixel);
+            }
+        }
+        (Rle2d::new(width * 4).with_pixel_size(4), image)
+    }
+
+    fn roundtrip_2d(rle: Rle2d, input: &[u8]) -> Vec<u8> {
+        let compressed = rle.compress(input).unwrap();
+        assert_eq!(rle.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_rle_2d_new() {
+        let rle = Rle2d::new(12).with_pixel_size(3);
+        assert_eq!((rle.stride(), rle.pixel_size()), (12, 3));
+        assert_eq!(Rle2d::new(5).pixel_size(), 1);
+        assert_eq!(Compressor::name(&rle), "RLE-2D");
+        assert_eq!(Decompressor::name(&rle), "RLE-2D");
+    }
+
+    #[test]
+    fn test_rle_2d_known_output() {
+        assert_eq!(roundtrip_2d(Rle2d::new(4), &[]), [4, 1, 0]);
+        // Row one: a literal and a repeat; row two: a copy of row one.
+        let input = [1, 2, 7, 7, 7, 7, 1, 2, 7, 7, 7, 7];
+        assert_eq!(
+            roundtrip_2d(Rle2d::new(6), &input),
+            [6, 1, 12, 0x04, 1, 2, 0x0D, 7, 0x16]
+        );
+        // Pixels shorter than three stay literal.
+        assert_eq!(roundtrip_2d(Rle2d::new(2), &[5, 5]), [2, 1, 2, 0x04, 5, 5]);
+    }
+
+    #[test]
+    fn test_rle_2d_roundtrip_shapes() {
+        roundtrip_2d(Rle2d::new(1), &[3; 10]);
+        roundtrip_2d(Rle2d::new(7), &[1, 2, 3, 4, 5, 6, 7, 1, 2, 3]);
+        roundtrip_2d(Rle2d::new(6).with_pixel_size(3), &[9; 21]);
+        let noise: Vec<u8> = (0..600u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13).to_le_bytes()[0])
+            .collect();
+        let compressed = roundtrip_2d(Rle2d::new(40), &noise);
+        assert!(compressed.len() < noise.len() + 16);
+        let (rle, image) = screenshot();
+        roundtrip_2d(rle.with_pixel_size(1), &image);
+        roundtrip_2d(Rle2d::new(100), &image);
+    }
+
+    #[test]
+    fn test_rle_2d_beats_1d_on_screenshot() {
+        let (rle, image) = screenshot();
+        let compressed = roundtrip_2d(rle, &image);
+        let flat = Rle::escaped().compress(&image).unwrap();
+        assert!(compressed.len() * 20 < image.len(), "{}", compressed.len());
+        assert!(
+            compressed.len() * 4 < flat.len(),
+            "{} vs {}",
+            compressed.len(),
+            flat.len()
+        );
+    }
+
+    #[test]
+    fn test_rle_2d_invalid_input() {
+        for rle in [
+            Rle2d::new(0),
+            Rle2d::new(4).with_pixel_size(0),
+            Rle2d::new(6).with_pixel_size(4),
+        ] {
+            assert!(matches!(
+                rle.compress(&[0; 12]),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+        assert!(Rle2d::new(8).with_pixel_size(4).compress(&[0; 6]).is_err());
+    }
+
+    #[test]
+    fn test_rle_2d_decoder_uses_stored_geometry() {
+        let input = [1, 2, 3, 1, 2, 3, 1, 2, 3];
+        let compressed = Rle2d::new(3).compress(&input).unwrap();
+        assert_eq!(Rle2d::new(8).decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_rle_2d_decompress_partial_back_to_back() {
+        let rle = Rle2d::new(4);
+        let first = rle.compress(&[1, 1, 1, 1, 1, 1, 1, 1]).unwrap();
+        let second = rle.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = rle.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [vec![1; 8], Vec::new(), vec![1; 8]]);
+        assert!(rle.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_rle_2d_decompress_corrupted() {
+        let rle = Rle2d::new(4);
+        // Copy from above on the first row.
+        assert!(rle.decompress(&[4, 1, 3, 0x0A]).is_err());
+        // Run past the original length.
+        assert!(rle.decompress(&[4, 1, 3, 0x0D, 7]).is_err());
+        // Unknown op, truncated literal, missing ops.
+        assert!(rle.decompress(&[4, 1, 1, 0x03]).is_err());
+        assert!(rle.decompress(&[4, 1, 3, 0x08, 1, 2]).is_err());
+        assert!(rle.decompress(&[4, 1, 3]).is_err());
+        // Geometry that cannot hold whole pixels.
+        assert!(rle.decompress(&[4, 0, 0]).is_err());
+        assert!(rle.decompress(&[6, 4, 0]).is_err());
+        assert!(rle.decompress(&[8, 4, 3]).is_err());
+    }
+
+    #[test]
+    fn test_rle_2d_limits_refuse_bombs() {
+        let (rle, input) = screenshot();
+        let compressed = rle.compress(&input).unwrap();
+        assert_eq!(
+            rle.decompressed_len(&compressed).unwrap(),
+            Some(input.len() as u64)
+        );
+
+        // One repeat op standing for a terabyte.
+        let len = 1u64 << 40;
+        let mut bomb = vec![4, 1];
+        varint::write_u64(&mut bomb, len);
+        varint::write_u64(&mut bomb, (len - 1) << 2 | u64::from(ROW_OP_REPEAT));
+        bomb.push(0);
+        assert_eq!(rle.decompressed_len(&bomb).unwrap(), Some(len));
+        let limits = DecompressionLimits::new().with_max_ratio(1000);
+        assert!(matches!(
+            rle.decompress_with_limits(&bomb, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert!(rle.decompressed_len(&[4]).is_err());
+    }
+}
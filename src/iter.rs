@@ -0,0 +1,136 @@
+use crate::error::Result;
+use crate::traits::{Compressor, Decompressor};
+
+/// Compression as an iterator adapter, for batch jobs that already hold
+/// their data as chunks.
+///
+/// Each chunk is compressed or decompressed on its own, as one frame, so
+/// `compress_with` and `decompress_with` undo each other chunk for chunk.
+/// Items are `Result`s: a failing chunk yields its error and the iterator
+/// carries on with the next, so collect into `Result<Vec<_>>` to stop at
+/// the first error.
+pub trait ChunkIterExt: Iterator + Sized
+where
+    Self::Item: AsRef<[u8]>,
+{
+    /// Compresses each chunk with `codec`.
+    fn compress_with<C: Compressor + ?Sized>(self, codec: &C) -> Compressed<'_, Self, C> {
+        Compressed {
+            chunks: self,
+            codec,
+        }
+    }
+
+    /// Decompresses each frame with `codec`.
+    fn decompress_with<C: Decompressor + ?Sized>(self, codec: &C) -> Decompressed<'_, Self, C> {
+        Decompressed {
+            chunks: self,
+            codec,
+        }
+    }
+}
+
+impl<I: Iterator> ChunkIterExt for I where I::Item: AsRef<[u8]> {}
+
+/// Iterator returned by [`ChunkIterExt::compress_with`].
+#[derive(Debug, Clone)]
+pub struct Compressed<'a, I, C: ?Sized> {
+    chunks: I,
+    codec: &'a C,
+}
+
+impl<I, C> Iterator for Compressed<'_, I, C>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+    C: Compressor + ?Sized,
+{
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunks.next()?;
+        Some(self.codec.compress(chunk.as_ref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+/// Iterator returned by [`ChunkIterExt::decompress_with`].
+#[derive(Debug, Clone)]
+pub struct Decompressed<'a, I, C: ?Sized> {
+    chunks: I,
+    codec: &'a C,
+}
+
+impl<I, C> Iterator for Decompressed<'_, I, C>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+    C: Decompressor + ?Sized,
+{
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.chunks.next()?;
+        Some(self.codec.decompress(frame.as_ref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz4, registry};
+
+    #[test]
+    fn test_roundtrip_chunks() {
+        let data = b"batch jobs compress chunk by chunk; ".repeat(100);
+        let frames = data
+            .chunks(500)
+            .compress_with(&Lz4::new())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(frames.len(), 8);
+        assert_eq!(frames[0], Lz4::new().compress(&data[..500]).unwrap());
+
+        let chunks = frames
+            .iter()
+            .decompress_with(&Lz4::new())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[test]
+    fn test_errors_per_chunk() {
+        let good = Huffman::new().compress(b"fine").unwrap();
+        let frames = [good.clone(), vec![0xff; 3], good];
+        let results: Vec<_> = frames.iter().decompress_with(&Huffman::new()).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_deref(), Ok(&b"fine"[..]));
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        let first_error: Result<Vec<_>> = frames.iter().decompress_with(&Huffman::new()).collect();
+        assert!(first_error.is_err());
+    }
+
+    #[test]
+    fn test_boxed_codec_and_owned_chunks() {
+        let codec = registry::codec_by_name("huffman").unwrap();
+        let chunks = vec![b"owned".to_vec(), Vec::new()];
+        let iter = chunks.into_iter().compress_with(&*codec);
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        let frames: Vec<Vec<u8>> = iter.map(Result::unwrap).collect();
+        let back: Vec<Vec<u8>> = frames
+            .iter()
+            .decompress_with(&codec)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(back, [b"owned".to_vec(), Vec::new()]);
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::algorithm::Algorithm;
+use crate::container::{self, FrameHeader};
+use crate::error::{CompressionError, Result};
+
+const DEFAULT_SEGMENT_LIMIT: u64 = 1024 * 1024;
+const SEGMENT_EXTENSION: &str = "seg";
+/// Length of the entry's header: its op and key length.
+pub const ENTRY_HEADER_LEN: usize = 5;
+pub const OP_PUT: u8 = 0;
+pub const OP_DELETE: u8 = 1;
+
+/// Where the latest record for a key lives: the whole frame, header and
+/// all.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    segment: u32,
+    offset: u64,
+    len: u32,
+}
+
+/// A decoded log record.
+enum Record {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+impl Record {
+    fn encode(&self) -> Result<Vec<u8>> {
+        let (op, key, value) = match self {
+            Self::Put { key, value } => (OP_PUT, key, value.as_slice()),
+            Self::Delete { key } => (OP_DELETE, key, &[][..]),
+        };
+        let key_len = u32::try_from(key.len())
+            .map_err(|_| CompressionError::InvalidInput("key too large".to_string()))?;
+
+        let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN + key.len() + value.len());
+        entry.push(op);
+        entry.extend_from_slice(&key_len.to_le_bytes());
+        entry.extend_from_slice(key);
+        entry.extend_from_slice(value);
+        Ok(entry)
+    }
+
+    fn decode(entry: &[u8]) -> Result<Self> {
+        let header = entry
+            .get(..ENTRY_HEADER_LEN)
+            .ok_or(CompressionError::CorruptedData)?;
+        let key_len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let key = entry
+            .get(ENTRY_HEADER_LEN..ENTRY_HEADER_LEN + key_len)
+            .ok_or(CompressionError::CorruptedData)?
+            .to_vec();
+        let value = &entry[ENTRY_HEADER_LEN + key_len..];
+
+        match header[0] {
+            OP_PUT => Ok(Self::Put {
+                key,
+                value: value.to_vec(),
+            }),
+            OP_DELETE if value.is_empty() => Ok(Self::Delete { key }),
+            _ => Err(CompressionError::CorruptedData),
+        }
+    }
+}
+
+/// Append-only compressed key-value log.
+///
+/// Every `put` and `delete` appends one record to the active segment file in
+/// `dir`; once a segment reaches the size limit a new one is started. Each
+/// record is a [`container`](crate::container) frame written with the
+/// log's [`Algorithm`] and a CRC-32, holding one `[op: u8][key length:
+/// u32][key][value]` entry, so a lookup reads and decompresses a single
+/// record and damage to it is caught. An in-memory index of each key's
+/// latest record is rebuilt by replaying the segments on [`KvLog::open`],
+/// and [`KvLog::compact`] rewrites only live entries, dropping overwritten
+/// values and deletions.
+#[derive(Debug)]
+pub struct KvLog {
+    dir: PathBuf,
+    algorithm: Algorithm,
+    segment_limit: u64,
+    segments: Vec<u32>,
+    active_len: u64,
+    index: HashMap<Vec<u8>, Location>,
+}
+
+impl KvLog {
+    /// Opens (or creates) a log in `dir` with the default 1 MiB segments,
+    /// compressing new records with `algorithm`. Existing records are read
+    /// with whichever algorithm their frame names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the directory cannot be read,
+    /// `CompressionError::ChecksumMismatch` if a record is damaged, or
+    /// `CompressionError::CorruptedData` if a segment is malformed.
+    pub fn open(dir: impl AsRef<Path>, algorithm: Algorithm) -> Result<Self> {
+        Self::with_segment_limit(dir, algorithm, DEFAULT_SEGMENT_LIMIT)
+    }
+
+    /// Opens a log that rotates segments once they reach `segment_limit`
+    /// bytes.
+    ///
+    /// # Errors
+    ///
+    /// As [`KvLog::open`].
+    pub fn with_segment_limit(
+        dir: impl AsRef<Path>,
+        algorithm: Algorithm,
+        segment_limit: u64,
+    ) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(id) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse().ok())
+            {
+                segments.push(id);
+            }
+        }
+        segments.sort_unstable();
+
+        let mut log = Self {
+            dir,
+            algorithm,
+            segment_limit,
+            segments,
+            active_len: 0,
+            index: HashMap::new(),
+        };
+        for segment in log.segments.clone() {
+            log.active_len = log.replay(segment)?;
+        }
+        Ok(log)
+    }
+
+    #[must_use]
+    pub const fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    #[must_use]
+    pub const fn segment_limit(&self) -> u64 {
+        self.segment_limit
+    }
+
+    /// Number of segment files currently on disk.
+    #[must_use]
+    pub const fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Number of live keys.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Live keys, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.index.keys().map(Vec::as_slice)
+    }
+
+    /// Stores `value` under `key`, replacing any previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from compression or from writing the segment.
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let record = Record::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        };
+        let location = self.append(&record)?;
+        self.index.insert(key.to_vec(), location);
+        Ok(())
+    }
+
+    /// Removes `key`, returning whether it was present.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from compression or from writing the segment.
+    pub fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        if !self.index.contains_key(key) {
+            return Ok(false);
+        }
+        self.append(&Record::Delete { key: key.to_vec() })?;
+        self.index.remove(key);
+        Ok(true)
+    }
+
+    /// Reads the latest value stored under `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the segment cannot be read,
+    /// `CompressionError::ChecksumMismatch` if the record is damaged, or
+    /// `CompressionError::CorruptedData` if it is malformed.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(&location) = self.index.get(key) else {
+            return Ok(None);
+        };
+
+        let mut file = File::open(self.segment_path(location.segment))?;
+        file.seek(SeekFrom::Start(location.offset))?;
+        let mut frame = vec![0u8; location.len as usize];
+        file.read_exact(&mut frame)?;
+
+        match Record::decode(&container::decode_frame(&frame)?)? {
+            Record::Put { value, .. } => Ok(Some(value)),
+            Record::Delete { .. } => Err(CompressionError::CorruptedData),
+        }
+    }
+
+    /// Rewrites all live entries into fresh segments and removes the old
+    /// ones, reclaiming space held by overwritten and deleted entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from reading, compressing, or writing segments.
+    /// The old segments are only removed once the new ones are written.
+    pub fn compact(&mut self) -> Result<()> {
+        let old = std::mem::take(&mut self.segments);
+        if !self.index.is_empty() {
+            self.segments.push(old.last().map_or(0, |&id| id + 1));
+        }
+        self.active_len = 0;
+
+        let mut keys: Vec<Vec<u8>> = self.index.keys().cloned().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let value = self.get(&key)?.ok_or(CompressionError::CorruptedData)?;
+            let location = self.append(&Record::Put {
+                key: key.clone(),
+                value,
+            })?;
+            self.index.insert(key, location);
+        }
+
+        // Oldest first, so a partial cleanup never leaves a put without the
+        // later delete that superseded it.
+        for segment in old {
+            fs::remove_file(self.segment_path(segment))?;
+        }
+        Ok(())
+    }
+
+    fn segment_path(&self, segment: u32) -> PathBuf {
+        self.dir.join(format!("{segment:08}.{SEGMENT_EXTENSION}"))
+    }
+
+    fn append(&mut self, record: &Record) -> Result<Location> {
+        let frame = container::encode_frame_checked(self.algorithm, &record.encode()?)?;
+        let len = u32::try_from(frame.len())
+            .map_err(|_| CompressionError::InvalidInput("record too large".to_string()))?;
+
+        let needs_rotation = self.active_len >= self.segment_limit;
+        let segment = match self.segments.last() {
+            Some(&id) if !needs_rotation => id,
+            Some(&id) => {
+                self.segments.push(id + 1);
+                self.active_len = 0;
+                id + 1
+            }
+            None => {
+                self.segments.push(0);
+                self.active_len = 0;
+                0
+            }
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.segment_path(segment))?;
+        file.write_all(&frame)?;
+
+        let location = Location {
+            segment,
+            offset: self.active_len,
+            len,
+        };
+        self.active_len += frame.len() as u64;
+        Ok(location)
+    }
+
+    /// Applies every record in `segment` to the index, returning its length.
+    fn replay(&mut self, segment: u32) -> Result<u64> {
+        let data = fs::read(self.segment_path(segment))?;
+        let mut pos = 0;
+        while pos < data.len() {
+            let header = FrameHeader::read(&data[pos..])?;
+            let frame = usize::try_from(header.payload_len)
+                .ok()
+                .and_then(|len| len.checked_add(header.encoded_len()))
+                .and_then(|len| data.get(pos..pos.checked_add(len)?))
+                .ok_or(CompressionError::CorruptedData)?;
+            let len = u32::try_from(frame.len()).map_err(|_| CompressionError::CorruptedData)?;
+
+            match Record::decode(&container::decode_frame(frame)?)? {
+                Record::Put { key, .. } => {
+                    let location = Location {
+                        segment,
+                        offset: pos as u64,
+                        len,
+                    };
+                    self.index.insert(key, location);
+                }
+                Record::Delete { key } => {
+                    self.index.remove(&key);
+                }
+            }
+            pos += frame.len();
+        }
+        Ok(data.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "compression_lib_kvlog_{}_{name}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_open_empty() {
+        let dir = TempDir::new("open_empty");
+        let log = KvLog::open(&dir.0, Algorithm::Lz77).unwrap();
+        assert!(log.is_empty());
+        assert_eq!(log.segment_count(), 0);
+        assert_eq!(log.segment_limit(), DEFAULT_SEGMENT_LIMIT);
+        assert_eq!(log.algorithm(), Algorithm::Lz77);
+        assert_eq!(log.get(b"missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_put_get_overwrite() {
+        let dir = TempDir::new("put_get");
+        let mut log = KvLog::open(&dir.0, Algorithm::Huffman).unwrap();
+        log.put(b"alpha", b"one").unwrap();
+        log.put(b"beta", b"two").unwrap();
+        log.put(b"alpha", b"three").unwrap();
+        assert_eq!(log.get(b"alpha").unwrap().unwrap(), b"three");
+        assert_eq!(log.get(b"beta").unwrap().unwrap(), b"two");
+        assert_eq!(log.len(), 2);
+    }
+
+    #[test]
+    fn test_empty_value() {
+        let dir = TempDir::new("empty_value");
+        let mut log = KvLog::open(&dir.0, Algorithm::Rle).unwrap();
+        log.put(b"k", b"").unwrap();
+        assert_eq!(log.get(b"k").unwrap().unwrap(), b"");
+    }
+
+    #[test]
+    fn test_delete() {
+        let dir = TempDir::new("delete");
+        let mut log = KvLog::open(&dir.0, Algorithm::Lz77).unwrap();
+        log.put(b"key", b"value").unwrap();
+        assert!(log.delete(b"key").unwrap());
+        assert!(!log.delete(b"key").unwrap());
+        assert_eq!(log.get(b"key").unwrap(), None);
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_reopen_replays_segments() {
+        let dir = TempDir::new("reopen");
+        {
+            let mut log = KvLog::with_segment_limit(&dir.0, Algorithm::Lz77, 64).unwrap();
+            for i in 0..20u8 {
+                log.put(&[b'k', i], &[i; 30]).unwrap();
+            }
+            log.delete(&[b'k', 3]).unwrap();
+            log.put(&[b'k', 4], b"updated").unwrap();
+        }
+
+        let log = KvLog::with_segment_limit(&dir.0, Algorithm::Lz77, 64).unwrap();
+        assert!(log.segment_count() > 1);
+        assert_eq!(log.len(), 19);
+        assert_eq!(log.get(&[b'k', 3]).unwrap(), None);
+        assert_eq!(log.get(&[b'k', 4]).unwrap().unwrap(), b"updated");
+        assert_eq!(log.get(&[b'k', 19]).unwrap().unwrap(), [19; 30]);
+    }
+
+    #[test]
+    fn test_segment_rotation() {
+        let dir = TempDir::new("rotation");
+        let mut log = KvLog::with_segment_limit(&dir.0, Algorithm::Rle, 32).unwrap();
+        for i in 0..10u8 {
+            log.put(&[i], b"0123456789").unwrap();
+        }
+        assert!(log.segment_count() >= 3);
+        for i in 0..10u8 {
+            assert_eq!(log.get(&[i]).unwrap().unwrap(), b"0123456789");
+        }
+    }
+
+    #[test]
+    fn test_compact_reclaims_space() {
+        let dir = TempDir::new("compact");
+        let mut log = KvLog::with_segment_limit(&dir.0, Algorithm::Huffman, 256).unwrap();
+        for round in 0..10u8 {
+            for key in 0..5u8 {
+                log.put(&[key], &[round; 40]).unwrap();
+            }
+        }
+        log.delete(&[0]).unwrap();
+        let before = log.segment_count();
+
+        log.compact().unwrap();
+        assert!(log.segment_count() < before);
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.get(&[0]).unwrap(), None);
+        assert_eq!(log.get(&[4]).unwrap().unwrap(), [9; 40]);
+
+        let reopened = KvLog::with_segment_limit(&dir.0, Algorithm::Huffman, 256).unwrap();
+        assert_eq!(reopened.len(), 4);
+        assert_eq!(reopened.get(&[2]).unwrap().unwrap(), [9; 40]);
+    }
+
+    #[test]
+    fn test_compact_all_deleted() {
+        let dir = TempDir::new("compact_empty");
+        let mut log = KvLog::open(&dir.0, Algorithm::Rle).unwrap();
+        log.put(b"gone", b"soon").unwrap();
+        log.delete(b"gone").unwrap();
+        log.compact().unwrap();
+        assert_eq!(log.segment_count(), 0);
+
+        log.put(b"back", b"again").unwrap();
+        let reopened = KvLog::open(&dir.0, Algorithm::Rle).unwrap();
+        assert_eq!(reopened.get(b"back").unwrap().unwrap(), b"again");
+        assert_eq!(reopened.get(b"gone").unwrap(), None);
+    }
+
+    #[test]
+    fn test_keys() {
+        let dir = TempDir::new("keys");
+        let mut log = KvLog::open(&dir.0, Algorithm::Rle).unwrap();
+        log.put(b"b", b"2").unwrap();
+        log.put(b"a", b"1").unwrap();
+        let mut keys: Vec<&[u8]> = log.keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, [&b"a"[..], &b"b"[..]]);
+    }
+
+    #[test]
+    fn test_truncated_segment() {
+        let dir = TempDir::new("truncated");
+        {
+            let mut log = KvLog::open(&dir.0, Algorithm::Lz77).unwrap();
+            log.put(b"key", b"value").unwrap();
+        }
+        let path = dir.0.join(format!("{:08}.{SEGMENT_EXTENSION}", 0));
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() - 1]).unwrap();
+
+        let result = KvLog::open(&dir.0, Algorithm::Lz77);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_records_are_checked_frames() {
+        let dir = TempDir::new("checked");
+        {
+            let mut log = KvLog::open(&dir.0, Algorithm::Stored).unwrap();
+            log.put(b"key", b"value").unwrap();
+        }
+        let path = dir.0.join(format!("{:08}.{SEGMENT_EXTENSION}", 0));
+        let mut data = fs::read(&path).unwrap();
+        let header = FrameHeader::read(&data).unwrap();
+        assert_eq!(header.algorithm, Algorithm::Stored);
+        assert!(header.checksum.is_some());
+        assert_eq!(header.original_len, (ENTRY_HEADER_LEN + 8) as u64);
+
+        // Damage that still decodes is caught by the checksum.
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        fs::write(&path, &data).unwrap();
+        let result = KvLog::open(&dir.0, Algorithm::Lz4);
+        assert!(matches!(result, Err(CompressionError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn test_reads_records_of_any_algorithm() {
+        let dir = TempDir::new("mixed");
+        {
+            let mut log = KvLog::open(&dir.0, Algorithm::Huffman).unwrap();
+            log.put(b"old", b"written with huffman").unwrap();
+        }
+        let mut log = KvLog::open(&dir.0, Algorithm::Lz4).unwrap();
+        log.put(b"new", b"written with lz4").unwrap();
+        assert_eq!(log.get(b"old").unwrap().unwrap(), b"written with huffman");
+        assert_eq!(log.get(b"new").unwrap().unwrap(), b"written with lz4");
+    }
+}
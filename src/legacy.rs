@@ -0,0 +1,657 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::lz77::{Lz77, Lz77Tokens};
+use crate::rle::Rle;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+pub const FORMAT_RLE: u8 = 0;
+pub const FORMAT_RLE_ESCAPED: u8 = 1;
+pub const FORMAT_RLE_VARINT: u8 = 2;
+pub const FORMAT_RLE_GOLOMB: u8 = 3;
+pub const FORMAT_LZ77: u8 = 4;
+pub const FORMAT_HUFFMAN: u8 = 5;
+
+/// Preorder tag of a leaf in an original Huffman tree; any other byte is an
+/// internal node.
+const TREE_LEAF: u8 = 1;
+const TREE_INTERNAL: u8 = 0;
+/// Nodes in a full binary tree over all 256 byte values.
+const MAX_TREE_NODES: usize = 511;
+
+/// A node of the tree in an original Huffman frame, with children by index.
+#[derive(Debug, Clone, Copy)]
+enum TreeNode {
+    Leaf(u8),
+    Internal(usize, usize),
+}
+
+/// Reads the subtree at `pos` into `nodes`, returning its index.
+fn read_tree(input: &[u8], pos: &mut usize, nodes: &mut Vec<TreeNode>) -> Result<usize> {
+    let tag = *input.get(*pos).ok_or(CompressionError::CorruptedData)?;
+    if nodes.len() == MAX_TREE_NODES {
+        return Err(CompressionError::CorruptedData);
+    }
+    *pos += 1;
+    let index = nodes.len();
+    if tag == TREE_LEAF {
+        let byte = *input.get(*pos).ok_or(CompressionError::CorruptedData)?;
+        *pos += 1;
+        nodes.push(TreeNode::Leaf(byte));
+    } else {
+        nodes.push(TreeNode::Internal(0, 0));
+        let left = read_tree(input, pos, nodes)?;
+        let right = read_tree(input, pos, nodes)?;
+        nodes[index] = TreeNode::Internal(left, right);
+    }
+    Ok(index)
+}
+
+fn read_u32(input: &[u8], pos: &mut usize) -> Result<usize> {
+    let bytes = input
+        .get(*pos..*pos + 4)
+        .ok_or(CompressionError::CorruptedData)?;
+    *pos += 4;
+    usize::try_from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .map_err(|_| CompressionError::CorruptedData)
+}
+
+/// Reads the tree, original length and bit count of a non-empty original
+/// Huffman frame, returning them with the offset of the coded bits.
+fn read_tree_header(input: &[u8]) -> Result<(Vec<TreeNode>, usize, usize, usize)> {
+    let mut pos = 0;
+    let mut nodes = Vec::new();
+    read_tree(input, &mut pos, &mut nodes)?;
+    let original_len = read_u32(input, &mut pos)?;
+    let num_bits = read_u32(input, &mut pos)?;
+    Ok((nodes, original_len, num_bits, pos))
+}
+
+/// Writes `input` as the original Huffman codec did: `[tree: preorder, 0 per
+/// internal node and 1 plus the byte per leaf][original length: u32 LE]
+/// [bit count: u32 LE][codes: bits, MSB-first]`, or nothing for empty input.
+/// A tree of one leaf codes each byte as a single 0 bit.
+fn compress_tree(input: &[u8]) -> Result<Vec<u8>> {
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+    let original_len = u32::try_from(input.len()).map_err(|_| {
+        CompressionError::InvalidInput("original Huffman frames hold under 4 GiB".to_string())
+    })?;
+    let mut freqs = [0u64; 256];
+    for &byte in input {
+        freqs[usize::from(byte)] += 1;
+    }
+    let mut nodes = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (byte, &freq) in (0..=u8::MAX).zip(&freqs) {
+        if freq > 0 {
+            heap.push(Reverse((freq, nodes.len())));
+            nodes.push(TreeNode::Leaf(byte));
+        }
+    }
+    while let (Some(Reverse((left_freq, left))), Some(Reverse((right_freq, right)))) =
+        (heap.pop(), heap.pop())
+    {
+        heap.push(Reverse((left_freq + right_freq, nodes.len())));
+        nodes.push(TreeNode::Internal(left, right));
+    }
+    // With the heap drained, the last node built is the root.
+    let mut output = Vec::new();
+    let mut codes = vec![Vec::new(); 256];
+    let mut stack = vec![(nodes.len() - 1, Vec::new())];
+    while let Some((index, prefix)) = stack.pop() {
+        match nodes[index] {
+            TreeNode::Leaf(byte) => {
+                output.extend_from_slice(&[TREE_LEAF, byte]);
+                codes[usize::from(byte)] = if prefix.is_empty() {
+                    vec![false]
+                } else {
+                    prefix
+                };
+            }
+            TreeNode::Internal(left, right) => {
+                output.push(TREE_INTERNAL);
+                let mut right_prefix = prefix.clone();
+                right_prefix.push(true);
+                stack.push((right, right_prefix));
+                let mut left_prefix = prefix;
+                left_prefix.push(false);
+                stack.push((left, left_prefix));
+            }
+        }
+    }
+
+    let mut writer = BitWriter::new();
+    for &byte in input {
+        for &bit in &codes[usize::from(byte)] {
+            writer.write_bit(bit);
+        }
+    }
+    // A Huffman code never takes more bits than 8 per byte.
+    let num_bits = u32::try_from(writer.bit_len()).map_err(|_| {
+        CompressionError::InvalidInput("original Huffman frames hold under 4 GiB".to_string())
+    })?;
+    output.extend_from_slice(&original_len.to_le_bytes());
+    output.extend_from_slice(&num_bits.to_le_bytes());
+    output.extend(writer.finish());
+    Ok(output)
+}
+
+/// Decodes the original Huffman frame at the start of `input`, which ends
+/// after the bytes holding its bit count.
+fn decompress_tree(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let (nodes, original_len, num_bits, pos) = read_tree_header(input)?;
+    let end = pos + num_bits.div_ceil(8);
+    let bits = input.get(pos..end).ok_or(CompressionError::CorruptedData)?;
+    // Every byte was coded with at least one bit.
+    if original_len > num_bits {
+        return Err(CompressionError::CorruptedData);
+    }
+    if let TreeNode::Leaf(byte) = nodes[0] {
+        return Ok((vec![byte; original_len], end));
+    }
+
+    let mut reader = BitReader::new(bits);
+    let mut output = Vec::with_capacity(original_len);
+    let mut node = 0;
+    for _ in 0..num_bits {
+        if output.len() == original_len {
+            break;
+        }
+        let bit = reader.read_bit().ok_or(CompressionError::CorruptedData)?;
+        if let TreeNode::Internal(left, right) = nodes[node] {
+            node = if bit { right } else { left };
+        }
+        if let TreeNode::Leaf(byte) = nodes[node] {
+            output.push(byte);
+            node = 0;
+        }
+    }
+    if output.len() != original_len {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok((output, end))
+}
+
+/// A raw, untagged output format of one of the original codecs.
+///
+/// Frames written by [`Rle`], [`Lz77`] and the original Huffman codec carry
+/// nothing that says which codec wrote them, so data stored before any
+/// container existed can only be read by naming its format explicitly. The
+/// original codecs wrote nothing at all for empty input, so an empty raw
+/// frame decodes to empty output in every format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LegacyFormat {
+    /// [`Rle::new`] pairs.
+    Rle,
+    /// [`Rle::escaped`] PackBits-style blocks.
+    RleEscaped,
+    /// [`Rle::varint`] pairs.
+    RleVarint,
+    /// [`Rle::golomb`] run lengths.
+    RleGolomb,
+    /// [`Lz77::new`] fixed-width tokens, or varint-offset or bit-packed
+    /// ones, which mark themselves; the window does not affect decoding.
+    Lz77,
+    /// Frames of the original tree-based Huffman codec, which stored its
+    /// tree in preorder ahead of the u32 original length and bit count.
+    /// [`Huffman`](crate::Huffman) has since moved to canonical codes.
+    Huffman,
+}
+
+impl LegacyFormat {
+    /// Every format, in tag order.
+    pub const ALL: [Self; 6] = [
+        Self::Rle,
+        Self::RleEscaped,
+        Self::RleVarint,
+        Self::RleGolomb,
+        Self::Lz77,
+        Self::Huffman,
+    ];
+
+    /// The byte that identifies this format in a [`Legacy`] frame.
+    #[must_use]
+    pub const fn tag(self) -> u8 {
+        match self {
+            Self::Rle => FORMAT_RLE,
+            Self::RleEscaped => FORMAT_RLE_ESCAPED,
+            Self::RleVarint => FORMAT_RLE_VARINT,
+            Self::RleGolomb => FORMAT_RLE_GOLOMB,
+            Self::Lz77 => FORMAT_LZ77,
+            Self::Huffman => FORMAT_HUFFMAN,
+        }
+    }
+
+    #[must_use]
+    pub const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            FORMAT_RLE => Some(Self::Rle),
+            FORMAT_RLE_ESCAPED => Some(Self::RleEscaped),
+            FORMAT_RLE_VARINT => Some(Self::RleVarint),
+            FORMAT_RLE_GOLOMB => Some(Self::RleGolomb),
+            FORMAT_LZ77 => Some(Self::Lz77),
+            FORMAT_HUFFMAN => Some(Self::Huffman),
+            _ => None,
+        }
+    }
+
+    /// Compresses `input` into this raw format.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the underlying codec.
+    pub fn compress(self, input: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Rle => Rle::new().compress(input),
+            Self::RleEscaped => Rle::escaped().compress(input),
+            Self::RleVarint => Rle::varint().compress(input),
+            Self::RleGolomb => Rle::golomb().compress(input),
+            Self::Lz77 => Lz77::new().with_tokens(Lz77Tokens::Fixed).compress(input),
+            Self::Huffman => compress_tree(input),
+        }
+    }
+
+    /// Upper bound on the length of a raw frame of this format compressed
+    /// from `input_len` bytes.
+    #[must_use]
+    pub fn max_compressed_len(self, input_len: usize) -> usize {
+        match self {
+            Self::Rle => Rle::new().max_compressed_len(input_len),
+            Self::RleEscaped => Rle::escaped().max_compressed_len(input_len),
+            Self::RleVarint => Rle::varint().max_compressed_len(input_len),
+            Self::RleGolomb => Rle::golomb().max_compressed_len(input_len),
+            Self::Lz77 => Lz77::new()
+                .with_tokens(Lz77Tokens::Fixed)
+                .max_compressed_len(input_len),
+            // A tree over all 256 bytes, the two lengths, and at most 8 bits
+            // per byte.
+            Self::Huffman => (256 * 2 + 255 + 8usize).saturating_add(input_len),
+        }
+    }
+
+    /// Whether a raw frame of this format marks its own end. Pair-format
+    /// RLE frames do not, so they run to the end of their input.
+    #[must_use]
+    pub const fn is_self_delimiting(self) -> bool {
+        !matches!(self, Self::Rle | Self::RleVarint)
+    }
+
+    /// Decodes one raw frame of this format from the start of `input`,
+    /// returning the data and the number of bytes consumed, which is all of
+    /// `input` for formats that are not [self-delimiting]. Empty `input` is
+    /// an empty frame.
+    ///
+    /// [self-delimiting]: LegacyFormat::is_self_delimiting
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the underlying codec.
+    pub fn decompress_partial(self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        if input.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
+        match self {
+            Self::Rle => Ok((Rle::new().decompress(input)?, input.len())),
+            Self::RleEscaped => Rle::escaped().decompress_partial(input),
+            Self::RleVarint => Ok((Rle::varint().decompress(input)?, input.len())),
+            Self::RleGolomb => Rle::golomb().decompress_partial(input),
+            Self::Lz77 => Lz77::new().decompress_partial(input),
+            Self::Huffman => decompress_tree(input),
+        }
+    }
+
+    /// Reads the original length from the header of a raw frame of this
+    /// format; `None` for the pair formats, which record none.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the underlying codec.
+    pub fn decompressed_len(self, input: &[u8]) -> Result<Option<u64>> {
+        if input.is_empty() {
+            return Ok(Some(0));
+        }
+        match self {
+            Self::Rle => Rle::new().decompressed_len(input),
+            Self::RleEscaped => Rle::escaped().decompressed_len(input),
+            Self::RleVarint => Rle::varint().decompressed_len(input),
+            Self::RleGolomb => Rle::golomb().decompressed_len(input),
+            Self::Lz77 => Lz77::new().decompressed_len(input),
+            Self::Huffman => read_tree_header(input).map(|(_, len, _, _)| Some(len as u64)),
+        }
+    }
+}
+
+/// Codec that tags raw frames with their [`LegacyFormat`] so they can pass
+/// through containers that expect a self-describing codec.
+///
+/// Existing raw data is wrapped with [`Legacy::wrap`] without being
+/// decoded or recompressed, after which it reads back through
+/// [`Chunked`](crate::Chunked), [`Mux`](crate::Mux), the pipe helpers or
+/// anything else that takes a [`Codec`](crate::Codec). Decompression
+/// follows the tag of each frame, whatever format the instance writes, so
+/// one `Legacy` reads a mix of formats. A pair-format RLE frame runs to
+/// the end of its input, so it can only be the last of several frames read
+/// back to back. Output layout: `[format: u8][raw
+/// frame]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Legacy {
+    format: LegacyFormat,
+}
+
+impl Legacy {
+    /// Creates a codec that writes new data in `format`.
+    #[must_use]
+    pub const fn new(format: LegacyFormat) -> Self {
+        Self { format }
+    }
+
+    #[must_use]
+    pub const fn format(&self) -> LegacyFormat {
+        self.format
+    }
+
+    /// Tags a raw frame written by the original codec for `format`.
+    #[must_use]
+    pub fn wrap(format: LegacyFormat, raw: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(1 + raw.len());
+        frame.push(format.tag());
+        frame.extend_from_slice(raw);
+        frame
+    }
+
+    /// The format a tagged `frame` holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidHeader` if `frame` is empty or its
+    /// tag is unknown.
+    pub fn detect(frame: &[u8]) -> Result<LegacyFormat> {
+        frame
+            .first()
+            .and_then(|&tag| LegacyFormat::from_tag(tag))
+            .ok_or(CompressionError::InvalidHeader)
+    }
+}
+
+impl Compressor for Legacy {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Ok(Self::wrap(self.format, &self.format.compress(input)?))
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        self.format.max_compressed_len(input_len).saturating_add(1)
+    }
+
+    fn name(&self) -> &'static str {
+        "Legacy"
+    }
+}
+
+impl Decompressor for Legacy {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        Self::detect(input)?.decompressed_len(&input[1..])
+    }
+
+    fn name(&self) -> &'static str {
+        "Legacy"
+    }
+}
+
+impl PartialDecompressor for Legacy {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let format = Self::detect(input)?;
+        let (output, consumed) = format.decompress_partial(&input[1..])?;
+        Ok((output, 1 + consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunked, Demux, Mux};
+
+    const TEXT: &[u8] = b"aaaaabbbbbcccccc stored before the container existed, \
+        stored before the container existed";
+
+    #[test]
+    fn test_legacy_name() {
+        let legacy = Legacy::new(LegacyFormat::Lz77);
+        assert_eq!(Compressor::name(&legacy), "Legacy");
+        assert_eq!(Decompressor::name(&legacy), "Legacy");
+        assert_eq!(legacy.format(), LegacyFormat::Lz77);
+    }
+
+    #[test]
+    fn test_tags_roundtrip() {
+        for (i, format) in LegacyFormat::ALL.into_iter().enumerate() {
+            assert_eq!(usize::from(format.tag()), i);
+            assert_eq!(LegacyFormat::from_tag(format.tag()), Some(format));
+        }
+        assert_eq!(LegacyFormat::from_tag(6), None);
+    }
+
+    #[test]
+    fn test_reads_existing_raw_frames() {
+        let raw = [
+            (LegacyFormat::Rle, Rle::new().compress(TEXT).unwrap()),
+            (
+                LegacyFormat::RleEscaped,
+                Rle::escaped().compress(TEXT).unwrap(),
+            ),
+            (
+                LegacyFormat::RleVarint,
+                Rle::varint().compress(TEXT).unwrap(),
+            ),
+            (
+                LegacyFormat::RleGolomb,
+                Rle::golomb().compress(TEXT).unwrap(),
+            ),
+            (LegacyFormat::Lz77, Lz77::realtime().compress(TEXT).unwrap()),
+        ];
+        // Any instance reads any format; the tag decides.
+        let reader = Legacy::new(LegacyFormat::Rle);
+        for (format, raw) in raw {
+            let frame = Legacy::wrap(format, &raw);
+            assert_eq!(Legacy::detect(&frame), Ok(format));
+            assert_eq!(reader.decompress(&frame).unwrap(), TEXT, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_reads_original_codec_output() {
+        // Written by the first release's `Rle`, `Lz77` and `Huffman`; its
+        // Huffman built the tree in hash order, so any shape must decode.
+        const ABRACADABRA: &[u8] = b"abracadabra";
+        const RUNS: &[u8] = b"aaaaabbbbbcccccc legacy legacy";
+        let frames: [(&[u8], LegacyFormat, &[u8]); 9] = [
+            (
+                ABRACADABRA,
+                LegacyFormat::Rle,
+                &[
+                    1, 97, 1, 98, 1, 114, 1, 97, 1, 99, 1, 97, 1, 100, 1, 97, 1, 98, 1, 114, 1, 97,
+                ],
+            ),
+            (
+                ABRACADABRA,
+                LegacyFormat::Lz77,
+                &[
+                    11, 0, 0, 0, 0, 0, 0, 97, 0, 0, 0, 98, 0, 0, 0, 114, 0, 0, 0, 97, 0, 0, 0, 99,
+                    0, 0, 0, 97, 0, 0, 0, 100, 7, 0, 4, 0,
+                ],
+            ),
+            (
+                ABRACADABRA,
+                LegacyFormat::Huffman,
+                &[
+                    0, 1, 97, 0, 0, 1, 100, 1, 99, 0, 1, 114, 1, 98, 11, 0, 0, 0, 23, 0, 0, 0, 124,
+                    168, 248,
+                ],
+            ),
+            (b"a", LegacyFormat::Rle, &[1, 97]),
+            (b"a", LegacyFormat::Lz77, &[1, 0, 0, 0, 0, 0, 0, 97]),
+            (
+                b"a",
+                LegacyFormat::Huffman,
+                &[1, 97, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+            ),
+            (
+                RUNS,
+                LegacyFormat::Rle,
+                &[
+                    5, 97, 5, 98, 6, 99, 1, 32, 1, 108, 1, 101, 1, 103, 1, 97, 1, 99, 1, 121, 1,
+                    32, 1, 108, 1, 101, 1, 103, 1, 97, 1, 99, 1, 121,
+                ],
+            ),
+            (
+                RUNS,
+                LegacyFormat::Lz77,
+                &[
+                    30, 0, 0, 0, 0, 0, 0, 97, 1, 0, 4, 98, 1, 0, 4, 99, 1, 0, 5, 32, 0, 0, 0, 108,
+                    0, 0, 0, 101, 0, 0, 0, 103, 0, 0, 0, 97, 0, 0, 0, 99, 0, 0, 0, 121, 7, 0, 7, 0,
+                ],
+            ),
+            (
+                RUNS,
+                LegacyFormat::Huffman,
+                &[
+                    0, 0, 0, 1, 101, 0, 1, 32, 1, 108, 1, 97, 0, 1, 99, 0, 0, 1, 121, 1, 103, 1,
+                    98, 30, 0, 0, 0, 83, 0, 0, 0, 85, 127, 255, 213, 81, 24, 214, 194, 49, 173,
+                    128,
+                ],
+            ),
+        ];
+        let reader = Legacy::new(LegacyFormat::Rle);
+        for (input, format, raw) in frames {
+            let frame = Legacy::wrap(format, raw);
+            assert_eq!(reader.decompress(&frame).unwrap(), input, "{format:?}");
+            if format.is_self_delimiting() {
+                assert_eq!(
+                    reader.decompressed_len(&frame).unwrap(),
+                    Some(input.len() as u64)
+                );
+            }
+        }
+
+        // The original codecs wrote nothing for empty input.
+        for format in LegacyFormat::ALL {
+            let frame = Legacy::wrap(format, &[]);
+            assert_eq!(reader.decompress(&frame).unwrap(), b"", "{format:?}");
+            assert_eq!(reader.decompressed_len(&frame).unwrap(), Some(0));
+        }
+        assert_eq!(LegacyFormat::Huffman.compress(b"").unwrap(), b"");
+    }
+
+    #[test]
+    fn test_huffman_tree_corrupted() {
+        let raw = LegacyFormat::Huffman.compress(TEXT).unwrap();
+        for len in 1..raw.len() {
+            assert!(
+                LegacyFormat::Huffman
+                    .decompress_partial(&raw[..len])
+                    .is_err(),
+                "{len}"
+            );
+        }
+        // A one-leaf tree claiming more bytes than it has bits.
+        assert!(
+            LegacyFormat::Huffman
+                .decompress_partial(&[1, 97, 0xFF, 0xFF, 0xFF, 0xFF, 1, 0, 0, 0, 0])
+                .is_err()
+        );
+        // Internal nodes nested past any real tree.
+        assert!(
+            LegacyFormat::Huffman
+                .decompress_partial(&[0; 1024])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_compress_writes_raw_format() {
+        for format in LegacyFormat::ALL {
+            let legacy = Legacy::new(format);
+            let frame = legacy.compress(TEXT).unwrap();
+            assert_eq!(frame[0], format.tag());
+            assert_eq!(frame[1..], format.compress(TEXT).unwrap());
+            assert_eq!(legacy.decompress(&frame).unwrap(), TEXT);
+            assert_eq!(
+                legacy.decompress(&legacy.compress(b"").unwrap()).unwrap(),
+                b""
+            );
+        }
+    }
+
+    #[test]
+    fn test_inside_containers() {
+        let legacy = Legacy::new(LegacyFormat::Huffman);
+        let input = TEXT.repeat(20);
+        let chunked = Chunked::with_threshold(legacy, 256, 256);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(chunked.decompress(&compressed).unwrap(), input);
+
+        let mut mux = Mux::new(Vec::new(), Legacy::new(LegacyFormat::RleEscaped));
+        mux.write(1, TEXT).unwrap();
+        mux.write(2, b"second").unwrap();
+        mux.flush().unwrap();
+        let stream = mux.into_inner();
+        let channels = Demux::new(stream.as_slice(), legacy).split().unwrap();
+        assert_eq!(channels[&1], TEXT);
+        assert_eq!(channels[&2], b"second");
+    }
+
+    #[test]
+    fn test_decompress_partial_mixed_formats() {
+        let stream = [
+            Legacy::new(LegacyFormat::Lz77).compress(TEXT).unwrap(),
+            Legacy::wrap(
+                LegacyFormat::RleGolomb,
+                &Rle::golomb().compress(b"xx").unwrap(),
+            ),
+            Legacy::new(LegacyFormat::Huffman).compress(b"mid").unwrap(),
+            // Pair frames are not self-delimiting, so one can only come last.
+            Legacy::new(LegacyFormat::Rle).compress(b"tail").unwrap(),
+        ]
+        .concat();
+        let frames = Legacy::new(LegacyFormat::Rle)
+            .decompress_frames(&stream)
+            .unwrap();
+        assert_eq!(
+            frames,
+            [
+                TEXT.to_vec(),
+                b"xx".to_vec(),
+                b"mid".to_vec(),
+                b"tail".to_vec()
+            ]
+        );
+        assert!(LegacyFormat::Lz77.is_self_delimiting());
+        assert!(!LegacyFormat::RleVarint.is_self_delimiting());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let legacy = Legacy::new(LegacyFormat::Lz77);
+        assert_eq!(legacy.decompress(&[]), Err(CompressionError::InvalidHeader));
+        assert_eq!(
+            legacy.decompress(&[9, 0, 0]),
+            Err(CompressionError::InvalidHeader)
+        );
+        let mut trailing = legacy.compress(TEXT).unwrap();
+        trailing.push(0);
+        assert!(legacy.decompress(&trailing).is_err());
+        let frame = legacy.compress(TEXT).unwrap();
+        assert!(legacy.decompress(&frame[..frame.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+/// How hard a codec works to shrink its input, from 1
+/// ([`CompressionLevel::FASTEST`]) to 9 ([`CompressionLevel::BEST`]).
+///
+/// [`Compressor::with_level`](crate::Compressor::with_level) maps the level
+/// onto each codec's own knobs: window size and match search depth for the
+/// LZ family, context order for PPM, codeword width for Tunstall, block
+/// size for BWT. Level 6,
+/// [`CompressionLevel::DEFAULT`], is the configuration `new()` gives, so
+/// frames only change when a level is chosen. Codecs with nothing to tune
+/// accept every level and compress the same at all of them. The level is
+/// never written to the frame; decoding needs no knowledge of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompressionLevel(u8);
+
+impl CompressionLevel {
+    pub const FASTEST: Self = Self(1);
+    pub const DEFAULT: Self = Self(6);
+    pub const BEST: Self = Self(9);
+
+    /// Creates level `level`, clamped to `1..=9`.
+    #[must_use]
+    pub const fn new(level: u8) -> Self {
+        if level < Self::FASTEST.0 {
+            Self::FASTEST
+        } else if level > Self::BEST.0 {
+            Self::BEST
+        } else {
+            Self(level)
+        }
+    }
+
+    #[must_use]
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    /// Picks this level's entry from a table of one setting per level,
+    /// fastest first.
+    #[must_use]
+    pub const fn pick<T: Copy>(self, table: &[T; 9]) -> T {
+        table[self.0 as usize - 1]
+    }
+}
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_clamps() {
+        assert_eq!(CompressionLevel::new(0), CompressionLevel::FASTEST);
+        assert_eq!(CompressionLevel::new(5).get(), 5);
+        assert_eq!(CompressionLevel::new(200), CompressionLevel::BEST);
+        assert_eq!(CompressionLevel::default().get(), 6);
+        assert!(CompressionLevel::FASTEST < CompressionLevel::BEST);
+    }
+
+    #[test]
+    fn test_pick() {
+        let table = [10, 20, 30, 40, 50, 60, 70, 80, 90];
+        assert_eq!(CompressionLevel::FASTEST.pick(&table), 10);
+        assert_eq!(CompressionLevel::DEFAULT.pick(&table), 60);
+        assert_eq!(CompressionLevel::BEST.pick(&table), 90);
+    }
+}
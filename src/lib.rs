@@ -17,17 +17,166 @@
 //! assert_eq!(decompressed, data);
 //! ```
 
+#![cfg_attr(not(any(feature = "ffi", feature = "mmap")), forbid(unsafe_code))]
+
+mod algorithm;
+pub mod amalgamate;
+mod arithmetic;
+#[cfg(feature = "async")]
+mod async_io;
+mod auto;
+mod bcj;
+mod bitmap;
+mod bitshuffle;
+mod bitstream;
+mod bwt;
+mod channel;
+mod checksum;
+mod chunked;
+mod chunking;
+mod classify;
+mod cm;
+mod cobs;
+#[cfg(feature = "compat-tests")]
+pub mod compat;
+pub mod container;
+#[cfg(feature = "corpus")]
+pub mod corpus;
+mod delta;
+pub mod difftest;
+pub mod dispatch;
 mod error;
+#[cfg(feature = "ffi")]
+#[allow(unsafe_code)]
+pub mod ffi;
+mod frame_delta;
+pub mod fs;
+#[cfg(feature = "codec")]
+mod framed;
+pub mod hash;
 mod huffman;
+mod id_column;
+mod identify;
+pub mod image;
+mod iter;
+mod kvlog;
+mod legacy;
+mod level;
+mod limits;
+mod lz4;
 mod lz77;
+mod lz77_huffman;
+mod lzma;
+mod lzp;
+mod mtf;
+mod mux;
+#[cfg(test)]
+mod oracle;
+mod pipe;
+mod pipeline;
+mod ppm;
+mod predictor;
+mod prefix_code;
+mod profile;
+mod progress;
+mod range_coder;
+pub mod registry;
+mod repair;
+mod rice;
 mod rle;
+mod rle0;
+mod search;
+#[cfg(feature = "serde")]
+mod serde_message;
+mod sequence;
+mod shannon_fano;
+mod shuffle;
+mod sink;
+mod sketch;
+mod snappy;
+pub mod spec;
+mod stats;
+mod stored;
+mod tiering;
+mod timestamp;
 mod traits;
+mod tunstall;
+pub mod varint;
+mod word_huffman;
+mod xor_float;
+mod zero_run;
 
+pub use algorithm::Algorithm;
+pub use arithmetic::Arithmetic;
+#[cfg(feature = "async")]
+pub use async_io::{AsyncCompressorWriter, AsyncDecompressorReader};
+pub use auto::{Auto, AutoAlgorithm};
+pub use bcj::{Bcj, BcjArch};
+pub use bitmap::{Bitmap, BitmapCodec};
+pub use bitshuffle::BitShuffle;
+pub use bwt::Bwt;
+pub use channel::{CompressedReceiver, CompressedSender, Message, compressed_channel};
+pub use checksum::{Checksum, ChecksumKind, adler32, crc32, xxhash64};
+pub use chunked::Chunked;
+pub use chunking::{Chunker, Chunks, Dedup};
+pub use classify::{ContentClass, classify};
+pub use cm::Cm;
+pub use cobs::Cobs;
+pub use container::Container;
+pub use delta::Delta;
 pub use error::{CompressionError, Result};
-pub use huffman::Huffman;
-pub use lz77::Lz77;
-pub use rle::Rle;
-pub use traits::{Codec, Compressor, Decompressor};
+pub use frame_delta::{DeltaMode, FrameDelta};
+#[cfg(feature = "codec")]
+pub use framed::MessageCodec;
+pub use huffman::{
+    CodeLen, EfficiencyReport, Huffman, HuffmanBuilder, Preset, SymbolEfficiency,
+    analyze_code_efficiency, build_codes,
+};
+pub use id_column::IdColumn;
+pub use identify::{Format, identify};
+pub use iter::{ChunkIterExt, Compressed, Decompressed};
+pub use kvlog::KvLog;
+pub use legacy::{Legacy, LegacyFormat};
+pub use level::CompressionLevel;
+pub use limits::DecompressionLimits;
+pub use lz4::Lz4;
+pub use lz77::{Lz77, Lz77Builder, Lz77Tokens};
+pub use lz77_huffman::Lz77Huffman;
+pub use lzma::Lzma;
+pub use lzp::Lzp;
+pub use mtf::Mtf;
+pub use mux::{Demux, Frame, Mux};
+pub use pipe::{
+    CompressorWriter, CopyOptions, CopyStats, DecompressorReader, StreamingCompressor,
+    StreamingDecompressor, copy_compress, copy_compress_slice, copy_decompress,
+    copy_decompress_buf,
+};
+pub use pipeline::Pipeline;
+pub use ppm::Ppm;
+pub use predictor::{PngFilter, Predictor};
+pub use profile::Profile;
+pub use progress::Progress;
+pub use repair::{Grammar, RePair};
+pub use rice::Rice;
+pub use rle::{Rle, RleMode};
+pub use rle0::Rle0;
+pub use search::{search, search_chunks};
+#[cfg(feature = "serde")]
+pub use serde_message::SerdeMessage;
+pub use sequence::{decode_i64_sequence, encode_i64_sequence};
+pub use shannon_fano::ShannonFano;
+pub use shuffle::Shuffle;
+pub use sketch::FrequencySketch;
+pub use snappy::Snappy;
+pub use stats::CompressionStats;
+pub use stored::Stored;
+pub use tiering::{MigratedBlocks, Migration, Tier, TieredStore};
+pub use timestamp::DeltaOfDelta;
+pub use traits::{Codec, Compressor, Decompressor, PartialDecompressor, Transform};
+pub use tunstall::Tunstall;
+pub use word_huffman::WordHuffman;
+pub use xor_float::XorFloat;
+pub use zero_run::ZeroRun;
 
 #[cfg(test)]
 mod tests {
@@ -91,6 +240,7 @@ mod tests {
 
     #[test]
     fn test_result_type_alias() {
+        #[allow(clippy::unnecessary_wraps)]
         fn returns_result() -> Result<Vec<u8>> {
             Ok(vec![1, 2, 3])
         }
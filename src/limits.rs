@@ -0,0 +1,126 @@
+use crate::error::{CompressionError, Result};
+
+/// Bounds on what decoding untrusted input may produce, as a defence
+/// against decompression bombs: a few bytes of crafted RLE or LZ77 can
+/// describe gigabytes of output.
+///
+/// Pass them to
+/// [`Decompressor::decompress_with_limits`](crate::Decompressor::decompress_with_limits),
+/// or to [`CopyOptions::with_limits`](crate::CopyOptions::with_limits) for
+/// streams. Codecs that record the original length in their header are
+/// refused from the header alone, before anything is decoded; the rest are
+/// checked once decoded, or as they decode where the codec supports it.
+/// Both limits are off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecompressionLimits {
+    max_output_size: u64,
+    max_ratio: u64,
+}
+
+impl Default for DecompressionLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecompressionLimits {
+    /// Limits that allow anything.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_output_size: u64::MAX,
+            max_ratio: u64::MAX,
+        }
+    }
+
+    /// Refuses output longer than `max_output_size` bytes.
+    #[must_use]
+    pub const fn with_max_output_size(mut self, max_output_size: u64) -> Self {
+        self.max_output_size = max_output_size;
+        self
+    }
+
+    /// Refuses output longer than `max_ratio` times its compressed input.
+    /// Real data rarely passes 1000; runs of one byte reach a few hundred
+    /// with RLE.
+    #[must_use]
+    pub const fn with_max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = max_ratio;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_output_size(&self) -> u64 {
+        self.max_output_size
+    }
+
+    #[must_use]
+    pub const fn max_ratio(&self) -> u64 {
+        self.max_ratio
+    }
+
+    /// The most output both limits allow from `input_len` bytes of input,
+    /// for decoders that cannot know their output length up front and
+    /// stop once they pass it.
+    #[must_use]
+    pub const fn max_output_for(&self, input_len: u64) -> u64 {
+        let by_ratio = input_len.saturating_mul(self.max_ratio);
+        if by_ratio < self.max_output_size {
+            by_ratio
+        } else {
+            self.max_output_size
+        }
+    }
+
+    /// Checks `output_len` bytes of output decoded from `input_len` bytes
+    /// against both limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::LimitExceeded` naming the limit broken.
+    pub fn check(&self, input_len: u64, output_len: u64) -> Result<()> {
+        if output_len > self.max_output_size {
+            return Err(CompressionError::LimitExceeded(format!(
+                "output of {output_len} bytes exceeds the limit of {}",
+                self.max_output_size
+            )));
+        }
+        if output_len > input_len.saturating_mul(self.max_ratio) {
+            return Err(CompressionError::LimitExceeded(format!(
+                "output of {output_len} bytes from {input_len} exceeds the ratio limit of {}",
+                self.max_ratio
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let unlimited = DecompressionLimits::default();
+        assert_eq!(unlimited, DecompressionLimits::new());
+        assert!(unlimited.check(1, u64::MAX).is_ok());
+
+        let limits = DecompressionLimits::new()
+            .with_max_output_size(1000)
+            .with_max_ratio(10);
+        assert_eq!((limits.max_output_size(), limits.max_ratio()), (1000, 10));
+        assert!(limits.check(100, 1000).is_ok());
+        assert!(matches!(
+            limits.check(200, 1001),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert!(limits.check(10, 100).is_ok());
+        assert_eq!(limits.max_output_for(10), 100);
+        assert_eq!(limits.max_output_for(200), 1000);
+        assert_eq!(unlimited.max_output_for(u64::MAX), u64::MAX);
+        assert!(matches!(
+            limits.check(10, 101),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+}
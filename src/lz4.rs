@@ -0,0 +1,428 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{CompressionError, Result};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Shortest match a sequence can carry.
+pub const MIN_MATCH: usize = 4;
+/// Farthest back a match can reach: offsets are 16-bit.
+pub const MAX_OFFSET: usize = u16::MAX as usize;
+/// Trailing bytes that are always literals.
+pub const LAST_LITERALS: usize = 5;
+/// A match must start at least this many bytes before the end.
+pub const MATCH_FIND_LIMIT: usize = 12;
+/// Nibble value meaning "extra length bytes follow".
+pub const RUN_MASK: u8 = 0x0F;
+const HASH_BITS: u32 = 12;
+/// Searches speed up by one byte per this many bytes without a match.
+const SKIP_TRIGGER: u32 = 6;
+
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]])
+}
+
+const fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2_654_435_761) >> (u32::BITS - HASH_BITS)) as usize
+}
+
+/// Appends the bytes extending a nibble length of 15.
+fn write_length(output: &mut Vec<u8>, mut rest: usize) {
+    while rest >= 255 {
+        output.push(255);
+        rest -= 255;
+    }
+    output.push(rest.to_le_bytes()[0]);
+}
+
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], offset: usize, match_len: usize) {
+    let literal_nibble = literals.len().min(usize::from(RUN_MASK));
+    let match_nibble = (match_len - MIN_MATCH).min(usize::from(RUN_MASK));
+    output.push((literal_nibble << 4 | match_nibble).to_le_bytes()[0]);
+    if literal_nibble == usize::from(RUN_MASK) {
+        write_length(output, literals.len() - literal_nibble);
+    }
+    output.extend_from_slice(literals);
+    output.extend_from_slice(&offset.to_le_bytes()[..2]);
+    if match_nibble == usize::from(RUN_MASK) {
+        write_length(output, match_len - MIN_MATCH - match_nibble);
+    }
+}
+
+fn write_last_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    let literal_nibble = literals.len().min(usize::from(RUN_MASK));
+    output.push((literal_nibble << 4).to_le_bytes()[0]);
+    if literal_nibble == usize::from(RUN_MASK) {
+        write_length(output, literals.len() - literal_nibble);
+    }
+    output.extend_from_slice(literals);
+}
+
+/// Reads the extension of a nibble length of 15.
+fn read_length(input: &[u8], pos: &mut usize, nibble: u8) -> Result<usize> {
+    let mut len = usize::from(nibble);
+    if nibble == RUN_MASK {
+        loop {
+            let &byte = input.get(*pos).ok_or(CompressionError::CorruptedData)?;
+            *pos += 1;
+            len = len
+                .checked_add(usize::from(byte))
+                .ok_or(CompressionError::CorruptedData)?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Ok(len)
+}
+
+/// Byte-aligned LZ77 in the LZ4 block format, tuned for speed over ratio.
+///
+/// Each sequence is a token byte whose high nibble is a literal count and
+/// low nibble a match length minus four, either extended by bytes when it
+/// reads 15, followed by the literals and a 16-bit little-endian offset.
+/// Matches are found through a single-probe hash table and decoding is a
+/// plain copy loop with no bit manipulation. The last sequence holds only
+/// literals, and the block body is readable by any LZ4 block decoder
+/// given the length. Output layout: `[original length: varint][token]
+/// [literal length bytes][literals][offset: u16 LE][match length bytes]`
+/// per sequence.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Lz4;
+
+impl Lz4 {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Lz4 {
+    /// Encodes `input`, counting its literals and matches into `stats`
+    /// if given.
+    fn encode(input: &[u8], stats: Option<&mut CompressionStats>) -> Vec<u8> {
+        let mut output = Vec::with_capacity(input.len() + input.len() / 255 + 16);
+        varint::write_u64(&mut output, input.len() as u64);
+
+        let mut table = vec![0u32; 1 << HASH_BITS];
+        let match_limit = input.len().saturating_sub(MATCH_FIND_LIMIT);
+        let match_end = input.len().saturating_sub(LAST_LITERALS);
+        let (mut matches, mut match_bytes) = (0, 0);
+        let mut anchor = 0;
+        let mut i = 0;
+        let mut misses = 1u32 << SKIP_TRIGGER;
+        while i < match_limit {
+            let sequence = read_u32(input, i);
+            let slot = &mut table[hash(sequence)];
+            let candidate = *slot as usize;
+            *slot = u32::try_from(i).unwrap_or(u32::MAX);
+
+            if candidate >= i
+                || i - candidate > MAX_OFFSET
+                || read_u32(input, candidate) != sequence
+            {
+                i += (misses >> SKIP_TRIGGER) as usize;
+                misses += 1;
+                continue;
+            }
+
+            let extra = input[i + MIN_MATCH..match_end]
+                .iter()
+                .zip(&input[candidate + MIN_MATCH..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            let match_len = MIN_MATCH + extra;
+            write_sequence(&mut output, &input[anchor..i], i - candidate, match_len);
+            matches += 1;
+            match_bytes += match_len;
+            i += match_len;
+            anchor = i;
+            misses = 1 << SKIP_TRIGGER;
+        }
+        write_last_literals(&mut output, &input[anchor..]);
+
+        if let Some(stats) = stats {
+            stats.tokens = Some((input.len() - match_bytes) as u64 + matches);
+            stats.matches = Some(matches);
+        }
+        output
+    }
+}
+
+impl Compressor for Lz4 {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Ok(Self::encode(input, None))
+    }
+
+    /// Also counts tokens: each literal byte is one, and so is each
+    /// match.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let mut stats = CompressionStats::new(input.len() as u64, 0, Duration::ZERO);
+        let output = Self::encode(input, Some(&mut stats));
+        stats.output_len = output.len() as u64;
+        stats.elapsed = start.elapsed();
+        Ok((output, stats))
+    }
+
+    /// LZ4's own bound: incompressible input is one literal run, whose
+    /// length takes a byte per 255.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let header = varint::len_u64(input_len as u64) + 16;
+        (input_len + input_len / 255).saturating_add(header)
+    }
+
+    fn name(&self) -> &'static str {
+        "LZ4"
+    }
+}
+
+impl Decompressor for Lz4 {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let mut pos = 0;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+        let output = output
+            .get_mut(..original_len)
+            .ok_or(CompressionError::BufferTooSmall)?;
+        if decode_sequences(input, pos, output)? != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(original_len)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "LZ4"
+    }
+}
+
+/// Decodes the sequences starting at `input[pos..]` until they fill
+/// `output`, returning the position just past them.
+fn decode_sequences(input: &[u8], mut pos: usize, output: &mut [u8]) -> Result<usize> {
+    let mut written = 0;
+    loop {
+        let &token = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+        pos += 1;
+
+        let literal_len = read_length(input, &mut pos, token >> 4)?;
+        let literals = input
+            .get(pos..pos.saturating_add(literal_len))
+            .ok_or(CompressionError::CorruptedData)?;
+        output
+            .get_mut(written..written + literal_len)
+            .ok_or(CompressionError::CorruptedData)?
+            .copy_from_slice(literals);
+        written += literal_len;
+        pos += literal_len;
+        if written == output.len() {
+            return Ok(pos);
+        }
+
+        let offset = input
+            .get(pos..pos + 2)
+            .map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .ok_or(CompressionError::CorruptedData)?;
+        pos += 2;
+        let match_len = read_length(input, &mut pos, token & RUN_MASK)?
+            .checked_add(MIN_MATCH)
+            .ok_or(CompressionError::CorruptedData)?;
+        if offset == 0 || offset > written || match_len > output.len() - written {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let start = written - offset;
+        if offset >= match_len {
+            output.copy_within(start..start + match_len, written);
+        } else {
+            // Overlapping match: each byte may be one this match wrote.
+            for i in 0..match_len {
+                output[written + i] = output[start + i];
+            }
+        }
+        written += match_len;
+    }
+}
+
+impl PartialDecompressor for Lz4 {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        // Every input byte decodes to at most 255 output bytes, so a longer
+        // claimed length is corrupt and must not size the allocation.
+        if original_len > input.len().saturating_mul(255) {
+            return Err(CompressionError::CorruptedData);
+        }
+        let mut output = vec![0; original_len];
+        let consumed = decode_sequences(input, pos, &mut output)?;
+        Ok((output, consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lz77;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = Lz4::new().compress(input).unwrap();
+        assert_eq!(Lz4::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn text(len: usize) -> Vec<u8> {
+        b"the quick brown fox jumps over the lazy dog; "
+            .iter()
+            .cycle()
+            .zip(0u32..)
+            .map(|(&byte, i)| if i % 61 == 0 { b'#' } else { byte })
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn test_lz4_name() {
+        assert_eq!(Compressor::name(&Lz4::new()), "LZ4");
+        assert_eq!(Decompressor::name(&Lz4::new()), "LZ4");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [0, 0x00]);
+        assert!(matches!(
+            Lz4::new().decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output() {
+        // One literal, then an overlapping match of 14 at offset 1, then the
+        // five mandatory trailing literals.
+        assert_eq!(
+            roundtrip(&[b'a'; 20]),
+            [20, 0x1A, b'a', 1, 0, 0x50, b'a', b'a', b'a', b'a', b'a']
+        );
+    }
+
+    #[test]
+    fn test_short_inputs_are_literals() {
+        for len in 1..=MATCH_FIND_LIMIT {
+            let compressed = roundtrip(&vec![b'z'; len]);
+            assert_eq!(compressed.len(), 2 + len);
+        }
+    }
+
+    #[test]
+    fn test_long_lengths() {
+        let mut input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        input.extend_from_slice(&[9; 5000]);
+        input.extend_from_slice(&(0..=255).rev().cycle().take(700).collect::<Vec<u8>>());
+        roundtrip(&input);
+    }
+
+    #[test]
+    fn test_roundtrip_text_and_noise() {
+        let compressed = roundtrip(&text(64 * 1024));
+        assert!(compressed.len() < 64 * 1024 / 4, "{}", compressed.len());
+        assert!(compressed.len() < Lz77::new().compress(&text(64 * 1024)).unwrap().len());
+
+        let noise: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13).to_le_bytes()[0])
+            .collect();
+        let compressed = roundtrip(&noise);
+        assert!(compressed.len() < noise.len() + noise.len() / 200 + 16);
+    }
+
+    #[test]
+    fn test_compress_with_stats() {
+        let input = b"abcdefgh abcdefgh abcdefgh, then something else entirely".repeat(8);
+        let (output, stats) = Lz4::new().compress_with_stats(&input).unwrap();
+        assert_eq!(output, Lz4::new().compress(&input).unwrap());
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (input.len() as u64, output.len() as u64)
+        );
+        let (tokens, matches) = (stats.tokens.unwrap(), stats.matches.unwrap());
+        assert!(matches > 0 && tokens > matches && tokens < input.len() as u64);
+        assert_eq!(stats.symbols, None);
+        let (_, stats) = Lz4::new().compress_with_stats(b"abc").unwrap();
+        assert_eq!((stats.tokens, stats.matches), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn test_matches_beyond_offset_limit_are_not_used() {
+        let block: Vec<u8> = (0..64u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 20).to_le_bytes()[0])
+            .collect();
+        let mut input = block.clone();
+        input.extend((0..MAX_OFFSET).map(|i| (i % 251).to_le_bytes()[0]));
+        input.extend_from_slice(&block);
+        roundtrip(&input);
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Lz4::new();
+        let first = codec.compress(&text(500)).unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [text(500), Vec::new(), text(500)]);
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Lz4::new();
+        // Offset of zero and offset before the start of the output.
+        assert!(codec.decompress(&[20, 0x1A, b'a', 0, 0, 0x50]).is_err());
+        assert!(codec.decompress(&[20, 0x1A, b'a', 2, 0, 0x50]).is_err());
+        // Match running past the original length.
+        assert!(codec.decompress(&[10, 0x1A, b'a', 1, 0, 0x50]).is_err());
+        // Literals past the end of the input.
+        assert!(codec.decompress(&[3, 0x30, b'a']).is_err());
+        // Extended length without its terminating byte.
+        assert!(codec.decompress(&[20, 0xF0, 255]).is_err());
+        // A length no frame of this size could decode to.
+        assert!(
+            codec
+                .decompress(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        let codec = Lz4::new();
+        let text = b"abcabcabcabc, overlapping and distant abcabcabcabc matches".repeat(4);
+        let compressed = codec.compress(&text).unwrap();
+        let mut output = vec![0; text.len() + 10];
+        let len = codec.decompress_into(&compressed, &mut output).unwrap();
+        assert_eq!(output[..len], text);
+        assert!(matches!(
+            codec.decompress_into(&compressed, &mut output[..text.len() - 1]),
+            Err(CompressionError::BufferTooSmall)
+        ));
+        let mut trailing = compressed;
+        trailing.push(0);
+        assert!(matches!(
+            codec.decompress_into(&trailing, &mut output),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+}
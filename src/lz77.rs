@@ -1,9 +1,86 @@
+use std::time::{Duration, Instant};
+
+use crate::bitstream::{BitReader, BitWriter};
+use crate::dispatch;
 use crate::error::{CompressionError, Result};
-use crate::traits::{Compressor, Decompressor};
+use crate::level::CompressionLevel;
+use crate::sink::{Sink, SliceSink};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
 
 const DEFAULT_WINDOW_SIZE: usize = 4096;
 const DEFAULT_LOOKAHEAD_SIZE: usize = 18;
-const MIN_MATCH_LENGTH: usize = 3;
+pub const MIN_MATCH_LENGTH: usize = 3;
+/// Bytes of the `[original length: u32]` header of an untagged frame.
+pub const HEADER_LEN: usize = 4;
+/// Bytes per `[offset: u16][length: u8][next: u8]` token.
+pub const TOKEN_LEN: usize = 4;
+/// Length field of a tagged frame, `[marker: u32][tag: u8][original
+/// length: varint]`. Any other value is the length of an untagged frame of
+/// fixed tokens, the original format, which still decodes. An untagged
+/// frame of exactly this length starts with a literal token, whose first
+/// byte is 0, and no tag is 0, so the two cannot be confused.
+pub const TAGGED_MARKER: u32 = u32::MAX;
+/// Tag of fixed tokens too long for an untagged frame.
+pub const TAG_FIXED: u8 = 1;
+/// Tag of a frame whose tokens carry varint offsets.
+pub const TAG_VARINT: u8 = 2;
+/// Tag of a frame of bit-packed tokens.
+pub const TAG_BIT_PACKED: u8 = 3;
+/// Tag no LZ77 frame uses, left to codecs that start their frames with an
+/// LZ77 header to mark input they store raw.
+pub const TAG_STORED: u8 = 0xFF;
+/// Bytes of the `[offset bits: u8][length bits: u8]` widths that follow the
+/// header of a bit-packed frame.
+pub const PACKED_WIDTHS_LEN: usize = 2;
+const REALTIME_WINDOW_SIZE: usize = 64;
+const REALTIME_LOOKAHEAD_SIZE: usize = 16;
+const NOMINAL_NS_PER_COMPARISON: u64 = 1;
+const UNLIMITED_CHAIN_LENGTH: usize = usize::MAX;
+/// Window and lookahead sizes for each [`CompressionLevel`], fastest
+/// first; level 6 is [`Lz77::new`]. The match search scans the whole
+/// window, so the window sets the speed.
+const LEVELS: [(usize, usize); 9] = [
+    (REALTIME_WINDOW_SIZE, REALTIME_LOOKAHEAD_SIZE),
+    (256, 18),
+    (512, 18),
+    (1024, 18),
+    (2048, 18),
+    (DEFAULT_WINDOW_SIZE, DEFAULT_LOOKAHEAD_SIZE),
+    (8192, 34),
+    (16384, 34),
+    (32768, 66),
+];
+
+/// Bytes of the header at the start of the frame `input`, untagged or
+/// tagged, for codecs that carry an [`Lz77`] header over their own body.
+pub fn frame_header_len(input: &[u8]) -> Result<usize> {
+    Lz77::read_header(input).map(|(_, _, header_len)| header_len)
+}
+
+/// Token encoding written by [`Lz77`]. Every format marks itself in the
+/// header, so any [`Lz77`] decodes all three. Frames hold inputs of any
+/// length.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Lz77Tokens {
+    /// `TOKEN_LEN`-byte `[offset: u16][length: u8][next: u8]` tokens
+    /// under an untagged `[original length: u32]` header, the original
+    /// format, or a `TAG_FIXED` header from 4 GiB up. Readers can slice
+    /// tokens at a fixed stride.
+    Fixed,
+    /// `[offset: varint][length: u8][next: u8]` tokens, or `[0][next: u8]`
+    /// for a literal, under a `TAG_VARINT` header.
+    Varint,
+    /// A `TAG_BIT_PACKED` header and the two widths, then a bitstream of
+    /// `[0: 1 bit][byte: 8 bits]` literals and `[1: 1 bit][offset - 1]
+    /// [length - MIN_MATCH_LENGTH][next: 8 bits]` matches, zero-padded to
+    /// a whole byte. The offset and length fields are as narrow as the
+    /// window and lookahead allow: 12 and 4 bits by default, so a literal
+    /// costs 9 bits and a match 25.
+    #[default]
+    BitPacked,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Token {
@@ -29,13 +106,13 @@ impl Token {
         }
     }
 
-    const fn to_bytes(self) -> [u8; 4] {
+    const fn to_bytes(self) -> [u8; TOKEN_LEN] {
         let offset_bytes = self.offset.to_le_bytes();
         [offset_bytes[0], offset_bytes[1], self.length, self.next]
     }
 
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() < 4 {
+        if bytes.len() < TOKEN_LEN {
             return None;
         }
         Some(Self {
@@ -44,12 +121,70 @@ impl Token {
             next: bytes[3],
         })
     }
+
+    /// Appends the token as `[offset: varint][length: u8][next: u8]`, or as
+    /// just `[0][next: u8]` for a literal.
+    fn write_varint(self, output: &mut Vec<u8>) {
+        varint::write_u64(output, u64::from(self.offset));
+        if self.offset != 0 {
+            output.push(self.length);
+        }
+        output.push(self.next);
+    }
+
+    /// Reads a token written by [`Token::write_varint`] at `pos`.
+    fn read_varint(input: &[u8], pos: &mut usize) -> Option<Self> {
+        let offset = u16::try_from(varint::read_u64(input, pos).ok()?).ok()?;
+        let length = if offset == 0 {
+            0
+        } else {
+            let &length = input.get(*pos)?;
+            *pos += 1;
+            // A match token always copies something.
+            if length == 0 {
+                return None;
+            }
+            length
+        };
+        let &next = input.get(*pos)?;
+        *pos += 1;
+        Some(Self::new_match(offset, length, next))
+    }
+
+    /// Writes the token in the [`Lz77Tokens::BitPacked`] layout.
+    fn write_packed(self, writer: &mut BitWriter, offset_bits: u32, length_bits: u32) {
+        writer.write_bit(self.length != 0);
+        if self.length != 0 {
+            writer.write_bits(u64::from(self.offset - 1), offset_bits);
+            let extra = usize::from(self.length) - MIN_MATCH_LENGTH;
+            writer.write_bits(extra as u64, length_bits);
+        }
+        writer.write_bits(u64::from(self.next), 8);
+    }
+
+    /// Reads a token written by [`Token::write_packed`].
+    fn read_packed(reader: &mut BitReader, offset_bits: u32, length_bits: u32) -> Option<Self> {
+        if !reader.read_bit()? {
+            return Some(Self::new_literal(reader.read_u8(8)?));
+        }
+        let offset = u16::try_from(reader.read_bits(offset_bits)? + 1).ok()?;
+        let length = reader.read_bits(length_bits)? + MIN_MATCH_LENGTH as u64;
+        let length = u8::try_from(length).ok()?;
+        Some(Self::new_match(offset, length, reader.read_u8(8)?))
+    }
+}
+
+/// Bits needed to write every value up to `max`.
+const fn bit_width(max: usize) -> u32 {
+    usize::BITS - max.leading_zeros()
 }
 
 #[derive(Debug, Clone)]
 pub struct Lz77 {
     window_size: usize,
     lookahead_size: usize,
+    max_chain_length: usize,
+    tokens: Lz77Tokens,
 }
 
 impl Default for Lz77 {
@@ -61,10 +196,7 @@ impl Default for Lz77 {
 impl Lz77 {
     #[must_use]
     pub const fn new() -> Self {
-        Self {
-            window_size: DEFAULT_WINDOW_SIZE,
-            lookahead_size: DEFAULT_LOOKAHEAD_SIZE,
-        }
+        Self::with_config(DEFAULT_WINDOW_SIZE, DEFAULT_LOOKAHEAD_SIZE)
     }
 
     #[must_use]
@@ -72,9 +204,70 @@ impl Lz77 {
         Self {
             window_size,
             lookahead_size,
+            max_chain_length: UNLIMITED_CHAIN_LENGTH,
+            tokens: Lz77Tokens::BitPacked,
         }
     }
 
+    /// Writes tokens in the given format instead of the default
+    /// [`Lz77Tokens::BitPacked`]. [`Lz77Tokens::Fixed`] suits readers that
+    /// predate the tagged formats or slice tokens at a fixed stride;
+    /// decoding accepts every format either way.
+    #[must_use]
+    pub const fn with_tokens(mut self, tokens: Lz77Tokens) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Limits the match search to the `max_chain_length` nearest candidate
+    /// positions per input byte, independently of the window size.
+    ///
+    /// Lower budgets trade ratio for speed; `0` disables matching entirely.
+    /// The default examines every position in the window.
+    #[must_use]
+    pub const fn with_max_chain_length(mut self, max_chain_length: usize) -> Self {
+        self.max_chain_length = max_chain_length;
+        self
+    }
+
+    /// Creates a latency-bounded configuration for real-time use.
+    ///
+    /// The small search window caps the work done per input byte, so the
+    /// cost of a `compress` call grows strictly linearly with input size.
+    #[must_use]
+    pub const fn realtime() -> Self {
+        Self::with_config(REALTIME_WINDOW_SIZE, REALTIME_LOOKAHEAD_SIZE)
+    }
+
+    /// Starts a [`Lz77Builder`] from the default configuration, for
+    /// settings checked before any data is compressed.
+    #[must_use]
+    pub const fn builder() -> Lz77Builder {
+        Lz77Builder::new()
+    }
+
+    /// Upper bound on byte comparisons performed per KiB of input, saturating
+    /// at `u64::MAX` for configurations too large to count.
+    #[must_use]
+    pub const fn worst_case_comparisons_per_kb(&self) -> u64 {
+        let candidates = if self.window_size < self.max_chain_length {
+            self.window_size
+        } else {
+            self.max_chain_length
+        };
+        (candidates as u64)
+            .saturating_mul(self.lookahead_size as u64)
+            .saturating_mul(1024)
+    }
+
+    /// Worst-case compression time per KiB of input, assuming a nominal cost
+    /// of one nanosecond per byte comparison.
+    #[must_use]
+    pub const fn worst_case_ns_per_kb(&self) -> u64 {
+        self.worst_case_comparisons_per_kb()
+            .saturating_mul(NOMINAL_NS_PER_COMPARISON)
+    }
+
     #[must_use]
     pub const fn window_size(&self) -> usize {
         self.window_size
@@ -85,45 +278,105 @@ impl Lz77 {
         self.lookahead_size
     }
 
+    #[must_use]
+    pub const fn max_chain_length(&self) -> usize {
+        self.max_chain_length
+    }
+
+    #[must_use]
+    pub const fn tokens(&self) -> Lz77Tokens {
+        self.tokens
+    }
+
+    /// Furthest offset and longest length a match can have.
+    fn max_match(&self) -> (usize, usize) {
+        (
+            self.window_size.min(usize::from(u16::MAX)),
+            self.lookahead_size.min(usize::from(u8::MAX)),
+        )
+    }
+
+    /// Widths of the offset and length fields of bit-packed tokens, just
+    /// wide enough for the longest match the configuration can find.
+    fn packed_widths(&self) -> (u32, u32) {
+        let (max_offset, max_length) = self.max_match();
+        (
+            bit_width(max_offset.saturating_sub(1)),
+            bit_width(max_length.saturating_sub(MIN_MATCH_LENGTH)),
+        )
+    }
+
     fn find_longest_match(&self, data: &[u8], position: usize) -> (usize, usize) {
-        let search_start = position.saturating_sub(self.window_size);
-        let lookahead_end = (position + self.lookahead_size).min(data.len());
+        // Offsets and lengths must fit their `u16` and `u8` token fields.
+        let (max_offset, max_length) = self.max_match();
+        let search_start = position.saturating_sub(max_offset);
+        let lookahead_end = (position + max_length).min(data.len());
 
         let mut best_offset = 0;
         let mut best_length = 0;
-
-        for start in search_start..position {
-            let mut length = 0;
-            while position + length < lookahead_end
-                && data[start + length] == data[position + length]
-                && length < self.lookahead_size
-            {
-                length += 1;
-            }
+        // Read once per search, so `dispatch::force_scalar` takes effect
+        // from the next one.
+        let backend = dispatch::backend();
+
+        // Nearest candidates first, so a limited budget keeps the most
+        // likely matches.
+        for start in (search_start..position).rev().take(self.max_chain_length) {
+            let length = dispatch::common_prefix_len_with(
+                backend,
+                &data[start..],
+                &data[position..lookahead_end],
+            );
 
             if length >= MIN_MATCH_LENGTH && length > best_length {
                 best_offset = position - start;
                 best_length = length;
+                if position + length == lookahead_end {
+                    break;
+                }
             }
         }
 
         (best_offset, best_length)
     }
-}
 
-impl Compressor for Lz77 {
-    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if input.is_empty() {
-            return Ok(Vec::new());
+    /// Encodes `input[start..]`, with matches free to reach back into
+    /// `input[..start]`, and counts the tokens into `stats` if given.
+    fn encode(
+        &self,
+        input: &[u8],
+        start: usize,
+        stats: Option<&mut CompressionStats>,
+    ) -> Vec<u8> {
+        let original_len = input.len() - start;
+        let mut output = Vec::with_capacity(self.header_len(original_len) + original_len);
+        match (self.tokens, u32::try_from(original_len)) {
+            (Lz77Tokens::Fixed, Ok(len)) if len != TAGGED_MARKER => {
+                output.extend_from_slice(&len.to_le_bytes());
+            }
+            (tokens, _) => {
+                let tag = match tokens {
+                    Lz77Tokens::Fixed => TAG_FIXED,
+                    Lz77Tokens::Varint => TAG_VARINT,
+                    Lz77Tokens::BitPacked => TAG_BIT_PACKED,
+                };
+                output.extend_from_slice(&TAGGED_MARKER.to_le_bytes());
+                output.push(tag);
+                varint::write_u64(&mut output, original_len as u64);
+            }
         }
-
-        let mut tokens = Vec::new();
-        let mut position = 0;
+        let (offset_bits, length_bits) = self.packed_widths();
+        let mut bits = BitWriter::new();
+        if self.tokens == Lz77Tokens::BitPacked {
+            // Both widths are at most 16.
+            output.extend([offset_bits.to_le_bytes()[0], length_bits.to_le_bytes()[0]]);
+        }
+        let mut position = start;
+        let (mut tokens, mut matches) = (0, 0);
 
         while position < input.len() {
             let (offset, length) = self.find_longest_match(input, position);
 
-            if length >= MIN_MATCH_LENGTH {
+            let token = if length >= MIN_MATCH_LENGTH {
                 let next_pos = position + length;
                 let next_byte = if next_pos < input.len() {
                     input[next_pos]
@@ -131,98 +384,335 @@ impl Compressor for Lz77 {
                     0
                 };
 
-                let token = Token::new_match(
-                    u16::try_from(offset).unwrap_or(u16::MAX),
-                    u8::try_from(length).unwrap_or(u8::MAX),
-                    next_byte,
-                );
-                tokens.push(token);
-
                 position = if next_pos < input.len() {
                     next_pos + 1
                 } else {
                     next_pos
                 };
+                matches += 1;
+                Token::new_match(
+                    u16::try_from(offset).unwrap_or(u16::MAX),
+                    u8::try_from(length).unwrap_or(u8::MAX),
+                    next_byte,
+                )
             } else {
-                let token = Token::new_literal(input[position]);
-                tokens.push(token);
                 position += 1;
+                Token::new_literal(input[position - 1])
+            };
+
+            tokens += 1;
+            match self.tokens {
+                Lz77Tokens::Fixed => output.extend_from_slice(&token.to_bytes()),
+                Lz77Tokens::Varint => token.write_varint(&mut output),
+                Lz77Tokens::BitPacked => token.write_packed(&mut bits, offset_bits, length_bits),
             }
         }
 
-        let original_len = u32::try_from(input.len()).unwrap_or(u32::MAX);
-        let mut output = Vec::with_capacity(4 + tokens.len() * 4);
-        output.extend_from_slice(&original_len.to_le_bytes());
-        for token in tokens {
-            output.extend_from_slice(&token.to_bytes());
+        output.extend(bits.finish());
+        if let Some(stats) = stats {
+            stats.tokens = Some(tokens);
+            stats.matches = Some(matches);
         }
-
-        Ok(output)
+        output
     }
 
-    fn name(&self) -> &'static str {
-        "LZ77"
+    /// Bytes of the header [`Lz77::compress`] writes for `input_len`
+    /// bytes, not counting the widths of a bit-packed frame.
+    fn header_len(&self, input_len: usize) -> usize {
+        let untagged = u32::try_from(input_len).is_ok_and(|len| len != TAGGED_MARKER);
+        if self.tokens == Lz77Tokens::Fixed && untagged {
+            HEADER_LEN
+        } else {
+            HEADER_LEN + 1 + varint::len_u64(input_len as u64)
+        }
     }
-}
 
-impl Decompressor for Lz77 {
-    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if input.is_empty() {
-            return Ok(Vec::new());
+    /// Splits the header of the frame at the start of `input` into its
+    /// token encoding, the original length and the header's own length.
+    fn read_header(input: &[u8]) -> Result<(Lz77Tokens, u64, usize)> {
+        let Some(&[a, b, c, d]) = input.get(..HEADER_LEN) else {
+            return Err(CompressionError::CorruptedData);
+        };
+        let header = u32::from_le_bytes([a, b, c, d]);
+        if header != TAGGED_MARKER {
+            return Ok((Lz77Tokens::Fixed, u64::from(header), HEADER_LEN));
         }
+        let tokens = match input.get(HEADER_LEN) {
+            Some(0) => return Ok((Lz77Tokens::Fixed, u64::from(header), HEADER_LEN)),
+            Some(&TAG_FIXED) => Lz77Tokens::Fixed,
+            Some(&TAG_VARINT) => Lz77Tokens::Varint,
+            Some(&TAG_BIT_PACKED) => Lz77Tokens::BitPacked,
+            _ => return Err(CompressionError::CorruptedData),
+        };
+        let mut pos = HEADER_LEN + 1;
+        let original_len =
+            varint::read_u64(input, &mut pos).map_err(|_| CompressionError::CorruptedData)?;
+        Ok((tokens, original_len, pos))
+    }
 
-        if input.len() < 4 {
-            return Err(CompressionError::CorruptedData);
+    /// Decodes the frame at the start of `input` on top of `history`,
+    /// returning the new bytes and the input consumed.
+    fn decode(history: &[u8], input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        // No offset reaches further back than `u16::MAX`.
+        let history = &history[history.len().saturating_sub(usize::from(u16::MAX))..];
+        let mut output = history.to_vec();
+        let consumed = Self::decode_into(input, &mut output)?;
+        Ok((output.split_off(history.len()), consumed))
+    }
+
+    /// Decodes the frame at the start of `input` onto the end of `output`,
+    /// whose bytes so far matches may reach back into, returning the
+    /// length of the frame.
+    fn decode_into(input: &[u8], output: &mut impl Sink) -> Result<usize> {
+        let (tokens, original_len, header_len) = Self::read_header(input)?;
+        let original_len =
+            usize::try_from(original_len).map_err(|_| CompressionError::CorruptedData)?;
+        let end = output
+            .len()
+            .checked_add(original_len)
+            .ok_or(CompressionError::CorruptedData)?;
+        output.reserve(original_len.min(input.len().saturating_mul(usize::from(u8::MAX))));
+        let mut consumed = header_len;
+        let (mut bits, offset_bits, length_bits) = if tokens == Lz77Tokens::BitPacked {
+            consumed += PACKED_WIDTHS_LEN;
+            let widths = input.get(header_len..consumed);
+            let Some(&[offset_bits, length_bits]) = widths else {
+                return Err(CompressionError::CorruptedData);
+            };
+            if offset_bits > 16 || length_bits > 8 {
+                return Err(CompressionError::CorruptedData);
+            }
+            let bits = BitReader::new(&input[consumed..]);
+            (bits, u32::from(offset_bits), u32::from(length_bits))
+        } else {
+            (BitReader::new(&[]), 0, 0)
+        };
+
+        // The frame ends with the token that completes the original length.
+        while output.len() < end {
+            let token = match tokens {
+                Lz77Tokens::Fixed => {
+                    let token = input
+                        .get(consumed..consumed + TOKEN_LEN)
+                        .and_then(Token::from_bytes);
+                    consumed += TOKEN_LEN;
+                    token
+                }
+                Lz77Tokens::Varint => Token::read_varint(input, &mut consumed),
+                Lz77Tokens::BitPacked => Token::read_packed(&mut bits, offset_bits, length_bits),
+            }
+            .ok_or(CompressionError::CorruptedData)?;
+
+            if token.length != 0 {
+                let length = usize::from(token.length).min(end - output.len());
+                output.copy_back(usize::from(token.offset), length)?;
+            }
+
+            if output.len() < end {
+                output.push(token.next)?;
+            }
         }
 
-        let original_len = u32::from_le_bytes([input[0], input[1], input[2], input[3]]) as usize;
-        let token_data = &input[4..];
+        consumed += bits.bytes_consumed();
+        Ok(consumed)
+    }
+}
+
+/// Builder for [`Lz77`] that rejects configurations the token formats
+/// cannot express, rather than clamping them at compress time.
+///
+/// Offsets are at most `u16::MAX` and match lengths at most `u8::MAX` in
+/// every token format, and a lookahead shorter than the 3-byte minimum match
+/// could never match, so [`Lz77Builder::build`] refuses windows outside
+/// `1..=u16::MAX` and lookaheads outside `3..=u8::MAX`.
+#[derive(Debug, Clone)]
+pub struct Lz77Builder {
+    window_size: usize,
+    lookahead_size: usize,
+    max_chain_length: usize,
+    tokens: Lz77Tokens,
+}
 
-        if !token_data.len().is_multiple_of(4) {
-            return Err(CompressionError::CorruptedData);
+impl Default for Lz77Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lz77Builder {
+    /// Starts from the configuration of [`Lz77::new`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            window_size: DEFAULT_WINDOW_SIZE,
+            lookahead_size: DEFAULT_LOOKAHEAD_SIZE,
+            max_chain_length: UNLIMITED_CHAIN_LENGTH,
+            tokens: Lz77Tokens::BitPacked,
         }
+    }
 
-        let mut output = Vec::with_capacity(original_len);
+    #[must_use]
+    pub const fn with_window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
 
-        for chunk in token_data.chunks_exact(4) {
-            let token =
-                Token::from_bytes(chunk).ok_or(CompressionError::CorruptedData)?;
+    #[must_use]
+    pub const fn with_lookahead_size(mut self, lookahead_size: usize) -> Self {
+        self.lookahead_size = lookahead_size;
+        self
+    }
 
-            if token.length != 0 {
-                let offset = usize::from(token.offset);
-                let length = usize::from(token.length);
+    /// See [`Lz77::with_max_chain_length`].
+    #[must_use]
+    pub const fn with_max_chain_length(mut self, max_chain_length: usize) -> Self {
+        self.max_chain_length = max_chain_length;
+        self
+    }
 
-                if offset == 0 || offset > output.len() {
-                    return Err(CompressionError::CorruptedData);
-                }
+    /// See [`Lz77::with_tokens`].
+    #[must_use]
+    pub const fn with_tokens(mut self, tokens: Lz77Tokens) -> Self {
+        self.tokens = tokens;
+        self
+    }
 
-                let start = output.len() - offset;
-                for i in 0..length {
-                    if output.len() >= original_len {
-                        break;
-                    }
-                    let byte = output[start + i];
-                    output.push(byte);
-                }
-            }
+    /// Takes the window and lookahead sizes of `level`, as
+    /// [`Compressor::with_level`] does, and lifts any chain limit.
+    #[must_use]
+    pub const fn with_level(mut self, level: CompressionLevel) -> Self {
+        (self.window_size, self.lookahead_size) = level.pick(&LEVELS);
+        self.max_chain_length = UNLIMITED_CHAIN_LENGTH;
+        self
+    }
 
-            if output.len() < original_len {
-                output.push(token.next);
-            }
+    /// Checks the configuration and creates the codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the window size is zero
+    /// or above `u16::MAX`, or the lookahead size is below
+    /// the minimum match length of 3 or above `u8::MAX`.
+    pub fn build(self) -> Result<Lz77> {
+        if !(1..=usize::from(u16::MAX)).contains(&self.window_size) {
+            return Err(CompressionError::InvalidInput(format!(
+                "LZ77 window size {} is outside 1..={}",
+                self.window_size,
+                u16::MAX
+            )));
+        }
+        if !(MIN_MATCH_LENGTH..=usize::from(u8::MAX)).contains(&self.lookahead_size) {
+            return Err(CompressionError::InvalidInput(format!(
+                "LZ77 lookahead size {} is outside {MIN_MATCH_LENGTH}..={}",
+                self.lookahead_size,
+                u8::MAX
+            )));
         }
+        Ok(Lz77 {
+            window_size: self.window_size,
+            lookahead_size: self.lookahead_size,
+            max_chain_length: self.max_chain_length,
+            tokens: self.tokens,
+        })
+    }
+}
+
+impl Compressor for Lz77 {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.encode(input, 0, None))
+    }
+
+    /// Only the last `window_size` bytes of `history` are searched.
+    fn compress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        let history = &history[history.len().saturating_sub(self.window_size)..];
+        let data = [history, input].concat();
+        Ok(self.encode(&data, history.len(), None))
+    }
+
+    /// Counts tokens and matches as it encodes.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let mut stats = CompressionStats::new(input.len() as u64, 0, Duration::ZERO);
+        let output = self.encode(input, 0, Some(&mut stats));
+        stats.output_len = output.len() as u64;
+        stats.elapsed = start.elapsed();
+        Ok((output, stats))
+    }
 
-        if output.len() != original_len {
+    /// Literals cost the most per byte in every token format; the one
+    /// exception is a bit-packed match cut short by the end of the input,
+    /// which may cost a few bits more than its bytes as literals would.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let body = match self.tokens {
+            Lz77Tokens::Fixed => input_len.saturating_mul(TOKEN_LEN),
+            Lz77Tokens::Varint => input_len.saturating_mul(2),
+            Lz77Tokens::BitPacked => {
+                let (offset_bits, length_bits) = self.packed_widths();
+                let match_bits = (1 + offset_bits + length_bits + 8) as usize;
+                let cut_short = match_bits.saturating_sub(9 * MIN_MATCH_LENGTH);
+                let bits = input_len.saturating_mul(9).saturating_add(cut_short);
+                PACKED_WIDTHS_LEN + bits.div_ceil(8)
+            }
+        };
+        body.saturating_add(self.header_len(input_len))
+    }
+
+    /// Sets the window and lookahead and lifts any chain limit; the token
+    /// format is kept.
+    fn with_level(self, level: CompressionLevel) -> Self {
+        let (window_size, lookahead_size) = level.pick(&LEVELS);
+        Self::with_config(window_size, lookahead_size).with_tokens(self.tokens)
+    }
+
+    fn name(&self) -> &'static str {
+        "LZ77"
+    }
+}
+
+impl Decompressor for Lz77 {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
             return Err(CompressionError::CorruptedData);
         }
+        Ok(output)
+    }
 
+    fn decompress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = Self::decode(history, input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
         Ok(output)
     }
 
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let (_, original_len, _) = Self::read_header(input)?;
+        if original_len > output.len() as u64 {
+            return Err(CompressionError::BufferTooSmall);
+        }
+        let mut sink = SliceSink::new(output);
+        if Self::decode_into(input, &mut sink)? != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(sink.len())
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        Self::read_header(input).map(|(_, len, _)| Some(len))
+    }
+
     fn name(&self) -> &'static str {
         "LZ77"
     }
 }
 
+impl PartialDecompressor for Lz77 {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        Self::decode(&[], input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +722,7 @@ mod tests {
         let lz77 = Lz77::new();
         assert_eq!(lz77.window_size(), DEFAULT_WINDOW_SIZE);
         assert_eq!(lz77.lookahead_size(), DEFAULT_LOOKAHEAD_SIZE);
+        assert_eq!(lz77.max_chain_length(), UNLIMITED_CHAIN_LENGTH);
     }
 
     #[test]
@@ -247,25 +738,186 @@ mod tests {
         assert_eq!(lz77.lookahead_size(), 32);
     }
 
+    #[test]
+    fn test_lz77_realtime() {
+        let lz77 = Lz77::realtime();
+        assert_eq!(lz77.window_size(), REALTIME_WINDOW_SIZE);
+        assert_eq!(lz77.lookahead_size(), REALTIME_LOOKAHEAD_SIZE);
+    }
+
+    #[test]
+    fn test_forced_scalar_matches_detected() {
+        let input = b"word after word after word, byte after byte after byte".repeat(20);
+        let lz77 = Lz77::new();
+        let detected = lz77.compress(&input).unwrap();
+        dispatch::force_scalar(true);
+        let scalar = lz77.compress(&input);
+        dispatch::force_scalar(false);
+        assert_eq!(scalar.unwrap(), detected);
+    }
+
+    #[test]
+    fn test_worst_case_bound() {
+        let lz77 = Lz77::with_config(16, 8);
+        assert_eq!(lz77.worst_case_comparisons_per_kb(), 1024 * 16 * 8);
+        assert_eq!(
+            lz77.worst_case_ns_per_kb(),
+            lz77.worst_case_comparisons_per_kb() * NOMINAL_NS_PER_COMPARISON
+        );
+        let huge = Lz77::with_config(usize::MAX, usize::MAX);
+        assert_eq!(huge.worst_case_comparisons_per_kb(), u64::MAX);
+        assert_eq!(huge.worst_case_ns_per_kb(), u64::MAX);
+    }
+
+    #[test]
+    fn test_max_chain_length_bounds_work() {
+        let lz77 = Lz77::with_config(4096, 8).with_max_chain_length(32);
+        assert_eq!(lz77.max_chain_length(), 32);
+        assert_eq!(lz77.window_size(), 4096);
+        assert_eq!(lz77.worst_case_comparisons_per_kb(), 1024 * 32 * 8);
+        let wide = Lz77::with_config(16, 8).with_max_chain_length(1000);
+        assert_eq!(wide.worst_case_comparisons_per_kb(), 1024 * 16 * 8);
+    }
+
+    #[test]
+    fn test_max_chain_length_zero_emits_literals() {
+        let input = b"abcabcabcabc";
+        let lz77 = Lz77::new().with_max_chain_length(0);
+        let compressed = lz77.compress(input).unwrap();
+        let literal_bits = input.len() * 9;
+        assert_eq!(
+            compressed.len(),
+            lz77.header_len(input.len()) + PACKED_WIDTHS_LEN + literal_bits.div_ceil(8)
+        );
+        assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_max_chain_length_trades_ratio() {
+        let input: Vec<u8> = (0..3000u32)
+            .map(|i| (i * i / 7).to_le_bytes()[0] % 16)
+            .collect();
+        let mut previous = usize::MAX;
+        for budget in [1, 4, 64, UNLIMITED_CHAIN_LENGTH] {
+            let lz77 = Lz77::new().with_max_chain_length(budget);
+            let compressed = lz77.compress(&input).unwrap();
+            assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+            assert!(compressed.len() <= previous);
+            previous = compressed.len();
+        }
+    }
+
+    #[test]
+    fn test_realtime_bound_below_default() {
+        assert!(Lz77::realtime().worst_case_ns_per_kb() < Lz77::new().worst_case_ns_per_kb());
+    }
+
+    #[test]
+    fn test_with_level() {
+        let default = Lz77::new()
+            .with_max_chain_length(8)
+            .with_level(CompressionLevel::DEFAULT);
+        assert_eq!(default.window_size(), DEFAULT_WINDOW_SIZE);
+        assert_eq!(default.max_chain_length(), UNLIMITED_CHAIN_LENGTH);
+        let fastest = Lz77::new().with_level(CompressionLevel::FASTEST);
+        assert_eq!(fastest.window_size(), Lz77::realtime().window_size());
+        let best = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .with_level(CompressionLevel::BEST);
+        assert_eq!(best.tokens(), Lz77Tokens::Fixed);
+
+        // Repeats 80 bytes apart, beyond the fastest level's window.
+        let phrase =
+            b"level one searches a short window, so it cannot see back to the line before. ";
+        let input = phrase.repeat(30);
+        let sizes: Vec<usize> = [
+            CompressionLevel::FASTEST,
+            CompressionLevel::DEFAULT,
+            CompressionLevel::BEST,
+        ]
+        .into_iter()
+        .map(|level| {
+            let compressed = Lz77::new().with_level(level).compress(&input).unwrap();
+            assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+            compressed.len()
+        })
+        .collect();
+        assert!(sizes[0] > sizes[1] && sizes[1] > sizes[2], "{sizes:?}");
+    }
+
+    #[test]
+    fn test_builder_validates() {
+        let lz77 = Lz77::builder()
+            .with_window_size(usize::from(u16::MAX))
+            .with_lookahead_size(usize::from(u8::MAX))
+            .with_tokens(Lz77Tokens::Varint)
+            .build()
+            .unwrap();
+        assert_eq!(lz77.window_size(), usize::from(u16::MAX));
+        assert_eq!(lz77.tokens(), Lz77Tokens::Varint);
+        let input = b"builders check the window before any offset is written. ".repeat(20);
+        let compressed = lz77.compress(&input).unwrap();
+        assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+
+        let best = Lz77::builder()
+            .with_level(CompressionLevel::BEST)
+            .build()
+            .unwrap();
+        assert_eq!(best.window_size(), LEVELS[8].0);
+        let default = Lz77::builder().build().unwrap();
+        assert_eq!(default.window_size(), Lz77::new().window_size());
+        assert_eq!(default.lookahead_size(), Lz77::new().lookahead_size());
+
+        for builder in [
+            Lz77::builder().with_window_size(0),
+            Lz77::builder().with_window_size(usize::from(u16::MAX) + 1),
+            Lz77::builder().with_lookahead_size(0),
+            Lz77::builder().with_lookahead_size(MIN_MATCH_LENGTH - 1),
+            Lz77::builder().with_lookahead_size(usize::from(u8::MAX) + 1),
+        ] {
+            assert!(matches!(
+                builder.build(),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_realtime_roundtrip_with_default_decoder() {
+        let input = b"the quick brown fox jumps over the lazy dog. the quick brown fox";
+        let compressed = Lz77::realtime().compress(input).unwrap();
+        let decompressed = Lz77::new().decompress(&compressed).unwrap();
+        assert_eq!(decompressed, input.as_slice());
+    }
+
     #[test]
     fn test_compress_empty() {
         let lz77 = Lz77::new();
         let result = lz77.compress(&[]).unwrap();
-        assert!(result.is_empty());
+        assert_eq!(result, [0xFF, 0xFF, 0xFF, 0xFF, TAG_BIT_PACKED, 0, 12, 4]);
+        let varint = Lz77::new().with_tokens(Lz77Tokens::Varint).compress(&[]).unwrap();
+        assert_eq!(varint, [0xFF, 0xFF, 0xFF, 0xFF, TAG_VARINT, 0]);
+        let fixed = lz77.with_tokens(Lz77Tokens::Fixed).compress(&[]).unwrap();
+        assert_eq!(fixed, [0; HEADER_LEN]);
     }
 
     #[test]
     fn test_decompress_empty() {
         let lz77 = Lz77::new();
-        let result = lz77.decompress(&[]).unwrap();
+        let result = lz77.decompress(&[0; HEADER_LEN]).unwrap();
         assert!(result.is_empty());
+        assert!(matches!(
+            lz77.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
     }
 
     #[test]
     fn test_compress_single_byte() {
         let lz77 = Lz77::new();
         let result = lz77.compress(&[0x42]).unwrap();
-        assert_eq!(result.len(), 8); // 4 bytes header + 4 bytes token
+        // 6 bytes header, 2 widths and a 9-bit literal.
+        assert_eq!(result.len(), 10);
     }
 
     #[test]
@@ -340,6 +992,135 @@ mod tests {
         assert!(matches!(result, Err(CompressionError::CorruptedData)));
     }
 
+    #[test]
+    fn test_older_token_formats_still_decode() {
+        let input = b"to be or not to be, that is the question; to be or not";
+        let encode = |tokens| Lz77::new().with_tokens(tokens).compress(input).unwrap();
+        let fixed = encode(Lz77Tokens::Fixed);
+        assert_eq!(fixed[..HEADER_LEN], 54u32.to_le_bytes());
+        assert_eq!((fixed.len() - HEADER_LEN) % TOKEN_LEN, 0);
+        let varint = encode(Lz77Tokens::Varint);
+        assert_eq!(varint[..=HEADER_LEN], [0xFF, 0xFF, 0xFF, 0xFF, TAG_VARINT]);
+        let packed = encode(Lz77Tokens::BitPacked);
+        assert_eq!(packed[..=HEADER_LEN], [0xFF, 0xFF, 0xFF, 0xFF, TAG_BIT_PACKED]);
+        assert!(packed.len() < varint.len() && varint.len() < fixed.len());
+        for frame in [fixed, varint, packed] {
+            assert_eq!(Lz77::new().decompress(&frame).unwrap(), input);
+            let decoder = Lz77::realtime().with_tokens(Lz77Tokens::Fixed);
+            assert_eq!(decoder.decompress(&frame).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_packed_widths_follow_config() {
+        assert_eq!(Lz77::new().packed_widths(), (12, 4));
+        assert_eq!(Lz77::realtime().packed_widths(), (6, 4));
+        assert_eq!(Lz77::with_config(1 << 20, 1000).packed_widths(), (16, 8));
+        assert_eq!(Lz77::with_config(0, 2).packed_widths(), (0, 0));
+        for lz77 in [Lz77::with_config(1 << 20, 1000), Lz77::with_config(1, 3)] {
+            let input = b"zzzzzzzzzzzzzzzzzzzzzzzz-zzzzzzzzzzzz".repeat(20);
+            let compressed = lz77.compress(&input).unwrap();
+            assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_max_compressed_len_holds() {
+        let literals: Vec<u8> = (0..=255).collect();
+        let ends_in_match = b"abcdefgh-abc".to_vec();
+        for tokens in [Lz77Tokens::Fixed, Lz77Tokens::Varint, Lz77Tokens::BitPacked] {
+            for lz77 in [Lz77::new(), Lz77::with_config(1 << 16, 255)] {
+                let lz77 = lz77.with_tokens(tokens);
+                for input in [&[][..], &literals, &ends_in_match] {
+                    let compressed = lz77.compress(input).unwrap();
+                    assert!(compressed.len() <= lz77.max_compressed_len(input.len()));
+                }
+            }
+        }
+        let fixed = Lz77::new().with_tokens(Lz77Tokens::Fixed);
+        assert_eq!(fixed.max_compressed_len(10), HEADER_LEN + 40);
+    }
+
+    #[test]
+    fn test_decompressed_len_reads_header() {
+        let input = b"abcabcabcabcabc-xyz";
+        for tokens in [Lz77Tokens::Fixed, Lz77Tokens::Varint, Lz77Tokens::BitPacked] {
+            let lz77 = Lz77::new().with_tokens(tokens);
+            let compressed = lz77.compress(input).unwrap();
+            assert_eq!(lz77.decompressed_len(&compressed).unwrap(), Some(19));
+            // The header alone is enough.
+            let header = &compressed[..lz77.header_len(input.len())];
+            assert_eq!(lz77.decompressed_len(header).unwrap(), Some(19));
+        }
+        assert!(Lz77::new().decompressed_len(&[0; 3]).is_err());
+        assert!(Lz77::new().decompressed_len(&[0xFF; 4]).is_err());
+        assert!(Lz77::new().decompressed_len(&[0xFF, 0xFF, 0xFF, 0xFF, 9]).is_err());
+    }
+
+    #[test]
+    fn test_untagged_lengths_from_1_gib_are_fixed() {
+        // The original format spent the whole u32 on the length, so these
+        // headers are lengths, not token formats.
+        for len in [1 << 30, 1 << 31, (1 << 31) | (1 << 30), u32::MAX - 1] {
+            let header = len.to_le_bytes();
+            let (tokens, original_len, header_len) = Lz77::read_header(&header).unwrap();
+            assert_eq!(tokens, Lz77Tokens::Fixed);
+            assert_eq!(original_len, u64::from(len));
+            assert_eq!(header_len, HEADER_LEN);
+        }
+        // A frame of exactly `u32::MAX` bytes starts with a literal token.
+        let header = [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, b'a'];
+        let (tokens, original_len, _) = Lz77::read_header(&header).unwrap();
+        assert_eq!((tokens, original_len), (Lz77Tokens::Fixed, u64::from(u32::MAX)));
+
+        // Nothing caps the length a frame can hold.
+        let huge = 1 << 40;
+        for tokens in [Lz77Tokens::Fixed, Lz77Tokens::Varint, Lz77Tokens::BitPacked] {
+            let lz77 = Lz77::new().with_tokens(tokens);
+            assert_eq!(lz77.header_len(huge), HEADER_LEN + 1 + 6);
+            let mut header = TAGGED_MARKER.to_le_bytes().to_vec();
+            header.push(match tokens {
+                Lz77Tokens::Fixed => TAG_FIXED,
+                Lz77Tokens::Varint => TAG_VARINT,
+                Lz77Tokens::BitPacked => TAG_BIT_PACKED,
+            });
+            varint::write_u64(&mut header, huge as u64);
+            assert_eq!(lz77.decompressed_len(&header).unwrap(), Some(huge as u64));
+        }
+    }
+
+    #[test]
+    fn test_packed_decompress_corrupted() {
+        let lz77 = Lz77::new();
+        let mut compressed = lz77.compress(b"abcabcabcabcabc").unwrap();
+        // Missing widths, oversized widths and truncated bits.
+        assert!(lz77.decompress(&compressed[..HEADER_LEN]).is_err());
+        assert!(lz77.decompress(&compressed[..compressed.len() - 1]).is_err());
+        compressed[HEADER_LEN] = 17;
+        assert!(lz77.decompress(&compressed).is_err());
+        compressed[HEADER_LEN] = 12;
+        compressed[HEADER_LEN + 1] = 9;
+        assert!(lz77.decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_varint_offsets_shrink_with_distance() {
+        let mut output = Vec::new();
+        Token::new_match(100, 5, b'x').write_varint(&mut output);
+        assert_eq!(output, [100, 5, b'x']);
+        output.clear();
+        Token::new_match(4000, 5, b'x').write_varint(&mut output);
+        assert_eq!(output.len(), 4);
+        let mut pos = 0;
+        assert_eq!(
+            Token::read_varint(&output, &mut pos),
+            Some(Token::new_match(4000, 5, b'x'))
+        );
+        assert_eq!(pos, output.len());
+        // A match that copies nothing is never written.
+        assert_eq!(Token::read_varint(&[7, 0, b'x'], &mut 0), None);
+    }
+
     #[test]
     fn test_token_new_literal() {
         let token = Token::new_literal(b'a');
@@ -370,6 +1151,22 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_compress_with_stats() {
+        let input = b"abcabcabcabcabcabc and then abcabcabc again".repeat(10);
+        for lz77 in [Lz77::new(), Lz77::new().with_tokens(Lz77Tokens::BitPacked)] {
+            let (output, stats) = lz77.compress_with_stats(&input).unwrap();
+            assert_eq!(output, lz77.compress(&input).unwrap());
+            assert_eq!(stats.input_len, input.len() as u64);
+            assert_eq!(stats.output_len, output.len() as u64);
+            let (tokens, matches) = (stats.tokens.unwrap(), stats.matches.unwrap());
+            assert!(matches > 0 && matches < tokens, "{matches} of {tokens}");
+            assert_eq!(stats.symbols, None);
+        }
+        let (_, stats) = Lz77::new().compress_with_stats(b"abcdefg").unwrap();
+        assert_eq!((stats.tokens, stats.matches), (Some(7), Some(0)));
+    }
+
     #[test]
     fn test_compressor_name() {
         let lz77 = Lz77::new();
@@ -452,4 +1249,97 @@ mod tests {
         let result = lz77.decompress(&bytes);
         assert!(matches!(result, Err(CompressionError::CorruptedData)));
     }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let lz77 = Lz77::new();
+        let first = lz77.compress(b"abcabcabcabc").unwrap();
+        let second = lz77.compress(b"").unwrap();
+        let third = lz77.compress(b"xyzxyzxyz!").unwrap();
+        let stream = [first.as_slice(), &second, &third].concat();
+
+        let (data, consumed) = lz77.decompress_partial(&stream).unwrap();
+        assert_eq!(data, b"abcabcabcabc");
+        assert_eq!(consumed, first.len());
+        let frames = lz77.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [b"abcabcabcabc".to_vec(), Vec::new(), b"xyzxyzxyz!".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_decompress_rejects_trailing_tokens() {
+        let lz77 = Lz77::new();
+        let mut compressed = lz77.compress(b"hello").unwrap();
+        compressed.extend_from_slice(&[0, 0, 0, b'!']);
+        assert!(matches!(
+            lz77.decompress(&compressed),
+            Err(CompressionError::CorruptedData)
+        ));
+        let (data, consumed) = lz77.decompress_partial(&compressed).unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(consumed, compressed.len() - TOKEN_LEN);
+    }
+
+    #[test]
+    fn test_history_roundtrip() {
+        let lz77 = Lz77::new();
+        let history = b"the quick brown fox jumps over the lazy dog";
+        let input = b"the lazy dog jumps over the quick brown fox";
+        let compressed = lz77.compress_with_history(history, input).unwrap();
+        assert!(compressed.len() < lz77.compress(input).unwrap().len());
+        assert_eq!(
+            lz77.decompress_with_history(history, &compressed).unwrap(),
+            input
+        );
+        assert!(lz77.decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_history_limited_to_window() {
+        let lz77 = Lz77::with_config(16, 8);
+        let mut history = b"abcdefgh".to_vec();
+        history.extend_from_slice(&[0; 64]);
+        let compressed = lz77.compress_with_history(&history, b"abcdefgh").unwrap();
+        // Eight 9-bit literals: "abcdefgh" in history is out of reach.
+        let header_len = lz77.header_len(8);
+        assert_eq!(compressed.len(), header_len + PACKED_WIDTHS_LEN + 9);
+        assert_eq!(
+            lz77.decompress_with_history(&history, &compressed).unwrap(),
+            b"abcdefgh"
+        );
+    }
+
+    #[test]
+    fn test_empty_history_matches_compress() {
+        let lz77 = Lz77::new();
+        let input = b"abcabcabcabc";
+        assert_eq!(
+            lz77.compress_with_history(&[], input).unwrap(),
+            lz77.compress(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        let text = b"abcabcabcabc, overlapping and distant abcabcabcabc matches".repeat(4);
+        for tokens in [Lz77Tokens::Fixed, Lz77Tokens::Varint, Lz77Tokens::BitPacked] {
+            let codec = Lz77::new().with_tokens(tokens);
+            let compressed = codec.compress(&text).unwrap();
+            let mut output = vec![0; text.len() + 10];
+            let len = codec.decompress_into(&compressed, &mut output).unwrap();
+            assert_eq!(output[..len], text, "{tokens:?}");
+            assert_eq!(
+                codec.decompress_into(&compressed, &mut output[..text.len() - 1]),
+                Err(CompressionError::BufferTooSmall)
+            );
+            let mut trailing = compressed;
+            trailing.push(0);
+            assert_eq!(
+                codec.decompress_into(&trailing, &mut output),
+                Err(CompressionError::CorruptedData)
+            );
+        }
+    }
 }
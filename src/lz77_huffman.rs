@@ -0,0 +1,311 @@
+use crate::error::{CompressionError, Result};
+use crate::huffman::Huffman;
+use crate::level::CompressionLevel;
+use crate::lz77::{self, Lz77, Lz77Tokens};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Token bytes per plane, in the order planes are stored: offset low byte,
+/// offset high byte, match length, next byte.
+pub const PLANES: usize = lz77::TOKEN_LEN;
+/// Start of a frame holding the input as is: a tagged LZ77 header whose tag
+/// no LZ77 frame uses.
+const STORED_PREFIX: [u8; lz77::HEADER_LEN + 1] = [0xFF, 0xFF, 0xFF, 0xFF, lz77::TAG_STORED];
+
+/// Splits the header of a stored frame at the start of `input` into the
+/// original length and the header's own length; `None` if the frame holds
+/// coded planes instead.
+fn stored_header(input: &[u8]) -> Result<Option<(usize, usize)>> {
+    if !input.starts_with(&STORED_PREFIX) {
+        return Ok(None);
+    }
+    let mut pos = STORED_PREFIX.len();
+    let len = varint::read_u64(input, &mut pos)?;
+    let len = usize::try_from(len).map_err(|_| CompressionError::CorruptedData)?;
+    Ok(Some((len, pos)))
+}
+
+/// Two-stage codec that Huffman-codes the token stream of [`Lz77`].
+///
+/// It codes [`Lz77Tokens::Fixed`] tokens, where a match costs four bytes
+/// however predictable its fields are, and literal tokens spend three of
+/// theirs on zeros. Splitting the tokens into planes of offset low bytes, offset high
+/// bytes, lengths and next bytes gives [`Huffman`] streams with sharply
+/// skewed statistics: offsets cluster near the cursor, lengths near the
+/// minimum, literals follow the text. Each plane gets its own table, and a
+/// plane that would not shrink is stored by Huffman as is. Input the planes
+/// would not shrink at all, such as very short or random input, is stored
+/// whole instead, so output is never more than a few bytes larger than the
+/// input. Output layout: `[LZ77 header][offset low plane][offset high
+/// plane][length plane][next plane]`, each plane a [`Huffman`] frame, or
+/// `[0xFF 0xFF 0xFF 0xFF][0xFF][original length: varint][bytes]`.
+#[derive(Debug, Clone, Default)]
+pub struct Lz77Huffman {
+    lz77: Lz77,
+}
+
+impl Lz77Huffman {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_lz77(Lz77::new())
+    }
+
+    /// Creates a codec whose first stage is `lz77`, for a different window,
+    /// lookahead or search budget.
+    #[must_use]
+    pub const fn with_lz77(lz77: Lz77) -> Self {
+        Self { lz77 }
+    }
+
+    #[must_use]
+    pub const fn lz77(&self) -> &Lz77 {
+        &self.lz77
+    }
+}
+
+impl Compressor for Lz77Huffman {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let tokens = self
+            .lz77
+            .clone()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(input)?;
+        let (header, tokens) = tokens.split_at(lz77::frame_header_len(&tokens)?);
+
+        let mut planes: Vec<Vec<u8>> = (0..PLANES)
+            .map(|_| Vec::with_capacity(tokens.len() / PLANES))
+            .collect();
+        for token in tokens.chunks_exact(PLANES) {
+            for (plane, &byte) in planes.iter_mut().zip(token) {
+                plane.push(byte);
+            }
+        }
+
+        let mut output = header.to_vec();
+        for plane in &planes {
+            output.extend_from_slice(&Huffman::new().compress(plane)?);
+        }
+        let stored_len = STORED_PREFIX.len() + varint::len_u64(input.len() as u64) + input.len();
+        if output.len() >= stored_len {
+            output.clear();
+            output.extend_from_slice(&STORED_PREFIX);
+            varint::write_u64(&mut output, input.len() as u64);
+            output.extend_from_slice(input);
+        }
+        Ok(output)
+    }
+
+    /// Coded planes are only kept when smaller than the stored frame.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let header = STORED_PREFIX.len() + varint::len_u64(input_len as u64);
+        input_len.saturating_add(header)
+    }
+
+    fn with_level(self, level: CompressionLevel) -> Self {
+        Self {
+            lz77: self.lz77.with_level(level),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LZ77+Huffman"
+    }
+}
+
+impl Decompressor for Lz77Huffman {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match stored_header(input)? {
+            Some((len, _)) => Ok(Some(len as u64)),
+            None => self.lz77.decompressed_len(input),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LZ77+Huffman"
+    }
+}
+
+impl PartialDecompressor for Lz77Huffman {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        if let Some((len, header_len)) = stored_header(input)? {
+            let end = header_len
+                .checked_add(len)
+                .ok_or(CompressionError::CorruptedData)?;
+            let bytes = input
+                .get(header_len..end)
+                .ok_or(CompressionError::CorruptedData)?;
+            return Ok((bytes.to_vec(), end));
+        }
+        let mut pos = lz77::frame_header_len(input)?;
+        let header = &input[..pos];
+        let mut planes = Vec::with_capacity(PLANES);
+        for _ in 0..PLANES {
+            let (plane, consumed) = Huffman::new().decompress_partial(&input[pos..])?;
+            pos += consumed;
+            planes.push(plane);
+        }
+        let count = planes[0].len();
+        if planes.iter().any(|plane| plane.len() != count) {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let mut tokens = Vec::with_capacity(header.len() + count * PLANES);
+        tokens.extend_from_slice(header);
+        for i in 0..count {
+            tokens.extend(planes.iter().map(|plane| plane[i]));
+        }
+        // Every token must be needed to reach the original length.
+        let (output, consumed) = self.lz77.decompress_partial(&tokens)?;
+        if consumed != tokens.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROSE: &[u8] = b"It was the best of times, it was the worst of times, it was \
+        the age of wisdom, it was the age of foolishness, it was the epoch of belief, \
+        it was the epoch of incredulity, it was the season of Light, it was the season \
+        of Darkness, it was the spring of hope, it was the winter of despair.";
+
+    fn roundtrip(codec: &Lz77Huffman, input: &[u8]) -> Vec<u8> {
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_lz77_huffman_name() {
+        assert_eq!(Compressor::name(&Lz77Huffman::new()), "LZ77+Huffman");
+        assert_eq!(Decompressor::name(&Lz77Huffman::new()), "LZ77+Huffman");
+        assert_eq!(Lz77Huffman::new().lz77().window_size(), 4096);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let codec = Lz77Huffman::new();
+        roundtrip(&codec, b"");
+        roundtrip(&codec, b"a");
+        roundtrip(&codec, b"abcabcabcabcabc");
+        roundtrip(&codec, &(0..=255).collect::<Vec<u8>>());
+        roundtrip(&codec, &[0; 5000]);
+        roundtrip(&codec, PROSE);
+    }
+
+    #[test]
+    fn test_beats_both_stages() {
+        let input = PROSE.repeat(8);
+        let compressed = roundtrip(&Lz77Huffman::new(), &input);
+        let tokens = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(&input)
+            .unwrap();
+        let lz77 = Lz77::new().compress(&input).unwrap();
+        let huffman = Huffman::new().compress(&input).unwrap();
+        assert!(
+            compressed.len() * 2 < tokens.len(),
+            "{} vs {}",
+            compressed.len(),
+            tokens.len()
+        );
+        assert!(compressed.len() < lz77.len());
+        assert!(compressed.len() * 3 < huffman.len());
+    }
+
+    #[test]
+    fn test_stored_fallback() {
+        let codec = Lz77Huffman::new();
+        let short = roundtrip(&codec, b"0123456789");
+        assert_eq!(short[..STORED_PREFIX.len()], STORED_PREFIX);
+        assert_eq!(short.len(), STORED_PREFIX.len() + 1 + 10);
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let random: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+        let stored = roundtrip(&codec, &random);
+        assert_eq!(stored.len(), codec.max_compressed_len(random.len()));
+        assert!(stored.len() <= random.len() + 8);
+        assert_eq!(codec.decompressed_len(&stored).unwrap(), Some(10_000));
+        // Compressible input still gets coded planes.
+        let planes = roundtrip(&codec, &PROSE.repeat(4));
+        assert!(!planes.starts_with(&STORED_PREFIX));
+        // A stored frame cut short, or claiming more than the input holds.
+        assert!(codec.decompress(&short[..short.len() - 1]).is_err());
+        assert!(codec.decompress(&STORED_PREFIX).is_err());
+    }
+
+    #[test]
+    fn test_with_lz77() {
+        let codec = Lz77Huffman::with_lz77(Lz77::realtime());
+        assert_eq!(codec.lz77().window_size(), 64);
+        let compressed = roundtrip(&codec, &PROSE.repeat(4));
+        // The token stream is LZ77's own, so any first-stage settings decode.
+        assert_eq!(
+            Lz77Huffman::new().decompress(&compressed).unwrap(),
+            PROSE.repeat(4)
+        );
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Lz77Huffman::new();
+        let stream = [
+            codec.compress(PROSE).unwrap(),
+            codec.compress(b"").unwrap(),
+            codec.compress(b"tail").unwrap(),
+        ]
+        .concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [PROSE.to_vec(), Vec::new(), b"tail".to_vec()]);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Lz77Huffman::new();
+        assert!(codec.decompress(&[]).is_err());
+        assert!(codec.decompress(&[1, 0, 0]).is_err());
+        let compressed = codec.compress(PROSE).unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        let mut trailing = compressed.clone();
+        trailing.push(0);
+        assert!(codec.decompress(&trailing).is_err());
+        // A longer claimed length runs out of tokens.
+        let mut longer = compressed;
+        longer[0] = longer[0].wrapping_add(1);
+        assert!(codec.decompress(&longer).is_err());
+    }
+
+    #[test]
+    fn test_mismatched_planes() {
+        let mut frame = 2u32.to_le_bytes().to_vec();
+        let huffman = Huffman::new();
+        for plane in [&[0, 0][..], &[0, 0], &[0], b"ab"] {
+            frame.extend_from_slice(&huffman.compress(plane).unwrap());
+        }
+        assert_eq!(
+            Lz77Huffman::new().decompress(&frame),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+}
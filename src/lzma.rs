@@ -0,0 +1,741 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::profile::Profile;
+use crate::range_coder::{BitCoder, PROB_INIT, RangeDecoder, RangeEncoder, reverse_tree, tree};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+const DEFAULT_DICT_BITS: u8 = 22;
+/// Smallest dictionary, as a power of two.
+pub const MIN_DICT_BITS: u8 = 12;
+/// Largest dictionary, as a power of two.
+pub const MAX_DICT_BITS: u8 = 27;
+/// Shortest match; only repeat matches are this short.
+pub const MIN_MATCH: u32 = 2;
+/// Longest match a single packet carries.
+pub const MAX_MATCH: u32 = MIN_MATCH + 8 + 8 + 256 - 1;
+/// High bits of the previous byte selecting a literal model.
+pub const LITERAL_CONTEXT_BITS: u32 = 3;
+/// Low bits of the position selecting an `is_match` probability.
+const POS_STATES: usize = 4;
+const KIND_LITERAL: usize = 0;
+const KIND_MATCH: usize = 1;
+const KIND_REP: usize = 2;
+/// One state per kind of the last two packets.
+const STATES: usize = 9;
+const LEN_LOW_BITS: u32 = 3;
+const LEN_MID_BITS: u32 = 3;
+const LEN_HIGH_BITS: u32 = 8;
+/// Match lengths above this share one distance-slot model.
+const LEN_STATES: usize = 4;
+const SLOT_BITS: u32 = 6;
+/// Slots from here on code their low bits with the shared align model.
+const END_SLOT_INDEX: u32 = 14;
+/// Footer bits of the last slot before [`END_SLOT_INDEX`].
+const MAX_FOOTER_BITS: u32 = END_SLOT_INDEX / 2 - 2;
+const ALIGN_BITS: u32 = 4;
+const HASH_BITS: u32 = 16;
+/// Candidates examined per position by the match finder by default.
+const DEFAULT_CHAIN_DEPTH: usize = 64;
+/// Dictionary bits and chain depth for each [`CompressionLevel`], fastest
+/// first; level 6 is [`Lzma::new`].
+const LEVELS: [(u8, usize); 9] = [
+    (16, 4),
+    (17, 8),
+    (18, 16),
+    (19, 24),
+    (20, 32),
+    (DEFAULT_DICT_BITS, DEFAULT_CHAIN_DEPTH),
+    (23, 128),
+    (24, 256),
+    (26, 1024),
+];
+/// Three-byte matches farther back than this cost more than literals.
+const FAR_SHORT_MATCH: u32 = 1 << 12;
+
+/// Match lengths minus [`MIN_MATCH`]: 3 bits below 8, 3 more below 16,
+/// then 8 bits.
+#[derive(Debug, Clone)]
+struct LengthModel {
+    choice: u32,
+    choice2: u32,
+    low: [u32; 1 << LEN_LOW_BITS],
+    mid: [u32; 1 << LEN_MID_BITS],
+    high: [u32; 1 << LEN_HIGH_BITS],
+}
+
+impl LengthModel {
+    const fn new() -> Self {
+        Self {
+            choice: PROB_INIT,
+            choice2: PROB_INIT,
+            low: [PROB_INIT; 1 << LEN_LOW_BITS],
+            mid: [PROB_INIT; 1 << LEN_MID_BITS],
+            high: [PROB_INIT; 1 << LEN_HIGH_BITS],
+        }
+    }
+
+    fn code<C: BitCoder>(&mut self, coder: &mut C, len: u32) -> Result<u32> {
+        let value = len.saturating_sub(MIN_MATCH);
+        let mid_start = 1 << LEN_LOW_BITS;
+        let high_start = mid_start + (1 << LEN_MID_BITS);
+        let (start, probs, bits) =
+            if coder.bit(&mut self.choice, u32::from(value >= mid_start))? == 0 {
+                (0, &mut self.low[..], LEN_LOW_BITS)
+            } else if coder.bit(&mut self.choice2, u32::from(value >= high_start))? == 0 {
+                (mid_start, &mut self.mid[..], LEN_MID_BITS)
+            } else {
+                (high_start, &mut self.high[..], LEN_HIGH_BITS)
+            };
+        Ok(MIN_MATCH + start + tree(coder, probs, bits, value.wrapping_sub(start))?)
+    }
+}
+
+/// Distance slot of a zero-based distance: its bit length and the bit
+/// below the top one.
+const fn slot(distance: u32) -> u32 {
+    if distance < 4 {
+        return distance;
+    }
+    let top = u32::BITS - 1 - distance.leading_zeros();
+    2 * top + (distance >> (top - 1) & 1)
+}
+
+/// Zero-based distances: a 6-bit slot per length state, then the bits
+/// below the slot's top two, context-coded for near slots and mostly
+/// direct for far ones.
+#[derive(Debug, Clone)]
+struct DistanceModel {
+    slots: [[u32; 1 << SLOT_BITS]; LEN_STATES],
+    footers: [[u32; 1 << MAX_FOOTER_BITS]; END_SLOT_INDEX as usize - 4],
+    align: [u32; 1 << ALIGN_BITS],
+}
+
+impl DistanceModel {
+    const fn new() -> Self {
+        Self {
+            slots: [[PROB_INIT; 1 << SLOT_BITS]; LEN_STATES],
+            footers: [[PROB_INIT; 1 << MAX_FOOTER_BITS]; END_SLOT_INDEX as usize - 4],
+            align: [PROB_INIT; 1 << ALIGN_BITS],
+        }
+    }
+
+    fn code<C: BitCoder>(&mut self, coder: &mut C, len: u32, distance: u32) -> Result<u32> {
+        let len_state = ((len - MIN_MATCH) as usize).min(LEN_STATES - 1);
+        let slot = tree(coder, &mut self.slots[len_state], SLOT_BITS, slot(distance))?;
+        if slot < 4 {
+            return Ok(slot);
+        }
+        let footer_bits = (slot >> 1) - 1;
+        let base = (2 | (slot & 1)) << footer_bits;
+        let rest = distance.wrapping_sub(base);
+        if slot < END_SLOT_INDEX {
+            let probs = &mut self.footers[slot as usize - 4];
+            return Ok(base + reverse_tree(coder, probs, footer_bits, rest)?);
+        }
+        let mut high = 0;
+        for i in (ALIGN_BITS..footer_bits).rev() {
+            high |= coder.direct(rest >> i & 1)? << i;
+        }
+        Ok(base + high + reverse_tree(coder, &mut self.align, ALIGN_BITS, rest)?)
+    }
+}
+
+/// One step of the LZ parse. Distances are zero-based: a distance of 0
+/// copies the previous byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Packet {
+    Literal(u8),
+    Match {
+        len: u32,
+        distance: u32,
+    },
+    /// A match at the same distance as the last one.
+    Rep {
+        len: u32,
+    },
+}
+
+/// The adaptive probabilities both sides evolve as they go.
+#[derive(Debug, Clone)]
+struct Model {
+    is_match: [[u32; POS_STATES]; STATES],
+    is_rep: [u32; STATES],
+    literals: Vec<[u32; 0x100]>,
+    match_len: LengthModel,
+    rep_len: LengthModel,
+    distance: DistanceModel,
+    state: usize,
+    rep0: u32,
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            is_match: [[PROB_INIT; POS_STATES]; STATES],
+            is_rep: [PROB_INIT; STATES],
+            literals: vec![[PROB_INIT; 0x100]; 1 << LITERAL_CONTEXT_BITS],
+            match_len: LengthModel::new(),
+            rep_len: LengthModel::new(),
+            distance: DistanceModel::new(),
+            state: 0,
+            rep0: 0,
+        }
+    }
+
+    /// Codes `packet` at `position` after the byte `previous`. The decoder
+    /// passes any packet and gets back the one it read.
+    fn code<C: BitCoder>(
+        &mut self,
+        coder: &mut C,
+        packet: Packet,
+        previous: u8,
+        position: usize,
+    ) -> Result<Packet> {
+        let (byte, len, distance) = match packet {
+            Packet::Literal(byte) => (byte, 0, 0),
+            Packet::Match { len, distance } => (0, len, distance),
+            Packet::Rep { len } => (0, len, 0),
+        };
+        let is_match = &mut self.is_match[self.state][position % POS_STATES];
+        let kind = if coder.bit(is_match, u32::from(!matches!(packet, Packet::Literal(_))))? == 0 {
+            KIND_LITERAL
+        } else if coder.bit(
+            &mut self.is_rep[self.state],
+            u32::from(matches!(packet, Packet::Rep { .. })),
+        )? == 0
+        {
+            KIND_MATCH
+        } else {
+            KIND_REP
+        };
+        self.state = self.state % 3 * 3 + kind;
+
+        Ok(match kind {
+            KIND_LITERAL => {
+                let probs = &mut self.literals[usize::from(previous >> (8 - LITERAL_CONTEXT_BITS))];
+                Packet::Literal(tree(coder, probs, 8, u32::from(byte))?.to_le_bytes()[0])
+            }
+            KIND_MATCH => {
+                let len = self.match_len.code(coder, len)?;
+                self.rep0 = self.distance.code(coder, len, distance)?;
+                Packet::Match {
+                    len,
+                    distance: self.rep0,
+                }
+            }
+            _ => Packet::Rep {
+                len: self.rep_len.code(coder, len)?,
+            },
+        })
+    }
+}
+
+/// Length of the common prefix of `input[a..]` and `input[b..]`, capped at
+/// [`MAX_MATCH`].
+fn common_len(input: &[u8], a: usize, b: usize) -> usize {
+    input[a..]
+        .iter()
+        .zip(&input[b..])
+        .take(MAX_MATCH as usize)
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+/// Hash chains over three-byte prefixes.
+#[derive(Debug)]
+struct MatchFinder {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+    window: usize,
+    depth: usize,
+    inserted: usize,
+}
+
+impl MatchFinder {
+    fn new(len: usize, window: usize, depth: usize) -> Self {
+        Self {
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            prev: vec![usize::MAX; len],
+            window,
+            depth,
+            inserted: 0,
+        }
+    }
+
+    fn hash(input: &[u8], pos: usize) -> usize {
+        let sequence = u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], 0]);
+        (sequence.wrapping_mul(2_654_435_761) >> (u32::BITS - HASH_BITS)) as usize
+    }
+
+    /// Longest earlier match for `pos` as `(len, distance)`, after indexing
+    /// every position before it. Positions must be queried in order.
+    fn find(&mut self, input: &[u8], pos: usize) -> (usize, usize) {
+        let last = input.len().saturating_sub(2);
+        while self.inserted < pos.min(last) {
+            let hash = Self::hash(input, self.inserted);
+            self.prev[self.inserted] = self.head[hash];
+            self.head[hash] = self.inserted;
+            self.inserted += 1;
+        }
+        if pos >= last {
+            return (0, 0);
+        }
+
+        let mut best = (0, 0);
+        let mut candidate = self.head[Self::hash(input, pos)];
+        for _ in 0..self.depth {
+            if candidate == usize::MAX || pos - candidate > self.window {
+                break;
+            }
+            let len = common_len(input, pos, candidate);
+            if len > best.0 {
+                best = (len, pos - candidate - 1);
+                if len == MAX_MATCH as usize {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+        }
+        best
+    }
+}
+
+/// Picks the packet to code at `pos`, preferring a repeat of the last
+/// distance and deferring a match by a byte when the next one is longer.
+fn choose(input: &[u8], pos: usize, rep0: usize, finder: &mut MatchFinder) -> Packet {
+    let (len, distance) = finder.find(input, pos);
+    let rep_len = if rep0 < pos {
+        common_len(input, pos, pos - rep0 - 1)
+    } else {
+        0
+    };
+    let to_u32 = |value: usize| u32::try_from(value).unwrap_or(u32::MAX);
+
+    if rep_len >= MIN_MATCH as usize && rep_len + 1 >= len {
+        return Packet::Rep {
+            len: to_u32(rep_len),
+        };
+    }
+    if len < 3 || (len == 3 && to_u32(distance) >= FAR_SHORT_MATCH) {
+        return Packet::Literal(input[pos]);
+    }
+    if pos + 1 < input.len() && finder.find(input, pos + 1).0 > len + 1 {
+        return Packet::Literal(input[pos]);
+    }
+    Packet::Match {
+        len: to_u32(len),
+        distance: to_u32(distance),
+    }
+}
+
+/// LZMA-style high-ratio codec: a large-window LZ parse coded bit by bit
+/// with adaptive context models.
+///
+/// Matches are found through hash chains over a dictionary of up to
+/// `2^dict_bits` bytes and coded with separate models for literals (by the
+/// high bits of the previous byte), match lengths, and distance slots (by
+/// length). A match repeating the previous distance costs only a flag and
+/// a length. Every decision goes through the shared range coder, so the
+/// ratio is well beyond [`Lz77`](crate::Lz77) followed by
+/// [`Huffman`](crate::Huffman), at the cost of slower compression. The
+/// format follows LZMA's design but is not readable by LZMA tools. Output
+/// layout: `[dict bits: u8][original length: varint][range-coded bytes]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Lzma {
+    dict_bits: u8,
+    chain_depth: usize,
+}
+
+impl Default for Lzma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lzma {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_dict_bits(DEFAULT_DICT_BITS)
+    }
+
+    /// Creates a codec whose matches reach back up to `2^dict_bits` bytes
+    /// (`MIN_DICT_BITS` to `MAX_DICT_BITS`). Decoding needs no more
+    /// memory than the output, whatever the dictionary.
+    #[must_use]
+    pub const fn with_dict_bits(dict_bits: u8) -> Self {
+        Self {
+            dict_bits,
+            chain_depth: DEFAULT_CHAIN_DEPTH,
+        }
+    }
+
+    /// Creates a codec whose dictionary just covers the match distance
+    /// recorded in `profile`, saving memory on data whose repeats are near.
+    #[must_use]
+    pub fn with_profile(profile: &Profile) -> Self {
+        let needed = u32::BITS - profile.match_distance().leading_zeros();
+        let dict_bits = u8::try_from(needed)
+            .unwrap_or(MAX_DICT_BITS)
+            .clamp(MIN_DICT_BITS, MAX_DICT_BITS);
+        Self::with_dict_bits(dict_bits)
+    }
+
+    #[must_use]
+    pub const fn dict_bits(&self) -> u8 {
+        self.dict_bits
+    }
+
+    /// Candidates the match finder examines per position.
+    #[must_use]
+    pub const fn chain_depth(&self) -> usize {
+        self.chain_depth
+    }
+}
+
+impl Lzma {
+    /// Encodes `input`, counting its literals and matches into `stats`
+    /// if given.
+    fn encode(&self, input: &[u8], stats: Option<&mut CompressionStats>) -> Result<Vec<u8>> {
+        if !(MIN_DICT_BITS..=MAX_DICT_BITS).contains(&self.dict_bits) {
+            return Err(CompressionError::InvalidInput(format!(
+                "LZMA dictionary bits must be between {MIN_DICT_BITS} and {MAX_DICT_BITS}"
+            )));
+        }
+
+        let mut output = vec![self.dict_bits];
+        varint::write_u64(&mut output, input.len() as u64);
+
+        let mut finder = MatchFinder::new(input.len(), 1 << self.dict_bits, self.chain_depth);
+        let mut model = Model::new();
+        let mut encoder = RangeEncoder::new();
+        let (mut matches, mut match_bytes) = (0, 0);
+        let mut pos = 0;
+        while pos < input.len() {
+            let packet = choose(input, pos, model.rep0 as usize, &mut finder);
+            let previous = pos.checked_sub(1).map_or(0, |i| input[i]);
+            model.code(&mut encoder, packet, previous, pos)?;
+            pos += match packet {
+                Packet::Literal(_) => 1,
+                Packet::Match { len, .. } | Packet::Rep { len } => {
+                    matches += 1;
+                    match_bytes += len as usize;
+                    len as usize
+                }
+            };
+        }
+        output.extend_from_slice(&encoder.finish());
+        if let Some(stats) = stats {
+            stats.tokens = Some((input.len() - match_bytes) as u64 + matches);
+            stats.matches = Some(matches);
+        }
+        Ok(output)
+    }
+}
+
+impl Compressor for Lzma {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        self.encode(input, None)
+    }
+
+    /// Also counts tokens: each literal byte is one, and so is each
+    /// match.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let mut stats = CompressionStats::new(input.len() as u64, 0, Duration::ZERO);
+        let output = self.encode(input, Some(&mut stats))?;
+        stats.output_len = output.len() as u64;
+        stats.elapsed = start.elapsed();
+        Ok((output, stats))
+    }
+
+    fn with_level(self, level: CompressionLevel) -> Self {
+        let (dict_bits, chain_depth) = level.pick(&LEVELS);
+        Self {
+            dict_bits,
+            chain_depth,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LZMA"
+    }
+}
+
+impl Decompressor for Lzma {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match input.first() {
+            Some(dict_bits) if (MIN_DICT_BITS..=MAX_DICT_BITS).contains(dict_bits) => {
+                varint::read_u64(input, &mut 1).map(Some)
+            }
+            _ => Err(CompressionError::CorruptedData),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LZMA"
+    }
+}
+
+impl PartialDecompressor for Lzma {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let (&dict_bits, _) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        if !(MIN_DICT_BITS..=MAX_DICT_BITS).contains(&dict_bits) {
+            return Err(CompressionError::CorruptedData);
+        }
+        let mut pos = 1;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        let window = 1 << dict_bits;
+        let mut model = Model::new();
+        let mut decoder = RangeDecoder::new(&input[pos..])?;
+        let mut output = Vec::with_capacity(original_len.min(input.len().saturating_mul(64)));
+        while output.len() < original_len {
+            let previous = output.last().copied().unwrap_or(0);
+            let packet = model.code(&mut decoder, Packet::Literal(0), previous, output.len())?;
+            let len = match packet {
+                Packet::Literal(byte) => {
+                    output.push(byte);
+                    continue;
+                }
+                Packet::Match { len, .. } | Packet::Rep { len } => len as usize,
+            };
+            let offset = model.rep0 as usize + 1;
+            if offset > output.len() || offset > window || len > original_len - output.len() {
+                return Err(CompressionError::CorruptedData);
+            }
+
+            let start = output.len() - offset;
+            if offset >= len {
+                output.extend_from_within(start..start + len);
+            } else {
+                // Overlapping match: each pass doubles the repeated span.
+                let mut remaining = len;
+                while remaining > 0 {
+                    let chunk = remaining.min(output.len() - start);
+                    output.extend_from_within(start..start + chunk);
+                    remaining -= chunk;
+                }
+            }
+        }
+        Ok((output, pos + decoder.bytes_consumed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz4, Lz77, Lz77Tokens};
+
+    fn roundtrip(codec: Lzma, input: &[u8]) -> Vec<u8> {
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    /// Real source text: this crate's own files.
+    fn text() -> Vec<u8> {
+        [include_str!("lz77.rs"), include_str!("huffman.rs")]
+            .concat()
+            .into_bytes()
+    }
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lzma_new() {
+        assert_eq!(Lzma::new().dict_bits(), DEFAULT_DICT_BITS);
+        assert_eq!(Lzma::default().dict_bits(), DEFAULT_DICT_BITS);
+        assert_eq!(Lzma::with_dict_bits(16).dict_bits(), 16);
+        assert_eq!(Lzma::new().chain_depth(), DEFAULT_CHAIN_DEPTH);
+        assert_eq!(Compressor::name(&Lzma::new()), "LZMA");
+        assert_eq!(Decompressor::name(&Lzma::new()), "LZMA");
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressed = roundtrip(Lzma::new(), &[]);
+        assert!(!compressed.is_empty());
+        assert!(Lzma::new().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_slot() {
+        let slots: Vec<u32> = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, u32::MAX]
+            .into_iter()
+            .map(slot)
+            .collect();
+        assert_eq!(slots, [0, 1, 2, 3, 4, 4, 5, 5, 6, 7, 8, 63]);
+    }
+
+    #[test]
+    fn test_roundtrip_mixed() {
+        for len in [1, 2, 3, 4, 5, 300] {
+            roundtrip(Lzma::new(), &text()[..len]);
+        }
+        roundtrip(Lzma::new(), &noise(5000, 1));
+        roundtrip(Lzma::new(), b"abababababababababab");
+        let mut input = vec![0u8; 3000];
+        input.extend_from_slice(&noise(40, 2));
+        input.extend_from_slice(&[0xFF; 1000]);
+        roundtrip(Lzma::with_dict_bits(MIN_DICT_BITS), &input);
+    }
+
+    #[test]
+    fn test_beats_lz77_huffman_on_text() {
+        let input = &text()[..12 * 1024];
+        let lzma = roundtrip(Lzma::new(), input).len();
+        let lz77 = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(input)
+            .unwrap();
+        let lz77_huffman = Huffman::new().compress(&lz77).unwrap().len();
+        let lz4 = Lz4::new().compress(input).unwrap().len();
+        assert!(lzma * 3 < lz77_huffman * 2, "{lzma} vs {lz77_huffman}");
+        assert!(lzma * 3 < lz4 * 2, "{lzma} vs {lz4}");
+    }
+
+    #[test]
+    fn test_long_range_matches() {
+        // A block of noise repeated beyond LZ77's 4 KiB window.
+        let block = noise(50_000, 3);
+        let input = [block.as_slice(), &block].concat();
+        let compressed = roundtrip(Lzma::new(), &input);
+        assert!(compressed.len() < 51_000, "{}", compressed.len());
+
+        // A dictionary smaller than the distance cannot see the repeat.
+        let small = roundtrip(Lzma::with_dict_bits(15), &input);
+        assert!(small.len() > 100_000, "{}", small.len());
+    }
+
+    #[test]
+    fn test_with_level() {
+        let default = Lzma::with_dict_bits(16).with_level(CompressionLevel::DEFAULT);
+        assert_eq!(default.dict_bits(), DEFAULT_DICT_BITS);
+        assert_eq!(default.chain_depth(), DEFAULT_CHAIN_DEPTH);
+        // The fastest level's 64 KiB dictionary misses a repeat 70 KB back.
+        let block = noise(70_000, 3);
+        let input = [block.as_slice(), &block].concat();
+        let fastest = roundtrip(Lzma::new().with_level(CompressionLevel::FASTEST), &input);
+        assert!(fastest.len() > 140_000, "{}", fastest.len());
+        let best = roundtrip(Lzma::new().with_level(CompressionLevel::BEST), &input);
+        assert!(best.len() < 72_000, "{}", best.len());
+    }
+
+    #[test]
+    fn test_long_runs() {
+        let compressed = roundtrip(Lzma::new(), &vec![b'x'; 100_000]);
+        assert!(compressed.len() < 100, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_invalid_dict_bits() {
+        for dict_bits in [MIN_DICT_BITS - 1, MAX_DICT_BITS + 1] {
+            let result = Lzma::with_dict_bits(dict_bits).compress(b"abc");
+            assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+            assert!(
+                Lzma::new()
+                    .decompress(&[dict_bits, 0, 0, 0, 0, 0, 0])
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decoder_enforces_stored_dictionary() {
+        let block = noise(5000, 4);
+        let input = [block.as_slice(), &block].concat();
+        let mut compressed = Lzma::with_dict_bits(16).compress(&input).unwrap();
+        assert_eq!(
+            Lzma::with_dict_bits(20).decompress(&compressed).unwrap(),
+            input
+        );
+        compressed[0] = MIN_DICT_BITS;
+        assert!(Lzma::new().decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_compress_with_stats() {
+        let input = b"abcdefgh abcdefgh abcdefgh, then something else entirely".repeat(8);
+        let (output, stats) = Lzma::new().compress_with_stats(&input).unwrap();
+        assert_eq!(output, Lzma::new().compress(&input).unwrap());
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (input.len() as u64, output.len() as u64)
+        );
+        let (tokens, matches) = (stats.tokens.unwrap(), stats.matches.unwrap());
+        assert!(matches > 0 && tokens > matches && tokens < input.len() as u64);
+        assert_eq!(stats.symbols, None);
+        let (_, stats) = Lzma::new().compress_with_stats(b"abc").unwrap();
+        assert_eq!((stats.tokens, stats.matches), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Lzma::new();
+        let first = codec.compress(&text()[..2000]).unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [text()[..2000].to_vec(), Vec::new(), text()[..2000].to_vec()]
+        );
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Lzma::new();
+        let compressed = codec.compress(&text()[..1024]).unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        assert!(codec.decompress(&compressed[..3]).is_err());
+        // Length promising more bytes than the stream holds.
+        let mut longer = compressed;
+        longer[1] = 0xFF;
+        assert!(codec.decompress(&longer).is_err());
+    }
+
+    #[test]
+    fn test_with_profile_sizes_dictionary() {
+        let near = Profile::capture(&b"abcdefgh".repeat(100));
+        assert_eq!(Lzma::with_profile(&near).dict_bits(), MIN_DICT_BITS);
+        assert_eq!(
+            Lzma::with_profile(&Profile::capture(&[])).dict_bits(),
+            MIN_DICT_BITS
+        );
+
+        // A block repeated 40 KB later needs a 64 KB dictionary.
+        let block = noise(40_000, 7);
+        let far = Profile::capture(&[block.as_slice(), &block].concat());
+        let codec = Lzma::with_profile(&far);
+        assert_eq!(codec.dict_bits(), 16);
+        roundtrip(codec, &[block.as_slice(), &block].concat());
+    }
+}
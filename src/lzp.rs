@@ -0,0 +1,332 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{CompressionError, Result};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Number of preceding bytes hashed to predict the next match position.
+pub const ORDER: usize = 4;
+/// Shortest match the encoder emits; shorter predictions are coded as
+/// literals.
+pub const MIN_MATCH: usize = 2;
+/// Flags packed into each control byte, lowest bit first.
+pub const FLAGS_PER_CONTROL: u32 = 8;
+const TABLE_BITS: u32 = 16;
+
+const fn hash(context: u32) -> usize {
+    (context.wrapping_mul(0x9E37_79B1) >> (u32::BITS - TABLE_BITS)) as usize
+}
+
+/// Table of the last position seen after each hashed context, shared in
+/// lockstep by the encoder and decoder.
+struct Predictions {
+    table: Vec<usize>,
+}
+
+impl Predictions {
+    fn new() -> Self {
+        Self {
+            table: vec![0; 1 << TABLE_BITS],
+        }
+    }
+
+    /// Records `pos` under the context ending there, returning the position
+    /// previously recorded under it, if any.
+    fn predict(&mut self, data: &[u8], pos: usize) -> Option<usize> {
+        if pos < ORDER {
+            return None;
+        }
+        let context =
+            u32::from_le_bytes([data[pos - 4], data[pos - 3], data[pos - 2], data[pos - 1]]);
+        // Position 0 never follows a full context, so it marks empty slots.
+        let predicted = std::mem::replace(&mut self.table[hash(context)], pos);
+        (predicted != 0).then_some(predicted)
+    }
+}
+
+/// LZP codec: matches whose position both sides predict, so only their
+/// lengths are written.
+///
+/// The hash of the `ORDER` bytes before each position selects the last
+/// place the same context was seen. Where a prediction exists, a flag says
+/// whether the data continues as it did there, and if so for how long; no
+/// offset is ever stored. Positions without a prediction take a plain
+/// literal and no flag. Flags are gathered eight to a control byte, which
+/// sits just before the first token it governs. Only the most recent
+/// occurrence of each context is ever tried, so the ratio trails
+/// [`Lz4`](crate::Lz4), whose offsets reach any earlier match, but each
+/// position costs one table lookup in both directions and no search.
+/// Output layout: `[original length: varint][control bytes, literals and
+/// length varints]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Lzp;
+
+impl Lzp {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+/// Writes flags into control bytes placed in the output as they fill.
+struct FlagWriter {
+    control: usize,
+    used: u32,
+}
+
+impl FlagWriter {
+    const fn new() -> Self {
+        Self {
+            control: 0,
+            used: FLAGS_PER_CONTROL,
+        }
+    }
+
+    fn push(&mut self, output: &mut Vec<u8>, flag: bool) {
+        if self.used == FLAGS_PER_CONTROL {
+            self.control = output.len();
+            self.used = 0;
+            output.push(0);
+        }
+        output[self.control] |= u8::from(flag) << self.used;
+        self.used += 1;
+    }
+}
+
+impl Lzp {
+    /// Encodes `input`, counting its literals and matches into `stats`
+    /// if given.
+    fn encode(input: &[u8], stats: Option<&mut CompressionStats>) -> Vec<u8> {
+        let mut output = Vec::with_capacity(varint::MAX_LEN_U64 + input.len() + input.len() / 8);
+        varint::write_u64(&mut output, input.len() as u64);
+        let mut predictions = Predictions::new();
+        let mut flags = FlagWriter::new();
+        let (mut matches, mut match_bytes) = (0, 0);
+        let mut pos = 0;
+        while pos < input.len() {
+            let Some(predicted) = predictions.predict(input, pos) else {
+                output.push(input[pos]);
+                pos += 1;
+                continue;
+            };
+            let len = input[pos..]
+                .iter()
+                .zip(&input[predicted..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len < MIN_MATCH {
+                flags.push(&mut output, false);
+                output.push(input[pos]);
+                pos += 1;
+                continue;
+            }
+            flags.push(&mut output, true);
+            varint::write_u64(&mut output, (len - MIN_MATCH) as u64);
+            for inner in pos + 1..pos + len {
+                predictions.predict(input, inner);
+            }
+            matches += 1;
+            match_bytes += len;
+            pos += len;
+        }
+        if let Some(stats) = stats {
+            stats.tokens = Some((input.len() - match_bytes) as u64 + matches);
+            stats.matches = Some(matches);
+        }
+        output
+    }
+}
+
+impl Compressor for Lzp {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Ok(Self::encode(input, None))
+    }
+
+    /// Also counts tokens: each literal byte is one, and so is each
+    /// match.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let mut stats = CompressionStats::new(input.len() as u64, 0, Duration::ZERO);
+        let output = Self::encode(input, Some(&mut stats));
+        stats.output_len = output.len() as u64;
+        stats.elapsed = start.elapsed();
+        Ok((output, stats))
+    }
+
+    /// Unmatched bytes cost themselves plus at most one flag bit; a match
+    /// length never costs more than the bytes it covers.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let header = varint::len_u64(input_len as u64);
+        (input_len + input_len.div_ceil(FLAGS_PER_CONTROL as usize)).saturating_add(header)
+    }
+
+    fn name(&self) -> &'static str {
+        "LZP"
+    }
+}
+
+impl Decompressor for Lzp {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "LZP"
+    }
+}
+
+impl PartialDecompressor for Lzp {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        let mut output = Vec::with_capacity(original_len.min(input.len().saturating_mul(64)));
+        let mut predictions = Predictions::new();
+        let mut control = 0u8;
+        let mut remaining_flags = 0;
+        while output.len() < original_len {
+            let Some(predicted) = predictions.predict(&output, output.len()) else {
+                let &byte = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+                output.push(byte);
+                pos += 1;
+                continue;
+            };
+            if remaining_flags == 0 {
+                control = *input.get(pos).ok_or(CompressionError::CorruptedData)?;
+                pos += 1;
+                remaining_flags = FLAGS_PER_CONTROL;
+            }
+            let is_match = control & 1 == 1;
+            control >>= 1;
+            remaining_flags -= 1;
+            if !is_match {
+                let &byte = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+                output.push(byte);
+                pos += 1;
+                continue;
+            }
+            let len = usize::try_from(varint::read_u64(input, &mut pos)?)
+                .ok()
+                .and_then(|extra| extra.checked_add(MIN_MATCH))
+                .filter(|&len| len <= original_len - output.len())
+                .ok_or(CompressionError::CorruptedData)?;
+            let start = output.len();
+            for i in 0..len {
+                if i > 0 {
+                    predictions.predict(&output, start + i);
+                }
+                output.push(output[predicted + i]);
+            }
+        }
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let codec = Lzp::new();
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_lzp_name() {
+        assert_eq!(Compressor::name(&Lzp::new()), "LZP");
+        assert_eq!(Decompressor::name(&Lzp::new()), "LZP");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(b"");
+        roundtrip(b"a");
+        roundtrip(b"abcd");
+        roundtrip(b"abcdabcdabcdabcd");
+        roundtrip(&[0; 1000]);
+        roundtrip(&(0..=255).cycle().take(5000).collect::<Vec<u8>>());
+        let mut state = 7u32;
+        let noise: Vec<u8> = (0..4000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state.to_le_bytes()[2]
+            })
+            .collect();
+        roundtrip(&noise);
+        roundtrip(&[noise.as_slice(), &noise].concat());
+    }
+
+    #[test]
+    fn test_runs_cost_one_length() {
+        let compressed = roundtrip(&[b'x'; 10_000]);
+        // Four unpredicted literals, then one flagged match covering the
+        // rest: context "xxxx" first predicts position 4 at position 5.
+        assert!(compressed.len() < 16, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_text_compresses() {
+        let text = b"the quick brown fox jumps over the lazy dog; \
+            the quick brown cat naps under the lazy dog. "
+            .repeat(40);
+        let compressed = roundtrip(&text);
+        assert!(compressed.len() * 10 < text.len(), "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_compress_with_stats() {
+        let input = b"abcdefgh abcdefgh abcdefgh, then something else entirely".repeat(8);
+        let (output, stats) = Lzp::new().compress_with_stats(&input).unwrap();
+        assert_eq!(output, Lzp::new().compress(&input).unwrap());
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (input.len() as u64, output.len() as u64)
+        );
+        let (tokens, matches) = (stats.tokens.unwrap(), stats.matches.unwrap());
+        assert!(matches > 0 && tokens > matches && tokens < input.len() as u64);
+        assert_eq!(stats.symbols, None);
+        let (_, stats) = Lzp::new().compress_with_stats(b"abc").unwrap();
+        assert_eq!((stats.tokens, stats.matches), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Lzp::new();
+        let text = b"abcabcabcabcabcabc".repeat(5);
+        let stream = [
+            codec.compress(&text).unwrap(),
+            codec.compress(b"").unwrap(),
+            codec.compress(&[9; 300]).unwrap(),
+        ]
+        .concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [text, Vec::new(), vec![9; 300]]);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Lzp::new();
+        assert!(codec.decompress(&[]).is_err());
+        // Truncated literals.
+        assert!(codec.decompress(&[5, b'a', b'b']).is_err());
+        // A match running past the original length.
+        let mut frame = codec.compress(&[b'z'; 50]).unwrap();
+        frame[0] = 20;
+        assert!(codec.decompress(&frame).is_err());
+        let mut trailing = codec.compress(b"hello hello hello").unwrap();
+        trailing.push(0);
+        assert!(codec.decompress(&trailing).is_err());
+    }
+}
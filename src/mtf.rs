@@ -0,0 +1,106 @@
+use crate::error::Result;
+use crate::traits::Transform;
+
+/// Move-to-front transform.
+///
+/// Keeps the 256 byte values in a list, most recently seen first, and
+/// replaces each byte with its position in the list before moving it to
+/// the front. A byte repeated soon after itself becomes a small number, so
+/// the clustered output of [`Bwt`](crate::Bwt) turns into mostly zeros and
+/// ones for [`Rle0`](crate::Rle0) or an entropy coder. The output is
+/// exactly as long as the input and every byte string is valid input in
+/// both directions. A [`Transform`] only: it has no settings to record.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Mtf;
+
+impl Mtf {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+/// The byte values in their starting order, each its own position.
+fn identity() -> [u8; 256] {
+    let mut list = [0u8; 256];
+    for (value, slot) in (0..=u8::MAX).zip(&mut list) {
+        *slot = value;
+    }
+    list
+}
+
+impl Transform for Mtf {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut list = identity();
+        Ok(input
+            .iter()
+            .map(|&byte| {
+                let position = list.iter().position(|&b| b == byte).unwrap_or(0);
+                list.copy_within(..position, 1);
+                list[0] = byte;
+                u8::try_from(position).unwrap_or(u8::MAX)
+            })
+            .collect())
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut list = identity();
+        Ok(input
+            .iter()
+            .map(|&position| {
+                let position = usize::from(position);
+                let byte = list[position];
+                list.copy_within(..position, 1);
+                list[0] = byte;
+                byte
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &'static str {
+        "MTF"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bwt;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let transformed = Mtf::new().forward(input).unwrap();
+        assert_eq!(transformed.len(), input.len());
+        assert_eq!(Mtf::new().inverse(&transformed).unwrap(), input);
+        transformed
+    }
+
+    #[test]
+    fn test_mtf_name() {
+        assert_eq!(Mtf::new().name(), "MTF");
+    }
+
+    #[test]
+    fn test_known_output() {
+        assert_eq!(roundtrip(b"aaab"), [b'a', 0, 0, b'b']);
+        assert_eq!(roundtrip(&[1, 0, 1, 0]), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(b"");
+        roundtrip(&(0..=255).rev().collect::<Vec<u8>>());
+        roundtrip(b"the quick brown fox jumps over the lazy dog");
+        // Every byte string is some transform's output.
+        let positions: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let bytes = Mtf::new().inverse(&positions).unwrap();
+        assert_eq!(Mtf::new().forward(&bytes).unwrap(), positions);
+    }
+
+    #[test]
+    fn test_turns_bwt_runs_into_zeros() {
+        let text = b"mississippi river, mississippi delta; ".repeat(50);
+        let transformed = roundtrip(&Bwt::with_block_size(text.len()).forward(&text).unwrap());
+        let nonzero = transformed.iter().filter(|&&b| b != 0).count();
+        assert!(nonzero * 2 < transformed.len(), "{nonzero}");
+    }
+}
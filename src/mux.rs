@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+
+use crate::error::{CompressionError, Result};
+use crate::traits::Codec;
+
+pub const FRAME_HEADER_LEN: usize = 6;
+
+/// A decompressed frame read back from a multiplexed stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub channel: u16,
+    pub data: Vec<u8>,
+    /// Whether this is a payload-less frame written by
+    /// [`Mux::write_sentinel`]; `data` is then empty.
+    pub sentinel: bool,
+}
+
+/// Interleaves independently compressed logical channels into one stream.
+///
+/// Each call to [`Mux::write`] emits one frame laid out as
+/// `[channel: u16][payload length: u32][payload]`, little-endian. Codecs
+/// never produce an empty payload, even for empty data, so a zero payload
+/// length is reserved for sentinel frames such as keepalives.
+#[derive(Debug)]
+pub struct Mux<W: Write, C: Codec> {
+    writer: W,
+    codec: C,
+}
+
+impl<W: Write, C: Codec> Mux<W, C> {
+    pub const fn new(writer: W, codec: C) -> Self {
+        Self { writer, codec }
+    }
+
+    /// Compresses `data` and writes it as a frame on `channel`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the compressed payload
+    /// exceeds `u32::MAX` bytes, or any error from compression or the
+    /// underlying writer.
+    pub fn write(&mut self, channel: u16, data: &[u8]) -> Result<()> {
+        let payload = self.codec.compress(data)?;
+        self.write_frame(channel, &payload)
+    }
+
+    /// Writes a sentinel frame with no payload on `channel`, e.g. as a
+    /// keepalive or end-of-channel marker.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if writing fails.
+    pub fn write_sentinel(&mut self, channel: u16) -> Result<()> {
+        self.write_frame(channel, &[])
+    }
+
+    fn write_frame(&mut self, channel: u16, payload: &[u8]) -> Result<()> {
+        let len = u32::try_from(payload.len())
+            .map_err(|_| CompressionError::InvalidInput("frame too large".to_string()))?;
+
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        header[..2].copy_from_slice(&channel.to_le_bytes());
+        header[2..].copy_from_slice(&len.to_le_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(payload)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if flushing fails.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Splits a stream produced by [`Mux`] back into per-channel frames.
+#[derive(Debug)]
+pub struct Demux<R: Read, C: Codec> {
+    reader: R,
+    codec: C,
+}
+
+impl<R: Read, C: Codec> Demux<R, C> {
+    pub const fn new(reader: R, codec: C) -> Self {
+        Self { reader, codec }
+    }
+
+    /// Reads and decompresses the next frame, or returns `None` at a clean
+    /// end of stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the stream ends inside a
+    /// frame, or any error from decompression or the underlying reader.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>> {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        let mut filled = 0;
+        while filled < FRAME_HEADER_LEN {
+            match self.reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(CompressionError::CorruptedData),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let channel = u16::from_le_bytes([header[0], header[1]]);
+        let len = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(u64::from(len))
+            .read_to_end(&mut payload)?;
+        if payload.len() != len as usize {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        if payload.is_empty() {
+            return Ok(Some(Frame {
+                channel,
+                data: Vec::new(),
+                sentinel: true,
+            }));
+        }
+
+        let data = self.codec.decompress(&payload)?;
+        Ok(Some(Frame {
+            channel,
+            data,
+            sentinel: false,
+        }))
+    }
+
+    /// Reads the remaining stream, concatenating the data of each channel.
+    /// Sentinel frames are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered by [`Demux::next_frame`].
+    pub fn split(mut self) -> Result<HashMap<u16, Vec<u8>>> {
+        let mut channels: HashMap<u16, Vec<u8>> = HashMap::new();
+        while let Some(frame) = self.next_frame()? {
+            if frame.sentinel {
+                continue;
+            }
+            channels
+                .entry(frame.channel)
+                .or_default()
+                .extend_from_slice(&frame.data);
+        }
+        Ok(channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz77, Rle};
+
+    #[test]
+    fn test_mux_demux_roundtrip() {
+        let mut mux = Mux::new(Vec::new(), Lz77::new());
+        mux.write(1, b"hello").unwrap();
+        mux.write(2, b"world").unwrap();
+        mux.write(1, b" again").unwrap();
+        let stream = mux.into_inner();
+
+        let mut demux = Demux::new(stream.as_slice(), Lz77::new());
+        let frames: Vec<Frame> = std::iter::from_fn(|| demux.next_frame().unwrap()).collect();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].channel, 1);
+        assert_eq!(frames[0].data, b"hello");
+        assert_eq!(frames[1].channel, 2);
+        assert_eq!(frames[1].data, b"world");
+        assert_eq!(frames[2].data, b" again");
+    }
+
+    #[test]
+    fn test_demux_split() {
+        let mut mux = Mux::new(Vec::new(), Huffman::new());
+        mux.write(7, b"aaa").unwrap();
+        mux.write(9, b"bbb").unwrap();
+        mux.write(7, b"ccc").unwrap();
+        mux.flush().unwrap();
+        let stream = mux.into_inner();
+
+        let channels = Demux::new(stream.as_slice(), Huffman::new())
+            .split()
+            .unwrap();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[&7], b"aaaccc");
+        assert_eq!(channels[&9], b"bbb");
+    }
+
+    #[test]
+    fn test_demux_empty_stream() {
+        let mut demux = Demux::new([].as_slice(), Rle::new());
+        assert_eq!(demux.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn test_mux_empty_frame() {
+        let mut mux = Mux::new(Vec::new(), Rle::new());
+        mux.write(3, b"").unwrap();
+        let stream = mux.into_inner();
+        assert!(stream.len() > FRAME_HEADER_LEN);
+
+        let mut demux = Demux::new(stream.as_slice(), Rle::new());
+        let frame = demux.next_frame().unwrap().unwrap();
+        assert_eq!(frame.channel, 3);
+        assert!(frame.data.is_empty());
+        assert!(!frame.sentinel);
+    }
+
+    #[test]
+    fn test_mux_sentinel_frame() {
+        let mut mux = Mux::new(Vec::new(), Lz77::new());
+        mux.write(1, b"data").unwrap();
+        mux.write_sentinel(1).unwrap();
+        mux.write_sentinel(2).unwrap();
+        let stream = mux.into_inner();
+
+        let mut demux = Demux::new(stream.as_slice(), Lz77::new());
+        assert!(!demux.next_frame().unwrap().unwrap().sentinel);
+        let frame = demux.next_frame().unwrap().unwrap();
+        assert_eq!(
+            frame,
+            Frame {
+                channel: 1,
+                data: Vec::new(),
+                sentinel: true,
+            }
+        );
+
+        let channels = Demux::new(stream.as_slice(), Lz77::new()).split().unwrap();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[&1], b"data");
+    }
+
+    #[test]
+    fn test_demux_truncated_header() {
+        let mut demux = Demux::new([1u8, 0, 4].as_slice(), Rle::new());
+        assert!(matches!(
+            demux.next_frame(),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_demux_truncated_payload() {
+        let mut mux = Mux::new(Vec::new(), Rle::new());
+        mux.write(1, b"aaaa").unwrap();
+        let mut stream = mux.into_inner();
+        stream.pop();
+
+        let mut demux = Demux::new(stream.as_slice(), Rle::new());
+        assert!(matches!(
+            demux.next_frame(),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_demux_corrupted_payload() {
+        let stream = [1u8, 0, 1, 0, 0, 0, 0xFF];
+        let mut demux = Demux::new(stream.as_slice(), Rle::new());
+        assert!(demux.next_frame().is_err());
+    }
+}
@@ -0,0 +1,613 @@
+//! Compression-ratio oracle guarding format and algorithm changes.
+//!
+//! Every codec is run over a fixed set of deterministic corpora and its
+//! output size is compared with the envelope recorded in [`ENVELOPES`]. A
+//! refactor of a match finder or token format that grows any output by more
+//! than [`TOLERANCE_PERCENT`] fails the suite. After an intentional change,
+//! regenerate the table with
+//! `cargo test oracle::tests::test_print_envelopes -- --ignored --nocapture`.
+//!
+//! With the `corpus` feature the same codecs are also checked against the
+//! embedded [`crate::corpus::standard`] files and [`STANDARD_ENVELOPES`],
+//! regenerated with `cargo test --features corpus
+//! oracle::tests::test_print_standard_envelopes -- --ignored --nocapture`.
+
+use std::fmt::Write;
+
+use crate::traits::Codec;
+use crate::{
+    Arithmetic, Auto, Cm, Huffman, Lz4, Lz77, Lz77Huffman, Lzma, Lzp, Ppm, Preset, RePair, Rle,
+    ShannonFano, Snappy, Stored, Tunstall, WordHuffman, ZeroRun,
+};
+
+/// Allowed growth over a recorded size before it counts as a regression.
+pub const TOLERANCE_PERCENT: usize = 1;
+
+const CORPUS_LEN: usize = 2048;
+
+/// Recorded compressed size of one corpus under one codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope {
+    pub codec: &'static str,
+    pub corpus: &'static str,
+    pub size: usize,
+}
+
+const fn envelope(codec: &'static str, corpus: &'static str, size: usize) -> Envelope {
+    Envelope {
+        codec,
+        corpus,
+        size,
+    }
+}
+
+/// Recorded sizes, in [`codecs`] then [`corpora`] order.
+pub const ENVELOPES: &[Envelope] = &[
+    envelope("stored", "text", 2050),
+    envelope("stored", "runs", 2050),
+    envelope("stored", "numeric", 2050),
+    envelope("stored", "skewed", 2050),
+    envelope("stored", "random", 2050),
+    envelope("rle", "text", 4096),
+    envelope("rle", "runs", 96),
+    envelope("rle", "numeric", 3062),
+    envelope("rle", "skewed", 2826),
+    envelope("rle", "random", 4070),
+    envelope("rle_escaped", "text", 2068),
+    envelope("rle_escaped", "runs", 101),
+    envelope("rle_escaped", "numeric", 2067),
+    envelope("rle_escaped", "skewed", 1945),
+    envelope("rle_escaped", "random", 2068),
+    envelope("rle_varint", "text", 4096),
+    envelope("rle_varint", "runs", 96),
+    envelope("rle_varint", "numeric", 3062),
+    envelope("rle_varint", "skewed", 2826),
+    envelope("rle_varint", "random", 4070),
+    envelope("rle_golomb", "text", 2306),
+    envelope("rle_golomb", "runs", 108),
+    envelope("rle_golomb", "numeric", 1853),
+    envelope("rle_golomb", "skewed", 1707),
+    envelope("rle_golomb", "random", 2295),
+    envelope("zero_run", "text", 2053),
+    envelope("zero_run", "runs", 1534),
+    envelope("zero_run", "numeric", 2050),
+    envelope("zero_run", "skewed", 1857),
+    envelope("zero_run", "random", 2053),
+    envelope("lz77", "text", 767),
+    envelope("lz77", "runs", 373),
+    envelope("lz77", "numeric", 1784),
+    envelope("lz77", "skewed", 1111),
+    envelope("lz77", "random", 2310),
+    envelope("lz77_realtime", "text", 1117),
+    envelope("lz77_realtime", "runs", 347),
+    envelope("lz77_realtime", "numeric", 1646),
+    envelope("lz77_realtime", "skewed", 1206),
+    envelope("lz77_realtime", "random", 2310),
+    envelope("lz77_chain16", "text", 1936),
+    envelope("lz77_chain16", "runs", 411),
+    envelope("lz77_chain16", "numeric", 1883),
+    envelope("lz77_chain16", "skewed", 1740),
+    envelope("lz77_chain16", "random", 2310),
+    envelope("lz77_huffman", "text", 717),
+    envelope("lz77_huffman", "runs", 226),
+    envelope("lz77_huffman", "numeric", 1399),
+    envelope("lz77_huffman", "skewed", 844),
+    envelope("lz77_huffman", "random", 2859),
+    envelope("lz4", "text", 1135),
+    envelope("lz4", "runs", 268),
+    envelope("lz4", "numeric", 2059),
+    envelope("lz4", "skewed", 1500),
+    envelope("lz4", "random", 2059),
+    envelope("snappy", "text", 785),
+    envelope("snappy", "runs", 223),
+    envelope("snappy", "numeric", 2053),
+    envelope("snappy", "skewed", 1264),
+    envelope("snappy", "random", 2053),
+    envelope("huffman", "text", 1162),
+    envelope("huffman", "runs", 527),
+    envelope("huffman", "numeric", 1422),
+    envelope("huffman", "skewed", 550),
+    envelope("huffman", "random", 2054),
+    envelope("huffman_text", "text", 1262),
+    envelope("huffman_text", "runs", 2051),
+    envelope("huffman_text", "numeric", 2051),
+    envelope("huffman_text", "skewed", 2051),
+    envelope("huffman_text", "random", 2051),
+    envelope("word_huffman", "text", 369),
+    envelope("word_huffman", "runs", 540),
+    envelope("word_huffman", "numeric", 2194),
+    envelope("word_huffman", "skewed", 563),
+    envelope("word_huffman", "random", 2926),
+    envelope("arithmetic", "text", 1201),
+    envelope("arithmetic", "runs", 391),
+    envelope("arithmetic", "numeric", 1233),
+    envelope("arithmetic", "skewed", 590),
+    envelope("arithmetic", "random", 2082),
+    envelope("shannon_fano", "text", 1162),
+    envelope("shannon_fano", "runs", 527),
+    envelope("shannon_fano", "numeric", 1424),
+    envelope("shannon_fano", "skewed", 550),
+    envelope("shannon_fano", "random", 2208),
+    envelope("tunstall", "text", 1225),
+    envelope("tunstall", "runs", 523),
+    envelope("tunstall", "numeric", 2030),
+    envelope("tunstall", "skewed", 613),
+    envelope("tunstall", "random", 3307),
+    envelope("ppm", "text", 329),
+    envelope("ppm", "runs", 80),
+    envelope("ppm", "numeric", 1409),
+    envelope("ppm", "skewed", 630),
+    envelope("ppm", "random", 2325),
+    envelope("lzma", "text", 477),
+    envelope("lzma", "runs", 115),
+    envelope("lzma", "numeric", 887),
+    envelope("lzma", "skewed", 765),
+    envelope("lzma", "random", 2086),
+    envelope("lzp", "text", 1104),
+    envelope("lzp", "runs", 186),
+    envelope("lzp", "numeric", 2053),
+    envelope("lzp", "skewed", 2035),
+    envelope("lzp", "random", 2055),
+    envelope("repair", "text", 861),
+    envelope("repair", "runs", 342),
+    envelope("repair", "numeric", 2372),
+    envelope("repair", "skewed", 1588),
+    envelope("repair", "random", 3103),
+    envelope("cm", "text", 288),
+    envelope("cm", "runs", 73),
+    envelope("cm", "numeric", 1308),
+    envelope("cm", "skewed", 549),
+    envelope("cm", "random", 2059),
+    envelope("auto", "text", 768),
+    envelope("auto", "runs", 102),
+    envelope("auto", "numeric", 1423),
+    envelope("auto", "skewed", 551),
+    envelope("auto", "random", 2051),
+];
+
+/// Recorded sizes for the standard corpus, in [`codecs`] then
+/// [`crate::corpus::standard`] order.
+#[cfg(feature = "corpus")]
+pub const STANDARD_ENVELOPES: &[Envelope] = &[
+    envelope("stored", "text.txt", 2554),
+    envelope("stored", "source.c", 2410),
+    envelope("stored", "page.html", 2400),
+    envelope("stored", "manual.1", 1614),
+    envelope("stored", "table.csv", 4193),
+    envelope("stored", "samples.bin", 4098),
+    envelope("stored", "scan.pbm", 4109),
+    envelope("rle", "text.txt", 5022),
+    envelope("rle", "source.c", 4710),
+    envelope("rle", "page.html", 4298),
+    envelope("rle", "manual.1", 3158),
+    envelope("rle", "table.csv", 8278),
+    envelope("rle", "samples.bin", 8152),
+    envelope("rle", "scan.pbm", 3628),
+    envelope("rle_escaped", "text.txt", 2576),
+    envelope("rle_escaped", "source.c", 2431),
+    envelope("rle_escaped", "page.html", 2292),
+    envelope("rle_escaped", "manual.1", 1628),
+    envelope("rle_escaped", "table.csv", 4228),
+    envelope("rle_escaped", "samples.bin", 4131),
+    envelope("rle_escaped", "scan.pbm", 2031),
+    envelope("rle_varint", "text.txt", 5022),
+    envelope("rle_varint", "source.c", 4710),
+    envelope("rle_varint", "page.html", 4298),
+    envelope("rle_varint", "manual.1", 3158),
+    envelope("rle_varint", "table.csv", 8278),
+    envelope("rle_varint", "samples.bin", 8152),
+    envelope("rle_varint", "scan.pbm", 3633),
+    envelope("rle_golomb", "text.txt", 2838),
+    envelope("rle_golomb", "source.c", 2665),
+    envelope("rle_golomb", "page.html", 2458),
+    envelope("rle_golomb", "manual.1", 1787),
+    envelope("rle_golomb", "table.csv", 4672),
+    envelope("rle_golomb", "samples.bin", 4592),
+    envelope("rle_golomb", "scan.pbm", 2093),
+    envelope("zero_run", "text.txt", 2557),
+    envelope("zero_run", "source.c", 2413),
+    envelope("zero_run", "page.html", 2403),
+    envelope("zero_run", "manual.1", 1617),
+    envelope("zero_run", "table.csv", 4196),
+    envelope("zero_run", "samples.bin", 4101),
+    envelope("zero_run", "scan.pbm", 1953),
+    envelope("lz77", "text.txt", 1819),
+    envelope("lz77", "source.c", 996),
+    envelope("lz77", "page.html", 1179),
+    envelope("lz77", "manual.1", 1173),
+    envelope("lz77", "table.csv", 1507),
+    envelope("lz77", "samples.bin", 4603),
+    envelope("lz77", "scan.pbm", 2244),
+    envelope("lz77_realtime", "text.txt", 2443),
+    envelope("lz77_realtime", "source.c", 1810),
+    envelope("lz77_realtime", "page.html", 1805),
+    envelope("lz77_realtime", "manual.1", 1561),
+    envelope("lz77_realtime", "table.csv", 2790),
+    envelope("lz77_realtime", "samples.bin", 4614),
+    envelope("lz77_realtime", "scan.pbm", 2277),
+    envelope("lz77_chain16", "text.txt", 2813),
+    envelope("lz77_chain16", "source.c", 2585),
+    envelope("lz77_chain16", "page.html", 2300),
+    envelope("lz77_chain16", "manual.1", 1760),
+    envelope("lz77_chain16", "table.csv", 4615),
+    envelope("lz77_chain16", "samples.bin", 4614),
+    envelope("lz77_chain16", "scan.pbm", 2582),
+    envelope("lz77_huffman", "text.txt", 1685),
+    envelope("lz77_huffman", "source.c", 1026),
+    envelope("lz77_huffman", "page.html", 1208),
+    envelope("lz77_huffman", "manual.1", 1200),
+    envelope("lz77_huffman", "table.csv", 1369),
+    envelope("lz77_huffman", "samples.bin", 5662),
+    envelope("lz77_huffman", "scan.pbm", 2191),
+    envelope("lz4", "text.txt", 2085),
+    envelope("lz4", "source.c", 1067),
+    envelope("lz4", "page.html", 1296),
+    envelope("lz4", "manual.1", 1247),
+    envelope("lz4", "table.csv", 2050),
+    envelope("lz4", "samples.bin", 4116),
+    envelope("lz4", "scan.pbm", 1996),
+    envelope("snappy", "text.txt", 1975),
+    envelope("snappy", "source.c", 990),
+    envelope("snappy", "page.html", 1249),
+    envelope("snappy", "manual.1", 1204),
+    envelope("snappy", "table.csv", 1843),
+    envelope("snappy", "samples.bin", 4101),
+    envelope("snappy", "scan.pbm", 2011),
+    envelope("huffman", "text.txt", 1433),
+    envelope("huffman", "source.c", 1547),
+    envelope("huffman", "page.html", 1498),
+    envelope("huffman", "manual.1", 1096),
+    envelope("huffman", "table.csv", 2223),
+    envelope("huffman", "samples.bin", 4097),
+    envelope("huffman", "scan.pbm", 1934),
+    envelope("huffman_text", "text.txt", 1398),
+    envelope("huffman_text", "source.c", 2006),
+    envelope("huffman_text", "page.html", 2223),
+    envelope("huffman_text", "manual.1", 1196),
+    envelope("huffman_text", "table.csv", 4194),
+    envelope("huffman_text", "samples.bin", 4099),
+    envelope("huffman_text", "scan.pbm", 4110),
+    envelope("word_huffman", "text.txt", 1434),
+    envelope("word_huffman", "source.c", 939),
+    envelope("word_huffman", "page.html", 1263),
+    envelope("word_huffman", "manual.1", 1122),
+    envelope("word_huffman", "table.csv", 1663),
+    envelope("word_huffman", "samples.bin", 5200),
+    envelope("word_huffman", "scan.pbm", 2477),
+    envelope("arithmetic", "text.txt", 1483),
+    envelope("arithmetic", "source.c", 1587),
+    envelope("arithmetic", "page.html", 1495),
+    envelope("arithmetic", "manual.1", 1098),
+    envelope("arithmetic", "table.csv", 2290),
+    envelope("arithmetic", "samples.bin", 3943),
+    envelope("arithmetic", "scan.pbm", 1744),
+    envelope("shannon_fano", "text.txt", 1439),
+    envelope("shannon_fano", "source.c", 1549),
+    envelope("shannon_fano", "page.html", 1501),
+    envelope("shannon_fano", "manual.1", 1099),
+    envelope("shannon_fano", "table.csv", 2223),
+    envelope("shannon_fano", "samples.bin", 4107),
+    envelope("shannon_fano", "scan.pbm", 1937),
+    envelope("tunstall", "text.txt", 1623),
+    envelope("tunstall", "source.c", 1782),
+    envelope("tunstall", "page.html", 1804),
+    envelope("tunstall", "manual.1", 1275),
+    envelope("tunstall", "table.csv", 2524),
+    envelope("tunstall", "samples.bin", 5821),
+    envelope("tunstall", "scan.pbm", 2653),
+    envelope("ppm", "text.txt", 1153),
+    envelope("ppm", "source.c", 673),
+    envelope("ppm", "page.html", 844),
+    envelope("ppm", "manual.1", 811),
+    envelope("ppm", "table.csv", 886),
+    envelope("ppm", "samples.bin", 4420),
+    envelope("ppm", "scan.pbm", 1621),
+    envelope("lzma", "text.txt", 1356),
+    envelope("lzma", "source.c", 690),
+    envelope("lzma", "page.html", 853),
+    envelope("lzma", "manual.1", 902),
+    envelope("lzma", "table.csv", 1160),
+    envelope("lzma", "samples.bin", 3964),
+    envelope("lzma", "scan.pbm", 1626),
+    envelope("lzp", "text.txt", 2411),
+    envelope("lzp", "source.c", 1213),
+    envelope("lzp", "page.html", 1482),
+    envelope("lzp", "manual.1", 1448),
+    envelope("lzp", "table.csv", 2552),
+    envelope("lzp", "samples.bin", 4113),
+    envelope("lzp", "scan.pbm", 2075),
+    envelope("repair", "text.txt", 2436),
+    envelope("repair", "source.c", 1389),
+    envelope("repair", "page.html", 1564),
+    envelope("repair", "manual.1", 1607),
+    envelope("repair", "table.csv", 2028),
+    envelope("repair", "samples.bin", 6282),
+    envelope("repair", "scan.pbm", 2853),
+    envelope("cm", "text.txt", 1101),
+    envelope("cm", "source.c", 564),
+    envelope("cm", "page.html", 733),
+    envelope("cm", "manual.1", 752),
+    envelope("cm", "table.csv", 812),
+    envelope("cm", "samples.bin", 3989),
+    envelope("cm", "scan.pbm", 1389),
+    envelope("auto", "text.txt", 1434),
+    envelope("auto", "source.c", 997),
+    envelope("auto", "page.html", 1180),
+    envelope("auto", "manual.1", 1097),
+    envelope("auto", "table.csv", 1508),
+    envelope("auto", "samples.bin", 4098),
+    envelope("auto", "scan.pbm", 1935),
+];
+
+/// Deterministic xorshift generator, so corpora never change between runs.
+struct XorShift(u64);
+
+impl XorShift {
+    const fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.next().to_le_bytes()[0]
+    }
+}
+
+/// The canonical corpora, each `CORPUS_LEN` bytes.
+pub fn corpora() -> Vec<(&'static str, Vec<u8>)> {
+    const WORDS: [&str; 12] = [
+        "the ", "quick ", "brown ", "fox ", "jumps ", "over ", "lazy ", "dog ", "and ", "runs ",
+        "away ", "again. ",
+    ];
+
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut text = Vec::with_capacity(CORPUS_LEN);
+    while text.len() < CORPUS_LEN {
+        let word = WORDS[usize::from(rng.byte()) % WORDS.len()];
+        text.extend_from_slice(word.as_bytes());
+    }
+
+    let mut runs = Vec::with_capacity(CORPUS_LEN);
+    while runs.len() < CORPUS_LEN {
+        let len = usize::from(rng.byte() % 64) + 1;
+        let byte = rng.byte() % 4;
+        runs.extend(std::iter::repeat_n(byte, len));
+    }
+
+    let numeric: Vec<u8> = (0..CORPUS_LEN / 4)
+        .flat_map(|i| {
+            let value = u32::try_from(i * 100).unwrap_or(u32::MAX) + u32::from(rng.byte() % 8);
+            value.to_le_bytes()
+        })
+        .collect();
+
+    // Geometric distribution: each extra bit of the random word halves the
+    // probability of the symbol.
+    let skewed: Vec<u8> = (0..CORPUS_LEN)
+        .map(|_| u8::try_from(rng.next().trailing_zeros()).unwrap_or(u8::MAX))
+        .collect();
+
+    let random: Vec<u8> = (0..CORPUS_LEN).map(|_| rng.byte()).collect();
+
+    vec![
+        ("text", truncated(text)),
+        ("runs", truncated(runs)),
+        ("numeric", numeric),
+        ("skewed", skewed),
+        ("random", random),
+    ]
+}
+
+fn truncated(mut corpus: Vec<u8>) -> Vec<u8> {
+    corpus.truncate(CORPUS_LEN);
+    corpus
+}
+
+/// The codecs under guard, labeled as in [`ENVELOPES`].
+pub fn codecs() -> Vec<(&'static str, Box<dyn Codec>)> {
+    vec![
+        ("stored", Box::new(Stored::new())),
+        ("rle", Box::new(Rle::new())),
+        ("rle_escaped", Box::new(Rle::escaped())),
+        ("rle_varint", Box::new(Rle::varint())),
+        ("rle_golomb", Box::new(Rle::golomb())),
+        ("zero_run", Box::new(ZeroRun::new())),
+        ("lz77", Box::new(Lz77::new())),
+        ("lz77_realtime", Box::new(Lz77::realtime())),
+        (
+            "lz77_chain16",
+            Box::new(Lz77::new().with_max_chain_length(16)),
+        ),
+        ("lz77_huffman", Box::new(Lz77Huffman::new())),
+        ("lz4", Box::new(Lz4::new())),
+        ("snappy", Box::new(Snappy::new())),
+        ("huffman", Box::new(Huffman::new())),
+        (
+            "huffman_text",
+            Box::new(Huffman::with_preset(Preset::EnglishText)),
+        ),
+        ("word_huffman", Box::new(WordHuffman::new())),
+        ("arithmetic", Box::new(Arithmetic::new())),
+        ("shannon_fano", Box::new(ShannonFano::new())),
+        ("tunstall", Box::new(Tunstall::new())),
+        ("ppm", Box::new(Ppm::new())),
+        ("lzma", Box::new(Lzma::new())),
+        ("lzp", Box::new(Lzp::new())),
+        ("repair", Box::new(RePair::new())),
+        ("cm", Box::new(Cm::new())),
+        ("auto", Box::new(Auto::new())),
+    ]
+}
+
+/// Compresses every corpus with every codec, checking that each output
+/// round-trips.
+///
+/// # Panics
+///
+/// Panics if a codec fails or does not reproduce its input.
+pub fn measure() -> Vec<Envelope> {
+    measure_corpora(&corpora())
+}
+
+/// Like [`measure`], over the embedded standard corpus.
+///
+/// # Panics
+///
+/// Panics if a codec fails or does not reproduce its input.
+#[cfg(feature = "corpus")]
+pub fn measure_standard() -> Vec<Envelope> {
+    let corpora: Vec<_> = crate::corpus::standard()
+        .iter()
+        .map(|file| (file.name, file.data.to_vec()))
+        .collect();
+    measure_corpora(&corpora)
+}
+
+fn measure_corpora(corpora: &[(&'static str, Vec<u8>)]) -> Vec<Envelope> {
+    let mut envelopes = Vec::new();
+    for (codec_name, codec) in codecs() {
+        for (corpus_name, corpus) in corpora {
+            let compressed = codec.compress(corpus).unwrap();
+            let restored = codec.decompress(&compressed).unwrap();
+            assert!(
+                restored == *corpus,
+                "{codec_name} does not round-trip {corpus_name}"
+            );
+            envelopes.push(envelope(codec_name, corpus_name, compressed.len()));
+        }
+    }
+    envelopes
+}
+
+/// Describes every measurement that exceeds its recorded envelope by more
+/// than the tolerance, or that has no envelope at all.
+pub fn regressions(recorded: &[Envelope], measured: &[Envelope]) -> Vec<String> {
+    let mut failures = Vec::new();
+    for current in measured {
+        let Some(expected) = recorded
+            .iter()
+            .find(|e| e.codec == current.codec && e.corpus == current.corpus)
+        else {
+            failures.push(format!(
+                "{}/{}: no recorded envelope",
+                current.codec, current.corpus
+            ));
+            continue;
+        };
+        let limit = expected.size + expected.size * TOLERANCE_PERCENT / 100;
+        if current.size > limit {
+            failures.push(format!(
+                "{}/{}: {} bytes, recorded {} (limit {limit})",
+                current.codec, current.corpus, current.size, expected.size
+            ));
+        }
+    }
+    failures
+}
+
+/// Renders `envelopes` as the source of a table such as [`ENVELOPES`].
+pub fn render(table: &str, envelopes: &[Envelope]) -> String {
+    let mut out = format!("pub const {table}: &[Envelope] = &[\n");
+    for e in envelopes {
+        let _ = writeln!(
+            out,
+            "    envelope({:?}, {:?}, {}),",
+            e.codec, e.corpus, e.size
+        );
+    }
+    out.push_str("];\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_ratio_regressions() {
+        let failures = regressions(ENVELOPES, &measure());
+        assert!(
+            failures.is_empty(),
+            "compression ratio regressed:\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_corpora_are_deterministic() {
+        let first = corpora();
+        assert_eq!(first, corpora());
+        assert!(first.iter().all(|(_, data)| data.len() == CORPUS_LEN));
+    }
+
+    #[test]
+    fn test_envelopes_cover_codecs_and_corpora() {
+        let corpora = corpora();
+        let labels: Vec<(&str, &str)> = codecs()
+            .iter()
+            .flat_map(|(codec, _)| corpora.iter().map(move |(corpus, _)| (*codec, *corpus)))
+            .collect();
+        let recorded: Vec<(&str, &str)> = ENVELOPES.iter().map(|e| (e.codec, e.corpus)).collect();
+        assert_eq!(recorded, labels);
+    }
+
+    #[test]
+    fn test_regression_detected() {
+        let recorded = [envelope("rle", "text", 100)];
+        assert!(regressions(&recorded, &[envelope("rle", "text", 101)]).is_empty());
+        let failures = regressions(&recorded, &[envelope("rle", "text", 102)]);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("rle/text"));
+    }
+
+    #[test]
+    fn test_improvement_is_not_a_failure() {
+        let recorded = [envelope("lz77", "runs", 500)];
+        assert!(regressions(&recorded, &[envelope("lz77", "runs", 10)]).is_empty());
+    }
+
+    #[test]
+    fn test_missing_envelope_reported() {
+        let failures = regressions(&[], &[envelope("huffman", "random", 1)]);
+        assert_eq!(failures, ["huffman/random: no recorded envelope"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render("ENVELOPES", &[envelope("rle", "runs", 42)]);
+        assert!(rendered.contains("envelope(\"rle\", \"runs\", 42),"));
+    }
+
+    #[test]
+    #[ignore = "prints the envelope table for regeneration"]
+    fn test_print_envelopes() {
+        print!("{}", render("ENVELOPES", &measure()));
+    }
+
+    #[cfg(feature = "corpus")]
+    #[test]
+    fn test_no_ratio_regressions_on_standard_corpus() {
+        let measured = measure_standard();
+        let labels: Vec<(&str, &str)> = measured.iter().map(|e| (e.codec, e.corpus)).collect();
+        let recorded: Vec<(&str, &str)> = STANDARD_ENVELOPES
+            .iter()
+            .map(|e| (e.codec, e.corpus))
+            .collect();
+        assert_eq!(recorded, labels);
+        let failures = regressions(STANDARD_ENVELOPES, &measured);
+        assert!(
+            failures.is_empty(),
+            "compression ratio regressed:\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[cfg(feature = "corpus")]
+    #[test]
+    #[ignore = "prints the standard envelope table for regeneration"]
+    fn test_print_standard_envelopes() {
+        print!("{}", render("STANDARD_ENVELOPES", &measure_standard()));
+    }
+}
@@ -0,0 +1,1247 @@
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::error::{CompressionError, Result};
+use crate::limits::DecompressionLimits;
+use crate::traits::{Codec, Compressor, Decompressor};
+
+pub const BLOCK_HEADER_LEN: usize = 4;
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Settings for [`copy_compress`] and [`copy_decompress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyOptions {
+    buffer_size: usize,
+    multi_member: bool,
+    limits: DecompressionLimits,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CopyOptions {
+    /// Options with a 64 KiB buffer.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            multi_member: false,
+            limits: DecompressionLimits::new(),
+        }
+    }
+
+    /// Compresses the input in blocks of `buffer_size` bytes, at least one.
+    /// Larger blocks compress better; smaller ones hold less memory and let
+    /// output start flowing sooner.
+    #[must_use]
+    pub const fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = if buffer_size == 0 { 1 } else { buffer_size };
+        self
+    }
+
+    #[must_use]
+    pub const fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Whether decompression carries on past an end marker with the next
+    /// stream, as gzip does with concatenated members, until the input is
+    /// exhausted. Off by default, so a stream can be followed by other data;
+    /// turn it on for files that several writers append whole streams to.
+    #[must_use]
+    pub const fn with_multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    #[must_use]
+    pub const fn multi_member(&self) -> bool {
+        self.multi_member
+    }
+
+    /// Bounds decompression by `limits`, applied to each block and to the
+    /// stream's totals, so a stream from an untrusted source cannot expand
+    /// without end.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        &self.limits
+    }
+}
+
+/// Totals reported by [`copy_compress`] and [`copy_decompress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyStats {
+    /// Bytes read from the reader.
+    pub bytes_in: u64,
+    /// Bytes written to the writer.
+    pub bytes_out: u64,
+    /// Compressed blocks written or read.
+    pub blocks: u64,
+    pub elapsed: Duration,
+}
+
+impl CopyStats {
+    /// Uncompressed size over compressed size, whichever direction the copy
+    /// ran; 0.0 if nothing was compressed.
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        let (plain, packed) = if self.bytes_in > self.bytes_out {
+            (self.bytes_in, self.bytes_out)
+        } else {
+            (self.bytes_out, self.bytes_in)
+        };
+        if packed == 0 {
+            return 0.0;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = plain as f64 / packed as f64;
+        ratio
+    }
+}
+
+/// Reads until `buffer` is full or the reader is exhausted, returning the
+/// number of bytes read.
+fn fill(reader: &mut impl Read, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// The `[payload length: u32]` prefix of a block.
+fn block_header(payload_len: usize) -> Result<[u8; BLOCK_HEADER_LEN]> {
+    u32::try_from(payload_len)
+        .map(u32::to_le_bytes)
+        .map_err(|_| CompressionError::InvalidInput("block too large".to_string()))
+}
+
+/// Reads one block and decompresses it, returning its payload length and
+/// data, or `None` at the end marker. Reads nothing past the block.
+fn read_block<C: Decompressor + ?Sized>(
+    reader: &mut impl Read,
+    codec: &C,
+    limits: &DecompressionLimits,
+) -> Result<Option<(usize, Vec<u8>)>> {
+    let mut header = [0; BLOCK_HEADER_LEN];
+    if fill(reader, &mut header)? != BLOCK_HEADER_LEN {
+        return Err(CompressionError::CorruptedData);
+    }
+    let len = u32::from_le_bytes(header);
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let mut payload = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut payload)?;
+    if payload.len() as u64 != u64::from(len) {
+        return Err(CompressionError::CorruptedData);
+    }
+    let data = codec.decompress_with_limits(&payload, limits)?;
+    Ok(Some((payload.len(), data)))
+}
+
+/// [`read_block`] for buffered readers: a block already whole in the
+/// reader's buffer is decompressed in place instead of being copied out
+/// first. Blocks split across refills take the copying path.
+fn read_block_buf<C: Decompressor + ?Sized>(
+    reader: &mut impl BufRead,
+    codec: &C,
+    limits: &DecompressionLimits,
+) -> Result<Option<(usize, Vec<u8>)>> {
+    let available = reader.fill_buf()?;
+    if let Some(header) = available.first_chunk::<BLOCK_HEADER_LEN>() {
+        let len = u32::from_le_bytes(*header) as usize;
+        if len == 0 {
+            reader.consume(BLOCK_HEADER_LEN);
+            return Ok(None);
+        }
+        if let Some(payload) = available.get(BLOCK_HEADER_LEN..BLOCK_HEADER_LEN + len) {
+            let data = codec.decompress_with_limits(payload, limits)?;
+            reader.consume(BLOCK_HEADER_LEN + len);
+            return Ok(Some((len, data)));
+        }
+    }
+    read_block(reader, codec, limits)
+}
+
+/// Writes the data of every block `next_block` yields, up to the end
+/// marker, and totals them. After each end marker, `next_member` says
+/// whether another stream follows. The totals are held to `limits`.
+fn copy_blocks<R>(
+    reader: &mut R,
+    mut writer: impl Write,
+    limits: &DecompressionLimits,
+    mut next_block: impl FnMut(&mut R) -> Result<Option<(usize, Vec<u8>)>>,
+    mut next_member: impl FnMut(&mut R) -> Result<bool>,
+) -> Result<CopyStats> {
+    let start = Instant::now();
+    let mut stats = CopyStats {
+        bytes_in: 0,
+        bytes_out: 0,
+        blocks: 0,
+        elapsed: Duration::ZERO,
+    };
+    loop {
+        while let Some((len, data)) = next_block(reader)? {
+            stats.bytes_in += (BLOCK_HEADER_LEN + len) as u64;
+            stats.bytes_out += data.len() as u64;
+            stats.blocks += 1;
+            limits.check(stats.bytes_in, stats.bytes_out)?;
+            writer.write_all(&data)?;
+        }
+        stats.bytes_in += BLOCK_HEADER_LEN as u64;
+        if !next_member(reader)? {
+            break;
+        }
+    }
+    writer.flush()?;
+    stats.elapsed = start.elapsed();
+    Ok(stats)
+}
+
+/// Compresses everything `reader` yields and writes it to `writer`, one
+/// block per buffer, until end of input.
+///
+/// Only one buffer of input and its compressed form are held at a time, so
+/// this suits data piped through stdin and stdout as well as files. Each
+/// block is written as `[payload length: u32][payload]`, little-endian, and
+/// a zero length, which no codec produces, ends the stream; read it back
+/// with [`copy_decompress`] using the same codec. The writer is flushed
+/// before returning.
+///
+/// # Errors
+///
+/// Returns `CompressionError::Io` if reading or writing fails,
+/// `CompressionError::InvalidInput` if a block compresses to more than
+/// `u32::MAX` bytes, or any error from the codec.
+pub fn copy_compress<C: Codec + ?Sized>(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let start = Instant::now();
+    let mut buffer = vec![0; options.buffer_size];
+    let mut stats = CopyStats {
+        bytes_in: 0,
+        bytes_out: 0,
+        blocks: 0,
+        elapsed: Duration::ZERO,
+    };
+    loop {
+        let len = fill(&mut reader, &mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        write_block(&mut writer, codec, &buffer[..len], &mut stats)?;
+        if len < buffer.len() {
+            break;
+        }
+    }
+    finish_stream(writer, start, stats)
+}
+
+/// [`copy_compress`] for input already in memory, such as a memory-mapped
+/// file.
+///
+/// Blocks are compressed straight from `input` rather than copied into a
+/// buffer first. The output is the same as [`copy_compress`]'s and is
+/// written block by block as it is produced.
+///
+/// # Errors
+///
+/// Returns the same errors as [`copy_compress`], except read errors.
+pub fn copy_compress_slice<C: Codec + ?Sized>(
+    input: &[u8],
+    mut writer: impl Write,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let start = Instant::now();
+    let mut stats = CopyStats {
+        bytes_in: 0,
+        bytes_out: 0,
+        blocks: 0,
+        elapsed: Duration::ZERO,
+    };
+    for block in input.chunks(options.buffer_size) {
+        write_block(&mut writer, codec, block, &mut stats)?;
+    }
+    finish_stream(writer, start, stats)
+}
+
+/// Compresses `block` and writes it with its length prefix.
+fn write_block<C: Codec + ?Sized>(
+    writer: &mut impl Write,
+    codec: &C,
+    block: &[u8],
+    stats: &mut CopyStats,
+) -> Result<()> {
+    let payload = codec.compress(block)?;
+    writer.write_all(&block_header(payload.len())?)?;
+    writer.write_all(&payload)?;
+    stats.bytes_in += block.len() as u64;
+    stats.bytes_out += (BLOCK_HEADER_LEN + payload.len()) as u64;
+    stats.blocks += 1;
+    Ok(())
+}
+
+/// Writes the end marker, flushes, and completes `stats`.
+fn finish_stream(
+    mut writer: impl Write,
+    start: Instant,
+    mut stats: CopyStats,
+) -> Result<CopyStats> {
+    writer.write_all(&[0; BLOCK_HEADER_LEN])?;
+    writer.flush()?;
+    stats.bytes_out += BLOCK_HEADER_LEN as u64;
+    stats.elapsed = start.elapsed();
+    Ok(stats)
+}
+
+/// Reverses [`copy_compress`]: decompresses blocks from `reader` and
+/// writes the data to `writer` until the end marker.
+///
+/// Nothing after the end marker is read, so a stream can be followed by
+/// other data in the same pipe. With
+/// [`with_multi_member`](CopyOptions::with_multi_member), streams written
+/// back to back are instead all decompressed, in order, until end of input.
+/// The buffer size only matters when compressing; it is accepted here so
+/// both directions share options.
+///
+/// # Errors
+///
+/// Returns `CompressionError::CorruptedData` if the input ends before the
+/// end marker, or, reading several streams, inside a later one,
+/// `CompressionError::Io` if reading or writing fails, or any error from
+/// the codec.
+pub fn copy_decompress<C: Codec + ?Sized>(
+    mut reader: impl Read,
+    writer: impl Write,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    if options.multi_member {
+        // Telling the end of input from another stream takes a look ahead.
+        return copy_decompress_buf(BufReader::new(reader), writer, codec, options);
+    }
+    copy_blocks(
+        &mut reader,
+        writer,
+        &options.limits,
+        |reader| read_block(reader, codec, &options.limits),
+        |_| Ok(false),
+    )
+}
+
+/// [`copy_decompress`] for a [`BufRead`], such as a `BufReader<File>` or a
+/// byte slice.
+///
+/// Blocks that sit whole in the reader's buffer are decompressed straight
+/// from it, skipping the copy into a payload buffer; give the reader a
+/// buffer larger than the compressed blocks to make that the common case.
+/// The output is the same as [`copy_decompress`]'s, and the reader is left
+/// just after the end marker, or at end of input when reading several
+/// streams.
+///
+/// # Errors
+///
+/// Returns the same errors as [`copy_decompress`].
+pub fn copy_decompress_buf<C: Codec + ?Sized>(
+    mut reader: impl BufRead,
+    writer: impl Write,
+    codec: &C,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    copy_blocks(
+        &mut reader,
+        writer,
+        &options.limits,
+        |reader| read_block_buf(reader, codec, &options.limits),
+        |reader| Ok(options.multi_member && !reader.fill_buf()?.is_empty()),
+    )
+}
+
+/// Push-based counterpart of [`copy_compress`], for data that arrives in
+/// pieces, such as messages from a socket or a channel.
+///
+/// Each [`write`](Self::write) appends to a pending block and compresses it
+/// once it reaches the buffer size, so no more than one block of input is
+/// held however much is written. [`finish`](Self::finish) compresses what
+/// is left and adds the end marker. The output is the stream
+/// [`copy_compress`] would write for the same bytes and buffer size, so
+/// [`copy_decompress`] reads it back. Drain it as it grows with
+/// [`take_output`](Self::take_output).
+#[derive(Debug, Clone)]
+pub struct StreamingCompressor<C> {
+    codec: C,
+    buffer_size: usize,
+    pending: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl<C: Compressor> StreamingCompressor<C> {
+    /// Creates a stream with 64 KiB blocks.
+    #[must_use]
+    pub const fn new(codec: C) -> Self {
+        Self::with_options(codec, &CopyOptions::new())
+    }
+
+    /// Creates a stream with blocks of `options`' buffer size.
+    #[must_use]
+    pub const fn with_options(codec: C, options: &CopyOptions) -> Self {
+        Self {
+            codec,
+            buffer_size: options.buffer_size,
+            pending: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Adds `chunk` to the stream, compressing every block it completes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if a block compresses to
+    /// more than `u32::MAX` bytes, or any error from the codec. Bytes of
+    /// `chunk` after the failed block are not added.
+    pub fn write(&mut self, mut chunk: &[u8]) -> Result<()> {
+        while !chunk.is_empty() {
+            let take = (self.buffer_size - self.pending.len()).min(chunk.len());
+            self.pending.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            if self.pending.len() == self.buffer_size {
+                self.flush_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the compressed bytes produced so far.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Bytes written but not yet compressed.
+    #[must_use]
+    pub const fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Compresses the pending bytes, ends the stream and returns whatever
+    /// output has not been taken.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`write`](Self::write).
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        if !self.pending.is_empty() {
+            self.flush_block()?;
+        }
+        self.output.extend_from_slice(&[0; BLOCK_HEADER_LEN]);
+        Ok(self.output)
+    }
+
+    fn flush_block(&mut self) -> Result<()> {
+        let payload = self.codec.compress(&self.pending)?;
+        self.output.extend_from_slice(&block_header(payload.len())?);
+        self.output.extend_from_slice(&payload);
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// A [`Write`] that compresses everything written to it into `writer`.
+///
+/// Bytes are gathered into blocks of the buffer size, as in
+/// [`StreamingCompressor`], and each full block is compressed and passed
+/// on. [`finish`](Self::finish) writes the last partial block and the end
+/// marker; a writer dropped without it leaves the stream incomplete. The
+/// result reads back with [`copy_decompress`] or [`StreamingDecompressor`].
+/// [`flush`](Write::flush) flushes `writer` but does not cut the pending
+/// block short. Codec errors surface as `ErrorKind::InvalidData`.
+#[derive(Debug)]
+pub struct CompressorWriter<W: Write, C: Compressor> {
+    writer: W,
+    stream: StreamingCompressor<C>,
+}
+
+impl<W: Write, C: Compressor> CompressorWriter<W, C> {
+    /// Creates a writer with 64 KiB blocks.
+    pub const fn new(writer: W, codec: C) -> Self {
+        Self::with_options(writer, codec, &CopyOptions::new())
+    }
+
+    /// Creates a writer with blocks of `options`' buffer size.
+    pub const fn with_options(writer: W, codec: C, options: &CopyOptions) -> Self {
+        Self {
+            writer,
+            stream: StreamingCompressor::with_options(codec, options),
+        }
+    }
+
+    pub const fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Compresses the pending bytes, writes the end marker, flushes and
+    /// returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if writing fails, or any error from
+    /// the codec.
+    pub fn finish(mut self) -> Result<W> {
+        let tail = self.stream.finish()?;
+        self.writer.write_all(&tail)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write, C: Compressor> Write for CompressorWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buf)?;
+        self.writer.write_all(&self.stream.take_output())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A [`Read`] that decompresses a stream from `reader` as it is read.
+///
+/// Blocks are read and decompressed one at a time, only when the caller
+/// has taken all of the previous one, so at most one block is held whatever
+/// the stream's length. Reads return 0 after the end marker, and nothing
+/// past it is read from `reader`. Corrupted or truncated input fails with
+/// `ErrorKind::InvalidData`.
+///
+/// It is also a [`BufRead`] whose buffer is the current decompressed block,
+/// so `fill_buf`, `read_line` and `lines` work without another copy, and
+/// `read_to_end` appends whole blocks rather than going through a scratch
+/// buffer.
+#[derive(Debug)]
+pub struct DecompressorReader<R: Read, C: Decompressor> {
+    reader: R,
+    codec: C,
+    limits: DecompressionLimits,
+    block: Vec<u8>,
+    position: usize,
+    ended: bool,
+}
+
+impl<R: Read, C: Decompressor> DecompressorReader<R, C> {
+    pub const fn new(reader: R, codec: C) -> Self {
+        Self {
+            reader,
+            codec,
+            limits: DecompressionLimits::new(),
+            block: Vec::new(),
+            position: 0,
+            ended: false,
+        }
+    }
+
+    /// Bounds what each block may decompress to, for streams from an
+    /// untrusted source. Unlimited by default.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        &self.limits
+    }
+
+    /// Returns the underlying reader, positioned after the last block read.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read, C: Decompressor> Read for DecompressorReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let start = buf.len();
+        buf.extend_from_slice(&self.block[self.position..]);
+        self.position = self.block.len();
+        while !self.ended {
+            match read_block(&mut self.reader, &self.codec, &self.limits)? {
+                Some((_, data)) if buf.is_empty() => *buf = data,
+                Some((_, data)) => buf.extend_from_slice(&data),
+                None => self.ended = true,
+            }
+        }
+        Ok(buf.len() - start)
+    }
+}
+
+impl<R: Read, C: Decompressor> BufRead for DecompressorReader<R, C> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        while self.position == self.block.len() && !self.ended {
+            match read_block(&mut self.reader, &self.codec, &self.limits)? {
+                Some((_, data)) => {
+                    self.block = data;
+                    self.position = 0;
+                }
+                None => self.ended = true,
+            }
+        }
+        Ok(&self.block[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.block.len());
+    }
+}
+
+/// Pull-based counterpart of [`copy_decompress`], for compressed data that
+/// arrives in pieces of any size, such as reads from a non-blocking socket.
+///
+/// [`feed`](Self::feed) hands over bytes as they come; a block header or
+/// payload split across pieces is held until the rest arrives, and each
+/// block is decompressed once it is complete. [`next_output`](Self::next_output)
+/// copies decompressed data out as space allows. After the end marker the
+/// stream is over: [`is_finished`](Self::is_finished) turns true once all
+/// output is taken, and bytes fed past the marker are left in
+/// [`remaining_input`](Self::remaining_input).
+#[derive(Debug, Clone)]
+pub struct StreamingDecompressor<C> {
+    codec: C,
+    limits: DecompressionLimits,
+    input: Vec<u8>,
+    /// Bytes at the front of `input` already decoded.
+    consumed: usize,
+    block: Vec<u8>,
+    position: usize,
+    ended: bool,
+}
+
+impl<C: Decompressor> StreamingDecompressor<C> {
+    #[must_use]
+    pub const fn new(codec: C) -> Self {
+        Self {
+            codec,
+            limits: DecompressionLimits::new(),
+            input: Vec::new(),
+            consumed: 0,
+            block: Vec::new(),
+            position: 0,
+            ended: false,
+        }
+    }
+
+    /// Bounds what each block may decompress to, for streams from an
+    /// untrusted source. Unlimited by default.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> &DecompressionLimits {
+        &self.limits
+    }
+
+    /// Adds the next piece of the compressed stream.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    /// Copies as much decompressed data into `out` as is ready, returning
+    /// how many bytes were written.
+    ///
+    /// Returns 0 when `out` is empty, when the next block has not fully
+    /// arrived yet, or when the stream is finished; tell the last two apart
+    /// with [`is_finished`](Self::is_finished).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the codec. The failed block is dropped.
+    pub fn next_output(&mut self, out: &mut [u8]) -> Result<usize> {
+        let mut written = 0;
+        while written < out.len() {
+            if self.position == self.block.len() && !self.next_block()? {
+                break;
+            }
+            let n = (self.block.len() - self.position).min(out.len() - written);
+            out[written..written + n]
+                .copy_from_slice(&self.block[self.position..self.position + n]);
+            self.position += n;
+            written += n;
+        }
+        Ok(written)
+    }
+
+    /// Decompresses the next block if it has fully arrived, returning
+    /// whether one was.
+    fn next_block(&mut self) -> Result<bool> {
+        if self.ended {
+            return Ok(false);
+        }
+        let unread = &self.input[self.consumed..];
+        let Some(header) = unread.first_chunk::<BLOCK_HEADER_LEN>() else {
+            return Ok(false);
+        };
+        let len = u32::from_le_bytes(*header) as usize;
+        if len == 0 {
+            self.ended = true;
+            self.consume(BLOCK_HEADER_LEN);
+            return Ok(false);
+        }
+        let Some(payload) = unread.get(BLOCK_HEADER_LEN..BLOCK_HEADER_LEN + len) else {
+            return Ok(false);
+        };
+        let decoded = self.codec.decompress_with_limits(payload, &self.limits);
+        self.consume(BLOCK_HEADER_LEN + len);
+        self.block = decoded?;
+        self.position = 0;
+        Ok(true)
+    }
+
+    /// Marks `len` more input bytes as decoded. The decoded prefix is only
+    /// dropped once it outgrows the rest of the buffer, so each fed byte is
+    /// moved a bounded number of times however many blocks it spans.
+    fn consume(&mut self, len: usize) {
+        self.consumed += len;
+        if self.consumed > self.input.len() / 2 {
+            self.input.drain(..self.consumed);
+            self.consumed = 0;
+        }
+    }
+
+    /// Whether the end marker has been read and all output taken.
+    #[must_use]
+    pub const fn is_finished(&self) -> bool {
+        self.ended && self.position == self.block.len()
+    }
+
+    /// Fed bytes not yet decoded; once finished, whatever followed the end
+    /// marker.
+    #[must_use]
+    pub fn remaining_input(&self) -> &[u8] {
+        &self.input[self.consumed..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz4};
+
+    /// Reader that hands out at most `step` bytes per call and is
+    /// interrupted once, as pipes may be.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+        interrupted: bool,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if !self.interrupted {
+                self.interrupted = true;
+                return Err(ErrorKind::Interrupted.into());
+            }
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn text(len: usize) -> Vec<u8> {
+        b"pipe the logs through the compressor; ".repeat(len / 38 + 1)[..len].to_vec()
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(CopyOptions::default().buffer_size(), 64 * 1024);
+        assert_eq!(CopyOptions::new().with_buffer_size(0).buffer_size(), 1);
+    }
+
+    #[test]
+    fn test_roundtrip_blocks_and_stats() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(4096);
+        let mut compressed = Vec::new();
+        let stats =
+            copy_compress(input.as_slice(), &mut compressed, &Lz4::new(), &options).unwrap();
+        assert_eq!(stats.bytes_in, 10_000);
+        assert_eq!(stats.bytes_out, compressed.len() as u64);
+        assert_eq!(stats.blocks, 3);
+        assert!(stats.ratio() > 5.0, "{}", stats.ratio());
+
+        let mut output = Vec::new();
+        let back =
+            copy_decompress(compressed.as_slice(), &mut output, &Lz4::new(), &options).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(back.bytes_in, stats.bytes_out);
+        assert_eq!(back.bytes_out, stats.bytes_in);
+        assert_eq!(back.blocks, 3);
+        assert!((back.ratio() - stats.ratio()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_reads_fill_blocks() {
+        let input = text(5000);
+        let reader = Trickle {
+            data: &input,
+            step: 7,
+            interrupted: false,
+        };
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut compressed = Vec::new();
+        let stats = copy_compress(reader, &mut compressed, &Huffman::new(), &options).unwrap();
+        assert_eq!(stats.blocks, 5);
+
+        let reader = Trickle {
+            data: &compressed,
+            step: 3,
+            interrupted: false,
+        };
+        let mut output = Vec::new();
+        copy_decompress(reader, &mut output, &Huffman::new(), &options).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let mut compressed = Vec::new();
+        let stats =
+            copy_compress(&[][..], &mut compressed, &Lz4::new(), &CopyOptions::new()).unwrap();
+        assert_eq!(compressed, [0; BLOCK_HEADER_LEN]);
+        assert_eq!((stats.blocks, stats.ratio()), (0, 0.0));
+        let mut output = Vec::new();
+        copy_decompress(
+            compressed.as_slice(),
+            &mut output,
+            &Lz4::new(),
+            &CopyOptions::new(),
+        )
+        .unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_stops_at_end_marker() {
+        let mut stream = Vec::new();
+        copy_compress(&b"first"[..], &mut stream, &Lz4::new(), &CopyOptions::new()).unwrap();
+        let first_len = stream.len();
+        stream.extend_from_slice(b"trailer");
+        let mut reader = stream.as_slice();
+        let mut output = Vec::new();
+        copy_decompress(&mut reader, &mut output, &Lz4::new(), &CopyOptions::new()).unwrap();
+        assert_eq!(output, b"first");
+        assert_eq!(reader, b"trailer");
+        assert_eq!(stream.len() - reader.len(), first_len);
+    }
+
+    #[test]
+    fn test_limits() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Lz4::new(), &options).unwrap();
+
+        let limits = DecompressionLimits::new().with_max_output_size(4500);
+        let options = options.with_limits(limits);
+        assert_eq!(options.limits(), &limits);
+        let mut output = Vec::new();
+        let result = copy_decompress(stream.as_slice(), &mut output, &Lz4::new(), &options);
+        assert!(matches!(result, Err(CompressionError::LimitExceeded(_))));
+        assert_eq!(output, input[..4000]);
+
+        let mut output = Vec::new();
+        let options = options.with_limits(DecompressionLimits::new().with_max_ratio(2));
+        let result = copy_decompress_buf(stream.as_slice(), &mut output, &Lz4::new(), &options);
+        assert!(matches!(result, Err(CompressionError::LimitExceeded(_))));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_readers_honor_limits() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Lz4::new(), &options).unwrap();
+        let limits = DecompressionLimits::new().with_max_output_size(500);
+
+        let mut reader = DecompressorReader::new(stream.as_slice(), Lz4::new()).with_limits(limits);
+        assert_eq!(reader.limits(), &limits);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut reader = DecompressorReader::new(stream.as_slice(), Lz4::new())
+            .with_limits(DecompressionLimits::new().with_max_output_size(1000));
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, input);
+
+        let mut decoder = StreamingDecompressor::new(Lz4::new()).with_limits(limits);
+        assert_eq!(decoder.limits(), &limits);
+        decoder.feed(&stream);
+        assert!(matches!(
+            decoder.next_output(&mut [0; 100]),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_multi_member() {
+        let options = CopyOptions::new()
+            .with_buffer_size(100)
+            .with_multi_member(true);
+        assert!(options.multi_member());
+        assert!(!CopyOptions::new().multi_member());
+        let mut stream = Vec::new();
+        let first =
+            copy_compress(text(250).as_slice(), &mut stream, &Lz4::new(), &options).unwrap();
+        copy_compress(&[][..], &mut stream, &Lz4::new(), &options).unwrap();
+        copy_compress(&b"appended"[..], &mut stream, &Lz4::new(), &options).unwrap();
+        let expected = [text(250), b"appended".to_vec()].concat();
+
+        let mut output = Vec::new();
+        let stats = copy_decompress(stream.as_slice(), &mut output, &Lz4::new(), &options).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(stats.bytes_in, stream.len() as u64);
+        assert_eq!(stats.blocks, first.blocks + 1);
+        let mut output = Vec::new();
+        copy_decompress_buf(stream.as_slice(), &mut output, &Lz4::new(), &options).unwrap();
+        assert_eq!(output, expected);
+
+        // Without the option only the first stream is read.
+        let mut output = Vec::new();
+        copy_decompress(
+            stream.as_slice(),
+            &mut output,
+            &Lz4::new(),
+            &CopyOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(output, text(250));
+
+        // A later stream that is cut short is still an error.
+        stream.pop();
+        let result = copy_decompress(stream.as_slice(), &mut Vec::new(), &Lz4::new(), &options);
+        assert_eq!(result.unwrap_err(), CompressionError::CorruptedData);
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let mut compressed = Vec::new();
+        let options = CopyOptions::new().with_buffer_size(100);
+        copy_compress(text(300).as_slice(), &mut compressed, &Lz4::new(), &options).unwrap();
+        for len in [0, 2, BLOCK_HEADER_LEN + 1, compressed.len() - 1] {
+            let result =
+                copy_decompress(&compressed[..len], &mut Vec::new(), &Lz4::new(), &options);
+            assert!(result.is_err(), "{len}");
+        }
+    }
+
+    #[test]
+    fn test_streaming_matches_copy_compress() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(4096);
+        let mut expected = Vec::new();
+        copy_compress(input.as_slice(), &mut expected, &Lz4::new(), &options).unwrap();
+        for piece in [1, 100, 4096, 5000, 20_000] {
+            let mut stream = StreamingCompressor::with_options(Lz4::new(), &options);
+            for chunk in input.chunks(piece) {
+                stream.write(chunk).unwrap();
+                assert!(stream.pending_len() < 4096);
+            }
+            assert_eq!(stream.finish().unwrap(), expected, "{piece}");
+        }
+
+        // A whole number of blocks ends with the marker alone.
+        let mut stream = StreamingCompressor::with_options(Lz4::new(), &options);
+        stream.write(&input[..8192]).unwrap();
+        let mut expected = Vec::new();
+        copy_compress(&input[..8192], &mut expected, &Lz4::new(), &options).unwrap();
+        assert_eq!(stream.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_streaming_output_drains() {
+        let input = text(3000);
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = StreamingCompressor::with_options(Huffman::new(), &options);
+        stream.write(&input[..999]).unwrap();
+        assert!(stream.take_output().is_empty());
+        stream.write(&input[999..2500]).unwrap();
+        let mut compressed = stream.take_output();
+        assert!(!compressed.is_empty());
+        assert!(stream.take_output().is_empty());
+        stream.write(&input[2500..]).unwrap();
+        compressed.extend(stream.finish().unwrap());
+
+        let mut output = Vec::new();
+        copy_decompress(
+            compressed.as_slice(),
+            &mut output,
+            &Huffman::new(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(output, input);
+        assert_eq!(
+            StreamingCompressor::new(Lz4::new()).finish().unwrap(),
+            [0; BLOCK_HEADER_LEN]
+        );
+    }
+
+    fn drain(stream: &mut StreamingDecompressor<impl Decompressor>, step: usize) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut out = vec![0; step];
+        loop {
+            let n = stream.next_output(&mut out).unwrap();
+            if n == 0 {
+                return output;
+            }
+            output.extend_from_slice(&out[..n]);
+        }
+    }
+
+    #[test]
+    fn test_streaming_decompressor_split_input() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(4096);
+        let mut compressed = Vec::new();
+        copy_compress(input.as_slice(), &mut compressed, &Lz4::new(), &options).unwrap();
+        for (piece, step) in [(1, 1), (3, 100), (5000, 7), (compressed.len(), 10_000)] {
+            let mut stream = StreamingDecompressor::new(Lz4::new());
+            let mut output = Vec::new();
+            for chunk in compressed.chunks(piece) {
+                stream.feed(chunk);
+                output.extend(drain(&mut stream, step));
+            }
+            assert!(stream.is_finished());
+            assert_eq!(output, input, "{piece}");
+        }
+    }
+
+    #[test]
+    fn test_streaming_decompressor_waits_for_blocks() {
+        let mut compressed = Vec::new();
+        copy_compress(
+            &b"first"[..],
+            &mut compressed,
+            &Lz4::new(),
+            &CopyOptions::new(),
+        )
+        .unwrap();
+        let mut stream = StreamingDecompressor::new(Lz4::new());
+        let mut out = [0; 16];
+        assert_eq!(stream.next_output(&mut out), Ok(0));
+        // A header alone, then a payload short of one byte.
+        stream.feed(&compressed[..2]);
+        assert_eq!(stream.next_output(&mut out), Ok(0));
+        stream.feed(&compressed[2..compressed.len() - BLOCK_HEADER_LEN - 1]);
+        assert_eq!(stream.next_output(&mut out), Ok(0));
+        assert!(!stream.is_finished());
+
+        stream.feed(&compressed[compressed.len() - BLOCK_HEADER_LEN - 1..]);
+        stream.feed(b"trailer");
+        assert_eq!(stream.next_output(&mut [0; 3]), Ok(3));
+        assert!(!stream.is_finished());
+        assert_eq!(stream.next_output(&mut out), Ok(2));
+        assert!(stream.is_finished());
+        assert_eq!(stream.next_output(&mut out), Ok(0));
+        assert_eq!(stream.remaining_input(), b"trailer");
+    }
+
+    #[test]
+    fn test_streaming_decompressor_many_blocks_at_once() {
+        let input = text(50_000);
+        let options = CopyOptions::new().with_buffer_size(64);
+        let mut compressed = Vec::new();
+        copy_compress(input.as_slice(), &mut compressed, &Lz4::new(), &options).unwrap();
+        let mut stream = StreamingDecompressor::new(Lz4::new());
+        stream.feed(&compressed);
+        stream.feed(b"trailer");
+
+        let mut output = vec![0; 10_000];
+        assert_eq!(stream.next_output(&mut output), Ok(10_000));
+        let rest = stream.remaining_input();
+        assert!(compressed.ends_with(&rest[..rest.len() - b"trailer".len()]));
+        output.extend(drain(&mut stream, 1000));
+        assert_eq!(output, input);
+        assert_eq!(stream.remaining_input(), b"trailer");
+    }
+
+    #[test]
+    fn test_streaming_decompressor_corrupted_block() {
+        let mut stream = StreamingDecompressor::new(Huffman::new());
+        stream.feed(&[3, 0, 0, 0, 0xff, 0xff, 0xff]);
+        assert!(stream.next_output(&mut [0; 8]).is_err());
+        assert!(stream.remaining_input().is_empty());
+        stream.feed(&[0; BLOCK_HEADER_LEN]);
+        assert_eq!(stream.next_output(&mut [0; 8]), Ok(0));
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn test_compressor_writer() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(4096);
+        let mut writer = CompressorWriter::with_options(Vec::new(), Lz4::new(), &options);
+        for chunk in input.chunks(333) {
+            writer.write_all(chunk).unwrap();
+        }
+        write!(writer, "{}", 42).unwrap();
+        writer.flush().unwrap();
+        assert!(!writer.get_ref().is_empty());
+        let compressed = writer.finish().unwrap();
+
+        let mut expected = Vec::new();
+        let mut full = input;
+        full.extend_from_slice(b"42");
+        copy_compress(full.as_slice(), &mut expected, &Lz4::new(), &options).unwrap();
+        assert_eq!(compressed, expected);
+
+        let empty = CompressorWriter::new(Vec::new(), Huffman::new());
+        assert_eq!(empty.finish().unwrap(), [0; BLOCK_HEADER_LEN]);
+    }
+
+    #[test]
+    fn test_decompressor_reader() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Huffman::new(), &options).unwrap();
+        stream.extend_from_slice(b"trailer");
+        let trickle = Trickle {
+            data: &stream,
+            step: 5,
+            interrupted: false,
+        };
+
+        let mut reader = DecompressorReader::new(trickle, Huffman::new());
+        let mut head = [0; 10];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(head, input[..10]);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, input[10..]);
+        assert_eq!(reader.read(&mut head).unwrap(), 0);
+        assert_eq!(reader.into_inner().data, b"trailer");
+    }
+
+    #[test]
+    fn test_decompressor_reader_truncated() {
+        let mut stream = Vec::new();
+        copy_compress(
+            text(300).as_slice(),
+            &mut stream,
+            &Lz4::new(),
+            &CopyOptions::new(),
+        )
+        .unwrap();
+        stream.truncate(stream.len() - 1);
+        let mut reader = DecompressorReader::new(stream.as_slice(), Lz4::new());
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_copy_decompress_buf() {
+        let input = text(10_000);
+        let options = CopyOptions::new().with_buffer_size(1000);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Lz4::new(), &options).unwrap();
+        let stream_len = stream.len();
+        stream.extend_from_slice(b"trailer");
+
+        let mut reader = stream.as_slice();
+        let mut output = Vec::new();
+        let stats = copy_decompress_buf(&mut reader, &mut output, &Lz4::new(), &options).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(reader, b"trailer");
+        assert_eq!(stats.bytes_in, stream_len as u64);
+        assert_eq!(stats.blocks, 10);
+
+        // Buffers smaller than a block fall back to copying.
+        for capacity in [1, 7, 300, 5000] {
+            let reader = std::io::BufReader::with_capacity(capacity, &stream[..stream_len]);
+            let mut output = Vec::new();
+            copy_decompress_buf(reader, &mut output, &Lz4::new(), &options).unwrap();
+            assert_eq!(output, input, "{capacity}");
+        }
+        let truncated = &stream[..stream_len - 1];
+        assert!(copy_decompress_buf(truncated, &mut Vec::new(), &Lz4::new(), &options).is_err());
+    }
+
+    #[test]
+    fn test_copy_compress_slice() {
+        let input = text(10_000);
+        for buffer_size in [1000, 3000, 20_000] {
+            let options = CopyOptions::new().with_buffer_size(buffer_size);
+            let mut expected = Vec::new();
+            let copied =
+                copy_compress(input.as_slice(), &mut expected, &Lz4::new(), &options).unwrap();
+            let mut stream = Vec::new();
+            let stats = copy_compress_slice(&input, &mut stream, &Lz4::new(), &options).unwrap();
+            assert_eq!(stream, expected, "{buffer_size}");
+            assert_eq!(
+                (stats.bytes_in, stats.bytes_out, stats.blocks),
+                (copied.bytes_in, copied.bytes_out, copied.blocks)
+            );
+        }
+        let mut stream = Vec::new();
+        copy_compress_slice(b"", &mut stream, &Lz4::new(), &CopyOptions::new()).unwrap();
+        assert_eq!(stream, [0; BLOCK_HEADER_LEN]);
+    }
+
+    #[test]
+    fn test_decompressor_reader_buf_read() {
+        let input = b"one\ntwo\nthree\n".repeat(100);
+        let options = CopyOptions::new().with_buffer_size(50);
+        let mut stream = Vec::new();
+        copy_compress(input.as_slice(), &mut stream, &Huffman::new(), &options).unwrap();
+
+        let reader = DecompressorReader::new(stream.as_slice(), Huffman::new());
+        let lines: Vec<String> = reader.lines().collect::<std::io::Result<_>>().unwrap();
+        assert_eq!(lines.len(), 300);
+        assert_eq!(lines[299], "three");
+
+        let mut reader = DecompressorReader::new(stream.as_slice(), Huffman::new());
+        assert_eq!(reader.fill_buf().unwrap(), &input[..50]);
+        reader.consume(4);
+        let mut output = b"prefix:".to_vec();
+        assert_eq!(reader.read_to_end(&mut output).unwrap(), input.len() - 4);
+        assert_eq!(output[..7], *b"prefix:");
+        assert_eq!(output[7..], input[4..]);
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::algorithm::Algorithm;
+use crate::error::{CompressionError, Result};
+use crate::limits::DecompressionLimits;
+use crate::traits::{Compressor, Decompressor};
+
+/// Most stages one frame can record, as the count is a single byte.
+pub const MAX_STAGES: usize = u8::MAX as usize;
+
+/// Codecs applied one after another, such as a [`Delta`](crate::Delta)
+/// filter feeding [`Lz4`](crate::Lz4).
+///
+/// Compression runs the stages in order, each compressing the previous
+/// one's output, and writes the stage list ahead of the result as
+/// [`Algorithm::tag`] bytes. Decompression reads the list back and undoes
+/// the stages last to first, so any `Pipeline` decodes any pipeline frame,
+/// whatever stages it was built with. Stages use their default settings.
+/// With no stages the input is stored as it is. Output layout:
+/// `[stage count: u8][stage tags: u8 each][last stage's output]`.
+#[derive(Debug, Default, Clone)]
+pub struct Pipeline {
+    stages: Vec<Algorithm>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline with no stages.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    /// Appends `algorithm`, to run on the output of the stages before it.
+    #[must_use]
+    pub fn with_stage(mut self, algorithm: Algorithm) -> Self {
+        self.stages.push(algorithm);
+        self
+    }
+
+    #[must_use]
+    pub fn stages(&self) -> &[Algorithm] {
+        &self.stages
+    }
+
+    /// Splits a frame into its stage list and the last stage's output.
+    fn read_header(input: &[u8]) -> Result<(Vec<Algorithm>, &[u8])> {
+        let (&count, rest) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        let tags = rest
+            .get(..usize::from(count))
+            .ok_or(CompressionError::CorruptedData)?;
+        let stages = tags
+            .iter()
+            .map(|&tag| Algorithm::from_tag(tag).ok_or(CompressionError::InvalidHeader))
+            .collect::<Result<_>>()?;
+        Ok((stages, &rest[tags.len()..]))
+    }
+
+    /// Undoes the stages last to first, each held to what `limits` allow
+    /// from the whole frame, so no stage's output can outgrow them.
+    fn decompress_limited(input: &[u8], limits: &DecompressionLimits) -> Result<Vec<u8>> {
+        let (stages, payload) = Self::read_header(input)?;
+        let stage_limits = DecompressionLimits::new()
+            .with_max_output_size(limits.max_output_for(input.len() as u64));
+        let mut data = payload.to_vec();
+        for stage in stages.iter().rev() {
+            data = stage
+                .new_codec()
+                .decompress_with_limits(&data, &stage_limits)?;
+        }
+        limits.check(input.len() as u64, data.len() as u64)?;
+        Ok(data)
+    }
+}
+
+impl Compressor for Pipeline {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let count = u8::try_from(self.stages.len()).map_err(|_| {
+            CompressionError::InvalidInput(format!(
+                "{} stages exceed the limit of {MAX_STAGES}",
+                self.stages.len()
+            ))
+        })?;
+        let mut data = input.to_vec();
+        for stage in &self.stages {
+            data = stage.new_codec().compress(&data)?;
+        }
+        let mut output = Vec::with_capacity(1 + self.stages.len() + data.len());
+        output.push(count);
+        output.extend(self.stages.iter().map(|stage| stage.tag()));
+        output.extend_from_slice(&data);
+        Ok(output)
+    }
+
+    /// Each stage's bound applied to the one before, plus the stage list.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        self.stages
+            .iter()
+            .fold(input_len, |len, stage| {
+                stage.new_codec().max_compressed_len(len)
+            })
+            .saturating_add(1 + self.stages.len())
+    }
+
+    fn name(&self) -> &'static str {
+        "Pipeline"
+    }
+}
+
+impl Decompressor for Pipeline {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Self::decompress_limited(input, &DecompressionLimits::new())
+    }
+
+    /// Known up front only when the first stage is also the last, since
+    /// earlier stages' headers are themselves compressed.
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let (stages, payload) = Self::read_header(input)?;
+        match stages.as_slice() {
+            [] => Ok(Some(payload.len() as u64)),
+            [stage] => stage.new_codec().decompressed_len(payload),
+            _ => Ok(None),
+        }
+    }
+
+    /// Each stage decodes under the limits, measured against the whole
+    /// frame, rather than only the final output being checked.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        Self::decompress_limited(input, limits)
+    }
+
+    fn name(&self) -> &'static str {
+        "Pipeline"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Delta, Lz4, Stored, varint};
+
+    fn roundtrip(pipeline: &Pipeline, input: &[u8]) -> Vec<u8> {
+        let compressed = pipeline.compress(input).unwrap();
+        assert!(compressed.len() <= pipeline.max_compressed_len(input.len()));
+        assert_eq!(Pipeline::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_pipeline_name() {
+        assert_eq!(Compressor::name(&Pipeline::new()), "Pipeline");
+        assert_eq!(Decompressor::name(&Pipeline::new()), "Pipeline");
+    }
+
+    #[test]
+    fn test_stages_match_nested_calls() {
+        let pipeline = Pipeline::new()
+            .with_stage(Algorithm::Delta)
+            .with_stage(Algorithm::Lz4);
+        assert_eq!(pipeline.stages(), [Algorithm::Delta, Algorithm::Lz4]);
+        let ramp: Vec<u8> = (0..=u8::MAX).flat_map(|byte| [byte; 3]).collect();
+        let compressed = roundtrip(&pipeline, &ramp);
+        let nested = Lz4::new()
+            .compress(&Delta::new().compress(&ramp).unwrap())
+            .unwrap();
+        assert_eq!(
+            compressed[..3],
+            [2, Algorithm::Delta.tag(), Algorithm::Lz4.tag()]
+        );
+        assert_eq!(compressed[3..], nested);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let text = b"stage after stage after stage; ".repeat(30);
+        for pipeline in [
+            Pipeline::new(),
+            Pipeline::new().with_stage(Algorithm::Huffman),
+            Pipeline::new()
+                .with_stage(Algorithm::Bwt)
+                .with_stage(Algorithm::Rle0)
+                .with_stage(Algorithm::Arithmetic),
+            Pipeline::new()
+                .with_stage(Algorithm::Lz77)
+                .with_stage(Algorithm::Lz77),
+        ] {
+            roundtrip(&pipeline, b"");
+            roundtrip(&pipeline, b"x");
+            roundtrip(&pipeline, &text);
+        }
+        assert_eq!(Pipeline::new().compress(b"raw").unwrap(), b"\x00raw");
+    }
+
+    #[test]
+    fn test_decompressed_len() {
+        let text = b"lengths survive one stage".repeat(3);
+        for (pipeline, expected) in [
+            (Pipeline::new(), Some(75)),
+            (Pipeline::new().with_stage(Algorithm::Lz4), Some(75)),
+            (
+                Pipeline::new()
+                    .with_stage(Algorithm::Lz4)
+                    .with_stage(Algorithm::Huffman),
+                None,
+            ),
+        ] {
+            let compressed = pipeline.compress(&text).unwrap();
+            assert_eq!(pipeline.decompressed_len(&compressed).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_decompress_with_limits_per_stage() {
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        // 8 GiB of zeros from ZeroRun, wrapped in a Stored stage that hides
+        // its length from the frame.
+        let mut bomb = Vec::new();
+        varint::write_u64(&mut bomb, 8 << 30);
+        varint::write_u64(&mut bomb, 8 << 30);
+        varint::write_u64(&mut bomb, 0);
+        let mut frame = vec![2, Algorithm::ZeroRun.tag(), Algorithm::Stored.tag()];
+        frame.extend_from_slice(&Stored::new().compress(&bomb).unwrap());
+        assert_eq!(Pipeline::new().decompressed_len(&frame).unwrap(), None);
+        assert!(matches!(
+            Pipeline::new().decompress_with_limits(&frame, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+
+        // Pair-format RLE records no length, so its stage is held to the
+        // limits as it decodes.
+        let pipeline = Pipeline::new()
+            .with_stage(Algorithm::Rle)
+            .with_stage(Algorithm::Stored);
+        let input = vec![0; 4096];
+        let compressed = pipeline.compress(&input).unwrap();
+        let tight = DecompressionLimits::new().with_max_output_size(1000);
+        assert!(matches!(
+            pipeline.decompress_with_limits(&compressed, &tight),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+        assert_eq!(
+            pipeline
+                .decompress_with_limits(&compressed, &limits)
+                .unwrap(),
+            input
+        );
+    }
+
+    #[test]
+    fn test_too_many_stages() {
+        let pipeline = (0..=MAX_STAGES).fold(Pipeline::new(), |pipeline, _| {
+            pipeline.with_stage(Algorithm::Stored)
+        });
+        assert!(matches!(
+            pipeline.compress(b"deep"),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let pipeline = Pipeline::new();
+        assert_eq!(
+            pipeline.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        );
+        // Two stages promised, one listed.
+        assert_eq!(
+            pipeline.decompress(&[2, Algorithm::Lz4.tag()]),
+            Err(CompressionError::CorruptedData)
+        );
+        assert_eq!(
+            pipeline.decompress(&[1, 200, 0]),
+            Err(CompressionError::InvalidHeader)
+        );
+        let mut frame = Pipeline::new()
+            .with_stage(Algorithm::Lz4)
+            .compress(b"truncate me, truncate me")
+            .unwrap();
+        frame.pop();
+        assert!(pipeline.decompress(&frame).is_err());
+    }
+}
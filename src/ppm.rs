@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::iter::Rev;
+use std::ops::RangeInclusive;
+
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::range_coder::{RangeDecoder, RangeEncoder};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+const DEFAULT_ORDER: u8 = 4;
+/// Longest context, in bytes; contexts are packed into a `u64` key.
+pub const MAX_ORDER: u8 = 8;
+/// Context order for each [`CompressionLevel`], fastest first; level 6 is
+/// [`Ppm::new`].
+const LEVELS: [u8; 9] = [1, 2, 2, 3, 3, DEFAULT_ORDER, 5, 5, 6];
+/// Symbol counts in a context are halved once their sum reaches this.
+pub const MAX_CONTEXT_TOTAL: u32 = 1 << 14;
+
+/// Symbols seen after one context, with their counts in first-seen order.
+#[derive(Debug, Default, Clone)]
+struct Context {
+    symbols: Vec<(u8, u16)>,
+    total: u32,
+}
+
+impl Context {
+    /// Symbols not excluded by a longer context, with their summed count.
+    /// The escape count (PPM method C) is the number of such symbols.
+    fn candidates(&self, excluded: &[bool; 256]) -> (Vec<(u8, u32)>, u32) {
+        let candidates: Vec<(u8, u32)> = self
+            .symbols
+            .iter()
+            .filter(|&&(symbol, _)| !excluded[usize::from(symbol)])
+            .map(|&(symbol, count)| (symbol, u32::from(count)))
+            .collect();
+        let sum = candidates.iter().map(|&(_, count)| count).sum();
+        (candidates, sum)
+    }
+
+    fn update(&mut self, symbol: u8) {
+        match self.symbols.iter_mut().find(|(s, _)| *s == symbol) {
+            Some((_, count)) => *count += 1,
+            None => self.symbols.push((symbol, 1)),
+        }
+        self.total += 1;
+        if self.total >= MAX_CONTEXT_TOTAL {
+            self.total = 0;
+            for (_, count) in &mut self.symbols {
+                *count = (*count).div_ceil(2);
+                self.total += u32::from(*count);
+            }
+        }
+    }
+}
+
+/// The adaptive context model both sides build as they go.
+#[derive(Debug)]
+struct Model {
+    order: usize,
+    contexts: Vec<HashMap<u64, Context>>,
+    history: u64,
+    seen: usize,
+}
+
+impl Model {
+    fn new(order: u8) -> Self {
+        let order = usize::from(order);
+        Self {
+            order,
+            contexts: vec![HashMap::new(); order + 1],
+            history: 0,
+            seen: 0,
+        }
+    }
+
+    /// Orders usable at the current position, longest first.
+    fn orders(&self) -> Rev<RangeInclusive<usize>> {
+        (0..=self.order.min(self.seen)).rev()
+    }
+
+    const fn key(&self, order: usize) -> u64 {
+        if order == 0 {
+            0
+        } else {
+            self.history & (u64::MAX >> (64 - 8 * order))
+        }
+    }
+
+    fn context(&self, order: usize) -> Option<&Context> {
+        self.contexts[order].get(&self.key(order))
+    }
+
+    fn update(&mut self, symbol: u8) {
+        for order in self.orders() {
+            let key = self.key(order);
+            self.contexts[order].entry(key).or_default().update(symbol);
+        }
+        self.history = (self.history << 8) | u64::from(symbol);
+        self.seen += 1;
+    }
+}
+
+/// Marks every candidate as excluded from shorter contexts.
+fn exclude(candidates: &[(u8, u32)], excluded: &mut [bool; 256], count: &mut u32) {
+    for &(symbol, _) in candidates {
+        excluded[usize::from(symbol)] = true;
+        *count += 1;
+    }
+}
+
+fn encode_symbol(model: &Model, symbol: u8, encoder: &mut RangeEncoder) {
+    let mut excluded = [false; 256];
+    let mut excluded_count = 0;
+    for order in model.orders() {
+        let Some(context) = model.context(order) else {
+            continue;
+        };
+        let (candidates, sum) = context.candidates(&excluded);
+        if candidates.is_empty() {
+            continue;
+        }
+        let escape = u32::try_from(candidates.len()).unwrap_or(u32::MAX);
+        let mut cumulative = 0;
+        for &(candidate, count) in &candidates {
+            if candidate == symbol {
+                encoder.encode(cumulative, count, sum + escape);
+                return;
+            }
+            cumulative += count;
+        }
+        encoder.encode(sum, escape, sum + escape);
+        exclude(&candidates, &mut excluded, &mut excluded_count);
+    }
+
+    // Order -1: every byte not yet excluded is equally likely.
+    let rank = excluded[..usize::from(symbol)]
+        .iter()
+        .filter(|&&excluded| !excluded)
+        .count();
+    let rank = u32::try_from(rank).unwrap_or(0);
+    encoder.encode(rank, 1, 256 - excluded_count);
+}
+
+fn decode_symbol(model: &Model, decoder: &mut RangeDecoder<'_>) -> Result<u8> {
+    let mut excluded = [false; 256];
+    let mut excluded_count = 0;
+    for order in model.orders() {
+        let Some(context) = model.context(order) else {
+            continue;
+        };
+        let (candidates, sum) = context.candidates(&excluded);
+        if candidates.is_empty() {
+            continue;
+        }
+        let escape = u32::try_from(candidates.len()).unwrap_or(u32::MAX);
+        let target = decoder.target(sum + escape)?;
+        if target < sum {
+            let mut cumulative = 0;
+            for &(symbol, count) in &candidates {
+                if target < cumulative + count {
+                    decoder.decode(cumulative, count)?;
+                    return Ok(symbol);
+                }
+                cumulative += count;
+            }
+        }
+        decoder.decode(sum, escape)?;
+        exclude(&candidates, &mut excluded, &mut excluded_count);
+    }
+
+    let target = decoder.target(256 - excluded_count)?;
+    let symbol = (0..=255u8)
+        .filter(|&symbol| !excluded[usize::from(symbol)])
+        .nth(usize::try_from(target).map_err(|_| CompressionError::CorruptedData)?)
+        .ok_or(CompressionError::CorruptedData)?;
+    decoder.decode(target, 1)?;
+    Ok(symbol)
+}
+
+/// Prediction by partial matching: an adaptive context-modelling coder for
+/// text and other data with strong local structure.
+///
+/// Each byte is predicted from the longest preceding context (up to
+/// `order` bytes) in which it has been seen, and range-coded with that
+/// context's counts. A context that has not seen the byte codes an escape,
+/// weighted by how many distinct bytes it has seen, and hands over to the
+/// next shorter context with its symbols excluded; a final uniform model
+/// covers bytes never seen before. The model is rebuilt on decompression,
+/// so nothing but the order is stored, at the cost of symmetric speed and
+/// memory growing with the number of distinct contexts. Output layout:
+/// `[order: u8][original length: varint][range-coded bytes]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Ppm {
+    order: u8,
+}
+
+impl Default for Ppm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ppm {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            order: DEFAULT_ORDER,
+        }
+    }
+
+    /// Creates a coder predicting from up to `order` preceding bytes (0 to
+    /// `MAX_ORDER`). Higher orders suit larger, more repetitive inputs.
+    #[must_use]
+    pub const fn with_order(order: u8) -> Self {
+        Self { order }
+    }
+
+    #[must_use]
+    pub const fn order(&self) -> u8 {
+        self.order
+    }
+}
+
+impl Compressor for Ppm {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if self.order > MAX_ORDER {
+            return Err(CompressionError::InvalidInput(format!(
+                "PPM order must be at most {MAX_ORDER}"
+            )));
+        }
+
+        let mut output = vec![self.order];
+        varint::write_u64(&mut output, input.len() as u64);
+
+        let mut model = Model::new(self.order);
+        let mut encoder = RangeEncoder::new();
+        for &byte in input {
+            encode_symbol(&model, byte, &mut encoder);
+            model.update(byte);
+        }
+        output.extend_from_slice(&encoder.finish());
+        Ok(output)
+    }
+
+    fn with_level(self, level: CompressionLevel) -> Self {
+        Self::with_order(level.pick(&LEVELS))
+    }
+
+    fn name(&self) -> &'static str {
+        "PPM"
+    }
+}
+
+impl Decompressor for Ppm {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match input.first() {
+            Some(&order) if order <= MAX_ORDER => varint::read_u64(input, &mut 1).map(Some),
+            _ => Err(CompressionError::CorruptedData),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "PPM"
+    }
+}
+
+impl PartialDecompressor for Ppm {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let (&order, _) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+        if order > MAX_ORDER {
+            return Err(CompressionError::CorruptedData);
+        }
+        let mut pos = 1;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        let mut model = Model::new(order);
+        let mut decoder = RangeDecoder::new(&input[pos..])?;
+        let mut output = Vec::with_capacity(original_len.min(input.len() * 16));
+        while output.len() < original_len {
+            let byte = decode_symbol(&model, &mut decoder)?;
+            model.update(byte);
+            output.push(byte);
+        }
+        Ok((output, pos + decoder.bytes_consumed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Huffman, Lz77};
+
+    fn roundtrip(codec: Ppm, input: &[u8]) -> Vec<u8> {
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn text() -> Vec<u8> {
+        let words = [
+            "the ", "quick ", "brown ", "fox ", "jumps ", "over ", "lazy ", "dog ", "and ",
+            "then ", "runs ", "away ", "from ", "a ", "sleepy ", "cat. ",
+        ];
+        let mut state = 0x1234_5678_u32;
+        let mut text = String::new();
+        while text.len() < 16 * 1024 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            text.push_str(words[(state >> 16) as usize % words.len()]);
+        }
+        text.into_bytes()
+    }
+
+    #[test]
+    fn test_ppm_new() {
+        let codec = Ppm::new();
+        assert_eq!(codec.order(), DEFAULT_ORDER);
+        assert_eq!(Ppm::default().order(), DEFAULT_ORDER);
+        assert_eq!(Ppm::with_order(2).order(), 2);
+        assert_eq!(Compressor::name(&codec), "PPM");
+        assert_eq!(Decompressor::name(&codec), "PPM");
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressed = roundtrip(Ppm::new(), &[]);
+        assert_eq!(&compressed[..2], [DEFAULT_ORDER, 0]);
+        assert!(matches!(
+            Ppm::new().decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip_all_orders() {
+        let input = text();
+        let binary: Vec<u8> = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13).to_le_bytes()[0])
+            .collect();
+        for order in 0..=MAX_ORDER {
+            let codec = Ppm::with_order(order);
+            roundtrip(codec, &input[..2048]);
+            roundtrip(codec, &binary);
+            roundtrip(codec, b"a");
+            roundtrip(codec, &[0xFF; 300]);
+        }
+    }
+
+    #[test]
+    fn test_beats_other_codecs_on_text() {
+        let input = text();
+        let ppm = roundtrip(Ppm::new(), &input).len();
+        let order0 = roundtrip(Ppm::with_order(0), &input).len();
+        let lz77 = Lz77::new().compress(&input).unwrap().len();
+        let huffman = Huffman::new().compress(&input).unwrap().len();
+        assert!(ppm * 2 < order0, "{ppm} vs {order0}");
+        assert!(ppm < lz77 && ppm < huffman, "{ppm} vs {lz77}, {huffman}");
+    }
+
+    #[test]
+    fn test_long_runs_rescale() {
+        let input = vec![b'x'; 100_000];
+        let compressed = roundtrip(Ppm::new(), &input);
+        assert!(compressed.len() < 100, "{}", compressed.len());
+    }
+
+    #[test]
+    fn test_invalid_order() {
+        let result = Ppm::with_order(MAX_ORDER + 1).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        assert!(
+            Ppm::new()
+                .decompress(&[MAX_ORDER + 1, 0, 0, 0, 0, 0, 0])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_decoder_uses_stored_order() {
+        let compressed = Ppm::with_order(6).compress(b"abracadabra").unwrap();
+        assert_eq!(
+            Ppm::with_order(1).decompress(&compressed).unwrap(),
+            b"abracadabra"
+        );
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Ppm::new();
+        let first = codec.compress(b"abracadabra").unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [b"abracadabra".to_vec(), Vec::new(), b"abracadabra".to_vec()]
+        );
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Ppm::new();
+        let compressed = codec.compress(&text()[..1024]).unwrap();
+        assert!(
+            codec
+                .decompress(&compressed[..compressed.len() - 1])
+                .is_err()
+        );
+        assert!(codec.decompress(&compressed[..3]).is_err());
+        // Length promising more bytes than the stream holds.
+        let mut longer = compressed;
+        longer[1] = 0xFF;
+        assert!(codec.decompress(&longer).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor};
+
+/// Bytes of the `[bytes per pixel: u8][stride: u32]` header.
+pub const HEADER_LEN: usize = 5;
+
+/// A PNG row filter, predicting each byte from its already-coded neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngFilter {
+    /// Bytes are stored unchanged.
+    None = 0,
+    /// Predicts from the byte one pixel to the left.
+    Sub = 1,
+    /// Predicts from the byte directly above.
+    Up = 2,
+    /// Predicts from the floored mean of the left and upper bytes.
+    Average = 3,
+    /// Predicts from whichever of left, upper and upper-left is closest to
+    /// `left + up - upper_left`.
+    Paeth = 4,
+}
+
+impl PngFilter {
+    pub const ALL: [Self; 5] = [Self::None, Self::Sub, Self::Up, Self::Average, Self::Paeth];
+
+    const fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::None),
+            1 => Some(Self::Sub),
+            2 => Some(Self::Up),
+            3 => Some(Self::Average),
+            4 => Some(Self::Paeth),
+            _ => None,
+        }
+    }
+
+    /// Predicted value from the `left`, `up` and `up_left` neighbours.
+    fn predict(self, left: u8, up: u8, up_left: u8) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Sub => left,
+            Self::Up => up,
+            Self::Average => left.midpoint(up),
+            Self::Paeth => paeth(left, up, up_left),
+        }
+    }
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let to_left = (estimate - i16::from(left)).abs();
+    let to_up = (estimate - i16::from(up)).abs();
+    let to_up_left = (estimate - i16::from(up_left)).abs();
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Neighbours of byte `i` of a row: left, up and upper-left, with bytes
+/// outside the image treated as zero.
+fn neighbours(row: &[u8], previous: &[u8], i: usize, bpp: usize) -> (u8, u8, u8) {
+    let up = previous.get(i).copied().unwrap_or(0);
+    if i < bpp {
+        return (0, up, 0);
+    }
+    let up_left = previous.get(i - bpp).copied().unwrap_or(0);
+    (row[i - bpp], up, up_left)
+}
+
+fn filter_row(filter: PngFilter, row: &[u8], previous: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    for i in 0..row.len() {
+        let (left, up, up_left) = neighbours(row, previous, i, bpp);
+        out.push(row[i].wrapping_sub(filter.predict(left, up, up_left)));
+    }
+}
+
+/// PNG's heuristic: the filter whose output has the smallest sum of
+/// absolute values, reading bytes as signed.
+fn choose_filter(row: &[u8], previous: &[u8], bpp: usize) -> PngFilter {
+    let mut scratch = Vec::with_capacity(row.len());
+    PngFilter::ALL
+        .into_iter()
+        .min_by_key(|&filter| {
+            scratch.clear();
+            filter_row(filter, row, previous, bpp, &mut scratch);
+            scratch
+                .iter()
+                .map(|&b| u64::from(b.cast_signed().unsigned_abs()))
+                .sum::<u64>()
+        })
+        .unwrap_or(PngFilter::None)
+}
+
+/// Reversible PNG-style prediction filter for row-oriented image data.
+///
+/// The input is split into rows of `stride` bytes, each pixel being
+/// `bytes_per_pixel` bytes. Every row is replaced by its residuals against
+/// one of the [`PngFilter`] predictors, chosen per row with PNG's minimum
+/// sum-of-absolute-differences heuristic unless fixed with
+/// [`Predictor::with_filter`]. Smooth images then become small residuals
+/// that Huffman or LZ77 code well. A shorter final row is filtered like the
+/// others. Output layout: `[bytes per pixel: u8][stride: u32]` followed by
+/// `[filter: u8][residuals]` per row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Predictor {
+    stride: u32,
+    bytes_per_pixel: u8,
+    filter: Option<PngFilter>,
+}
+
+impl Predictor {
+    /// Creates an adaptive predictor for rows of `stride` bytes and one byte
+    /// per pixel.
+    #[must_use]
+    pub const fn new(stride: u32) -> Self {
+        Self {
+            stride,
+            bytes_per_pixel: 1,
+            filter: None,
+        }
+    }
+
+    /// Sets the pixel size, so Sub, Average and Paeth predict from the same
+    /// channel of the neighbouring pixel.
+    #[must_use]
+    pub const fn with_bytes_per_pixel(mut self, bytes_per_pixel: u8) -> Self {
+        self.bytes_per_pixel = bytes_per_pixel;
+        self
+    }
+
+    /// Applies `filter` to every row instead of choosing per row.
+    #[must_use]
+    pub const fn with_filter(mut self, filter: PngFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    #[must_use]
+    pub const fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    #[must_use]
+    pub const fn bytes_per_pixel(&self) -> u8 {
+        self.bytes_per_pixel
+    }
+
+    /// The fixed filter, or `None` if chosen adaptively per row.
+    #[must_use]
+    pub const fn filter(&self) -> Option<PngFilter> {
+        self.filter
+    }
+}
+
+impl Compressor for Predictor {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if self.stride == 0 || self.bytes_per_pixel == 0 {
+            return Err(CompressionError::InvalidInput(
+                "stride and pixel size must be non-zero".to_string(),
+            ));
+        }
+
+        let stride = self.stride as usize;
+        let bpp = usize::from(self.bytes_per_pixel);
+        let mut output = Vec::with_capacity(HEADER_LEN + input.len() + input.len() / stride + 1);
+        output.push(self.bytes_per_pixel);
+        output.extend_from_slice(&self.stride.to_le_bytes());
+
+        let mut previous: &[u8] = &[];
+        for row in input.chunks(stride) {
+            let filter = self
+                .filter
+                .unwrap_or_else(|| choose_filter(row, previous, bpp));
+            output.push(filter as u8);
+            filter_row(filter, row, previous, bpp, &mut output);
+            previous = row;
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Predictor"
+    }
+}
+
+impl Decompressor for Predictor {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let header = input
+            .get(..HEADER_LEN)
+            .ok_or(CompressionError::CorruptedData)?;
+        let bpp = usize::from(header[0]);
+        let stride = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if bpp == 0 || stride == 0 {
+            return Err(CompressionError::InvalidHeader);
+        }
+
+        let body = &input[HEADER_LEN..];
+        let mut output = Vec::with_capacity(body.len());
+        let mut row_start = 0usize;
+        for encoded in body.chunks(stride + 1) {
+            let (&filter, residuals) = encoded
+                .split_first()
+                .filter(|(_, residuals)| !residuals.is_empty())
+                .ok_or(CompressionError::CorruptedData)?;
+            let filter = PngFilter::from_byte(filter).ok_or(CompressionError::CorruptedData)?;
+            let previous_start = row_start.saturating_sub(stride);
+            for (i, &residual) in residuals.iter().enumerate() {
+                let (previous, row) = output.split_at(row_start);
+                let previous = &previous[previous_start..];
+                let (left, up, up_left) = neighbours(row, previous, i, bpp);
+                output.push(residual.wrapping_add(filter.predict(left, up, up_left)));
+            }
+            row_start = output.len();
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Predictor"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Huffman;
+
+    fn roundtrip(predictor: Predictor, input: &[u8]) -> Vec<u8> {
+        let filtered = predictor.compress(input).unwrap();
+        assert_eq!(predictor.decompress(&filtered).unwrap(), input);
+        filtered
+    }
+
+    /// A smooth `width` x `height` RGB gradient.
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| {
+                (0..width).flat_map(move |x| {
+                    [x + y, x * 2, y * 3].map(|channel| channel.to_le_bytes()[0])
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_predictor_new() {
+        let predictor = Predictor::new(12);
+        assert_eq!(predictor.stride(), 12);
+        assert_eq!(predictor.bytes_per_pixel(), 1);
+        assert_eq!(predictor.filter(), None);
+        assert_eq!(Compressor::name(&predictor), "Predictor");
+        assert_eq!(Decompressor::name(&predictor), "Predictor");
+    }
+
+    #[test]
+    fn test_empty() {
+        let predictor = Predictor::new(4);
+        assert_eq!(roundtrip(predictor, &[]), [1, 4, 0, 0, 0]);
+        assert!(matches!(
+            predictor.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output_per_filter() {
+        // Two rows of three one-byte pixels.
+        let input = [10, 20, 30, 12, 22, 35];
+        let expected: [(PngFilter, [u8; 6]); 5] = [
+            (PngFilter::None, [10, 20, 30, 12, 22, 35]),
+            (PngFilter::Sub, [10, 10, 10, 12, 10, 13]),
+            (PngFilter::Up, [10, 20, 30, 2, 2, 5]),
+            (PngFilter::Average, [10, 15, 20, 7, 6, 9]),
+            (PngFilter::Paeth, [10, 10, 10, 2, 2, 5]),
+        ];
+        for (filter, residuals) in expected {
+            let filtered = roundtrip(Predictor::new(3).with_filter(filter), &input);
+            let tag = filter as u8;
+            let mut want = vec![1, 3, 0, 0, 0, tag];
+            want.extend_from_slice(&residuals[..3]);
+            want.push(tag);
+            want.extend_from_slice(&residuals[3..]);
+            assert_eq!(filtered, want, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn test_paeth_predictor() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(10, 10, 20), 10);
+        assert_eq!(paeth(0, 255, 255), 0);
+    }
+
+    #[test]
+    fn test_multibyte_pixels() {
+        let input = gradient(16, 8);
+        for filter in PngFilter::ALL {
+            roundtrip(
+                Predictor::new(48)
+                    .with_bytes_per_pixel(3)
+                    .with_filter(filter),
+                &input,
+            );
+        }
+    }
+
+    #[test]
+    fn test_partial_last_row() {
+        let input: Vec<u8> = (0..50u8).collect();
+        for filter in PngFilter::ALL {
+            roundtrip(Predictor::new(16).with_filter(filter), &input);
+        }
+        roundtrip(Predictor::new(16), &input);
+    }
+
+    #[test]
+    fn test_adaptive_picks_best_row_filter() {
+        // Row 0 is a ramp (Sub wins); row 1 repeats row 0 (Up wins).
+        let ramp: Vec<u8> = (0..32).collect();
+        let input = [ramp.clone(), ramp].concat();
+        let filtered = roundtrip(Predictor::new(32), &input);
+        assert_eq!(filtered[HEADER_LEN], PngFilter::Sub as u8);
+        assert_eq!(filtered[HEADER_LEN + 33], PngFilter::Up as u8);
+        assert!(filtered[HEADER_LEN + 34..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_improves_huffman_on_gradient() {
+        let input = gradient(64, 64);
+        let plain = Huffman::new().compress(&input).unwrap();
+        let predictor = Predictor::new(64 * 3).with_bytes_per_pixel(3);
+        let predicted = Huffman::new()
+            .compress(&predictor.compress(&input).unwrap())
+            .unwrap();
+        assert!(predicted.len() * 2 < plain.len());
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let result = Predictor::new(0).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Predictor::new(4).with_bytes_per_pixel(0).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Predictor::new(4).decompress(&[1, 0, 0, 0, 0]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_decompress_unknown_filter() {
+        let result = Predictor::new(2).decompress(&[1, 2, 0, 0, 0, 5, 1, 2]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+        // A filter byte with no residuals after it.
+        let result = Predictor::new(2).decompress(&[1, 2, 0, 0, 0, 0, 1, 2, 0]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+}
@@ -0,0 +1,570 @@
+//! Canonical prefix-code framing shared by the Huffman-family coders.
+//!
+//! Coders only decide code lengths; this module assigns canonical codes,
+//! serializes the lengths compactly, and handles the bitstream. Output
+//! layout: `[code-length table][original length: u32][bit count: u32][bits]`,
+//! or for a stored frame `[stored marker: 11 bits][original length:
+//! u32][bytes]`.
+//!
+//! Inputs of 4 GiB or more keep the same layout with 64-bit lengths. A
+//! coded frame writes the otherwise impossible pair `[u32::MAX][0]` (every
+//! symbol takes at least one bit) followed by `[original length: u64][bit
+//! count: u64]`; a stored frame sets the bit after its marker and writes
+//! `[original length: u64]`. Frames that fit in 32 bits are unchanged.
+
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::sink::Sink;
+
+pub const MAX_CODE_LENGTH: u8 = 63;
+pub const SYMBOL_LIST_LIMIT: usize = 32;
+/// Length-field width that marks a stored frame in place of a code-length
+/// table; real tables always have a width of at least one bit.
+pub const STORED_WIDTH: u8 = 0;
+/// Bytes a stored frame adds to its input: the padded marker and the
+/// original length.
+pub const STORED_OVERHEAD: usize = 6;
+/// [`STORED_OVERHEAD`] for inputs whose length needs 64 bits.
+pub const WIDE_STORED_OVERHEAD: usize = 10;
+/// Original length that, paired with a bit count of 0, announces 64-bit
+/// lengths in a coded frame.
+pub const WIDE_MARKER: u32 = u32::MAX;
+
+/// Symbols with a non-zero code length, ordered by (length, symbol).
+pub fn canonical_order(lengths: &[u8; 256]) -> Vec<u8> {
+    let mut symbols: Vec<u8> = (0..=255u8)
+        .filter(|&s| lengths[usize::from(s)] > 0)
+        .collect();
+    symbols.sort_by_key(|&s| (lengths[usize::from(s)], s));
+    symbols
+}
+
+/// Assigns canonical codes: codes of equal length are consecutive integers in
+/// symbol order, and shorter codes numerically precede longer ones.
+pub fn canonical_codes(lengths: &[u8; 256]) -> [u64; 256] {
+    let mut codes = [0u64; 256];
+    let mut code = 0u64;
+    let mut prev_len = 0u8;
+    for symbol in canonical_order(lengths) {
+        let len = lengths[usize::from(symbol)];
+        code <<= len - prev_len;
+        codes[usize::from(symbol)] = code;
+        code += 1;
+        prev_len = len;
+    }
+    codes
+}
+
+const fn length_field_bits(max_len: u8) -> u32 {
+    u8::BITS - max_len.leading_zeros()
+}
+
+/// Writes the code-length table.
+///
+/// Layout: `[symbols - 1: 8 bits][field width: 3 bits]`, then either the
+/// used symbols as 8-bit values (fewer than 32 symbols) or a 256-bit presence
+/// bitmap, followed by each used symbol's code length in `field width` bits.
+pub fn write_code_lengths(lengths: &[u8; 256], writer: &mut BitWriter) {
+    let symbols: Vec<u8> = (0..=255u8)
+        .filter(|&s| lengths[usize::from(s)] > 0)
+        .collect();
+    let max_len = lengths.iter().copied().max().unwrap_or(0);
+    let width = length_field_bits(max_len);
+
+    writer.write_bits(symbols.len() as u64 - 1, 8);
+    writer.write_bits(u64::from(width), 3);
+
+    if symbols.len() < SYMBOL_LIST_LIMIT {
+        for &symbol in &symbols {
+            writer.write_bits(u64::from(symbol), 8);
+        }
+    } else {
+        for &len in lengths {
+            writer.write_bit(len > 0);
+        }
+    }
+
+    for &symbol in &symbols {
+        writer.write_bits(u64::from(lengths[usize::from(symbol)]), width);
+    }
+}
+
+/// Bytes [`write_code_lengths`] takes for `lengths` on its own.
+pub fn code_lengths_len(lengths: &[u8; 256]) -> usize {
+    let mut writer = BitWriter::new();
+    write_code_lengths(lengths, &mut writer);
+    writer.finish().len()
+}
+
+pub fn read_code_lengths(reader: &mut BitReader) -> Result<[u8; 256]> {
+    let count = usize::from(reader.read_u8(8).ok_or(CompressionError::CorruptedData)?) + 1;
+    let width = u32::from(reader.read_u8(3).ok_or(CompressionError::CorruptedData)?);
+    if width == 0 {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    let mut symbols = Vec::with_capacity(count);
+    if count < SYMBOL_LIST_LIMIT {
+        let mut previous = None;
+        for _ in 0..count {
+            let symbol = reader.read_u8(8).ok_or(CompressionError::CorruptedData)?;
+            if previous.is_some_and(|p| symbol <= p) {
+                return Err(CompressionError::CorruptedData);
+            }
+            previous = Some(symbol);
+            symbols.push(symbol);
+        }
+    } else {
+        for symbol in 0..=255u8 {
+            if reader.read_bit().ok_or(CompressionError::CorruptedData)? {
+                symbols.push(symbol);
+            }
+        }
+        if symbols.len() != count {
+            return Err(CompressionError::CorruptedData);
+        }
+    }
+
+    let mut lengths = [0u8; 256];
+    let mut kraft_sum = 0u128;
+    for symbol in symbols {
+        let len = reader
+            .read_u8(width)
+            .ok_or(CompressionError::CorruptedData)?;
+        if len == 0 || len > MAX_CODE_LENGTH {
+            return Err(CompressionError::CorruptedData);
+        }
+        lengths[usize::from(symbol)] = len;
+        kraft_sum += 1u128 << (MAX_CODE_LENGTH - len);
+    }
+
+    if kraft_sum > 1u128 << MAX_CODE_LENGTH {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    Ok(lengths)
+}
+
+/// Decodes canonical codes bit by bit using per-length code counts.
+pub struct CanonicalDecoder {
+    counts: [u64; MAX_CODE_LENGTH as usize + 1],
+    /// Symbols in [`canonical_order`], in a fixed array so that building a
+    /// decoder allocates nothing.
+    symbols: [u8; 256],
+}
+
+impl CanonicalDecoder {
+    pub fn new(lengths: &[u8; 256]) -> Self {
+        let mut counts = [0u64; MAX_CODE_LENGTH as usize + 1];
+        for &len in lengths.iter().filter(|&&len| len > 0) {
+            counts[usize::from(len)] += 1;
+        }
+        // Each length's symbols start after all shorter lengths' symbols.
+        let mut next = [0usize; MAX_CODE_LENGTH as usize + 1];
+        for &len in lengths {
+            next[usize::from(len)] += 1;
+        }
+        let mut start = 0;
+        for next in &mut next[1..] {
+            let count = *next;
+            *next = start;
+            start += count;
+        }
+        let mut symbols = [0; 256];
+        for (symbol, &len) in (0..=u8::MAX).zip(lengths) {
+            if len > 0 {
+                symbols[next[usize::from(len)]] = symbol;
+                next[usize::from(len)] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    pub fn decode_symbol(&self, reader: &mut BitReader) -> Result<u8> {
+        let mut code = 0u64;
+        let mut first = 0u64;
+        let mut index = 0u64;
+
+        for &count in &self.counts[1..] {
+            code |= u64::from(reader.read_bit().ok_or(CompressionError::CorruptedData)?);
+            if code - first < count {
+                let position = usize::try_from(index + code - first)
+                    .map_err(|_| CompressionError::CorruptedData)?;
+                return Ok(self.symbols[position]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(CompressionError::CorruptedData)
+    }
+}
+
+/// Encodes `input` with canonical codes of the given `lengths`, which must
+/// cover every byte in `input`.
+pub fn encode(input: &[u8], lengths: &[u8; 256]) -> Vec<u8> {
+    let codes = canonical_codes(lengths);
+
+    let mut header = BitWriter::new();
+    write_code_lengths(lengths, &mut header);
+    let mut output = header.finish();
+
+    let mut bits = BitWriter::new();
+    for &byte in input {
+        let index = usize::from(byte);
+        bits.write_bits(codes[index], u32::from(lengths[index]));
+    }
+
+    write_lengths(&mut output, input.len() as u64, bits.bit_len() as u64);
+    output.extend_from_slice(&bits.finish());
+
+    output
+}
+
+/// Writes a coded frame's original length and bit count as `u32`s, or
+/// after the wide marker as `u64`s when either does not fit.
+fn write_lengths(output: &mut Vec<u8>, original_len: u64, num_bits: u64) {
+    if let (Ok(len), Ok(bits)) = (u32::try_from(original_len), u32::try_from(num_bits)) {
+        output.extend_from_slice(&len.to_le_bytes());
+        output.extend_from_slice(&bits.to_le_bytes());
+    } else {
+        output.extend_from_slice(&WIDE_MARKER.to_le_bytes());
+        output.extend_from_slice(&0u32.to_le_bytes());
+        output.extend_from_slice(&original_len.to_le_bytes());
+        output.extend_from_slice(&num_bits.to_le_bytes());
+    }
+}
+
+/// Reads a `width`-byte little-endian integer at `input[pos..]`.
+fn read_le(input: &[u8], pos: usize, width: usize) -> Result<u64> {
+    let bytes = input
+        .get(pos..pos + width)
+        .ok_or(CompressionError::CorruptedData)?;
+    let mut buf = [0; 8];
+    buf[..width].copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Reads what [`write_lengths`] wrote at `input[pos..]`, returning the
+/// original length, the bit count and the position after them.
+fn read_lengths(input: &[u8], pos: usize) -> Result<(u64, u64, usize)> {
+    let original_len = read_le(input, pos, 4)?;
+    let num_bits = read_le(input, pos + 4, 4)?;
+    if original_len == u64::from(WIDE_MARKER) && num_bits == 0 {
+        return Ok((
+            read_le(input, pos + 8, 8)?,
+            read_le(input, pos + 16, 8)?,
+            pos + 24,
+        ));
+    }
+    Ok((original_len, num_bits, pos + 8))
+}
+
+/// Bytes of [`encode_empty`]'s frame: a one-entry table of 20 bits, then
+/// the original length and bit count.
+pub const EMPTY_LEN: usize = 11;
+
+/// Encodes empty input as a frame with a single one-bit code and no bits, so
+/// that it is distinguishable from a missing buffer.
+pub fn encode_empty() -> Vec<u8> {
+    let mut lengths = [0u8; 256];
+    lengths[0] = 1;
+    encode(&[], &lengths)
+}
+
+/// Bytes [`encode_stored`] adds to an input of `input_len` bytes.
+#[must_use]
+pub const fn stored_overhead(input_len: usize) -> usize {
+    if input_len > u32::MAX as usize {
+        WIDE_STORED_OVERHEAD
+    } else {
+        STORED_OVERHEAD
+    }
+}
+
+/// Frames `input` verbatim behind a stored marker, for data no code would
+/// shrink.
+pub fn encode_stored(input: &[u8]) -> Vec<u8> {
+    let original_len = u32::try_from(input.len());
+    let mut marker = BitWriter::new();
+    marker.write_bits(0xFF, 8);
+    marker.write_bits(u64::from(STORED_WIDTH), 3);
+    marker.write_bit(original_len.is_err());
+    let mut output = Vec::with_capacity(stored_overhead(input.len()) + input.len());
+    output.extend_from_slice(&marker.finish());
+    match original_len {
+        Ok(len) => output.extend_from_slice(&len.to_le_bytes()),
+        Err(_) => output.extend_from_slice(&(input.len() as u64).to_le_bytes()),
+    }
+    output.extend_from_slice(input);
+    output
+}
+
+/// Reads the original length of the stored frame at the start of `input`,
+/// returning it and the length of the frame's header.
+fn read_stored_len(input: &[u8]) -> Result<(u64, usize)> {
+    let mut reader = BitReader::new(input);
+    reader.read_bits(11);
+    if reader.read_bit() == Some(true) {
+        Ok((read_le(input, 2, 8)?, WIDE_STORED_OVERHEAD))
+    } else {
+        Ok((read_le(input, 2, 4)?, STORED_OVERHEAD))
+    }
+}
+
+/// Whether `input` starts with the marker [`encode_stored`] writes.
+fn is_stored(input: &[u8]) -> bool {
+    let mut reader = BitReader::new(input);
+    reader.read_u8(8) == Some(0xFF) && reader.read_u8(3) == Some(STORED_WIDTH)
+}
+
+/// Decodes data produced by [`encode`] or [`encode_stored`], which must
+/// fill `input` exactly.
+pub fn decode(input: &[u8]) -> Result<Vec<u8>> {
+    let (output, consumed) = decode_partial(input)?;
+    if consumed != input.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(output)
+}
+
+/// Reads the original length from the header of the frame at the start of
+/// `input`, skipping the code lengths but decoding nothing.
+pub fn decoded_len(input: &[u8]) -> Result<u64> {
+    if is_stored(input) {
+        return Ok(read_stored_len(input)?.0);
+    }
+    let mut header = BitReader::new(input);
+    read_code_lengths(&mut header)?;
+    Ok(read_lengths(input, header.bytes_consumed())?.0)
+}
+
+/// Decodes the frame at the start of `input`, returning the data and the
+/// number of bytes the frame occupied.
+pub fn decode_partial(input: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut output = Vec::new();
+    let consumed = decode_partial_into(input, &mut output)?;
+    Ok((output, consumed))
+}
+
+/// Decodes the frame at the start of `input` into `output`, returning the
+/// number of bytes the frame occupied.
+pub fn decode_partial_into(input: &[u8], output: &mut impl Sink) -> Result<usize> {
+    if is_stored(input) {
+        let (original_len, pos) = read_stored_len(input)?;
+        let stored = usize::try_from(original_len)
+            .ok()
+            .and_then(|len| input.get(pos..pos.checked_add(len)?))
+            .ok_or(CompressionError::CorruptedData)?;
+        output.extend_from_slice(stored)?;
+        return Ok(pos + stored.len());
+    }
+
+    let mut header = BitReader::new(input);
+    let lengths = read_code_lengths(&mut header)?;
+    let (original_len, num_bits, pos) = read_lengths(input, header.bytes_consumed())?;
+    let to_usize = |value| usize::try_from(value).map_err(|_| CompressionError::CorruptedData);
+    let (original_len, num_bits) = (to_usize(original_len)?, to_usize(num_bits)?);
+
+    let payload = input
+        .get(pos..pos.saturating_add(num_bits.div_ceil(8)))
+        .ok_or(CompressionError::CorruptedData)?;
+    let decoder = CanonicalDecoder::new(&lengths);
+    let mut bits = BitReader::new(payload);
+    output.reserve(original_len.min(num_bits));
+
+    for _ in 0..original_len {
+        output.push(decoder.decode_symbol(&mut bits)?)?;
+    }
+
+    if bits.bits_read() != num_bits {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    Ok(pos + payload.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lengths_from(entries: &[(u8, u8)]) -> [u8; 256] {
+        let mut lengths = [0u8; 256];
+        for &(symbol, len) in entries {
+            lengths[usize::from(symbol)] = len;
+        }
+        lengths
+    }
+
+    #[test]
+    fn test_empty_len() {
+        assert_eq!(encode_empty().len(), EMPTY_LEN);
+    }
+
+    #[test]
+    fn test_canonical_codes() {
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 2), (b'c', 3), (b'd', 3)]);
+        let codes = canonical_codes(&lengths);
+        assert_eq!(codes[usize::from(b'a')], 0b0);
+        assert_eq!(codes[usize::from(b'b')], 0b10);
+        assert_eq!(codes[usize::from(b'c')], 0b110);
+        assert_eq!(codes[usize::from(b'd')], 0b111);
+    }
+
+    #[test]
+    fn test_code_lengths_roundtrip_symbol_list() {
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 2), (b'c', 3), (b'd', 3)]);
+        let mut writer = BitWriter::new();
+        write_code_lengths(&lengths, &mut writer);
+        let bytes = writer.finish();
+        let decoded = read_code_lengths(&mut BitReader::new(&bytes)).unwrap();
+        assert_eq!(decoded, lengths);
+    }
+
+    #[test]
+    fn test_code_lengths_roundtrip_bitmap() {
+        let lengths = [8u8; 256];
+        let mut writer = BitWriter::new();
+        write_code_lengths(&lengths, &mut writer);
+        let bytes = writer.finish();
+        assert!(bytes.len() < 256);
+        let decoded = read_code_lengths(&mut BitReader::new(&bytes)).unwrap();
+        assert_eq!(decoded, lengths);
+    }
+
+    #[test]
+    fn test_code_lengths_header_is_compact() {
+        let lengths = lengths_from(&[
+            (b' ', 3),
+            (b'd', 3),
+            (b'e', 3),
+            (b'h', 3),
+            (b'l', 3),
+            (b'o', 3),
+            (b'r', 3),
+            (b'w', 3),
+        ]);
+        let mut writer = BitWriter::new();
+        write_code_lengths(&lengths, &mut writer);
+        // 8 distinct symbols: 11 header bits + 8 * 8 symbol bits + 8 * 2 length bits
+        assert_eq!(writer.finish().len(), 12);
+    }
+
+    #[test]
+    fn test_read_code_lengths_truncated() {
+        let result = read_code_lengths(&mut BitReader::new(&[]));
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_read_code_lengths_rejects_oversubscribed() {
+        let mut writer = BitWriter::new();
+        writer.write_bits(2, 8); // three symbols
+        writer.write_bits(1, 3); // one-bit lengths
+        for symbol in [b'a', b'b', b'c'] {
+            writer.write_bits(u64::from(symbol), 8);
+        }
+        for _ in 0..3 {
+            writer.write_bits(1, 1);
+        }
+        let bytes = writer.finish();
+        let result = read_code_lengths(&mut BitReader::new(&bytes));
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_read_code_lengths_rejects_unsorted_symbols() {
+        let mut writer = BitWriter::new();
+        writer.write_bits(1, 8);
+        writer.write_bits(1, 3);
+        writer.write_bits(u64::from(b'b'), 8);
+        writer.write_bits(u64::from(b'a'), 8);
+        writer.write_bits(0b11, 2);
+        let bytes = writer.finish();
+        let result = read_code_lengths(&mut BitReader::new(&bytes));
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 2), (b'c', 3), (b'd', 3)]);
+        let input = b"abacabadcab";
+        let encoded = encode(input, &lengths);
+        assert_eq!(decode(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn test_wide_lengths() {
+        let mut fields = Vec::new();
+        write_lengths(&mut fields, 5 << 32, 7 << 32);
+        assert_eq!(fields.len(), 24);
+        assert_eq!(read_lengths(&fields, 0).unwrap(), (5 << 32, 7 << 32, 24));
+        fields.clear();
+        write_lengths(&mut fields, u64::from(u32::MAX), u64::from(u32::MAX));
+        assert_eq!(fields.len(), 8);
+        assert_eq!(read_lengths(&fields, 0).unwrap().2, 8);
+
+        // Small inputs never take the wide form, but it still decodes.
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 2), (b'c', 2)]);
+        let encoded = encode(b"abcabca", &lengths);
+        let header = code_lengths_len(&lengths);
+        let mut wide = encoded[..header].to_vec();
+        wide.extend_from_slice(&WIDE_MARKER.to_le_bytes());
+        wide.extend_from_slice(&[0; 4]);
+        wide.extend_from_slice(&7u64.to_le_bytes());
+        wide.extend_from_slice(&11u64.to_le_bytes());
+        wide.extend_from_slice(&encoded[header + 8..]);
+        assert_eq!(decode(&wide).unwrap(), b"abcabca");
+        assert_eq!(decoded_len(&wide).unwrap(), 7);
+        assert!(decode(&wide[..header + 20]).is_err());
+
+        let mut marker = BitWriter::new();
+        marker.write_bits(0xFF, 8);
+        marker.write_bits(u64::from(STORED_WIDTH), 3);
+        marker.write_bit(true);
+        let mut stored = marker.finish();
+        stored.extend_from_slice(&3u64.to_le_bytes());
+        stored.extend_from_slice(b"abc");
+        assert_eq!(stored.len(), WIDE_STORED_OVERHEAD + 3);
+        assert_eq!(decode(&stored).unwrap(), b"abc");
+        assert_eq!(decoded_len(&stored).unwrap(), 3);
+
+        assert_eq!(stored_overhead(u32::MAX as usize), STORED_OVERHEAD);
+        if let Ok(len) = usize::try_from(1u64 << 32) {
+            assert_eq!(stored_overhead(len), WIDE_STORED_OVERHEAD);
+        }
+    }
+
+    #[test]
+    fn test_stored_frame() {
+        let encoded = encode_stored(b"abc");
+        assert_eq!(encoded.len(), STORED_OVERHEAD + 3);
+        assert_eq!(decode(&encoded).unwrap(), b"abc");
+        assert_eq!(decode(&encode_stored(&[])).unwrap(), b"");
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode(&encoded[..4]).is_err());
+        // Real tables never use the stored width.
+        let lengths = [8u8; 256];
+        assert!(!is_stored(&encode(&[0, 1], &lengths)));
+        assert_eq!(code_lengths_len(&lengths), encode(&[], &lengths).len() - 8);
+    }
+
+    #[test]
+    fn test_decoded_len() {
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 2), (b'c', 2)]);
+        let encoded = encode(b"abcabca", &lengths);
+        assert_eq!(decoded_len(&encoded).unwrap(), 7);
+        assert_eq!(decoded_len(&encode_stored(b"abc")).unwrap(), 3);
+        assert_eq!(decoded_len(&encode_empty()).unwrap(), 0);
+        let header = code_lengths_len(&lengths);
+        assert!(decoded_len(&encoded[..header + 3]).is_err());
+    }
+
+    #[test]
+    fn test_decode_truncated_lengths() {
+        let lengths = lengths_from(&[(b'a', 1), (b'b', 1)]);
+        let encoded = encode(b"abab", &lengths);
+        assert!(decode(&encoded[..encoded.len() - 2]).is_err());
+    }
+}
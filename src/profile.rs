@@ -0,0 +1,254 @@
+use crate::classify::entropy;
+use crate::error::{CompressionError, Result};
+use crate::varint;
+
+/// Delta strides tried by [`Profile::capture`].
+const DELTA_STRIDES: [u8; 4] = [1, 2, 4, 8];
+/// A delta filter is recommended only if it cuts the entropy by this much.
+const DELTA_GAIN: f64 = 0.9;
+/// Bytes hashed to find repeats when measuring match distances.
+const MATCH_LEN: usize = 4;
+const HASH_BITS: u32 = 16;
+/// Share of the sample's match distances the recorded distance covers.
+const DISTANCE_PERCENTILE: usize = 90;
+
+/// Differences each byte from the one `stride` earlier, as [`crate::Delta`]
+/// does, without the header.
+fn delta(sample: &[u8], stride: usize) -> Vec<u8> {
+    let stride = stride.min(sample.len());
+    sample[..stride]
+        .iter()
+        .copied()
+        .chain(
+            sample
+                .iter()
+                .zip(&sample[stride..])
+                .map(|(&previous, &current)| current.wrapping_sub(previous)),
+        )
+        .collect()
+}
+
+/// The stride whose delta filter lowers the order-0 entropy the most, if
+/// any lowers it enough to be worth a pass.
+fn best_delta_stride(sample: &[u8]) -> Option<u8> {
+    let raw = entropy(sample.iter());
+    DELTA_STRIDES
+        .into_iter()
+        .filter(|&stride| sample.len() > usize::from(stride) * 2)
+        .map(|stride| (stride, entropy(delta(sample, usize::from(stride)).iter())))
+        .filter(|&(_, filtered)| filtered < raw * DELTA_GAIN)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(stride, _)| stride)
+}
+
+/// Distance back to the previous occurrence of each repeated
+/// `MATCH_LEN`-byte sequence, found through a single-probe hash table.
+fn match_distances(sample: &[u8]) -> Vec<u32> {
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut distances = Vec::new();
+    for (pos, window) in sample.windows(MATCH_LEN).enumerate() {
+        let sequence = u32::from_le_bytes([window[0], window[1], window[2], window[3]]);
+        let hash = sequence.wrapping_mul(2_654_435_761) >> (u32::BITS - HASH_BITS);
+        let slot = &mut table[usize::try_from(hash).unwrap_or(0)];
+        let candidate = *slot;
+        *slot = pos;
+        if candidate != usize::MAX && sample[candidate..candidate + MATCH_LEN] == *window {
+            distances.push(u32::try_from(pos - candidate).unwrap_or(u32::MAX));
+        }
+    }
+    distances
+}
+
+/// Statistics captured once from representative data and reused to
+/// warm-start later compressions of similar data.
+///
+/// [`Profile::capture`] measures a sample: whether a delta filter pays off
+/// and at which stride, the byte frequencies after that filter, and how far
+/// back repeats typically reach. Codecs built from a profile skip the
+/// corresponding analysis on every call: [`Huffman::with_profile`] codes
+/// with a table built from the recorded frequencies instead of counting
+/// each input and storing its table, and [`Lzma::with_profile`] sizes its
+/// dictionary to the recorded distance. A profile is plain data, so it can
+/// be written to a file with [`Profile::encode`] and shipped to both ends.
+/// Encoded layout: `[delta stride: u8][match distance: varint]
+/// [frequency: varint]` for each of the 256 byte values; a stride of zero
+/// means no filter.
+///
+/// [`Huffman::with_profile`]: crate::Huffman::with_profile
+/// [`Lzma::with_profile`]: crate::Lzma::with_profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    delta_stride: u8,
+    match_distance: u32,
+    frequencies: [u32; 256],
+}
+
+impl Profile {
+    /// Captures a profile from `sample`, which should look like the data
+    /// that will later be compressed with it.
+    #[must_use]
+    pub fn capture(sample: &[u8]) -> Self {
+        let delta_stride = best_delta_stride(sample);
+        let filtered = delta_stride.map_or_else(
+            || sample.to_vec(),
+            |stride| delta(sample, usize::from(stride)),
+        );
+        let mut frequencies = [0u32; 256];
+        for &byte in &filtered {
+            let count = &mut frequencies[usize::from(byte)];
+            *count = count.saturating_add(1);
+        }
+
+        let mut distances = match_distances(sample);
+        distances.sort_unstable();
+        let percentile = (distances.len() * DISTANCE_PERCENTILE).div_ceil(100);
+        let match_distance = distances
+            .get(percentile.saturating_sub(1))
+            .copied()
+            .unwrap_or(0);
+
+        Self {
+            delta_stride: delta_stride.unwrap_or(0),
+            match_distance,
+            frequencies,
+        }
+    }
+
+    /// Stride of the delta filter to run before coding, as
+    /// [`Delta::with_stride`](crate::Delta::with_stride), if the sample
+    /// benefited from one.
+    #[must_use]
+    pub const fn delta_stride(&self) -> Option<u8> {
+        match self.delta_stride {
+            0 => None,
+            stride => Some(stride),
+        }
+    }
+
+    /// Distance that 90% of the sample's repeats reach back no further
+    /// than; zero if nothing repeated.
+    #[must_use]
+    pub const fn match_distance(&self) -> u32 {
+        self.match_distance
+    }
+
+    /// Count of each byte value in the sample, after the delta filter if
+    /// there is one.
+    #[must_use]
+    pub const fn frequencies(&self) -> &[u32; 256] {
+        &self.frequencies
+    }
+
+    /// Serializes the profile.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = vec![self.delta_stride];
+        varint::write_u64(&mut output, u64::from(self.match_distance));
+        for &count in &self.frequencies {
+            varint::write_u64(&mut output, u64::from(count));
+        }
+        output
+    }
+
+    /// Parses a profile written by [`Profile::encode`], returning it and
+    /// the number of bytes read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the input is truncated
+    /// or a value does not fit its field.
+    pub fn decode(input: &[u8]) -> Result<(Self, usize)> {
+        let &delta_stride = input.first().ok_or(CompressionError::CorruptedData)?;
+        let mut pos = 1;
+        let match_distance = varint::read_u32(input, &mut pos)?;
+        let mut frequencies = [0u32; 256];
+        for count in &mut frequencies {
+            *count = varint::read_u32(input, &mut pos)?;
+        }
+        let profile = Self {
+            delta_stride,
+            match_distance,
+            frequencies,
+        };
+        Ok((profile, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor_readings(count: u32) -> Vec<u8> {
+        (0..count)
+            .flat_map(|i| (20_000 + i * 3 + i % 7).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_capture_text() {
+        let sample = b"the cat sat on the mat; the dog sat on the log. ".repeat(20);
+        let profile = Profile::capture(&sample);
+        assert_eq!(profile.delta_stride(), None);
+        assert_eq!(profile.frequencies()[usize::from(b't')], 160);
+        assert_eq!(profile.frequencies()[usize::from(b'z')], 0);
+        // The sentence repeats every 48 bytes; "the " and "sat on the "
+        // also recur inside it.
+        assert!(
+            (1..=48).contains(&profile.match_distance()),
+            "{}",
+            profile.match_distance()
+        );
+    }
+
+    #[test]
+    fn test_capture_numeric_picks_delta() {
+        let profile = Profile::capture(&sensor_readings(2000));
+        assert_eq!(profile.delta_stride(), Some(4));
+        // After differencing, the high bytes are almost all zero.
+        let zeros = profile.frequencies()[0];
+        assert!(zeros > 2000 * 2, "{zeros}");
+    }
+
+    #[test]
+    fn test_capture_empty_and_noise() {
+        let empty = Profile::capture(&[]);
+        assert_eq!((empty.delta_stride(), empty.match_distance()), (None, 0));
+        assert!(empty.frequencies().iter().all(|&count| count == 0));
+
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+        let profile = Profile::capture(&noise);
+        assert_eq!(profile.delta_stride(), None);
+        assert_eq!(profile.frequencies().iter().sum::<u32>(), 4096);
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        for sample in [&b""[..], b"abracadabra abracadabra", &sensor_readings(100)] {
+            let profile = Profile::capture(sample);
+            let mut encoded = profile.encode();
+            encoded.push(0xAA);
+            let (decoded, consumed) = Profile::decode(&encoded).unwrap();
+            assert_eq!(decoded, profile);
+            assert_eq!(consumed, encoded.len() - 1);
+        }
+    }
+
+    #[test]
+    fn test_decode_corrupted() {
+        let encoded = Profile::capture(b"abc").encode();
+        assert!(Profile::decode(&[]).is_err());
+        assert!(Profile::decode(&encoded[..encoded.len() - 1]).is_err());
+        // A frequency too large for a u32.
+        let mut wide = encoded[..encoded.len() - 1].to_vec();
+        varint::write_u64(&mut wide, 1 << 40);
+        assert!(Profile::decode(&wide).is_err());
+    }
+}
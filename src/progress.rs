@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+/// Snapshot of a multi-part operation, passed to progress callbacks after
+/// each entry completes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Index of the entry that just completed.
+    pub entry: usize,
+    /// Total number of entries.
+    pub entries: usize,
+    /// Input bytes of the entry that just completed.
+    pub entry_bytes: u64,
+    /// Input bytes processed so far, including this entry.
+    pub bytes_done: u64,
+    /// Total input bytes.
+    pub bytes_total: u64,
+    /// Time since the operation started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Fraction of the input processed, from 0.0 to 1.0.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total == 0 {
+            return 1.0;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let fraction = self.bytes_done as f64 / self.bytes_total as f64;
+        fraction
+    }
+
+    /// Estimated time remaining, extrapolated from the throughput so far.
+    /// `None` until some bytes have been processed.
+    #[must_use]
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.bytes_done == 0 {
+            return None;
+        }
+        let remaining = self.bytes_total.saturating_sub(self.bytes_done);
+        let nanos = self.elapsed.as_nanos() * u128::from(remaining) / u128::from(self.bytes_done);
+        Some(Duration::from_nanos(
+            u64::try_from(nanos).unwrap_or(u64::MAX),
+        ))
+    }
+
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.entry + 1 == self.entries
+    }
+}
+
+/// Tracks an operation over a known number of entries and bytes.
+#[derive(Debug)]
+pub struct Tracker {
+    start: Instant,
+    entries: usize,
+    bytes_total: u64,
+    bytes_done: u64,
+    completed: usize,
+}
+
+impl Tracker {
+    pub fn new(entries: usize, bytes_total: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            entries,
+            bytes_total,
+            bytes_done: 0,
+            completed: 0,
+        }
+    }
+
+    /// Records one completed entry of `entry_bytes` input bytes.
+    pub fn advance(&mut self, entry_bytes: u64) -> Progress {
+        self.bytes_done += entry_bytes;
+        let progress = Progress {
+            entry: self.completed,
+            entries: self.entries,
+            entry_bytes,
+            bytes_done: self.bytes_done,
+            bytes_total: self.bytes_total,
+            elapsed: self.start.elapsed(),
+        };
+        self.completed += 1;
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(bytes_done: u64, bytes_total: u64, elapsed_ms: u64) -> Progress {
+        Progress {
+            entry: 0,
+            entries: 2,
+            entry_bytes: bytes_done,
+            bytes_done,
+            bytes_total,
+            elapsed: Duration::from_millis(elapsed_ms),
+        }
+    }
+
+    #[test]
+    fn test_fraction() {
+        assert!((progress(25, 100, 0).fraction() - 0.25).abs() < f64::EPSILON);
+        assert!((progress(0, 0, 0).fraction() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_remaining() {
+        assert_eq!(progress(0, 100, 10).remaining(), None);
+        assert_eq!(
+            progress(25, 100, 100).remaining(),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(progress(100, 100, 100).remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_tracker_advance() {
+        let mut tracker = Tracker::new(3, 30);
+        let first = tracker.advance(10);
+        assert_eq!(first.entry, 0);
+        assert_eq!(first.bytes_done, 10);
+        assert!(!first.is_complete());
+        tracker.advance(10);
+        let last = tracker.advance(10);
+        assert_eq!(last.entry, 2);
+        assert_eq!(last.bytes_done, 30);
+        assert!(last.is_complete());
+    }
+}
@@ -0,0 +1,349 @@
+//! Byte-oriented range coder shared by the context-modelling codecs.
+//!
+//! Models supply cumulative frequencies over a total below
+//! [`MAX_TOTAL`]; this module turns them into bytes. The encoder follows the
+//! LZMA design: a 32-bit range, a 64-bit low bound, and carry propagation
+//! through a run of pending `0xFF` bytes. A stream of `n` normalisation
+//! shifts is exactly `n + 5` bytes, and the decoder reads exactly that many,
+//! so frames can be concatenated.
+//!
+//! [`BitCoder`] layers LZMA-style adaptive binary models on top: each bit
+//! is coded with a probability that drifts towards the bits seen, and
+//! [`tree`] codes multi-bit values through a binary tree of them.
+
+use crate::error::{CompressionError, Result};
+
+/// Exclusive bound on the frequency total of a single coding step.
+pub const MAX_TOTAL: u32 = 1 << 16;
+const TOP: u32 = 1 << 24;
+/// Bytes written by [`RangeEncoder::finish`] and read on decoder start-up.
+pub const FLUSH_LEN: usize = 5;
+/// Adaptive bit probabilities are fixed point with this many fraction bits.
+pub const PROB_BITS: u32 = 11;
+/// Each coded bit moves its probability by 1/2^`MOVE_BITS` of the gap.
+pub const MOVE_BITS: u32 = 5;
+const PROB_ONE: u32 = 1 << PROB_BITS;
+/// Starting probability of an adaptive bit: even odds.
+pub const PROB_INIT: u32 = PROB_ONE / 2;
+
+/// Encodes symbols given as `(cumulative, frequency, total)` triples.
+#[derive(Debug, Clone)]
+pub struct RangeEncoder {
+    output: Vec<u8>,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: usize,
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RangeEncoder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    /// Narrows the range to `[cumulative, cumulative + frequency)` out of
+    /// `total`.
+    pub fn encode(&mut self, cumulative: u32, frequency: u32, total: u32) {
+        debug_assert!(frequency > 0 && cumulative + frequency <= total && total < MAX_TOTAL);
+        let step = self.range / total;
+        self.low += u64::from(step * cumulative);
+        self.range = step * frequency;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        let carry = self.low >> 32;
+        if carry != 0 || self.low < 0xFF00_0000 {
+            let carry = carry.to_le_bytes()[0];
+            self.output.push(self.cache.wrapping_add(carry));
+            for _ in 1..self.cache_size {
+                self.output.push(0xFFu8.wrapping_add(carry));
+            }
+            self.cache_size = 0;
+            self.cache = (self.low >> 24).to_le_bytes()[0];
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    /// Flushes the low bound and returns the coded bytes.
+    #[must_use]
+    pub fn finish(mut self) -> Vec<u8> {
+        for _ in 0..FLUSH_LEN {
+            self.shift_low();
+        }
+        self.output
+    }
+}
+
+/// Decodes a stream written by [`RangeEncoder`].
+#[derive(Debug, Clone)]
+pub struct RangeDecoder<'a> {
+    input: &'a [u8],
+    position: usize,
+    code: u32,
+    range: u32,
+    step: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Starts decoding, reading the first [`FLUSH_LEN`] bytes.
+    pub fn new(input: &'a [u8]) -> Result<Self> {
+        let mut decoder = Self {
+            input,
+            position: 0,
+            code: 0,
+            range: u32::MAX,
+            step: 1,
+        };
+        if decoder.next_byte()? != 0 {
+            return Err(CompressionError::CorruptedData);
+        }
+        for _ in 1..FLUSH_LEN {
+            decoder.code = (decoder.code << 8) | u32::from(decoder.next_byte()?);
+        }
+        Ok(decoder)
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .input
+            .get(self.position)
+            .ok_or(CompressionError::CorruptedData)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    /// Returns the cumulative frequency the next symbol falls on out of
+    /// `total`. Must be followed by [`RangeDecoder::decode`] with the same
+    /// total.
+    pub fn target(&mut self, total: u32) -> Result<u32> {
+        debug_assert!(total > 0 && total < MAX_TOTAL);
+        self.step = self.range / total;
+        let target = self.code / self.step;
+        if target >= total {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(target)
+    }
+
+    /// Consumes the symbol occupying `[cumulative, cumulative + frequency)`.
+    pub fn decode(&mut self, cumulative: u32, frequency: u32) -> Result<()> {
+        self.code -= self.step * cumulative;
+        self.range = self.step * frequency;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.next_byte()?);
+        }
+        Ok(())
+    }
+
+    /// Number of input bytes read so far.
+    #[must_use]
+    pub const fn bytes_consumed(&self) -> usize {
+        self.position
+    }
+}
+
+/// Lets one routine both encode and decode: the encoder codes `bit` and
+/// returns it, the decoder ignores `bit` and returns what it read.
+pub trait BitCoder {
+    /// Codes a bit with the adaptive chance of zero `prob`.
+    fn bit(&mut self, prob: &mut u32, bit: u32) -> Result<u32>;
+    /// Codes a bit at even odds.
+    fn direct(&mut self, bit: u32) -> Result<u32>;
+}
+
+const fn update(prob: &mut u32, bit: u32) {
+    if bit == 0 {
+        *prob += (PROB_ONE - *prob) >> MOVE_BITS;
+    } else {
+        *prob -= *prob >> MOVE_BITS;
+    }
+}
+
+impl BitCoder for RangeEncoder {
+    fn bit(&mut self, prob: &mut u32, bit: u32) -> Result<u32> {
+        if bit == 0 {
+            self.encode(0, *prob, PROB_ONE);
+        } else {
+            self.encode(*prob, PROB_ONE - *prob, PROB_ONE);
+        }
+        update(prob, bit);
+        Ok(bit)
+    }
+
+    fn direct(&mut self, bit: u32) -> Result<u32> {
+        self.encode(bit, 1, 2);
+        Ok(bit)
+    }
+}
+
+impl BitCoder for RangeDecoder<'_> {
+    fn bit(&mut self, prob: &mut u32, _: u32) -> Result<u32> {
+        let bit = u32::from(self.target(PROB_ONE)? >= *prob);
+        if bit == 0 {
+            self.decode(0, *prob)?;
+        } else {
+            self.decode(*prob, PROB_ONE - *prob)?;
+        }
+        update(prob, bit);
+        Ok(bit)
+    }
+
+    fn direct(&mut self, _: u32) -> Result<u32> {
+        let bit = self.target(2)?;
+        self.decode(bit, 1)?;
+        Ok(bit)
+    }
+}
+
+/// Codes the low `bits` bits of `value`, most significant first, through a
+/// binary tree of `2^bits` probabilities.
+pub fn tree<C: BitCoder>(coder: &mut C, probs: &mut [u32], bits: u32, value: u32) -> Result<u32> {
+    let mut node = 1;
+    for i in (0..bits).rev() {
+        node = node << 1 | coder.bit(&mut probs[node as usize], value >> i & 1)?;
+    }
+    Ok(node - (1 << bits))
+}
+
+/// Like [`tree`], least significant bit first.
+pub fn reverse_tree<C: BitCoder>(
+    coder: &mut C,
+    probs: &mut [u32],
+    bits: u32,
+    value: u32,
+) -> Result<u32> {
+    let mut node = 1;
+    let mut result = 0;
+    for i in 0..bits {
+        let bit = coder.bit(&mut probs[node as usize], value >> i & 1)?;
+        node = node << 1 | bit;
+        result |= bit << i;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Codes `symbols` under a fixed skewed model over 0..4.
+    const FREQUENCIES: [u32; 4] = [1, 60_000, 3, 200];
+
+    fn cumulative(symbol: usize) -> u32 {
+        FREQUENCIES[..symbol].iter().sum()
+    }
+
+    fn total() -> u32 {
+        FREQUENCIES.iter().sum()
+    }
+
+    fn encode(symbols: &[usize]) -> Vec<u8> {
+        let mut encoder = RangeEncoder::new();
+        for &symbol in symbols {
+            encoder.encode(cumulative(symbol), FREQUENCIES[symbol], total());
+        }
+        encoder.finish()
+    }
+
+    fn decode(bytes: &[u8], count: usize) -> Result<(Vec<usize>, usize)> {
+        let mut decoder = RangeDecoder::new(bytes)?;
+        let mut symbols = Vec::new();
+        for _ in 0..count {
+            let target = decoder.target(total())?;
+            let symbol = (0..FREQUENCIES.len())
+                .find(|&s| target < cumulative(s) + FREQUENCIES[s])
+                .unwrap();
+            decoder.decode(cumulative(symbol), FREQUENCIES[symbol])?;
+            symbols.push(symbol);
+        }
+        Ok((symbols, decoder.bytes_consumed()))
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        let bytes = encode(&[]);
+        assert_eq!(bytes.len(), FLUSH_LEN);
+        assert_eq!(decode(&bytes, 0).unwrap(), (Vec::new(), FLUSH_LEN));
+    }
+
+    #[test]
+    fn test_roundtrip_consumes_exact_length() {
+        let symbols: Vec<usize> = (0..5000).map(|i| [1, 1, 3, 1, 0, 2, 1][i % 7]).collect();
+        let mut bytes = encode(&symbols);
+        let len = bytes.len();
+        bytes.extend_from_slice(b"trailing");
+        assert_eq!(decode(&bytes, symbols.len()).unwrap(), (symbols, len));
+    }
+
+    #[test]
+    fn test_likely_symbols_cost_little() {
+        // About 0.005 bits each, so roughly 61 bytes plus the flush.
+        let bytes = encode(&vec![1; 100_000]);
+        assert!(bytes.len() < 72, "{}", bytes.len());
+    }
+
+    #[test]
+    fn test_carry_propagation() {
+        // Alternating extremes of the range force carries through 0xFF runs.
+        let symbols: Vec<usize> = (0..20_000)
+            .map(|i| if i % 3 == 0 { 3 } else { 1 })
+            .collect();
+        let bytes = encode(&symbols);
+        assert_eq!(decode(&bytes, symbols.len()).unwrap().0, symbols);
+    }
+
+    #[test]
+    fn test_bit_models_roundtrip() {
+        let values: Vec<u32> = (0..2000).map(|i| [3, 3, 200, 3, 17][i % 5]).collect();
+        let mut encoder = RangeEncoder::new();
+        let (mut forward, mut reverse) = ([PROB_INIT; 256], [PROB_INIT; 256]);
+        for &value in &values {
+            tree(&mut encoder, &mut forward, 8, value).unwrap();
+            reverse_tree(&mut encoder, &mut reverse, 8, value).unwrap();
+            encoder.direct(value & 1).unwrap();
+        }
+        let bytes = encoder.finish();
+        // A bit per direct bit, and not much over the 1.4-bit entropy of
+        // the value mix per tree once the models adapt.
+        assert!(bytes.len() < values.len() * 5 / 8, "{}", bytes.len());
+
+        let mut decoder = RangeDecoder::new(&bytes).unwrap();
+        let (mut forward, mut reverse) = ([PROB_INIT; 256], [PROB_INIT; 256]);
+        for &value in &values {
+            assert_eq!(tree(&mut decoder, &mut forward, 8, 0).unwrap(), value);
+            assert_eq!(
+                reverse_tree(&mut decoder, &mut reverse, 8, 0).unwrap(),
+                value
+            );
+            assert_eq!(decoder.direct(0).unwrap(), value & 1);
+        }
+        assert_eq!(decoder.bytes_consumed(), bytes.len());
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let bytes = encode(&[0, 2, 0, 2, 0, 2]);
+        assert!(decode(&bytes[..bytes.len() - 1], 6).is_err());
+        assert!(RangeDecoder::new(&[0, 0]).is_err());
+        assert!(RangeDecoder::new(&[1, 0, 0, 0, 0]).is_err());
+    }
+}
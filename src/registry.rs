@@ -0,0 +1,173 @@
+//! Codecs looked up by name at run time.
+//!
+//! [`codec_by_name`] turns a name from a configuration file or command line
+//! into a boxed codec, so an application can switch algorithms without a
+//! rebuild. Every [`Algorithm`] is built in under its name with its default
+//! settings; applications add their own codecs, or their
+//! own settings of a built-in one, with [`register`], and
+//! [`codec_by_name_with_level`] tunes a built-in one to a compression level.
+//! Registrations are process-wide and last until exit. The boxes are `Send + Sync`, so one
+//! codec can be shared between threads.
+
+use std::sync::{PoisonError, RwLock};
+
+use crate::algorithm::Algorithm;
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::traits::Codec;
+
+/// Creates a codec for a registered name; called once per lookup.
+pub type CodecFactory = fn() -> Box<dyn Codec + Send + Sync>;
+
+/// Codecs added through [`register`], in registration order.
+static REGISTERED: RwLock<Vec<(String, CodecFactory)>> = RwLock::new(Vec::new());
+
+/// Creates the codec registered as `name`, compared ignoring ASCII case, or
+/// `None` if no codec has that name.
+#[must_use]
+pub fn codec_by_name(name: &str) -> Option<Box<dyn Codec + Send + Sync>> {
+    codec_by_name_with_level(name, CompressionLevel::DEFAULT)
+}
+
+/// [`codec_by_name`] with a built-in codec tuned to `level`, as by
+/// [`Algorithm::new_codec_with_level`]. Registered codecs come from their
+/// factory as is.
+#[must_use]
+pub fn codec_by_name_with_level(
+    name: &str,
+    level: CompressionLevel,
+) -> Option<Box<dyn Codec + Send + Sync>> {
+    if let Ok(algorithm) = name.parse::<Algorithm>() {
+        return Some(algorithm.new_codec_with_level(level));
+    }
+    let factory = REGISTERED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|&(_, factory)| factory)?;
+    Some(factory())
+}
+
+/// Makes [`codec_by_name`] create codecs with `factory` for `name`.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `name` is empty or already
+/// names a codec, built-in or registered, ignoring ASCII case.
+pub fn register(name: &str, factory: CodecFactory) -> Result<()> {
+    if name.is_empty() {
+        return Err(CompressionError::InvalidInput(
+            "codec name is empty".to_string(),
+        ));
+    }
+    let mut registered = REGISTERED.write().unwrap_or_else(PoisonError::into_inner);
+    let taken = name.parse::<Algorithm>().is_ok()
+        || registered
+            .iter()
+            .any(|(known, _)| known.eq_ignore_ascii_case(name));
+    if taken {
+        return Err(CompressionError::InvalidInput(format!(
+            "codec name {name:?} is already registered"
+        )));
+    }
+    registered.push((name.to_string(), factory));
+    drop(registered);
+    Ok(())
+}
+
+/// Every name [`codec_by_name`] accepts: the [`Algorithm`] names, then
+/// registered ones in the order they were added.
+#[must_use]
+pub fn names() -> Vec<String> {
+    let registered = REGISTERED.read().unwrap_or_else(PoisonError::into_inner);
+    Algorithm::ALL
+        .iter()
+        .map(|algorithm| algorithm.name().to_string())
+        .chain(registered.iter().map(|(name, _)| name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunked, Compressor, Decompressor, Lz77, Stored};
+
+    #[test]
+    fn test_algorithms_are_built_in() {
+        for algorithm in Algorithm::ALL {
+            let codec = codec_by_name(algorithm.name()).unwrap();
+            assert_eq!(
+                Compressor::name(&codec),
+                Compressor::name(&algorithm.new_codec())
+            );
+        }
+        let mut names = names();
+        assert_eq!(names[0], Algorithm::ALL[0].name());
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_lookup_ignores_case() {
+        assert_eq!(Compressor::name(&codec_by_name("LZ77").unwrap()), "LZ77");
+        assert_eq!(Compressor::name(&codec_by_name("Lz4").unwrap()), "LZ4");
+        assert!(codec_by_name("gzip").is_none());
+        assert!(codec_by_name("").is_none());
+    }
+
+    #[test]
+    fn test_lookup_with_level() {
+        let input = b"levels chosen next to the name in a config file. ".repeat(40);
+        let best = codec_by_name_with_level("lz77", CompressionLevel::BEST).unwrap();
+        let expected = Lz77::new()
+            .with_level(CompressionLevel::BEST)
+            .compress(&input)
+            .unwrap();
+        assert_eq!(best.compress(&input).unwrap(), expected);
+        assert!(codec_by_name_with_level("gzip", CompressionLevel::BEST).is_none());
+
+        register("stored_copy", || Box::new(Stored::new())).unwrap();
+        let stored = codec_by_name_with_level("stored_copy", CompressionLevel::BEST).unwrap();
+        assert_eq!(Compressor::name(&stored), "Stored");
+    }
+
+    #[test]
+    fn test_register() {
+        register("lz77_wide", || Box::new(Lz77::with_config(32768, 255))).unwrap();
+        let codec = codec_by_name("LZ77_WIDE").unwrap();
+        let input = b"registered codecs keep their own settings. ".repeat(10);
+        let compressed = codec.compress(&input).unwrap();
+        assert_eq!(Lz77::new().decompress(&compressed).unwrap(), input);
+        assert!(names().contains(&"lz77_wide".to_string()));
+
+        for taken in ["lz77_wide", "Lz77_Wide", "lz4", ""] {
+            assert!(matches!(
+                register(taken, || Box::new(Stored::new())),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_boxed_codecs_compose_and_cross_threads() {
+        let chunked = Chunked::with_threshold(codec_by_name("lz4").unwrap(), 64, 64);
+        let input = b"boxed codecs nest inside wrappers".repeat(10);
+        let compressed = chunked.compress(&input).unwrap();
+        assert_eq!(chunked.decompress(&compressed).unwrap(), input);
+
+        let codec = codec_by_name("huffman").unwrap();
+        let compressed = std::thread::scope(|scope| {
+            scope
+                .spawn(|| codec.compress(b"shared across threads").unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(
+            codec.decompress(&compressed).unwrap(),
+            b"shared across threads"
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Symbol of the first rule; symbols below it are literal bytes.
+pub const FIRST_RULE: u32 = 256;
+const NONE: usize = usize::MAX;
+
+/// A straight-line grammar deriving exactly one string.
+///
+/// Symbols below 256 (`FIRST_RULE`) are bytes; symbol `256 + i` is rule
+/// `i`, which expands to its two symbols in turn. A rule only refers to
+/// bytes and earlier rules, so every rule derives a fixed phrase that
+/// occurred at least twice when the rule was made. The phrases are the
+/// repeated patterns of the input, and [`Grammar::sequence`] shows where
+/// they occur.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Grammar {
+    rules: Vec<(u32, u32)>,
+    sequence: Vec<u32>,
+}
+
+impl Grammar {
+    /// Builds the grammar of `input` with Re-Pair: the most frequent
+    /// adjacent pair of symbols is replaced by a new rule, over and over,
+    /// until no pair occurs twice. Ties go to the smallest pair.
+    #[must_use]
+    pub fn build(input: &[u8]) -> Self {
+        Builder::new(input).run()
+    }
+
+    /// Rules in the order they were made, each as its left and right
+    /// symbol.
+    #[must_use]
+    pub fn rules(&self) -> &[(u32, u32)] {
+        &self.rules
+    }
+
+    /// The top-level symbols, which expand to the whole input.
+    #[must_use]
+    pub fn sequence(&self) -> &[u32] {
+        &self.sequence
+    }
+
+    /// The bytes `symbol` derives, or `None` if it names no rule.
+    #[must_use]
+    pub fn expand_symbol(&self, symbol: u32) -> Option<Vec<u8>> {
+        let mut output = Vec::new();
+        expand_into(&self.rules, symbol, &mut output, usize::MAX).ok()?;
+        Some(output)
+    }
+
+    /// The phrase rule `index` derives, or `None` past the last rule.
+    #[must_use]
+    pub fn expand_rule(&self, index: usize) -> Option<Vec<u8>> {
+        self.expand_symbol(FIRST_RULE.checked_add(u32::try_from(index).ok()?)?)
+    }
+
+    /// The bytes the whole grammar derives.
+    #[must_use]
+    pub fn expand(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        for &symbol in &self.sequence {
+            // Built grammars only use their own rules, so this cannot fail.
+            let _ = expand_into(&self.rules, symbol, &mut output, usize::MAX);
+        }
+        output
+    }
+}
+
+/// Appends the expansion of `symbol` to `output`, failing if it names a
+/// missing rule or would take `output` past `limit` bytes.
+fn expand_into(
+    rules: &[(u32, u32)],
+    symbol: u32,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> Result<()> {
+    let mut stack = vec![symbol];
+    while let Some(symbol) = stack.pop() {
+        if let Ok(byte) = u8::try_from(symbol) {
+            if output.len() == limit {
+                return Err(CompressionError::CorruptedData);
+            }
+            output.push(byte);
+            continue;
+        }
+        let &(left, right) = usize::try_from(symbol - FIRST_RULE)
+            .ok()
+            .and_then(|index| rules.get(index))
+            .ok_or(CompressionError::CorruptedData)?;
+        stack.push(right);
+        stack.push(left);
+    }
+    Ok(())
+}
+
+/// Re-Pair over a doubly linked sequence, with pair counts kept up to date
+/// as replacements change the neighbours of each new symbol.
+struct Builder {
+    symbols: Vec<u32>,
+    prev: Vec<usize>,
+    next: Vec<usize>,
+    counts: HashMap<(u32, u32), usize>,
+    /// Left positions each pair has occupied; entries go stale and are
+    /// checked when used.
+    positions: HashMap<(u32, u32), Vec<usize>>,
+    /// Candidate pairs by count; an entry is stale once its count moved.
+    queue: BinaryHeap<(usize, Reverse<(u32, u32)>)>,
+    rules: Vec<(u32, u32)>,
+}
+
+impl Builder {
+    fn new(input: &[u8]) -> Self {
+        let len = input.len();
+        let mut builder = Self {
+            symbols: input.iter().map(|&byte| u32::from(byte)).collect(),
+            prev: (0..len).map(|i| i.checked_sub(1).unwrap_or(NONE)).collect(),
+            next: (1..=len).map(|i| if i < len { i } else { NONE }).collect(),
+            counts: HashMap::new(),
+            positions: HashMap::new(),
+            queue: BinaryHeap::new(),
+            rules: Vec::new(),
+        };
+        for i in 1..len {
+            builder.add(i - 1);
+        }
+        builder
+    }
+
+    fn pair_at(&self, left: usize) -> (u32, u32) {
+        (self.symbols[left], self.symbols[self.next[left]])
+    }
+
+    /// Counts the pair starting at `left`.
+    fn add(&mut self, left: usize) {
+        let pair = self.pair_at(left);
+        let count = self.counts.entry(pair).or_default();
+        *count += 1;
+        if *count >= 2 {
+            self.queue.push((*count, Reverse(pair)));
+        }
+        self.positions.entry(pair).or_default().push(left);
+    }
+
+    /// Uncounts the pair starting at `left`.
+    fn remove(&mut self, left: usize) {
+        let pair = self.pair_at(left);
+        if let Some(count) = self.counts.get_mut(&pair) {
+            *count = count.saturating_sub(1);
+            if *count >= 2 {
+                self.queue.push((*count, Reverse(pair)));
+            }
+        }
+    }
+
+    /// Non-overlapping live occurrences of `pair`, left to right.
+    fn occurrences(&mut self, pair: (u32, u32)) -> Vec<usize> {
+        let mut candidates = self.positions.remove(&pair).unwrap_or_default();
+        candidates.sort_unstable();
+        candidates.dedup();
+        let mut taken: Vec<usize> = Vec::with_capacity(candidates.len());
+        for left in candidates {
+            let live = self.prev[left] != left
+                && self.next[left] != NONE
+                && self.pair_at(left) == pair
+                && taken.last().is_none_or(|&last| self.next[last] != left);
+            if live {
+                taken.push(left);
+            }
+        }
+        taken
+    }
+
+    fn run(mut self) -> Grammar {
+        while let Some((count, Reverse(pair))) = self.queue.pop() {
+            if self.counts.get(&pair) != Some(&count) {
+                continue;
+            }
+            self.counts.remove(&pair);
+            let occurrences = self.occurrences(pair);
+            if occurrences.len() < 2 {
+                continue;
+            }
+            let symbol = FIRST_RULE
+                + u32::try_from(self.rules.len()).expect("rules are fewer than input bytes");
+            self.rules.push(pair);
+            for left in occurrences {
+                let right = self.next[left];
+                let before = self.prev[left];
+                let after = self.next[right];
+                if before != NONE {
+                    self.remove(before);
+                }
+                if after != NONE {
+                    self.remove(right);
+                }
+                self.symbols[left] = symbol;
+                self.next[left] = after;
+                // A node that is its own predecessor has been removed.
+                self.prev[right] = right;
+                if after != NONE {
+                    self.prev[after] = left;
+                    self.add(left);
+                }
+                if before != NONE {
+                    self.add(before);
+                }
+            }
+        }
+
+        let mut sequence = Vec::new();
+        let mut node = if self.symbols.is_empty() { NONE } else { 0 };
+        while node != NONE {
+            sequence.push(self.symbols[node]);
+            node = self.next[node];
+        }
+        Grammar {
+            rules: self.rules,
+            sequence,
+        }
+    }
+}
+
+/// Grammar-based codec: [`Grammar::build`] followed by a plain
+/// serialization of the rules and the top-level sequence.
+///
+/// Re-Pair finds repeats at every scale at once, long phrases built from
+/// shorter ones, and [`RePair::grammar`] hands the result to callers that
+/// want the patterns rather than the bytes. Building is slower than any LZ
+/// codec and takes memory proportional to the input. Symbols are stored as
+/// varints with no entropy coding, so every byte above 127 and every rule
+/// symbol takes two bytes or more, and incompressible data grows by up to
+/// half. Output layout: `[original length: varint][rule count:
+/// varint][rules: left, right varints][sequence length:
+/// varint][sequence: varints]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RePair;
+
+impl RePair {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// The grammar [`RePair`] would store for `input`.
+    #[must_use]
+    pub fn grammar(input: &[u8]) -> Grammar {
+        Grammar::build(input)
+    }
+}
+
+fn read_len(input: &[u8], pos: &mut usize) -> Result<usize> {
+    usize::try_from(varint::read_u64(input, pos)?).map_err(|_| CompressionError::CorruptedData)
+}
+
+impl Compressor for RePair {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let grammar = Grammar::build(input);
+        let mut output = Vec::new();
+        varint::write_u64(&mut output, input.len() as u64);
+        varint::write_u64(&mut output, grammar.rules.len() as u64);
+        for &(left, right) in &grammar.rules {
+            varint::write_u64(&mut output, u64::from(left));
+            varint::write_u64(&mut output, u64::from(right));
+        }
+        varint::write_u64(&mut output, grammar.sequence.len() as u64);
+        for &symbol in &grammar.sequence {
+            varint::write_u64(&mut output, u64::from(symbol));
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "RePair"
+    }
+}
+
+impl Decompressor for RePair {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "RePair"
+    }
+}
+
+impl PartialDecompressor for RePair {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = read_len(input, &mut pos)?;
+
+        let rule_count = read_len(input, &mut pos)?;
+        let mut rules = Vec::with_capacity(rule_count.min(input.len() / 2));
+        for index in 0..rule_count {
+            // Rules may only use bytes and earlier rules.
+            let limit = u64::from(FIRST_RULE) + index as u64;
+            let mut symbol = || {
+                let value = varint::read_u64(input, &mut pos)?;
+                if value >= limit {
+                    return Err(CompressionError::CorruptedData);
+                }
+                u32::try_from(value).map_err(|_| CompressionError::CorruptedData)
+            };
+            let left = symbol()?;
+            let right = symbol()?;
+            rules.push((left, right));
+        }
+
+        let sequence_len = read_len(input, &mut pos)?;
+        let mut output = Vec::with_capacity(original_len.min(input.len().saturating_mul(64)));
+        for _ in 0..sequence_len {
+            let symbol = u32::try_from(varint::read_u64(input, &mut pos)?)
+                .map_err(|_| CompressionError::CorruptedData)?;
+            expand_into(&rules, symbol, &mut output, original_len)?;
+        }
+        if output.len() != original_len {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let codec = RePair::new();
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        let grammar = RePair::grammar(input);
+        assert_eq!(grammar.expand(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_repair_name() {
+        assert_eq!(Compressor::name(&RePair::new()), "RePair");
+        assert_eq!(Decompressor::name(&RePair::new()), "RePair");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(b"");
+        roundtrip(b"a");
+        roundtrip(b"aa");
+        roundtrip(b"aaa");
+        roundtrip(b"aaaa");
+        roundtrip(b"abababab");
+        roundtrip(&[0; 1000]);
+        roundtrip(&(0..=255).collect::<Vec<u8>>());
+        let mut state = 11u32;
+        let noise: Vec<u8> = (0..3000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state.to_le_bytes()[2] & 0x0F
+            })
+            .collect();
+        roundtrip(&noise);
+    }
+
+    #[test]
+    fn test_grammar_of_classic_example() {
+        let grammar = Grammar::build(b"abracadabra");
+        // "ab", "br" and "ra" all occur twice; "ab" is smallest. Then "ra",
+        // and finally the two rules side by side.
+        assert_eq!(grammar.rules()[0], (u32::from(b'a'), u32::from(b'b')));
+        let abra = FIRST_RULE + u32::try_from(grammar.rules().len()).unwrap() - 1;
+        assert_eq!(grammar.expand_symbol(abra).unwrap(), b"abra");
+        assert_eq!(
+            grammar.sequence(),
+            [
+                abra,
+                u32::from(b'c'),
+                u32::from(b'a'),
+                u32::from(b'd'),
+                abra
+            ]
+        );
+        assert_eq!(grammar.expand_symbol(abra + 1), None);
+        assert_eq!(grammar.expand_rule(grammar.rules().len()), None);
+        assert_eq!(grammar.expand_symbol(u32::from(b'x')).unwrap(), b"x");
+    }
+
+    #[test]
+    fn test_every_rule_is_a_repeat() {
+        let text = b"to be or not to be, that is the question: to be or not";
+        let grammar = Grammar::build(text);
+        assert!(!grammar.rules().is_empty());
+        for i in 0..grammar.rules().len() {
+            let phrase = grammar.expand_rule(i).unwrap();
+            let hits = text.windows(phrase.len()).filter(|w| *w == phrase).count();
+            assert!(hits >= 2, "{:?}", String::from_utf8_lossy(&phrase));
+        }
+        let longest = (0..grammar.rules().len())
+            .map(|i| grammar.expand_rule(i))
+            .map(|phrase| phrase.unwrap().len())
+            .max();
+        // Pairs form greedily, so phrases need not start on word
+        // boundaries: here the longest is "e or not".
+        assert_eq!(longest, Some(b"e or not".len()));
+    }
+
+    #[test]
+    fn test_repeats_compress() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(50);
+        let compressed = roundtrip(&text);
+        assert!(compressed.len() * 10 < text.len(), "{}", compressed.len());
+        // Runs collapse to a logarithmic number of rules.
+        assert!(Grammar::build(&[7; 4096]).rules().len() <= 12);
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = RePair::new();
+        let stream = [
+            codec.compress(b"abcabcabc").unwrap(),
+            codec.compress(b"").unwrap(),
+            codec.compress(&[5; 64]).unwrap(),
+        ]
+        .concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [b"abcabcabc".to_vec(), Vec::new(), vec![5; 64]]);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = RePair::new();
+        assert!(codec.decompress(&[]).is_err());
+        // A rule referring to itself.
+        assert!(
+            codec
+                .decompress(&[2, 1, 0x80, 0x02, 0x61, 1, 0x80, 0x02])
+                .is_err()
+        );
+        // An undefined rule in the sequence.
+        assert!(codec.decompress(&[2, 0, 1, 0x80, 0x02]).is_err());
+        // A rule doubling itself past the original length.
+        let mut bomb = vec![1, 20, b'a', b'a'];
+        for i in 0..19u8 {
+            bomb.extend_from_slice(&[0x80 + i, 0x02, 0x80 + i, 0x02]);
+        }
+        bomb.extend_from_slice(&[1, 0x80 + 19, 0x02]);
+        assert!(codec.decompress(&bomb).is_err());
+        let mut trailing = codec.compress(b"abab").unwrap();
+        trailing.push(0);
+        assert!(codec.decompress(&trailing).is_err());
+    }
+}
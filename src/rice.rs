@@ -0,0 +1,291 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+
+pub const MAX_K: u8 = 31;
+pub const ADAPTIVE_FLAG: u8 = 0x80;
+pub const HEADER_LEN: usize = 5;
+/// Quotients at or above this are escaped and the value stored verbatim.
+pub const ESCAPE_QUOTIENT: u32 = 32;
+/// Number of samples after which the adaptive statistics are halved.
+const ADAPT_RESET: u32 = 64;
+
+/// Running estimate of the best Rice parameter, in the style of LOCO-I.
+#[derive(Debug, Clone, Copy)]
+struct Adapter {
+    sum: u64,
+    count: u32,
+}
+
+impl Adapter {
+    const fn new(k: u8) -> Self {
+        Self {
+            sum: 1u64 << k,
+            count: 1,
+        }
+    }
+
+    fn k(self) -> u8 {
+        let mut k = 0;
+        while k < MAX_K && (u64::from(self.count) << k) < self.sum {
+            k += 1;
+        }
+        k
+    }
+
+    const fn update(&mut self, value: u32) {
+        self.sum += value as u64;
+        self.count += 1;
+        if self.count >= ADAPT_RESET {
+            self.sum /= 2;
+            self.count /= 2;
+        }
+    }
+}
+
+/// Golomb–Rice coder for sequences of small unsigned integers.
+///
+/// Each value is split into a unary-coded quotient `value >> k` and a
+/// `k`-bit remainder. In adaptive mode `k` tracks the running mean of the
+/// values seen so far, so no tuning is needed for drifting distributions.
+/// Output layout: `[flags | k: u8][count: u32][bits]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rice {
+    k: u8,
+    adaptive: bool,
+}
+
+impl Default for Rice {
+    fn default() -> Self {
+        Self::adaptive()
+    }
+}
+
+impl Rice {
+    /// Creates a coder with a fixed parameter `k` (0 to 31).
+    #[must_use]
+    pub const fn new(k: u8) -> Self {
+        Self { k, adaptive: false }
+    }
+
+    /// Creates a coder that adapts `k` to the data as it goes.
+    #[must_use]
+    pub const fn adaptive() -> Self {
+        Self {
+            k: 0,
+            adaptive: true,
+        }
+    }
+
+    #[must_use]
+    pub const fn k(&self) -> u8 {
+        self.k
+    }
+
+    #[must_use]
+    pub const fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    /// Encodes `values` into a self-describing byte buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `k` exceeds 31 or there
+    /// are more than `u32::MAX` values.
+    pub fn encode(&self, values: &[u32]) -> Result<Vec<u8>> {
+        if self.k > MAX_K {
+            return Err(CompressionError::InvalidInput(format!(
+                "rice parameter {} exceeds {MAX_K}",
+                self.k
+            )));
+        }
+        let count = u32::try_from(values.len())
+            .map_err(|_| CompressionError::InvalidInput("too many values".to_string()))?;
+
+        let mut output = Vec::with_capacity(HEADER_LEN + values.len());
+        output.push(if self.adaptive { ADAPTIVE_FLAG } else { self.k });
+        output.extend_from_slice(&count.to_le_bytes());
+
+        let mut writer = BitWriter::new();
+        let mut adapter = Adapter::new(self.k);
+        for &value in values {
+            let k = if self.adaptive { adapter.k() } else { self.k };
+            write_value(&mut writer, value, k);
+            adapter.update(value);
+        }
+        output.extend_from_slice(&writer.finish());
+
+        Ok(output)
+    }
+
+    /// Decodes a buffer produced by [`Rice::encode`]. The parameters are
+    /// read from the buffer, so any `Rice` instance can decode it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the buffer is truncated
+    /// or malformed.
+    pub fn decode(&self, input: &[u8]) -> Result<Vec<u32>> {
+        if input.len() < HEADER_LEN {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let adaptive = input[0] & ADAPTIVE_FLAG != 0;
+        let fixed_k = input[0] & !ADAPTIVE_FLAG;
+        if fixed_k > MAX_K || (adaptive && fixed_k != 0) {
+            return Err(CompressionError::CorruptedData);
+        }
+        let count = u32::from_le_bytes([input[1], input[2], input[3], input[4]]) as usize;
+
+        let payload = &input[HEADER_LEN..];
+        let mut reader = BitReader::new(payload);
+        let mut values = Vec::with_capacity(count.min(payload.len() * 8));
+        let mut adapter = Adapter::new(fixed_k);
+        for _ in 0..count {
+            let k = if adaptive { adapter.k() } else { fixed_k };
+            let value = read_value(&mut reader, k)?;
+            values.push(value);
+            adapter.update(value);
+        }
+
+        if reader.bytes_consumed() != payload.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        Ok(values)
+    }
+}
+
+fn write_value(writer: &mut BitWriter, value: u32, k: u8) {
+    let quotient = value >> k;
+    if quotient >= ESCAPE_QUOTIENT {
+        writer.write_bits(u64::MAX, ESCAPE_QUOTIENT);
+        writer.write_bits(u64::from(value), u32::BITS);
+        return;
+    }
+    writer.write_bits((1u64 << quotient) - 1, quotient);
+    writer.write_bit(false);
+    writer.write_bits(u64::from(value), u32::from(k));
+}
+
+fn read_value(reader: &mut BitReader, k: u8) -> Result<u32> {
+    let mut quotient = 0u32;
+    while quotient < ESCAPE_QUOTIENT && reader.read_bit().ok_or(CompressionError::CorruptedData)? {
+        quotient += 1;
+    }
+
+    if quotient == ESCAPE_QUOTIENT {
+        let value = reader
+            .read_bits(u32::BITS)
+            .ok_or(CompressionError::CorruptedData)?;
+        return u32::try_from(value).map_err(|_| CompressionError::CorruptedData);
+    }
+
+    let remainder = reader
+        .read_bits(u32::from(k))
+        .ok_or(CompressionError::CorruptedData)?;
+    let value = (u64::from(quotient) << k) | remainder;
+    u32::try_from(value).map_err(|_| CompressionError::CorruptedData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(rice: Rice, values: &[u32]) -> Vec<u8> {
+        let encoded = rice.encode(values).unwrap();
+        assert_eq!(rice.decode(&encoded).unwrap(), values);
+        encoded
+    }
+
+    #[test]
+    fn test_rice_new() {
+        let rice = Rice::new(3);
+        assert_eq!(rice.k(), 3);
+        assert!(!rice.is_adaptive());
+        assert!(Rice::default().is_adaptive());
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        let encoded = roundtrip(Rice::new(2), &[]);
+        assert_eq!(encoded.len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn test_known_bits() {
+        // k = 2: 5 -> quotient 1 ("10"), remainder 01; 2 -> "0" + 10.
+        let encoded = Rice::new(2).encode(&[5, 2]).unwrap();
+        assert_eq!(&encoded[HEADER_LEN..], &[0b1001_0100]);
+    }
+
+    #[test]
+    fn test_roundtrip_small_values() {
+        let values: Vec<u32> = (0..500).map(|i| i % 7).collect();
+        let encoded = roundtrip(Rice::new(2), &values);
+        assert!(encoded.len() < values.len());
+    }
+
+    #[test]
+    fn test_roundtrip_k_zero() {
+        roundtrip(Rice::new(0), &[0, 1, 2, 3, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_roundtrip_escape() {
+        roundtrip(Rice::new(0), &[0, u32::MAX, 1, 1_000_000, 31, 32]);
+    }
+
+    #[test]
+    fn test_roundtrip_max_k() {
+        roundtrip(Rice::new(MAX_K), &[0, u32::MAX, 12345]);
+    }
+
+    #[test]
+    fn test_roundtrip_adaptive() {
+        let mut values: Vec<u32> = (0..200).map(|i| i % 4).collect();
+        values.extend((0..200).map(|i| 5000 + i * 3));
+        roundtrip(Rice::adaptive(), &values);
+    }
+
+    #[test]
+    fn test_adaptive_beats_bad_fixed_k() {
+        let values: Vec<u32> = (0..1000).map(|i| 1000 + i % 50).collect();
+        let adaptive = Rice::adaptive().encode(&values).unwrap();
+        let fixed = Rice::new(0).encode(&values).unwrap();
+        assert!(adaptive.len() < fixed.len());
+    }
+
+    #[test]
+    fn test_decode_uses_stored_parameters() {
+        let encoded = Rice::new(5).encode(&[40, 41, 42]).unwrap();
+        assert_eq!(Rice::adaptive().decode(&encoded).unwrap(), vec![40, 41, 42]);
+    }
+
+    #[test]
+    fn test_invalid_k() {
+        let result = Rice::new(32).encode(&[1]);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let encoded = Rice::new(1).encode(&[100, 200, 300]).unwrap();
+        let result = Rice::new(1).decode(&encoded[..encoded.len() - 1]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+        assert!(Rice::new(1).decode(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_decode_trailing_bytes() {
+        let mut encoded = Rice::new(1).encode(&[1, 2, 3]).unwrap();
+        encoded.push(0);
+        assert!(Rice::new(1).decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_decode_invalid_header() {
+        let result = Rice::new(1).decode(&[40, 0, 0, 0, 0]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+}
@@ -1,74 +1,491 @@
+use crate::bitstream::{BitReader, BitWriter};
 use crate::error::{CompressionError, Result};
-use crate::traits::{Compressor, Decompressor};
+use crate::limits::DecompressionLimits;
+use crate::sink::{Sink, SliceSink};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
 
-const MAX_RUN_LENGTH: u8 = 255;
+pub const MAX_RUN_LENGTH: u8 = 255;
+/// Bytes per `[run length][byte]` pair.
+pub const PAIR_LEN: usize = 2;
+/// Encoding of empty input: a single zero-length run, valid only on its own.
+pub const EMPTY_FRAME: [u8; PAIR_LEN] = [0, 0];
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Rle;
+/// Bytes of the escaped format's `[original length: u32]` header.
+pub const ESCAPED_HEADER_LEN: usize = 4;
+/// Set in an escaped-format control byte to mark a run rather than literals.
+pub const RUN_FLAG: u8 = 0x80;
+/// Shortest run the escaped format encodes as a run.
+pub const MIN_ESCAPED_RUN: usize = 3;
+/// Longest literal block behind a single control byte.
+pub const MAX_LITERAL_BLOCK: usize = 128;
+const MAX_ESCAPED_RUN: usize = MIN_ESCAPED_RUN + (RUN_FLAG - 1) as usize;
+
+/// Longest run behind a single varint-format count.
+pub const MAX_VARINT_RUN: u64 = u32::MAX as u64;
+
+/// Output format produced by [`Rle`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RleMode {
+    /// `[run length: u8][byte]` pairs; unrepeated bytes double in size.
+    #[default]
+    Pairs,
+    /// `[original length: u32]` followed by PackBits-style blocks: a control
+    /// byte below `RUN_FLAG` introduces `control + 1` literal bytes, and
+    /// one with the flag set repeats the next byte `(control & 0x7F) + 3`
+    /// times. Incompressible input grows by under 1%.
+    Escaped,
+    /// `[run length: varint][byte]` pairs, so a run of up to
+    /// `MAX_VARINT_RUN` bytes costs at most six bytes. Empty input is
+    /// `EMPTY_FRAME`.
+    Varint,
+    /// `[original length: varint]` followed by a bitstream of `[byte: 8 bits]
+    /// [run length - 1: exp-Golomb]` pairs, zero-padded to a whole byte. A
+    /// run costs `2 * floor(log2(run)) + 1` bits, so isolated bytes and long
+    /// runs are both cheap, as in fax-like bilevel scans where run lengths
+    /// vary wildly.
+    Golomb,
+}
+
+/// Run-length coder.
+///
+/// The default [`RleMode::Pairs`] format has no header; runs longer than
+/// `MAX_RUN_LENGTH` are split across pairs. Runs are counted in elements
+/// of `element_size` bytes (one by default), so repeated `u16` samples or
+/// RGBA pixels collapse like repeated bytes; the byte in each format's
+/// description is then a whole element. Both sides must use the same mode
+/// and element size.
+#[derive(Debug, Clone, Copy)]
+pub struct Rle {
+    mode: RleMode,
+    element_size: u8,
+}
+
+impl Default for Rle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Rle {
     #[must_use]
     pub const fn new() -> Self {
-        Self
+        Self::with_mode(RleMode::Pairs)
+    }
+
+    /// Creates a coder using the [`RleMode::Escaped`] format, which stores
+    /// unrepeated stretches as literal blocks.
+    #[must_use]
+    pub const fn escaped() -> Self {
+        Self::with_mode(RleMode::Escaped)
+    }
+
+    /// Creates a coder using the [`RleMode::Varint`] format, which collapses
+    /// long runs into a single pair.
+    #[must_use]
+    pub const fn varint() -> Self {
+        Self::with_mode(RleMode::Varint)
+    }
+
+    /// Creates a coder using the [`RleMode::Golomb`] format, which codes run
+    /// lengths in a number of bits that grows with their logarithm.
+    #[must_use]
+    pub const fn golomb() -> Self {
+        Self::with_mode(RleMode::Golomb)
+    }
+
+    #[must_use]
+    pub const fn with_mode(mode: RleMode) -> Self {
+        Self {
+            mode,
+            element_size: 1,
+        }
+    }
+
+    /// Detects runs of `element_size`-byte elements instead of single
+    /// bytes. Input lengths must then be a multiple of the element size.
+    #[must_use]
+    pub const fn with_element_size(mut self, element_size: u8) -> Self {
+        self.element_size = element_size;
+        self
+    }
+
+    #[must_use]
+    pub const fn mode(&self) -> RleMode {
+        self.mode
+    }
+
+    #[must_use]
+    pub const fn element_size(&self) -> u8 {
+        self.element_size
+    }
+
+    fn checked_element_size(self) -> Result<usize> {
+        if self.element_size == 0 {
+            return Err(CompressionError::InvalidInput(
+                "element size must be non-zero".to_string(),
+            ));
+        }
+        Ok(usize::from(self.element_size))
+    }
+
+    /// Writes the frame for `input` to `output`. Golomb frames are built in
+    /// a bit buffer and copied over; the other modes write directly.
+    fn compress_to(self, input: &[u8], output: &mut impl Sink) -> Result<()> {
+        let size = self.checked_element_size()?;
+        if !input.len().is_multiple_of(size) {
+            return Err(CompressionError::InvalidInput(format!(
+                "input length {} is not a multiple of the {size}-byte element size",
+                input.len()
+            )));
+        }
+
+        match self.mode {
+            RleMode::Pairs => compress_pairs(input, size, output),
+            RleMode::Escaped => compress_escaped(input, size, output),
+            RleMode::Varint => compress_varint(input, size, output),
+            RleMode::Golomb => output.extend_from_slice(&compress_golomb(input, size)),
+        }
     }
 }
 
-impl Compressor for Rle {
-    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if input.is_empty() {
-            return Ok(Vec::new());
+/// Number of repeats of the `size`-byte element at `start`, capped at `max`.
+fn run_length(input: &[u8], start: usize, max: usize, size: usize) -> usize {
+    let element = &input[start..start + size];
+    input[start..]
+        .chunks_exact(size)
+        .take(max)
+        .take_while(|&candidate| candidate == element)
+        .count()
+}
+
+fn compress_pairs(input: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    if input.is_empty() {
+        return output.extend_from_slice(&EMPTY_FRAME);
+    }
+
+    output.reserve(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        let run = run_length(input, i, usize::from(MAX_RUN_LENGTH), size);
+        output.push(u8::try_from(run).unwrap_or(MAX_RUN_LENGTH))?;
+        output.extend_from_slice(&input[i..i + size])?;
+        i += run * size;
+    }
+
+    Ok(())
+}
+
+fn decompress_pairs(input: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    if input == EMPTY_FRAME {
+        return Ok(());
+    }
+
+    let pair_len = 1 + size;
+    if input.is_empty() || !input.len().is_multiple_of(pair_len) {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    for chunk in input.chunks_exact(pair_len) {
+        let (&count, element) = chunk.split_first().ok_or(CompressionError::CorruptedData)?;
+
+        if count == 0 {
+            return Err(CompressionError::CorruptedData);
         }
 
-        let mut output = Vec::with_capacity(input.len());
-        let mut i = 0;
+        output.extend_run(element, usize::from(count))?;
+    }
 
-        while i < input.len() {
-            let current_byte = input[i];
-            let mut run_length: u8 = 1;
+    Ok(())
+}
 
-            while i + usize::from(run_length) < input.len()
-                && input[i + usize::from(run_length)] == current_byte
-                && run_length < MAX_RUN_LENGTH
-            {
-                run_length += 1;
-            }
+/// Output length of a varint-run frame, summed from its run counts without
+/// expanding them.
+fn varint_decoded_len(input: &[u8], size: usize) -> Result<u64> {
+    let mut total = 0u64;
+    let mut pos = 0;
+    while pos < input.len() {
+        let count = varint::read_u64(input, &mut pos)?;
+        pos += size;
+        total = total.saturating_add(count.saturating_mul(size as u64));
+    }
+    Ok(total)
+}
+
+fn compress_varint(input: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    if input.is_empty() {
+        return output.extend_from_slice(&EMPTY_FRAME);
+    }
 
-            output.push(run_length);
-            output.push(current_byte);
-            i += usize::from(run_length);
+    let max = usize::try_from(MAX_VARINT_RUN).unwrap_or(usize::MAX);
+    output.reserve(input.len().min(1024));
+    let mut count = [0; varint::MAX_LEN_U64];
+    let mut i = 0;
+    while i < input.len() {
+        let run = run_length(input, i, max, size);
+        let len = varint::write_u64_into(&mut count, run as u64)?;
+        output.extend_from_slice(&count[..len])?;
+        output.extend_from_slice(&input[i..i + size])?;
+        i += run * size;
+    }
+    Ok(())
+}
+
+fn decompress_varint(input: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    if input == EMPTY_FRAME {
+        return Ok(());
+    }
+    if input.is_empty() {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    let mut pos = 0;
+    while pos < input.len() {
+        let count = varint::read_u64(input, &mut pos)?;
+        if count == 0 || count > MAX_VARINT_RUN {
+            return Err(CompressionError::CorruptedData);
         }
+        let element = input
+            .get(pos..pos + size)
+            .ok_or(CompressionError::CorruptedData)?;
+        pos += size;
+        let count = usize::try_from(count).map_err(|_| CompressionError::CorruptedData)?;
+        output.extend_run(element, count)?;
+    }
+    Ok(())
+}
 
-        Ok(output)
+fn compress_golomb(input: &[u8], size: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 8 + varint::MAX_LEN_U64);
+    varint::write_u64(&mut output, input.len() as u64);
+
+    let mut writer = BitWriter::new();
+    let mut i = 0;
+    while i < input.len() {
+        let run = run_length(input, i, usize::MAX, size);
+        for &byte in &input[i..i + size] {
+            writer.write_bits(u64::from(byte), 8);
+        }
+        writer.write_exp_golomb(run as u64 - 1);
+        i += run * size;
     }
+    output.extend_from_slice(&writer.finish());
+    output
+}
 
-    fn name(&self) -> &'static str {
-        "RLE"
+/// Decodes the Golomb frame at the start of `input` into `output`,
+/// returning the length of the frame.
+fn decompress_golomb_partial(input: &[u8], size: usize, output: &mut impl Sink) -> Result<usize> {
+    let mut pos = 0;
+    let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+        .map_err(|_| CompressionError::CorruptedData)?;
+    if !original_len.is_multiple_of(size) {
+        return Err(CompressionError::CorruptedData);
     }
+
+    output.reserve(original_len.min(input.len() * 64));
+    let mut element = [0; u8::MAX as usize];
+    let element = &mut element[..size];
+    let mut reader = BitReader::new(&input[pos..]);
+    let mut written = 0;
+    while written < original_len {
+        for byte in &mut *element {
+            *byte = reader.read_u8(8).ok_or(CompressionError::CorruptedData)?;
+        }
+        let run = reader
+            .read_exp_golomb()
+            .and_then(|run| usize::try_from(run).ok())
+            .ok_or(CompressionError::CorruptedData)?;
+        let remaining = (original_len - written) / size;
+        if run >= remaining {
+            return Err(CompressionError::CorruptedData);
+        }
+        output.extend_run(element, run + 1)?;
+        written += (run + 1) * size;
+    }
+
+    Ok(pos + reader.bytes_consumed())
 }
 
-impl Decompressor for Rle {
-    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
-        if input.is_empty() {
-            return Ok(Vec::new());
+fn flush_literals(literals: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    if let Some(last) = (literals.len() / size).checked_sub(1) {
+        output.push(u8::try_from(last).unwrap_or(u8::MAX))?;
+        output.extend_from_slice(literals)?;
+    }
+    Ok(())
+}
+
+fn compress_escaped(input: &[u8], size: usize, output: &mut impl Sink) -> Result<()> {
+    let original_len = u32::try_from(input.len())
+        .map_err(|_| CompressionError::InvalidInput("input too large".to_string()))?;
+    output.reserve(ESCAPED_HEADER_LEN + input.len() + input.len() / 64);
+    output.extend_from_slice(&original_len.to_le_bytes())?;
+
+    let max_block = MAX_LITERAL_BLOCK * size;
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let run = run_length(input, i, MAX_ESCAPED_RUN, size);
+        if run >= MIN_ESCAPED_RUN {
+            flush_literals(&input[literal_start..i], size, output)?;
+            output.push(RUN_FLAG | u8::try_from(run - MIN_ESCAPED_RUN).unwrap_or(0))?;
+            output.extend_from_slice(&input[i..i + size])?;
+            i += run * size;
+            literal_start = i;
+        } else {
+            i += run * size;
+            while i - literal_start >= max_block {
+                let end = literal_start + max_block;
+                flush_literals(&input[literal_start..end], size, output)?;
+                literal_start = end;
+            }
         }
+    }
+    flush_literals(&input[literal_start..], size, output)
+}
+
+/// Decodes the escaped frame at the start of `input` into `output`,
+/// returning the length of the frame.
+fn decompress_escaped_partial(input: &[u8], size: usize, output: &mut impl Sink) -> Result<usize> {
+    let header = input
+        .get(..ESCAPED_HEADER_LEN)
+        .ok_or(CompressionError::CorruptedData)?;
+    let original_len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if !original_len.is_multiple_of(size) {
+        return Err(CompressionError::CorruptedData);
+    }
 
-        if !input.len().is_multiple_of(2) {
+    output.reserve(original_len.min(input.len() * MAX_ESCAPED_RUN));
+    let mut blocks = &input[ESCAPED_HEADER_LEN..];
+    let mut written = 0;
+    while written < original_len {
+        let (&control, rest) = blocks
+            .split_first()
+            .ok_or(CompressionError::CorruptedData)?;
+        let (element, count, len) = if control & RUN_FLAG == 0 {
+            let len = (usize::from(control) + 1) * size;
+            (rest.get(..len), 1, len)
+        } else {
+            let count = usize::from(control & !RUN_FLAG) + MIN_ESCAPED_RUN;
+            (rest.get(..size), count, size)
+        };
+        let element = element.ok_or(CompressionError::CorruptedData)?;
+        written += count * element.len();
+        if written > original_len {
             return Err(CompressionError::CorruptedData);
         }
+        output.extend_run(element, count)?;
+        blocks = &rest[len..];
+    }
+
+    Ok(input.len() - blocks.len())
+}
 
+/// Decodes the whole of `input`, which must hold exactly one frame of
+/// `mode`.
+fn decompress_mode(input: &[u8], mode: RleMode, size: usize, output: &mut impl Sink) -> Result<()> {
+    let consumed = match mode {
+        RleMode::Pairs => return decompress_pairs(input, size, output),
+        RleMode::Varint => return decompress_varint(input, size, output),
+        RleMode::Escaped => decompress_escaped_partial(input, size, output)?,
+        RleMode::Golomb => decompress_golomb_partial(input, size, output)?,
+    };
+    if consumed != input.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(())
+}
+
+impl Compressor for Rle {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
         let mut output = Vec::new();
+        self.compress_to(input, &mut output)?;
+        Ok(output)
+    }
 
-        for chunk in input.chunks_exact(2) {
-            let count = chunk[0];
-            let byte = chunk[1];
+    /// Writes in place except in [`RleMode::Golomb`], whose frame is
+    /// copied from a bit buffer.
+    fn compress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let mut sink = SliceSink::new(output);
+        self.compress_to(input, &mut sink)?;
+        Ok(sink.len())
+    }
 
-            if count == 0 {
-                return Err(CompressionError::CorruptedData);
+    /// Every element a run of its own is the worst case in each mode: a
+    /// longer run never costs more than its elements would apart.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        let size = usize::from(self.element_size.max(1));
+        let elements = input_len / size;
+        match self.mode {
+            RleMode::Pairs | RleMode::Varint => elements.saturating_mul(size + 1).max(PAIR_LEN),
+            RleMode::Escaped => (ESCAPED_HEADER_LEN + input_len)
+                .saturating_add(elements.div_ceil(MAX_LITERAL_BLOCK)),
+            RleMode::Golomb => {
+                let bits = elements.saturating_mul(8 * size + 1);
+                varint::len_u64(input_len as u64).saturating_add(bits.div_ceil(8))
             }
+        }
+    }
 
-            output.extend(std::iter::repeat_n(byte, usize::from(count)));
+    fn name(&self) -> &'static str {
+        "RLE"
+    }
+}
+
+impl Decompressor for Rle {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        decompress_mode(input, self.mode, self.checked_element_size()?, &mut output)?;
+        Ok(output)
+    }
+
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        if self
+            .decompressed_len(input)?
+            .is_some_and(|len| len > output.len() as u64)
+        {
+            return Err(CompressionError::BufferTooSmall);
         }
+        let mut sink = SliceSink::new(output);
+        decompress_mode(input, self.mode, self.checked_element_size()?, &mut sink)?;
+        Ok(sink.len())
+    }
+
+    /// The pair formats record no length, so they report `None`.
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        let len = match self.mode {
+            RleMode::Pairs | RleMode::Varint => return Ok(None),
+            RleMode::Escaped => {
+                let Some(&[a, b, c, d]) = input.get(..ESCAPED_HEADER_LEN) else {
+                    return Err(CompressionError::CorruptedData);
+                };
+                u64::from(u32::from_le_bytes([a, b, c, d]))
+            }
+            RleMode::Golomb => varint::read_u64(input, &mut 0)?,
+        };
+        if !len.is_multiple_of(self.checked_element_size()? as u64) {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(Some(len))
+    }
 
+    /// A varint run can stand for 4 GiB, so those frames are checked
+    /// against `limits` from their run counts before any run is expanded.
+    /// Pair runs cannot reach 255 times their input.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        if self.mode == RleMode::Varint {
+            let size = self.checked_element_size()?;
+            limits.check(input.len() as u64, varint_decoded_len(input, size)?)?;
+        } else if let Some(len) = self.decompressed_len(input)? {
+            limits.check(input.len() as u64, len)?;
+        }
+        let output = self.decompress(input)?;
+        limits.check(input.len() as u64, output.len() as u64)?;
         Ok(output)
     }
 
@@ -77,6 +494,31 @@ impl Decompressor for Rle {
     }
 }
 
+impl PartialDecompressor for Rle {
+    /// Only [`RleMode::Escaped`] and [`RleMode::Golomb`] frames are
+    /// self-delimiting; the pair formats return
+    /// `CompressionError::InvalidInput`.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        match self.mode {
+            RleMode::Pairs | RleMode::Varint => Err(CompressionError::InvalidInput(
+                "pair-format RLE frames are not self-delimiting".to_string(),
+            )),
+            RleMode::Escaped => {
+                let mut output = Vec::new();
+                let consumed =
+                    decompress_escaped_partial(input, self.checked_element_size()?, &mut output)?;
+                Ok((output, consumed))
+            }
+            RleMode::Golomb => {
+                let mut output = Vec::new();
+                let consumed =
+                    decompress_golomb_partial(input, self.checked_element_size()?, &mut output)?;
+                Ok((output, consumed))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,20 +533,27 @@ mod tests {
     fn test_rle_default() {
         let rle = Rle::default();
         assert_eq!(Compressor::name(&rle), "RLE");
+        assert_eq!(rle.mode(), RleMode::Pairs);
+        assert_eq!(rle.element_size(), 1);
     }
 
     #[test]
     fn test_compress_empty() {
         let rle = Rle::new();
         let result = rle.compress(&[]).unwrap();
-        assert!(result.is_empty());
+        assert_eq!(result, EMPTY_FRAME);
     }
 
     #[test]
     fn test_decompress_empty() {
         let rle = Rle::new();
-        let result = rle.decompress(&[]).unwrap();
+        let result = rle.decompress(&EMPTY_FRAME).unwrap();
         assert!(result.is_empty());
+        assert!(matches!(
+            rle.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+        assert!(rle.decompress(&[0, 0, 1, b'a']).is_err());
     }
 
     #[test]
@@ -272,4 +721,434 @@ mod tests {
         let decompressed = rle.decompress(&compressed).unwrap();
         assert_eq!(decompressed, input);
     }
+
+    fn roundtrip_escaped(input: &[u8]) -> Vec<u8> {
+        let rle = Rle::escaped();
+        let compressed = rle.compress(input).unwrap();
+        assert_eq!(rle.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_escaped_empty() {
+        assert_eq!(roundtrip_escaped(&[]), [0, 0, 0, 0]);
+        assert!(Rle::escaped().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_escaped_known_output() {
+        let compressed = roundtrip_escaped(b"abcaaaaad");
+        assert_eq!(
+            compressed,
+            [9, 0, 0, 0, 2, b'a', b'b', b'c', RUN_FLAG | 2, b'a', 0, b'd']
+        );
+    }
+
+    #[test]
+    fn test_escaped_short_runs_stay_literal() {
+        let compressed = roundtrip_escaped(b"aabbcc");
+        assert_eq!(
+            compressed,
+            [6, 0, 0, 0, 5, b'a', b'a', b'b', b'b', b'c', b'c']
+        );
+    }
+
+    #[test]
+    fn test_escaped_incompressible_expansion() {
+        let input: Vec<u8> = (0..10_000u32)
+            .map(|i| (i * 7 + i / 256).to_le_bytes()[0])
+            .collect();
+        let compressed = roundtrip_escaped(&input);
+        let pairs = Rle::new().compress(&input).unwrap();
+        assert!(compressed.len() <= input.len() + ESCAPED_HEADER_LEN + input.len() / 128 + 1);
+        assert!(compressed.len() * 3 < pairs.len() * 2);
+    }
+
+    #[test]
+    fn test_escaped_long_runs() {
+        let mut input = vec![0u8; 1000];
+        input.extend_from_slice(b"xyz");
+        input.extend(std::iter::repeat_n(b'q', MAX_ESCAPED_RUN + 1));
+        let compressed = roundtrip_escaped(&input);
+        assert!(compressed.len() < 30);
+    }
+
+    #[test]
+    fn test_escaped_literal_block_boundaries() {
+        for len in [127, 128, 129, 256, 257] {
+            let input: Vec<u8> = (0..len).map(|i: u32| i.to_le_bytes()[0]).collect();
+            roundtrip_escaped(&input);
+        }
+    }
+
+    #[test]
+    fn test_escaped_decompress_corrupted() {
+        let rle = Rle::escaped();
+        // Literal block running past the end.
+        assert!(rle.decompress(&[2, 0, 0, 0, 3, b'a', b'b']).is_err());
+        // Run without its byte.
+        assert!(rle.decompress(&[3, 0, 0, 0, RUN_FLAG]).is_err());
+        // Output longer or shorter than the header says.
+        assert!(rle.decompress(&[2, 0, 0, 0, RUN_FLAG, b'a']).is_err());
+        assert!(rle.decompress(&[4, 0, 0, 0, RUN_FLAG, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_escaped_decompress_partial() {
+        let rle = Rle::escaped();
+        let first = rle.compress(b"abcaaaaad").unwrap();
+        let second = rle.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = rle.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [b"abcaaaaad".to_vec(), Vec::new(), b"abcaaaaad".to_vec()]
+        );
+        assert!(rle.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_pairs_decompress_partial_unsupported() {
+        let result = Rle::new().decompress_partial(&[1, b'a']);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Rle::varint().decompress_partial(&[1, b'a']);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    fn roundtrip_varint(input: &[u8]) -> Vec<u8> {
+        let rle = Rle::varint();
+        let compressed = rle.compress(input).unwrap();
+        assert_eq!(rle.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_varint_empty() {
+        assert_eq!(roundtrip_varint(&[]), EMPTY_FRAME);
+        assert!(Rle::varint().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_varint_known_output() {
+        let mut input = vec![b'a'; 300];
+        input.push(b'b');
+        assert_eq!(roundtrip_varint(&input), [0xAC, 0x02, b'a', 1, b'b']);
+    }
+
+    #[test]
+    fn test_varint_long_zero_region() {
+        let input = vec![0u8; 10 << 20];
+        let compressed = roundtrip_varint(&input);
+        assert_eq!(compressed, [0x80, 0x80, 0x80, 0x05, 0]);
+        assert!(Rle::new().compress(&input).unwrap().len() > 80_000);
+    }
+
+    #[test]
+    fn test_varint_roundtrip_mixed() {
+        let input: Vec<u8> = (0..5000u32)
+            .map(|i| (i / 37 % 3).to_le_bytes()[0])
+            .collect();
+        roundtrip_varint(&input);
+        roundtrip_varint(b"abcdef");
+    }
+
+    #[test]
+    fn test_varint_decompress_corrupted() {
+        let rle = Rle::varint();
+        // Zero count outside the empty frame.
+        assert!(rle.decompress(&[1, b'a', 0, b'b']).is_err());
+        // Count without its byte, and a truncated varint.
+        assert!(rle.decompress(&[3]).is_err());
+        assert!(rle.decompress(&[0x80]).is_err());
+        // Count beyond the largest run.
+        assert!(
+            rle.decompress(&[0x80, 0x80, 0x80, 0x80, 0x10, b'a'])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_varint_long_element_runs() {
+        let rle = Rle::varint().with_element_size(3);
+        let mut input = [1, 2, 3].repeat(1 << 20);
+        input.extend_from_slice(&[4, 5, 6, 1, 2, 3]);
+        let compressed = rle.compress(&input).unwrap();
+        assert_eq!(
+            compressed,
+            [0x80, 0x80, 0x40, 1, 2, 3, 1, 4, 5, 6, 1, 1, 2, 3]
+        );
+        assert_eq!(rle.decompress(&compressed).unwrap(), input);
+    }
+
+    fn roundtrip_golomb(input: &[u8]) -> Vec<u8> {
+        let rle = Rle::golomb();
+        let compressed = rle.compress(input).unwrap();
+        assert_eq!(rle.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    /// A bilevel scan, one byte per pixel, with run lengths spread over
+    /// several orders of magnitude.
+    fn bilevel(runs: u32) -> Vec<u8> {
+        let mut input = Vec::new();
+        let mut state = 0x2545_F491_u32;
+        for i in 0..runs {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let len = 1 + (state >> 16) % (1 << ((state >> 8) % 11));
+            input.resize(input.len() + len as usize, u8::from(i % 2 == 1));
+        }
+        input
+    }
+
+    #[test]
+    fn test_golomb_empty() {
+        assert_eq!(roundtrip_golomb(&[]), [0]);
+        assert!(Rle::golomb().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_golomb_known_output() {
+        // a, run 3 (011), b, run 1 (1), padded.
+        assert_eq!(roundtrip_golomb(b"aaab"), [4, 0x61, 0x6C, 0x50]);
+    }
+
+    #[test]
+    fn test_golomb_denser_on_variable_runs() {
+        let input = bilevel(2000);
+        let compressed = roundtrip_golomb(&input);
+        for mode in [RleMode::Pairs, RleMode::Escaped, RleMode::Varint] {
+            let other = Rle::with_mode(mode).compress(&input).unwrap();
+            assert!(compressed.len() < other.len(), "{mode:?}");
+        }
+        assert_eq!(roundtrip_golomb(&vec![7; 1 << 20]).len(), 3 + 1 + 6);
+    }
+
+    #[test]
+    fn test_golomb_decompress_partial() {
+        let rle = Rle::golomb();
+        let first = rle.compress(b"abcaaaaad").unwrap();
+        let second = rle.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = rle.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [b"abcaaaaad".to_vec(), Vec::new(), b"abcaaaaad".to_vec()]
+        );
+        assert!(rle.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_golomb_decompress_corrupted() {
+        let rle = Rle::golomb();
+        // Run of 4 where the header promises 3 bytes.
+        assert!(rle.decompress(&[3, 0x61, 0x50]).is_err());
+        // Bitstream ends before the length is reached.
+        assert!(rle.decompress(&[5, 0x61, 0x6C, 0x50]).is_err());
+        assert!(rle.decompress(&[1, 0x61]).is_err());
+        // Trailing bytes after the frame.
+        assert!(rle.decompress(&[4, 0x61, 0x6C, 0x50, 0]).is_err());
+    }
+
+    fn pixels() -> Vec<u8> {
+        let mut input = [0x10, 0x20, 0x30, 0xFF].repeat(100);
+        input.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        input.extend_from_slice(&[0, 0, 0, 0].repeat(300));
+        input
+    }
+
+    #[test]
+    fn test_element_runs_all_modes() {
+        let input = pixels();
+        for mode in [
+            RleMode::Pairs,
+            RleMode::Escaped,
+            RleMode::Varint,
+            RleMode::Golomb,
+        ] {
+            let bytes = Rle::with_mode(mode);
+            let elements = bytes.with_element_size(4);
+            assert_eq!(elements.element_size(), 4);
+            let compressed = elements.compress(&input).unwrap();
+            assert_eq!(elements.decompress(&compressed).unwrap(), input);
+            assert!(compressed.len() * 10 < bytes.compress(&input).unwrap().len());
+            assert_eq!(
+                elements.compress(&[]).unwrap(),
+                bytes.compress(&[]).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_compressed_len_holds() {
+        let alternating: Vec<u8> = (0..1000u32).map(|i| (i % 2).to_le_bytes()[0]).collect();
+        for mode in [
+            RleMode::Pairs,
+            RleMode::Escaped,
+            RleMode::Varint,
+            RleMode::Golomb,
+        ] {
+            for size in [1, 4] {
+                let rle = Rle::with_mode(mode).with_element_size(size);
+                for input in [&[][..], &[7; 4], &alternating, &pixels()] {
+                    let compressed = rle.compress(input).unwrap();
+                    assert!(compressed.len() <= rle.max_compressed_len(input.len()));
+                }
+            }
+        }
+        // Alternating bytes are the worst case for pairs.
+        let pairs = Rle::new().compress(&alternating).unwrap();
+        assert_eq!(
+            pairs.len(),
+            Rle::new().max_compressed_len(alternating.len())
+        );
+    }
+
+    #[test]
+    fn test_decompressed_len() {
+        let input = [5, 5, 5, 5, 6, 6, 7, 8];
+        for rle in [Rle::escaped(), Rle::golomb()] {
+            let compressed = rle.compress(&input).unwrap();
+            assert_eq!(rle.decompressed_len(&compressed).unwrap(), Some(8));
+            assert!(rle.decompressed_len(&[]).is_err());
+        }
+        let pairs = Rle::new().compress(&input).unwrap();
+        assert_eq!(Rle::new().decompressed_len(&pairs).unwrap(), None);
+        // A length that is not a whole number of elements.
+        let escaped = Rle::escaped().compress(&input[..7]).unwrap();
+        let rle = Rle::escaped().with_element_size(2);
+        assert!(rle.decompressed_len(&escaped).is_err());
+    }
+
+    #[test]
+    fn test_limits_refuse_bombs() {
+        // Twelve bytes claiming 8 GiB of zeros.
+        let mut bomb = Vec::new();
+        varint::write_u64(&mut bomb, MAX_VARINT_RUN);
+        bomb.push(0);
+        bomb.extend_from_slice(&bomb.clone());
+        let limits = DecompressionLimits::new().with_max_output_size(1 << 20);
+        assert!(matches!(
+            Rle::varint().decompress_with_limits(&bomb, &limits),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+
+        let input = vec![7; 10_000];
+        for rle in [Rle::new(), Rle::escaped(), Rle::varint(), Rle::golomb()] {
+            let compressed = rle.compress(&input).unwrap();
+            assert_eq!(
+                rle.decompress_with_limits(&compressed, &limits).unwrap(),
+                input
+            );
+            let tight = DecompressionLimits::new().with_max_output_size(9_999);
+            assert!(matches!(
+                rle.decompress_with_limits(&compressed, &tight),
+                Err(CompressionError::LimitExceeded(_))
+            ));
+        }
+        let ratio = DecompressionLimits::new().with_max_ratio(10);
+        let compressed = Rle::escaped().compress(&input).unwrap();
+        assert!(matches!(
+            Rle::escaped().decompress_with_limits(&compressed, &ratio),
+            Err(CompressionError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_element_known_output() {
+        let rle = Rle::new().with_element_size(2);
+        let compressed = rle.compress(&[1, 0, 1, 0, 1, 0, 2, 0]).unwrap();
+        assert_eq!(compressed, [3, 1, 0, 1, 2, 0]);
+
+        let rle = Rle::escaped().with_element_size(2);
+        let compressed = rle.compress(&[9, 8, 1, 0, 1, 0, 1, 0]).unwrap();
+        assert_eq!(compressed, [8, 0, 0, 0, 0, 9, 8, RUN_FLAG, 1, 0]);
+    }
+
+    #[test]
+    fn test_element_size_one_matches_bytes() {
+        let input = b"aaabccddddde";
+        for mode in [RleMode::Pairs, RleMode::Escaped, RleMode::Varint] {
+            assert_eq!(
+                Rle::with_mode(mode)
+                    .with_element_size(1)
+                    .compress(input)
+                    .unwrap(),
+                Rle::with_mode(mode).compress(input).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_element_length_must_be_multiple() {
+        let rle = Rle::new().with_element_size(4);
+        let result = rle.compress(&[1, 2, 3, 4, 5]);
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+
+        // A pair stride of five bytes, cut short.
+        assert!(rle.decompress(&[2, 1, 2, 3, 4, 1, 9]).is_err());
+        assert!(
+            Rle::varint()
+                .with_element_size(4)
+                .decompress(&[2, 1, 2])
+                .is_err()
+        );
+        // Escaped header length of 6 is not a whole number of elements.
+        let escaped = Rle::escaped().with_element_size(4);
+        assert!(matches!(
+            escaped.decompress(&[6, 0, 0, 0, 1, 1, 2, 3, 4, 5, 6, 7, 8]),
+            Err(CompressionError::CorruptedData)
+        ));
+        assert!(escaped.decompress(&[4, 0, 0, 0, RUN_FLAG, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_zero_element_size() {
+        let rle = Rle::new().with_element_size(0);
+        assert!(matches!(
+            rle.compress(b"ab"),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            rle.decompress(&[1, b'a']),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_into_matches_vec_all_modes() {
+        let mut input = [7, 7, 1, 2].repeat(50);
+        input.extend_from_slice(&[0; 600]);
+        input.extend_from_slice(b"abcdefgh");
+        for mode in [
+            RleMode::Pairs,
+            RleMode::Escaped,
+            RleMode::Varint,
+            RleMode::Golomb,
+        ] {
+            for rle in [
+                Rle::with_mode(mode),
+                Rle::with_mode(mode).with_element_size(2),
+            ] {
+                let compressed = rle.compress(&input).unwrap();
+                let mut frame = vec![0; rle.max_compressed_len(input.len())];
+                let len = rle.compress_into(&input, &mut frame).unwrap();
+                assert_eq!(frame[..len], compressed, "{mode:?}");
+                assert_eq!(
+                    rle.compress_into(&input, &mut frame[..len - 1]),
+                    Err(CompressionError::BufferTooSmall)
+                );
+
+                let mut output = vec![0; input.len()];
+                assert_eq!(
+                    rle.decompress_into(&compressed, &mut output),
+                    Ok(input.len())
+                );
+                assert_eq!(output, input, "{mode:?}");
+                assert_eq!(
+                    rle.decompress_into(&compressed, &mut output[..input.len() - 1]),
+                    Err(CompressionError::BufferTooSmall)
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,301 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Zero-run digit worth one in its position.
+pub const RUNA: u16 = 0;
+/// Zero-run digit worth two in its position.
+pub const RUNB: u16 = 1;
+/// Size of the symbol alphabet: the two run digits and the bytes 1 to 255
+/// shifted up by one.
+pub const SYMBOLS: usize = 257;
+/// Byte introducing one of the two symbols above 254 in [`Rle0`] output.
+pub const ESCAPE: u8 = 255;
+
+/// bzip2's zero-run stage (RLE0, RUNA/RUNB), meant to follow a
+/// move-to-front pass over [`Bwt`](crate::Bwt) output.
+///
+/// After move-to-front, most bytes are zero and they come in long runs.
+/// Each run of `n` zeros becomes `n` written in bijective base 2, least
+/// significant digit first, with `RUNA` worth 1 and `RUNB` worth 2 in
+/// each place, so a run costs about `log2(n)` symbols and needs no
+/// terminator: any other symbol ends it. Nonzero bytes shift up by one to
+/// make room. [`Rle0::to_symbols`] gives the 257-symbol stream an entropy
+/// coder would consume; as a byte codec, symbols up to 254 are written as
+/// is and the two above as `ESCAPE` followed by the symbol minus 255.
+/// Output layout: `[original length: varint][symbols]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Rle0;
+
+impl Rle0 {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Encodes `input` as RLE0 symbols, each below `SYMBOLS`.
+    #[must_use]
+    pub fn to_symbols(input: &[u8]) -> Vec<u16> {
+        let mut symbols = Vec::with_capacity(input.len());
+        let mut run = 0usize;
+        for &byte in input {
+            if byte == 0 {
+                run += 1;
+            } else {
+                push_run(&mut symbols, run);
+                run = 0;
+                symbols.push(u16::from(byte) + 1);
+            }
+        }
+        push_run(&mut symbols, run);
+        symbols
+    }
+
+    /// Reverses [`Rle0::to_symbols`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if a symbol is outside the
+    /// alphabet or a zero run overflows `usize`.
+    pub fn from_symbols(symbols: &[u16]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(symbols.len());
+        let mut run = Run::default();
+        for &symbol in symbols {
+            if !run.push(symbol)? {
+                output.resize(output.len() + run.take(), 0);
+                output.push(byte_of(symbol)?);
+            }
+        }
+        output.resize(output.len() + run.take(), 0);
+        Ok(output)
+    }
+}
+
+/// Appends the RUNA/RUNB digits of a run of `len` zeros.
+fn push_run(symbols: &mut Vec<u16>, mut len: usize) {
+    while len > 0 {
+        if len & 1 == 1 {
+            symbols.push(RUNA);
+            len = (len - 1) / 2;
+        } else {
+            symbols.push(RUNB);
+            len = (len - 2) / 2;
+        }
+    }
+}
+
+/// The byte a non-run symbol stands for.
+fn byte_of(symbol: u16) -> Result<u8> {
+    u8::try_from(symbol - 1).map_err(|_| CompressionError::CorruptedData)
+}
+
+/// Zero run being accumulated from its digits.
+#[derive(Default)]
+struct Run {
+    len: usize,
+    weight: usize,
+}
+
+impl Run {
+    /// Adds `symbol` to the run if it is a run digit, returning whether it
+    /// was.
+    fn push(&mut self, symbol: u16) -> Result<bool> {
+        let digit = match symbol {
+            RUNA => 1,
+            RUNB => 2,
+            _ => return Ok(false),
+        };
+        if self.weight == 0 {
+            self.weight = 1;
+        }
+        self.len = self
+            .weight
+            .checked_mul(digit)
+            .and_then(|value| self.len.checked_add(value))
+            .ok_or(CompressionError::CorruptedData)?;
+        self.weight = self.weight.saturating_mul(2);
+        Ok(true)
+    }
+
+    /// Ends the run, returning its length.
+    fn take(&mut self) -> usize {
+        std::mem::take(self).len
+    }
+}
+
+impl Compressor for Rle0 {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let symbols = Self::to_symbols(input);
+        let mut output = Vec::with_capacity(varint::MAX_LEN_U64 + symbols.len());
+        varint::write_u64(&mut output, input.len() as u64);
+        for symbol in symbols {
+            let [low, _] = symbol.to_le_bytes();
+            if symbol < u16::from(ESCAPE) {
+                output.push(low);
+            } else {
+                output.extend_from_slice(&[ESCAPE, low.wrapping_sub(ESCAPE)]);
+            }
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "RLE0"
+    }
+}
+
+impl Decompressor for Rle0 {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "RLE0"
+    }
+}
+
+impl PartialDecompressor for Rle0 {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = usize::try_from(varint::read_u64(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        let mut output = Vec::with_capacity(original_len.min(input.len() * 8));
+        let mut run = Run::default();
+        // Run digits only ever add to the pending run, so the frame ends
+        // exactly when the output and the pending run reach the length.
+        while output.len() + run.len < original_len {
+            let &byte = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+            pos += 1;
+            let symbol = if byte == ESCAPE {
+                let &extra = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+                pos += 1;
+                if usize::from(ESCAPE) + usize::from(extra) >= SYMBOLS {
+                    return Err(CompressionError::CorruptedData);
+                }
+                u16::from(ESCAPE) + u16::from(extra)
+            } else {
+                u16::from(byte)
+            };
+            if !run.push(symbol)? {
+                output.resize(output.len() + run.take(), 0);
+                output.push(byte_of(symbol)?);
+            }
+        }
+        if output.len() + run.len != original_len {
+            return Err(CompressionError::CorruptedData);
+        }
+        output.resize(original_len, 0);
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = Rle0::new().compress(input).unwrap();
+        assert_eq!(Rle0::new().decompress(&compressed).unwrap(), input);
+        let symbols = Rle0::to_symbols(input);
+        assert!(symbols.iter().all(|&s| usize::from(s) < SYMBOLS));
+        assert_eq!(Rle0::from_symbols(&symbols).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_rle0_name() {
+        assert_eq!(Compressor::name(&Rle0::new()), "RLE0");
+        assert_eq!(Decompressor::name(&Rle0::new()), "RLE0");
+    }
+
+    #[test]
+    fn test_bijective_run_digits() {
+        const A: u16 = RUNA;
+        const B: u16 = RUNB;
+        let cases: [(usize, &[u16]); 8] = [
+            (1, &[A]),
+            (2, &[B]),
+            (3, &[A, A]),
+            (4, &[B, A]),
+            (5, &[A, B]),
+            (6, &[B, B]),
+            (7, &[A, A, A]),
+            (14, &[B, B, B]),
+        ];
+        for (len, digits) in cases {
+            assert_eq!(Rle0::to_symbols(&vec![0; len]), digits, "{len}");
+        }
+        assert_eq!(Rle0::to_symbols(b"\x01\x00\x00\xFF"), [2, B, 256]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(b"");
+        roundtrip(&[0]);
+        roundtrip(&[255]);
+        roundtrip(&[254, 253, 0, 255]);
+        roundtrip(&(0..=255).collect::<Vec<u8>>());
+        for len in 0..300 {
+            roundtrip(&[vec![0; len], vec![7], vec![0; len]].concat());
+        }
+    }
+
+    #[test]
+    fn test_long_runs_are_logarithmic() {
+        let input = [vec![0; 100_000], vec![3], vec![0; 1_000_000]].concat();
+        let compressed = roundtrip(&input);
+        // 16 and 19 digits, one literal and a three-byte length.
+        assert_eq!(compressed.len(), 3 + 16 + 1 + 19);
+    }
+
+    #[test]
+    fn test_escaped_symbols() {
+        let compressed = roundtrip(&[253, 254, 255]);
+        assert_eq!(compressed, [3, 254, ESCAPE, 0, ESCAPE, 1]);
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Rle0::new();
+        let stream = [
+            codec.compress(&[0, 0, 0, 5, 0, 0]).unwrap(),
+            codec.compress(&[]).unwrap(),
+            codec.compress(&[0; 10]).unwrap(),
+            codec.compress(&[255]).unwrap(),
+        ]
+        .concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [vec![0, 0, 0, 5, 0, 0], Vec::new(), vec![0; 10], vec![255]]
+        );
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Rle0::new();
+        assert!(codec.decompress(&[]).is_err());
+        // Truncated: three bytes promised, one delivered.
+        assert!(codec.decompress(&[3, 5]).is_err());
+        // A run overshooting the length.
+        assert!(codec.decompress(&[1, 1]).is_err());
+        // Escape past the alphabet, and a dangling escape.
+        assert!(codec.decompress(&[1, ESCAPE, 2]).is_err());
+        assert!(codec.decompress(&[1, ESCAPE]).is_err());
+        assert!(codec.decompress(&[1, 5, 5]).is_err());
+        assert_eq!(
+            Rle0::from_symbols(&[257]),
+            Err(CompressionError::CorruptedData)
+        );
+        assert!(Rle0::from_symbols(&[RUNB; 80]).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use std::ops::Range;
+
+use crate::error::{CompressionError, Result};
+use crate::rle::{self, Rle, RleMode};
+use crate::varint;
+
+/// A maximal run of a single byte in the original stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    byte: u8,
+    start: usize,
+    len: usize,
+}
+
+/// Appends a run, merging it into the previous run if it repeats the same byte.
+fn push_run(runs: &mut Vec<Run>, byte: u8, len: usize) {
+    match runs.last_mut() {
+        Some(last) if last.byte == byte => last.len += len,
+        Some(last) => {
+            let start = last.start + last.len;
+            runs.push(Run { byte, start, len });
+        }
+        None => runs.push(Run {
+            byte,
+            start: 0,
+            len,
+        }),
+    }
+}
+
+fn runs_from_pairs(compressed: &[u8]) -> Result<Vec<Run>> {
+    if compressed == rle::EMPTY_FRAME {
+        return Ok(Vec::new());
+    }
+    if compressed.is_empty() || !compressed.len().is_multiple_of(rle::PAIR_LEN) {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    let mut runs = Vec::new();
+    for chunk in compressed.chunks_exact(rle::PAIR_LEN) {
+        let count = chunk[0];
+        if count == 0 {
+            return Err(CompressionError::CorruptedData);
+        }
+        push_run(&mut runs, chunk[1], usize::from(count));
+    }
+    Ok(runs)
+}
+
+fn runs_from_varint(compressed: &[u8]) -> Result<Vec<Run>> {
+    if compressed == rle::EMPTY_FRAME {
+        return Ok(Vec::new());
+    }
+    if compressed.is_empty() {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    while pos < compressed.len() {
+        let count = varint::read_u64(compressed, &mut pos)?;
+        if count == 0 || count > rle::MAX_VARINT_RUN {
+            return Err(CompressionError::CorruptedData);
+        }
+        let &byte = compressed.get(pos).ok_or(CompressionError::CorruptedData)?;
+        pos += 1;
+        let count = usize::try_from(count).map_err(|_| CompressionError::CorruptedData)?;
+        push_run(&mut runs, byte, count);
+    }
+    Ok(runs)
+}
+
+/// The runs of a frame written by `rle`, which must count single bytes in
+/// a format without literal blocks or a bitstream.
+fn runs_from_rle(compressed: &[u8], rle: Rle) -> Result<Vec<Run>> {
+    if rle.element_size() != 1 {
+        return Err(CompressionError::InvalidInput(format!(
+            "cannot search RLE frames of {}-byte elements",
+            rle.element_size()
+        )));
+    }
+    match rle.mode() {
+        RleMode::Pairs => runs_from_pairs(compressed),
+        RleMode::Varint => runs_from_varint(compressed),
+        mode @ (RleMode::Escaped | RleMode::Golomb) => Err(CompressionError::InvalidInput(
+            format!("cannot search RLE frames in {mode:?} mode"),
+        )),
+    }
+}
+
+fn runs_from_bytes(data: &[u8]) -> Vec<Run> {
+    let mut runs = Vec::new();
+    for &byte in data {
+        push_run(&mut runs, byte, 1);
+    }
+    runs
+}
+
+/// Finds every occurrence of `pattern` in data compressed by `rle` without
+/// decompressing it.
+///
+/// Matching is done directly on the run sequence: the first and last runs
+/// of the pattern must fit inside a text run, and any runs in between must
+/// match exactly. Returns the (possibly overlapping) match offsets in the
+/// original stream in ascending order. Only the [`RleMode::Pairs`] and
+/// [`RleMode::Varint`] formats with one-byte elements can be searched;
+/// frames stored in a container are searched with
+/// `container::search_frame`, and chunked data with [`search_chunks`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `pattern` is empty or `rle`
+/// writes a format that cannot be searched, and
+/// `CompressionError::CorruptedData` if `compressed` is not valid data in
+/// `rle`'s format.
+pub fn search(pattern: &[u8], compressed: &[u8], rle: Rle) -> Result<Vec<usize>> {
+    if pattern.is_empty() {
+        return Err(CompressionError::InvalidInput("empty pattern".to_string()));
+    }
+    let text = runs_from_rle(compressed, rle)?;
+    Ok(search_runs(&runs_from_bytes(pattern), &text))
+}
+
+/// Finds every occurrence of `pattern` in data compressed by `rle` as a
+/// sequence of independent frames, such as the chunks of a
+/// [`Chunked`](crate::Chunked) frame, without decompressing them.
+///
+/// Each chunk is searched on its own, skipping those shorter than the
+/// pattern, and a match straddling boundaries is found by searching the
+/// last `pattern.len() - 1` bytes before a chunk joined to as many at its
+/// start. Offsets are in the concatenated original data, ascending.
+///
+/// # Errors
+///
+/// As [`search`], for `pattern` and each chunk.
+pub fn search_chunks<'a>(
+    pattern: &[u8],
+    chunks: impl IntoIterator<Item = &'a [u8]>,
+    rle: Rle,
+) -> Result<Vec<usize>> {
+    if pattern.is_empty() {
+        return Err(CompressionError::InvalidInput("empty pattern".to_string()));
+    }
+
+    let pat = runs_from_bytes(pattern);
+    let overlap = pattern.len() - 1;
+    let mut matches = Vec::new();
+    // Original data before the current chunk, and its last `overlap` bytes.
+    let mut base = 0;
+    let mut tail = Vec::with_capacity(overlap);
+    for chunk in chunks {
+        let text = runs_from_rle(chunk, rle)?;
+        let len = text.last().map_or(0, |run| run.start + run.len);
+
+        if !tail.is_empty() {
+            let mut window = tail.clone();
+            window.extend(expand(&text, 0..overlap.min(len)));
+            let crossing = window
+                .windows(pattern.len())
+                .enumerate()
+                .filter(|&(i, candidate)| i < tail.len() && candidate == pattern)
+                .map(|(i, _)| base - tail.len() + i);
+            matches.extend(crossing);
+        }
+        if len >= pattern.len() {
+            matches.extend(search_runs(&pat, &text).into_iter().map(|i| base + i));
+        }
+
+        tail.extend(expand(&text, len.saturating_sub(overlap)..len));
+        tail.drain(..tail.len().saturating_sub(overlap));
+        base += len;
+    }
+    Ok(matches)
+}
+
+/// The bytes `runs` expand to within `range` of their original data.
+fn expand(runs: &[Run], range: Range<usize>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(range.len());
+    for run in runs {
+        let start = run.start.max(range.start);
+        let end = (run.start + run.len).min(range.end);
+        if start < end {
+            bytes.resize(bytes.len() + end - start, run.byte);
+        }
+    }
+    bytes
+}
+
+/// Match offsets of the non-empty run sequence `pat` in `text`.
+fn search_runs(pat: &[Run], text: &[Run]) -> Vec<usize> {
+    let mut matches = Vec::new();
+
+    let first = pat[0];
+    if pat.len() == 1 {
+        for run in text
+            .iter()
+            .filter(|run| run.byte == first.byte && run.len >= first.len)
+        {
+            matches.extend(run.start..=run.start + run.len - first.len);
+        }
+        return matches;
+    }
+
+    let last = pat[pat.len() - 1];
+    let middle = &pat[1..pat.len() - 1];
+    for window in text.windows(pat.len()) {
+        let head = window[0];
+        let tail = window[window.len() - 1];
+        if head.byte != first.byte || head.len < first.len {
+            continue;
+        }
+        if tail.byte != last.byte || tail.len < last.len {
+            continue;
+        }
+        let inner_matches = window[1..window.len() - 1]
+            .iter()
+            .zip(middle)
+            .all(|(t, p)| t.byte == p.byte && t.len == p.len);
+        if inner_matches {
+            matches.push(head.start + head.len - first.len);
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Compressor;
+
+    fn naive_search(pattern: &[u8], data: &[u8]) -> Vec<usize> {
+        if pattern.len() > data.len() {
+            return Vec::new();
+        }
+        (0..=data.len() - pattern.len())
+            .filter(|&i| &data[i..i + pattern.len()] == pattern)
+            .collect()
+    }
+
+    fn check(pattern: &[u8], data: &[u8]) {
+        for rle in [Rle::new(), Rle::varint()] {
+            let compressed = rle.compress(data).unwrap();
+            assert_eq!(
+                search(pattern, &compressed, rle).unwrap(),
+                naive_search(pattern, data),
+                "{:?}",
+                rle.mode()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_empty_pattern() {
+        let result = search(b"", &[1, b'a'], Rle::new());
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_search_empty_data() {
+        assert!(
+            search(b"a", &rle::EMPTY_FRAME, Rle::new())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            search(b"a", &[], Rle::new()),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_search_corrupted_odd_length() {
+        let result = search(b"a", &[1, 2, 3], Rle::new());
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_search_corrupted_zero_count() {
+        let result = search(b"a", &[0, b'a'], Rle::new());
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_search_varint_corrupted() {
+        let varint = Rle::varint();
+        assert!(search(b"a", &rle::EMPTY_FRAME, varint).unwrap().is_empty());
+        for frame in [&[][..], &[0, b'a'], &[0x80], &[2]] {
+            assert!(matches!(
+                search(b"a", frame, varint),
+                Err(CompressionError::CorruptedData)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_search_rejects_unsearchable_formats() {
+        let data = b"aaaabbbbbbbbcc";
+        for rle in [
+            Rle::escaped(),
+            Rle::golomb(),
+            Rle::new().with_element_size(2),
+        ] {
+            let compressed = rle.compress(data).unwrap();
+            assert!(matches!(
+                search(b"ab", &compressed, rle),
+                Err(CompressionError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_search_single_run_pattern() {
+        check(b"aa", b"aaaabaaa");
+    }
+
+    #[test]
+    fn test_search_multi_run_pattern() {
+        check(b"abbc", b"aabbcabbcc");
+    }
+
+    #[test]
+    fn test_search_exact_middle_run() {
+        check(b"abbbc", b"abbc abbbc abbbbc");
+    }
+
+    #[test]
+    fn test_search_no_match() {
+        check(b"xyz", b"hello world");
+    }
+
+    #[test]
+    fn test_search_text() {
+        check(b"the", b"the quick brown fox jumps over the lazy dog");
+    }
+
+    #[test]
+    fn test_search_across_fragmented_runs() {
+        let mut data = vec![0xAA; 600];
+        data.push(0xBB);
+        check(&[0xAA; 300], &data);
+        check(&[0xAA, 0xAA, 0xBB], &data);
+    }
+
+    #[test]
+    fn test_search_pattern_longer_than_data() {
+        check(b"aaaa", b"aa");
+    }
+
+    #[test]
+    fn test_search_chunks_across_boundaries() {
+        let data = b"abcabcaaaabbbbab abc a bc abcabc";
+        for rle in [Rle::new(), Rle::varint()] {
+            for size in [1, 2, 3, 5, 8, data.len()] {
+                let chunks: Vec<Vec<u8>> = data
+                    .chunks(size)
+                    .map(|chunk| rle.compress(chunk).unwrap())
+                    .collect();
+                for pattern in [&b"abc"[..], b"aab", b"a", b"bbbba", b"abc abc", b"zz"] {
+                    assert_eq!(
+                        search_chunks(pattern, chunks.iter().map(Vec::as_slice), rle).unwrap(),
+                        naive_search(pattern, data),
+                        "{size} {pattern:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_chunks_errors() {
+        let chunks = [&[2, b'a'][..], &[0, b'a']];
+        assert!(matches!(
+            search_chunks(b"a", chunks, Rle::new()),
+            Err(CompressionError::CorruptedData)
+        ));
+        assert!(matches!(
+            search_chunks(b"", chunks, Rle::new()),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(search_chunks(b"a", [], Rle::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_binary_data() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 13) as u8).collect();
+        check(&[0, 7, 1], &data);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::error::{CompressionError, Result};
+use crate::varint::{self, zigzag_decode, zigzag_encode};
+
+pub const FLAG_RUNS: u8 = 0x01;
+
+/// Consecutive differences, the first taken against zero.
+///
+/// Differences use wrapping arithmetic: a jump such as `i64::MIN` to
+/// `i64::MAX` does not fit in an `i64`, but its wrapped value is still the
+/// unique delta that [`decode_i64_sequence`] adds back, so no input is
+/// rejected or silently corrupted.
+fn deltas(values: &[i64]) -> impl Iterator<Item = u64> + '_ {
+    std::iter::once(0)
+        .chain(values.iter().copied())
+        .zip(values)
+        .map(|(previous, &current)| zigzag_encode(current.wrapping_sub(previous)))
+}
+
+fn plain_body(values: &[i64]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(values.len());
+    for delta in deltas(values) {
+        varint::write_u64(&mut body, delta);
+    }
+    body
+}
+
+fn run_body(values: &[i64]) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut current: Option<(u64, u64)> = None;
+    for delta in deltas(values) {
+        current = match current {
+            Some((run_delta, len)) if run_delta == delta => Some((run_delta, len + 1)),
+            Some((run_delta, len)) => {
+                varint::write_u64(&mut body, run_delta);
+                varint::write_u64(&mut body, len);
+                Some((delta, 1))
+            }
+            None => Some((delta, 1)),
+        };
+    }
+    if let Some((run_delta, len)) = current {
+        varint::write_u64(&mut body, run_delta);
+        varint::write_u64(&mut body, len);
+    }
+    body
+}
+
+/// Encodes signed integers as zig-zag varint deltas.
+///
+/// Counters and timestamps become runs of small deltas; when the deltas
+/// repeat, they are further stored as `(delta, run length)` pairs if that is
+/// smaller. Output layout: `[flags: u8][count: varint][body]`.
+#[must_use]
+pub fn encode_i64_sequence(values: &[i64]) -> Vec<u8> {
+    let plain = plain_body(values);
+    let runs = run_body(values);
+    let (flags, body) = if runs.len() < plain.len() {
+        (FLAG_RUNS, runs)
+    } else {
+        (0, plain)
+    };
+
+    let mut output = Vec::with_capacity(body.len() + 1 + varint::MAX_LEN_U64);
+    output.push(flags);
+    varint::write_u64(&mut output, values.len() as u64);
+    output.extend_from_slice(&body);
+    output
+}
+
+/// Decodes a buffer produced by [`encode_i64_sequence`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidHeader` for unknown flags, or
+/// `CompressionError::CorruptedData` if the buffer is truncated, has
+/// trailing bytes, or its run lengths disagree with the stored count.
+pub fn decode_i64_sequence(input: &[u8]) -> Result<Vec<i64>> {
+    let (&flags, rest) = input.split_first().ok_or(CompressionError::CorruptedData)?;
+    if flags & !FLAG_RUNS != 0 {
+        return Err(CompressionError::InvalidHeader);
+    }
+
+    let mut pos = 0;
+    let count = usize::try_from(varint::read_u64(rest, &mut pos)?)
+        .map_err(|_| CompressionError::CorruptedData)?;
+    let mut values = Vec::with_capacity(count.min(rest.len()));
+    let mut previous = 0i64;
+
+    while values.len() < count {
+        let delta = zigzag_decode(varint::read_u64(rest, &mut pos)?);
+        let len = if flags & FLAG_RUNS == 0 {
+            1
+        } else {
+            varint::read_u64(rest, &mut pos)?
+        };
+        let remaining = (count - values.len()) as u64;
+        if len == 0 || len > remaining {
+            return Err(CompressionError::CorruptedData);
+        }
+        for _ in 0..len {
+            previous = previous.wrapping_add(delta);
+            values.push(previous);
+        }
+    }
+
+    if pos != rest.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(values: &[i64]) -> Vec<u8> {
+        let encoded = encode_i64_sequence(values);
+        assert_eq!(decode_i64_sequence(&encoded).unwrap(), values);
+        encoded
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [0, 0]);
+    }
+
+    #[test]
+    fn test_known_plain_encoding() {
+        // Deltas 5, -2, 1 zig-zag to 10, 3, 2.
+        assert_eq!(encode_i64_sequence(&[5, 3, 4]), [0, 3, 10, 3, 2]);
+    }
+
+    #[test]
+    fn test_counter_uses_runs() {
+        let values: Vec<i64> = (1_000..2_000).collect();
+        let encoded = roundtrip(&values);
+        assert_eq!(encoded[0], FLAG_RUNS);
+        assert!(encoded.len() < 12);
+    }
+
+    #[test]
+    fn test_timestamps_with_jitter() {
+        let values: Vec<i64> = (0..500)
+            .map(|i| 1_700_000_000_000 + i * 1000 + (i % 3))
+            .collect();
+        let encoded = roundtrip(&values);
+        assert!(encoded.len() < values.len() * 3);
+    }
+
+    #[test]
+    fn test_negative_and_mixed() {
+        roundtrip(&[-1, -100, 50, 0, -7, -7, -7, 12_345_678]);
+    }
+
+    #[test]
+    fn test_extreme_jumps_do_not_overflow() {
+        roundtrip(&[i64::MIN, i64::MAX, i64::MIN, 0, i64::MAX, -1, i64::MIN]);
+    }
+
+    #[test]
+    fn test_single_value() {
+        roundtrip(&[i64::MIN]);
+        roundtrip(&[42]);
+    }
+
+    #[test]
+    fn test_decode_invalid_flags() {
+        let result = decode_i64_sequence(&[0x80, 0]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_decode_run_exceeds_count() {
+        // Count 2, but a run of 3.
+        let result = decode_i64_sequence(&[FLAG_RUNS, 2, 2, 3]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+        let result = decode_i64_sequence(&[FLAG_RUNS, 2, 2, 0]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_decode_truncated_and_trailing() {
+        let mut encoded = encode_i64_sequence(&[1, 5, 9, 2]);
+        assert!(decode_i64_sequence(&encoded[..encoded.len() - 1]).is_err());
+        encoded.push(0);
+        assert!(decode_i64_sequence(&encoded).is_err());
+        assert!(decode_i64_sequence(&[]).is_err());
+    }
+
+    #[test]
+    fn test_huge_count_does_not_allocate() {
+        let mut input = vec![0];
+        varint::write_u64(&mut input, u64::MAX >> 1);
+        assert!(decode_i64_sequence(&input).is_err());
+    }
+}
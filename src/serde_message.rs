@@ -0,0 +1,87 @@
+use bincode::Options;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::channel::Message;
+use crate::error::{CompressionError, Result};
+
+/// Sends any serde type through a
+/// [`compressed_channel`](crate::compressed_channel), serialized with
+/// bincode.
+///
+/// The wrapper is transparent: build it with `SerdeMessage(value)` and take
+/// the value back out of `.0`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerdeMessage<T>(pub T);
+
+/// Varint lengths and integers, with nothing allowed after the value.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().reject_trailing_bytes()
+}
+
+impl<T: Serialize + DeserializeOwned> Message for SerdeMessage<T> {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        options()
+            .serialize(&self.0)
+            .map_err(|e| CompressionError::InvalidInput(e.to_string()))
+    }
+
+    /// Reads no further than `bytes`, so a damaged length cannot claim more
+    /// memory than the message holds.
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        options()
+            .with_limit(bytes.len() as u64)
+            .deserialize(&bytes)
+            .map(Self)
+            .map_err(|_| CompressionError::CorruptedData)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{Lz4, compressed_channel};
+
+    type Row = (u32, String, Vec<u64>);
+
+    #[test]
+    fn test_send_recv() {
+        let (sender, receiver) = compressed_channel::<SerdeMessage<Vec<Row>>, _>(Lz4::new(), 64);
+        let rows: Vec<Row> = (0..200)
+            .map(|i| (i, format!("row {}", i % 7), vec![u64::from(i); 3]))
+            .collect();
+        sender.send(&SerdeMessage(rows.clone())).unwrap();
+        sender.send(&SerdeMessage(Vec::new())).unwrap();
+        assert_eq!(receiver.recv().unwrap().0, rows);
+        assert!(receiver.recv().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip_map() {
+        let map = BTreeMap::from([("a".to_string(), 1u8), ("b".to_string(), 2)]);
+        let bytes = SerdeMessage(map.clone()).to_bytes().unwrap();
+        let message: SerdeMessage<BTreeMap<String, u8>> = SerdeMessage::from_bytes(bytes).unwrap();
+        assert_eq!(message.0, map);
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let mut bytes = SerdeMessage((7u32, "seven".to_string()))
+            .to_bytes()
+            .unwrap();
+        bytes.push(0);
+        assert!(matches!(
+            SerdeMessage::<(u32, String)>::from_bytes(bytes),
+            Err(CompressionError::CorruptedData)
+        ));
+        // A string claiming 16 MiB, and one that is not UTF-8.
+        assert!(SerdeMessage::<String>::from_bytes(vec![0xFC, 0, 0, 0, 1]).is_err());
+        assert!(SerdeMessage::<String>::from_bytes(vec![1, 0xFF]).is_err());
+        assert_eq!(
+            SerdeMessage::<String>::from_bytes(vec![2, b'o', b'k']).unwrap(),
+            SerdeMessage("ok".to_string())
+        );
+    }
+}
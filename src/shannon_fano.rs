@@ -0,0 +1,203 @@
+use crate::error::{CompressionError, Result};
+use crate::prefix_code::{self, MAX_CODE_LENGTH};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+/// Recursively splits `symbols` (sorted by descending frequency) into two
+/// groups of near-equal total frequency, one extra code bit per split.
+fn assign_lengths(symbols: &[(usize, u8)], depth: u8, lengths: &mut [u8; 256]) {
+    if let [(_, symbol)] = symbols {
+        lengths[usize::from(*symbol)] = depth.max(1);
+        return;
+    }
+
+    let total: usize = symbols.iter().map(|&(count, _)| count).sum();
+    let mut prefix = 0;
+    let mut split = 1;
+    let mut best_diff = usize::MAX;
+    for (i, &(count, _)) in symbols[..symbols.len() - 1].iter().enumerate() {
+        prefix += count;
+        let diff = (2 * prefix).abs_diff(total);
+        if diff < best_diff {
+            best_diff = diff;
+            split = i + 1;
+        }
+    }
+
+    assign_lengths(&symbols[..split], depth + 1, lengths);
+    assign_lengths(&symbols[split..], depth + 1, lengths);
+}
+
+fn shannon_fano_lengths(input: &[u8]) -> [u8; 256] {
+    let mut counts = [0usize; 256];
+    for &byte in input {
+        counts[usize::from(byte)] += 1;
+    }
+
+    let mut symbols: Vec<(usize, u8)> = (0..=255u8)
+        .zip(counts)
+        .filter(|&(_, count)| count > 0)
+        .map(|(symbol, count)| (count, symbol))
+        .collect();
+    symbols.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut lengths = [0u8; 256];
+    if !symbols.is_empty() {
+        assign_lengths(&symbols, 0, &mut lengths);
+    }
+    lengths
+}
+
+/// Shannon–Fano coder.
+///
+/// Shares the canonical code-length header and bitstream with [`Huffman`],
+/// differing only in how code lengths are chosen, so its output is also
+/// decodable by `Huffman` and the two are directly comparable.
+///
+/// [`Huffman`]: crate::Huffman
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ShannonFano;
+
+impl ShannonFano {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Compressor for ShannonFano {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Ok(prefix_code::encode_empty());
+        }
+
+        let lengths = shannon_fano_lengths(input);
+        if lengths.iter().any(|&len| len > MAX_CODE_LENGTH) {
+            return Err(CompressionError::InvalidInput(
+                "code lengths exceed format maximum".to_string(),
+            ));
+        }
+        Ok(prefix_code::encode(input, &lengths))
+    }
+
+    fn name(&self) -> &'static str {
+        "ShannonFano"
+    }
+}
+
+impl Decompressor for ShannonFano {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        prefix_code::decode(input)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        prefix_code::decoded_len(input).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "ShannonFano"
+    }
+}
+
+impl PartialDecompressor for ShannonFano {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        prefix_code::decode_partial(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Huffman;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let codec = ShannonFano::new();
+        let compressed = codec.compress(input).unwrap();
+        assert_eq!(codec.decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_shannon_fano_new() {
+        let codec = ShannonFano::new();
+        assert_eq!(Compressor::name(&codec), "ShannonFano");
+        assert_eq!(Decompressor::name(&codec), "ShannonFano");
+    }
+
+    #[test]
+    fn test_compress_empty() {
+        let codec = ShannonFano::new();
+        let compressed = roundtrip(&[]);
+        assert_eq!(compressed, prefix_code::encode_empty());
+        assert!(codec.decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_single_byte() {
+        roundtrip(&[0x42]);
+    }
+
+    #[test]
+    fn test_roundtrip_all_same() {
+        let compressed = roundtrip(&[0xAA; 100]);
+        assert!(compressed.len() < 100);
+    }
+
+    #[test]
+    fn test_roundtrip_text() {
+        roundtrip(b"the quick brown fox jumps over the lazy dog");
+    }
+
+    #[test]
+    fn test_roundtrip_binary_data() {
+        let input: Vec<u8> = (0..=255).collect();
+        roundtrip(&input);
+    }
+
+    #[test]
+    fn test_lengths_classic_example() {
+        // Frequencies 15, 7, 6, 6, 5 split as {A, B} | {C, D, E}.
+        let mut input = Vec::new();
+        for (symbol, count) in [(b'a', 15), (b'b', 7), (b'c', 6), (b'd', 6), (b'e', 5)] {
+            input.extend(std::iter::repeat_n(symbol, count));
+        }
+        let lengths = shannon_fano_lengths(&input);
+        assert_eq!(lengths[usize::from(b'a')], 2);
+        assert_eq!(lengths[usize::from(b'b')], 2);
+        assert_eq!(lengths[usize::from(b'c')], 2);
+        assert_eq!(lengths[usize::from(b'd')], 3);
+        assert_eq!(lengths[usize::from(b'e')], 3);
+    }
+
+    #[test]
+    fn test_lengths_satisfy_kraft() {
+        let input = b"abracadabra, the quick brown fox".repeat(3);
+        let lengths = shannon_fano_lengths(&input);
+        let kraft: f64 = lengths
+            .iter()
+            .filter(|&&len| len > 0)
+            .map(|&len| 0.5f64.powi(i32::from(len)))
+            .sum();
+        assert!((kraft - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_never_beats_huffman() {
+        let input = b"aaaaaaaaaaaaaaabbbbbbbccccccddddddeeeee";
+        let sf = ShannonFano::new().compress(input).unwrap();
+        let huffman = Huffman::new().compress(input).unwrap();
+        assert!(sf.len() >= huffman.len());
+    }
+
+    #[test]
+    fn test_decodable_by_huffman() {
+        let input = b"shared bitstream and header machinery";
+        let compressed = ShannonFano::new().compress(input).unwrap();
+        assert_eq!(Huffman::new().decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = ShannonFano::new();
+        assert!(codec.decompress(&[1, 0]).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, Transform};
+
+const DEFAULT_ELEMENT_SIZE: u8 = 4;
+
+/// Reversible byte-shuffle filter, in the style of Blosc.
+///
+/// Treats the input as an array of `element_size`-byte elements and
+/// transposes it, emitting the first byte of every element, then the second,
+/// and so on. The high bytes of small integers or the exponents of similar
+/// floats then sit next to each other as long runs for RLE, LZ77 or Huffman.
+/// Trailing bytes that do not fill an element are copied unchanged. Output
+/// layout: `[element size: u8][shuffled bytes]`; run it before the main codec.
+/// As a [`Transform`] it writes the shuffled bytes alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shuffle {
+    element_size: u8,
+}
+
+impl Default for Shuffle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shuffle {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_element_size(DEFAULT_ELEMENT_SIZE)
+    }
+
+    /// Creates a filter for `element_size`-byte elements.
+    #[must_use]
+    pub const fn with_element_size(element_size: u8) -> Self {
+        Self { element_size }
+    }
+
+    #[must_use]
+    pub const fn element_size(&self) -> u8 {
+        self.element_size
+    }
+
+    fn checked_element_size(self) -> Result<usize> {
+        if self.element_size == 0 {
+            return Err(CompressionError::InvalidInput(
+                "element size must be non-zero".to_string(),
+            ));
+        }
+        Ok(usize::from(self.element_size))
+    }
+}
+
+impl Transform for Shuffle {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let size = self.checked_element_size()?;
+        let body = input.len() / size * size;
+
+        let mut output = Vec::with_capacity(input.len());
+        for byte in 0..size {
+            output.extend(input[..body].iter().skip(byte).step_by(size));
+        }
+        output.extend_from_slice(&input[body..]);
+
+        Ok(output)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let size = self.checked_element_size()?;
+        let elements = input.len() / size;
+        let body = elements * size;
+
+        let mut output = vec![0u8; input.len()];
+        for (byte, plane) in input[..body].chunks_exact(elements.max(1)).enumerate() {
+            for (element, &value) in plane.iter().enumerate() {
+                output[element * size + byte] = value;
+            }
+        }
+        output[body..].copy_from_slice(&input[body..]);
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "Shuffle"
+    }
+}
+
+impl Compressor for Shuffle {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let shuffled = self.forward(input)?;
+        let mut output = Vec::with_capacity(shuffled.len() + 1);
+        output.push(self.element_size);
+        output.extend_from_slice(&shuffled);
+        Ok(output)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        input_len.saturating_add(1)
+    }
+
+    fn name(&self) -> &'static str {
+        "Shuffle"
+    }
+}
+
+impl Decompressor for Shuffle {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        if input[0] == 0 {
+            return Err(CompressionError::InvalidHeader);
+        }
+        Self::with_element_size(input[0]).inverse(&input[1..])
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match input.split_first() {
+            None => Err(CompressionError::CorruptedData),
+            Some((0, _)) => Err(CompressionError::InvalidHeader),
+            Some((_, body)) => Ok(Some(body.len() as u64)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Shuffle"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lz77, Rle};
+
+    fn roundtrip(shuffle: Shuffle, input: &[u8]) -> Vec<u8> {
+        let filtered = shuffle.compress(input).unwrap();
+        assert_eq!(shuffle.decompress(&filtered).unwrap(), input);
+        filtered
+    }
+
+    #[test]
+    fn test_shuffle_new() {
+        let shuffle = Shuffle::new();
+        assert_eq!(shuffle.element_size(), DEFAULT_ELEMENT_SIZE);
+        assert_eq!(Shuffle::default(), shuffle);
+        assert_eq!(Compressor::name(&shuffle), "Shuffle");
+        assert_eq!(Decompressor::name(&shuffle), "Shuffle");
+    }
+
+    #[test]
+    fn test_empty() {
+        let shuffle = Shuffle::new();
+        assert_eq!(roundtrip(shuffle, &[]), [shuffle.element_size()]);
+        assert!(matches!(
+            shuffle.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output() {
+        let filtered = Shuffle::with_element_size(2)
+            .compress(&[1, 0xA, 2, 0xB, 3, 0xC])
+            .unwrap();
+        assert_eq!(filtered, [2, 1, 2, 3, 0xA, 0xB, 0xC]);
+    }
+
+    #[test]
+    fn test_transform_omits_header() {
+        let shuffle = Shuffle::with_element_size(2);
+        let input = [1, 0xA, 2, 0xB, 3];
+        let shuffled = shuffle.forward(&input).unwrap();
+        assert_eq!(shuffled, [1, 2, 0xA, 0xB, 3]);
+        assert_eq!(shuffle.inverse(&shuffled).unwrap(), input);
+        assert_eq!(Transform::name(&shuffle), "Shuffle");
+        assert!(matches!(
+            Shuffle::with_element_size(0).inverse(&input),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_trailing_partial_element() {
+        let filtered = roundtrip(
+            Shuffle::with_element_size(4),
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+        );
+        assert_eq!(&filtered[9..], &[9, 10]);
+    }
+
+    #[test]
+    fn test_shorter_than_element() {
+        assert_eq!(
+            roundtrip(Shuffle::with_element_size(8), &[1, 2, 3]),
+            [8, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_element_size_one_is_identity() {
+        let input = b"no change expected";
+        let filtered = roundtrip(Shuffle::with_element_size(1), input);
+        assert_eq!(&filtered[1..], input);
+    }
+
+    #[test]
+    fn test_roundtrip_various_sizes() {
+        let input: Vec<u8> = (0..1000u32).map(|i| (i * 37).to_le_bytes()[0]).collect();
+        for size in [2, 3, 4, 7, 8, 16, 255] {
+            roundtrip(Shuffle::with_element_size(size), &input);
+        }
+    }
+
+    #[test]
+    fn test_improves_rle_on_u32_array() {
+        let input: Vec<u8> = (0..1000u32).flat_map(u32::to_le_bytes).collect();
+        let plain = Rle::new().compress(&input).unwrap();
+        let shuffled = Rle::new()
+            .compress(&Shuffle::new().compress(&input).unwrap())
+            .unwrap();
+        assert!(shuffled.len() * 2 < plain.len());
+    }
+
+    #[test]
+    fn test_pipeline_with_lz77() {
+        let input: Vec<u8> = (0..300u16).flat_map(|i| (i * 3).to_le_bytes()).collect();
+        let shuffle = Shuffle::with_element_size(2);
+        let compressed = Lz77::new()
+            .compress(&shuffle.compress(&input).unwrap())
+            .unwrap();
+        let restored = shuffle
+            .decompress(&Lz77::new().decompress(&compressed).unwrap())
+            .unwrap();
+        assert_eq!(restored, input);
+    }
+
+    #[test]
+    fn test_zero_element_size() {
+        let result = Shuffle::with_element_size(0).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Shuffle::new().decompress(&[0, 1]);
+        assert!(matches!(result, Err(CompressionError::InvalidHeader)));
+    }
+}
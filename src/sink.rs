@@ -0,0 +1,224 @@
+//! Destinations for decoded bytes, so that one decoding loop serves both
+//! [`Decompressor::decompress`](crate::Decompressor::decompress), which
+//! grows a `Vec`, and
+//! [`Decompressor::decompress_into`](crate::Decompressor::decompress_into),
+//! which fills the caller's buffer without allocating.
+
+use crate::error::{CompressionError, Result};
+
+/// Append-only output of a coder.
+pub trait Sink {
+    /// Number of bytes written so far.
+    fn len(&self) -> usize;
+
+    /// Hints that about `additional` more bytes are coming, so a growing
+    /// sink can allocate once. Does nothing by default.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the sink is full.
+    fn push(&mut self, byte: u8) -> Result<()>;
+
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if `bytes` do not fit.
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Appends `count` copies of `element`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the run's length
+    /// overflows `usize`, or `CompressionError::BufferTooSmall` if it does
+    /// not fit.
+    fn extend_run(&mut self, element: &[u8], count: usize) -> Result<()>;
+
+    /// Appends `len` bytes copied from `offset` bytes back, byte by byte,
+    /// so that a copy overlapping its own output repeats it as LZ77
+    /// matches do.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if `offset` is zero or
+    /// reaches before the first byte, or `CompressionError::BufferTooSmall`
+    /// if the copy does not fit.
+    fn copy_back(&mut self, offset: usize, len: usize) -> Result<()>;
+}
+
+/// End of a run of `count` elements starting at `start`.
+fn run_end(start: usize, element: &[u8], count: usize) -> Result<usize> {
+    count
+        .checked_mul(element.len())
+        .and_then(|run| run.checked_add(start))
+        .ok_or(CompressionError::CorruptedData)
+}
+
+const fn check_offset(offset: usize, len: usize) -> Result<usize> {
+    if offset == 0 || offset > len {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(len - offset)
+}
+
+impl Sink for Vec<u8> {
+    fn len(&self) -> usize {
+        Self::len(self)
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        Self::push(self, byte);
+        Ok(())
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        Self::extend_from_slice(self, bytes);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Self::reserve(self, additional);
+    }
+
+    fn extend_run(&mut self, element: &[u8], count: usize) -> Result<()> {
+        let end = run_end(Self::len(self), element, count)?;
+        if let [byte] = element {
+            self.resize(end, *byte);
+        } else {
+            self.extend(std::iter::repeat_n(element, count).flatten());
+        }
+        Ok(())
+    }
+
+    fn copy_back(&mut self, offset: usize, len: usize) -> Result<()> {
+        let start = check_offset(offset, Self::len(self))?;
+        Self::reserve(self, len);
+        for i in start..start + len {
+            let byte = self[i];
+            Self::push(self, byte);
+        }
+        Ok(())
+    }
+}
+
+/// Sink writing into the start of a caller's buffer.
+#[derive(Debug)]
+pub struct SliceSink<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub const fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Room for the next `len` bytes.
+    fn take(&mut self, len: usize) -> Result<&mut [u8]> {
+        let start = self.len;
+        let slots = start
+            .checked_add(len)
+            .and_then(|end| self.buffer.get_mut(start..end))
+            .ok_or(CompressionError::BufferTooSmall)?;
+        self.len += len;
+        Ok(slots)
+    }
+}
+
+impl Sink for SliceSink<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.take(1)?[0] = byte;
+        Ok(())
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<()> {
+        self.take(bytes.len())?.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn extend_run(&mut self, element: &[u8], count: usize) -> Result<()> {
+        let len = run_end(0, element, count)?;
+        let slots = self.take(len)?;
+        if let [byte] = element {
+            slots.fill(*byte);
+        } else {
+            for slot in slots.chunks_exact_mut(element.len()) {
+                slot.copy_from_slice(element);
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_back(&mut self, offset: usize, len: usize) -> Result<()> {
+        let start = check_offset(offset, self.len)?;
+        let end = self.len;
+        self.take(len)?;
+        if offset >= len {
+            self.buffer.copy_within(start..start + len, end);
+        } else {
+            for i in 0..len {
+                self.buffer[end + i] = self.buffer[start + i];
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the same sequence to `sink`, as a decoder would.
+    fn fill(sink: &mut impl Sink) -> Result<()> {
+        sink.push(b'a')?;
+        sink.extend_from_slice(b"bc")?;
+        sink.extend_run(b"d", 3)?;
+        sink.extend_run(b"ef", 2)?;
+        sink.copy_back(2, 5)?;
+        sink.copy_back(12, 4)
+    }
+
+    #[test]
+    fn test_slice_matches_vec() {
+        let mut vec = Vec::new();
+        fill(&mut vec).unwrap();
+        assert_eq!(vec, b"abcdddefefefefeddde");
+
+        let mut buffer = [0; 32];
+        let mut slice = SliceSink::new(&mut buffer);
+        fill(&mut slice).unwrap();
+        let len = slice.len();
+        assert_eq!(buffer[..len], vec);
+    }
+
+    #[test]
+    fn test_slice_full() {
+        let mut buffer = [0; 18];
+        assert_eq!(
+            fill(&mut SliceSink::new(&mut buffer)),
+            Err(CompressionError::BufferTooSmall)
+        );
+        let mut slice = SliceSink::new(&mut buffer);
+        assert_eq!(
+            slice.extend_run(b"ab", usize::MAX),
+            Err(CompressionError::CorruptedData)
+        );
+        assert_eq!(slice.len(), 0);
+    }
+
+    #[test]
+    fn test_copy_back_bounds() {
+        let mut vec = b"ab".to_vec();
+        assert_eq!(vec.copy_back(0, 1), Err(CompressionError::CorruptedData));
+        assert_eq!(vec.copy_back(3, 1), Err(CompressionError::CorruptedData));
+        let mut buffer = [0; 4];
+        let mut slice = SliceSink::new(&mut buffer);
+        slice.extend_from_slice(b"ab").unwrap();
+        assert_eq!(slice.copy_back(3, 1), Err(CompressionError::CorruptedData));
+        assert_eq!(slice.copy_back(2, 3), Err(CompressionError::BufferTooSmall));
+    }
+}
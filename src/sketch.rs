@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+const DEFAULT_WIDTH_BITS: u8 = 12;
+const DEFAULT_DEPTH: u8 = 4;
+const DEFAULT_TRACKED: usize = 256;
+/// Widest counter row, in bits of index: 2^24 counters per row.
+pub const MAX_WIDTH_BITS: u8 = 24;
+/// Most counter rows.
+pub const MAX_DEPTH: u8 = 8;
+
+/// Scrambles a symbol so nearby values land in unrelated counters
+/// (the `SplitMix64` finaliser).
+const fn mix(symbol: u64) -> u64 {
+    let mut x = symbol.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Index into the counters for `symbol` in each of `depth` rows of
+/// 2^`width_bits`, by double hashing.
+fn slots(width_bits: u8, depth: u8, symbol: u64) -> impl Iterator<Item = usize> {
+    let hash = mix(symbol);
+    let (h1, h2) = (hash, (hash >> 32) | 1);
+    let mask = (1u64 << width_bits) - 1;
+    (0..u64::from(depth)).map(move |row| {
+        let column = h1.wrapping_add(row.wrapping_mul(h2)) & mask;
+        // Rows and columns both fit the counter vector's length.
+        usize::try_from(row << width_bits | column).unwrap_or(usize::MAX)
+    })
+}
+
+/// Symbol frequency estimates in a fixed memory envelope, for alphabets
+/// too large to count exactly, such as 16-bit or variable-width symbols
+/// over a long stream.
+///
+/// A count-min sketch keeps `depth` rows of 2^`width_bits` saturating
+/// counters. Each symbol bumps one counter per row, chosen by hashing, and
+/// its estimate is the smallest of them: never below the true count, and
+/// above it only by what colliding symbols contributed. Only the counters
+/// that are already the smallest are raised (conservative update), which
+/// keeps collisions from piling up. Alongside, the `tracked` symbols with
+/// the highest estimates are kept by name, so the heavy hitters can be
+/// listed with [`top`](Self::top), for instance to build a code for the
+/// common symbols with [`build_codes`](crate::build_codes) and escape the
+/// rest. Adaptive models call [`halve`](Self::halve) periodically so recent
+/// symbols outweigh old ones. Memory is allocated once, up front, and does
+/// not grow with the number of distinct symbols.
+#[derive(Debug, Clone)]
+pub struct FrequencySketch {
+    width_bits: u8,
+    depth: u8,
+    counters: Vec<u32>,
+    tracked: usize,
+    heavy: HashMap<u64, u64>,
+    /// No tracked symbol's count is below this.
+    floor: u64,
+    total: u64,
+}
+
+impl Default for FrequencySketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrequencySketch {
+    /// Creates a sketch of four rows of 4096 counters tracking the top 256
+    /// symbols: about 70 KiB.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_dimensions(DEFAULT_WIDTH_BITS, DEFAULT_DEPTH, DEFAULT_TRACKED)
+    }
+
+    /// Creates a sketch of `depth` rows of 2^`width_bits` counters that
+    /// tracks the `tracked` most frequent symbols by name. `width_bits` is
+    /// clamped to `1..=MAX_WIDTH_BITS` and `depth` to `1..=MAX_DEPTH`.
+    ///
+    /// Each estimate overshoots by at most `e / 2^width_bits` of the total
+    /// with probability `1 - e^-depth`; wider rows buy accuracy, more rows
+    /// buy confidence.
+    #[must_use]
+    pub fn with_dimensions(width_bits: u8, depth: u8, tracked: usize) -> Self {
+        let width_bits = width_bits.clamp(1, MAX_WIDTH_BITS);
+        let depth = depth.clamp(1, MAX_DEPTH);
+        Self {
+            width_bits,
+            depth,
+            counters: vec![0; usize::from(depth) << width_bits],
+            tracked,
+            heavy: HashMap::with_capacity(tracked),
+            floor: 0,
+            total: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn width_bits(&self) -> u8 {
+        self.width_bits
+    }
+
+    #[must_use]
+    pub const fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[must_use]
+    pub const fn tracked(&self) -> usize {
+        self.tracked
+    }
+
+    /// Number of symbols added, less what [`halve`](Self::halve) removed.
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Approximate bytes of memory held, fixed at construction.
+    #[must_use]
+    pub const fn memory_bytes(&self) -> usize {
+        self.counters.capacity() * size_of::<u32>()
+            + self.tracked * size_of::<(u64, u64)>()
+            + size_of::<Self>()
+    }
+
+    fn sketch_estimate(&self, symbol: u64) -> u32 {
+        slots(self.width_bits, self.depth, symbol)
+            .map(|slot| self.counters[slot])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Counts one occurrence of `symbol`.
+    pub fn add(&mut self, symbol: u64) {
+        let estimate = self.sketch_estimate(symbol).saturating_add(1);
+        for slot in slots(self.width_bits, self.depth, symbol) {
+            let counter = &mut self.counters[slot];
+            *counter = (*counter).max(estimate);
+        }
+        self.total += 1;
+        self.track(symbol, u64::from(estimate));
+    }
+
+    /// Records `symbol`'s new estimate among the heavy hitters, evicting
+    /// the smallest if it has been overtaken.
+    fn track(&mut self, symbol: u64, estimate: u64) {
+        if let Some(count) = self.heavy.get_mut(&symbol) {
+            *count = estimate;
+            return;
+        }
+        if self.heavy.len() < self.tracked {
+            self.heavy.insert(symbol, estimate);
+            self.floor = self.floor.min(estimate);
+            return;
+        }
+        if estimate <= self.floor {
+            return;
+        }
+        // Ties evict the larger symbol, so the result does not depend on
+        // the map's iteration order.
+        let Some((&smallest, &count)) = self
+            .heavy
+            .iter()
+            .min_by_key(|&(&symbol, &count)| (count, std::cmp::Reverse(symbol)))
+        else {
+            return;
+        };
+        if estimate > count {
+            self.heavy.remove(&smallest);
+            self.heavy.insert(symbol, estimate);
+            self.floor = self.heavy.values().copied().min().unwrap_or(0);
+        } else {
+            self.floor = count;
+        }
+    }
+
+    /// Estimated number of occurrences of `symbol`: never less than the
+    /// true count.
+    #[must_use]
+    pub fn estimate(&self, symbol: u64) -> u64 {
+        self.heavy
+            .get(&symbol)
+            .copied()
+            .unwrap_or_else(|| u64::from(self.sketch_estimate(symbol)))
+    }
+
+    /// Up to `n` of the tracked symbols with their estimated counts, most
+    /// frequent first and ties in symbol order.
+    #[must_use]
+    pub fn top(&self, n: usize) -> Vec<(u64, u64)> {
+        let mut top: Vec<(u64, u64)> = self
+            .heavy
+            .iter()
+            .map(|(&symbol, &count)| (symbol, count))
+            .collect();
+        top.sort_unstable_by_key(|&(symbol, count)| (std::cmp::Reverse(count), symbol));
+        top.truncate(n);
+        top
+    }
+
+    /// Halves every count, rounding down, so that what comes next weighs as
+    /// much as everything seen so far. Symbols whose count drops to zero
+    /// stop being tracked.
+    pub fn halve(&mut self) {
+        for counter in &mut self.counters {
+            *counter >>= 1;
+        }
+        self.heavy.retain(|_, count| {
+            *count >>= 1;
+            *count > 0
+        });
+        self.floor >>= 1;
+        self.total >>= 1;
+    }
+
+    /// Forgets everything counted, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.counters.fill(0);
+        self.heavy.clear();
+        self.floor = 0;
+        self.total = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Zipf-like stream over a 16-bit alphabet: symbol `k` of `distinct`
+    /// appears about `len / (k + 1)` times in proportion.
+    #[allow(clippy::cast_precision_loss)]
+    fn zipf(len: usize, distinct: u64) -> Vec<u64> {
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let weights: Vec<f64> = (1..=distinct).map(|k| 1.0 / k as f64).collect();
+        let sum: f64 = weights.iter().sum();
+        let mut cumulative = Vec::with_capacity(weights.len());
+        let mut acc = 0.0;
+        for weight in weights {
+            acc += weight / sum;
+            cumulative.push(acc);
+        }
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                let rank = cumulative.partition_point(|&c| c < u);
+                // Spread ranks over the alphabet so they do not sit together.
+                (rank as u64).wrapping_mul(40_503) & 0xFFFF
+            })
+            .collect()
+    }
+
+    fn exact(stream: &[u64]) -> HashMap<u64, u64> {
+        let mut counts = HashMap::new();
+        for &symbol in stream {
+            *counts.entry(symbol).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let sketch = FrequencySketch::new();
+        assert_eq!(
+            (sketch.width_bits(), sketch.depth(), sketch.tracked()),
+            (12, 4, 256)
+        );
+        assert!(
+            sketch.memory_bytes() < 80 * 1024,
+            "{}",
+            sketch.memory_bytes()
+        );
+        let clamped = FrequencySketch::with_dimensions(40, 0, 1);
+        assert_eq!((clamped.width_bits(), clamped.depth()), (MAX_WIDTH_BITS, 1));
+    }
+
+    #[test]
+    fn test_small_alphabet_is_exact() {
+        let mut sketch = FrequencySketch::new();
+        for (symbol, count) in [(7u64, 50), (65_535, 3), (1 << 40, 20)] {
+            for _ in 0..count {
+                sketch.add(symbol);
+            }
+        }
+        assert_eq!(sketch.estimate(7), 50);
+        assert_eq!(sketch.estimate(65_535), 3);
+        assert_eq!(sketch.estimate(1 << 40), 20);
+        assert_eq!(sketch.estimate(8), 0);
+        assert_eq!(sketch.total(), 73);
+        assert_eq!(sketch.top(2), [(7, 50), (1 << 40, 20)]);
+    }
+
+    #[test]
+    fn test_estimates_never_undercount() {
+        let stream = zipf(200_000, 20_000);
+        let mut sketch = FrequencySketch::with_dimensions(10, 4, 64);
+        let before = sketch.memory_bytes();
+        for &symbol in &stream {
+            sketch.add(symbol);
+        }
+        assert_eq!(sketch.memory_bytes(), before);
+
+        let counts = exact(&stream);
+        let mut overshoot = 0;
+        for (&symbol, &count) in &counts {
+            let estimate = sketch.estimate(symbol);
+            assert!(estimate >= count, "{symbol}: {estimate} < {count}");
+            overshoot += estimate - count;
+        }
+        // Far more distinct symbols than counters, yet the average error
+        // stays a small fraction of the stream.
+        let average = overshoot / counts.len() as u64;
+        assert!(average < 200_000 / 1024, "{average}");
+    }
+
+    #[test]
+    fn test_top_finds_heavy_hitters() {
+        let stream = zipf(100_000, 50_000);
+        let mut sketch = FrequencySketch::new();
+        for &symbol in &stream {
+            sketch.add(symbol);
+        }
+        let counts = exact(&stream);
+        let mut expected: Vec<(u64, u64)> = counts.into_iter().collect();
+        expected.sort_unstable_by_key(|&(symbol, count)| (std::cmp::Reverse(count), symbol));
+
+        let top = sketch.top(10);
+        assert_eq!(top.len(), 10);
+        for &(symbol, _) in &expected[..10] {
+            assert!(top.iter().any(|&(s, _)| s == symbol), "{symbol} missing");
+        }
+        for &(symbol, count) in &top {
+            let truth = expected.iter().find(|&&(s, _)| s == symbol).unwrap().1;
+            assert!(
+                count >= truth && count - truth < truth / 20,
+                "{count} vs {truth}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_top_feeds_build_codes() {
+        let stream = zipf(20_000, 5_000);
+        let mut sketch = FrequencySketch::new();
+        for &symbol in &stream {
+            sketch.add(symbol);
+        }
+        let top = sketch.top(255);
+        let escapes = sketch.total()
+            - top
+                .iter()
+                .map(|&(_, count)| count)
+                .sum::<u64>()
+                .min(sketch.total());
+        let mut freqs: Vec<u64> = top.iter().map(|&(_, count)| count).collect();
+        freqs.push(escapes.max(1));
+        let codes = crate::build_codes(&freqs);
+        assert_eq!(codes.len(), 256);
+        assert!(codes[0].len <= codes[254].len);
+    }
+
+    #[test]
+    fn test_halve_ages_counts() {
+        let mut sketch = FrequencySketch::with_dimensions(8, 2, 2);
+        for _ in 0..9 {
+            sketch.add(1);
+        }
+        sketch.add(2);
+        sketch.halve();
+        assert_eq!(sketch.estimate(1), 4);
+        assert_eq!(sketch.top(2), [(1, 4)]);
+        assert_eq!(sketch.total(), 5);
+
+        // A newcomer overtakes once the old counts have decayed.
+        for _ in 0..5 {
+            sketch.add(3);
+        }
+        sketch.add(4);
+        assert_eq!(sketch.top(2), [(3, 5), (1, 4)]);
+
+        sketch.clear();
+        assert_eq!((sketch.estimate(3), sketch.total()), (0, 0));
+        assert!(sketch.top(2).is_empty());
+    }
+}
@@ -0,0 +1,409 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{CompressionError, Result};
+use crate::stats::CompressionStats;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Element tag for a literal run.
+pub const TAG_LITERAL: u8 = 0b00;
+/// Element tag for a copy with an 11-bit offset and a length of 4 to 11.
+pub const TAG_COPY_1: u8 = 0b01;
+/// Element tag for a copy with a 16-bit offset.
+pub const TAG_COPY_2: u8 = 0b10;
+/// Element tag for a copy with a 32-bit offset. Decoded but never written.
+pub const TAG_COPY_4: u8 = 0b11;
+/// Literal lengths minus one at or above this spill into trailing bytes.
+pub const MAX_INLINE_LITERAL: usize = 60;
+/// Shortest match the encoder emits.
+pub const MIN_MATCH: usize = 4;
+/// Longest length a single copy element carries.
+pub const MAX_COPY_LEN: usize = 64;
+/// Farthest back a match can reach: written offsets are at most 16-bit.
+pub const MAX_OFFSET: usize = u16::MAX as usize;
+const MAX_COPY_1_OFFSET: usize = (1 << 11) - 1;
+const MAX_COPY_1_LEN: usize = 11;
+const HASH_BITS: u32 = 14;
+/// Searches speed up by one byte per this many bytes without a match.
+const SKIP_TRIGGER: u32 = 5;
+
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]])
+}
+
+const fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(0x1E35_A7BD) >> (u32::BITS - HASH_BITS)) as usize
+}
+
+fn write_literal(output: &mut Vec<u8>, literal: &[u8]) {
+    let n = literal.len() - 1;
+    if n < MAX_INLINE_LITERAL {
+        output.push((n << 2).to_le_bytes()[0] | TAG_LITERAL);
+    } else {
+        let extra = (usize::BITS - n.leading_zeros()).div_ceil(8) as usize;
+        output.push(((MAX_INLINE_LITERAL - 1 + extra) << 2).to_le_bytes()[0] | TAG_LITERAL);
+        output.extend_from_slice(&n.to_le_bytes()[..extra]);
+    }
+    output.extend_from_slice(literal);
+}
+
+fn write_copy_2(output: &mut Vec<u8>, offset: usize, len: usize) {
+    output.push(((len - 1) << 2).to_le_bytes()[0] | TAG_COPY_2);
+    output.extend_from_slice(&offset.to_le_bytes()[..2]);
+}
+
+fn write_copy(output: &mut Vec<u8>, offset: usize, mut len: usize) {
+    // Split long matches so the remainder never drops below MIN_MATCH.
+    while len >= MAX_COPY_LEN + MIN_MATCH {
+        write_copy_2(output, offset, MAX_COPY_LEN);
+        len -= MAX_COPY_LEN;
+    }
+    if len > MAX_COPY_LEN {
+        write_copy_2(output, offset, MAX_COPY_LEN - MIN_MATCH);
+        len -= MAX_COPY_LEN - MIN_MATCH;
+    }
+    if len <= MAX_COPY_1_LEN && offset <= MAX_COPY_1_OFFSET {
+        output.push(((offset >> 8) << 5 | (len - MIN_MATCH) << 2).to_le_bytes()[0] | TAG_COPY_1);
+        output.push(offset.to_le_bytes()[0]);
+    } else {
+        write_copy_2(output, offset, len);
+    }
+}
+
+/// Reads a little-endian offset of `width` bytes.
+fn read_offset(input: &[u8], pos: &mut usize, width: usize) -> Result<usize> {
+    let bytes = input
+        .get(*pos..*pos + width)
+        .ok_or(CompressionError::CorruptedData)?;
+    *pos += width;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |offset, &byte| offset << 8 | usize::from(byte)))
+}
+
+/// Byte-aligned LZ77 in the Snappy raw block format.
+///
+/// Each element starts with a tag byte whose low two bits select a literal
+/// run or a copy with a 1-, 2- or 4-byte offset; the upper six bits hold a
+/// length, with long literal lengths spilling into up to four trailing
+/// bytes. Matches are found through a single-probe hash table, as in
+/// [`Lz4`](crate::Lz4). Frames are readable by any Snappy block decoder and
+/// this decoder accepts any Snappy block, including 4-byte-offset copies
+/// the encoder never writes. The framing (stream) format's chunk headers and
+/// checksums are not handled. Output layout: `[original length: varint]
+/// [tag][literal length bytes][literals]` or `[tag][offset: 1, 2 or 4 bytes
+/// LE]` per element.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Snappy;
+
+impl Snappy {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Snappy {
+    /// Encodes `input`, counting its literals and matches into `stats`
+    /// if given.
+    fn encode(input: &[u8], stats: Option<&mut CompressionStats>) -> Result<Vec<u8>> {
+        let len = u32::try_from(input.len()).map_err(|_| {
+            CompressionError::InvalidInput(format!(
+                "input of {} bytes exceeds the Snappy length limit",
+                input.len()
+            ))
+        })?;
+        let mut output = Vec::with_capacity(32 + input.len() + input.len() / 6);
+        varint::write_u64(&mut output, u64::from(len));
+
+        let mut table = vec![0u32; 1 << HASH_BITS];
+        let (mut matches, mut match_bytes) = (0, 0);
+        let mut anchor = 0;
+        let mut i = 0;
+        let mut misses = 1u32 << SKIP_TRIGGER;
+        while i + MIN_MATCH <= input.len() {
+            let sequence = read_u32(input, i);
+            let slot = &mut table[hash(sequence)];
+            let candidate = *slot as usize;
+            *slot = u32::try_from(i).unwrap_or(u32::MAX);
+
+            if candidate >= i
+                || i - candidate > MAX_OFFSET
+                || read_u32(input, candidate) != sequence
+            {
+                i += (misses >> SKIP_TRIGGER) as usize;
+                misses += 1;
+                continue;
+            }
+
+            let extra = input[i + MIN_MATCH..]
+                .iter()
+                .zip(&input[candidate + MIN_MATCH..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if anchor < i {
+                write_literal(&mut output, &input[anchor..i]);
+            }
+            write_copy(&mut output, i - candidate, MIN_MATCH + extra);
+            matches += 1;
+            match_bytes += MIN_MATCH + extra;
+            i += MIN_MATCH + extra;
+            anchor = i;
+            misses = 1 << SKIP_TRIGGER;
+        }
+        if anchor < input.len() {
+            write_literal(&mut output, &input[anchor..]);
+        }
+
+        if let Some(stats) = stats {
+            stats.tokens = Some((input.len() - match_bytes) as u64 + matches);
+            stats.matches = Some(matches);
+        }
+        Ok(output)
+    }
+}
+
+impl Compressor for Snappy {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        Self::encode(input, None)
+    }
+
+    /// Also counts tokens: each literal byte is one, and so is each
+    /// match.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let mut stats = CompressionStats::new(input.len() as u64, 0, Duration::ZERO);
+        let output = Self::encode(input, Some(&mut stats))?;
+        stats.output_len = output.len() as u64;
+        stats.elapsed = start.elapsed();
+        Ok((output, stats))
+    }
+
+    /// Snappy's own bound, which also covers the length varint.
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        (input_len + input_len / 6).saturating_add(32)
+    }
+
+    fn name(&self) -> &'static str {
+        "Snappy"
+    }
+}
+
+impl Decompressor for Snappy {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u32(input, &mut 0).map(|len| Some(u64::from(len)))
+    }
+
+    fn name(&self) -> &'static str {
+        "Snappy"
+    }
+}
+
+impl PartialDecompressor for Snappy {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = usize::try_from(varint::read_u32(input, &mut pos)?)
+            .map_err(|_| CompressionError::CorruptedData)?;
+
+        let mut output = Vec::with_capacity(original_len.min(input.len().saturating_mul(64)));
+        while output.len() < original_len {
+            let &tag = input.get(pos).ok_or(CompressionError::CorruptedData)?;
+            pos += 1;
+            let upper = usize::from(tag >> 2);
+
+            let (offset, len) = match tag & 0b11 {
+                TAG_LITERAL => {
+                    let len = if upper < MAX_INLINE_LITERAL {
+                        upper + 1
+                    } else {
+                        read_offset(input, &mut pos, upper + 1 - MAX_INLINE_LITERAL)?
+                            .checked_add(1)
+                            .ok_or(CompressionError::CorruptedData)?
+                    };
+                    let literal = input
+                        .get(pos..pos.saturating_add(len))
+                        .ok_or(CompressionError::CorruptedData)?;
+                    if len > original_len - output.len() {
+                        return Err(CompressionError::CorruptedData);
+                    }
+                    output.extend_from_slice(literal);
+                    pos += len;
+                    continue;
+                }
+                TAG_COPY_1 => {
+                    let low = read_offset(input, &mut pos, 1)?;
+                    (upper >> 3 << 8 | low, (upper & 0b111) + MIN_MATCH)
+                }
+                TAG_COPY_2 => (read_offset(input, &mut pos, 2)?, upper + 1),
+                _ => (read_offset(input, &mut pos, 4)?, upper + 1),
+            };
+            if offset == 0 || offset > output.len() || len > original_len - output.len() {
+                return Err(CompressionError::CorruptedData);
+            }
+
+            let start = output.len() - offset;
+            if offset >= len {
+                output.extend_from_within(start..start + len);
+            } else {
+                // Overlapping copy: each pass doubles the repeated span.
+                let mut remaining = len;
+                while remaining > 0 {
+                    let chunk = remaining.min(output.len() - start);
+                    output.extend_from_within(start..start + chunk);
+                    remaining -= chunk;
+                }
+            }
+        }
+
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = Snappy::new().compress(input).unwrap();
+        assert_eq!(Snappy::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn text(len: usize) -> Vec<u8> {
+        b"the quick brown fox jumps over the lazy dog; "
+            .iter()
+            .cycle()
+            .zip(0u32..)
+            .map(|(&byte, i)| if i % 61 == 0 { b'#' } else { byte })
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn test_snappy_name() {
+        assert_eq!(Compressor::name(&Snappy::new()), "Snappy");
+        assert_eq!(Decompressor::name(&Snappy::new()), "Snappy");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [0]);
+        assert!(matches!(
+            Snappy::new().decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_reference_vectors() {
+        // Produced by the reference Snappy encoder, plus a hand-built block
+        // with a 4-byte-offset copy, which the reference never writes for
+        // short inputs.
+        let wikipedia =
+            b"Wikipedia is a free, web-based, collaborative, multilingual encyclopedia project.";
+        let ascending: Vec<u8> = (0..64).collect();
+        let vectors: [(Vec<u8>, Vec<u8>); 6] = [
+            (vec![1, 0, b'a'], b"a".to_vec()),
+            (
+                [&[27, 24][..], b"hello, ", &[74, 7, 0, 0, 33]].concat(),
+                b"hello, hello, hello, hello!".to_vec(),
+            ),
+            (vec![100, 0, b'a', 254, 1, 0, 138, 1, 0], vec![b'a'; 100]),
+            ([&[64, 240, 63][..], &ascending].concat(), ascending),
+            ([&[81, 240, 80][..], wikipedia].concat(), wikipedia.to_vec()),
+            (vec![5, 0, b'a', 0x0F, 1, 0, 0, 0], b"aaaaa".to_vec()),
+        ];
+        for (compressed, expected) in &vectors {
+            assert_eq!(&Snappy::new().decompress(compressed).unwrap(), expected);
+        }
+        // The encoder agrees byte for byte where the reference's choices are
+        // unambiguous.
+        for (compressed, expected) in &vectors[..4] {
+            assert_eq!(&Snappy::new().compress(expected).unwrap(), compressed);
+        }
+    }
+
+    #[test]
+    fn test_two_byte_offsets() {
+        let mut input = b"0123456789abcdef".to_vec();
+        input.extend((0..3000u32).map(|i| (i % 251).to_le_bytes()[0] ^ 0x55));
+        input.extend_from_slice(b"0123456789abcdef");
+        let compressed = roundtrip(&input);
+        // A 16-byte copy reaching back 3016 bytes.
+        assert_eq!(compressed[compressed.len() - 3..], [62, 200, 11]);
+    }
+
+    #[test]
+    fn test_long_literals_and_copies() {
+        let mut input: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13).to_le_bytes()[0])
+            .collect();
+        input.extend_from_slice(&[9; 5000]);
+        let compressed = roundtrip(&input);
+        // Opens with a literal whose length spills into trailing bytes.
+        assert_eq!(compressed[3] & 0b11, TAG_LITERAL);
+        assert!(usize::from(compressed[3] >> 2) >= MAX_INLINE_LITERAL);
+        assert!(compressed.len() < input.len() + 300);
+    }
+
+    #[test]
+    fn test_compress_with_stats() {
+        let input = b"abcdefgh abcdefgh abcdefgh, then something else entirely".repeat(8);
+        let (output, stats) = Snappy::new().compress_with_stats(&input).unwrap();
+        assert_eq!(output, Snappy::new().compress(&input).unwrap());
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (input.len() as u64, output.len() as u64)
+        );
+        let (tokens, matches) = (stats.tokens.unwrap(), stats.matches.unwrap());
+        assert!(matches > 0 && tokens > matches && tokens < input.len() as u64);
+        assert_eq!(stats.symbols, None);
+        let (_, stats) = Snappy::new().compress_with_stats(b"abc").unwrap();
+        assert_eq!((stats.tokens, stats.matches), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn test_roundtrip_text() {
+        let compressed = roundtrip(&text(64 * 1024));
+        assert!(compressed.len() < 64 * 1024 / 4, "{}", compressed.len());
+        for len in [1, 3, 4, 5, 17, 65, 69, 1000] {
+            roundtrip(&text(len));
+        }
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Snappy::new();
+        let first = codec.compress(&text(500)).unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [text(500), Vec::new(), text(500)]);
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Snappy::new();
+        // Offset of zero and offset before the start of the output.
+        assert!(codec.decompress(&[5, 0, b'a', 0x01, 0]).is_err());
+        assert!(codec.decompress(&[5, 0, b'a', 0x01, 2]).is_err());
+        // Copy running past the original length.
+        assert!(codec.decompress(&[3, 0, b'a', 0x01, 1]).is_err());
+        // Literal past the end of the input, and a truncated length.
+        assert!(codec.decompress(&[3, 0x08, b'a']).is_err());
+        assert!(codec.decompress(&[70, 0xF4, 69]).is_err());
+        // Missing elements and a truncated 4-byte offset.
+        assert!(codec.decompress(&[5, 0, b'a']).is_err());
+        assert!(codec.decompress(&[5, 0, b'a', 0x0F, 1, 0]).is_err());
+    }
+}
@@ -0,0 +1,1724 @@
+//! Machine-readable description of the crate's on-disk formats.
+//!
+//! The tables below are built from the same constants the encoders and
+//! decoders use, and the tests check them against real encoder output, so
+//! the emitted JSON cannot silently drift from the code. Implementers in
+//! other languages can consume [`to_json`] instead of reading the source.
+
+use std::fmt::Write;
+
+use crate::{
+    Algorithm, ChecksumKind, auto, bcj, bitmap, bwt, chunked, chunking, cm, cobs, container,
+    huffman, id_column, image, kvlog, legacy, lz4, lz77, lz77_huffman, lzma, lzp, mux, pipe,
+    pipeline, ppm, predictor, prefix_code, range_coder, repair, rice, rle, rle0, sequence, snappy,
+    tiering, timestamp, tunstall, word_huffman, xor_float, zero_run,
+};
+
+/// Version of the formats described here.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Encoding of a single field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    U8,
+    U16Le,
+    U32Le,
+    U64Le,
+    /// LEB128 variable-length integer.
+    Varint,
+    /// MSB-first bitstream running to the end of the buffer.
+    Bits,
+    /// Opaque bytes; the length is given by an earlier field or by the end
+    /// of the buffer.
+    Bytes,
+    /// Fixed-size records repeated to the end of the buffer.
+    Repeated {
+        size: usize,
+        fields: &'static [Field],
+    },
+}
+
+impl FieldKind {
+    const fn type_name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U16Le => "u16le",
+            Self::U32Le => "u32le",
+            Self::U64Le => "u64le",
+            Self::Varint => "varint",
+            Self::Bits => "bits",
+            Self::Bytes => "bytes",
+            Self::Repeated { .. } => "repeated",
+        }
+    }
+}
+
+/// One field of a format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    /// Byte offset from the start of the buffer (or record), or `None` if
+    /// the field directly follows a variable-length one.
+    pub offset: Option<usize>,
+    pub kind: FieldKind,
+}
+
+impl Field {
+    const fn at(name: &'static str, offset: usize, kind: FieldKind) -> Self {
+        Self {
+            name,
+            offset: Some(offset),
+            kind,
+        }
+    }
+
+    const fn after(name: &'static str, kind: FieldKind) -> Self {
+        Self {
+            name,
+            offset: None,
+            kind,
+        }
+    }
+}
+
+/// Description of one on-disk format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub name: &'static str,
+    pub version: u32,
+    /// Integrity check covering the payload, if any.
+    pub checksum: Option<&'static str>,
+    /// Frame written for empty input with default settings, which decoders
+    /// must accept. Never itself empty; `None` where it depends on required
+    /// parameters or a wrapped codec, or the format is not a codec frame.
+    pub empty: Option<&'static [u8]>,
+    pub fields: &'static [Field],
+    /// Named limits and tag values a decoder needs.
+    pub constants: &'static [(&'static str, u64)],
+}
+
+const RLE_PAIR: [Field; 2] = [
+    Field::at("run_length", 0, FieldKind::U8),
+    Field::at("byte", 1, FieldKind::U8),
+];
+
+const LZ77_TOKEN: [Field; 3] = [
+    Field::at("offset", 0, FieldKind::U16Le),
+    Field::at("length", 2, FieldKind::U8),
+    Field::at("next", 3, FieldKind::U8),
+];
+
+const FORMATS: &[FormatSpec] = &[
+    FormatSpec {
+        name: "rle",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[Field::at(
+            "pairs",
+            0,
+            FieldKind::Repeated {
+                size: rle::PAIR_LEN,
+                fields: &RLE_PAIR,
+            },
+        )],
+        constants: &[("max_run_length", rle::MAX_RUN_LENGTH as u64)],
+    },
+    FormatSpec {
+        name: "rle_escaped",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::U32Le),
+            Field::at("blocks", rle::ESCAPED_HEADER_LEN, FieldKind::Bytes),
+        ],
+        constants: &[
+            ("run_flag", rle::RUN_FLAG as u64),
+            ("min_run", rle::MIN_ESCAPED_RUN as u64),
+            ("max_literal_block", rle::MAX_LITERAL_BLOCK as u64),
+        ],
+    },
+    FormatSpec {
+        name: "rle_varint",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("run_length", 0, FieldKind::Varint),
+            Field::after("byte", FieldKind::U8),
+            Field::after("pairs", FieldKind::Bytes),
+        ],
+        constants: &[("max_run_length", rle::MAX_VARINT_RUN)],
+    },
+    FormatSpec {
+        name: "rle_golomb",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("runs", FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "zero_run",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("tokens", FieldKind::Bytes),
+        ],
+        constants: &[("min_zero_run", zero_run::MIN_ZERO_RUN as u64)],
+    },
+    FormatSpec {
+        name: "rle0",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("symbols", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("runa", rle0::RUNA as u64),
+            ("runb", rle0::RUNB as u64),
+            ("escape", rle0::ESCAPE as u64),
+            ("symbols", rle0::SYMBOLS as u64),
+        ],
+    },
+    FormatSpec {
+        name: "auto",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("algorithm", 0, FieldKind::U8),
+            Field::after("payload", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("algorithm_stored", auto::ALGORITHM_STORED as u64),
+            ("algorithm_rle", auto::ALGORITHM_RLE as u64),
+            ("algorithm_lz77", auto::ALGORITHM_LZ77 as u64),
+            ("algorithm_huffman", auto::ALGORITHM_HUFFMAN as u64),
+        ],
+    },
+    FormatSpec {
+        name: "legacy",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is the format byte, then that format's empty frame.
+        empty: None,
+        fields: &[
+            Field::at("format", 0, FieldKind::U8),
+            Field::after("raw_frame", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("format_rle", legacy::FORMAT_RLE as u64),
+            ("format_rle_escaped", legacy::FORMAT_RLE_ESCAPED as u64),
+            ("format_rle_varint", legacy::FORMAT_RLE_VARINT as u64),
+            ("format_rle_golomb", legacy::FORMAT_RLE_GOLOMB as u64),
+            ("format_lz77", legacy::FORMAT_LZ77 as u64),
+            ("format_huffman", legacy::FORMAT_HUFFMAN as u64),
+        ],
+    },
+    FormatSpec {
+        name: "stored",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("length", 0, FieldKind::Varint),
+            Field::after("data", FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "lz77",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::U32Le),
+            Field::at(
+                "tokens",
+                lz77::HEADER_LEN,
+                FieldKind::Repeated {
+                    size: lz77::TOKEN_LEN,
+                    fields: &LZ77_TOKEN,
+                },
+            ),
+        ],
+        // An original length of `tagged_marker` followed by a nonzero byte
+        // starts a tagged frame instead: the marker, `tag_fixed` and the
+        // length as a varint, as for inputs of 4 GiB and up.
+        constants: &[
+            ("min_match_length", lz77::MIN_MATCH_LENGTH as u64),
+            ("tagged_marker", lz77::TAGGED_MARKER as u64),
+            ("tag_fixed", lz77::TAG_FIXED as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lz77_varint",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0xff, 0xff, 0xff, 0xff, 2, 0]),
+        fields: &[
+            Field::at("marker", 0, FieldKind::U32Le),
+            Field::at("tag", lz77::HEADER_LEN, FieldKind::U8),
+            Field::at("original_length", lz77::HEADER_LEN + 1, FieldKind::Varint),
+            Field::after("tokens", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("tagged_marker", lz77::TAGGED_MARKER as u64),
+            ("tag_varint", lz77::TAG_VARINT as u64),
+            ("min_match_length", lz77::MIN_MATCH_LENGTH as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lz77_packed",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0xff, 0xff, 0xff, 0xff, 3, 0, 12, 4]),
+        fields: &[
+            Field::at("marker", 0, FieldKind::U32Le),
+            Field::at("tag", lz77::HEADER_LEN, FieldKind::U8),
+            Field::at("original_length", lz77::HEADER_LEN + 1, FieldKind::Varint),
+            Field::after("offset_bits", FieldKind::U8),
+            Field::after("length_bits", FieldKind::U8),
+            Field::after("tokens", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("tagged_marker", lz77::TAGGED_MARKER as u64),
+            ("tag_bit_packed", lz77::TAG_BIT_PACKED as u64),
+            ("min_match_length", lz77::MIN_MATCH_LENGTH as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lz77_huffman",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0xff, 0xff, 0xff, 0xff, 0xff, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::U32Le),
+            Field::at("offset_low_plane", lz77::HEADER_LEN, FieldKind::Bytes),
+            Field::after("offset_high_plane", FieldKind::Bytes),
+            Field::after("length_plane", FieldKind::Bytes),
+            Field::after("next_plane", FieldKind::Bytes),
+        ],
+        // Input the planes would not shrink is stored whole instead:
+        // `tagged_marker`, `stored_tag`, the length as a varint, then the
+        // bytes.
+        constants: &[
+            ("planes", lz77_huffman::PLANES as u64),
+            ("tagged_marker", lz77::TAGGED_MARKER as u64),
+            ("stored_tag", lz77::TAG_STORED as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lz4",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("sequences", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("min_match", lz4::MIN_MATCH as u64),
+            ("max_offset", lz4::MAX_OFFSET as u64),
+            ("last_literals", lz4::LAST_LITERALS as u64),
+            ("match_find_limit", lz4::MATCH_FIND_LIMIT as u64),
+            ("run_mask", lz4::RUN_MASK as u64),
+        ],
+    },
+    FormatSpec {
+        name: "snappy",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("elements", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("tag_literal", snappy::TAG_LITERAL as u64),
+            ("tag_copy_1", snappy::TAG_COPY_1 as u64),
+            ("tag_copy_2", snappy::TAG_COPY_2 as u64),
+            ("tag_copy_4", snappy::TAG_COPY_4 as u64),
+            ("max_inline_literal", snappy::MAX_INLINE_LITERAL as u64),
+            ("min_match", snappy::MIN_MATCH as u64),
+            ("max_copy_len", snappy::MAX_COPY_LEN as u64),
+            ("max_offset", snappy::MAX_OFFSET as u64),
+        ],
+    },
+    // From 4 GiB up, the two length fields hold `wide_marker` and 0 and are
+    // followed by `[original length: u64][bit count: u64]`; a stored frame
+    // instead sets the bit after its marker and takes a u64 length.
+    FormatSpec {
+        name: "prefix_code",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 32, 16, 0, 0, 0, 0, 0, 0, 0, 0]),
+        fields: &[
+            Field::at("code_length_table", 0, FieldKind::Bits),
+            Field::after("original_length", FieldKind::U32Le),
+            Field::after("bit_count", FieldKind::U32Le),
+            Field::after("bits", FieldKind::Bits),
+        ],
+        constants: &[
+            ("max_code_length", prefix_code::MAX_CODE_LENGTH as u64),
+            ("symbol_list_limit", prefix_code::SYMBOL_LIST_LIMIT as u64),
+            ("stored_width", prefix_code::STORED_WIDTH as u64),
+            ("stored_overhead", prefix_code::STORED_OVERHEAD as u64),
+            (
+                "wide_stored_overhead",
+                prefix_code::WIDE_STORED_OVERHEAD as u64,
+            ),
+            ("wide_marker", prefix_code::WIDE_MARKER as u64),
+        ],
+    },
+    FormatSpec {
+        name: "huffman_preset",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("preset", 0, FieldKind::U8),
+            Field::at("original_length", 1, FieldKind::Varint),
+            Field::after("payload", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("preset_stored", huffman::PRESET_STORED as u64),
+            ("preset_english_text", huffman::PRESET_ENGLISH_TEXT as u64),
+            ("preset_json", huffman::PRESET_JSON as u64),
+            ("preset_base64", huffman::PRESET_BASE64 as u64),
+            ("preset_hex", huffman::PRESET_HEX as u64),
+            ("preset_profile", huffman::PRESET_PROFILE as u64),
+        ],
+    },
+    FormatSpec {
+        name: "word_huffman",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[
+            0, 0, 32, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 244,
+            128,
+        ]),
+        fields: &[
+            Field::at("new_runs", 0, FieldKind::Varint),
+            Field::after("run_list", FieldKind::Bytes),
+            Field::after("symbols", FieldKind::Varint),
+            Field::after("codes", FieldKind::Bits),
+        ],
+        constants: &[("lexicon_len", word_huffman::LEXICON_LEN as u64)],
+    },
+    FormatSpec {
+        name: "profile",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: None,
+        fields: &[
+            Field::at("delta_stride", 0, FieldKind::U8),
+            Field::at("match_distance", 1, FieldKind::Varint),
+            Field::after("frequencies", FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "tunstall",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[12, 0, 0, 0, 0, 0, 0, 1]),
+        fields: &[
+            Field::at("codeword_bits", 0, FieldKind::U8),
+            Field::at("original_length", 1, FieldKind::U32Le),
+            Field::at("symbol_count_minus_one", 5, FieldKind::U8),
+            Field::at("symbols", tunstall::HEADER_LEN, FieldKind::Bytes),
+            Field::after("weights", FieldKind::Bytes),
+            Field::after("codewords", FieldKind::Bits),
+        ],
+        constants: &[
+            ("min_codeword_bits", tunstall::MIN_CODEWORD_BITS as u64),
+            ("max_codeword_bits", tunstall::MAX_CODEWORD_BITS as u64),
+        ],
+    },
+    FormatSpec {
+        name: "arithmetic",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 127, 255, 252, 0]),
+        fields: &[Field::at("range_coded", 0, FieldKind::Bytes)],
+        constants: &[
+            ("prob_bits", range_coder::PROB_BITS as u64),
+            ("move_bits", range_coder::MOVE_BITS as u64),
+            ("flush_len", range_coder::FLUSH_LEN as u64),
+        ],
+    },
+    FormatSpec {
+        name: "ppm",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[4, 0, 0, 0, 0, 0, 0]),
+        fields: &[
+            Field::at("order", 0, FieldKind::U8),
+            Field::at("original_length", 1, FieldKind::Varint),
+            Field::after("range_coded", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("max_order", ppm::MAX_ORDER as u64),
+            ("max_context_total", ppm::MAX_CONTEXT_TOTAL as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lzma",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[22, 0, 0, 0, 0, 0, 0]),
+        fields: &[
+            Field::at("dict_bits", 0, FieldKind::U8),
+            Field::at("original_length", 1, FieldKind::Varint),
+            Field::after("range_coded", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("min_dict_bits", lzma::MIN_DICT_BITS as u64),
+            ("max_dict_bits", lzma::MAX_DICT_BITS as u64),
+            ("min_match", lzma::MIN_MATCH as u64),
+            ("max_match", lzma::MAX_MATCH as u64),
+            ("prob_bits", range_coder::PROB_BITS as u64),
+            ("move_bits", range_coder::MOVE_BITS as u64),
+            ("literal_context_bits", lzma::LITERAL_CONTEXT_BITS as u64),
+        ],
+    },
+    FormatSpec {
+        name: "lzp",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("tokens", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("order", lzp::ORDER as u64),
+            ("min_match", lzp::MIN_MATCH as u64),
+            ("flags_per_control", lzp::FLAGS_PER_CONTROL as u64),
+        ],
+    },
+    FormatSpec {
+        name: "repair",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("rule_count", FieldKind::Varint),
+            Field::after("rules", FieldKind::Bytes),
+            Field::after("sequence_length", FieldKind::Varint),
+            Field::after("sequence", FieldKind::Bytes),
+        ],
+        constants: &[("first_rule", repair::FIRST_RULE as u64)],
+    },
+    FormatSpec {
+        name: "cm",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0, 0, 0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("range_coded", FieldKind::Bytes),
+        ],
+        constants: &[("min_match", cm::MIN_MATCH as u64)],
+    },
+    FormatSpec {
+        name: "rice",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is `flags_and_k` and a zero count, with no bits.
+        empty: None,
+        fields: &[
+            Field::at("flags_and_k", 0, FieldKind::U8),
+            Field::at("count", 1, FieldKind::U32Le),
+            Field::at("bits", rice::HEADER_LEN, FieldKind::Bits),
+        ],
+        constants: &[
+            ("adaptive_flag", rice::ADAPTIVE_FLAG as u64),
+            ("max_k", rice::MAX_K as u64),
+            ("escape_quotient", rice::ESCAPE_QUOTIENT as u64),
+        ],
+    },
+    FormatSpec {
+        name: "chunked",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is `mode_single`, then the inner codec's empty frame.
+        empty: None,
+        fields: &[
+            Field::at("mode", 0, FieldKind::U8),
+            Field::at("payload", 1, FieldKind::Bytes),
+        ],
+        constants: &[
+            ("mode_single", chunked::MODE_SINGLE as u64),
+            ("mode_chunked", chunked::MODE_CHUNKED as u64),
+            ("mode_chained", chunked::MODE_CHAINED as u64),
+        ],
+    },
+    FormatSpec {
+        name: "pipeline",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("stage_count", 0, FieldKind::U8),
+            Field::at("stages", 1, FieldKind::Bytes),
+            Field::after("payload", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("max_stages", pipeline::MAX_STAGES as u64),
+            ("stage_stored", Algorithm::Stored.tag() as u64),
+            ("stage_rle", Algorithm::Rle.tag() as u64),
+            ("stage_zero_run", Algorithm::ZeroRun.tag() as u64),
+            ("stage_rle0", Algorithm::Rle0.tag() as u64),
+            ("stage_lz77", Algorithm::Lz77.tag() as u64),
+            ("stage_lz77_huffman", Algorithm::Lz77Huffman.tag() as u64),
+            ("stage_lz4", Algorithm::Lz4.tag() as u64),
+            ("stage_snappy", Algorithm::Snappy.tag() as u64),
+            ("stage_huffman", Algorithm::Huffman.tag() as u64),
+            ("stage_word_huffman", Algorithm::WordHuffman.tag() as u64),
+            ("stage_arithmetic", Algorithm::Arithmetic.tag() as u64),
+            ("stage_shannon_fano", Algorithm::ShannonFano.tag() as u64),
+            ("stage_tunstall", Algorithm::Tunstall.tag() as u64),
+            ("stage_ppm", Algorithm::Ppm.tag() as u64),
+            ("stage_lzma", Algorithm::Lzma.tag() as u64),
+            ("stage_lzp", Algorithm::Lzp.tag() as u64),
+            ("stage_repair", Algorithm::RePair.tag() as u64),
+            ("stage_cm", Algorithm::Cm.tag() as u64),
+            ("stage_auto", Algorithm::Auto.tag() as u64),
+            ("stage_delta", Algorithm::Delta.tag() as u64),
+            ("stage_shuffle", Algorithm::Shuffle.tag() as u64),
+            ("stage_bitshuffle", Algorithm::BitShuffle.tag() as u64),
+            ("stage_bwt", Algorithm::Bwt.tag() as u64),
+            ("stage_cobs", Algorithm::Cobs.tag() as u64),
+            ("stage_bcj", Algorithm::Bcj.tag() as u64),
+            ("stage_fax", Algorithm::Fax.tag() as u64),
+            ("stage_rle2d", Algorithm::Rle2d.tag() as u64),
+        ],
+    },
+    // The algorithm byte takes the pipeline's stage tags. The checksum is
+    // present only when the `checksum_mask` bits of the flags hold a
+    // checksum tag, and is 4 bytes for CRC-32 and Adler-32 or 8 for
+    // xxHash64; the payload follows it. With the `flag_chunked` bit set
+    // the payload is a `chunked` frame of the algorithm's output, in
+    // chained mode exactly when `flag_chained` is also set.
+    FormatSpec {
+        name: "container",
+        version: FORMAT_VERSION,
+        checksum: Some("crc32, adler32 or xxhash64"),
+        // Empty input has an original length of 0 and the algorithm's empty
+        // frame as its payload.
+        empty: None,
+        fields: &[
+            Field::at("magic", 0, FieldKind::U32Le),
+            Field::at("version", 4, FieldKind::U8),
+            Field::at("algorithm", 5, FieldKind::U8),
+            Field::at("flags", 6, FieldKind::U8),
+            Field::at("original_length", 7, FieldKind::U64Le),
+            Field::at("payload_length", 15, FieldKind::U64Le),
+            Field::at("checksum", container::HEADER_LEN, FieldKind::Bytes),
+            Field::after("payload", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("magic", u32::from_le_bytes(container::MAGIC) as u64),
+            ("version", container::VERSION as u64),
+            ("known_flags", container::KNOWN_FLAGS as u64),
+            ("checksum_mask", container::CHECKSUM_MASK as u64),
+            ("flag_chunked", container::FLAG_CHUNKED as u64),
+            ("flag_chained", container::FLAG_CHAINED as u64),
+            ("checksum_crc32", ChecksumKind::Crc32.tag() as u64),
+            ("checksum_adler32", ChecksumKind::Adler32.tag() as u64),
+            ("checksum_xxhash64", ChecksumKind::XxHash64.tag() as u64),
+        ],
+    },
+    FormatSpec {
+        name: "dedup",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is a zero chunk count, then the inner codec's empty
+        // frame.
+        empty: None,
+        fields: &[
+            Field::at("chunk_count", 0, FieldKind::Varint),
+            Field::after("chunk_table", FieldKind::Bytes),
+            Field::after("payload", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("tag_new", chunking::TAG_NEW),
+            ("min_avg_size", chunking::MIN_AVG_SIZE as u64),
+            ("max_avg_size", chunking::MAX_AVG_SIZE as u64),
+            ("normalization", chunking::NORMALIZATION as u64),
+        ],
+    },
+    FormatSpec {
+        name: "mux_frame",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Payloads are never empty, since codecs never emit an empty frame; a
+        // zero `length` marks a sentinel frame that carries no data.
+        empty: None,
+        fields: &[
+            Field::at("channel", 0, FieldKind::U16Le),
+            Field::at("length", 2, FieldKind::U32Le),
+            Field::at("payload", mux::FRAME_HEADER_LEN, FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "pipe_block",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // A zero `length` ends the stream, so empty input is that block
+        // alone.
+        empty: None,
+        fields: &[
+            Field::at("length", 0, FieldKind::U32Le),
+            Field::at("payload", pipe::BLOCK_HEADER_LEN, FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "bitmap",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::Varint),
+            Field::after("chunks", FieldKind::Varint),
+            Field::after("chunk_data", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("kind_zero_fill", bitmap::KIND_ZERO_FILL),
+            ("kind_one_fill", bitmap::KIND_ONE_FILL),
+            ("kind_literals", bitmap::KIND_LITERALS),
+        ],
+    },
+    FormatSpec {
+        name: "bcj",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0]),
+        fields: &[
+            Field::at("arch", 0, FieldKind::U8),
+            Field::at("code", 1, FieldKind::Bytes),
+        ],
+        constants: &[
+            ("arch_x86", bcj::ARCH_X86 as u64),
+            ("arch_arm", bcj::ARCH_ARM as u64),
+            ("arch_arm64", bcj::ARCH_ARM64 as u64),
+        ],
+    },
+    FormatSpec {
+        name: "fax",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[8, 0]),
+        fields: &[
+            Field::at("width", 0, FieldKind::Varint),
+            Field::after("height", FieldKind::Varint),
+            Field::after("rows", FieldKind::Bits),
+        ],
+        constants: &[("max_width", image::MAX_WIDTH as u64)],
+    },
+    FormatSpec {
+        name: "rle_2d",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[1, 1, 0]),
+        fields: &[
+            Field::at("stride", 0, FieldKind::Varint),
+            Field::after("pixel_size", FieldKind::U8),
+            Field::after("original_length", FieldKind::Varint),
+            Field::after("ops", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("op_literal", image::ROW_OP_LITERAL as u64),
+            ("op_repeat", image::ROW_OP_REPEAT as u64),
+            ("op_above", image::ROW_OP_ABOVE as u64),
+            ("min_run", image::MIN_ROW_RUN as u64),
+        ],
+    },
+    FormatSpec {
+        name: "cobs",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[1, 0]),
+        fields: &[
+            Field::at("code", 0, FieldKind::U8),
+            Field::after("blocks", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("delimiter", cobs::DELIMITER as u64),
+            ("max_block", cobs::MAX_BLOCK as u64),
+        ],
+    },
+    FormatSpec {
+        name: "delta",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[1]),
+        fields: &[
+            Field::at("stride", 0, FieldKind::U8),
+            Field::at("deltas", 1, FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "bwt",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0, 0, 0, 0x10, 0x0e, 0]),
+        fields: &[
+            Field::at("original_length", 0, FieldKind::U32Le),
+            Field::at("block_size", 4, FieldKind::U32Le),
+            Field::at("blocks", bwt::HEADER_LEN, FieldKind::Bytes),
+        ],
+        constants: &[
+            ("primary_index_len", bwt::PRIMARY_INDEX_LEN as u64),
+            ("max_block_size", bwt::MAX_BLOCK_SIZE as u64),
+        ],
+    },
+    FormatSpec {
+        name: "shuffle",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[4]),
+        fields: &[
+            Field::at("element_size", 0, FieldKind::U8),
+            Field::at("shuffled", 1, FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "predictor",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is `bytes_per_pixel` and `stride`, with no rows.
+        empty: None,
+        fields: &[
+            Field::at("bytes_per_pixel", 0, FieldKind::U8),
+            Field::at("stride", 1, FieldKind::U32Le),
+            Field::at("rows", predictor::HEADER_LEN, FieldKind::Bytes),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "bitshuffle",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[4]),
+        fields: &[
+            Field::at("element_size", 0, FieldKind::U8),
+            Field::at("planes", 1, FieldKind::Bits),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "delta_of_delta",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0, 0, 0]),
+        fields: &[
+            Field::at("count", 0, FieldKind::U32Le),
+            Field::at("first", timestamp::HEADER_LEN, FieldKind::U64Le),
+            Field::at("bits", timestamp::HEADER_LEN + 8, FieldKind::Bits),
+        ],
+        constants: &[],
+    },
+    FormatSpec {
+        name: "i64_sequence",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[0, 0]),
+        fields: &[
+            Field::at("flags", 0, FieldKind::U8),
+            Field::at("count", 1, FieldKind::Varint),
+            Field::after("body", FieldKind::Bytes),
+        ],
+        constants: &[("flag_runs", sequence::FLAG_RUNS as u64)],
+    },
+    FormatSpec {
+        name: "id_column",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: Some(&[2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        fields: &[
+            Field::at("timestamps_length", 0, FieldKind::Varint),
+            Field::after("timestamps", FieldKind::Bytes),
+            Field::after("columns", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("id_len", id_column::ID_LEN as u64),
+            ("timestamp_len", id_column::TIMESTAMP_LEN as u64),
+            ("column_stored", id_column::COLUMN_STORED as u64),
+            ("column_huffman", id_column::COLUMN_HUFFMAN as u64),
+        ],
+    },
+    FormatSpec {
+        name: "xor_float",
+        version: FORMAT_VERSION,
+        checksum: None,
+        // Empty input is `width` and a zero count, with no bits.
+        empty: None,
+        fields: &[
+            Field::at("width", 0, FieldKind::U8),
+            Field::at("count", 1, FieldKind::U32Le),
+            Field::at("bits", xor_float::HEADER_LEN, FieldKind::Bits),
+        ],
+        constants: &[("leading_bits", xor_float::LEADING_BITS as u64)],
+    },
+    // Each log record is a `container` frame with a CRC-32, whose data is
+    // one entry.
+    FormatSpec {
+        name: "kvlog_entry",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: None,
+        fields: &[
+            Field::at("op", 0, FieldKind::U8),
+            Field::at("key_length", 1, FieldKind::U32Le),
+            Field::at("key", kvlog::ENTRY_HEADER_LEN, FieldKind::Bytes),
+            Field::after("value", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("op_put", kvlog::OP_PUT as u64),
+            ("op_delete", kvlog::OP_DELETE as u64),
+        ],
+    },
+    FormatSpec {
+        name: "tiering_index",
+        version: FORMAT_VERSION,
+        checksum: None,
+        empty: None,
+        fields: &[
+            Field::at("blocks", 0, FieldKind::Varint),
+            Field::after("entries", FieldKind::Bytes),
+        ],
+        constants: &[
+            ("tier_hot", tiering::TIER_HOT as u64),
+            ("tier_cold", tiering::TIER_COLD as u64),
+        ],
+    },
+];
+
+/// Every format the crate reads or writes.
+#[must_use]
+pub const fn formats() -> &'static [FormatSpec] {
+    FORMATS
+}
+
+/// Looks up a format by name.
+#[must_use]
+pub fn format(name: &str) -> Option<&'static FormatSpec> {
+    FORMATS.iter().find(|spec| spec.name == name)
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_fields(out: &mut String, fields: &[Field]) {
+    out.push('[');
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        write_string(out, field.name);
+        out.push_str(",\"offset\":");
+        match field.offset {
+            Some(offset) => {
+                let _ = write!(out, "{offset}");
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"type\":");
+        write_string(out, field.kind.type_name());
+        if let FieldKind::Repeated { size, fields } = field.kind {
+            let _ = write!(out, ",\"size\":{size},\"fields\":");
+            write_fields(out, fields);
+        }
+        out.push('}');
+    }
+    out.push(']');
+}
+
+/// Renders every format as a single JSON document.
+#[must_use]
+pub fn to_json() -> String {
+    let mut out = String::new();
+    let _ = write!(out, "{{\"version\":{FORMAT_VERSION},\"formats\":[");
+    for (i, spec) in FORMATS.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        write_string(&mut out, spec.name);
+        let _ = write!(out, ",\"version\":{},\"checksum\":", spec.version);
+        match spec.checksum {
+            Some(checksum) => write_string(&mut out, checksum),
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"empty\":");
+        match spec.empty {
+            Some(bytes) => {
+                out.push('[');
+                for (j, byte) in bytes.iter().enumerate() {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    let _ = write!(out, "{byte}");
+                }
+                out.push(']');
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"fields\":");
+        write_fields(&mut out, spec.fields);
+        out.push_str(",\"constants\":{");
+        for (j, (name, value)) in spec.constants.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            write_string(&mut out, name);
+            let _ = write!(out, ":{value}");
+        }
+        out.push_str("}}");
+    }
+    out.push_str("]}");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Arithmetic, Auto, Bcj, BcjArch, BitShuffle, BitmapCodec, Bwt, Chunked, Chunker, Cm, Cobs,
+        Codec, Compressor, CopyOptions, Dedup, Delta, DeltaOfDelta, Huffman, IdColumn, Legacy,
+        LegacyFormat, Lz4, Lz77, Lz77Huffman, Lz77Tokens, Lzma, Lzp, Mux, PartialDecompressor,
+        Pipeline, Ppm, Predictor, Preset, Profile, RePair, Rice, Rle, Rle0, Shuffle, Snappy,
+        Stored, Tunstall, WordHuffman, XorFloat, ZeroRun, copy_compress, encode_i64_sequence,
+        varint,
+    };
+
+    /// Reads the fixed-offset integer field `name` of format `format`.
+    fn read_field(format_name: &str, name: &str, bytes: &[u8]) -> u64 {
+        let spec = format(format_name).unwrap();
+        let field = spec.fields.iter().find(|f| f.name == name).unwrap();
+        let offset = field.offset.unwrap();
+        let width = match field.kind {
+            FieldKind::U8 => 1,
+            FieldKind::U16Le => 2,
+            FieldKind::U32Le => 4,
+            FieldKind::U64Le => 8,
+            kind => panic!("{name} is {kind:?}, not a fixed-width integer"),
+        };
+        let mut buf = [0u8; 8];
+        buf[..width].copy_from_slice(&bytes[offset..offset + width]);
+        u64::from_le_bytes(buf)
+    }
+
+    fn constant(format_name: &str, name: &str) -> u64 {
+        let spec = format(format_name).unwrap();
+        spec.constants.iter().find(|(n, _)| *n == name).unwrap().1
+    }
+
+    #[test]
+    fn test_names_unique_and_versioned() {
+        let mut names: Vec<&str> = formats().iter().map(|s| s.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), formats().len());
+        assert!(formats().iter().all(|s| s.version == FORMAT_VERSION));
+    }
+
+    #[test]
+    fn test_fixed_offsets_increase() {
+        for spec in formats() {
+            let offsets: Vec<usize> = spec.fields.iter().filter_map(|f| f.offset).collect();
+            assert!(
+                offsets.windows(2).all(|w| w[0] < w[1]),
+                "{} offsets out of order",
+                spec.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_repeated_records_fit() {
+        for spec in formats() {
+            for field in spec.fields {
+                if let FieldKind::Repeated { size, fields } = field.kind {
+                    assert!(fields.iter().all(|f| f.offset.unwrap() < size));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lz77_matches_encoder() {
+        let input = b"abcabcabcabcxyz";
+        let compressed = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(input)
+            .unwrap();
+        assert_eq!(read_field("lz77", "original_length", &compressed), 15);
+        let FieldKind::Repeated { size, .. } = format("lz77").unwrap().fields[1].kind else {
+            panic!("tokens should be repeated");
+        };
+        assert_eq!((compressed.len() - lz77::HEADER_LEN) % size, 0);
+    }
+
+    #[test]
+    fn test_lz77_packed_matches_encoder() {
+        let compressed = Lz77::new().compress(b"abcabcabcabcxyz").unwrap();
+        let marker = read_field("lz77_packed", "marker", &compressed);
+        assert_eq!(marker, constant("lz77_packed", "tagged_marker"));
+        let tag = read_field("lz77_packed", "tag", &compressed);
+        assert_eq!(tag, constant("lz77_packed", "tag_bit_packed"));
+        // A one-byte varint length, then the two widths.
+        assert_eq!(compressed[5..8], [15, 12, 4]);
+        // Three 9-bit literals, one 25-bit match and two more literals.
+        assert_eq!(compressed.len(), 8 + (9 * 5 + 25_usize).div_ceil(8));
+    }
+
+    #[test]
+    fn test_lz77_varint_matches_encoder() {
+        let compressed = Lz77::new()
+            .with_tokens(Lz77Tokens::Varint)
+            .compress(b"abcabcabcabcxyz")
+            .unwrap();
+        let marker = read_field("lz77_varint", "marker", &compressed);
+        assert_eq!(marker, constant("lz77_varint", "tagged_marker"));
+        let tag = read_field("lz77_varint", "tag", &compressed);
+        assert_eq!(tag, constant("lz77_varint", "tag_varint"));
+        // A one-byte varint length, three literals [0][next], then offset 3
+        // as one varint byte.
+        assert_eq!(
+            compressed[lz77::HEADER_LEN + 1..],
+            [15, 0, b'a', 0, b'b', 0, b'c', 3, 9, b'x', 0, b'y', 0, b'z']
+        );
+    }
+
+    #[test]
+    fn test_lz77_huffman_matches_encoder() {
+        let input = b"abcabcabcabcabcx".repeat(20);
+        let compressed = Lz77Huffman::new().compress(&input).unwrap();
+        assert_eq!(
+            read_field("lz77_huffman", "original_length", &compressed),
+            320
+        );
+        // The first plane holds the low offset byte of each LZ77 token.
+        let tokens = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(&input)
+            .unwrap();
+        let offset_low: Vec<u8> = tokens[lz77::HEADER_LEN..]
+            .iter()
+            .step_by(lz77::TOKEN_LEN)
+            .copied()
+            .collect();
+        let (plane, _) = Huffman::new()
+            .decompress_partial(&compressed[lz77::HEADER_LEN..])
+            .unwrap();
+        assert_eq!(plane, offset_low);
+        assert_eq!(constant("lz77_huffman", "planes"), lz77::TOKEN_LEN as u64);
+
+        // Input the planes would not shrink is stored behind the marker.
+        let stored = Lz77Huffman::new().compress(b"abc").unwrap();
+        let marker = constant("lz77_huffman", "tagged_marker");
+        assert_eq!(
+            read_field("lz77_huffman", "original_length", &stored),
+            marker
+        );
+        let tag = constant("lz77_huffman", "stored_tag");
+        assert_eq!(u64::from(stored[lz77::HEADER_LEN]), tag);
+        assert_eq!(stored[lz77::HEADER_LEN + 1..], [3, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    fn test_id_column_matches_encoder() {
+        let ids = [&[0, 0, 0, 0, 1, 0][..], &[7; 10]].concat().repeat(3);
+        let compressed = IdColumn::new().compress(&ids).unwrap();
+        let mut pos = 0;
+        let timestamps_len = varint::read_u64(&compressed, &mut pos).unwrap();
+        let end = pos + usize::try_from(timestamps_len).unwrap();
+        assert_eq!(
+            crate::decode_i64_sequence(&compressed[pos..end]).unwrap(),
+            [256_i64; 3]
+        );
+        assert_eq!(
+            u64::from(compressed[end]),
+            constant("id_column", "column_stored")
+        );
+    }
+
+    #[test]
+    fn test_lz4_matches_encoder() {
+        let compressed = Lz4::new().compress(&[b'a'; 20]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 20);
+        let token = u64::from(compressed[pos]);
+        assert_eq!(
+            token & constant("lz4", "run_mask"),
+            14 - constant("lz4", "min_match")
+        );
+        let trailing = u64::from(compressed[compressed.len() - 6] >> 4);
+        assert_eq!(trailing, constant("lz4", "last_literals"));
+    }
+
+    #[test]
+    fn test_snappy_matches_encoder() {
+        let compressed = Snappy::new().compress(&[b'a'; 12]).unwrap();
+        assert_eq!(u64::from(compressed[0]), 12);
+        assert_eq!(
+            u64::from(compressed[1] & 0b11),
+            constant("snappy", "tag_literal")
+        );
+        let copy = compressed[3];
+        assert_eq!(u64::from(copy & 0b11), constant("snappy", "tag_copy_1"));
+        assert_eq!(
+            u64::from(copy >> 2 & 0b111),
+            11 - constant("snappy", "min_match")
+        );
+    }
+
+    #[test]
+    fn test_prefix_code_stored_matches_encoder() {
+        let noise: Vec<u8> = (0..=255u8).rev().collect();
+        let stored = Huffman::new().compress(&noise).unwrap();
+        let overhead = usize::try_from(constant("prefix_code", "stored_overhead")).unwrap();
+        assert_eq!(stored.len(), overhead + noise.len());
+        // The marker is a full symbol count followed by the width field.
+        assert_eq!(stored[0], 0xFF);
+        assert_eq!(
+            u64::from(stored[1] >> 5),
+            constant("prefix_code", "stored_width")
+        );
+        assert_eq!(&stored[overhead..], noise.as_slice());
+    }
+
+    #[test]
+    fn test_huffman_preset_matches_encoder() {
+        let compressed = Huffman::with_preset(Preset::Hex)
+            .compress(b"0123456789abcdef")
+            .unwrap();
+        assert_eq!(
+            read_field("huffman_preset", "preset", &compressed),
+            constant("huffman_preset", "preset_hex")
+        );
+        let mut pos = 1;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 16);
+
+        let stored = Huffman::with_preset(Preset::Hex).compress(b"XYZ").unwrap();
+        assert_eq!(
+            read_field("huffman_preset", "preset", &stored),
+            constant("huffman_preset", "preset_stored")
+        );
+        assert_eq!(&stored[2..], b"XYZ");
+    }
+
+    #[test]
+    fn test_profile_matches_encoder() {
+        let sample: Vec<u8> = (0..400u32).flat_map(|i| (i * 5).to_le_bytes()).collect();
+        let profile = Profile::capture(&sample);
+        let encoded = profile.encode();
+        assert_eq!(read_field("profile", "delta_stride", &encoded), 4);
+        let mut pos = 1;
+        varint::read_u64(&encoded, &mut pos).unwrap();
+        let mut frequencies = Vec::new();
+        while pos < encoded.len() {
+            frequencies.push(varint::read_u32(&encoded, &mut pos).unwrap());
+        }
+        assert_eq!(frequencies, profile.frequencies());
+    }
+
+    #[test]
+    fn test_word_huffman_matches_encoder() {
+        let compressed = WordHuffman::new()
+            .compress(b"the cat and the other cat")
+            .unwrap();
+        let mut pos = 0;
+        // "cat" is the only run missing from the lexicon.
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 1);
+        assert!(constant("word_huffman", "lexicon_len") > 100);
+    }
+
+    #[test]
+    fn test_rle_matches_encoder() {
+        let compressed = Rle::new().compress(&[7u8; 300]).unwrap();
+        let max = constant("rle", "max_run_length");
+        assert_eq!(u64::from(compressed[0]), max);
+        assert_eq!(compressed[1], 7);
+    }
+
+    #[test]
+    fn test_rle_escaped_matches_encoder() {
+        let compressed = Rle::escaped().compress(b"xy\0\0\0\0").unwrap();
+        assert_eq!(read_field("rle_escaped", "original_length", &compressed), 6);
+        let run = compressed[rle::ESCAPED_HEADER_LEN + 3];
+        assert_eq!(
+            u64::from(run),
+            constant("rle_escaped", "run_flag") + 4 - constant("rle_escaped", "min_run")
+        );
+    }
+
+    #[test]
+    fn test_rle_varint_matches_encoder() {
+        let compressed = Rle::varint().compress(&[7; 300]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 300);
+        assert_eq!(&compressed[pos..], [7]);
+        assert_eq!(
+            constant("rle_varint", "max_run_length"),
+            u64::from(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn test_rle_golomb_matches_encoder() {
+        let compressed = Rle::golomb().compress(&[7; 300]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 300);
+        let mut reader = crate::bitstream::BitReader::new(&compressed[pos..]);
+        assert_eq!(reader.read_u8(8), Some(7));
+        assert_eq!(reader.read_exp_golomb(), Some(299));
+        assert_eq!(reader.bytes_consumed(), compressed.len() - pos);
+    }
+
+    #[test]
+    fn test_bitmap_matches_encoder() {
+        let compressed = BitmapCodec::new().compress(&[0xFF; 16]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 16);
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 1);
+        let header = varint::read_u64(&compressed, &mut pos).unwrap();
+        assert_eq!(header & 0b11, constant("bitmap", "kind_one_fill"));
+        assert_eq!(header >> 2, 2);
+    }
+
+    #[test]
+    fn test_fax_matches_encoder() {
+        let compressed = crate::image::Fax::new(300, 2).compress(&[0; 76]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 300);
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 2);
+        assert!(constant("fax", "max_width") >= 300);
+    }
+
+    #[test]
+    fn test_rle_2d_matches_encoder() {
+        let compressed = crate::image::Rle2d::new(6)
+            .with_pixel_size(2)
+            .compress(&[4; 12])
+            .unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 6);
+        assert_eq!(compressed[pos], 2);
+        pos += 1;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 12);
+        // One run of six two-byte pixels, then the pixel.
+        let op = varint::read_u64(&compressed, &mut pos).unwrap();
+        assert_eq!(op & 0b11, constant("rle_2d", "op_repeat"));
+        assert_eq!((op >> 2) + 1, 6);
+        assert_eq!(&compressed[pos..], [4, 4]);
+        assert_eq!(constant("rle_2d", "min_run"), 3);
+    }
+
+    #[test]
+    fn test_cobs_matches_encoder() {
+        let encoded = Cobs::new().compress(&[0x11, 0, 0x22]).unwrap();
+        assert_eq!(read_field("cobs", "code", &encoded), 2);
+        assert_eq!(
+            u64::from(*encoded.last().unwrap()),
+            constant("cobs", "delimiter")
+        );
+        let full: Vec<u8> = vec![1; 300];
+        let encoded = Cobs::new().compress(&full).unwrap();
+        assert_eq!(
+            read_field("cobs", "code", &encoded),
+            constant("cobs", "max_block") + 1
+        );
+    }
+
+    #[test]
+    fn test_zero_run_matches_encoder() {
+        let compressed = ZeroRun::new().compress(&[0; 300]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 300);
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 300);
+        assert_eq!(&compressed[pos..], [0]);
+    }
+
+    #[test]
+    fn test_rle0_matches_encoder() {
+        let compressed = Rle0::new().compress(&[0, 0, 0, 0, 7, 255]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 6);
+        let symbols: Vec<u64> = compressed[pos..].iter().map(|&b| u64::from(b)).collect();
+        let escape = constant("rle0", "escape");
+        assert_eq!(
+            symbols,
+            [
+                constant("rle0", "runb"),
+                constant("rle0", "runa"),
+                8,
+                escape,
+                256 - escape
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_matches_encoder() {
+        let compressed = Auto::new().compress(&[5; 500]).unwrap();
+        assert_eq!(
+            read_field("auto", "algorithm", &compressed),
+            constant("auto", "algorithm_rle")
+        );
+        let stored = Auto::new().compress(b"x").unwrap();
+        assert_eq!(
+            read_field("auto", "algorithm", &stored),
+            constant("auto", "algorithm_stored")
+        );
+        assert_eq!(&stored[1..], b"\x01x");
+    }
+
+    #[test]
+    fn test_legacy_matches_encoder() {
+        let compressed = Legacy::new(LegacyFormat::Lz77).compress(b"abcabc").unwrap();
+        assert_eq!(
+            read_field("legacy", "format", &compressed),
+            constant("legacy", "format_lz77")
+        );
+        let raw = Lz77::new()
+            .with_tokens(Lz77Tokens::Fixed)
+            .compress(b"abcabc");
+        assert_eq!(compressed[1..], raw.unwrap());
+    }
+
+    #[test]
+    fn test_bwt_matches_encoder() {
+        let transformed = Bwt::with_block_size(4).compress(b"banana").unwrap();
+        assert_eq!(read_field("bwt", "original_length", &transformed), 6);
+        assert_eq!(read_field("bwt", "block_size", &transformed), 4);
+        let blocks = &transformed[bwt::HEADER_LEN..];
+        let index_len = bwt::PRIMARY_INDEX_LEN;
+        assert_eq!(constant("bwt", "primary_index_len"), index_len as u64);
+        // Two blocks, "bana" and "na", each behind its primary index.
+        assert_eq!(blocks.len(), 2 * index_len + 6);
+        assert_eq!(&blocks[index_len..index_len + 4], b"anba");
+    }
+
+    #[test]
+    fn test_stored_matches_encoder() {
+        let compressed = Stored::new().compress(&[9; 200]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 200);
+        assert_eq!(&compressed[pos..], [9; 200]);
+    }
+
+    #[test]
+    fn test_tunstall_and_rice_match_encoders() {
+        let compressed = Tunstall::with_codeword_bits(10)
+            .compress(b"banana bandana")
+            .unwrap();
+        assert_eq!(read_field("tunstall", "codeword_bits", &compressed), 10);
+        assert_eq!(read_field("tunstall", "original_length", &compressed), 14);
+
+        let compressed = Ppm::with_order(3).compress(&[b'a'; 200]).unwrap();
+        assert_eq!(read_field("ppm", "order", &compressed), 3);
+        let mut pos = 1;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 200);
+        assert_eq!(constant("ppm", "max_order"), 8);
+
+        let compressed = Arithmetic::new().compress(&[]).unwrap();
+        assert_eq!(compressed.len() as u64, constant("arithmetic", "flush_len"));
+        assert_eq!(format("arithmetic").unwrap().fields[0].offset, Some(0));
+
+        let compressed = Lzma::with_dict_bits(16).compress(&[b'a'; 200]).unwrap();
+        assert_eq!(read_field("lzma", "dict_bits", &compressed), 16);
+        let mut pos = 1;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 200);
+        assert_eq!(constant("lzma", "max_match"), 273);
+
+        let compressed = Lzp::new().compress(&[b'a'; 200]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 200);
+        // One unpredicted literal past the context, then a flagged match
+        // covering the rest.
+        let order = usize::try_from(constant("lzp", "order")).unwrap();
+        assert_eq!(compressed[pos..=pos + order], [b'a'; 5]);
+        pos += order + 1;
+        assert_eq!(compressed[pos], 1);
+        pos += 1;
+        let extra = varint::read_u64(&compressed, &mut pos).unwrap();
+        assert_eq!(extra + constant("lzp", "min_match"), 200 - 5);
+        assert_eq!(pos, compressed.len());
+
+        // One rule for "ab", used twice; 256 is the varint [0x80, 0x02].
+        let compressed = RePair::new().compress(b"abab").unwrap();
+        assert_eq!(compressed, [4, 1, b'a', b'b', 2, 0x80, 0x02, 0x80, 0x02]);
+        assert_eq!(constant("repair", "first_rule"), 256);
+
+        let compressed = Cm::new().compress(&[b'a'; 200]).unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&compressed, &mut pos).unwrap(), 200);
+        assert_eq!(constant("cm", "min_match"), 5);
+
+        let encoded = Rice::adaptive().encode(&[1, 2, 3]).unwrap();
+        assert_eq!(
+            read_field("rice", "flags_and_k", &encoded),
+            constant("rice", "adaptive_flag")
+        );
+        assert_eq!(read_field("rice", "count", &encoded), 3);
+    }
+
+    #[test]
+    fn test_framing_formats_match_encoders() {
+        let mut mux = Mux::new(Vec::new(), Rle::new());
+        mux.write(513, b"aaaa").unwrap();
+        let stream = mux.into_inner();
+        assert_eq!(read_field("mux_frame", "channel", &stream), 513);
+        assert_eq!(read_field("mux_frame", "length", &stream), 2);
+
+        let mut piped = Vec::new();
+        copy_compress(&b"aaaa"[..], &mut piped, &Rle::new(), &CopyOptions::new()).unwrap();
+        assert_eq!(read_field("pipe_block", "length", &piped), 2);
+
+        let chunked = Chunked::with_threshold(Huffman::new(), 0, 4)
+            .compress(b"abcdefgh")
+            .unwrap();
+        assert_eq!(
+            read_field("chunked", "mode", &chunked),
+            constant("chunked", "mode_chunked")
+        );
+        let chained = Chunked::with_threshold(Lz77::new(), 0, 4)
+            .with_chained(true)
+            .compress(b"abcdefgh")
+            .unwrap();
+        assert_eq!(
+            read_field("chunked", "mode", &chained),
+            constant("chunked", "mode_chained")
+        );
+
+        let piped = Pipeline::new()
+            .with_stage(Algorithm::Delta)
+            .with_stage(Algorithm::Lz4)
+            .compress(b"abcdefgh")
+            .unwrap();
+        assert_eq!(read_field("pipeline", "stage_count", &piped), 2);
+        assert_eq!(u64::from(piped[1]), constant("pipeline", "stage_delta"));
+        assert_eq!(u64::from(piped[2]), constant("pipeline", "stage_lz4"));
+        // One tag per algorithm, after the stage limit.
+        assert_eq!(
+            format("pipeline").unwrap().constants.len(),
+            1 + Algorithm::ALL.len()
+        );
+
+        let framed = container::encode_frame(Algorithm::Huffman, b"abcdefgh").unwrap();
+        assert_eq!(
+            read_field("container", "magic", &framed),
+            constant("container", "magic")
+        );
+        assert_eq!(
+            read_field("container", "version", &framed),
+            constant("container", "version")
+        );
+        assert_eq!(
+            read_field("container", "algorithm", &framed),
+            constant("pipeline", "stage_huffman")
+        );
+        assert_eq!(read_field("container", "flags", &framed), 0);
+        assert_eq!(read_field("container", "original_length", &framed), 8);
+        assert_eq!(
+            read_field("container", "payload_length", &framed),
+            (framed.len() - container::HEADER_LEN) as u64
+        );
+        let checked = container::encode_frame_with_checksum(
+            Algorithm::Huffman,
+            Some(ChecksumKind::XxHash64),
+            b"abcdefgh",
+        )
+        .unwrap();
+        assert_eq!(
+            read_field("container", "flags", &checked) & constant("container", "checksum_mask"),
+            constant("container", "checksum_xxhash64")
+        );
+        let spec = format("container").unwrap();
+        let field = spec.fields.iter().find(|f| f.name == "checksum").unwrap();
+        let offset = field.offset.unwrap();
+        assert_eq!(
+            checked[offset..offset + 8],
+            crate::xxhash64(b"abcdefgh", 0).to_le_bytes()
+        );
+
+        // Equal minimum, average and maximum give fixed 64-byte chunks.
+        let input = [&[7; 64][..], &[7; 64], b"tail"].concat();
+        let dedup = Dedup::with_chunker(Stored::new(), Chunker::with_sizes(64, 64, 64))
+            .compress(&input)
+            .unwrap();
+        let mut pos = 0;
+        assert_eq!(varint::read_u64(&dedup, &mut pos).unwrap(), 3);
+        let new = u8::try_from(constant("dedup", "tag_new")).unwrap();
+        assert_eq!(dedup[1..6], [new, 64, new + 1, new, 4]);
+        // The payload holds each distinct chunk once.
+        assert_eq!(dedup[6], 68);
+        assert_eq!(dedup[7..], [&[7; 64][..], b"tail"].concat());
+
+        let bcj = Bcj::with_arch(BcjArch::Arm64).compress(b"abcd").unwrap();
+        assert_eq!(
+            read_field("bcj", "arch", &bcj),
+            constant("bcj", "arch_arm64")
+        );
+
+        let delta = Delta::with_stride(3).compress(b"abcdef").unwrap();
+        assert_eq!(read_field("delta", "stride", &delta), 3);
+
+        let shuffled = Shuffle::with_element_size(2).compress(b"abcdef").unwrap();
+        assert_eq!(read_field("shuffle", "element_size", &shuffled), 2);
+
+        let predicted = Predictor::new(3)
+            .with_bytes_per_pixel(3)
+            .compress(b"abcdef")
+            .unwrap();
+        assert_eq!(read_field("predictor", "bytes_per_pixel", &predicted), 3);
+        assert_eq!(read_field("predictor", "stride", &predicted), 3);
+
+        let planes = BitShuffle::with_element_size(8)
+            .compress(b"abcdefgh")
+            .unwrap();
+        assert_eq!(read_field("bitshuffle", "element_size", &planes), 8);
+    }
+
+    #[test]
+    fn test_integer_formats_match_encoders() {
+        let encoded = DeltaOfDelta::new().encode(&[1000, 1010, 1020]).unwrap();
+        assert_eq!(read_field("delta_of_delta", "count", &encoded), 3);
+        assert_eq!(read_field("delta_of_delta", "first", &encoded), 1000);
+
+        let encoded = XorFloat::new().encode_f32(&[1.0, 2.0]).unwrap();
+        assert_eq!(read_field("xor_float", "width", &encoded), 32);
+        assert_eq!(read_field("xor_float", "count", &encoded), 2);
+
+        let encoded = encode_i64_sequence(&(0..100).collect::<Vec<_>>());
+        assert_eq!(
+            read_field("i64_sequence", "flags", &encoded),
+            constant("i64_sequence", "flag_runs")
+        );
+    }
+
+    #[test]
+    fn test_empty_frames_match_encoders() {
+        let codecs: Vec<(&str, Box<dyn Codec>)> = vec![
+            ("rle", Box::new(Rle::new())),
+            ("rle_escaped", Box::new(Rle::escaped())),
+            ("rle_varint", Box::new(Rle::varint())),
+            ("rle_golomb", Box::new(Rle::golomb())),
+            ("zero_run", Box::new(ZeroRun::new())),
+            ("rle0", Box::new(Rle0::new())),
+            ("auto", Box::new(Auto::new())),
+            ("stored", Box::new(Stored::new())),
+            ("lz77", Box::new(Lz77::new().with_tokens(Lz77Tokens::Fixed))),
+            (
+                "lz77_varint",
+                Box::new(Lz77::new().with_tokens(Lz77Tokens::Varint)),
+            ),
+            ("lz77_packed", Box::new(Lz77::new())),
+            ("lz77_huffman", Box::new(Lz77Huffman::new())),
+            ("lz4", Box::new(Lz4::new())),
+            ("snappy", Box::new(Snappy::new())),
+            ("prefix_code", Box::new(Huffman::new())),
+            (
+                "huffman_preset",
+                Box::new(Huffman::with_preset(Preset::Hex)),
+            ),
+            ("word_huffman", Box::new(WordHuffman::new())),
+            ("tunstall", Box::new(Tunstall::new())),
+            ("arithmetic", Box::new(Arithmetic::new())),
+            ("ppm", Box::new(Ppm::new())),
+            ("lzma", Box::new(Lzma::new())),
+            ("lzp", Box::new(Lzp::new())),
+            ("repair", Box::new(RePair::new())),
+            ("cm", Box::new(Cm::new())),
+            ("pipeline", Box::new(Pipeline::new())),
+            ("bitmap", Box::new(BitmapCodec::new())),
+            ("bcj", Box::new(Bcj::new())),
+            ("fax", Algorithm::Fax.new_codec()),
+            ("rle_2d", Algorithm::Rle2d.new_codec()),
+            ("cobs", Box::new(Cobs::new())),
+            ("delta", Box::new(Delta::new())),
+            ("bwt", Box::new(Bwt::new())),
+            ("shuffle", Box::new(Shuffle::new())),
+            ("bitshuffle", Box::new(BitShuffle::new())),
+            ("id_column", Box::new(IdColumn::new())),
+        ];
+        let empty = |name| format(name).unwrap().empty.unwrap();
+        for (name, codec) in &codecs {
+            assert_eq!(codec.compress(&[]).unwrap(), empty(name), "{name}");
+            assert!(codec.decompress(empty(name)).unwrap().is_empty(), "{name}");
+        }
+        assert_eq!(
+            DeltaOfDelta::new().encode(&[]).unwrap(),
+            empty("delta_of_delta")
+        );
+        assert_eq!(encode_i64_sequence(&[]), empty("i64_sequence"));
+
+        let covered = codecs.len() + 2;
+        assert_eq!(
+            formats().iter().filter(|s| s.empty.is_some()).count(),
+            covered
+        );
+        assert!(
+            formats()
+                .iter()
+                .filter_map(|s| s.empty)
+                .all(|e| !e.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let json = to_json();
+        assert!(json.starts_with(&format!("{{\"version\":{FORMAT_VERSION},")));
+        for spec in formats() {
+            assert!(json.contains(&format!("{{\"name\":\"{}\"", spec.name)));
+        }
+        assert!(json.contains("{\"name\":\"offset\",\"offset\":0,\"type\":\"u16le\"}"));
+        assert!(json.contains("\"max_code_length\":63"));
+        assert!(
+            json.contains("{\"name\":\"rle\",\"version\":1,\"checksum\":null,\"empty\":[0,0],")
+        );
+        assert!(json.contains("\"empty\":null,"));
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+        assert_eq!(json.matches('[').count(), json.matches(']').count());
+    }
+
+    #[test]
+    fn test_json_string_escaping() {
+        let mut out = String::new();
+        write_string(&mut out, "a\"b\\c\n");
+        assert_eq!(out, "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
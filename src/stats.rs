@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// What one call to
+/// [`Compressor::compress_with_stats`](crate::Compressor::compress_with_stats)
+/// did.
+///
+/// Sizes and time are filled in for every codec. The token counts are only
+/// known to codecs that report them, and are `None` elsewhere: LZ77, LZ4,
+/// Snappy, LZMA and LZP count their tokens and how many were matches, and
+/// Huffman the distinct bytes it codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub input_len: u64,
+    pub output_len: u64,
+    /// Tokens written by an LZ-family codec, literals and matches alike;
+    /// a codec that writes literals in runs counts each byte of a run.
+    pub tokens: Option<u64>,
+    /// How many of `tokens` were back-references.
+    pub matches: Option<u64>,
+    /// Distinct symbols in the input of an entropy coder.
+    pub symbols: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl CompressionStats {
+    /// Stats with sizes and time only.
+    #[must_use]
+    pub const fn new(input_len: u64, output_len: u64, elapsed: Duration) -> Self {
+        Self {
+            input_len,
+            output_len,
+            tokens: None,
+            matches: None,
+            symbols: None,
+            elapsed,
+        }
+    }
+
+    /// Input size over output size; 0.0 if there was no output.
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        if self.output_len == 0 {
+            return 0.0;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = self.input_len as f64 / self.output_len as f64;
+        ratio
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratio() {
+        let stats = CompressionStats::new(1000, 250, Duration::ZERO);
+        assert!((stats.ratio() - 4.0).abs() < 1e-9);
+        assert_eq!(
+            (stats.tokens, stats.matches, stats.symbols),
+            (None, None, None)
+        );
+        assert!(CompressionStats::new(0, 0, Duration::ZERO).ratio().abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Passthrough codec that frames input verbatim.
+///
+/// Output is never smaller than the input, but it is a real [`Codec`] with
+/// a self-delimiting frame, so it can stand in wherever a codec is expected:
+/// as the fallback when nothing else shrinks the data, as a channel of a
+/// container whose contents are already compressed, or as the baseline that
+/// shows a benchmark's framing and call overhead. Output layout:
+/// `[length: varint][bytes]`.
+///
+/// [`Codec`]: crate::Codec
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stored;
+
+impl Stored {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Compressor for Stored {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() + varint::MAX_LEN_U64);
+        varint::write_u64(&mut output, input.len() as u64);
+        output.extend_from_slice(input);
+        Ok(output)
+    }
+
+    fn compress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let header_len = varint::write_u64_into(output, input.len() as u64)?;
+        let len = header_len + input.len();
+        output
+            .get_mut(header_len..len)
+            .ok_or(CompressionError::BufferTooSmall)?
+            .copy_from_slice(input);
+        Ok(len)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        varint::len_u64(input_len as u64).saturating_add(input_len)
+    }
+
+    fn name(&self) -> &'static str {
+        "Stored"
+    }
+}
+
+impl Decompressor for Stored {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let (bytes, consumed) = frame(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        output
+            .get_mut(..bytes.len())
+            .ok_or(CompressionError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "Stored"
+    }
+}
+
+/// Finds the bytes of the frame at the start of `input`, and the input it
+/// occupies.
+fn frame(input: &[u8]) -> Result<(&[u8], usize)> {
+    let mut pos = 0;
+    let len = usize::try_from(varint::read_u64(input, &mut pos)?)
+        .map_err(|_| CompressionError::CorruptedData)?;
+    let bytes = input
+        .get(pos..pos.saturating_add(len))
+        .ok_or(CompressionError::CorruptedData)?;
+    Ok((bytes, pos + len))
+}
+
+impl PartialDecompressor for Stored {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let (bytes, consumed) = frame(input)?;
+        Ok((bytes.to_vec(), consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Codec;
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = Stored::new().compress(input).unwrap();
+        assert_eq!(Stored::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    #[test]
+    fn test_stored_name() {
+        assert_eq!(Compressor::name(&Stored::new()), "Stored");
+        assert_eq!(Decompressor::name(&Stored::new()), "Stored");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [0]);
+        assert!(Stored::new().decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_overhead_is_length_header() {
+        assert_eq!(roundtrip(b"abc"), b"\x03abc");
+        let input = vec![0xA5; 300];
+        let compressed = roundtrip(&input);
+        assert_eq!(compressed.len(), 302);
+        assert_eq!(&compressed[2..], input.as_slice());
+    }
+
+    #[test]
+    fn test_usable_as_codec() {
+        let codecs: Vec<Box<dyn Codec>> = vec![Box::new(Stored::new())];
+        let compressed = codecs[0].compress(b"payload").unwrap();
+        assert_eq!(codecs[0].decompress(&compressed).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = Stored::new();
+        let stream = [
+            codec.compress(b"one").unwrap(),
+            codec.compress(b"").unwrap(),
+        ]
+        .concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [b"one".to_vec(), Vec::new()]);
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_into_buffers() {
+        let codec = Stored::new();
+        let mut frame = [0u8; 8];
+        assert_eq!(codec.compress_into(b"payload", &mut frame).unwrap(), 8);
+        assert_eq!(frame, *b"\x07payload");
+        assert_eq!(codec.max_compressed_len(7), 8);
+        let mut data = [0u8; 7];
+        assert_eq!(codec.decompress_into(&frame, &mut data).unwrap(), 7);
+        assert_eq!(data, *b"payload");
+        assert!(matches!(
+            codec.compress_into(b"payload", &mut frame[..7]),
+            Err(CompressionError::BufferTooSmall)
+        ));
+        assert!(matches!(
+            codec.decompress_into(&frame, &mut data[..6]),
+            Err(CompressionError::BufferTooSmall)
+        ));
+        assert!(matches!(
+            codec.decompress_into(&frame[..7], &mut data),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = Stored::new();
+        assert!(codec.decompress(b"\x05abc").is_err());
+        assert!(
+            codec
+                .decompress(b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01")
+                .is_err()
+        );
+        assert!(codec.decompress(b"\x80").is_err());
+    }
+}
@@ -0,0 +1,600 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{CompressionError, Result};
+use crate::traits::Codec;
+use crate::varint;
+
+const DEFAULT_COLD_AFTER: u64 = 1024;
+const DATA_FILE: &str = "blocks.dat";
+const INDEX_FILE: &str = "index";
+const INDEX_TEMP_FILE: &str = "index.tmp";
+pub const TIER_HOT: u8 = 0;
+pub const TIER_COLD: u8 = 1;
+
+/// Which codec a block is stored with, or which one it should end up in
+/// when given as a hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// The fast codec; new blocks start here.
+    Hot,
+    /// The high-ratio codec.
+    Cold,
+}
+
+impl Tier {
+    const fn tag(self) -> u8 {
+        match self {
+            Self::Hot => TIER_HOT,
+            Self::Cold => TIER_COLD,
+        }
+    }
+
+    const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            TIER_HOT => Some(Self::Hot),
+            TIER_COLD => Some(Self::Cold),
+            _ => None,
+        }
+    }
+}
+
+/// Where a block lives and how recently it was used.
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    tier: Tier,
+    offset: u64,
+    len: u64,
+    /// Value of the read clock when the block was last appended or read.
+    last_access: u64,
+    hint: Option<Tier>,
+}
+
+/// Blocks read out of the hot tier by [`TieredStore::plan_migration`],
+/// ready to be recompressed with the cold codec.
+///
+/// [`Migration::run`] does the expensive part and touches neither the store
+/// nor its files, so it can be moved to a worker thread while the store
+/// keeps serving reads; [`TieredStore::commit`] then applies the result.
+#[derive(Debug, Clone)]
+pub struct Migration<C: Codec> {
+    cold: C,
+    blocks: Vec<(usize, Vec<u8>)>,
+}
+
+impl<C: Codec> Migration<C> {
+    /// Ids of the blocks this migration moves.
+    pub fn blocks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().map(|&(id, _)| id)
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Compresses every block with the cold codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the cold codec.
+    pub fn run(self) -> Result<MigratedBlocks> {
+        let blocks = self
+            .blocks
+            .into_iter()
+            .map(|(id, data)| Ok((id, self.cold.compress(&data)?)))
+            .collect::<Result<_>>()?;
+        Ok(MigratedBlocks { blocks })
+    }
+}
+
+/// Output of [`Migration::run`], to be handed to [`TieredStore::commit`].
+#[derive(Debug, Clone)]
+pub struct MigratedBlocks {
+    blocks: Vec<(usize, Vec<u8>)>,
+}
+
+/// Block store that keeps recently used blocks in a fast codec and moves
+/// cold ones to a high-ratio codec.
+///
+/// Blocks are appended to one data file in `dir` and addressed by id, so
+/// any block can be read with a single seek. Every block starts in the hot
+/// tier. Reads advance a clock, and a block not read for
+/// [`cold_after`](Self::cold_after) reads becomes a candidate for
+/// migration; callers who know better can pin a block with
+/// [`hint`](Self::hint). Migration happens in three steps so the
+/// recompression can run in the background:
+/// [`plan_migration`](Self::plan_migration) reads the candidates,
+/// [`Migration::run`] recompresses them anywhere, and
+/// [`commit`](Self::commit) appends the new payloads to the data file and
+/// then replaces the index in one rename. A
+/// crash at any point leaves either the old index or the new one, both
+/// pointing at intact data. Space held by replaced payloads is not
+/// reclaimed. Index layout: `[blocks: varint]` then `[tier: u8][offset:
+/// varint][length: varint]` per block.
+#[derive(Debug)]
+pub struct TieredStore<H: Codec, C: Codec> {
+    dir: PathBuf,
+    hot: H,
+    cold: C,
+    cold_after: u64,
+    blocks: Vec<Block>,
+    data_len: u64,
+    clock: u64,
+}
+
+impl<H: Codec, C: Codec + Clone> TieredStore<H, C> {
+    /// Opens (or creates) a store in `dir`, writing new blocks with `hot`
+    /// and migrating them to `cold`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the directory cannot be read, or
+    /// `CompressionError::CorruptedData` if the index is malformed or
+    /// points past the end of the data file.
+    pub fn open(dir: impl AsRef<Path>, hot: H, cold: C) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let data_len = match fs::metadata(dir.join(DATA_FILE)) {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+        let blocks = match fs::read(dir.join(INDEX_FILE)) {
+            Ok(index) => decode_index(&index, data_len)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            dir,
+            hot,
+            cold,
+            cold_after: DEFAULT_COLD_AFTER,
+            blocks,
+            data_len,
+            clock: 0,
+        })
+    }
+
+    /// Treats blocks as cold once `reads` other reads have passed since
+    /// they were last used.
+    #[must_use]
+    pub const fn with_cold_after(mut self, reads: u64) -> Self {
+        self.cold_after = reads;
+        self
+    }
+
+    #[must_use]
+    pub const fn cold_after(&self) -> u64 {
+        self.cold_after
+    }
+
+    /// Number of blocks stored.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// The tier block `id` is stored in, or `None` if there is no such
+    /// block.
+    #[must_use]
+    pub fn tier(&self, id: usize) -> Option<Tier> {
+        self.blocks.get(id).map(|block| block.tier)
+    }
+
+    /// Compresses `data` with the hot codec and stores it as a new block,
+    /// returning its id.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from compression or from writing the files.
+    pub fn append(&mut self, data: &[u8]) -> Result<usize> {
+        let payload = self.hot.compress(data)?;
+        let offset = self.write_payloads(&[&payload])?;
+        self.blocks.push(Block {
+            tier: Tier::Hot,
+            offset,
+            len: payload.len() as u64,
+            last_access: self.clock,
+            hint: None,
+        });
+        self.write_index()?;
+        Ok(self.blocks.len() - 1)
+    }
+
+    /// Reads block `id` and marks it as just used.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` for an unknown id,
+    /// `CompressionError::Io` if the data file cannot be read, or any
+    /// decompression error.
+    pub fn read(&mut self, id: usize) -> Result<Vec<u8>> {
+        let block = *self
+            .blocks
+            .get(id)
+            .ok_or_else(|| CompressionError::InvalidInput(format!("no block {id}")))?;
+        let payload = self.read_payload(&block)?;
+        self.clock += 1;
+        self.blocks[id].last_access = self.clock;
+        match block.tier {
+            Tier::Hot => self.hot.decompress(&payload),
+            Tier::Cold => self.cold.decompress(&payload),
+        }
+    }
+
+    /// Pins block `id` to `tier` regardless of reads: `Tier::Hot` keeps it
+    /// from migrating, `Tier::Cold` migrates it at the next opportunity.
+    /// `None` clears the hint. Unknown ids are ignored.
+    pub fn hint(&mut self, id: usize, tier: Option<Tier>) {
+        if let Some(block) = self.blocks.get_mut(id) {
+            block.hint = tier;
+        }
+    }
+
+    /// Ids of hot blocks due for migration, coldest first.
+    #[must_use]
+    pub fn cold_blocks(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = (0..self.blocks.len())
+            .filter(|&id| {
+                let block = &self.blocks[id];
+                block.tier == Tier::Hot
+                    && block.hint.map_or_else(
+                        || self.clock - block.last_access >= self.cold_after,
+                        |tier| tier == Tier::Cold,
+                    )
+            })
+            .collect();
+        ids.sort_by_key(|&id| {
+            let block = &self.blocks[id];
+            (block.hint != Some(Tier::Cold), block.last_access)
+        });
+        ids
+    }
+
+    /// Reads up to `max_blocks` of the [`cold_blocks`](Self::cold_blocks)
+    /// for migration.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from reading or decompressing a block.
+    pub fn plan_migration(&self, max_blocks: usize) -> Result<Migration<C>> {
+        let mut blocks = Vec::new();
+        for id in self.cold_blocks().into_iter().take(max_blocks) {
+            let payload = self.read_payload(&self.blocks[id])?;
+            blocks.push((id, self.hot.decompress(&payload)?));
+        }
+        Ok(Migration {
+            cold: self.cold.clone(),
+            blocks,
+        })
+    }
+
+    /// Moves the recompressed blocks into the cold tier, returning how many
+    /// moved. Blocks pinned hot or already moved since the migration was
+    /// planned are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the files. The index is untouched
+    /// unless the new payloads were written in full.
+    pub fn commit(&mut self, migrated: MigratedBlocks) -> Result<usize> {
+        let moving: Vec<(usize, Vec<u8>)> = migrated
+            .blocks
+            .into_iter()
+            .filter(|&(id, _)| {
+                self.blocks
+                    .get(id)
+                    .is_some_and(|block| block.tier == Tier::Hot && block.hint != Some(Tier::Hot))
+            })
+            .collect();
+        if moving.is_empty() {
+            return Ok(0);
+        }
+
+        let payloads: Vec<&[u8]> = moving
+            .iter()
+            .map(|(_, payload)| payload.as_slice())
+            .collect();
+        let mut offset = self.write_payloads(&payloads)?;
+        for (id, payload) in &moving {
+            let block = &mut self.blocks[*id];
+            block.tier = Tier::Cold;
+            block.offset = offset;
+            block.len = payload.len() as u64;
+            offset += block.len;
+        }
+        self.write_index()?;
+        Ok(moving.len())
+    }
+
+    /// Plans, runs and commits a migration of up to `max_blocks` blocks on
+    /// the calling thread, returning how many moved.
+    ///
+    /// # Errors
+    ///
+    /// As [`plan_migration`](Self::plan_migration), [`Migration::run`] and
+    /// [`commit`](Self::commit).
+    pub fn migrate(&mut self, max_blocks: usize) -> Result<usize> {
+        let migrated = self.plan_migration(max_blocks)?.run()?;
+        self.commit(migrated)
+    }
+
+    fn read_payload(&self, block: &Block) -> Result<Vec<u8>> {
+        let len = usize::try_from(block.len).map_err(|_| CompressionError::CorruptedData)?;
+        let mut file = File::open(self.dir.join(DATA_FILE))?;
+        file.seek(SeekFrom::Start(block.offset))?;
+        let mut payload = vec![0u8; len];
+        file.read_exact(&mut payload)?;
+        Ok(payload)
+    }
+
+    /// Appends `payloads` to the data file and syncs it, returning the
+    /// offset of the first.
+    fn write_payloads(&mut self, payloads: &[&[u8]]) -> Result<u64> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(DATA_FILE))?;
+        let offset = self.data_len;
+        for payload in payloads {
+            file.write_all(payload)?;
+            self.data_len += payload.len() as u64;
+        }
+        file.sync_all()?;
+        Ok(offset)
+    }
+
+    /// Replaces the index by writing a temporary file and renaming it over
+    /// the old one.
+    fn write_index(&self) -> Result<()> {
+        let mut index = Vec::with_capacity(self.blocks.len() * 6 + 4);
+        varint::write_u64(&mut index, self.blocks.len() as u64);
+        for block in &self.blocks {
+            index.push(block.tier.tag());
+            varint::write_u64(&mut index, block.offset);
+            varint::write_u64(&mut index, block.len);
+        }
+        let temp = self.dir.join(INDEX_TEMP_FILE);
+        let mut file = File::create(&temp)?;
+        file.write_all(&index)?;
+        file.sync_all()?;
+        fs::rename(temp, self.dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+}
+
+fn decode_index(index: &[u8], data_len: u64) -> Result<Vec<Block>> {
+    let mut pos = 0;
+    let count = varint::read_u64(index, &mut pos)?;
+    let mut blocks = Vec::new();
+    for _ in 0..count {
+        let &tag = index.get(pos).ok_or(CompressionError::CorruptedData)?;
+        pos += 1;
+        let tier = Tier::from_tag(tag).ok_or(CompressionError::CorruptedData)?;
+        let offset = varint::read_u64(index, &mut pos)?;
+        let len = varint::read_u64(index, &mut pos)?;
+        if offset.checked_add(len).is_none_or(|end| end > data_len) {
+            return Err(CompressionError::CorruptedData);
+        }
+        blocks.push(Block {
+            tier,
+            offset,
+            len,
+            last_access: 0,
+            hint: None,
+        });
+    }
+    if pos != index.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write as _;
+
+    use super::*;
+    use crate::{Lz4, Lzma};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "compression_lib_tiering_{}_{name}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn block(i: usize) -> Vec<u8> {
+        let mut text = String::new();
+        for reading in i * 100..i * 100 + 100 {
+            let _ = writeln!(
+                text,
+                "sensor {i} t={}.{} h={} p={}",
+                15 + reading % 11,
+                reading * 7 % 10,
+                30 + reading * 13 % 40,
+                990 + reading * 29 % 50
+            );
+        }
+        text.into_bytes()
+    }
+
+    fn open(dir: &TempDir) -> TieredStore<Lz4, Lzma> {
+        TieredStore::open(&dir.0, Lz4::new(), Lzma::with_dict_bits(16)).unwrap()
+    }
+
+    #[test]
+    fn test_open_empty() {
+        let dir = TempDir::new("open_empty");
+        let mut store = open(&dir);
+        assert!(store.is_empty());
+        assert_eq!(store.cold_after(), DEFAULT_COLD_AFTER);
+        assert_eq!(store.tier(0), None);
+        assert!(matches!(
+            store.read(0),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(store.cold_blocks().is_empty());
+    }
+
+    #[test]
+    fn test_append_read_reopen() {
+        let dir = TempDir::new("reopen");
+        {
+            let mut store = open(&dir);
+            for i in 0..5 {
+                assert_eq!(store.append(&block(i)).unwrap(), i);
+            }
+            assert_eq!(store.read(3).unwrap(), block(3));
+        }
+        let mut store = open(&dir);
+        assert_eq!(store.len(), 5);
+        for i in 0..5 {
+            assert_eq!(store.tier(i), Some(Tier::Hot));
+            assert_eq!(store.read(i).unwrap(), block(i));
+        }
+    }
+
+    #[test]
+    fn test_unread_blocks_migrate() {
+        let dir = TempDir::new("migrate");
+        let mut store = open(&dir).with_cold_after(3);
+        for i in 0..4 {
+            store.append(&block(i)).unwrap();
+        }
+        // Block 0 is read all along; the others go cold after three reads.
+        for _ in 0..3 {
+            store.read(0).unwrap();
+        }
+        assert_eq!(store.cold_blocks(), [1, 2, 3]);
+        let hot_size = fs::metadata(dir.0.join(DATA_FILE)).unwrap().len();
+
+        assert_eq!(store.migrate(2).unwrap(), 2);
+        assert_eq!(store.tier(1), Some(Tier::Cold));
+        assert_eq!(store.tier(3), Some(Tier::Hot));
+        assert_eq!(store.migrate(10).unwrap(), 1);
+        assert_eq!(store.migrate(10).unwrap(), 0);
+        assert_eq!(store.tier(0), Some(Tier::Hot));
+
+        // Cold payloads are smaller than the hot ones they replace.
+        let grown = fs::metadata(dir.0.join(DATA_FILE)).unwrap().len() - hot_size;
+        assert!(grown * 2 < hot_size, "{grown} vs {hot_size}");
+
+        let mut store = open(&dir);
+        for i in 0..4 {
+            assert_eq!(store.read(i).unwrap(), block(i));
+        }
+        assert_eq!(store.tier(2), Some(Tier::Cold));
+    }
+
+    #[test]
+    fn test_hints_override_reads() {
+        let dir = TempDir::new("hints");
+        let mut store = open(&dir).with_cold_after(1);
+        for i in 0..3 {
+            store.append(&block(i)).unwrap();
+        }
+        store.read(2).unwrap();
+        store.hint(0, Some(Tier::Hot));
+        store.hint(2, Some(Tier::Cold));
+        store.hint(9, Some(Tier::Cold));
+        // The hinted block comes first even though it was read last.
+        assert_eq!(store.cold_blocks(), [2, 1]);
+
+        store.hint(1, None);
+        store.hint(0, None);
+        assert_eq!(store.cold_blocks(), [2, 0, 1]);
+    }
+
+    #[test]
+    fn test_background_migration() {
+        let dir = TempDir::new("background");
+        let mut store = open(&dir).with_cold_after(0);
+        for i in 0..4 {
+            store.append(&block(i)).unwrap();
+        }
+        let migration = store.plan_migration(4).unwrap();
+        assert_eq!(migration.blocks().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        let worker = std::thread::spawn(move || migration.run());
+
+        // The store keeps serving reads meanwhile, and a block pinned hot
+        // before the commit stays where it is.
+        assert_eq!(store.read(1).unwrap(), block(1));
+        store.hint(2, Some(Tier::Hot));
+        let migrated = worker.join().unwrap().unwrap();
+        assert_eq!(store.commit(migrated.clone()).unwrap(), 3);
+        assert_eq!(store.tier(2), Some(Tier::Hot));
+        // Committing the same result again finds nothing left to move.
+        assert_eq!(store.commit(migrated).unwrap(), 0);
+        for i in 0..4 {
+            assert_eq!(store.read(i).unwrap(), block(i));
+        }
+    }
+
+    #[test]
+    fn test_interrupted_commit_keeps_old_index() {
+        let dir = TempDir::new("interrupted");
+        {
+            let mut store = open(&dir).with_cold_after(0);
+            store.append(&block(0)).unwrap();
+            store.migrate(1).unwrap();
+            store.append(&block(1)).unwrap();
+        }
+        // A crash after writing payloads but before the rename leaves extra
+        // data and a stray temporary index; both are ignored.
+        let mut data = OpenOptions::new()
+            .append(true)
+            .open(dir.0.join(DATA_FILE))
+            .unwrap();
+        data.write_all(b"partial payload").unwrap();
+        fs::write(dir.0.join(INDEX_TEMP_FILE), b"garbage").unwrap();
+
+        let mut store = open(&dir);
+        assert_eq!(store.tier(0), Some(Tier::Cold));
+        assert_eq!(store.read(0).unwrap(), block(0));
+        assert_eq!(store.read(1).unwrap(), block(1));
+        store.append(&block(2)).unwrap();
+        assert_eq!(open(&dir).read(2).unwrap(), block(2));
+    }
+
+    #[test]
+    fn test_corrupted_index() {
+        let dir = TempDir::new("corrupted");
+        {
+            let mut store = open(&dir);
+            store.append(&block(0)).unwrap();
+        }
+        let index = fs::read(dir.0.join(INDEX_FILE)).unwrap();
+        fs::write(dir.0.join(INDEX_FILE), &index[..index.len() - 1]).unwrap();
+        assert!(matches!(
+            TieredStore::open(&dir.0, Lz4::new(), Lzma::new()),
+            Err(CompressionError::CorruptedData)
+        ));
+
+        // A block reaching past the end of the data file.
+        fs::write(dir.0.join(INDEX_FILE), [1, TIER_HOT, 0, 0x80, 0x80, 0x04]).unwrap();
+        assert!(matches!(
+            TieredStore::open(&dir.0, Lz4::new(), Lzma::new()),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+}
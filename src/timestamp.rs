@@ -0,0 +1,230 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::varint::{zigzag_decode, zigzag_encode};
+
+pub const HEADER_LEN: usize = 4;
+
+/// Value widths for each prefix `0`, `10`, `110`, `1110`, `1111`, after
+/// zig-zag mapping the delta-of-delta.
+const BUCKETS: [(u32, u32); 5] = [(1, 0), (2, 7), (3, 9), (4, 12), (4, 64)];
+
+/// Delta-of-delta coder for timestamp sequences, in the style of Gorilla.
+///
+/// Regularly spaced timestamps have a constant delta, so the difference
+/// between consecutive deltas is almost always zero and costs one bit. Small
+/// jitter costs 9 to 16 bits; anything else is stored in full. Arithmetic
+/// wraps, so non-monotonic input still round-trips, just less compactly.
+/// Output layout: `[count: u32][first: u64][bits]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeltaOfDelta;
+
+impl DeltaOfDelta {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Encodes `timestamps` into a self-describing byte buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if there are more than
+    /// `u32::MAX` timestamps.
+    pub fn encode(&self, timestamps: &[u64]) -> Result<Vec<u8>> {
+        let count = u32::try_from(timestamps.len())
+            .map_err(|_| CompressionError::InvalidInput("too many timestamps".to_string()))?;
+
+        let mut output = Vec::with_capacity(HEADER_LEN + 8 + timestamps.len() / 8);
+        output.extend_from_slice(&count.to_le_bytes());
+        let Some((&first, rest)) = timestamps.split_first() else {
+            return Ok(output);
+        };
+        output.extend_from_slice(&first.to_le_bytes());
+
+        let mut writer = BitWriter::new();
+        let mut previous = first;
+        let mut previous_delta = 0u64;
+        for &timestamp in rest {
+            let delta = timestamp.wrapping_sub(previous);
+            let value = zigzag_encode(delta.wrapping_sub(previous_delta).cast_signed());
+            write_bucketed(&mut writer, value);
+            previous = timestamp;
+            previous_delta = delta;
+        }
+        output.extend_from_slice(&writer.finish());
+
+        Ok(output)
+    }
+
+    /// Decodes a buffer produced by [`DeltaOfDelta::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` if the buffer is truncated
+    /// or has trailing bytes.
+    pub fn decode(&self, input: &[u8]) -> Result<Vec<u64>> {
+        let header = input
+            .get(..HEADER_LEN)
+            .ok_or(CompressionError::CorruptedData)?;
+        let count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if count == 0 {
+            return if input.len() == HEADER_LEN {
+                Ok(Vec::new())
+            } else {
+                Err(CompressionError::CorruptedData)
+            };
+        }
+
+        let first = input
+            .get(HEADER_LEN..HEADER_LEN + 8)
+            .and_then(|b| b.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(CompressionError::CorruptedData)?;
+        let payload = &input[HEADER_LEN + 8..];
+        let mut reader = BitReader::new(payload);
+
+        let mut timestamps = Vec::with_capacity(count.min(payload.len() * 8 + 1));
+        timestamps.push(first);
+        let mut previous = first;
+        let mut previous_delta = 0u64;
+        for _ in 1..count {
+            let value = read_bucketed(&mut reader)?;
+            let delta = previous_delta.wrapping_add(zigzag_decode(value).cast_unsigned());
+            previous = previous.wrapping_add(delta);
+            timestamps.push(previous);
+            previous_delta = delta;
+        }
+
+        if reader.bytes_consumed() != payload.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(timestamps)
+    }
+}
+
+fn write_bucketed(writer: &mut BitWriter, value: u64) {
+    for (i, &(prefix_len, width)) in BUCKETS.iter().enumerate() {
+        let last = i == BUCKETS.len() - 1;
+        if last || value < 1u64 << width {
+            // `i` ones, then a terminating zero unless this is the last bucket.
+            let ones = u32::try_from(i).unwrap_or(0);
+            writer.write_bits(u64::MAX, ones);
+            if ones < prefix_len {
+                writer.write_bit(false);
+            }
+            writer.write_bits(value, width);
+            return;
+        }
+    }
+}
+
+fn read_bucketed(reader: &mut BitReader) -> Result<u64> {
+    let mut bucket = 0;
+    while bucket < BUCKETS.len() - 1 && reader.read_bit().ok_or(CompressionError::CorruptedData)? {
+        bucket += 1;
+    }
+    let (_, width) = BUCKETS[bucket];
+    reader
+        .read_bits(width)
+        .ok_or(CompressionError::CorruptedData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(timestamps: &[u64]) -> Vec<u8> {
+        let codec = DeltaOfDelta::new();
+        let encoded = codec.encode(timestamps).unwrap();
+        assert_eq!(codec.decode(&encoded).unwrap(), timestamps);
+        encoded
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]).len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn test_single() {
+        assert_eq!(roundtrip(&[1_700_000_000]).len(), HEADER_LEN + 8);
+    }
+
+    #[test]
+    fn test_regular_interval_is_about_one_bit() {
+        let timestamps: Vec<u64> = (0..1000).map(|i| 1_700_000_000_000 + i * 60_000).collect();
+        let encoded = roundtrip(&timestamps);
+        // The first delta is large; every later one costs a single bit.
+        assert!(encoded.len() < HEADER_LEN + 8 + 10 + 1000 / 8 + 1);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let timestamps: Vec<u64> = (0..500)
+            .map(|i| 1_000_000 + i * 1000 + (i * 7919) % 50)
+            .collect();
+        let encoded = roundtrip(&timestamps);
+        assert!(encoded.len() < timestamps.len() * 2);
+    }
+
+    #[test]
+    fn test_bucket_boundaries() {
+        // Deltas-of-delta on either side of each bucket's zig-zag limit.
+        let mut timestamps = vec![1_000_000u64, 1_000_000];
+        for dod in [
+            0i64,
+            63,
+            -64,
+            64,
+            -65,
+            255,
+            -256,
+            256,
+            2047,
+            -2048,
+            2048,
+            1 << 40,
+        ] {
+            let delta = timestamps[timestamps.len() - 1]
+                .wrapping_sub(timestamps[timestamps.len() - 2])
+                .cast_signed()
+                + dod;
+            let next = timestamps[timestamps.len() - 1].wrapping_add(delta.cast_unsigned());
+            timestamps.push(next);
+        }
+        roundtrip(&timestamps);
+    }
+
+    #[test]
+    fn test_non_monotonic_and_extremes() {
+        roundtrip(&[u64::MAX, 0, u64::MAX, 5, 3, 3, 1 << 63]);
+    }
+
+    #[test]
+    fn test_known_bits() {
+        // Deltas 10, 10, 11: dod 10 -> "10" + 0010100, 0 -> "0", 1 -> "10" + 0000010.
+        let encoded = DeltaOfDelta::new().encode(&[100, 110, 120, 131]).unwrap();
+        assert_eq!(
+            &encoded[HEADER_LEN + 8..],
+            &[0b1000_1010, 0b0010_0000, 0b0100_0000]
+        );
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let timestamps: Vec<u64> = (0..50).map(|i| i * i).collect();
+        let encoded = DeltaOfDelta::new().encode(&timestamps).unwrap();
+        let codec = DeltaOfDelta::new();
+        assert!(codec.decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(codec.decode(&encoded[..HEADER_LEN + 3]).is_err());
+        assert!(codec.decode(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_decode_trailing_bytes() {
+        let mut encoded = DeltaOfDelta::new().encode(&[1, 2, 3]).unwrap();
+        encoded.push(0);
+        assert!(DeltaOfDelta::new().decode(&encoded).is_err());
+        assert!(DeltaOfDelta::new().decode(&[0, 0, 0, 0, 9]).is_err());
+    }
+}
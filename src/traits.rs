@@ -1,19 +1,112 @@
-use crate::error::Result;
+use std::time::Instant;
+
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::limits::DecompressionLimits;
+use crate::stats::CompressionStats;
 
 /// Trait for compression algorithms.
 pub trait Compressor {
     /// Compresses the input bytes and returns the compressed data.
     ///
+    /// Empty input still produces a non-empty frame, so an empty buffer is
+    /// never valid compressed data.
+    ///
     /// # Errors
     ///
     /// Returns `CompressionError` if compression fails due to invalid input
     /// or other algorithm-specific issues.
     fn compress(&self, input: &[u8]) -> Result<Vec<u8>>;
 
+    /// Compresses `input` as the continuation of `history`, letting
+    /// back-references reach into the preceding data.
+    ///
+    /// The frame decodes only with [`Decompressor::decompress_with_history`]
+    /// and the same history. The default supports only an empty history.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the format cannot carry
+    /// history, or any error from compression.
+    fn compress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        if history.is_empty() {
+            self.compress(input)
+        } else {
+            Err(CompressionError::InvalidInput(format!(
+                "{} cannot carry history between frames",
+                self.name()
+            )))
+        }
+    }
+
+    /// Compresses `input` into `output`, returning the number of bytes
+    /// written.
+    ///
+    /// The default compresses into a new `Vec` and copies it over, so it
+    /// allocates as much as [`Compressor::compress`]; only codecs that
+    /// override it write in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the frame does not fit
+    /// in `output`, or any error from compression. Either way `output` may
+    /// have been partly written.
+    fn compress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        copy_into(&self.compress(input)?, output)
+    }
+
+    /// Compresses `input` and reports the sizes, the time taken and, for
+    /// codecs that track them, token and symbol counts.
+    ///
+    /// The output is the same as [`Compressor::compress`]'s. The default
+    /// times `compress` and leaves the counts `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from compression.
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        let start = Instant::now();
+        let output = self.compress(input)?;
+        let stats = CompressionStats::new(input.len() as u64, output.len() as u64, start.elapsed());
+        Ok((output, stats))
+    }
+
+    /// Upper bound on the length of a frame compressed from `input_len`
+    /// bytes, for sizing the buffer given to [`Compressor::compress_into`].
+    ///
+    /// The default is `usize::MAX`, meaning no bound is known; codecs with
+    /// a computable worst case override it.
+    fn max_compressed_len(&self, _input_len: usize) -> usize {
+        usize::MAX
+    }
+
+    /// Reconfigures the codec to trade speed for ratio at `level`, keeping
+    /// any setting the level does not cover.
+    ///
+    /// The default returns the codec unchanged; codecs with tuning knobs
+    /// override it, and [`CompressionLevel::DEFAULT`] always gives the
+    /// configuration of `new()`.
+    #[must_use]
+    fn with_level(self, _level: CompressionLevel) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
     /// Returns the name of this compression algorithm.
     fn name(&self) -> &'static str;
 }
 
+/// Copies `bytes` to the start of `output`, returning their length.
+fn copy_into(bytes: &[u8], output: &mut [u8]) -> Result<usize> {
+    output
+        .get_mut(..bytes.len())
+        .ok_or(CompressionError::BufferTooSmall)?
+        .copy_from_slice(bytes);
+    Ok(bytes.len())
+}
+
 /// Trait for decompression algorithms.
 pub trait Decompressor {
     /// Decompresses the input bytes and returns the original data.
@@ -24,19 +117,296 @@ pub trait Decompressor {
     /// data, invalid format, or other algorithm-specific issues.
     fn decompress(&self, input: &[u8]) -> Result<Vec<u8>>;
 
+    /// Decompresses a frame produced by [`Compressor::compress_with_history`]
+    /// with the same `history`. The default supports only an empty history.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the format cannot carry
+    /// history, or any error from decompression.
+    fn decompress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        if history.is_empty() {
+            self.decompress(input)
+        } else {
+            Err(CompressionError::InvalidInput(format!(
+                "{} cannot carry history between frames",
+                self.name()
+            )))
+        }
+    }
+
+    /// Decompresses `input` into `output`, returning the length of the
+    /// original data.
+    ///
+    /// The default decompresses into a new `Vec` and copies it over, so it
+    /// allocates as much as [`Decompressor::decompress`]; only codecs that
+    /// override it write in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the data does not fit
+    /// in `output`, or any error from decompression. Either way `output`
+    /// may have been partly written.
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        copy_into(&self.decompress(input)?, output)
+    }
+
+    /// Length of the data `input` decompresses to, read from its header
+    /// alone, for sizing the buffer given to
+    /// [`Decompressor::decompress_into`] or refusing oversized payloads
+    /// before decoding them.
+    ///
+    /// The default is `None`, meaning the format does not record the length
+    /// up front. The header is taken at its word: a frame that is corrupt
+    /// past it still reports the length it claims.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::CorruptedData` or
+    /// `CompressionError::InvalidHeader` if the header is truncated or
+    /// malformed.
+    fn decompressed_len(&self, _input: &[u8]) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Decompresses `input` from an untrusted source, refusing output that
+    /// breaks `limits`.
+    ///
+    /// A length recorded in the header is checked before decoding starts,
+    /// so a bomb never gets to allocate its output; formats without one
+    /// are decoded first and checked after, unless they override this to
+    /// stop as soon as the output passes the limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::LimitExceeded` if the output would be too
+    /// large, or any error from decompression.
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        if let Some(len) = self.decompressed_len(input)? {
+            limits.check(input.len() as u64, len)?;
+        }
+        let output = self.decompress(input)?;
+        limits.check(input.len() as u64, output.len() as u64)?;
+        Ok(output)
+    }
+
     /// Returns the name of this decompression algorithm.
     fn name(&self) -> &'static str;
 }
 
+/// Decompression of self-delimiting frames.
+///
+/// Implemented by formats whose frames record enough to find their own end,
+/// so that a framing layer can decode back-to-back frames from one buffer
+/// without storing their lengths separately.
+pub trait PartialDecompressor: Decompressor {
+    /// Decodes the frame at the start of `input`, returning its data and the
+    /// exact number of input bytes it occupied. Any bytes after the frame
+    /// are left unread.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the frame is truncated or corrupted.
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)>;
+
+    /// Decodes a frame that must fill `input` exactly.
+    ///
+    /// Bytes left after a complete frame are reported as
+    /// `CompressionError::TrailingData` rather than as corruption, so a
+    /// protocol can tell a framing bug from damaged data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::TrailingData` with the number of unread
+    /// bytes, or the first error from
+    /// [`PartialDecompressor::decompress_partial`].
+    fn decompress_strict(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed < input.len() {
+            return Err(CompressionError::TrailingData(input.len() - consumed));
+        }
+        Ok(output)
+    }
+
+    /// Decodes the frame at the start of `input` and returns its data along
+    /// with whatever follows it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from
+    /// [`PartialDecompressor::decompress_partial`].
+    fn decompress_lenient<'a>(&self, input: &'a [u8]) -> Result<(Vec<u8>, &'a [u8])> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        let trailing = input
+            .get(consumed..)
+            .ok_or(CompressionError::CorruptedData)?;
+        Ok((output, trailing))
+    }
+
+    /// Decodes consecutive frames until `input` is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from [`PartialDecompressor::decompress_partial`].
+    fn decompress_frames(&self, mut input: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut frames = Vec::new();
+        while !input.is_empty() {
+            let (frame, consumed) = self.decompress_partial(input)?;
+            if consumed == 0 {
+                return Err(CompressionError::CorruptedData);
+            }
+            frames.push(frame);
+            input = &input[consumed..];
+        }
+        Ok(frames)
+    }
+
+    /// Decodes consecutive frames until `input` is exhausted and returns
+    /// their data joined, as gzip does for a file of several members. Suits
+    /// logs that are appended to one frame at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from [`PartialDecompressor::decompress_partial`].
+    fn decompress_concatenated(&self, mut input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        while !input.is_empty() {
+            let (frame, consumed) = self.decompress_partial(input)?;
+            if consumed == 0 {
+                return Err(CompressionError::CorruptedData);
+            }
+            if output.is_empty() {
+                output = frame;
+            } else {
+                output.extend_from_slice(&frame);
+            }
+            input = &input[consumed..];
+        }
+        Ok(output)
+    }
+}
+
 /// Trait combining both compression and decompression capabilities.
 pub trait Codec: Compressor + Decompressor {}
 
 impl<T: Compressor + Decompressor> Codec for T {}
 
+/// A reversible rearrangement of bytes, such as a delta or shuffle filter,
+/// with no framing of its own.
+///
+/// Unlike a [`Compressor`], a transform records none of its settings in the
+/// output: [`Transform::inverse`] relies on being called with the same
+/// settings that produced it. That leaves framing to whatever carries the
+/// output, so chained stages do not each pay for a header.
+pub trait Transform {
+    /// Applies the transform.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the transform's settings
+    /// cannot be applied to `input`.
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>>;
+
+    /// Undoes [`Transform::forward`] with the same settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the settings are
+    /// unusable, or `CompressionError::CorruptedData` if `input` cannot be
+    /// the output of `forward`.
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>>;
+
+    /// Returns the transform's name.
+    fn name(&self) -> &'static str;
+}
+
+/// Boxed codecs, such as those from
+/// [`registry::codec_by_name`](crate::registry::codec_by_name), compress
+/// like the codec inside, so they fit anywhere a [`Codec`] is taken. A level
+/// cannot reach a codec behind `dyn`, so `with_level` leaves it unchanged.
+impl<T: Compressor + ?Sized> Compressor for Box<T> {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        (**self).compress(input)
+    }
+
+    fn compress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        (**self).compress_with_history(history, input)
+    }
+
+    fn compress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        (**self).compress_into(input, output)
+    }
+
+    fn compress_with_stats(&self, input: &[u8]) -> Result<(Vec<u8>, CompressionStats)> {
+        (**self).compress_with_stats(input)
+    }
+
+    fn max_compressed_len(&self, input_len: usize) -> usize {
+        (**self).max_compressed_len(input_len)
+    }
+
+    fn name(&self) -> &'static str {
+        Compressor::name(&**self)
+    }
+}
+
+impl<T: Decompressor + ?Sized> Decompressor for Box<T> {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        (**self).decompress(input)
+    }
+
+    fn decompress_with_history(&self, history: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        (**self).decompress_with_history(history, input)
+    }
+
+    fn decompress_into(&self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        (**self).decompress_into(input, output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        (**self).decompressed_len(input)
+    }
+
+    fn decompress_with_limits(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Vec<u8>> {
+        (**self).decompress_with_limits(input, limits)
+    }
+
+    fn name(&self) -> &'static str {
+        Decompressor::name(&**self)
+    }
+}
+
+impl<T: PartialDecompressor + ?Sized> PartialDecompressor for Box<T> {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        (**self).decompress_partial(input)
+    }
+}
+
+impl<T: Transform + ?Sized> Transform for Box<T> {
+    fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+        (**self).forward(input)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+        (**self).inverse(input)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::CompressionError;
 
     struct MockCodec;
 
@@ -113,6 +483,62 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_history_unsupported_by_default() {
+        let codec = MockCodec;
+        assert_eq!(codec.compress_with_history(&[], b"ab").unwrap(), b"ab");
+        assert_eq!(codec.decompress_with_history(&[], b"ab").unwrap(), b"ab");
+        assert!(matches!(
+            codec.compress_with_history(b"x", b"ab"),
+            Err(CompressionError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            codec.decompress_with_history(b"x", b"ab"),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_into_defaults_copy() {
+        let codec = MockCodec;
+        let mut buffer = [0u8; 8];
+        assert_eq!(codec.compress_into(b"abc", &mut buffer).unwrap(), 3);
+        assert_eq!(&buffer[..3], b"abc");
+        assert_eq!(codec.decompress_into(b"xyzw", &mut buffer).unwrap(), 4);
+        assert_eq!(&buffer[..4], b"xyzw");
+        assert!(matches!(
+            codec.compress_into(b"too long to fit", &mut buffer),
+            Err(CompressionError::BufferTooSmall)
+        ));
+        assert!(matches!(
+            codec.decompress_into(b"too long to fit", &mut buffer),
+            Err(CompressionError::BufferTooSmall)
+        ));
+        assert!(matches!(
+            codec.compress_into(&[], &mut buffer),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_compress_with_stats_default() {
+        let (output, stats) = MockCodec.compress_with_stats(b"abcd").unwrap();
+        assert_eq!(output, MockCodec.compress(b"abcd").unwrap());
+        assert_eq!(
+            (stats.input_len, stats.output_len),
+            (4, output.len() as u64)
+        );
+        assert_eq!(stats.tokens, None);
+        let boxed: Box<dyn Compressor> = Box::new(MockCodec);
+        assert_eq!(boxed.compress_with_stats(b"abcd").unwrap().0, output);
+    }
+
+    #[test]
+    fn test_decompressed_len_unknown_by_default() {
+        assert_eq!(MockCodec.decompressed_len(b"abc").unwrap(), None);
+        assert_eq!(MockCodec.decompressed_len(&[]).unwrap(), None);
+    }
+
     fn accepts_codec<T: Codec>(codec: &T, data: &[u8]) -> Result<Vec<u8>> {
         let compressed = codec.compress(data)?;
         codec.decompress(&compressed)
@@ -125,4 +551,118 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), b"test");
     }
+
+    /// Reverses the input; its own inverse.
+    struct Reverse;
+
+    impl Transform for Reverse {
+        fn forward(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(input.iter().rev().copied().collect())
+        }
+
+        fn inverse(&self, input: &[u8]) -> Result<Vec<u8>> {
+            self.forward(input)
+        }
+
+        fn name(&self) -> &'static str {
+            "Reverse"
+        }
+    }
+
+    #[test]
+    fn test_transform_through_box() {
+        let transform: Box<dyn Transform> = Box::new(Reverse);
+        let forward = transform.forward(b"abc").unwrap();
+        assert_eq!(forward, b"cba");
+        assert_eq!(transform.inverse(&forward).unwrap(), b"abc");
+        assert_eq!(transform.name(), "Reverse");
+    }
+
+    /// Frames of `[len: u8][data]`, so a zero byte is an empty frame that
+    /// consumes nothing when `stall` is set.
+    struct LengthPrefixed {
+        stall: bool,
+    }
+
+    impl Decompressor for LengthPrefixed {
+        fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.decompress_partial(input)?.0)
+        }
+
+        fn name(&self) -> &'static str {
+            "LengthPrefixed"
+        }
+    }
+
+    impl PartialDecompressor for LengthPrefixed {
+        fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+            let len = usize::from(input[0]);
+            let data = input.get(1..=len).ok_or(CompressionError::CorruptedData)?;
+            let consumed = if self.stall && len == 0 { 0 } else { len + 1 };
+            Ok((data.to_vec(), consumed))
+        }
+    }
+
+    #[test]
+    fn test_decompress_frames() {
+        let codec = LengthPrefixed { stall: false };
+        let frames = codec
+            .decompress_frames(&[2, b'a', b'b', 0, 1, b'c'])
+            .unwrap();
+        assert_eq!(frames, [b"ab".to_vec(), Vec::new(), b"c".to_vec()]);
+        assert!(codec.decompress_frames(&[]).unwrap().is_empty());
+        assert!(codec.decompress_frames(&[2, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_decompress_strict() {
+        let codec = LengthPrefixed { stall: false };
+        assert_eq!(codec.decompress_strict(&[2, b'a', b'b']).unwrap(), b"ab");
+        // The lax `decompress` ignores what follows the frame.
+        assert_eq!(codec.decompress(&[1, b'a', 0, 7]).unwrap(), b"a");
+        assert_eq!(
+            codec.decompress_strict(&[1, b'a', 0, 7]),
+            Err(CompressionError::TrailingData(2))
+        );
+        assert_eq!(
+            codec.decompress_strict(&[2, b'a']),
+            Err(CompressionError::CorruptedData)
+        );
+    }
+
+    #[test]
+    fn test_decompress_lenient() {
+        let codec = LengthPrefixed { stall: false };
+        let (data, trailing) = codec.decompress_lenient(&[1, b'a', 0, 7]).unwrap();
+        assert_eq!((data.as_slice(), trailing), (&b"a"[..], &[0, 7][..]));
+        let (data, trailing) = codec.decompress_lenient(&[2, b'a', b'b']).unwrap();
+        assert_eq!((data.as_slice(), trailing), (&b"ab"[..], &[][..]));
+        assert!(codec.decompress_lenient(&[3, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_decompress_concatenated() {
+        let codec = LengthPrefixed { stall: false };
+        assert_eq!(
+            codec
+                .decompress_concatenated(&[2, b'a', b'b', 0, 1, b'c'])
+                .unwrap(),
+            b"abc"
+        );
+        assert!(codec.decompress_concatenated(&[]).unwrap().is_empty());
+        assert!(codec.decompress_concatenated(&[1, b'a', 2, b'b']).is_err());
+    }
+
+    #[test]
+    fn test_decompress_frames_rejects_zero_consumption() {
+        let codec = LengthPrefixed { stall: true };
+        assert!(matches!(
+            codec.decompress_frames(&[0]),
+            Err(CompressionError::CorruptedData)
+        ));
+        assert!(matches!(
+            codec.decompress_concatenated(&[0]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
 }
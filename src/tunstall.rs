@@ -0,0 +1,494 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+use crate::level::CompressionLevel;
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+
+const DEFAULT_CODEWORD_BITS: u8 = 12;
+pub const MIN_CODEWORD_BITS: u8 = 8;
+pub const MAX_CODEWORD_BITS: u8 = 16;
+/// Codeword width for each [`CompressionLevel`], fastest first; level 6 is
+/// [`Tunstall::new`].
+const LEVELS: [u8; 9] = [8, 9, 10, 10, 11, DEFAULT_CODEWORD_BITS, 13, 14, 16];
+const MAX_WORD_LENGTH: usize = 255;
+/// Bytes of the `[codeword bits][original length][symbols - 1]` header.
+pub const HEADER_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    probability: f64,
+    depth: usize,
+    parent: Option<usize>,
+    symbol: u8,
+    first_child: Option<usize>,
+}
+
+/// Heap entry ordering leaves by probability, then by lowest node index.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    probability: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A Tunstall parse tree over the symbols present in the input.
+///
+/// Node 0 is the root; the children of an internal node are stored
+/// contiguously in alphabet order starting at `first_child`.
+#[derive(Debug)]
+struct Dictionary {
+    rank: [Option<usize>; 256],
+    nodes: Vec<Node>,
+    codes: Vec<Option<u32>>,
+    words: Vec<usize>,
+}
+
+impl Dictionary {
+    fn build(symbols: &[u8], weights: &[u8], codeword_bits: u8) -> Self {
+        let total: f64 = weights.iter().map(|&w| f64::from(w)).sum();
+        let probabilities: Vec<f64> = weights.iter().map(|&w| f64::from(w) / total).collect();
+        let mut rank = [None; 256];
+        for (i, &symbol) in symbols.iter().enumerate() {
+            rank[usize::from(symbol)] = Some(i);
+        }
+
+        let mut dictionary = Self {
+            rank,
+            nodes: vec![Node {
+                probability: 1.0,
+                depth: 0,
+                parent: None,
+                symbol: 0,
+                first_child: None,
+            }],
+            codes: Vec::new(),
+            words: Vec::new(),
+        };
+
+        let capacity = 1usize << codeword_bits;
+        let mut heap = BinaryHeap::new();
+        let mut leaves = 0;
+        let mut next = Some(0);
+
+        while let Some(index) = next {
+            let first_child = dictionary.nodes.len();
+            let parent = dictionary.nodes[index];
+            for (&symbol, &probability) in symbols.iter().zip(&probabilities) {
+                let child = Node {
+                    probability: parent.probability * probability,
+                    depth: parent.depth + 1,
+                    parent: Some(index),
+                    symbol,
+                    first_child: None,
+                };
+                if child.depth < MAX_WORD_LENGTH {
+                    heap.push(Candidate {
+                        probability: child.probability,
+                        index: dictionary.nodes.len(),
+                    });
+                }
+                dictionary.nodes.push(child);
+            }
+            dictionary.nodes[index].first_child = Some(first_child);
+            leaves = leaves + symbols.len() - usize::from(index != 0);
+
+            // A single-symbol alphabet never gains leaves, so it grows one
+            // word until the length limit empties the heap.
+            next = if symbols.len() == 1 || leaves + symbols.len() - 1 <= capacity {
+                heap.pop().map(|candidate| candidate.index)
+            } else {
+                None
+            };
+        }
+
+        let mut code = 0u32;
+        dictionary.codes = dictionary
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                (i != 0 && node.first_child.is_none()).then(|| {
+                    code += 1;
+                    code - 1
+                })
+            })
+            .collect();
+        dictionary.words = (0..dictionary.nodes.len())
+            .filter(|&i| dictionary.codes[i].is_some())
+            .collect();
+        dictionary
+    }
+
+    fn child(&self, node: usize, symbol: u8) -> Option<usize> {
+        let first = self.nodes[node].first_child?;
+        Some(first + self.rank[usize::from(symbol)]?)
+    }
+
+    /// Any leaf below `node`, used to terminate a parse that ends mid-word.
+    fn any_leaf(&self, mut node: usize) -> usize {
+        while let Some(first) = self.nodes[node].first_child {
+            node = first;
+        }
+        node
+    }
+
+    fn word(&self, mut node: usize) -> Vec<u8> {
+        let mut word = Vec::with_capacity(self.nodes[node].depth);
+        while let Some(parent) = self.nodes[node].parent {
+            word.push(self.nodes[node].symbol);
+            node = parent;
+        }
+        word.reverse();
+        word
+    }
+}
+
+fn quantize_weights(input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut counts = [0usize; 256];
+    for &byte in input {
+        counts[usize::from(byte)] += 1;
+    }
+    let max = counts.iter().copied().max().unwrap_or(1);
+
+    let mut symbols = Vec::new();
+    let mut weights = Vec::new();
+    for (symbol, &count) in (0..=255u8).zip(&counts) {
+        if count > 0 {
+            symbols.push(symbol);
+            let scaled = (count * 255 / max).max(1);
+            weights.push(u8::try_from(scaled).unwrap_or(u8::MAX));
+        }
+    }
+    // Empty input still needs a one-symbol alphabet to form a valid header.
+    if symbols.is_empty() {
+        symbols.push(0);
+        weights.push(1);
+    }
+    (symbols, weights)
+}
+
+/// Variable-to-fixed Tunstall coder.
+///
+/// The input is parsed greedily into dictionary words of varying length,
+/// each emitted as a fixed-width codeword, so decoding is a table lookup
+/// and copy per codeword. Output layout: `[codeword bits: u8][original
+/// length: u32][symbols - 1: u8][symbols][weights]` followed by the packed
+/// codewords; inputs longer than `u32::MAX` bytes are refused.
+#[derive(Debug, Clone, Copy)]
+pub struct Tunstall {
+    codeword_bits: u8,
+}
+
+impl Default for Tunstall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tunstall {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            codeword_bits: DEFAULT_CODEWORD_BITS,
+        }
+    }
+
+    /// Creates a coder emitting `bits`-wide codewords (8 to 16).
+    #[must_use]
+    pub const fn with_codeword_bits(bits: u8) -> Self {
+        Self {
+            codeword_bits: bits,
+        }
+    }
+
+    #[must_use]
+    pub const fn codeword_bits(&self) -> u8 {
+        self.codeword_bits
+    }
+}
+
+impl Compressor for Tunstall {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if !(MIN_CODEWORD_BITS..=MAX_CODEWORD_BITS).contains(&self.codeword_bits) {
+            return Err(CompressionError::InvalidInput(format!(
+                "codeword width must be {MIN_CODEWORD_BITS}-{MAX_CODEWORD_BITS} bits"
+            )));
+        }
+        let original_len = u32::try_from(input.len())
+            .map_err(|_| CompressionError::InvalidInput("input too large".to_string()))?;
+
+        let (symbols, weights) = quantize_weights(input);
+        let dictionary = Dictionary::build(&symbols, &weights, self.codeword_bits);
+        let width = u32::from(self.codeword_bits);
+
+        let mut writer = BitWriter::new();
+        let mut node = 0;
+        for &byte in input {
+            node = dictionary
+                .child(node, byte)
+                .ok_or(CompressionError::CorruptedData)?;
+            if let Some(code) = dictionary.codes[node] {
+                writer.write_bits(u64::from(code), width);
+                node = 0;
+            }
+        }
+        if node != 0 {
+            let leaf = dictionary.any_leaf(node);
+            let code = dictionary.codes[leaf].ok_or(CompressionError::CorruptedData)?;
+            writer.write_bits(u64::from(code), width);
+        }
+
+        let mut output = Vec::with_capacity(HEADER_LEN + symbols.len() * 2 + writer.bit_len() / 8 + 1);
+        output.push(self.codeword_bits);
+        output.extend_from_slice(&original_len.to_le_bytes());
+        output.push(u8::try_from(symbols.len() - 1).unwrap_or(u8::MAX));
+        output.extend_from_slice(&symbols);
+        output.extend_from_slice(&weights);
+        output.extend_from_slice(&writer.finish());
+
+        Ok(output)
+    }
+
+    fn with_level(self, level: CompressionLevel) -> Self {
+        Self::with_codeword_bits(level.pick(&LEVELS))
+    }
+
+    fn name(&self) -> &'static str {
+        "Tunstall"
+    }
+}
+
+impl Decompressor for Tunstall {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        match *input {
+            [codeword_bits, a, b, c, d, ..]
+                if input.len() >= HEADER_LEN
+                    && (MIN_CODEWORD_BITS..=MAX_CODEWORD_BITS).contains(&codeword_bits) =>
+            {
+                Ok(Some(u64::from(u32::from_le_bytes([a, b, c, d]))))
+            }
+            _ => Err(CompressionError::CorruptedData),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Tunstall"
+    }
+}
+
+impl PartialDecompressor for Tunstall {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        if input.len() < HEADER_LEN {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let codeword_bits = input[0];
+        if !(MIN_CODEWORD_BITS..=MAX_CODEWORD_BITS).contains(&codeword_bits) {
+            return Err(CompressionError::CorruptedData);
+        }
+        let original_len = u32::from_le_bytes([input[1], input[2], input[3], input[4]]) as usize;
+        let count = usize::from(input[5]) + 1;
+        let header_len = HEADER_LEN + count * 2;
+        if input.len() < header_len {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let symbols = &input[HEADER_LEN..HEADER_LEN + count];
+        let weights = &input[HEADER_LEN + count..header_len];
+        if symbols.windows(2).any(|pair| pair[0] >= pair[1]) || weights.contains(&0) {
+            return Err(CompressionError::CorruptedData);
+        }
+
+        let dictionary = Dictionary::build(symbols, weights, codeword_bits);
+        let words: Vec<Vec<u8>> = dictionary
+            .words
+            .iter()
+            .map(|&node| dictionary.word(node))
+            .collect();
+
+        let payload = &input[header_len..];
+        let mut reader = BitReader::new(payload);
+        let mut output = Vec::with_capacity(original_len.min(payload.len() * MAX_WORD_LENGTH));
+        while output.len() < original_len {
+            let code = reader
+                .read_bits(u32::from(codeword_bits))
+                .ok_or(CompressionError::CorruptedData)?;
+            let word = usize::try_from(code)
+                .ok()
+                .and_then(|code| words.get(code))
+                .ok_or(CompressionError::CorruptedData)?;
+            let take = word.len().min(original_len - output.len());
+            output.extend_from_slice(&word[..take]);
+        }
+
+        Ok((output, header_len + reader.bytes_consumed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(tunstall: Tunstall, input: &[u8]) -> Vec<u8> {
+        let compressed = tunstall.compress(input).unwrap();
+        let decompressed = tunstall.decompress(&compressed).unwrap();
+        assert_eq!(decompressed, input);
+        compressed
+    }
+
+    #[test]
+    fn test_tunstall_new() {
+        let tunstall = Tunstall::new();
+        assert_eq!(tunstall.codeword_bits(), DEFAULT_CODEWORD_BITS);
+        assert_eq!(Tunstall::default().codeword_bits(), DEFAULT_CODEWORD_BITS);
+    }
+
+    #[test]
+    fn test_compress_empty() {
+        let tunstall = Tunstall::new();
+        let compressed = roundtrip(tunstall, &[]);
+        assert_eq!(compressed, [DEFAULT_CODEWORD_BITS, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(matches!(
+            tunstall.decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_roundtrip_single_byte() {
+        roundtrip(Tunstall::new(), &[0x42]);
+    }
+
+    #[test]
+    fn test_roundtrip_text() {
+        roundtrip(
+            Tunstall::new(),
+            b"the quick brown fox jumps over the lazy dog",
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_all_same() {
+        let compressed = roundtrip(Tunstall::new(), &[0xAA; 1000]);
+        assert!(compressed.len() < 100);
+    }
+
+    #[test]
+    fn test_roundtrip_binary_data() {
+        let input: Vec<u8> = (0..=255).collect();
+        roundtrip(Tunstall::new(), &input);
+        roundtrip(Tunstall::with_codeword_bits(8), &input);
+    }
+
+    #[test]
+    fn test_roundtrip_skewed() {
+        let input: Vec<u8> = (0..5000u32)
+            .map(|i| if i % 10 == 0 { b'b' } else { b'a' })
+            .collect();
+        let compressed = roundtrip(Tunstall::new(), &input);
+        assert!(compressed.len() < input.len() / 2);
+    }
+
+    #[test]
+    fn test_roundtrip_codeword_widths() {
+        let input = b"abracadabra abracadabra abracadabra".repeat(10);
+        for bits in MIN_CODEWORD_BITS..=MAX_CODEWORD_BITS {
+            roundtrip(Tunstall::with_codeword_bits(bits), &input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_codeword_width() {
+        let result = Tunstall::with_codeword_bits(4).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+        let result = Tunstall::with_codeword_bits(17).compress(b"abc");
+        assert!(matches!(result, Err(CompressionError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_dictionary_respects_capacity() {
+        let dictionary = Dictionary::build(b"abc", &[200, 50, 5], 8);
+        assert!(dictionary.words.len() <= 256);
+        assert!(dictionary.words.len() > 250);
+    }
+
+    #[test]
+    fn test_decompress_truncated_header() {
+        let result = Tunstall::new().decompress(&[12, 1, 0]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_decompress_truncated_payload() {
+        let tunstall = Tunstall::new();
+        let mut compressed = tunstall.compress(b"hello hello hello").unwrap();
+        compressed.truncate(compressed.len() - 2);
+        assert!(tunstall.decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_decompress_trailing_bytes() {
+        let tunstall = Tunstall::new();
+        let mut compressed = tunstall.compress(b"hello").unwrap();
+        compressed.extend_from_slice(&[0, 0]);
+        assert!(tunstall.decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_decompress_invalid_width() {
+        let result = Tunstall::new().decompress(&[3, 1, 0, 0, 0, 0, b'a', 1, 0]);
+        assert!(matches!(result, Err(CompressionError::CorruptedData)));
+    }
+
+    #[test]
+    fn test_names() {
+        let tunstall = Tunstall::new();
+        assert_eq!(Compressor::name(&tunstall), "Tunstall");
+        assert_eq!(Decompressor::name(&tunstall), "Tunstall");
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let tunstall = Tunstall::new();
+        let first = tunstall.compress(b"banana bandana").unwrap();
+        let second = tunstall.compress(b"aaaaaaaaab").unwrap();
+        let stream = [first.as_slice(), &second].concat();
+
+        let (data, consumed) = tunstall.decompress_partial(&stream).unwrap();
+        assert_eq!(data, b"banana bandana");
+        assert_eq!(consumed, first.len());
+        let frames = tunstall.decompress_frames(&stream).unwrap();
+        assert_eq!(frames, [b"banana bandana".to_vec(), b"aaaaaaaaab".to_vec()]);
+    }
+}
@@ -0,0 +1,296 @@
+//! LEB128 variable-length integers.
+//!
+//! Each byte carries seven value bits, least significant group first, with
+//! the high bit set on every byte except the last. Signed values are
+//! zig-zag mapped first so that small magnitudes of either sign stay short.
+//! Sequences are stored as `[count: varint][values: varint...]`.
+
+use crate::error::{CompressionError, Result};
+
+/// Longest encoding of a `u64`: ten groups of seven bits.
+pub const MAX_LEN_U64: usize = 10;
+
+/// Maps signed to unsigned so that 0, -1, 1, -2, ... become 0, 1, 2, 3, ...
+#[must_use]
+pub const fn zigzag_encode(value: i64) -> u64 {
+    value.cast_unsigned().wrapping_shl(1) ^ (value >> 63).cast_unsigned()
+}
+
+/// Inverse of [`zigzag_encode`].
+#[must_use]
+pub const fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1).cast_signed() ^ -((value & 1).cast_signed())
+}
+
+/// Appends `value` to `output` as LEB128.
+pub fn write_u64(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value.to_le_bytes()[0] | 0x80);
+        value >>= 7;
+    }
+    output.push(value.to_le_bytes()[0]);
+}
+
+/// Number of bytes [`write_u64`] takes for `value`.
+#[must_use]
+pub const fn len_u64(value: u64) -> usize {
+    let bits = u64::BITS - (value | 1).leading_zeros();
+    bits.div_ceil(7) as usize
+}
+
+/// Writes `value` as LEB128 at the start of `output`, returning the number
+/// of bytes written.
+///
+/// # Errors
+///
+/// Returns `CompressionError::BufferTooSmall` if `output` is too short.
+pub fn write_u64_into(output: &mut [u8], mut value: u64) -> Result<usize> {
+    let mut len = 0;
+    loop {
+        let slot = output
+            .get_mut(len)
+            .ok_or(CompressionError::BufferTooSmall)?;
+        len += 1;
+        if value < 0x80 {
+            *slot = value.to_le_bytes()[0];
+            return Ok(len);
+        }
+        *slot = value.to_le_bytes()[0] | 0x80;
+        value >>= 7;
+    }
+}
+
+/// Reads one LEB128 value starting at `*pos`, advancing `*pos` past it.
+///
+/// # Errors
+///
+/// Returns `CompressionError::CorruptedData` if the input ends mid-value,
+/// the value overflows `u64`, or the encoding is longer than necessary.
+pub fn read_u64(input: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for i in 0..MAX_LEN_U64 {
+        let byte = *input.get(*pos + i).ok_or(CompressionError::CorruptedData)?;
+        let group = u64::from(byte & 0x7F);
+        let shift = 7 * i;
+        if i == MAX_LEN_U64 - 1 && group > 1 {
+            return Err(CompressionError::CorruptedData);
+        }
+        value |= group << shift;
+        if byte & 0x80 == 0 {
+            if i > 0 && byte == 0 {
+                return Err(CompressionError::CorruptedData);
+            }
+            *pos += i + 1;
+            return Ok(value);
+        }
+    }
+    Err(CompressionError::CorruptedData)
+}
+
+/// Reads one LEB128 value that must fit in a `u32`.
+///
+/// # Errors
+///
+/// As [`read_u64`], and also if the value exceeds `u32::MAX`.
+pub fn read_u32(input: &[u8], pos: &mut usize) -> Result<u32> {
+    u32::try_from(read_u64(input, pos)?).map_err(|_| CompressionError::CorruptedData)
+}
+
+fn encode_with(len: usize, values: impl Iterator<Item = u64>) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len + 1);
+    write_u64(&mut output, len as u64);
+    for value in values {
+        write_u64(&mut output, value);
+    }
+    output
+}
+
+fn decode_with<T>(input: &[u8], read: impl Fn(&[u8], &mut usize) -> Result<T>) -> Result<Vec<T>> {
+    let mut pos = 0;
+    let count =
+        usize::try_from(read_u64(input, &mut pos)?).map_err(|_| CompressionError::CorruptedData)?;
+    // Every value takes at least one byte, which bounds the allocation.
+    if count > input.len() - pos {
+        return Err(CompressionError::CorruptedData);
+    }
+
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        values.push(read(input, &mut pos)?);
+    }
+    if pos != input.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(values)
+}
+
+/// Encodes a `u32` sequence.
+#[must_use]
+pub fn encode_u32(values: &[u32]) -> Vec<u8> {
+    encode_with(values.len(), values.iter().map(|&v| u64::from(v)))
+}
+
+/// Decodes a sequence produced by [`encode_u32`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::CorruptedData` if the input is truncated,
+/// has trailing bytes, or holds a value wider than 32 bits.
+pub fn decode_u32(input: &[u8]) -> Result<Vec<u32>> {
+    decode_with(input, read_u32)
+}
+
+/// Encodes a `u64` sequence.
+#[must_use]
+pub fn encode_u64(values: &[u64]) -> Vec<u8> {
+    encode_with(values.len(), values.iter().copied())
+}
+
+/// Decodes a sequence produced by [`encode_u64`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::CorruptedData` if the input is truncated or
+/// has trailing bytes.
+pub fn decode_u64(input: &[u8]) -> Result<Vec<u64>> {
+    decode_with(input, read_u64)
+}
+
+/// Encodes a signed sequence, zig-zag mapping each value.
+#[must_use]
+pub fn encode_i64(values: &[i64]) -> Vec<u8> {
+    encode_with(values.len(), values.iter().map(|&v| zigzag_encode(v)))
+}
+
+/// Decodes a sequence produced by [`encode_i64`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::CorruptedData` if the input is truncated or
+/// has trailing bytes.
+pub fn decode_i64(input: &[u8]) -> Result<Vec<i64>> {
+    decode_with(input, |input, pos| read_u64(input, pos).map(zigzag_decode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(value: u64) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_u64(&mut output, value);
+        output
+    }
+
+    #[test]
+    fn test_known_encodings() {
+        assert_eq!(encoded(0), [0x00]);
+        assert_eq!(encoded(127), [0x7F]);
+        assert_eq!(encoded(128), [0x80, 0x01]);
+        assert_eq!(encoded(300), [0xAC, 0x02]);
+        assert_eq!(encoded(u64::MAX).len(), MAX_LEN_U64);
+    }
+
+    #[test]
+    fn test_read_write_boundaries() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            16_383,
+            16_384,
+            u64::from(u32::MAX),
+            u64::MAX,
+        ] {
+            let bytes = encoded(value);
+            let mut pos = 0;
+            assert_eq!(read_u64(&bytes, &mut pos).unwrap(), value);
+            assert_eq!(pos, bytes.len());
+            assert_eq!(len_u64(value), bytes.len());
+            let mut slice = [0xFF; MAX_LEN_U64];
+            assert_eq!(write_u64_into(&mut slice, value).unwrap(), bytes.len());
+            assert_eq!(slice[..bytes.len()], bytes);
+            assert!(matches!(
+                write_u64_into(&mut slice[..bytes.len() - 1], value),
+                Err(CompressionError::BufferTooSmall)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_zigzag() {
+        assert_eq!(zigzag_encode(0), 0);
+        assert_eq!(zigzag_encode(-1), 1);
+        assert_eq!(zigzag_encode(1), 2);
+        assert_eq!(zigzag_encode(-2), 3);
+        assert_eq!(zigzag_encode(i64::MAX), u64::MAX - 1);
+        assert_eq!(zigzag_encode(i64::MIN), u64::MAX);
+        for value in [0, 1, -1, 12345, -12345, i64::MAX, i64::MIN] {
+            assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_u32() {
+        let values = [0, 1, 200, 70_000, u32::MAX];
+        assert_eq!(decode_u32(&encode_u32(&values)).unwrap(), values);
+    }
+
+    #[test]
+    fn test_roundtrip_u64() {
+        let values = [0, u64::MAX, 1 << 35, 42];
+        assert_eq!(decode_u64(&encode_u64(&values)).unwrap(), values);
+    }
+
+    #[test]
+    fn test_roundtrip_i64() {
+        let values = [0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+        assert_eq!(decode_i64(&encode_i64(&values)).unwrap(), values);
+    }
+
+    #[test]
+    fn test_empty_sequence() {
+        assert_eq!(encode_u32(&[]), [0]);
+        assert!(decode_u32(&[0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_small_values_are_compact() {
+        let values: Vec<u32> = (0..100).collect();
+        assert_eq!(encode_u32(&values).len(), 101);
+    }
+
+    #[test]
+    fn test_decode_u32_rejects_wide_value() {
+        let input = encode_u64(&[u64::from(u32::MAX) + 1]);
+        assert!(matches!(
+            decode_u32(&input),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let mut pos = 0;
+        assert!(read_u64(&[0x80, 0x80], &mut pos).is_err());
+        assert!(decode_u64(&[]).is_err());
+        assert!(decode_u64(&[3, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_read_overflow_and_overlong() {
+        let mut too_wide = vec![0xFF; 9];
+        too_wide.push(0x02);
+        assert!(read_u64(&too_wide, &mut 0).is_err());
+        assert!(read_u64(&[0x80, 0x00], &mut 0).is_err());
+        assert!(read_u64(&[0xFF; 11], &mut 0).is_err());
+    }
+
+    #[test]
+    fn test_decode_trailing_bytes() {
+        let mut input = encode_u32(&[1, 2]);
+        input.push(0);
+        assert!(decode_u32(&input).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::bitstream::{BitReader, BitWriter};
+use crate::error::{CompressionError, Result};
+
+/// Bytes of the `[width: u8][count: u32]` header.
+pub const HEADER_LEN: usize = 5;
+/// Bits of the leading-zero count; longer runs are capped.
+pub const LEADING_BITS: u32 = 5;
+
+/// XOR-based floating-point coder, in the style of Gorilla.
+///
+/// Each value is XOR-ed with its predecessor. Identical values cost one bit;
+/// otherwise only the meaningful bits between the leading and trailing zero
+/// runs are stored, reusing the previous run lengths when they still fit.
+/// Slowly changing metrics typically shrink to a few bits per sample.
+/// Output layout: `[width: u8][count: u32][first value][bits]`, where
+/// `width` is 32 or 64.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XorFloat;
+
+impl XorFloat {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Encodes `values` into a self-describing byte buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if there are more than
+    /// `u32::MAX` values.
+    pub fn encode_f64(&self, values: &[f64]) -> Result<Vec<u8>> {
+        encode_bits(values.iter().map(|v| v.to_bits()), values.len(), u64::BITS)
+    }
+
+    /// Decodes a buffer produced by [`XorFloat::encode_f64`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidHeader` if the buffer holds `f32`
+    /// values, or `CompressionError::CorruptedData` if it is malformed.
+    pub fn decode_f64(&self, input: &[u8]) -> Result<Vec<f64>> {
+        Ok(decode_bits(input, u64::BITS)?
+            .into_iter()
+            .map(f64::from_bits)
+            .collect())
+    }
+
+    /// Encodes `values` into a self-describing byte buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if there are more than
+    /// `u32::MAX` values.
+    pub fn encode_f32(&self, values: &[f32]) -> Result<Vec<u8>> {
+        encode_bits(
+            values.iter().map(|v| u64::from(v.to_bits())),
+            values.len(),
+            u32::BITS,
+        )
+    }
+
+    /// Decodes a buffer produced by [`XorFloat::encode_f32`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidHeader` if the buffer holds `f64`
+    /// values, or `CompressionError::CorruptedData` if it is malformed.
+    pub fn decode_f32(&self, input: &[u8]) -> Result<Vec<f32>> {
+        decode_bits(input, u32::BITS)?
+            .into_iter()
+            .map(|bits| {
+                u32::try_from(bits)
+                    .map(f32::from_bits)
+                    .map_err(|_| CompressionError::CorruptedData)
+            })
+            .collect()
+    }
+}
+
+/// Bits needed for `meaningful length - 1` at the given value width.
+const fn length_bits(width: u32) -> u32 {
+    width.trailing_zeros()
+}
+
+fn encode_bits(values: impl Iterator<Item = u64>, len: usize, width: u32) -> Result<Vec<u8>> {
+    let count = u32::try_from(len)
+        .map_err(|_| CompressionError::InvalidInput("too many values".to_string()))?;
+    let mut output = Vec::with_capacity(HEADER_LEN + len);
+    output.push(u8::try_from(width).unwrap_or(u8::MAX));
+    output.extend_from_slice(&count.to_le_bytes());
+
+    let unused = u64::BITS - width;
+    let mut writer = BitWriter::new();
+    let mut previous: Option<u64> = None;
+    let mut window: Option<(u32, u32)> = None;
+    for value in values {
+        let Some(last) = previous else {
+            writer.write_bits(value, width);
+            previous = Some(value);
+            continue;
+        };
+        previous = Some(value);
+
+        let xor = value ^ last;
+        if xor == 0 {
+            writer.write_bit(false);
+            continue;
+        }
+        writer.write_bit(true);
+
+        let leading = (xor.leading_zeros() - unused).min((1 << LEADING_BITS) - 1);
+        let trailing = xor.trailing_zeros();
+        match window {
+            Some((prev_leading, prev_trailing))
+                if leading >= prev_leading && trailing >= prev_trailing =>
+            {
+                writer.write_bit(false);
+                let meaningful = width - prev_leading - prev_trailing;
+                writer.write_bits(xor >> prev_trailing, meaningful);
+            }
+            _ => {
+                writer.write_bit(true);
+                let meaningful = width - leading - trailing;
+                writer.write_bits(u64::from(leading), LEADING_BITS);
+                writer.write_bits(u64::from(meaningful - 1), length_bits(width));
+                writer.write_bits(xor >> trailing, meaningful);
+                window = Some((leading, trailing));
+            }
+        }
+    }
+    output.extend_from_slice(&writer.finish());
+
+    Ok(output)
+}
+
+fn decode_bits(input: &[u8], width: u32) -> Result<Vec<u64>> {
+    let header = input
+        .get(..HEADER_LEN)
+        .ok_or(CompressionError::CorruptedData)?;
+    if u32::from(header[0]) != width {
+        return Err(CompressionError::InvalidHeader);
+    }
+    let count = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+
+    let payload = &input[HEADER_LEN..];
+    let mut reader = BitReader::new(payload);
+    let mut values = Vec::with_capacity(count.min(payload.len() * 8));
+    let mut window: Option<(u32, u32)> = None;
+    for _ in 0..count {
+        let Some(&last) = values.last() else {
+            values.push(
+                reader
+                    .read_bits(width)
+                    .ok_or(CompressionError::CorruptedData)?,
+            );
+            continue;
+        };
+
+        let read_bit =
+            |reader: &mut BitReader| reader.read_bit().ok_or(CompressionError::CorruptedData);
+        if !read_bit(&mut reader)? {
+            values.push(last);
+            continue;
+        }
+
+        if read_bit(&mut reader)? {
+            let leading = reader
+                .read_bits(LEADING_BITS)
+                .ok_or(CompressionError::CorruptedData)?;
+            let meaningful = reader
+                .read_bits(length_bits(width))
+                .ok_or(CompressionError::CorruptedData)?
+                + 1;
+            let leading = u32::try_from(leading).map_err(|_| CompressionError::CorruptedData)?;
+            let meaningful =
+                u32::try_from(meaningful).map_err(|_| CompressionError::CorruptedData)?;
+            let trailing = width
+                .checked_sub(leading + meaningful)
+                .ok_or(CompressionError::CorruptedData)?;
+            window = Some((leading, trailing));
+        }
+
+        let (leading, trailing) = window.ok_or(CompressionError::CorruptedData)?;
+        let meaningful = width - leading - trailing;
+        let bits = reader
+            .read_bits(meaningful)
+            .ok_or(CompressionError::CorruptedData)?;
+        values.push(last ^ (bits << trailing));
+    }
+
+    if reader.bytes_consumed() != payload.len() {
+        return Err(CompressionError::CorruptedData);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip_f64(values: &[f64]) -> Vec<u8> {
+        let codec = XorFloat::new();
+        let encoded = codec.encode_f64(values).unwrap();
+        let decoded = codec.decode_f64(&encoded).unwrap();
+        let bits: Vec<u64> = decoded.iter().map(|v| v.to_bits()).collect();
+        let expected: Vec<u64> = values.iter().map(|v| v.to_bits()).collect();
+        assert_eq!(bits, expected);
+        encoded
+    }
+
+    fn roundtrip_f32(values: &[f32]) -> Vec<u8> {
+        let codec = XorFloat::new();
+        let encoded = codec.encode_f32(values).unwrap();
+        let decoded = codec.decode_f32(&encoded).unwrap();
+        let bits: Vec<u32> = decoded.iter().map(|v| v.to_bits()).collect();
+        let expected: Vec<u32> = values.iter().map(|v| v.to_bits()).collect();
+        assert_eq!(bits, expected);
+        encoded
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip_f64(&[]).len(), HEADER_LEN);
+        assert_eq!(roundtrip_f32(&[]).len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn test_single_value() {
+        assert_eq!(roundtrip_f64(&[1.5]).len(), HEADER_LEN + 8);
+        assert_eq!(roundtrip_f32(&[1.5]).len(), HEADER_LEN + 4);
+    }
+
+    #[test]
+    fn test_constant_series_is_one_bit_per_value() {
+        let values = vec![42.125; 801];
+        let encoded = roundtrip_f64(&values);
+        assert_eq!(encoded.len(), HEADER_LEN + 8 + 100);
+    }
+
+    #[test]
+    fn test_slowly_changing_metric() {
+        let values: Vec<f64> = (0..1000).map(|i| 20.0 + f64::from(i % 10) * 0.5).collect();
+        let encoded = roundtrip_f64(&values);
+        assert!(encoded.len() < values.len() * 8 / 3);
+    }
+
+    #[test]
+    fn test_f32_series() {
+        let values: Vec<f32> = (0..500u16).map(|i| f32::from(i) * 0.25).collect();
+        let encoded = roundtrip_f32(&values);
+        assert!(encoded.len() < values.len() * 4);
+    }
+
+    #[test]
+    fn test_special_values() {
+        roundtrip_f64(&[
+            0.0,
+            -0.0,
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::EPSILON,
+        ]);
+        roundtrip_f32(&[0.0, -0.0, f32::NAN, f32::INFINITY, f32::MIN, f32::MAX]);
+    }
+
+    #[test]
+    fn test_full_width_xor() {
+        // Sign bit and lowest mantissa bit both change: no zero runs at all.
+        let a = f64::from_bits(0x0000_0000_0000_0000);
+        let b = f64::from_bits(0x8000_0000_0000_0001);
+        roundtrip_f64(&[a, b, a, b]);
+    }
+
+    #[test]
+    fn test_width_mismatch() {
+        let codec = XorFloat::new();
+        let encoded = codec.encode_f32(&[1.0, 2.0]).unwrap();
+        assert!(matches!(
+            codec.decode_f64(&encoded),
+            Err(CompressionError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_decode_truncated_and_trailing() {
+        let codec = XorFloat::new();
+        let values: Vec<f64> = (0..20).map(|i| f64::from(i).sqrt()).collect();
+        let mut encoded = codec.encode_f64(&values).unwrap();
+        assert!(codec.decode_f64(&encoded[..encoded.len() - 1]).is_err());
+        encoded.push(0);
+        assert!(codec.decode_f64(&encoded).is_err());
+        assert!(codec.decode_f64(&[64, 1]).is_err());
+    }
+
+    #[test]
+    fn test_decode_invalid_window() {
+        // Two values: first 0, then control "11", leading 31, length 64.
+        let mut writer = BitWriter::new();
+        writer.write_bits(0, 64);
+        writer.write_bits(0b11, 2);
+        writer.write_bits(31, LEADING_BITS);
+        writer.write_bits(63, 6);
+        let mut input = vec![64, 2, 0, 0, 0];
+        input.extend_from_slice(&writer.finish());
+        assert!(matches!(
+            XorFloat::new().decode_f64(&input),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+}
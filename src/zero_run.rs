@@ -0,0 +1,227 @@
+use crate::error::{CompressionError, Result};
+use crate::traits::{Compressor, Decompressor, PartialDecompressor};
+use crate::varint;
+
+/// Shortest zero run worth ending a literal span for: a split costs two
+/// varint bytes.
+pub const MIN_ZERO_RUN: usize = 3;
+
+/// Zero-run suppression for mostly-zero buffers such as memory snapshots
+/// and sparse tensors.
+///
+/// The input is split into tokens of a zero run followed by a span of
+/// nonzero bytes, with short zero runs kept inside spans. Zero runs cost a
+/// varint however long they are, and spans are copied verbatim, so decoding
+/// is little more than a memset and a memcpy per token. Output layout:
+/// `[original length: varint]` then `[zeros: varint][literals: varint]
+/// [literal bytes]` per token until the length is reached.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ZeroRun;
+
+impl ZeroRun {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+/// Length of the zero run starting at `start`.
+fn zero_run(input: &[u8], start: usize) -> usize {
+    input[start..].iter().take_while(|&&byte| byte == 0).count()
+}
+
+fn read_len(input: &[u8], pos: &mut usize) -> Result<usize> {
+    usize::try_from(varint::read_u64(input, pos)?).map_err(|_| CompressionError::CorruptedData)
+}
+
+impl Compressor for ZeroRun {
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(input.len() / 4 + varint::MAX_LEN_U64);
+        varint::write_u64(&mut output, input.len() as u64);
+
+        let mut pos = 0;
+        while pos < input.len() {
+            let zeros = zero_run(input, pos);
+            let literal_start = pos + zeros;
+
+            let mut literal_end = literal_start;
+            while literal_end < input.len() {
+                let run = zero_run(input, literal_end);
+                if run >= MIN_ZERO_RUN || literal_end + run == input.len() {
+                    break;
+                }
+                literal_end += run.max(1);
+            }
+
+            varint::write_u64(&mut output, zeros as u64);
+            varint::write_u64(&mut output, (literal_end - literal_start) as u64);
+            output.extend_from_slice(&input[literal_start..literal_end]);
+            pos = literal_end;
+        }
+
+        Ok(output)
+    }
+
+    fn name(&self) -> &'static str {
+        "ZeroRun"
+    }
+}
+
+impl Decompressor for ZeroRun {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let (output, consumed) = self.decompress_partial(input)?;
+        if consumed != input.len() {
+            return Err(CompressionError::CorruptedData);
+        }
+        Ok(output)
+    }
+
+    fn decompressed_len(&self, input: &[u8]) -> Result<Option<u64>> {
+        varint::read_u64(input, &mut 0).map(Some)
+    }
+
+    fn name(&self) -> &'static str {
+        "ZeroRun"
+    }
+}
+
+impl PartialDecompressor for ZeroRun {
+    fn decompress_partial(&self, input: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let mut pos = 0;
+        let original_len = read_len(input, &mut pos)?;
+
+        let mut output = Vec::with_capacity(original_len.min(input.len() * 64));
+        while output.len() < original_len {
+            let zeros = read_len(input, &mut pos)?;
+            let literals = read_len(input, &mut pos)?;
+            let remaining = original_len - output.len();
+            if (zeros == 0 && literals == 0) || zeros > remaining || literals > remaining - zeros {
+                return Err(CompressionError::CorruptedData);
+            }
+
+            // The counts are untrusted, so neither sum may wrap.
+            let filled = output
+                .len()
+                .checked_add(zeros)
+                .ok_or(CompressionError::CorruptedData)?;
+            let end = pos
+                .checked_add(literals)
+                .ok_or(CompressionError::CorruptedData)?;
+            output.resize(filled, 0);
+            let span = input.get(pos..end).ok_or(CompressionError::CorruptedData)?;
+            output.extend_from_slice(span);
+            pos = end;
+        }
+
+        Ok((output, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rle, RleMode};
+
+    fn roundtrip(input: &[u8]) -> Vec<u8> {
+        let compressed = ZeroRun::new().compress(input).unwrap();
+        assert_eq!(ZeroRun::new().decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    /// A sparse buffer: short nonzero records scattered through zeros.
+    fn sparse(len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        for (i, record) in (0..len).step_by(997).enumerate() {
+            let value = u32::try_from(i).unwrap().wrapping_mul(0x9E37_79B9) | 1;
+            let end = (record + 4).min(len);
+            data[record..end].copy_from_slice(&value.to_le_bytes()[..end - record]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_zero_run_name() {
+        let codec = ZeroRun::new();
+        assert_eq!(Compressor::name(&codec), "ZeroRun");
+        assert_eq!(Decompressor::name(&codec), "ZeroRun");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(roundtrip(&[]), [0]);
+        assert!(matches!(
+            ZeroRun::new().decompress(&[]),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+
+    #[test]
+    fn test_known_output() {
+        let input = [0, 0, 0, 0, 7, 0, 8, 0, 0, 0, 9];
+        assert_eq!(roundtrip(&input), [11, 4, 3, 7, 0, 8, 3, 1, 9]);
+    }
+
+    #[test]
+    fn test_trailing_zeros() {
+        assert_eq!(roundtrip(&[5, 0]), [2, 0, 1, 5, 1, 0]);
+        assert_eq!(roundtrip(&[0; 1000]), [0xE8, 0x07, 0xE8, 0x07, 0]);
+    }
+
+    #[test]
+    fn test_no_zeros() {
+        let input: Vec<u8> = (1..=255).collect();
+        let compressed = roundtrip(&input);
+        assert_eq!(compressed.len(), input.len() + 5);
+    }
+
+    #[test]
+    fn test_beats_rle_on_sparse_data() {
+        let input = sparse(1 << 20);
+        let compressed = roundtrip(&input);
+        for mode in [RleMode::Pairs, RleMode::Escaped, RleMode::Varint] {
+            let rle = Rle::with_mode(mode).compress(&input).unwrap();
+            assert!(compressed.len() < rle.len(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_decompress_partial_back_to_back() {
+        let codec = ZeroRun::new();
+        let first = codec.compress(&[0, 0, 0, 1, 2]).unwrap();
+        let second = codec.compress(&[]).unwrap();
+        let stream = [first.as_slice(), &second, &first].concat();
+        let frames = codec.decompress_frames(&stream).unwrap();
+        assert_eq!(
+            frames,
+            [vec![0, 0, 0, 1, 2], Vec::new(), vec![0, 0, 0, 1, 2]]
+        );
+        assert!(codec.decompress(&stream).is_err());
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let codec = ZeroRun::new();
+        // Empty token.
+        assert!(codec.decompress(&[1, 0, 0, 0, 1, 5]).is_err());
+        // Tokens overrunning the original length.
+        assert!(codec.decompress(&[2, 3, 0]).is_err());
+        assert!(codec.decompress(&[2, 1, 2, 5, 6]).is_err());
+        // Literal span past the end of the input.
+        assert!(codec.decompress(&[3, 0, 3, 5]).is_err());
+        // Input ends before the length is reached.
+        assert!(codec.decompress(&[4, 2]).is_err());
+    }
+
+    #[test]
+    fn test_decompress_huge_literal_count() {
+        let mut frame = Vec::new();
+        varint::write_u64(&mut frame, u64::MAX);
+        varint::write_u64(&mut frame, 0);
+        varint::write_u64(&mut frame, u64::MAX);
+        frame.push(1);
+        assert!(matches!(
+            ZeroRun::new().decompress(&frame),
+            Err(CompressionError::CorruptedData)
+        ));
+    }
+}